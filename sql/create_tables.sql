@@ -8,12 +8,44 @@ CREATE TABLE IF NOT EXISTS user
     username   VARCHAR(32)  NOT NULL, # only lower case letters, 0-9 and '_' is supported
     is_active  BOOLEAN      NOT NULL DEFAULT TRUE,
     created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    deleted_at TIMESTAMP(6) NULL,     # soft delete: row kept so message history still resolves
+    legal_hold BOOLEAN      NOT NULL DEFAULT FALSE, # no deleting or pruning the account or its messages
+    is_bot     BOOLEAN      NOT NULL DEFAULT FALSE, # set by an operator; only bots can post through incoming webhooks
+
+    INDEX ix_user_created (created_at), # admin stats
 
     CONSTRAINT pk_user PRIMARY KEY (user_id)
     ) ENGINE = InnoDB
     DEFAULT CHARSET = utf8mb4
     COLLATE = utf8mb4_0900_ai_ci;
 
+CREATE TABLE IF NOT EXISTS username_rule
+(
+    rule_id    BIGINT AUTO_INCREMENT        NOT NULL,
+    kind       ENUM ('reserved', 'pattern') NOT NULL, # exact name, or a regex
+    value      VARCHAR(255)                 NOT NULL,
+    created_at TIMESTAMP(6)                 NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+    CONSTRAINT pk_username_rule PRIMARY KEY (rule_id),
+    CONSTRAINT uq_username_rule UNIQUE (kind, value)
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_bin; # patterns are case-sensitive
+
+CREATE TABLE IF NOT EXISTS invite_code
+(
+    code       VARCHAR(32)  NOT NULL,
+    max_uses   INT UNSIGNED NOT NULL,
+    uses       INT UNSIGNED NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP(6) NULL,     # NULL never expires
+    minted_by  VARCHAR(255) NOT NULL, # the admin caller; may be a service principal, so no foreign key
+    created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+    CONSTRAINT pk_invite_code PRIMARY KEY (code)
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_bin;
+
 CREATE TABLE IF NOT EXISTS conversation_kind
 (
     kind_id TINYINT UNSIGNED NOT NULL,
@@ -42,15 +74,21 @@ CREATE TABLE IF NOT EXISTS permission
 
 INSERT INTO permission (perm_id, perm_key)
 VALUES (1, 'message.send'),
-       (2, 'member.invite');
+       (2, 'member.invite'),
+       (3, 'member.pin');
 
 CREATE TABLE IF NOT EXISTS conversation
 (
     conversation_id BINARY(16)       NOT NULL, # UUID
     kind_id         TINYINT UNSIGNED NOT NULL,
     created_at      TIMESTAMP(6)     NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
-    last_msg_off    BIGINT UNSIGNED  NOT NULL DEFAULT 0,
+    last_msg_off    BIGINT UNSIGNED  NOT NULL DEFAULT 0, # also the message offset counter
     last_msg_at     TIMESTAMP(6)     NULL,
+    offset_ceiling  BIGINT UNSIGNED  NOT NULL DEFAULT 0, # highest offset reserved by an external allocator
+    deleted_at      TIMESTAMP(6)     NULL,
+    members_version BIGINT UNSIGNED  NOT NULL DEFAULT 0, # bumped on every membership change
+    frozen          BOOLEAN          NOT NULL DEFAULT FALSE, # read-only: no new messages
+    retention_days  INT UNSIGNED     NULL, # the owner's override; NULL follows the server default
 
     INDEX ix_conv_last (last_msg_at DESC),
 
@@ -75,12 +113,57 @@ CREATE TABLE IF NOT EXISTS auth_credential
     DEFAULT CHARSET = utf8mb4
     COLLATE = utf8mb4_0900_ai_ci;
 
+CREATE TABLE IF NOT EXISTS login_location
+(
+    user_id       BINARY(16)   NOT NULL,
+    location      VARCHAR(64)  NOT NULL, # coarse: a network prefix or region, never a precise place
+    last_ip       VARCHAR(45)  NOT NULL,
+    first_seen_at TIMESTAMP(6) NOT NULL,
+    last_seen_at  TIMESTAMP(6) NOT NULL,
+    logins        INT UNSIGNED NOT NULL,
+
+    CONSTRAINT pk_login_location PRIMARY KEY (user_id, location),
+    CONSTRAINT fk_login_location_user FOREIGN KEY (user_id) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
+CREATE TABLE IF NOT EXISTS device
+(
+    platform      ENUM ('apns', 'fcm') NOT NULL,
+    token         VARCHAR(512) CHARACTER SET ascii NOT NULL, # from the push service; names one app install
+    user_id       BINARY(16)   NOT NULL,
+    locale        VARCHAR(35)  NOT NULL, # BCP 47
+    registered_at TIMESTAMP(6) NOT NULL,
+
+    INDEX ix_device_user (user_id, registered_at),
+
+    CONSTRAINT pk_device PRIMARY KEY (platform, token),
+    CONSTRAINT fk_device_user FOREIGN KEY (user_id) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
+CREATE TABLE IF NOT EXISTS contact_hash
+(
+    contact_hash BINARY(32)   NOT NULL, # salted SHA-256 of the user's own phone number or email
+    user_id      BINARY(16)   NOT NULL,
+    created_at   TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+    CONSTRAINT pk_contact_hash PRIMARY KEY (contact_hash, user_id),
+    INDEX ix_contact_hash_user (user_id),
+    CONSTRAINT fk_contact_hash_user FOREIGN KEY (user_id) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
 CREATE TABLE IF NOT EXISTS friendship
 (
     user_min     BINARY(16)                  NOT NULL,
     user_max     BINARY(16)                  NOT NULL,
     status       ENUM ('pending','accepted') NOT NULL,
     requested_by BINARY(16)                  NOT NULL, # must be either user_min or user_max
+    idem_key     BINARY(16)                  NULL,     # client key of the winning request
     created_at   TIMESTAMP(6)                NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
 
     # Helpful indexes for “my friends / my requests”
@@ -120,6 +203,10 @@ CREATE TABLE IF NOT EXISTS chat_group
     description     TEXT,
     created_at      TIMESTAMP(6) DEFAULT CURRENT_TIMESTAMP(6),
     conversation_id BINARY(16)  NOT NULL,
+    deleted_at      TIMESTAMP(6) NULL, # set when the group is disbanded
+    member_count    INT UNSIGNED NOT NULL DEFAULT 0, # kept in step with conversation_member
+    avatar_url      VARCHAR(512) NULL,
+    theme_color     CHAR(7)      NULL, # '#rrggbb'
 
     CONSTRAINT pk_chat_group PRIMARY KEY (group_id),
     CONSTRAINT uq_chat_group_conversation UNIQUE (conversation_id),
@@ -137,34 +224,23 @@ CREATE TABLE IF NOT EXISTS group_create_idem
     conversation_id BINARY(16)                              NULL,
     status          ENUM ('pending', 'succeeded', 'failed') NOT NULL DEFAULT 'pending',
     created_at      TIMESTAMP(6)                                     DEFAULT CURRENT_TIMESTAMP(6),
+    claimed_at      TIMESTAMP(6)                            NOT NULL DEFAULT CURRENT_TIMESTAMP(6), # bumped on retake
 
-    CONSTRAINT pk_group_create_idem PRIMARY KEY (owner_id, idem_key)
-    ) ENGINE = InnoDB
-    DEFAULT CHARSET = utf8mb4
-    COLLATE = utf8mb4_0900_ai_ci;
-
-CREATE TABLE IF NOT EXISTS conversation_counter
-(
-    conversation_id BINARY(16)      NOT NULL,
-    next_offset     BIGINT UNSIGNED NOT NULL,
-
-    CONSTRAINT pk_conversation_counter PRIMARY KEY (conversation_id),
-    CONSTRAINT ck_conversation_counter_positive CHECK ( next_offset > 0 ),
-    CONSTRAINT fk_convcounter_conversation FOREIGN KEY (conversation_id) REFERENCES conversation (conversation_id) ON DELETE CASCADE
+    CONSTRAINT pk_group_create_idem PRIMARY KEY (owner_id, idem_key),
+    INDEX idx_group_create_idem_created (created_at)
     ) ENGINE = InnoDB
     DEFAULT CHARSET = utf8mb4
     COLLATE = utf8mb4_0900_ai_ci;
 
-# INSERT INTO conversation_counters (conversation_id, next_offset)
-# VALUES (UUID_TO_BIN(?), 1)
-# ON DUPLICATE KEY UPDATE next_offset = LAST_INSERT_ID(next_offset + 1);
-
 CREATE TABLE IF NOT EXISTS conversation_member
 (
     conversation_id BINARY(16)      NOT NULL,
     user_id         BINARY(16)      NOT NULL,
     joined_at       TIMESTAMP(6)    NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
     last_read_off   BIGINT UNSIGNED NOT NULL DEFAULT 0,
+    pinned          BOOLEAN         NOT NULL DEFAULT FALSE, # first in this member's recent list
+    added_version   BIGINT UNSIGNED NOT NULL DEFAULT 0, # conversation.members_version that added this member
+    sent_count      BIGINT UNSIGNED NOT NULL DEFAULT 0, # last message.sender_seq handed out
 
     INDEX ix_member_user (user_id, conversation_id),
 
@@ -175,6 +251,19 @@ CREATE TABLE IF NOT EXISTS conversation_member
     DEFAULT CHARSET = utf8mb4
     COLLATE = utf8mb4_0900_ai_ci;
 
+CREATE TABLE IF NOT EXISTS conversation_meta
+(
+    conversation_id BINARY(16)    NOT NULL,
+    meta_key        VARCHAR(64)   NOT NULL,
+    meta_value      VARCHAR(1024) NOT NULL, # set by integrations, shown to members
+    updated_at      TIMESTAMP(6)  NOT NULL DEFAULT CURRENT_TIMESTAMP(6) ON UPDATE CURRENT_TIMESTAMP(6),
+
+    CONSTRAINT pk_conversation_meta PRIMARY KEY (conversation_id, meta_key),
+    CONSTRAINT fk_convmeta_conversation FOREIGN KEY (conversation_id) REFERENCES conversation (conversation_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
 CREATE TABLE IF NOT EXISTS message
 (
     message_id      BINARY(16)      NOT NULL, # UUID
@@ -182,9 +271,21 @@ CREATE TABLE IF NOT EXISTS message
     message_offset  BIGINT UNSIGNED NOT NULL,
     sender_id       BINARY(16)      NOT NULL,
     content         TEXT            NOT NULL,
+    content_encrypted BOOLEAN       NOT NULL DEFAULT FALSE, # content is sealed with the conversation key, see conversation_key
     created_at      TIMESTAMP(6)    NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    sender_seq      BIGINT UNSIGNED NOT NULL DEFAULT 0, # the sender's own count in the conversation
+    edited_at       TIMESTAMP(6)    NULL,
+    edit_count      INT UNSIGNED    NOT NULL DEFAULT 0,
+    deleted_at      TIMESTAMP(6)    NULL, # a tombstone; content is kept but never returned
+    kind            ENUM ('user', 'system') NOT NULL DEFAULT 'user', # system: written by the server, e.g. a welcome
+    reply_to        BINARY(16)      NULL, # message_id answered, same conversation; no FK, a pruned root leaves its replies
 
     INDEX ix_message_id (message_id),
+    INDEX ix_message_sender (sender_id, created_at), # account export
+    INDEX ix_message_conv_time (conversation_id, created_at), # jump to date
+    INDEX ix_message_time (created_at), # admin stats
+    INDEX ix_message_thread (conversation_id, reply_to, message_offset), # thread history
+    FULLTEXT INDEX ft_message_content (content), # message search; words under innodb_ft_min_token_size aren't indexed
 
     # Clustered by conversation then offset for fast pagination
     CONSTRAINT pk_message PRIMARY KEY (conversation_id, message_offset),
@@ -196,6 +297,160 @@ CREATE TABLE IF NOT EXISTS message
     DEFAULT CHARSET = utf8mb4
     COLLATE = utf8mb4_0900_ai_ci;
 
+# Who each message mentions; `created_at` and `message_id` are the message's,
+# copied so "mentions of me" pages over the index alone
+CREATE TABLE IF NOT EXISTS message_mention
+(
+    conversation_id BINARY(16)      NOT NULL,
+    message_offset  BIGINT UNSIGNED NOT NULL,
+    user_id         BINARY(16)      NOT NULL,
+    message_id      BINARY(16)      NOT NULL,
+    created_at      TIMESTAMP(6)    NOT NULL,
+
+    INDEX ix_mention_user (user_id, created_at, message_id),
+
+    CONSTRAINT pk_message_mention PRIMARY KEY (conversation_id, message_offset, user_id),
+    CONSTRAINT fk_mention_message FOREIGN KEY (conversation_id, message_offset) REFERENCES message (conversation_id, message_offset) ON DELETE CASCADE,
+    CONSTRAINT fk_mention_user FOREIGN KEY (user_id) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
+# Receivers whose client acked a message; the sender's own is never stored
+CREATE TABLE IF NOT EXISTS message_delivery
+(
+    conversation_id BINARY(16)      NOT NULL,
+    message_offset  BIGINT UNSIGNED NOT NULL,
+    user_id         BINARY(16)      NOT NULL,
+    delivered_at    TIMESTAMP(6)    NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+    CONSTRAINT pk_message_delivery PRIMARY KEY (conversation_id, message_offset, user_id),
+    CONSTRAINT fk_delivery_message FOREIGN KEY (conversation_id, message_offset) REFERENCES message (conversation_id, message_offset) ON DELETE CASCADE,
+    CONSTRAINT fk_delivery_user FOREIGN KEY (user_id) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
+# Messages pinned to the top of their conversation, for every member
+CREATE TABLE IF NOT EXISTS pinned_message
+(
+    conversation_id BINARY(16)      NOT NULL,
+    message_offset  BIGINT UNSIGNED NOT NULL,
+    pinned_by       BINARY(16)      NOT NULL,
+    pinned_at       TIMESTAMP(6)    NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+    INDEX ix_pinned_message_time (conversation_id, pinned_at),
+
+    CONSTRAINT pk_pinned_message PRIMARY KEY (conversation_id, message_offset),
+    # a pruned message takes its pin with it
+    CONSTRAINT fk_pinned_message FOREIGN KEY (conversation_id, message_offset) REFERENCES message (conversation_id, message_offset) ON DELETE CASCADE,
+    CONSTRAINT fk_pinned_by FOREIGN KEY (pinned_by) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
+# Files uploaded to a conversation; the content is in the blob store under
+# attachments/<conversation_id>/<attachment_id>
+CREATE TABLE IF NOT EXISTS attachment
+(
+    attachment_id   BINARY(16)      NOT NULL, # UUID
+    conversation_id BINARY(16)      NOT NULL,
+    uploader_id     BINARY(16)      NOT NULL,
+    mime            VARCHAR(127)    NOT NULL,
+    size            BIGINT UNSIGNED NOT NULL, # bytes
+    created_at      TIMESTAMP(6)    NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    message_id      BINARY(16)      NULL, # the message it was sent with; one at most
+
+    INDEX ix_attachment_message (message_id),
+
+    CONSTRAINT pk_attachment PRIMARY KEY (attachment_id),
+    CONSTRAINT fk_attachment_conversation FOREIGN KEY (conversation_id) REFERENCES conversation (conversation_id) ON DELETE CASCADE,
+    CONSTRAINT fk_attachment_uploader FOREIGN KEY (uploader_id) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
+# Account export jobs, one per user; the archive is in the blob store under
+# blob_key, encrypted with archive_key
+CREATE TABLE IF NOT EXISTS export_job
+(
+    user_id          BINARY(16)      NOT NULL,
+    stage            VARCHAR(16)     NOT NULL, # queued, profile, friends, groups, messages, ready or failed
+    records_written  BIGINT UNSIGNED NOT NULL DEFAULT 0,
+    messages_written BIGINT UNSIGNED NOT NULL DEFAULT 0,
+    messages_total   BIGINT UNSIGNED NULL,
+    started_at       TIMESTAMP(6)    NOT NULL,
+    finished_at      TIMESTAMP(6)    NULL,
+    updated_at       TIMESTAMP(6)    NOT NULL DEFAULT CURRENT_TIMESTAMP(6), # a running job not updated for long was abandoned
+    file_name        VARCHAR(64)     NOT NULL,
+    blob_key         VARCHAR(255)    CHARACTER SET ascii NOT NULL,
+    archive_key      VARBINARY(256)  NOT NULL, # wrapped by the KMS key key_id, if set
+    key_id           VARCHAR(64)     NULL,
+
+    INDEX ix_export_job_finished (finished_at),
+    INDEX ix_export_job_updated (updated_at),
+
+    CONSTRAINT pk_export_job PRIMARY KEY (user_id),
+    CONSTRAINT fk_export_job_user FOREIGN KEY (user_id) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
+# Endpoints a group owner registered to get the conversation's new messages
+CREATE TABLE IF NOT EXISTS webhook
+(
+    webhook_id      BINARY(16)    NOT NULL, # UUID
+    conversation_id BINARY(16)    NOT NULL,
+    url             VARCHAR(2048) CHARACTER SET ascii NOT NULL,
+    secret          VARCHAR(64)   CHARACTER SET ascii NOT NULL, # keys each delivery's HMAC-SHA256 signature
+    created_by      BINARY(16)    NOT NULL,
+    created_at      TIMESTAMP(6)  NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+    INDEX ix_webhook_conversation (conversation_id, created_at),
+
+    CONSTRAINT pk_webhook PRIMARY KEY (webhook_id),
+    CONSTRAINT fk_webhook_conversation FOREIGN KEY (conversation_id) REFERENCES conversation (conversation_id) ON DELETE CASCADE,
+    CONSTRAINT fk_webhook_creator FOREIGN KEY (created_by) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
+# URLs integrations post to; each post is sent as a message from bot_user_id
+CREATE TABLE IF NOT EXISTS incoming_webhook
+(
+    incoming_webhook_id BINARY(16)   NOT NULL, # UUID
+    conversation_id     BINARY(16)   NOT NULL,
+    name                VARCHAR(64)  NOT NULL,
+    bot_user_id         BINARY(16)   NOT NULL, # a member the owner picked
+    token_hash          CHAR(64)     CHARACTER SET ascii NOT NULL, # hex SHA-256 of the token in the URL
+    created_by          BINARY(16)   NOT NULL,
+    created_at          TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+    INDEX ix_incoming_webhook_conversation (conversation_id, created_at),
+
+    CONSTRAINT pk_incoming_webhook PRIMARY KEY (incoming_webhook_id),
+    CONSTRAINT uq_incoming_webhook_token UNIQUE (token_hash),
+    CONSTRAINT fk_incoming_webhook_conversation FOREIGN KEY (conversation_id) REFERENCES conversation (conversation_id) ON DELETE CASCADE,
+    CONSTRAINT fk_incoming_webhook_bot FOREIGN KEY (bot_user_id) REFERENCES user (user_id) ON DELETE CASCADE,
+    CONSTRAINT fk_incoming_webhook_creator FOREIGN KEY (created_by) REFERENCES user (user_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
+# Per-conversation data keys for `message.content` encryption, wrapped by the KMS
+CREATE TABLE IF NOT EXISTS conversation_key
+(
+    conversation_id BINARY(16)     NOT NULL,
+    kms_key_id      VARCHAR(128)   NOT NULL, # master key that wrapped it
+    wrapped_key     VARBINARY(512) NOT NULL,
+    created_at      TIMESTAMP(6)   NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+
+    CONSTRAINT pk_conversation_key PRIMARY KEY (conversation_id),
+    CONSTRAINT fk_conversation_key_conversation FOREIGN KEY (conversation_id) REFERENCES conversation (conversation_id) ON DELETE CASCADE
+    ) ENGINE = InnoDB
+    DEFAULT CHARSET = utf8mb4
+    COLLATE = utf8mb4_0900_ai_ci;
+
 CREATE TABLE IF NOT EXISTS conversation_role
 (
     role_id         BIGINT AUTO_INCREMENT NOT NULL,
@@ -249,8 +504,10 @@ CREATE TABLE IF NOT EXISTS outbox
     created_at      TIMESTAMP(6)    NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
     delivered_at    TIMESTAMP(6)    NULL,
     attempt_count   INT             NOT NULL DEFAULT 0,
-    next_attempt_at TIMESTAMP(6)    NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    next_attempt_at TIMESTAMP(6)    NOT NULL DEFAULT CURRENT_TIMESTAMP(6), # also holds scheduled events back
     last_error      VARCHAR(1024)   NULL,
+    trace_id        BINARY(16)      NULL, # originating request, carried into the Kafka envelope
+    redelivered_at  TIMESTAMP(6)    NULL, # last operator replay, if any
 
     INDEX idx_outbox_ready (delivered_at, next_attempt_at, created_at),
     INDEX idx_outbox_type (event_type),
@@ -1 +1,4 @@
-pub mod v1;
\ No newline at end of file
+mod mtls;
+pub mod v1;
+
+pub use mtls::*;
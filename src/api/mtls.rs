@@ -0,0 +1,148 @@
+use crate::logger::*;
+use crate::settings::Http as HttpSettings;
+use anyhow::anyhow;
+use hyper::server::conn::Http;
+use hyper::service::{Service, service_fn};
+use hyper::{Body, Request};
+use std::convert::Infallible;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use warp::{Filter, Reply};
+
+/// Who a verified client certificate belongs to: the subject's common name,
+/// or the whole subject when it has none.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientPrincipal(pub String);
+
+impl fmt::Display for ClientPrincipal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The peer of a connection accepted by `serve_mtls`; `warp::addr::remote`
+/// only sees connections `warp::serve` accepted.
+#[derive(Debug, Clone, Copy)]
+pub struct PeerAddr(pub SocketAddr);
+
+/// Serves `filter` like `warp::serve(..).tls()`, but also asks clients for a
+/// certificate signed by `http.client_ca_path`. Presenting one is optional;
+/// requests on a connection that did carry its `ClientPrincipal`, and every
+/// request carries a `PeerAddr`, as request extensions.
+///
+/// Stops accepting once `shutdown` resolves; connections already open are
+/// left to finish on their own.
+pub async fn serve_mtls<F, R>(
+    filter: F,
+    address: SocketAddr,
+    http: &HttpSettings,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()>
+where
+    F: Filter<Extract = (R,), Error = Infallible> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    let acceptor = TlsAcceptor::from(Arc::new(server_config(http)?));
+    let listener = TcpListener::bind(address).await?;
+    info!("listening with optional client certificates on {}", address);
+
+    tokio::pin!(shutdown);
+    loop {
+        let (tcp, remote_addr) = tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("accept failed: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        let acceptor = acceptor.clone();
+        let service = warp::service(filter.clone());
+        tokio::spawn(async move {
+            let tls = match acceptor.accept(tcp).await {
+                Ok(tls) => tls,
+                Err(e) => {
+                    debug!("TLS handshake with {} failed: {}", remote_addr, e);
+                    return;
+                }
+            };
+            let principal = tls
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(principal_of);
+
+            let service = service_fn(move |mut request: Request<Body>| {
+                request.extensions_mut().insert(PeerAddr(remote_addr));
+                if let Some(principal) = &principal {
+                    request.extensions_mut().insert(principal.clone());
+                }
+                service.clone().call(request)
+            });
+            if let Err(e) = Http::new()
+                .serve_connection(tls, service)
+                .with_upgrades()
+                .await
+            {
+                debug!("connection from {} ended: {}", remote_addr, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn server_config(http: &HttpSettings) -> anyhow::Result<ServerConfig> {
+    let ca_path = http
+        .client_ca_path
+        .as_deref()
+        .ok_or_else(|| anyhow!("http.client_ca_path is not set"))?;
+
+    let certs = read_certs(&http.cert_path)?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(&http.key_path)?))?
+        .ok_or_else(|| anyhow!("no private key in {}", http.key_path))?;
+
+    let mut roots = RootCertStore::empty();
+    for ca in read_certs(ca_path)? {
+        roots.add(ca)?;
+    }
+    let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+        .allow_unauthenticated()
+        .build()?;
+
+    let mut config = ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn read_certs(path: &str) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        return Err(anyhow!("no certificates in {}", path));
+    }
+    Ok(certs)
+}
+
+fn principal_of(cert: &CertificateDer<'_>) -> Option<ClientPrincipal> {
+    let (_, cert) = x509_parser::parse_x509_certificate(cert.as_ref()).ok()?;
+    let subject = cert.subject();
+    let name = match subject.iter_common_name().next() {
+        Some(cn) => cn.as_str().ok()?.to_owned(),
+        None => subject.to_string(),
+    };
+    Some(ClientPrincipal(name))
+}
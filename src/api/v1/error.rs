@@ -1,49 +1,136 @@
+use super::i18n::{Locale, with_locale};
 use crate::api::v1::handler::ApiResponse;
 use crate::application_port::*;
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
 use std::convert::Infallible;
-use thiserror::Error;
 use tracing::warn;
 use warp::http::StatusCode;
-use warp::{Rejection, reject};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply, reject};
 
-pub async fn recover_error(err: Rejection) -> Result<impl warp::Reply, Infallible> {
+/// Wraps `filter` so rejections become the error envelope, with `message`
+/// in the language the caller's `Accept-Language` asks for.
+pub fn recover_errors<F, R>(
+    filter: F,
+) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    let outcome = filter
+        .map(|reply: R| Ok(reply.into_response()))
+        .recover(|err: Rejection| async move { Ok::<_, Infallible>(Err(err)) })
+        .unify();
+    with_locale()
+        .and(outcome)
+        .map(|locale: Locale, outcome: Result<Response, Rejection>| {
+            outcome.unwrap_or_else(|err| recover_error(&err, locale))
+        })
+}
+
+fn recover_error(err: &Rejection, locale: Locale) -> Response {
     if let Some(err) = err.find::<ApiErrorCode>() {
-        let json = warp::reply::json(&ApiResponse::<()>::err(err.clone(), err.to_string()));
-        Ok(warp::reply::with_status(json, StatusCode::OK))
+        let json = warp::reply::json(&ApiResponse::<()>::err(err.clone(), err.message(locale)));
+        warp::reply::with_status(json, err.status()).into_response()
     } else {
         let json = warp::reply::json(&ApiResponse::<()> {
             success: false,
             data: None,
-            error: Some(ApiError {
-                code: ApiErrorCode::InternalError,
-                message: format!("Unhandled error: {:?}", err),
-            }),
+            error: Some(ApiError::new(
+                ApiErrorCode::InternalError,
+                format!("Unhandled error: {:?}", err),
+            )),
         });
-        Ok(warp::reply::with_status(
-            json,
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ))
+        warp::reply::with_status(json, StatusCode::INTERNAL_SERVER_ERROR).into_response()
     }
 }
 
 #[derive(Debug, Serialize)]
 pub struct ApiError {
+    #[serde(serialize_with = "code_name")]
     pub code: ApiErrorCode,
     pub message: String,
+    /// When a rate-limited call may be retried.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+impl ApiError {
+    pub fn new(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        let reset_at = match code {
+            ApiErrorCode::RateLimited { reset_at } => Some(reset_at),
+            _ => None,
+        };
+        ApiError {
+            code,
+            message: message.into(),
+            reset_at,
+        }
+    }
+}
+
+/// Codes go out as bare names; whatever a variant carries is lifted into
+/// its own `ApiError` field.
+fn code_name<S: Serializer>(code: &ApiErrorCode, s: S) -> Result<S::Ok, S::Error> {
+    match code {
+        ApiErrorCode::RateLimited { .. } => s.serialize_str("RateLimited"),
+        code => code.serialize(s),
+    }
 }
 
-#[derive(Debug, Clone, Error, Serialize)]
+/// Stable codes clients can branch on; the message that goes with each is
+/// looked up per locale, see `ApiErrorCode::message`.
+#[derive(Debug, Clone, Serialize)]
 pub enum ApiErrorCode {
-    #[error("Invalid captcha ID or answer")]
     InvalidCaptcha,
-    #[error("Invalid username or password")]
     InvalidCredentials,
-    #[error("Username already taken")]
     UsernameTaken,
-    #[error("Token is not valid")]
+    UsernameNotAllowed,
+    InviteRequired,
+    InvalidInvite,
     InvalidToken,
-    #[error("Internal error")]
+    Forbidden,
+    AlreadyFriends,
+    NotFriends,
+    UserNotFound,
+    SelfRelation,
+    InvalidGroupName,
+    InvalidGroupAppearance,
+    GroupNotFound,
+    BatchTooLarge,
+    BadCursor,
+    BadPageSize,
+    BadIdempotencyKey,
+    NotMember,
+    ConversationNotFound,
+    ConversationFrozen,
+    MessageNotFound,
+    BadReplaySelection,
+    LegalHold,
+    InvalidMetadata,
+    InvalidImport,
+    InvalidUsernameRule,
+    InvalidInviteMint,
+    AttachmentNotFound,
+    InvalidUpload,
+    DiscoveryDisabled,
+    InvalidSearch,
+    SearchUnavailable,
+    QrLoginNotFound,
+    InvalidDevice,
+    DeviceNotFound,
+    TooManyPins,
+    BadStatsRange,
+    InvalidWebhook,
+    WebhookNotFound,
+    InvalidNotice,
+    #[serde(skip)]
+    RateLimited {
+        reset_at: DateTime<Utc>,
+    },
+    UnsupportedProtocolVersion,
+    TooManySessions,
     InternalError,
 }
 
@@ -52,8 +139,45 @@ impl ApiErrorCode {
         warn!("Internal error: {}", error);
         ApiErrorCode::InternalError
     }
+
+    /// Malformed requests get a 400, and rate-limited ones or a chat session
+    /// past the per-IP limit a 429; everything else keeps the envelope-only
+    /// 200.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiErrorCode::BadCursor
+            | ApiErrorCode::BadPageSize
+            | ApiErrorCode::BadIdempotencyKey
+            | ApiErrorCode::InvalidGroupName
+            | ApiErrorCode::InvalidGroupAppearance
+            | ApiErrorCode::BatchTooLarge
+            | ApiErrorCode::BadReplaySelection
+            | ApiErrorCode::InvalidMetadata
+            | ApiErrorCode::InvalidImport
+            | ApiErrorCode::InvalidUsernameRule
+            | ApiErrorCode::InvalidInviteMint
+            | ApiErrorCode::InvalidUpload
+            | ApiErrorCode::InvalidSearch
+            | ApiErrorCode::InvalidDevice
+            | ApiErrorCode::BadStatsRange
+            | ApiErrorCode::InvalidWebhook
+            | ApiErrorCode::InvalidNotice => StatusCode::BAD_REQUEST,
+            ApiErrorCode::RateLimited { .. } | ApiErrorCode::TooManySessions => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => StatusCode::OK,
+        }
+    }
 }
 
+impl std::fmt::Display for ApiErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message(Locale::En))
+    }
+}
+
+impl std::error::Error for ApiErrorCode {}
+
 impl reject::Reject for ApiErrorCode {}
 
 impl From<CaptchaError> for ApiErrorCode {
@@ -71,8 +195,131 @@ impl From<AuthError> for ApiErrorCode {
     fn from(error: AuthError) -> Self {
         match error {
             AuthError::InvalidCredentials => ApiErrorCode::InvalidCredentials,
+            AuthError::LegalHold => ApiErrorCode::LegalHold,
+            AuthError::UsernameNotAllowed => ApiErrorCode::UsernameNotAllowed,
+            AuthError::InvalidUsernameRule(_) => ApiErrorCode::InvalidUsernameRule,
+            AuthError::InviteRequired => ApiErrorCode::InviteRequired,
+            AuthError::InvalidInvite => ApiErrorCode::InvalidInvite,
+            AuthError::InvalidInviteMint(_) => ApiErrorCode::InvalidInviteMint,
+            AuthError::QrLoginNotFound => ApiErrorCode::QrLoginNotFound,
             AuthError::InternalError(e) => ApiErrorCode::internal(e),
             _ => ApiErrorCode::InternalError,
         }
     }
 }
+
+impl From<RelationError> for ApiErrorCode {
+    fn from(error: RelationError) -> Self {
+        match error {
+            RelationError::UserNotFound => ApiErrorCode::UserNotFound,
+            RelationError::SelfRelation => ApiErrorCode::SelfRelation,
+            RelationError::AlreadyFriends => ApiErrorCode::AlreadyFriends,
+            RelationError::NotFriends => ApiErrorCode::NotFriends,
+            RelationError::NotOwner => ApiErrorCode::Forbidden,
+            RelationError::NotMember => ApiErrorCode::NotMember,
+            RelationError::GroupNotFound => ApiErrorCode::GroupNotFound,
+            RelationError::InvalidAppearance(_) => ApiErrorCode::InvalidGroupAppearance,
+            RelationError::RateLimited { reset_at } => ApiErrorCode::RateLimited { reset_at },
+            e => ApiErrorCode::internal(e),
+        }
+    }
+}
+
+impl From<ReplayError> for ApiErrorCode {
+    fn from(error: ReplayError) -> Self {
+        match error {
+            ReplayError::Store(e) => ApiErrorCode::internal(e),
+            _ => ApiErrorCode::BadReplaySelection,
+        }
+    }
+}
+
+impl From<ImportError> for ApiErrorCode {
+    fn from(error: ImportError) -> Self {
+        match error {
+            ImportError::TooLarge(_) => ApiErrorCode::BatchTooLarge,
+            ImportError::Invalid(_) => ApiErrorCode::InvalidImport,
+            ImportError::Store(e) => ApiErrorCode::internal(e),
+        }
+    }
+}
+
+impl From<ExportError> for ApiErrorCode {
+    fn from(error: ExportError) -> Self {
+        ApiErrorCode::internal(error)
+    }
+}
+
+impl From<DiscoveryError> for ApiErrorCode {
+    fn from(error: DiscoveryError) -> Self {
+        match error {
+            DiscoveryError::NotDiscoverable => ApiErrorCode::DiscoveryDisabled,
+            DiscoveryError::TooMany(_) => ApiErrorCode::BatchTooLarge,
+            DiscoveryError::RateLimited { reset_at } => ApiErrorCode::RateLimited { reset_at },
+            DiscoveryError::Store(e) => ApiErrorCode::internal(e),
+        }
+    }
+}
+
+impl From<DeviceError> for ApiErrorCode {
+    fn from(error: DeviceError) -> Self {
+        match error {
+            DeviceError::Invalid(_) => ApiErrorCode::InvalidDevice,
+            DeviceError::NotFound => ApiErrorCode::DeviceNotFound,
+            DeviceError::Store(e) => ApiErrorCode::internal(e),
+        }
+    }
+}
+
+impl From<WebhookError> for ApiErrorCode {
+    fn from(error: WebhookError) -> Self {
+        match error {
+            WebhookError::Invalid(_) => ApiErrorCode::InvalidWebhook,
+            WebhookError::NotMember => ApiErrorCode::NotMember,
+            WebhookError::Forbidden => ApiErrorCode::Forbidden,
+            WebhookError::NotFound => ApiErrorCode::WebhookNotFound,
+            WebhookError::Chat(e) => ApiErrorCode::from(e),
+            WebhookError::Store(e) => ApiErrorCode::internal(e),
+        }
+    }
+}
+
+impl From<NoticeError> for ApiErrorCode {
+    fn from(error: NoticeError) -> Self {
+        match error {
+            NoticeError::Invalid(_) => ApiErrorCode::InvalidNotice,
+            NoticeError::Store(e) => ApiErrorCode::internal(e),
+        }
+    }
+}
+
+impl From<StatsError> for ApiErrorCode {
+    fn from(error: StatsError) -> Self {
+        match error {
+            StatsError::InvalidRange(_) => ApiErrorCode::BadStatsRange,
+            StatsError::Store(e) => ApiErrorCode::internal(e),
+        }
+    }
+}
+
+impl From<ChatError> for ApiErrorCode {
+    fn from(error: ChatError) -> Self {
+        match error {
+            ChatError::BadCursor => ApiErrorCode::BadCursor,
+            ChatError::NotMember => ApiErrorCode::NotMember,
+            ChatError::ConversationNotFound => ApiErrorCode::ConversationNotFound,
+            ChatError::Frozen => ApiErrorCode::ConversationFrozen,
+            ChatError::MessageNotFound => ApiErrorCode::MessageNotFound,
+            ChatError::InvalidReply => ApiErrorCode::MessageNotFound,
+            ChatError::InvalidAttachment => ApiErrorCode::AttachmentNotFound,
+            ChatError::AttachmentNotFound => ApiErrorCode::AttachmentNotFound,
+            ChatError::InvalidUpload(_) => ApiErrorCode::InvalidUpload,
+            ChatError::TooManyPins => ApiErrorCode::TooManyPins,
+            ChatError::InvalidSearch(_) => ApiErrorCode::InvalidSearch,
+            ChatError::SearchUnavailable => ApiErrorCode::SearchUnavailable,
+            ChatError::Forbidden(_) => ApiErrorCode::Forbidden,
+            ChatError::InvalidMeta(_) => ApiErrorCode::InvalidMetadata,
+            e => ApiErrorCode::internal(e),
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use super::error::ApiErrorCode;
+use crate::api::{ClientPrincipal, PeerAddr};
+use crate::application_port::*;
+use crate::domain_model::{Cursor, IdempotencyKey, PageSize, TraceId, UserId};
+use crate::logger::*;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use warp::{Filter, http, reject};
+
+/// The header a retried write repeats so it is applied once.
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+pub(super) fn with<ServiceType>(
+    service: Arc<ServiceType>,
+) -> impl Filter<Extract = (Arc<ServiceType>,), Error = Infallible> + Clone
+where
+    ServiceType: Send + Sync + ?Sized,
+{
+    warp::any().map(move || service.clone())
+}
+
+pub(super) fn with_value<T: Clone + Send + Sync>(
+    value: T,
+) -> impl Filter<Extract = (T,), Error = Infallible> + Clone {
+    warp::any().map(move || value.clone())
+}
+
+/// The caller's `x-trace-id`, or a fresh one when it is missing or malformed.
+pub(super) fn with_trace() -> impl Filter<Extract = (TraceId,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("x-trace-id").map(|header: Option<String>| {
+        header
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    })
+}
+
+/// The caller's `Idempotency-Key`, if it sent one; a key that isn't a UUID
+/// is rejected rather than ignored, so a retry can't apply twice.
+pub(super) fn with_idempotency_key()
+-> impl Filter<Extract = (Option<IdempotencyKey>,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>(IDEMPOTENCY_KEY_HEADER).and_then(
+        |header: Option<String>| async move {
+            header
+                .map(|value| value.parse().map(IdempotencyKey))
+                .transpose()
+                .map_err(|_| reject::custom(ApiErrorCode::BadIdempotencyKey))
+        },
+    )
+}
+
+/// One page of a keyset-paginated list, as the caller asked for it.
+#[derive(Debug)]
+pub struct Page<C> {
+    pub size: PageSize,
+    /// Where the page starts; `None` for the first page.
+    pub cursor: Option<C>,
+}
+
+/// Reads `page_size` and the cursor in `cursor_param` from the query
+/// string, so handlers only ever see a size within `max_page_size` and a
+/// cursor that decoded.
+pub(super) fn with_page<C>(
+    cursor_param: &'static str,
+    max_page_size: PageSize,
+) -> impl Filter<Extract = (Page<C>,), Error = warp::Rejection> + Clone
+where
+    C: Cursor + Send + 'static,
+{
+    warp::query::<HashMap<String, String>>().and_then(
+        move |mut params: HashMap<String, String>| async move {
+            let size = params
+                .get("page_size")
+                .and_then(|value| value.parse().ok())
+                .map(PageSize)
+                .ok_or_else(|| reject::custom(ApiErrorCode::BadPageSize))?;
+            Ok::<_, warp::Rejection>(Page {
+                size: check_page_size(size, max_page_size)?,
+                cursor: decode_cursor(params.remove(cursor_param))?,
+            })
+        },
+    )
+}
+
+pub(super) fn check_page_size(
+    page_size: PageSize,
+    max: PageSize,
+) -> Result<PageSize, warp::Rejection> {
+    if page_size.0 == 0 || page_size > max {
+        return Err(reject::custom(ApiErrorCode::BadPageSize));
+    }
+    Ok(page_size)
+}
+
+fn decode_cursor<C: Cursor>(cursor: Option<String>) -> Result<Option<C>, warp::Rejection> {
+    cursor.map(|s| C::decode(&s)).transpose().map_err(|e| {
+        debug!("rejecting cursor: {e}");
+        reject::custom(ApiErrorCode::BadCursor)
+    })
+}
+
+/// The user a Bearer token was issued to, checked against the store.
+pub(super) fn with_verification(
+    auth_service: Arc<dyn AuthService>,
+) -> impl Filter<Extract = (UserId,), Error = warp::Rejection> + Clone {
+    warp::header::<String>(http::header::AUTHORIZATION.as_ref()).and_then(move |token: String| {
+        let auth_service = auth_service.clone();
+        async move {
+            if let Some(token) = token.strip_prefix("Bearer ") {
+                let user_id = auth_service
+                    .verify_token(token)
+                    .await
+                    .map_err(ApiErrorCode::from)
+                    .map_err(reject::custom)?;
+                Ok(user_id)
+            } else {
+                Err(reject::custom(ApiErrorCode::InvalidToken))
+            }
+        }
+    })
+}
+
+/// Who called an admin route.
+#[derive(Debug, Clone)]
+pub enum Caller {
+    /// A Bearer token with the admin role.
+    User(UserId),
+    /// A client certificate listed in `http.service_principals`.
+    Service(ClientPrincipal),
+}
+
+impl std::fmt::Display for Caller {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Caller::User(user_id) => write!(f, "user {}", user_id),
+            Caller::Service(principal) => write!(f, "service {}", principal),
+        }
+    }
+}
+
+/// Authorizes a token whose claims carry `role` if the user still holds it,
+/// so a role taken away stops working before the token expires. A caller
+/// without a token may instead present a client certificate whose principal
+/// is in `services`; see `api::serve_mtls`.
+pub(super) fn with_role(
+    auth_service: Arc<dyn AuthService>,
+    role: Role,
+    services: Arc<HashSet<String>>,
+) -> impl Filter<Extract = (Caller,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>(http::header::AUTHORIZATION.as_ref())
+        .and(warp::ext::optional::<ClientPrincipal>())
+        .and_then(
+            move |token: Option<String>, principal: Option<ClientPrincipal>| {
+                let auth_service = auth_service.clone();
+                let services = services.clone();
+                async move {
+                    let Some(token) = token else {
+                        return match principal {
+                            Some(principal) if services.contains(&principal.0) => {
+                                Ok(Caller::Service(principal))
+                            }
+                            Some(_) => Err(reject::custom(ApiErrorCode::Forbidden)),
+                            None => Err(reject::custom(ApiErrorCode::InvalidToken)),
+                        };
+                    };
+                    let Some(token) = token.strip_prefix("Bearer ") else {
+                        return Err(reject::custom(ApiErrorCode::InvalidToken));
+                    };
+                    let claims = auth_service
+                        .verify_claims(token)
+                        .await
+                        .map_err(ApiErrorCode::from)
+                        .map_err(reject::custom)?;
+                    // the token's roles are only as fresh as the token
+                    let holds_role = claims.grants.has_role(role)
+                        && auth_service
+                            .has_role(claims.user_id, role)
+                            .await
+                            .map_err(ApiErrorCode::from)
+                            .map_err(reject::custom)?;
+                    if holds_role {
+                        Ok(Caller::User(claims.user_id))
+                    } else {
+                        Err(reject::custom(ApiErrorCode::Forbidden))
+                    }
+                }
+            },
+        )
+}
+
+/// `warp::addr::remote` sees nothing on connections `serve_mtls` accepted.
+pub(super) fn with_remote_addr()
+-> impl Filter<Extract = (Option<SocketAddr>,), Error = Infallible> + Clone {
+    warp::addr::remote()
+        .and(warp::ext::optional::<PeerAddr>())
+        .map(|remote: Option<SocketAddr>, peer: Option<PeerAddr>| {
+            remote.or(peer.map(|peer| peer.0))
+        })
+}
+
+/// The client's IP: the last entry of `header` when a trusted proxy is
+/// configured to write one, since anything before it came from the client;
+/// the peer's address otherwise, or when the header is missing or garbled.
+pub(super) fn with_client_ip(
+    header: Option<String>,
+) -> impl Filter<Extract = (Option<IpAddr>,), Error = Infallible> + Clone {
+    with_remote_addr().and(warp::header::headers_cloned()).map(
+        move |remote: Option<SocketAddr>, headers: http::HeaderMap| {
+            let forwarded = header
+                .as_deref()
+                .and_then(|header| headers.get_all(header).iter().next_back())
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').next())
+                .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+            forwarded.or(remote.map(|addr| addr.ip()))
+        },
+    )
+}
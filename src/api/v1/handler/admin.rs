@@ -0,0 +1,445 @@
+use super::{ApiResponse, FreezeConversationRequest, traced};
+use crate::api::v1::error::ApiErrorCode;
+use crate::api::v1::extract::Caller;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::{EventId, ReplaySelection};
+use crate::logger::*;
+use crate::server::{SessionControl, SessionDirectory};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use warp::reject;
+
+/// Every node's sessions, as of their last report.
+pub async fn admin_sessions(
+    _admin: Caller,
+    session_directory: Arc<dyn SessionDirectory>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let sessions = session_directory
+        .sessions()
+        .await
+        .map_err(ApiErrorCode::internal)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(sessions)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DrainRequest {
+    pub draining: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DrainResponse {
+    pub draining: bool,
+    /// Sessions still open on this node.
+    pub sessions: usize,
+}
+
+pub async fn admin_drain(
+    body: DrainRequest,
+    admin: Caller,
+    session_control: Arc<dyn SessionControl>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("admin [{}] setting draining to {}", admin, body.draining);
+
+    session_control.set_draining(body.draining);
+
+    Ok(warp::reply::json(&ApiResponse::ok(DrainResponse {
+        draining: session_control.draining(),
+        sessions: session_control.sessions().len(),
+    })))
+}
+
+#[derive(Debug, Serialize)]
+pub struct DisconnectResponse {
+    pub refresh_tokens_revoked: u64,
+}
+
+/// The refresh tokens are revoked whichever node issued them; the sessions
+/// close on whichever nodes hold them once the event reaches them, after
+/// this returns.
+pub async fn admin_disconnect(
+    user_id: UserId,
+    admin: Caller,
+    user_service: Arc<dyn UserService>,
+    auth_service: Arc<dyn AuthService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("admin [{}] disconnecting user [{}]", admin, user_id);
+
+    // Revoke first so the client cannot refresh its way back in after the close.
+    let refresh_tokens_revoked = auth_service
+        .revoke_sessions(user_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+    user_service
+        .disconnect_sessions(user_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(DisconnectResponse {
+        refresh_tokens_revoked,
+    })))
+}
+
+pub async fn admin_deactivate(
+    user_id: UserId,
+    admin: Caller,
+    trace_id: TraceId,
+    user_service: Arc<dyn UserService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("admin [{}] deactivating user [{}]", admin, user_id);
+
+    traced(trace_id, user_service.deactivate_account(user_id))
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LegalHoldRequest {
+    pub hold: bool,
+}
+
+pub async fn admin_legal_hold(
+    user_id: UserId,
+    body: LegalHoldRequest,
+    admin: Caller,
+    user_service: Arc<dyn UserService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!(
+        "admin [{}] setting legal hold on user [{}] to {}",
+        admin, user_id, body.hold
+    );
+
+    user_service
+        .set_legal_hold(user_id, body.hold)
+        .await
+        .map_err(|e| match e {
+            AuthError::UserNotFound => ApiErrorCode::UserNotFound,
+            e => ApiErrorCode::from(e),
+        })
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetBotRequest {
+    pub bot: bool,
+}
+
+pub async fn admin_bot(
+    user_id: UserId,
+    body: SetBotRequest,
+    admin: Caller,
+    user_service: Arc<dyn UserService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!(
+        "admin [{}] setting bot flag on user [{}] to {}",
+        admin, user_id, body.bot
+    );
+
+    user_service
+        .set_bot(user_id, body.bot)
+        .await
+        .map_err(|e| match e {
+            AuthError::UserNotFound => ApiErrorCode::UserNotFound,
+            e => ApiErrorCode::from(e),
+        })
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetConversationMetaRequest {
+    pub key: String,
+    /// `None` removes the key.
+    pub value: Option<String>,
+}
+
+pub async fn admin_set_conversation_meta(
+    conversation_id: ConversationId,
+    body: SetConversationMetaRequest,
+    admin: Caller,
+    conversation_meta_service: Arc<dyn ConversationMetaService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!(
+        "admin [{}] setting meta [{}] on conversation [{}]",
+        admin, body.key, conversation_id
+    );
+
+    conversation_meta_service
+        .set(conversation_id, &body.key, body.value.as_deref())
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+pub async fn admin_freeze_conversation(
+    conversation_id: ConversationId,
+    body: FreezeConversationRequest,
+    admin: Caller,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!(
+        "admin [{}] setting frozen on conversation [{}] to {}",
+        admin, conversation_id, body.frozen
+    );
+
+    conversation_service
+        .set_frozen(conversation_id, None, body.frozen)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+pub async fn admin_import(
+    admin: Caller,
+    body: ImportBatch,
+    import_service: Arc<dyn ImportService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("admin [{}] importing {} rows", admin, body.len());
+
+    let report = import_service
+        .import(body)
+        .await
+        .map_err(|e| {
+            if let ImportError::Invalid(reason) = &e {
+                info!("import batch rejected: {reason}");
+            }
+            ApiErrorCode::from(e)
+        })
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(report)))
+}
+
+pub async fn admin_username_rules(
+    _admin: Caller,
+    username_policy_service: Arc<dyn UsernamePolicyService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let rules = username_policy_service
+        .rules()
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(rules)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsernameRuleChangeResponse {
+    /// `false` when the rule was already there, or already gone.
+    pub changed: bool,
+}
+
+pub async fn admin_add_username_rule(
+    body: UsernameRule,
+    admin: Caller,
+    username_policy_service: Arc<dyn UsernamePolicyService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("admin [{}] adding username rule {:?}", admin, body);
+
+    let changed = username_policy_service
+        .add_rule(body)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(
+        UsernameRuleChangeResponse { changed },
+    )))
+}
+
+pub async fn admin_remove_username_rule(
+    body: UsernameRule,
+    admin: Caller,
+    username_policy_service: Arc<dyn UsernamePolicyService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("admin [{}] removing username rule {:?}", admin, body);
+
+    let changed = username_policy_service
+        .remove_rule(body)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(
+        UsernameRuleChangeResponse { changed },
+    )))
+}
+
+pub async fn admin_mint_invites(
+    body: MintInvites,
+    admin: Caller,
+    invite_service: Arc<dyn InviteService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("admin [{}] minting {} invite codes", admin, body.count);
+
+    let codes = invite_service
+        .mint(body, admin.to_string())
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(codes)))
+}
+
+pub async fn admin_list_invites(
+    _admin: Caller,
+    invite_service: Arc<dyn InviteService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let codes = invite_service
+        .list()
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(codes)))
+}
+
+/// Either `event_ids`, or a `from`/`to` window on `created_at`.
+#[derive(Debug, Deserialize)]
+pub struct ReplayEventsRequest {
+    pub event_ids: Option<Vec<EventId>>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplayEventsResponse {
+    pub requeued: u64,
+}
+
+pub async fn admin_replay_events(
+    body: ReplayEventsRequest,
+    admin: Caller,
+    event_replay_service: Arc<dyn EventReplayService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let selection = match body {
+        ReplayEventsRequest {
+            event_ids: Some(ids),
+            from: None,
+            to: None,
+        } => ReplaySelection::Events(ids),
+        ReplayEventsRequest {
+            event_ids: None,
+            from: Some(from),
+            to: Some(to),
+        } => ReplaySelection::CreatedBetween { from, to },
+        _ => return Err(reject::custom(ApiErrorCode::BadReplaySelection)),
+    };
+    info!("admin [{}] replaying outbox events: {:?}", admin, selection);
+
+    let requeued = event_replay_service
+        .replay(selection)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(ReplayEventsResponse {
+        requeued,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DailyStatsQuery {
+    /// Today and the days before it; 30 when absent.
+    #[serde(default = "default_stats_days")]
+    pub days: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TopGroupsQuery {
+    #[serde(default = "default_stats_days")]
+    pub days: u32,
+    #[serde(default = "default_top_groups_limit")]
+    pub limit: u32,
+}
+
+fn default_stats_days() -> u32 {
+    30
+}
+
+fn default_top_groups_limit() -> u32 {
+    10
+}
+
+pub async fn admin_daily_stats(
+    query: DailyStatsQuery,
+    _admin: Caller,
+    stats_service: Arc<dyn StatsService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let days = stats_service
+        .daily(query.days)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(days)))
+}
+
+pub async fn admin_top_groups(
+    query: TopGroupsQuery,
+    _admin: Caller,
+    stats_service: Arc<dyn StatsService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let groups = stats_service
+        .top_groups(query.days, query.limit)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(groups)))
+}
+
+/// Omit `user_ids` to reach everyone online, and `publish_at` to publish now.
+#[derive(Debug, Deserialize)]
+pub struct PublishNoticeRequest {
+    pub kind: NoticeKind,
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub user_ids: Option<Vec<UserId>>,
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+pub async fn admin_publish_notice(
+    body: PublishNoticeRequest,
+    admin: Caller,
+    notice_service: Arc<dyn NoticeService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let draft = NoticeDraft {
+        kind: body.kind,
+        title: body.title,
+        body: body.body,
+        starts_at: body.starts_at,
+        ends_at: body.ends_at,
+        receivers: body.user_ids,
+        publish_at: body.publish_at,
+    };
+    let notice = notice_service
+        .publish(draft)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+    info!(
+        "admin [{}] published notice [{}] for {}",
+        admin, notice.notice_id.0, notice.published_at
+    );
+
+    Ok(warp::reply::json(&ApiResponse::ok(notice)))
+}
@@ -0,0 +1,353 @@
+use super::ApiResponse;
+use crate::api::v1::error::ApiErrorCode;
+use crate::api::v1::i18n::Locale;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::logger::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use warp::http::StatusCode;
+use warp::http::header::{self, HeaderValue};
+use warp::hyper::Body;
+use warp::{Reply, reject};
+
+#[derive(Debug, Serialize)]
+struct CaptchaResponse {
+    id: uuid::Uuid,
+    image_base64: String,
+    /// What to do with the image, in the caller's language.
+    prompt: &'static str,
+    expire_at: DateTime<Utc>,
+}
+
+pub async fn generate_captcha(
+    locale: Locale,
+    captcha_service: Arc<dyn CaptchaService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let captcha = captcha_service
+        .generate()
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let response = CaptchaResponse {
+        id: captcha.id.0,
+        image_base64: captcha.image_base64,
+        prompt: locale.captcha_prompt(),
+        expire_at: captcha.expire_at,
+    };
+    Ok(warp::reply::json(&response))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: Secret<String>,
+    pub captcha_id: uuid::Uuid,
+    pub captcha_answer: String,
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginResponse {
+    pub user_id: UserId,
+    pub auth_tokens: AuthTokens,
+}
+
+pub async fn login(
+    body: LoginRequest,
+    remote_addr: Option<SocketAddr>,
+    auth_service: Arc<dyn AuthService>,
+    captcha_service: Arc<dyn CaptchaService>,
+    login_risk_evaluator: Arc<dyn LoginRiskEvaluator>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let validation_input = ValidationInput {
+        id: CaptchaId(body.captcha_id),
+        answer: body.captcha_answer,
+    };
+    captcha_service
+        .validate(validation_input)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let login_input = LoginInput {
+        username: body.username.clone(),
+        password: body.password.clone(),
+        device: body.device.clone(),
+    };
+    let login_result = auth_service
+        .login(login_input)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    if let Some(remote_addr) = remote_addr {
+        let attempt = LoginAttempt {
+            user_id: login_result.user_id,
+            ip: remote_addr.ip(),
+            device: body.device,
+            at: Utc::now(),
+        };
+        // the evaluator only alerts, so a failure must not lock anyone out
+        match login_risk_evaluator.evaluate(attempt).await {
+            Ok(LoginRisk::Usual) => {}
+            Ok(LoginRisk::Unusual { location }) => info!(
+                "user [{}] logged in from unusual location {}",
+                login_result.user_id, location
+            ),
+            Err(e) => warn!("login risk of user [{}]: {}", login_result.user_id, e),
+        }
+    }
+
+    let login_response = LoginResponse {
+        user_id: login_result.user_id,
+        auth_tokens: login_result.tokens,
+    };
+    let api_response = ApiResponse::ok(login_response);
+
+    Ok(warp::reply::json(&api_response))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignupRequest {
+    pub username: String,
+    pub password: Secret<String>,
+    pub captcha_id: uuid::Uuid,
+    pub captcha_answer: String,
+    #[serde(default)]
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignupResponse;
+
+pub async fn signup(
+    body: SignupRequest,
+    auth_service: Arc<dyn AuthService>,
+    captcha_service: Arc<dyn CaptchaService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let validation_input = ValidationInput {
+        id: CaptchaId(body.captcha_id),
+        answer: body.captcha_answer,
+    };
+    captcha_service
+        .validate(validation_input)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let signup_input = SignupInput {
+        username: body.username,
+        password: body.password,
+        invite_code: body.invite_code,
+    };
+    let _user_id = auth_service
+        .signup(signup_input)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(SignupResponse)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartQrLoginRequest {
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+/// Called by the client that wants to sign in; it shows `nonce` as a QR code
+/// and keeps `poll_secret` to itself.
+pub async fn start_qr_login(
+    body: StartQrLoginRequest,
+    auth_service: Arc<dyn AuthService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let ticket = auth_service
+        .start_qr_login(body.device)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(ticket)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApproveQrLoginRequest {
+    pub nonce: String,
+}
+
+/// Called by a signed-in client that scanned the code.
+pub async fn approve_qr_login(
+    body: ApproveQrLoginRequest,
+    user_id: UserId,
+    auth_service: Arc<dyn AuthService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    auth_service
+        .approve_qr_login(user_id, &body.nonce)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollQrLoginRequest {
+    pub nonce: String,
+    pub poll_secret: Secret<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum PollQrLoginResponse {
+    Pending,
+    Approved(LoginResponse),
+}
+
+/// Polled by the client that started the QR login, every couple of seconds
+/// until approved or expired.
+pub async fn poll_qr_login(
+    body: PollQrLoginRequest,
+    auth_service: Arc<dyn AuthService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let login_result = auth_service
+        .poll_qr_login(&body.nonce, body.poll_secret.expose())
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let response = match login_result {
+        None => PollQrLoginResponse::Pending,
+        Some(login_result) => PollQrLoginResponse::Approved(LoginResponse {
+            user_id: login_result.user_id,
+            auth_tokens: login_result.tokens,
+        }),
+    };
+    Ok(warp::reply::json(&ApiResponse::ok(response)))
+}
+
+pub async fn list_sessions(
+    user_id: UserId,
+    auth_service: Arc<dyn AuthService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let sessions = auth_service
+        .list_sessions(user_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(sessions)))
+}
+
+pub async fn logout_all(
+    user_id: UserId,
+    auth_service: Arc<dyn AuthService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let revoked = auth_service
+        .revoke_sessions(user_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(revoked)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterDeviceRequest {
+    pub platform: PushPlatform,
+    pub token: String,
+    /// What language the device's notifications are in, e.g. `de-DE`.
+    pub locale: String,
+}
+
+pub async fn register_device(
+    body: RegisterDeviceRequest,
+    user_id: UserId,
+    device_service: Arc<dyn DeviceService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let registration = DeviceRegistration {
+        platform: body.platform,
+        token: body.token,
+        locale: body.locale,
+    };
+    let device = device_service
+        .register(user_id, registration)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(device)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnregisterDeviceRequest {
+    pub platform: PushPlatform,
+    pub token: String,
+}
+
+pub async fn unregister_device(
+    body: UnregisterDeviceRequest,
+    user_id: UserId,
+    device_service: Arc<dyn DeviceService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    device_service
+        .unregister(user_id, body.platform, &body.token)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+/// `202` with the job's progress while the archive is being built, then the
+/// archive itself as JSON Lines, streamed as it is decrypted.
+pub async fn export_account(
+    user_id: UserId,
+    export_service: Arc<dyn ExportService>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let status = export_service
+        .export(user_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    if status.progress.stage == ExportStage::Failed {
+        return Err(reject::custom(ApiErrorCode::internal(format!(
+            "export for [{user_id}] failed"
+        ))));
+    }
+    let Some(archive) = status.archive else {
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&ApiResponse::ok(status.progress)),
+            StatusCode::ACCEPTED,
+        )
+        .into_response());
+    };
+
+    let mut response = warp::reply::Response::new(Body::wrap_stream(archive.content));
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+    if let Ok(disposition) =
+        HeaderValue::from_str(&format!("attachment; filename=\"{}\"", archive.file_name))
+    {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    Ok(response)
+}
+
+/// Every soft-launched feature, and whether the caller is in its cohort.
+pub async fn my_features(
+    user_id: UserId,
+    feature_flags: Arc<FeatureFlags>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&ApiResponse::ok(
+        feature_flags.for_user(user_id),
+    )))
+}
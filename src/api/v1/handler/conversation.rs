@@ -0,0 +1,727 @@
+use super::{ApiResponse, CursorPage, traced};
+use crate::api::v1::error::ApiErrorCode;
+use crate::api::v1::extract::{Page, check_page_size};
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::logger::Secret;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use warp::http::header::{self, HeaderValue};
+use warp::reject;
+
+/// Upper bound on `members` per `POST conversations`, the owner aside.
+const MAX_NEW_GROUP_MEMBERS: usize = 100;
+
+/// Longest group name, in characters; `chat_group.group_name` holds 64.
+const MAX_GROUP_NAME_CHARS: usize = 64;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateConversationRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub members: Vec<UserId>,
+    /// For clients that can't set the `Idempotency-Key` header, which wins
+    /// when both are sent.
+    #[serde(default)]
+    pub key: Option<IdempotencyKey>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateConversationResponse {
+    pub group_id: GroupId,
+    pub conversation_id: ConversationId,
+}
+
+/// An ad-hoc group chat with the caller as owner and `members` already in.
+pub async fn create_conversation(
+    body: CreateConversationRequest,
+    user_id: UserId,
+    idempotency_key: Option<IdempotencyKey>,
+    trace_id: TraceId,
+    relationship_service: Arc<dyn RelationshipService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let key = idempotency_key
+        .or(body.key)
+        .ok_or_else(|| reject::custom(ApiErrorCode::BadIdempotencyKey))?;
+    let name = body.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_GROUP_NAME_CHARS {
+        return Err(reject::custom(ApiErrorCode::InvalidGroupName));
+    }
+    if body.members.len() > MAX_NEW_GROUP_MEMBERS {
+        return Err(reject::custom(ApiErrorCode::BatchTooLarge));
+    }
+
+    let (group_id, conversation_id) = traced(
+        trace_id,
+        relationship_service.create_group(
+            user_id,
+            name,
+            body.description.as_deref(),
+            &body.members,
+            key,
+        ),
+    )
+    .await
+    .map_err(ApiErrorCode::from)
+    .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(
+        CreateConversationResponse {
+            group_id,
+            conversation_id,
+        },
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenDirectRequest {
+    pub user_id: UserId,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpenDirectResponse {
+    pub conversation_id: ConversationId,
+}
+
+/// The caller's direct conversation with `user_id`, created on first use;
+/// see `RelationshipService::open_direct`.
+pub async fn open_direct(
+    body: OpenDirectRequest,
+    user_id: UserId,
+    relationship_service: Arc<dyn RelationshipService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let conversation_id = relationship_service
+        .open_direct(user_id, body.user_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(OpenDirectResponse {
+        conversation_id,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationHistoryQuery {
+    pub conversation_id: ConversationId,
+}
+
+pub async fn generate_conversation_history(
+    query: ConversationHistoryQuery,
+    page: Page<OffsetCursor>,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let history = conversation_service
+        .get_history(user_id, query.conversation_id, page.size, page.cursor)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    // pages are oldest-first, so the next (older) page starts before the first item
+    let page = CursorPage::new(history, page.size, |items| {
+        items.first().map(|first| OffsetCursor {
+            offset: first.message_offset,
+        })
+    });
+    let response = ApiResponse::ok(page);
+    Ok(warp::reply::json(&response))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistorySummaryQuery {
+    pub conversation_id: ConversationId,
+}
+
+pub async fn generate_history_summary(
+    query: HistorySummaryQuery,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let summary = conversation_service
+        .history_summary(user_id, query.conversation_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let response = ApiResponse::ok(summary);
+    Ok(warp::reply::json(&response))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OffsetAtQuery {
+    pub conversation_id: ConversationId,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OffsetAtResponse {
+    /// `None` when the conversation has no messages.
+    pub offset: Option<MessageOffset>,
+}
+
+pub async fn offset_at(
+    query: OffsetAtQuery,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let offset = conversation_service
+        .offset_at(user_id, query.conversation_id, query.at)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(OffsetAtResponse {
+        offset,
+    })))
+}
+
+/// How many conversations `unread_summary` lists when the caller doesn't say.
+const DEFAULT_UNREAD_TOP: PageSize = PageSize(20);
+
+#[derive(Debug, Deserialize)]
+pub struct UnreadSummaryQuery {
+    pub top: Option<PageSize>,
+}
+
+pub async fn generate_unread_summary(
+    query: UnreadSummaryQuery,
+    user_id: UserId,
+    max_page_size: PageSize,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let top = check_page_size(query.top.unwrap_or(DEFAULT_UNREAD_TOP), max_page_size)?;
+
+    let summary = conversation_service
+        .unread_summary(user_id, top)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(summary)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PinConversationRequest {
+    pub conversation_id: ConversationId,
+    pub pinned: bool,
+}
+
+pub async fn pin_conversation(
+    body: PinConversationRequest,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    conversation_service
+        .set_pinned(user_id, body.conversation_id, body.pinned)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EditMessageRequest {
+    pub content: Secret<String>,
+}
+
+/// The sender only; returns the message as stored after the edit.
+pub async fn edit_message(
+    conversation_id: ConversationId,
+    message_id: MessageId,
+    body: EditMessageRequest,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let record = conversation_service
+        .edit_message(
+            conversation_id,
+            user_id,
+            message_id,
+            body.content.expose().as_str(),
+        )
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(record)))
+}
+
+/// The sender or the group owner; history keeps a tombstone in its place.
+pub async fn delete_message(
+    conversation_id: ConversationId,
+    message_id: MessageId,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    conversation_service
+        .delete_message(conversation_id, user_id, message_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+/// Group owners, or either member of a direct conversation.
+pub async fn pin_message(
+    conversation_id: ConversationId,
+    message_id: MessageId,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    conversation_service
+        .pin_message(conversation_id, user_id, message_id, true)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+pub async fn unpin_message(
+    conversation_id: ConversationId,
+    message_id: MessageId,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    conversation_service
+        .pin_message(conversation_id, user_id, message_id, false)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+/// Most recently pinned first.
+pub async fn pinned_messages(
+    conversation_id: ConversationId,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pins = conversation_service
+        .pinned_messages(user_id, conversation_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(pins)))
+}
+
+/// Members whose client got the caller's message, earliest first.
+pub async fn message_deliveries(
+    conversation_id: ConversationId,
+    message_id: MessageId,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let deliveries = conversation_service
+        .deliveries(user_id, conversation_id, message_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(deliveries)))
+}
+
+/// Replies to `message_id`, oldest first; `after` continues from the last
+/// reply of the previous page.
+pub async fn thread_history(
+    conversation_id: ConversationId,
+    message_id: MessageId,
+    page: Page<OffsetCursor>,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let replies = conversation_service
+        .thread_history(user_id, conversation_id, message_id, page.size, page.cursor)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let page = CursorPage::new(replies, page.size, |items| {
+        items.last().map(|last| OffsetCursor {
+            offset: last.message_offset,
+        })
+    });
+    Ok(warp::reply::json(&ApiResponse::ok(page)))
+}
+
+/// Messages mentioning the caller, newest first; `before` continues from the
+/// last one of the previous page.
+pub async fn mentions(
+    page: Page<SearchCursor>,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let found = conversation_service
+        .mentions(user_id, page.size, page.cursor)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let page = CursorPage::new(found, page.size, |items| {
+        items.last().map(|last| SearchCursor {
+            created_at: last.created_at,
+            message_id: last.message_id,
+        })
+    });
+    Ok(warp::reply::json(&ApiResponse::ok(page)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MessageSearchQuery {
+    pub q: String,
+    #[serde(flatten)]
+    pub filters: SearchFilters,
+}
+
+/// Messages matching every word of `q` across the caller's conversations,
+/// newest first; `before` continues from the last match of the previous page.
+pub async fn message_search(
+    query: MessageSearchQuery,
+    page: Page<SearchCursor>,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let found = conversation_service
+        .search_messages(user_id, &query.q, &query.filters, page.size, page.cursor)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let page = CursorPage::new(found, page.size, |items| {
+        items.last().map(|last| SearchCursor {
+            created_at: last.created_at,
+            message_id: last.message_id,
+        })
+    });
+    Ok(warp::reply::json(&ApiResponse::ok(page)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FreezeConversationRequest {
+    pub frozen: bool,
+}
+
+/// Group owners only; admins use `admin_freeze_conversation`.
+pub async fn freeze_conversation(
+    conversation_id: ConversationId,
+    body: FreezeConversationRequest,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    conversation_service
+        .set_frozen(conversation_id, Some(user_id), body.frozen)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+pub async fn conversation_retention(
+    conversation_id: ConversationId,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let retention = conversation_service
+        .retention(user_id, conversation_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(retention)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRetentionRequest {
+    /// `null` returns to the server default.
+    pub days: Option<u32>,
+}
+
+/// Group owners only. Out-of-bounds days are clamped, not refused; the reply
+/// carries what was stored.
+pub async fn set_conversation_retention(
+    conversation_id: ConversationId,
+    body: SetRetentionRequest,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let retention = conversation_service
+        .set_retention(conversation_id, user_id, body.days)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(retention)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterWebhookRequest {
+    pub url: String,
+}
+
+/// Group owners only. The reply is the one place the hook's secret is shown.
+pub async fn register_webhook(
+    conversation_id: ConversationId,
+    body: RegisterWebhookRequest,
+    user_id: UserId,
+    webhook_service: Arc<dyn WebhookService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let webhook = webhook_service
+        .register(user_id, conversation_id, &body.url)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(webhook)))
+}
+
+pub async fn conversation_webhooks(
+    conversation_id: ConversationId,
+    user_id: UserId,
+    webhook_service: Arc<dyn WebhookService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let webhooks = webhook_service
+        .list(user_id, conversation_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(webhooks)))
+}
+
+pub async fn remove_webhook(
+    conversation_id: ConversationId,
+    webhook_id: WebhookId,
+    user_id: UserId,
+    webhook_service: Arc<dyn WebhookService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    webhook_service
+        .remove(user_id, conversation_id, webhook_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueIncomingWebhookRequest {
+    pub name: String,
+    /// The bot account, a member, its messages are sent as.
+    pub bot_user_id: UserId,
+}
+
+/// Group owners only. The reply is the one place the token is shown; the
+/// integration posts to `hooks/{token}`.
+pub async fn issue_incoming_webhook(
+    conversation_id: ConversationId,
+    body: IssueIncomingWebhookRequest,
+    user_id: UserId,
+    webhook_service: Arc<dyn WebhookService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let webhook = webhook_service
+        .issue_incoming(user_id, conversation_id, &body.name, body.bot_user_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(webhook)))
+}
+
+pub async fn incoming_webhooks(
+    conversation_id: ConversationId,
+    user_id: UserId,
+    webhook_service: Arc<dyn WebhookService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let webhooks = webhook_service
+        .list_incoming(user_id, conversation_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(webhooks)))
+}
+
+pub async fn revoke_incoming_webhook(
+    conversation_id: ConversationId,
+    incoming_webhook_id: IncomingWebhookId,
+    user_id: UserId,
+    webhook_service: Arc<dyn WebhookService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    webhook_service
+        .revoke_incoming(user_id, conversation_id, incoming_webhook_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IncomingMessageRequest {
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IncomingMessageResponse {
+    pub conversation_id: ConversationId,
+    pub message_id: MessageId,
+    pub message_offset: MessageOffset,
+    pub created_at: DateTime<Utc>,
+    /// An `Idempotency-Key` already used by this hook: nothing was sent.
+    pub duplicate: bool,
+}
+
+/// Unauthenticated: the token in the path is the credential.
+pub async fn post_incoming_message(
+    token: String,
+    body: IncomingMessageRequest,
+    idempotency_key: Option<IdempotencyKey>,
+    trace_id: TraceId,
+    webhook_service: Arc<dyn WebhookService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let sent = traced(
+        trace_id,
+        webhook_service.post_incoming(&token, &body.content, idempotency_key),
+    )
+    .await
+    .map_err(ApiErrorCode::from)
+    .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(
+        IncomingMessageResponse {
+            conversation_id: sent.record.conversation_id,
+            message_id: sent.record.message_id,
+            message_offset: sent.record.message_offset,
+            created_at: sent.record.created_at,
+            duplicate: sent.duplicate,
+        },
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarkReadRequest {
+    /// The newest offset the client has shown; capped at the newest message.
+    pub offset: MessageOffset,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkReadResponse {
+    pub last_read_off: MessageOffset,
+}
+
+pub async fn mark_read(
+    conversation_id: ConversationId,
+    body: MarkReadRequest,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let last_read_off = conversation_service
+        .mark_read(user_id, conversation_id, body.offset)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(MarkReadResponse {
+        last_read_off,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationMembersQuery {
+    /// The `members_version` the client's list is at; omit for a snapshot.
+    pub since_version: Option<u64>,
+}
+
+pub async fn conversation_members(
+    conversation_id: ConversationId,
+    query: ConversationMembersQuery,
+    user_id: UserId,
+    conversation_service: Arc<dyn ConversationService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let delta = conversation_service
+        .members_since(user_id, conversation_id, query.since_version)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(delta)))
+}
+
+pub async fn conversation_meta(
+    conversation_id: ConversationId,
+    user_id: UserId,
+    conversation_meta_service: Arc<dyn ConversationMetaService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let entries = conversation_meta_service
+        .list(user_id, conversation_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(entries)))
+}
+
+/// The body is the file itself; its `Content-Type`, minus parameters,
+/// is what downloads are served as.
+pub async fn upload_attachment(
+    conversation_id: ConversationId,
+    user_id: UserId,
+    content_type: Option<String>,
+    body: warp::hyper::body::Bytes,
+    attachment_service: Arc<dyn AttachmentService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mime = content_type
+        .as_deref()
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    let attachment = attachment_service
+        .upload(user_id, conversation_id, &mime, body.to_vec())
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(attachment)))
+}
+
+/// Raw bytes, not the JSON envelope. Only raster images display inline;
+/// anything else, SVG included, downloads, so an upload can't run script
+/// on this origin.
+pub async fn download_attachment(
+    conversation_id: ConversationId,
+    attachment_id: AttachmentId,
+    user_id: UserId,
+    attachment_service: Arc<dyn AttachmentService>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let blob = attachment_service
+        .download(user_id, conversation_id, attachment_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let inline = blob.content_type.starts_with("image/") && blob.content_type != "image/svg+xml";
+    let content_type = HeaderValue::from_str(&blob.content_type)
+        .unwrap_or_else(|_| HeaderValue::from_static("application/octet-stream"));
+    let mut response = warp::reply::Response::new(blob.content.into());
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, content_type);
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_static(if inline { "inline" } else { "attachment" }),
+    );
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("private"));
+    Ok(response)
+}
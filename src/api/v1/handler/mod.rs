@@ -0,0 +1,73 @@
+mod admin;
+mod auth;
+mod conversation;
+mod relationship;
+mod system;
+mod ws;
+
+pub use admin::*;
+pub use auth::*;
+pub use conversation::*;
+pub use relationship::*;
+pub use system::*;
+pub use ws::*;
+
+use super::error::*;
+use crate::domain_model::{Cursor, PageSize, TraceId};
+use serde::Serialize;
+use tracing::{Instrument, info_span};
+
+#[derive(Debug, Serialize)]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<ApiError>,
+}
+
+impl<T: Serialize> ApiResponse<T> {
+    pub fn ok(data: T) -> Self {
+        ApiResponse {
+            success: true,
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    pub fn err(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        ApiResponse {
+            success: false,
+            data: None,
+            error: Some(ApiError::new(code, message)),
+        }
+    }
+}
+
+/// One page of a keyset-paginated list; pass `next_cursor` back to continue.
+#[derive(Debug, Serialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> CursorPage<T> {
+    /// A short page means the list is exhausted.
+    fn new<C: Cursor>(
+        items: Vec<T>,
+        page_size: PageSize,
+        cursor_of: impl FnOnce(&[T]) -> Option<C>,
+    ) -> Self {
+        let next_cursor = if items.len() < page_size.0 as usize {
+            None
+        } else {
+            cursor_of(&items).map(|c| c.encode())
+        };
+        Self { items, next_cursor }
+    }
+}
+
+/// Runs a service call under `trace_id`, so the outbox events it enqueues
+/// (and their fan-out) log the same trace.
+async fn traced<F: Future>(trace_id: TraceId, f: F) -> F::Output {
+    let span = info_span!("request", %trace_id);
+    trace_id.scope(f.instrument(span)).await
+}
@@ -0,0 +1,198 @@
+use super::{ApiResponse, CursorPage, traced};
+use crate::api::v1::error::{ApiError, ApiErrorCode};
+use crate::api::v1::extract::Page;
+use crate::api::v1::i18n::Locale;
+use crate::application_port::*;
+use crate::domain_model::*;
+use serde::Deserialize;
+use std::sync::Arc;
+use warp::http::StatusCode;
+use warp::reject;
+
+pub async fn generate_friend_list(
+    page: Page<FriendCursor>,
+    user_id: UserId,
+    relationship_service: Arc<dyn RelationshipService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let summary = relationship_service
+        .list_friends(user_id, page.size, page.cursor)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    let page = CursorPage::new(summary, page.size, |items| {
+        items.last().map(|last| FriendCursor {
+            since: last.since,
+            other_user: last.user_id,
+        })
+    });
+    let response = ApiResponse::ok(page);
+    Ok(warp::reply::json(&response))
+}
+
+/// Upper bound on ids per `group_member_counts` call.
+const MAX_MEMBER_COUNT_BATCH: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct GroupMemberCountsRequest {
+    pub group_ids: Vec<GroupId>,
+}
+
+pub async fn group_member_counts(
+    body: GroupMemberCountsRequest,
+    user_id: UserId,
+    relationship_service: Arc<dyn RelationshipService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if body.group_ids.len() > MAX_MEMBER_COUNT_BATCH {
+        return Err(reject::custom(ApiErrorCode::BatchTooLarge));
+    }
+
+    let counts = relationship_service
+        .group_member_counts(user_id, &body.group_ids)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(counts)))
+}
+
+/// Members only.
+pub async fn group_appearance(
+    group_id: GroupId,
+    user_id: UserId,
+    relationship_service: Arc<dyn RelationshipService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let appearance = relationship_service
+        .group_appearance(user_id, group_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(appearance)))
+}
+
+/// Owner only; the body replaces the whole appearance.
+pub async fn set_group_appearance(
+    group_id: GroupId,
+    body: GroupAppearance,
+    user_id: UserId,
+    relationship_service: Arc<dyn RelationshipService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    relationship_service
+        .set_group_appearance(group_id, user_id, body)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(())))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddFriendRequest {
+    pub other: String,
+    /// For clients that predate the `Idempotency-Key` header, which wins
+    /// when both are sent.
+    #[serde(default)]
+    pub key: Option<IdempotencyKey>,
+}
+
+pub async fn add_friend(
+    body: AddFriendRequest,
+    user_id: UserId,
+    idempotency_key: Option<IdempotencyKey>,
+    trace_id: TraceId,
+    locale: Locale,
+    user_service: Arc<dyn UserService>,
+    relationship_service: Arc<dyn RelationshipService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let key = idempotency_key
+        .or(body.key)
+        .ok_or_else(|| reject::custom(ApiErrorCode::BadIdempotencyKey))?;
+    let other_id: UserId = user_service
+        .resolve_username(&body.other)
+        .await
+        .map_err(|e| match e {
+            AuthError::UserNotFound => ApiErrorCode::UserNotFound,
+            e => ApiErrorCode::internal(e),
+        })
+        .map_err(reject::custom)?;
+
+    let outcome = traced(
+        trace_id,
+        relationship_service.add_friend(user_id, other_id, key),
+    )
+    .await
+    .map_err(ApiErrorCode::from)
+    .map_err(reject::custom)?;
+
+    // an existing friendship is a conflict, but the client still needs the conversation
+    let (response, status) = match outcome {
+        AddFriendOutcome::Created(_) => (ApiResponse::ok(outcome), StatusCode::OK),
+        AddFriendOutcome::AlreadyFriends(_) => {
+            let code = ApiErrorCode::AlreadyFriends;
+            let response = ApiResponse {
+                success: false,
+                data: Some(outcome),
+                error: Some(ApiError::new(code.clone(), code.message(locale))),
+            };
+            (response, StatusCode::CONFLICT)
+        }
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&response),
+        status,
+    ))
+}
+
+pub async fn discovery_settings(
+    user_id: UserId,
+    contact_discovery_service: Arc<dyn ContactDiscoveryService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let settings = contact_discovery_service
+        .settings(user_id)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(settings)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscoveryIdentifiersRequest {
+    /// The caller's own identifiers, hashed; empty turns discovery off.
+    pub identifiers: Vec<ContactHash>,
+}
+
+pub async fn set_discovery_identifiers(
+    body: DiscoveryIdentifiersRequest,
+    user_id: UserId,
+    contact_discovery_service: Arc<dyn ContactDiscoveryService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let settings = contact_discovery_service
+        .set_identifiers(user_id, &body.identifiers)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(settings)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscoverContactsRequest {
+    /// Hashes of the address book's phone numbers and email addresses.
+    pub hashes: Vec<ContactHash>,
+}
+
+pub async fn discover_contacts(
+    body: DiscoverContactsRequest,
+    user_id: UserId,
+    contact_discovery_service: Arc<dyn ContactDiscoveryService>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let matches = contact_discovery_service
+        .discover(user_id, &body.hashes)
+        .await
+        .map_err(ApiErrorCode::from)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::json(&ApiResponse::ok(matches)))
+}
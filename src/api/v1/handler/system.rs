@@ -0,0 +1,75 @@
+use super::ApiResponse;
+use crate::api::v1::error::ApiErrorCode;
+use crate::metrics;
+use crate::server::{HealthMonitor, SessionControl};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, LazyLock};
+use std::time::Instant;
+use warp::http::StatusCode;
+use warp::http::header;
+use warp::reject;
+
+/// The origin of `monotonic_ms` in `server_time`, and the id that names it.
+static CLOCK_ORIGIN: LazyLock<(Instant, uuid::Uuid)> =
+    LazyLock::new(|| (Instant::now(), uuid::Uuid::new_v4()));
+
+#[derive(Debug, Serialize)]
+pub struct ServerTimeResponse {
+    /// UTC wall clock when the response was built.
+    pub server_time: DateTime<Utc>,
+    /// `server_time` as Unix milliseconds.
+    pub unix_ms: i64,
+    /// Milliseconds on this process's monotonic clock, which never jumps
+    /// when the wall clock is adjusted. Only comparable between responses
+    /// with the same `clock_id`; every process has its own.
+    pub monotonic_ms: u64,
+    pub clock_id: uuid::Uuid,
+}
+
+/// For clients to estimate their clock skew; not cacheable.
+pub async fn server_time() -> Result<impl warp::Reply, warp::Rejection> {
+    let (origin, clock_id) = *CLOCK_ORIGIN;
+    let now = Utc::now();
+
+    Ok(warp::reply::with_header(
+        warp::reply::json(&ApiResponse::ok(ServerTimeResponse {
+            server_time: now,
+            unix_ms: now.timestamp_millis(),
+            monotonic_ms: origin.elapsed().as_millis() as u64,
+            clock_id,
+        })),
+        header::CACHE_CONTROL,
+        "no-store",
+    ))
+}
+
+pub async fn health(
+    health: Arc<HealthMonitor>,
+    session_control: Arc<dyn SessionControl>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut report = health.report();
+    report.draining = session_control.draining();
+    let status = if report.healthy && !report.draining {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ApiResponse::ok(report)),
+        status,
+    ))
+}
+
+pub async fn metrics() -> Result<impl warp::Reply, warp::Rejection> {
+    let body = metrics::render()
+        .map_err(ApiErrorCode::internal)
+        .map_err(reject::custom)?;
+
+    Ok(warp::reply::with_header(
+        body,
+        "content-type",
+        "text/plain; version=0.0.4",
+    ))
+}
@@ -0,0 +1,83 @@
+use crate::api::v1::error::ApiErrorCode;
+use crate::domain_model::UserId;
+use crate::logger::*;
+use crate::protocol::ProtocolVersion;
+use crate::server::{ConnectionAcceptor, ConnectionMeta};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use warp::reject;
+
+#[derive(Debug, Deserialize)]
+pub struct ChatQuery {
+    pub protocol_version: Option<u16>,
+    pub device_id: Option<String>,
+    #[serde(default)]
+    pub data_saver: bool,
+    #[serde(default)]
+    pub snapshot: bool,
+}
+
+/// Refuses a client over its session limit with a 429 here, before the
+/// upgrade.
+pub async fn negotiate_connection(
+    query: ChatQuery,
+    remote_addr: Option<SocketAddr>,
+    client_ip: Option<IpAddr>,
+    user_agent: Option<String>,
+    connection_acceptor: Arc<dyn ConnectionAcceptor>,
+) -> Result<(ProtocolVersion, ConnectionMeta), warp::Rejection> {
+    let protocol_version = match query.protocol_version {
+        None => ProtocolVersion::default(),
+        Some(v) => ProtocolVersion::try_from(v)
+            .map_err(|_| reject::custom(ApiErrorCode::UnsupportedProtocolVersion))?,
+    };
+    let meta = ConnectionMeta {
+        remote_addr,
+        client_ip,
+        user_agent,
+        device_id: query.device_id,
+        data_saver: query.data_saver,
+        snapshot: query.snapshot,
+    };
+    if !connection_acceptor.has_room_for(&meta) {
+        return Err(reject::custom(ApiErrorCode::TooManySessions));
+    }
+    Ok((protocol_version, meta))
+}
+
+/// Hands the upgraded socket to `join_chat`.
+pub fn upgrade_chat(
+    user_id: UserId,
+    protocol_version: ProtocolVersion,
+    meta: ConnectionMeta,
+    ws: warp::ws::Ws,
+    connection_acceptor: Arc<dyn ConnectionAcceptor>,
+) -> impl warp::Reply {
+    ws.on_upgrade(move |socket| {
+        join_chat(socket, user_id, protocol_version, meta, connection_acceptor)
+    })
+}
+
+pub async fn join_chat(
+    socket: warp::ws::WebSocket,
+    user_id: UserId,
+    protocol_version: ProtocolVersion,
+    meta: ConnectionMeta,
+    connection_acceptor: Arc<dyn ConnectionAcceptor>,
+) {
+    let (s2c, c2s) = socket.split();
+    if let Err(e) = connection_acceptor
+        .accept_connection(
+            Box::new(s2c),
+            Box::new(c2s),
+            user_id,
+            protocol_version,
+            meta,
+        )
+        .await
+    {
+        error!("accepting connection: {}", e);
+    }
+}
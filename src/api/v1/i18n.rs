@@ -0,0 +1,394 @@
+use super::error::ApiErrorCode;
+use std::convert::Infallible;
+use warp::Filter;
+use warp::http::HeaderMap;
+use warp::http::header::ACCEPT_LANGUAGE;
+
+/// A language the message catalog covers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Es,
+    Zh,
+}
+
+impl Locale {
+    fn from_tag(tag: &str) -> Option<Locale> {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            "es" => Some(Locale::Es),
+            "zh" => Some(Locale::Zh),
+            _ => None,
+        }
+    }
+
+    /// The best match for an `Accept-Language` header, by `q` and then by
+    /// order; English when nothing matches or the header is missing.
+    ///
+    /// Only the primary subtag counts, so `de-CH` gets German.
+    pub fn negotiate(accept_language: Option<&str>) -> Locale {
+        let Some(header) = accept_language else {
+            return Locale::default();
+        };
+
+        let mut ranges: Vec<(&str, f32)> = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let tag = parts.next().filter(|tag| !tag.is_empty())?;
+                let q = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+                Some((tag, q))
+            })
+            .filter(|(_, q)| *q > 0.0)
+            .collect();
+        // stable, so equal weights keep the client's order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        ranges
+            .into_iter()
+            .find_map(|(tag, _)| match tag {
+                "*" => Some(Locale::default()),
+                tag => Locale::from_tag(tag),
+            })
+            .unwrap_or_default()
+    }
+
+    fn catalog(self) -> &'static Catalog {
+        match self {
+            Locale::En => &EN,
+            Locale::De => &DE,
+            Locale::Es => &ES,
+            Locale::Zh => &ZH,
+        }
+    }
+
+    /// What the captcha image asks of the user.
+    pub fn captcha_prompt(self) -> &'static str {
+        self.catalog().captcha_prompt
+    }
+}
+
+/// The caller's `Accept-Language`, negotiated; never rejects.
+pub(super) fn with_locale() -> impl Filter<Extract = (Locale,), Error = Infallible> + Clone {
+    warp::header::headers_cloned().map(|headers: HeaderMap| {
+        Locale::negotiate(
+            headers
+                .get(ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok()),
+        )
+    })
+}
+
+impl ApiErrorCode {
+    /// The user-facing text for this code. The code itself is what clients
+    /// should branch on; the text may change between releases.
+    pub fn message(&self, locale: Locale) -> &'static str {
+        let catalog = locale.catalog();
+        match self {
+            ApiErrorCode::InvalidCaptcha => catalog.invalid_captcha,
+            ApiErrorCode::InvalidCredentials => catalog.invalid_credentials,
+            ApiErrorCode::UsernameTaken => catalog.username_taken,
+            ApiErrorCode::UsernameNotAllowed => catalog.username_not_allowed,
+            ApiErrorCode::InviteRequired => catalog.invite_required,
+            ApiErrorCode::InvalidInvite => catalog.invalid_invite,
+            ApiErrorCode::InvalidToken => catalog.invalid_token,
+            ApiErrorCode::Forbidden => catalog.forbidden,
+            ApiErrorCode::AlreadyFriends => catalog.already_friends,
+            ApiErrorCode::NotFriends => catalog.not_friends,
+            ApiErrorCode::UserNotFound => catalog.user_not_found,
+            ApiErrorCode::SelfRelation => catalog.self_relation,
+            ApiErrorCode::InvalidGroupName => catalog.invalid_group_name,
+            ApiErrorCode::InvalidGroupAppearance => catalog.invalid_group_appearance,
+            ApiErrorCode::GroupNotFound => catalog.group_not_found,
+            ApiErrorCode::BatchTooLarge => catalog.batch_too_large,
+            ApiErrorCode::BadCursor => catalog.bad_cursor,
+            ApiErrorCode::BadPageSize => catalog.bad_page_size,
+            ApiErrorCode::BadIdempotencyKey => catalog.bad_idempotency_key,
+            ApiErrorCode::NotMember => catalog.not_member,
+            ApiErrorCode::ConversationNotFound => catalog.conversation_not_found,
+            ApiErrorCode::ConversationFrozen => catalog.conversation_frozen,
+            ApiErrorCode::MessageNotFound => catalog.message_not_found,
+            ApiErrorCode::BadReplaySelection => catalog.bad_replay_selection,
+            ApiErrorCode::LegalHold => catalog.legal_hold,
+            ApiErrorCode::InvalidMetadata => catalog.invalid_metadata,
+            ApiErrorCode::InvalidImport => catalog.invalid_import,
+            ApiErrorCode::InvalidUsernameRule => catalog.invalid_username_rule,
+            ApiErrorCode::InvalidInviteMint => catalog.invalid_invite_mint,
+            ApiErrorCode::AttachmentNotFound => catalog.attachment_not_found,
+            ApiErrorCode::InvalidUpload => catalog.invalid_upload,
+            ApiErrorCode::DiscoveryDisabled => catalog.discovery_disabled,
+            ApiErrorCode::InvalidSearch => catalog.invalid_search,
+            ApiErrorCode::SearchUnavailable => catalog.search_unavailable,
+            ApiErrorCode::QrLoginNotFound => catalog.qr_login_not_found,
+            ApiErrorCode::InvalidDevice => catalog.invalid_device,
+            ApiErrorCode::DeviceNotFound => catalog.device_not_found,
+            ApiErrorCode::TooManyPins => catalog.too_many_pins,
+            ApiErrorCode::BadStatsRange => catalog.bad_stats_range,
+            ApiErrorCode::InvalidWebhook => catalog.invalid_webhook,
+            ApiErrorCode::WebhookNotFound => catalog.webhook_not_found,
+            ApiErrorCode::InvalidNotice => catalog.invalid_notice,
+            ApiErrorCode::RateLimited { .. } => catalog.rate_limited,
+            ApiErrorCode::UnsupportedProtocolVersion => catalog.unsupported_protocol_version,
+            ApiErrorCode::TooManySessions => catalog.too_many_sessions,
+            ApiErrorCode::InternalError => catalog.internal_error,
+        }
+    }
+}
+
+/// Every message in one language. A struct rather than a map so a missing
+/// translation fails to compile.
+struct Catalog {
+    captcha_prompt: &'static str,
+    invalid_captcha: &'static str,
+    invalid_credentials: &'static str,
+    username_taken: &'static str,
+    username_not_allowed: &'static str,
+    invite_required: &'static str,
+    invalid_invite: &'static str,
+    invalid_token: &'static str,
+    forbidden: &'static str,
+    already_friends: &'static str,
+    not_friends: &'static str,
+    user_not_found: &'static str,
+    self_relation: &'static str,
+    invalid_group_name: &'static str,
+    invalid_group_appearance: &'static str,
+    group_not_found: &'static str,
+    batch_too_large: &'static str,
+    bad_cursor: &'static str,
+    bad_page_size: &'static str,
+    bad_idempotency_key: &'static str,
+    not_member: &'static str,
+    conversation_not_found: &'static str,
+    conversation_frozen: &'static str,
+    message_not_found: &'static str,
+    bad_replay_selection: &'static str,
+    legal_hold: &'static str,
+    invalid_metadata: &'static str,
+    invalid_import: &'static str,
+    invalid_username_rule: &'static str,
+    invalid_invite_mint: &'static str,
+    attachment_not_found: &'static str,
+    invalid_upload: &'static str,
+    discovery_disabled: &'static str,
+    invalid_search: &'static str,
+    search_unavailable: &'static str,
+    qr_login_not_found: &'static str,
+    invalid_device: &'static str,
+    device_not_found: &'static str,
+    too_many_pins: &'static str,
+    bad_stats_range: &'static str,
+    invalid_webhook: &'static str,
+    webhook_not_found: &'static str,
+    invalid_notice: &'static str,
+    rate_limited: &'static str,
+    unsupported_protocol_version: &'static str,
+    too_many_sessions: &'static str,
+    internal_error: &'static str,
+}
+
+const EN: Catalog = Catalog {
+    captcha_prompt: "Enter the characters shown in the image",
+    invalid_captcha: "Invalid captcha ID or answer",
+    invalid_credentials: "Invalid username or password",
+    username_taken: "Username already taken",
+    username_not_allowed: "This username is not available",
+    invite_required: "Signup needs an invite code",
+    invalid_invite: "Invite code is invalid, used up or expired",
+    invalid_token: "Token is not valid",
+    forbidden: "Permission denied",
+    already_friends: "Already friends",
+    not_friends: "Only friends can start a direct conversation",
+    user_not_found: "User not found",
+    self_relation: "Cannot relate to yourself",
+    invalid_group_name: "Group name must be 1 to 64 characters",
+    invalid_group_appearance: "Avatar must be an https URL and the theme color #rrggbb",
+    group_not_found: "Group not found",
+    batch_too_large: "Too many items in one request",
+    bad_cursor: "Invalid pagination cursor",
+    bad_page_size: "Page size out of range",
+    bad_idempotency_key: "Idempotency key is missing or not a UUID",
+    not_member: "Not a member of this conversation",
+    conversation_not_found: "Conversation not found",
+    conversation_frozen: "This conversation is frozen; no new messages can be sent",
+    message_not_found: "Message not found",
+    bad_replay_selection: "Invalid replay selection",
+    legal_hold: "The account is under legal hold",
+    invalid_metadata: "Invalid metadata key or value",
+    invalid_import: "Invalid row in import batch",
+    invalid_username_rule: "Invalid username rule",
+    invalid_invite_mint: "Invalid invite code request",
+    attachment_not_found: "Attachment not found",
+    invalid_upload: "Upload must be a non-empty file of at most 25 MiB with a valid content type",
+    discovery_disabled: "Turn on contact discovery to look up contacts",
+    invalid_search: "Search for up to 8 words of at least 3 letters",
+    search_unavailable: "Message search is not available on this server",
+    qr_login_not_found: "QR code expired or already used; show a new one",
+    invalid_device: "Invalid push token or locale",
+    device_not_found: "This device is not registered",
+    too_many_pins: "Unpin a message before pinning another",
+    bad_stats_range: "Stats cover 1 to 90 days and at most 50 groups",
+    invalid_webhook: "Invalid webhook URL, name, member or message; a group has at most 5 of each kind",
+    webhook_not_found: "This webhook does not exist",
+    invalid_notice: "Invalid notice title, body, window, receivers or schedule",
+    rate_limited: "Too many requests; try again later",
+    unsupported_protocol_version: "Protocol version is not supported",
+    too_many_sessions: "Too many chat sessions from this address",
+    internal_error: "Internal error",
+};
+
+const DE: Catalog = Catalog {
+    captcha_prompt: "Gib die Zeichen aus dem Bild ein",
+    invalid_captcha: "Ungültige Captcha-ID oder Antwort",
+    invalid_credentials: "Ungültiger Benutzername oder ungültiges Passwort",
+    username_taken: "Benutzername ist bereits vergeben",
+    username_not_allowed: "Dieser Benutzername ist nicht verfügbar",
+    invite_required: "Für die Registrierung ist ein Einladungscode nötig",
+    invalid_invite: "Einladungscode ist ungültig, aufgebraucht oder abgelaufen",
+    invalid_token: "Token ist ungültig",
+    forbidden: "Zugriff verweigert",
+    already_friends: "Ihr seid bereits befreundet",
+    not_friends: "Nur Freunde können eine Direktunterhaltung beginnen",
+    user_not_found: "Benutzer nicht gefunden",
+    self_relation: "Das geht nicht mit dir selbst",
+    invalid_group_name: "Der Gruppenname muss 1 bis 64 Zeichen lang sein",
+    invalid_group_appearance: "Der Avatar muss eine https-URL sein und die Designfarbe #rrggbb",
+    group_not_found: "Gruppe nicht gefunden",
+    batch_too_large: "Zu viele Einträge in einer Anfrage",
+    bad_cursor: "Ungültiger Seiten-Cursor",
+    bad_page_size: "Seitengröße außerhalb des zulässigen Bereichs",
+    bad_idempotency_key: "Idempotenzschlüssel fehlt oder ist keine UUID",
+    not_member: "Kein Mitglied dieser Unterhaltung",
+    conversation_not_found: "Unterhaltung nicht gefunden",
+    conversation_frozen: "Diese Unterhaltung ist eingefroren; es können keine Nachrichten gesendet werden",
+    message_not_found: "Nachricht nicht gefunden",
+    bad_replay_selection: "Ungültige Auswahl für die Wiedergabe",
+    legal_hold: "Das Konto unterliegt einer rechtlichen Aufbewahrungspflicht",
+    invalid_metadata: "Ungültiger Metadaten-Schlüssel oder -Wert",
+    invalid_import: "Ungültige Zeile im Import-Stapel",
+    invalid_username_rule: "Ungültige Benutzernamen-Regel",
+    invalid_invite_mint: "Ungültige Anfrage für Einladungscodes",
+    attachment_not_found: "Anhang nicht gefunden",
+    invalid_upload: "Upload muss eine nicht leere Datei bis 25 MiB mit gültigem Inhaltstyp sein",
+    discovery_disabled: "Aktiviere die Kontaktsuche, um Kontakte zu finden",
+    invalid_search: "Suche nach bis zu 8 Wörtern mit mindestens 3 Buchstaben",
+    search_unavailable: "Die Nachrichtensuche ist auf diesem Server nicht verfügbar",
+    qr_login_not_found: "QR-Code abgelaufen oder bereits verwendet; zeige einen neuen an",
+    invalid_device: "Ungültiges Push-Token oder ungültige Sprache",
+    device_not_found: "Dieses Gerät ist nicht registriert",
+    too_many_pins: "Löse eine Nachricht, bevor du eine weitere anheftest",
+    bad_stats_range: "Statistiken umfassen 1 bis 90 Tage und höchstens 50 Gruppen",
+    invalid_webhook: "Ungültige Webhook-URL, Name, Mitglied oder Nachricht; eine Gruppe hat höchstens 5 je Art",
+    webhook_not_found: "Diesen Webhook gibt es nicht",
+    invalid_notice: "Ungültiger Titel, Text, Zeitraum, Empfänger oder Zeitplan der Mitteilung",
+    rate_limited: "Zu viele Anfragen; bitte später erneut versuchen",
+    unsupported_protocol_version: "Protokollversion wird nicht unterstützt",
+    too_many_sessions: "Zu viele Chat-Sitzungen von dieser Adresse",
+    internal_error: "Interner Fehler",
+};
+
+const ES: Catalog = Catalog {
+    captcha_prompt: "Escribe los caracteres que aparecen en la imagen",
+    invalid_captcha: "ID o respuesta de captcha no válidos",
+    invalid_credentials: "Usuario o contraseña incorrectos",
+    username_taken: "El nombre de usuario ya está en uso",
+    username_not_allowed: "Este nombre de usuario no está disponible",
+    invite_required: "Se necesita un código de invitación para registrarse",
+    invalid_invite: "El código de invitación no es válido, está agotado o ha caducado",
+    invalid_token: "El token no es válido",
+    forbidden: "Permiso denegado",
+    already_friends: "Ya sois amigos",
+    not_friends: "Solo los amigos pueden iniciar una conversación directa",
+    user_not_found: "Usuario no encontrado",
+    self_relation: "No puedes relacionarte contigo mismo",
+    invalid_group_name: "El nombre del grupo debe tener entre 1 y 64 caracteres",
+    invalid_group_appearance: "El avatar debe ser una URL https y el color del tema #rrggbb",
+    group_not_found: "Grupo no encontrado",
+    batch_too_large: "Demasiados elementos en una sola solicitud",
+    bad_cursor: "Cursor de paginación no válido",
+    bad_page_size: "Tamaño de página fuera de rango",
+    bad_idempotency_key: "Falta la clave de idempotencia o no es un UUID",
+    not_member: "No eres miembro de esta conversación",
+    conversation_not_found: "Conversación no encontrada",
+    conversation_frozen: "Esta conversación está congelada; no se pueden enviar mensajes",
+    message_not_found: "Mensaje no encontrado",
+    bad_replay_selection: "Selección de reproducción no válida",
+    legal_hold: "La cuenta está sujeta a una retención legal",
+    invalid_metadata: "Clave o valor de metadatos no válido",
+    invalid_import: "Fila no válida en el lote de importación",
+    invalid_username_rule: "Regla de nombre de usuario no válida",
+    invalid_invite_mint: "Solicitud de códigos de invitación no válida",
+    attachment_not_found: "Archivo adjunto no encontrado",
+    invalid_upload: "La subida debe ser un archivo no vacío de hasta 25 MiB con un tipo de contenido válido",
+    discovery_disabled: "Activa el descubrimiento de contactos para buscar contactos",
+    invalid_search: "Busca hasta 8 palabras de al menos 3 letras",
+    search_unavailable: "La búsqueda de mensajes no está disponible en este servidor",
+    qr_login_not_found: "El código QR ha caducado o ya se usó; muestra uno nuevo",
+    invalid_device: "Token de notificaciones o idioma no válido",
+    device_not_found: "Este dispositivo no está registrado",
+    too_many_pins: "Desfija un mensaje antes de fijar otro",
+    bad_stats_range: "Las estadísticas abarcan de 1 a 90 días y como máximo 50 grupos",
+    invalid_webhook: "URL, nombre, miembro o mensaje de webhook no válido; un grupo tiene como máximo 5 de cada tipo",
+    webhook_not_found: "Este webhook no existe",
+    invalid_notice: "Título, texto, periodo, destinatarios o programación del aviso no válidos",
+    rate_limited: "Demasiadas solicitudes; inténtalo más tarde",
+    unsupported_protocol_version: "Versión de protocolo no compatible",
+    too_many_sessions: "Demasiadas sesiones de chat desde esta dirección",
+    internal_error: "Error interno",
+};
+
+const ZH: Catalog = Catalog {
+    captcha_prompt: "请输入图片中的字符",
+    invalid_captcha: "验证码 ID 或答案无效",
+    invalid_credentials: "用户名或密码错误",
+    username_taken: "用户名已被占用",
+    username_not_allowed: "该用户名不可用",
+    invite_required: "注册需要邀请码",
+    invalid_invite: "邀请码无效、已用完或已过期",
+    invalid_token: "令牌无效",
+    forbidden: "权限不足",
+    already_friends: "你们已经是好友",
+    not_friends: "只有好友才能发起私聊",
+    user_not_found: "用户不存在",
+    self_relation: "不能对自己执行此操作",
+    invalid_group_name: "群组名称须为 1 到 64 个字符",
+    invalid_group_appearance: "头像须为 https 链接，主题颜色须为 #rrggbb 格式",
+    group_not_found: "群组不存在",
+    batch_too_large: "单次请求的条目过多",
+    bad_cursor: "分页游标无效",
+    bad_page_size: "分页大小超出范围",
+    bad_idempotency_key: "幂等键缺失或不是有效的 UUID",
+    not_member: "你不是该会话的成员",
+    conversation_not_found: "会话不存在",
+    conversation_frozen: "该会话已冻结，无法发送新消息",
+    message_not_found: "消息不存在",
+    bad_replay_selection: "重放选择无效",
+    legal_hold: "该账户处于法律保留状态",
+    invalid_metadata: "元数据的键或值无效",
+    invalid_import: "导入批次中有无效的行",
+    invalid_username_rule: "用户名规则无效",
+    invalid_invite_mint: "邀请码请求无效",
+    attachment_not_found: "附件不存在",
+    invalid_upload: "上传内容必须是不超过 25 MiB 的非空文件，且内容类型有效",
+    discovery_disabled: "请先开启联系人发现功能再查找联系人",
+    invalid_search: "最多搜索 8 个词，每个词至少 3 个字符",
+    search_unavailable: "此服务器不支持消息搜索",
+    qr_login_not_found: "二维码已过期或已被使用，请重新生成",
+    invalid_device: "推送令牌或语言无效",
+    device_not_found: "此设备未注册",
+    too_many_pins: "请先取消置顶一条消息，再置顶新的消息",
+    bad_stats_range: "统计范围为 1 到 90 天，最多 50 个群组",
+    invalid_webhook: "Webhook 的地址、名称、成员或消息无效；每个群组每种最多 5 个",
+    webhook_not_found: "该 Webhook 不存在",
+    invalid_notice: "通知的标题、内容、时间段、接收者或发布时间无效",
+    rate_limited: "请求过于频繁，请稍后再试",
+    unsupported_protocol_version: "不支持该协议版本",
+    too_many_sessions: "来自该地址的聊天会话过多",
+    internal_error: "内部错误",
+};
@@ -1,6 +1,9 @@
 mod error;
+mod extract;
 mod handler;
+mod i18n;
 mod router;
 
-pub use error::recover_error;
-pub use router::routes;
+pub use error::recover_errors;
+pub use i18n::Locale;
+pub use router::{admin_routes, public_routes, routes};
@@ -1,29 +1,56 @@
-use super::error::*;
+use super::extract::*;
 use super::handler;
-use crate::api::v1::handler::{ConversationHistoryQuery, FriendListQuery};
+use super::handler::{
+    ChatQuery, ConversationHistoryQuery, ConversationMembersQuery, DailyStatsQuery,
+    HistorySummaryQuery, MessageSearchQuery, OffsetAtQuery, TopGroupsQuery, UnreadSummaryQuery,
+};
+use super::i18n::with_locale;
 use crate::application_port::*;
-use crate::domain_model::UserId;
+use crate::domain_model::{
+    AttachmentId, ConversationId, FriendCursor, GroupId, IncomingWebhookId, MessageId,
+    OffsetCursor, SearchCursor, UserId, WebhookId,
+};
 use crate::server::*;
-use std::convert::Infallible;
 use std::sync::Arc;
-use warp::{Filter, http, reject};
+use warp::Filter;
 
+/// Largest `admin/import` body; a full batch of long messages fits.
+const IMPORT_BODY_LIMIT: u64 = 32 * 1024 * 1024;
+/// Largest body posted to an incoming webhook.
+const INCOMING_WEBHOOK_BODY_LIMIT: u64 = 64 * 1024;
+
+/// Every route on one listener.
 pub fn routes(
     server: Arc<Server>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    public_routes(server.clone()).or(admin_routes(server))
+}
+
+/// The routes clients call, plus `health`.
+pub fn public_routes(
+    server: Arc<Server>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     // TODO: need a timeout
     let captcha = warp::get()
         .and(warp::path("captcha"))
         .and(warp::path::end())
+        .and(with_locale())
         .and(with(server.captcha_service.clone()))
         .and_then(handler::generate_captcha);
 
+    let time = warp::get()
+        .and(warp::path("time"))
+        .and(warp::path::end())
+        .and_then(handler::server_time);
+
     let login = warp::post()
         .and(warp::path("login"))
         .and(warp::path::end())
         .and(warp::body::json())
+        .and(with_remote_addr())
         .and(with(server.auth_service.clone()))
         .and(with(server.captcha_service.clone()))
+        .and(with(server.login_risk_evaluator.clone()))
         .and_then(handler::login);
 
     let signup = warp::post()
@@ -34,10 +61,29 @@ pub fn routes(
         .and(with(server.captcha_service.clone()))
         .and_then(handler::signup);
 
+    let start_qr_login = warp::post()
+        .and(warp::path!("qr_login" / "start"))
+        .and(warp::body::json())
+        .and(with(server.auth_service.clone()))
+        .and_then(handler::start_qr_login);
+
+    let approve_qr_login = warp::post()
+        .and(warp::path!("qr_login" / "approve"))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.auth_service.clone()))
+        .and_then(handler::approve_qr_login);
+
+    let poll_qr_login = warp::post()
+        .and(warp::path!("qr_login" / "poll"))
+        .and(warp::body::json())
+        .and(with(server.auth_service.clone()))
+        .and_then(handler::poll_qr_login);
+
     let friend_list = warp::get()
         .and(warp::path("friend_list"))
         .and(warp::path::end())
-        .and(warp::query::<FriendListQuery>())
+        .and(with_page::<FriendCursor>("after", server.max_page_size))
         .and(with_verification(server.auth_service.clone()))
         .and(with(server.relationship_service.clone()))
         .and_then(handler::generate_friend_list);
@@ -47,68 +93,661 @@ pub fn routes(
         .and(warp::path::end())
         .and(warp::body::json())
         .and(with_verification(server.auth_service.clone()))
+        .and(with_idempotency_key())
+        .and(with_trace())
+        .and(with_locale())
         .and(with(server.user_service.clone()))
         .and(with(server.relationship_service.clone()))
         .and_then(handler::add_friend);
 
+    let group_member_counts = warp::post()
+        .and(warp::path("group_member_counts"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.relationship_service.clone()))
+        .and_then(handler::group_member_counts);
+
+    let group_appearance = warp::get()
+        .and(warp::path!("groups" / GroupId / "appearance"))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.relationship_service.clone()))
+        .and_then(handler::group_appearance);
+
+    let set_group_appearance = warp::post()
+        .and(warp::path!("groups" / GroupId / "appearance"))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.relationship_service.clone()))
+        .and_then(handler::set_group_appearance);
+
+    let create_conversation = warp::post()
+        .and(warp::path("conversations"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with_idempotency_key())
+        .and(with_trace())
+        .and(with(server.relationship_service.clone()))
+        .and_then(handler::create_conversation);
+
+    let open_direct = warp::post()
+        .and(warp::path!("conversations" / "direct"))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.relationship_service.clone()))
+        .and_then(handler::open_direct);
+
     let conversation_history = warp::get()
         .and(warp::path("conversation_history"))
         .and(warp::path::end())
         .and(warp::query::<ConversationHistoryQuery>())
+        .and(with_page::<OffsetCursor>("before", server.max_page_size))
         .and(with_verification(server.auth_service.clone()))
         .and(with(server.conversation_service.clone()))
         .and_then(handler::generate_conversation_history);
 
+    let history_summary = warp::get()
+        .and(warp::path("history_summary"))
+        .and(warp::path::end())
+        .and(warp::query::<HistorySummaryQuery>())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::generate_history_summary);
+
+    let offset_at = warp::get()
+        .and(warp::path("offset_at"))
+        .and(warp::path::end())
+        .and(warp::query::<OffsetAtQuery>())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::offset_at);
+
+    let unread_summary = warp::get()
+        .and(warp::path("unread_summary"))
+        .and(warp::path::end())
+        .and(warp::query::<UnreadSummaryQuery>())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with_value(server.max_page_size))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::generate_unread_summary);
+
+    let pin_conversation = warp::post()
+        .and(warp::path("pin_conversation"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::pin_conversation);
+
+    let edit_message = warp::post()
+        .and(warp::path!(
+            "conversations" / ConversationId / "messages" / MessageId / "edit"
+        ))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::edit_message);
+
+    let delete_message = warp::post()
+        .and(warp::path!(
+            "conversations" / ConversationId / "messages" / MessageId / "delete"
+        ))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::delete_message);
+
+    let pin_message = warp::post()
+        .and(warp::path!(
+            "conversations" / ConversationId / "messages" / MessageId / "pin"
+        ))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::pin_message);
+
+    let unpin_message = warp::post()
+        .and(warp::path!(
+            "conversations" / ConversationId / "messages" / MessageId / "unpin"
+        ))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::unpin_message);
+
+    let pinned_messages = warp::get()
+        .and(warp::path!("conversations" / ConversationId / "pins"))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::pinned_messages);
+
+    let message_deliveries = warp::get()
+        .and(warp::path!(
+            "conversations" / ConversationId / "messages" / MessageId / "deliveries"
+        ))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::message_deliveries);
+
+    let thread_history = warp::get()
+        .and(warp::path!(
+            "conversations" / ConversationId / "messages" / MessageId / "thread"
+        ))
+        .and(with_page::<OffsetCursor>("after", server.max_page_size))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::thread_history);
+
+    let mentions = warp::get()
+        .and(warp::path("mentions"))
+        .and(warp::path::end())
+        .and(with_page::<SearchCursor>("before", server.max_page_size))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::mentions);
+
+    let message_search = warp::get()
+        .and(warp::path("message_search"))
+        .and(warp::path::end())
+        .and(warp::query::<MessageSearchQuery>())
+        .and(with_page::<SearchCursor>("before", server.max_page_size))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::message_search);
+
+    let freeze_conversation = warp::post()
+        .and(warp::path!("conversations" / ConversationId / "freeze"))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::freeze_conversation);
+
+    let conversation_retention = warp::get()
+        .and(warp::path!("conversations" / ConversationId / "retention"))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::conversation_retention);
+
+    let set_conversation_retention = warp::post()
+        .and(warp::path!("conversations" / ConversationId / "retention"))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::set_conversation_retention);
+
+    let mark_read = warp::post()
+        .and(warp::path!("conversations" / ConversationId / "read"))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::mark_read);
+
+    let conversation_members = warp::get()
+        .and(warp::path!("conversations" / ConversationId / "members"))
+        .and(warp::query::<ConversationMembersQuery>())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::conversation_members);
+
+    let conversation_meta = warp::get()
+        .and(warp::path!("conversations" / ConversationId / "meta"))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.conversation_meta_service.clone()))
+        .and_then(handler::conversation_meta);
+
+    let upload_attachment = warp::post()
+        .and(warp::path!(
+            "conversations" / ConversationId / "attachments"
+        ))
+        .and(with_verification(server.auth_service.clone()))
+        .and(warp::body::content_length_limit(MAX_ATTACHMENT_BYTES))
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::body::bytes())
+        .and(with(server.attachment_service.clone()))
+        .and_then(handler::upload_attachment);
+
+    let download_attachment = warp::get()
+        .and(warp::path!(
+            "conversations" / ConversationId / "attachments" / AttachmentId
+        ))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.attachment_service.clone()))
+        .and_then(handler::download_attachment);
+
+    let sessions = warp::get()
+        .and(warp::path("sessions"))
+        .and(warp::path::end())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.auth_service.clone()))
+        .and_then(handler::list_sessions);
+
+    let export = warp::get()
+        .and(warp::path!("me" / "export"))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.export_service.clone()))
+        .and_then(handler::export_account);
+
+    let features = warp::get()
+        .and(warp::path!("me" / "features"))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.feature_flags.clone()))
+        .and_then(handler::my_features);
+
+    let discovery_settings = warp::get()
+        .and(warp::path!("me" / "discovery"))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.contact_discovery_service.clone()))
+        .and_then(handler::discovery_settings);
+
+    let set_discovery_identifiers = warp::put()
+        .and(warp::path!("me" / "discovery"))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.contact_discovery_service.clone()))
+        .and_then(handler::set_discovery_identifiers);
+
+    let discover_contacts = warp::post()
+        .and(warp::path!("contacts" / "discover"))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.contact_discovery_service.clone()))
+        .and_then(handler::discover_contacts);
+
+    let logout_all = warp::post()
+        .and(warp::path("logout_all"))
+        .and(warp::path::end())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.auth_service.clone()))
+        .and_then(handler::logout_all);
+
+    let register_webhook = warp::post()
+        .and(warp::path!("conversations" / ConversationId / "webhooks"))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.webhook_service.clone()))
+        .and_then(handler::register_webhook);
+
+    let conversation_webhooks = warp::get()
+        .and(warp::path!("conversations" / ConversationId / "webhooks"))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.webhook_service.clone()))
+        .and_then(handler::conversation_webhooks);
+
+    let remove_webhook = warp::delete()
+        .and(warp::path!(
+            "conversations" / ConversationId / "webhooks" / WebhookId
+        ))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.webhook_service.clone()))
+        .and_then(handler::remove_webhook);
+
+    let issue_incoming_webhook = warp::post()
+        .and(warp::path!(
+            "conversations" / ConversationId / "incoming-webhooks"
+        ))
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.webhook_service.clone()))
+        .and_then(handler::issue_incoming_webhook);
+
+    let incoming_webhooks = warp::get()
+        .and(warp::path!(
+            "conversations" / ConversationId / "incoming-webhooks"
+        ))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.webhook_service.clone()))
+        .and_then(handler::incoming_webhooks);
+
+    let revoke_incoming_webhook = warp::delete()
+        .and(warp::path!(
+            "conversations" / ConversationId / "incoming-webhooks" / IncomingWebhookId
+        ))
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.webhook_service.clone()))
+        .and_then(handler::revoke_incoming_webhook);
+
+    // integrations authenticate with the token in the path, not a session
+    let post_incoming_message = warp::post()
+        .and(warp::path!("hooks" / String))
+        .and(warp::body::content_length_limit(
+            INCOMING_WEBHOOK_BODY_LIMIT,
+        ))
+        .and(warp::body::json())
+        .and(with_idempotency_key())
+        .and(with_trace())
+        .and(with(server.webhook_service.clone()))
+        .and_then(handler::post_incoming_message);
+
+    let register_device = warp::post()
+        .and(warp::path("devices"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.device_service.clone()))
+        .and_then(handler::register_device);
+
+    let unregister_device = warp::delete()
+        .and(warp::path("devices"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_verification(server.auth_service.clone()))
+        .and(with(server.device_service.clone()))
+        .and_then(handler::unregister_device);
+
     let chat = warp::get()
         .and(warp::path("chat"))
         .and(warp::path::end())
         .and(with_verification(server.auth_service.clone()))
+        .and(
+            warp::query::<ChatQuery>()
+                .and(with_remote_addr())
+                .and(with_client_ip(server.client_ip_header.clone()))
+                .and(warp::header::optional::<String>("user-agent"))
+                .and(with(server.connection_acceptor.clone()))
+                .and_then(handler::negotiate_connection)
+                .untuple_one(),
+        )
         .and(warp::ws())
         .and(with(server.connection_acceptor.clone()))
-        .map(
-            |user_id: UserId,
-             ws: warp::ws::Ws,
-             connection_acceptor: Arc<dyn ConnectionAcceptor>| {
-                ws.on_upgrade(move |socket| {
-                    handler::join_chat(socket, user_id, connection_acceptor)
-                })
-            },
-        );
+        .map(handler::upgrade_chat);
 
     captcha
+        .or(time)
         .or(login)
         .or(signup)
+        .or(start_qr_login)
+        .or(approve_qr_login)
+        .or(poll_qr_login)
         .or(friend_list)
         .or(add_friend)
+        .or(group_member_counts)
+        .or(group_appearance)
+        .or(set_group_appearance)
+        .or(create_conversation)
+        .or(open_direct)
         .or(conversation_history)
+        .or(history_summary)
+        .or(offset_at)
+        .or(unread_summary)
+        .or(pin_conversation)
+        .or(edit_message)
+        .or(delete_message)
+        .or(pin_message)
+        .or(unpin_message)
+        .or(pinned_messages)
+        .or(message_deliveries)
+        .or(thread_history)
+        .or(message_search)
+        .or(mentions)
+        .or(freeze_conversation)
+        .or(conversation_retention)
+        .or(set_conversation_retention)
+        .or(mark_read)
+        .or(conversation_members)
+        .or(conversation_meta)
+        .or(upload_attachment)
+        .or(download_attachment)
+        .or(sessions)
+        .or(logout_all)
+        .or(register_webhook)
+        .or(conversation_webhooks)
+        .or(remove_webhook)
+        .or(issue_incoming_webhook)
+        .or(incoming_webhooks)
+        .or(revoke_incoming_webhook)
+        .or(post_incoming_message)
+        .or(register_device)
+        .or(unregister_device)
+        .or(export)
+        .or(features)
+        .or(discovery_settings)
+        .or(set_discovery_identifiers)
+        .or(discover_contacts)
+        .or(health(
+            server.health.clone(),
+            server.session_control.clone(),
+        ))
         .or(chat)
 }
 
-fn with<ServiceType>(
-    service: Arc<ServiceType>,
-) -> impl Filter<Extract = (Arc<ServiceType>,), Error = Infallible> + Clone
-where
-    ServiceType: Send + Sync + ?Sized,
-{
-    warp::any().map(move || service.clone())
+/// Operator routes and `metrics`, meant for a private listener, plus `health`.
+pub fn admin_routes(
+    server: Arc<Server>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let metrics = warp::get()
+        .and(warp::path("metrics"))
+        .and(warp::path::end())
+        .and_then(handler::metrics);
+
+    let admin_sessions = warp::get()
+        .and(warp::path!("admin" / "sessions"))
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.session_directory.clone()))
+        .and_then(handler::admin_sessions);
+
+    let admin_drain = warp::post()
+        .and(warp::path!("admin" / "drain"))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.session_control.clone()))
+        .and_then(handler::admin_drain);
+
+    let admin_disconnect = warp::post()
+        .and(warp::path!("admin" / "users" / UserId / "disconnect"))
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.user_service.clone()))
+        .and(with(server.auth_service.clone()))
+        .and_then(handler::admin_disconnect);
+
+    let admin_deactivate = warp::post()
+        .and(warp::path!("admin" / "users" / UserId / "deactivate"))
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with_trace())
+        .and(with(server.user_service.clone()))
+        .and_then(handler::admin_deactivate);
+
+    let admin_legal_hold = warp::post()
+        .and(warp::path!("admin" / "users" / UserId / "legal_hold"))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.user_service.clone()))
+        .and_then(handler::admin_legal_hold);
+
+    let admin_bot = warp::post()
+        .and(warp::path!("admin" / "users" / UserId / "bot"))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.user_service.clone()))
+        .and_then(handler::admin_bot);
+
+    let admin_conversation_meta = warp::post()
+        .and(warp::path!(
+            "admin" / "conversations" / ConversationId / "meta"
+        ))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.conversation_meta_service.clone()))
+        .and_then(handler::admin_set_conversation_meta);
+
+    let admin_freeze_conversation = warp::post()
+        .and(warp::path!(
+            "admin" / "conversations" / ConversationId / "freeze"
+        ))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.conversation_service.clone()))
+        .and_then(handler::admin_freeze_conversation);
+
+    let admin_replay_events = warp::post()
+        .and(warp::path!("admin" / "events" / "replay"))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.event_replay_service.clone()))
+        .and_then(handler::admin_replay_events);
+
+    let admin_publish_notice = warp::post()
+        .and(warp::path!("admin" / "notices"))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.notice_service.clone()))
+        .and_then(handler::admin_publish_notice);
+
+    // a whole batch of history in one body
+    let admin_import = warp::post()
+        .and(warp::path!("admin" / "import"))
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(warp::body::content_length_limit(IMPORT_BODY_LIMIT))
+        .and(warp::body::json())
+        .and(with(server.import_service.clone()))
+        .and_then(handler::admin_import);
+
+    let admin_username_rules = warp::get()
+        .and(warp::path!("admin" / "usernames" / "rules"))
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.username_policy_service.clone()))
+        .and_then(handler::admin_username_rules);
+
+    let admin_add_username_rule = warp::post()
+        .and(warp::path!("admin" / "usernames" / "rules"))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.username_policy_service.clone()))
+        .and_then(handler::admin_add_username_rule);
+
+    let admin_remove_username_rule = warp::post()
+        .and(warp::path!("admin" / "usernames" / "rules" / "remove"))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.username_policy_service.clone()))
+        .and_then(handler::admin_remove_username_rule);
+
+    let admin_mint_invites = warp::post()
+        .and(warp::path!("admin" / "invites"))
+        .and(warp::body::json())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.invite_service.clone()))
+        .and_then(handler::admin_mint_invites);
+
+    let admin_list_invites = warp::get()
+        .and(warp::path!("admin" / "invites"))
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.invite_service.clone()))
+        .and_then(handler::admin_list_invites);
+
+    let admin_daily_stats = warp::get()
+        .and(warp::path!("admin" / "stats" / "daily"))
+        .and(warp::query::<DailyStatsQuery>())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.stats_service.clone()))
+        .and_then(handler::admin_daily_stats);
+
+    let admin_top_groups = warp::get()
+        .and(warp::path!("admin" / "stats" / "groups"))
+        .and(warp::query::<TopGroupsQuery>())
+        .and(with_role(
+            server.auth_service.clone(),
+            Role::Admin,
+            server.service_principals.clone(),
+        ))
+        .and(with(server.stats_service.clone()))
+        .and_then(handler::admin_top_groups);
+
+    health(server.health.clone(), server.session_control.clone())
+        .or(metrics)
+        .or(admin_sessions)
+        .or(admin_drain)
+        .or(admin_disconnect)
+        .or(admin_deactivate)
+        .or(admin_legal_hold)
+        .or(admin_bot)
+        .or(admin_conversation_meta)
+        .or(admin_freeze_conversation)
+        .or(admin_replay_events)
+        .or(admin_publish_notice)
+        .or(admin_import)
+        .or(admin_username_rules)
+        .or(admin_add_username_rule)
+        .or(admin_remove_username_rule)
+        .or(admin_mint_invites)
+        .or(admin_list_invites)
+        .or(admin_daily_stats)
+        .or(admin_top_groups)
 }
 
-fn with_verification(
-    auth_service: Arc<dyn AuthService>,
-) -> impl Filter<Extract = (UserId,), Error = warp::Rejection> + Clone {
-    warp::header::<String>(http::header::AUTHORIZATION.as_ref()).and_then(move |token: String| {
-        let auth_service = auth_service.clone();
-        async move {
-            if let Some(token) = token.strip_prefix("Bearer ") {
-                let user_id = auth_service
-                    .verify_token(token)
-                    .await
-                    .map_err(ApiErrorCode::from)
-                    .map_err(reject::custom)?;
-                Ok(user_id)
-            } else {
-                Err(reject::custom(ApiErrorCode::InvalidToken))
-            }
-        }
-    })
+fn health(
+    monitor: Arc<HealthMonitor>,
+    session_control: Arc<dyn SessionControl>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("health"))
+        .and(warp::path::end())
+        .and(with(monitor))
+        .and(with(session_control))
+        .and_then(handler::health)
 }
@@ -0,0 +1,109 @@
+use crate::application_impl::fake_store::FakeStore;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use crate::infra_memory::MemoryBlobStore;
+use chrono::{SubsecRound, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// In-memory `AttachmentService`; see `FakeStore`. Content is kept in a
+/// `MemoryBlobStore` of its own.
+pub struct FakeAttachmentService {
+    store: Arc<FakeStore>,
+    blobs: MemoryBlobStore,
+}
+
+impl FakeAttachmentService {
+    pub fn new(store: Arc<FakeStore>) -> Self {
+        Self {
+            store,
+            blobs: MemoryBlobStore::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AttachmentService for FakeAttachmentService {
+    async fn upload(
+        &self,
+        uploader: UserId,
+        conversation_id: ConversationId,
+        mime: &str,
+        content: Vec<u8>,
+    ) -> Result<Attachment, ChatError> {
+        check_upload(mime, content.len() as u64)?;
+        let is_member = self
+            .store
+            .state()
+            .members(conversation_id)
+            .is_some_and(|members| members.contains(&uploader));
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+
+        let record = AttachmentRecord {
+            attachment_id: AttachmentId(Uuid::new_v4()),
+            conversation_id,
+            uploader,
+            mime: mime.to_owned(),
+            size: content.len() as u64,
+            created_at: Utc::now().trunc_subsecs(6),
+            message_id: None,
+        };
+        self.blobs
+            .put(&record.blob_key(), &record.mime, &content)
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        self.store
+            .state()
+            .attachments
+            .insert(record.attachment_id, record.clone());
+
+        Ok(record.attachment())
+    }
+
+    async fn download(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        attachment_id: AttachmentId,
+    ) -> Result<Blob, ChatError> {
+        let record = {
+            let state = self.store.state();
+            let is_member = state
+                .members(conversation_id)
+                .is_some_and(|members| members.contains(&user_id));
+            if !is_member {
+                return Err(ChatError::NotMember);
+            }
+            let record = state
+                .attachments
+                .get(&attachment_id)
+                .filter(|record| {
+                    record.conversation_id == conversation_id
+                        && (record.message_id.is_some() || record.uploader == user_id)
+                })
+                .ok_or(ChatError::AttachmentNotFound)?;
+            let deleted = record.message_id.is_some_and(|message_id| {
+                state.conversations.get(&conversation_id).is_some_and(|c| {
+                    c.messages
+                        .iter()
+                        .any(|m| m.message_id == message_id && m.deleted_at.is_some())
+                })
+            });
+            if deleted {
+                return Err(ChatError::AttachmentNotFound);
+            }
+            record.clone()
+        };
+
+        let blob = self
+            .blobs
+            .get(&record.blob_key())
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?
+            .ok_or(ChatError::AttachmentNotFound)?;
+        Ok(blob)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{SubsecRound, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct RealAttachmentService {
+    attachment_repo: Arc<dyn AttachmentRepo>,
+    conversation_role_repo: Arc<dyn ConversationRoleRepo>,
+    blob_store: Arc<dyn BlobStore>,
+    tx_manager: Arc<dyn TxManager>,
+}
+
+impl RealAttachmentService {
+    pub fn new(
+        attachment_repo: Arc<dyn AttachmentRepo>,
+        conversation_role_repo: Arc<dyn ConversationRoleRepo>,
+        blob_store: Arc<dyn BlobStore>,
+        tx_manager: Arc<dyn TxManager>,
+    ) -> Self {
+        Self {
+            attachment_repo,
+            conversation_role_repo,
+            blob_store,
+            tx_manager,
+        }
+    }
+
+    async fn check_member(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<(), ChatError> {
+        let is_member = self
+            .conversation_role_repo
+            .membership_exists(conversation_id, user_id)
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl AttachmentService for RealAttachmentService {
+    async fn upload(
+        &self,
+        uploader: UserId,
+        conversation_id: ConversationId,
+        mime: &str,
+        content: Vec<u8>,
+    ) -> Result<Attachment, ChatError> {
+        check_upload(mime, content.len() as u64)?;
+        self.check_member(conversation_id, uploader).await?;
+
+        let record = AttachmentRecord {
+            attachment_id: AttachmentId(Uuid::new_v4()),
+            conversation_id,
+            uploader,
+            mime: mime.to_owned(),
+            size: content.len() as u64,
+            created_at: Utc::now().trunc_subsecs(6),
+            message_id: None,
+        };
+        // content first: a row never points at nothing, though a failed
+        // insert leaves an object no row points at
+        self.blob_store
+            .put(&record.blob_key(), &record.mime, &content)
+            .await
+            .map_err(|e| ChatError::Store(format!("store attachment: {e}")))?;
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        self.attachment_repo
+            .insert_in_tx(&mut *tx, &record)
+            .await
+            .map_err(|e| ChatError::Store(format!("insert attachment: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(record.attachment())
+    }
+
+    async fn download(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        attachment_id: AttachmentId,
+    ) -> Result<Blob, ChatError> {
+        self.check_member(conversation_id, user_id).await?;
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        let record = self
+            .attachment_repo
+            .get_in_tx(&mut *tx, conversation_id, attachment_id, user_id)
+            .await
+            .map_err(|e| ChatError::Store(format!("query attachment: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        let record = record.ok_or(ChatError::AttachmentNotFound)?;
+
+        let blob = self
+            .blob_store
+            .get(&record.blob_key())
+            .await
+            .map_err(|e| ChatError::Store(format!("load attachment: {e}")))?
+            .ok_or_else(|| {
+                ChatError::Store(format!(
+                    "attachment [{}] has no content",
+                    record.attachment_id.0
+                ))
+            })?;
+        // the type checked at upload, whatever the store reports
+        Ok(Blob {
+            content_type: record.mime,
+            content: blob.content,
+        })
+    }
+}
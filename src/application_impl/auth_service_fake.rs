@@ -1,46 +1,311 @@
+use crate::application_impl::fake_store::{FakeStore, FakeUser};
 use crate::application_port::*;
 use crate::domain_model::UserId;
-use chrono::{Duration, Utc};
+use crate::logger::Secret;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+const ACCESS_PREFIX: &str = "fake-access-token:";
+const REFRESH_PREFIX: &str = "fake-refresh-token:";
 
 #[derive(Debug)]
-pub struct FakeAuthService;
+struct FakeQrLogin {
+    poll_secret: String,
+    device: Option<String>,
+    approved_by: Option<UserId>,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct FakeAuthState {
+    /// Live refresh sessions by JTI.
+    sessions: HashMap<String, RefreshSession>,
+    next_jti: u64,
+    /// By nonce; expired ones are dropped whenever one is started.
+    qr_logins: HashMap<String, FakeQrLogin>,
+}
+
+/// In-memory auth for running the API without MySQL or Redis.
+///
+/// Users are kept in the `FakeStore` and live only as long as the process.
+/// Ids are derived from the username and tokens are readable strings, so a
+/// restarted server accepts the same access tokens once the user signs up
+/// again: `fake-access-token:<username>` and
+/// `fake-refresh-token:<username>:<jti>`.
+pub struct FakeAuthService {
+    admins: HashSet<UserId>,
+    store: Arc<FakeStore>,
+    username_policy: Arc<dyn UsernamePolicyService>,
+    invite_only: bool,
+    qr_login_ttl: std::time::Duration,
+    state: Mutex<FakeAuthState>,
+}
 
 impl FakeAuthService {
-    pub fn new() -> Self {
-        Self
+    pub fn new(
+        admins: HashSet<UserId>,
+        store: Arc<FakeStore>,
+        username_policy: Arc<dyn UsernamePolicyService>,
+        invite_only: bool,
+        qr_login_ttl: std::time::Duration,
+    ) -> Self {
+        Self {
+            admins,
+            store,
+            username_policy,
+            invite_only,
+            qr_login_ttl,
+            state: Mutex::new(FakeAuthState::default()),
+        }
+    }
+
+    /// The user behind `username`, as long as they are still active.
+    fn active_user(&self, username: &str) -> Result<UserId, AuthError> {
+        let users = self.store.state();
+        users
+            .usernames
+            .get(username)
+            .copied()
+            .filter(|user_id| users.is_active(*user_id))
+            .ok_or(AuthError::TokenInvalid)
+    }
+
+    fn state(&self) -> Result<std::sync::MutexGuard<'_, FakeAuthState>, AuthError> {
+        self.state
+            .lock()
+            .map_err(|e| AuthError::InternalError(e.to_string()))
+    }
+
+    fn grants_for(&self, user_id: UserId) -> Grants {
+        let mut grants = Grants {
+            roles: vec![Role::User],
+            scopes: vec![SCOPE_CHAT.to_string()],
+        };
+        if self.admins.contains(&user_id) {
+            grants.roles.push(Role::Admin);
+            grants.scopes.push(SCOPE_ADMIN.to_string());
+        }
+        grants
+    }
+
+    /// Opens a refresh session and issues both tokens for it.
+    fn issue(
+        state: &mut FakeAuthState,
+        username: &str,
+        user_id: UserId,
+        device: Option<String>,
+        started_at: DateTime<Utc>,
+    ) -> AuthTokens {
+        state.next_jti += 1;
+        let jti = state.next_jti.to_string();
+        let now = Utc::now();
+        state.sessions.insert(
+            jti.clone(),
+            RefreshSession {
+                jti: jti.clone(),
+                user_id,
+                device,
+                issued_at: now,
+                started_at,
+            },
+        );
+
+        AuthTokens {
+            access_token: AccessToken(Secret::new(format!("{ACCESS_PREFIX}{username}"))),
+            access_token_expires_at: now + Duration::days(1),
+            refresh_token: RefreshToken(Secret::new(format!("{REFRESH_PREFIX}{username}:{jti}"))),
+            refresh_token_expires_at: now + Duration::days(7),
+        }
     }
 }
 
-// Minimal fake implementation for basic use only.
-// Extend to simulate more error cases and configurable responses when needed.
 #[async_trait::async_trait]
 impl AuthService for FakeAuthService {
     async fn signup(&self, request: SignupInput) -> Result<UserId, AuthError> {
-        Ok(get_fake_id(&request.username))
+        self.username_policy.check(&request.username).await?;
+
+        let mut users = self.store.state();
+        if users.usernames.contains_key(&request.username) {
+            return Err(AuthError::UserExists);
+        }
+        if self.invite_only {
+            let code = request
+                .invite_code
+                .as_deref()
+                .ok_or(AuthError::InviteRequired)?;
+            match users.invites.get_mut(code) {
+                Some(invite) if invite.redeemable(Utc::now()) => invite.uses += 1,
+                _ => return Err(AuthError::InvalidInvite),
+            }
+        }
+
+        let user_id = get_fake_id(&request.username);
+        users.usernames.insert(request.username.clone(), user_id);
+        users.users.insert(
+            user_id,
+            FakeUser {
+                username: request.username,
+                password: request.password,
+                active: true,
+                legal_hold: false,
+                is_bot: false,
+                created_at: Utc::now(),
+            },
+        );
+        Ok(user_id)
     }
 
     async fn login(&self, request: LoginInput) -> Result<LoginResult, AuthError> {
-        Ok(LoginResult {
-            user_id: get_fake_id(&request.username),
-            tokens: get_fake_token(&request.username),
-        })
+        let user_id = {
+            let users = self.store.state();
+            let user_id = users.usernames.get(&request.username).copied();
+            match user_id.and_then(|id| users.users.get(&id).map(|u| (id, u))) {
+                Some((id, user)) if user.active && user.password == request.password => id,
+                _ => return Err(AuthError::InvalidCredentials),
+            }
+        };
+
+        let mut state = self.state()?;
+
+        let tokens = Self::issue(
+            &mut state,
+            &request.username,
+            user_id,
+            request.device,
+            Utc::now(),
+        );
+        Ok(LoginResult { user_id, tokens })
     }
 
     async fn verify_token(&self, token: &str) -> Result<UserId, AuthError> {
-        if let Some(username) = token.strip_prefix("fake-access-token:") {
-            Ok(get_fake_id(&username))
-        } else {
-            Err(AuthError::TokenInvalid)
-        }
+        let username = token
+            .strip_prefix(ACCESS_PREFIX)
+            .ok_or(AuthError::TokenInvalid)?;
+        self.active_user(username)
+    }
+
+    async fn verify_claims(&self, token: &str) -> Result<TokenVerifyResult, AuthError> {
+        let user_id = self.verify_token(token).await?;
+        Ok(TokenVerifyResult {
+            user_id,
+            jti: None,
+            grants: self.grants_for(user_id),
+        })
+    }
+
+    async fn has_role(&self, user_id: UserId, role: Role) -> Result<bool, AuthError> {
+        Ok(self.grants_for(user_id).has_role(role))
     }
 
     async fn refresh_token(&self, refresh_token: &str) -> Result<AuthTokens, AuthError> {
-        if let Some(username) = refresh_token.strip_prefix("fake-refresh-token:") {
-            Ok(get_fake_token(&username))
-        } else {
-            Err(AuthError::TokenInvalid)
+        let (username, jti) = refresh_token
+            .strip_prefix(REFRESH_PREFIX)
+            .and_then(|rest| rest.rsplit_once(':'))
+            .ok_or(AuthError::TokenInvalid)?;
+
+        let user_id = self.active_user(username)?;
+        let mut state = self.state()?;
+        // rotation: the old refresh token stops working once used
+        let session = match state.sessions.remove(jti) {
+            Some(session) if session.user_id == user_id => session,
+            _ => return Err(AuthError::TokenInvalid),
+        };
+
+        Ok(Self::issue(
+            &mut state,
+            username,
+            user_id,
+            session.device,
+            session.started_at,
+        ))
+    }
+
+    async fn list_sessions(&self, user_id: UserId) -> Result<Vec<RefreshSession>, AuthError> {
+        let state = self.state()?;
+        let mut sessions: Vec<RefreshSession> = state
+            .sessions
+            .values()
+            .filter(|s| s.user_id == user_id)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|s| s.started_at);
+        Ok(sessions)
+    }
+
+    async fn revoke_sessions(&self, user_id: UserId) -> Result<u64, AuthError> {
+        let mut state = self.state()?;
+        let before = state.sessions.len();
+        state.sessions.retain(|_, s| s.user_id != user_id);
+        Ok((before - state.sessions.len()) as u64)
+    }
+
+    async fn start_qr_login(&self, device: Option<String>) -> Result<QrLoginTicket, AuthError> {
+        let nonce = nanoid::nanoid!(32);
+        let poll_secret = nanoid::nanoid!(32);
+        let expires_at = Utc::now() + self.qr_login_ttl;
+
+        let mut state = self.state()?;
+        let now = Utc::now();
+        state.qr_logins.retain(|_, login| login.expires_at > now);
+        state.qr_logins.insert(
+            nonce.clone(),
+            FakeQrLogin {
+                poll_secret: poll_secret.clone(),
+                device,
+                approved_by: None,
+                expires_at,
+            },
+        );
+
+        Ok(QrLoginTicket {
+            nonce,
+            poll_secret: Secret::new(poll_secret),
+            expires_at,
+        })
+    }
+
+    async fn approve_qr_login(&self, user_id: UserId, nonce: &str) -> Result<(), AuthError> {
+        let mut state = self.state()?;
+        match state.qr_logins.get_mut(nonce) {
+            Some(login) if login.expires_at > Utc::now() && login.approved_by.is_none() => {
+                login.approved_by = Some(user_id);
+                Ok(())
+            }
+            _ => Err(AuthError::QrLoginNotFound),
         }
     }
+
+    async fn poll_qr_login(
+        &self,
+        nonce: &str,
+        poll_secret: &str,
+    ) -> Result<Option<LoginResult>, AuthError> {
+        let mut state = self.state()?;
+        let user_id = match state.qr_logins.get(nonce) {
+            Some(login) if login.expires_at > Utc::now() && login.poll_secret == poll_secret => {
+                match login.approved_by {
+                    Some(user_id) => user_id,
+                    None => return Ok(None),
+                }
+            }
+            _ => return Err(AuthError::QrLoginNotFound),
+        };
+        let login = state
+            .qr_logins
+            .remove(nonce)
+            .ok_or(AuthError::QrLoginNotFound)?;
+
+        let username = {
+            let users = self.store.state();
+            users
+                .username(user_id)
+                .filter(|_| users.is_active(user_id))
+                .ok_or(AuthError::QrLoginNotFound)?
+        };
+        let tokens = Self::issue(&mut state, &username, user_id, login.device, Utc::now());
+        Ok(Some(LoginResult { user_id, tokens }))
+    }
 }
 
 fn get_fake_id(username: &str) -> UserId {
@@ -49,13 +314,3 @@ fn get_fake_id(username: &str) -> UserId {
         username.as_bytes(),
     ))
 }
-
-fn get_fake_token(username: &str) -> AuthTokens {
-    let now = Utc::now();
-    AuthTokens {
-        access_token: AccessToken(format!("fake-access-token:{}", username)),
-        access_token_expires_at: now + Duration::days(1), // 1 day
-        refresh_token: RefreshToken(format!("fake-refresh-token:{}", username)),
-        refresh_token_expires_at: now + Duration::days(7), // 7 days
-    }
-}
@@ -1,12 +1,15 @@
 use crate::application_port::*;
 use crate::domain_model::UserId;
 use crate::domain_port::*;
+use crate::logger::Secret;
 use argon2::password_hash::rand_core::OsRng;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use chrono::{DateTime, Utc};
 use jsonwebtoken::errors::ErrorKind;
 use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 use uuid::Uuid;
@@ -51,6 +54,11 @@ pub struct JwtConfig {
     pub audience: String,
     pub access_ttl: Duration,
     pub refresh_ttl: Duration,
+    /// Restart the refresh window on every rotation instead of keeping the
+    /// expiry of the original login.
+    pub sliding_refresh: bool,
+    /// Hard cap on a sliding session, counted from the original login.
+    pub refresh_max_lifetime: Duration,
     pub signing_key: Vec<u8>,
 }
 
@@ -62,6 +70,10 @@ struct AccessClaims {
     iss: String,
     aud: String,
     jti: String, // optional: can be used for blacklist
+    #[serde(default)]
+    roles: Vec<Role>,
+    #[serde(default)]
+    scopes: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -77,6 +89,7 @@ struct RefreshClaims {
 fn encode_access(
     uid: UserId,
     jti: String,
+    grants: &Grants,
     cfg: &JwtConfig,
 ) -> Result<(String, DateTime<Utc>), AuthError> {
     let iat_dt = Utc::now();
@@ -88,6 +101,8 @@ fn encode_access(
         iss: cfg.issuer.clone(),
         aud: cfg.audience.clone(),
         jti,
+        roles: grants.roles.clone(),
+        scopes: grants.scopes.clone(),
     };
     let token = encode(
         &Header::new(Algorithm::HS256),
@@ -101,10 +116,18 @@ fn encode_access(
 fn encode_refresh(
     uid: UserId,
     jti: String,
+    session_started_at: DateTime<Utc>,
     cfg: &JwtConfig,
 ) -> Result<(String, DateTime<Utc>), AuthError> {
     let iat_dt = Utc::now();
-    let exp_dt = iat_dt + cfg.refresh_ttl;
+    let exp_dt = if cfg.sliding_refresh {
+        (iat_dt + cfg.refresh_ttl).min(session_started_at + cfg.refresh_max_lifetime)
+    } else {
+        session_started_at + cfg.refresh_ttl
+    };
+    if exp_dt <= iat_dt {
+        return Err(AuthError::TokenExpired);
+    }
     let claims = RefreshClaims {
         sub: uid.0.to_string(),
         exp: exp_dt.timestamp(),
@@ -175,30 +198,36 @@ impl TokenCodec for JwtHs256Codec {
         &self,
         user: UserId,
         jti: Option<String>,
+        grants: &Grants,
     ) -> Result<(AccessToken, DateTime<Utc>), AuthError> {
         let jti = jti.unwrap_or_else(Self::gen_jti);
-        let (token, exp_dt) = encode_access(user, jti, &self.cfg)?;
-        Ok((AccessToken(token), exp_dt))
+        let (token, exp_dt) = encode_access(user, jti, grants, &self.cfg)?;
+        Ok((AccessToken(Secret::new(token)), exp_dt))
     }
 
     async fn issue_refresh_token(
         &self,
         user: UserId,
         jti: String,
+        session_started_at: DateTime<Utc>,
     ) -> Result<(RefreshToken, DateTime<Utc>), AuthError> {
-        let (token, exp_dt) = encode_refresh(user, jti, &self.cfg)?;
-        Ok((RefreshToken(token), exp_dt))
+        let (token, exp_dt) = encode_refresh(user, jti, session_started_at, &self.cfg)?;
+        Ok((RefreshToken(Secret::new(token)), exp_dt))
     }
 
     async fn verify_access_token(
         &self,
         token: &AccessToken,
     ) -> Result<TokenVerifyResult, AuthError> {
-        let claims = decode_access(&token.0, &self.cfg)?;
+        let claims = decode_access(token.0.expose(), &self.cfg)?;
         let user_id = Self::parse_user_id(&claims.sub)?;
         Ok(TokenVerifyResult {
             user_id,
             jti: Some(claims.jti),
+            grants: Grants {
+                roles: claims.roles,
+                scopes: claims.scopes,
+            },
         })
     }
 
@@ -206,11 +235,12 @@ impl TokenCodec for JwtHs256Codec {
         &self,
         token: &RefreshToken,
     ) -> Result<TokenVerifyResult, AuthError> {
-        let claims = decode_refresh(&token.0, &self.cfg)?;
+        let claims = decode_refresh(token.0.expose(), &self.cfg)?;
         let user_id = Self::parse_user_id(&claims.sub)?;
         Ok(TokenVerifyResult {
             user_id,
             jti: Some(claims.jti),
+            grants: Grants::default(),
         })
     }
 }
@@ -222,11 +252,18 @@ pub struct RealAuthService {
     token_codec: Arc<dyn TokenCodec>,
     session_store: Arc<dyn AuthSessionStore>,
     tx_manager: Arc<dyn TxManager>,
+    admins: HashSet<UserId>,
+    username_policy: Arc<dyn UsernamePolicyService>,
+    /// Set while `auth.invite_only`; signup then redeems a code.
+    invites: Option<Arc<dyn InviteCodeRepo>>,
+    qr_logins: Arc<dyn QrLoginStore>,
+    qr_login_ttl: Duration,
     min_username_len: usize,
     min_password_len: usize,
 }
 
 impl RealAuthService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         auth_repo: Arc<dyn AuthRepo>,
         user_repo: Arc<dyn UserRepo>,
@@ -234,6 +271,11 @@ impl RealAuthService {
         token_codec: Arc<dyn TokenCodec>,
         session_store: Arc<dyn AuthSessionStore>,
         tx_manager: Arc<dyn TxManager>,
+        admins: HashSet<UserId>,
+        username_policy: Arc<dyn UsernamePolicyService>,
+        invites: Option<Arc<dyn InviteCodeRepo>>,
+        qr_logins: Arc<dyn QrLoginStore>,
+        qr_login_ttl: Duration,
     ) -> Self {
         Self {
             auth_repo,
@@ -242,6 +284,11 @@ impl RealAuthService {
             token_codec,
             session_store,
             tx_manager,
+            admins,
+            username_policy,
+            invites,
+            qr_logins,
+            qr_login_ttl,
             min_username_len: 6,
             min_password_len: 6,
         }
@@ -257,6 +304,18 @@ impl RealAuthService {
         Ok(())
     }
 
+    fn grants_for(&self, user_id: UserId) -> Grants {
+        let mut grants = Grants {
+            roles: vec![Role::User],
+            scopes: vec![SCOPE_CHAT.to_string()],
+        };
+        if self.admins.contains(&user_id) {
+            grants.roles.push(Role::Admin);
+            grants.scopes.push(SCOPE_ADMIN.to_string());
+        }
+        grants
+    }
+
     #[inline]
     fn new_user_id() -> UserId {
         UserId(Uuid::new_v4())
@@ -267,6 +326,52 @@ impl RealAuthService {
         Uuid::new_v4().to_string()
     }
 
+    fn secret_hash(secret: &str) -> String {
+        hex::encode(Sha256::digest(secret.as_bytes()))
+    }
+
+    /// Opens a refresh session for `user_id` and issues both tokens for it.
+    async fn open_session(
+        &self,
+        user_id: UserId,
+        device: Option<String>,
+    ) -> Result<LoginResult, AuthError> {
+        let jti = Self::new_jti();
+        let now = Utc::now();
+
+        let (access_token, access_exp) = self
+            .token_codec
+            .issue_access_token(user_id, Some(jti.clone()), &self.grants_for(user_id))
+            .await?;
+
+        let (refresh_token, refresh_exp) = self
+            .token_codec
+            .issue_refresh_token(user_id, jti.clone(), now)
+            .await?;
+
+        let session = RefreshSession {
+            jti,
+            user_id,
+            device,
+            issued_at: now,
+            started_at: now,
+        };
+        let ttl_secs = Self::ttl_secs(refresh_exp);
+        self.session_store
+            .save_refresh_jti(&session, ttl_secs)
+            .await?;
+
+        Ok(LoginResult {
+            user_id,
+            tokens: AuthTokens {
+                access_token,
+                refresh_token,
+                access_token_expires_at: access_exp,
+                refresh_token_expires_at: refresh_exp,
+            },
+        })
+    }
+
     fn ttl_secs(until: DateTime<Utc>) -> u64 {
         let now = Utc::now();
         let secs = (until - now).num_seconds();
@@ -277,9 +382,17 @@ impl RealAuthService {
 #[async_trait::async_trait]
 impl AuthService for RealAuthService {
     async fn signup(&self, request: SignupInput) -> std::result::Result<UserId, AuthError> {
-        let SignupInput { username, password } = request;
-
-        self.validate_signup(&username, &password)?;
+        let SignupInput {
+            username,
+            password,
+            invite_code,
+        } = request;
+
+        self.validate_signup(&username, password.expose())?;
+        self.username_policy.check(&username).await?;
+        if self.invites.is_some() && invite_code.is_none() {
+            return Err(AuthError::InviteRequired);
+        }
 
         if self.user_repo.username_exists(&username).await? {
             return Err(AuthError::UserExists);
@@ -292,11 +405,22 @@ impl AuthService for RealAuthService {
             .map_err(|e| AuthError::Store(e.to_string()))?;
         let user_id = Self::new_user_id();
 
+        // taken in the signup transaction, so a failed signup gives the use back
+        if let (Some(invites), Some(code)) = (&self.invites, &invite_code) {
+            let redeemed = invites.redeem_in_tx(tx.as_mut(), code).await?;
+            if !redeemed {
+                return Err(AuthError::InvalidInvite);
+            }
+        }
+
         self.user_repo
             .create_in_tx(tx.as_mut(), user_id, &username)
             .await?;
 
-        let password_hash = self.credential_hasher.hash_password(&password).await?;
+        let password_hash = self
+            .credential_hasher
+            .hash_password(password.expose())
+            .await?;
         self.auth_repo
             .create_credentials_in_tx(tx.as_mut(), user_id, &username, &password_hash)
             .await?;
@@ -309,7 +433,11 @@ impl AuthService for RealAuthService {
     }
 
     async fn login(&self, request: LoginInput) -> std::result::Result<LoginResult, AuthError> {
-        let LoginInput { username, password } = request;
+        let LoginInput {
+            username,
+            password,
+            device,
+        } = request;
 
         let rec = self
             .auth_repo
@@ -323,44 +451,19 @@ impl AuthService for RealAuthService {
 
         let ok = self
             .credential_hasher
-            .verify_password(&password, &rec.password_hash)
+            .verify_password(password.expose(), &rec.password_hash)
             .await?;
         if !ok {
             return Err(AuthError::InvalidCredentials);
         }
 
-        let jti = Self::new_jti();
-
-        let (access_token, access_exp) = self
-            .token_codec
-            .issue_access_token(rec.user_id, Some(jti.clone()))
-            .await?;
-
-        let (refresh_token, refresh_exp) = self
-            .token_codec
-            .issue_refresh_token(rec.user_id, jti.clone())
-            .await?;
-
-        let ttl_secs = Self::ttl_secs(refresh_exp);
-        self.session_store
-            .save_refresh_jti(rec.user_id, &jti, ttl_secs)
-            .await?;
-
-        Ok(LoginResult {
-            user_id: rec.user_id,
-            tokens: AuthTokens {
-                access_token,
-                refresh_token,
-                access_token_expires_at: access_exp,
-                refresh_token_expires_at: refresh_exp,
-            },
-        })
+        self.open_session(rec.user_id, device).await
     }
 
     async fn verify_token(&self, token: &str) -> std::result::Result<UserId, AuthError> {
         let verify_result = self
             .token_codec
-            .verify_access_token(&AccessToken(token.to_string()))
+            .verify_access_token(&AccessToken(Secret::new(token.to_string())))
             .await?;
 
         if !self.user_repo.id_exists(verify_result.user_id).await? {
@@ -370,13 +473,26 @@ impl AuthService for RealAuthService {
         Ok(verify_result.user_id)
     }
 
+    async fn verify_claims(
+        &self,
+        token: &str,
+    ) -> std::result::Result<TokenVerifyResult, AuthError> {
+        self.token_codec
+            .verify_access_token(&AccessToken(Secret::new(token.to_string())))
+            .await
+    }
+
+    async fn has_role(&self, user_id: UserId, role: Role) -> std::result::Result<bool, AuthError> {
+        Ok(self.grants_for(user_id).has_role(role) && self.user_repo.id_exists(user_id).await?)
+    }
+
     async fn refresh_token(
         &self,
         refresh_token: &str,
     ) -> std::result::Result<AuthTokens, AuthError> {
         let verify_result = self
             .token_codec
-            .verify_refresh_token(&RefreshToken(refresh_token.to_string()))
+            .verify_refresh_token(&RefreshToken(Secret::new(refresh_token.to_string())))
             .await?;
 
         if !self.user_repo.id_exists(verify_result.user_id).await? {
@@ -387,30 +503,37 @@ impl AuthService for RealAuthService {
         let jti = verify_result.jti.ok_or(AuthError::TokenInvalid)?;
 
         // Rotation: check-and-consume
-        match self
+        let previous = match self
             .session_store
             .check_refresh_jti(user_id, &jti, true)
             .await?
         {
-            Some(found_user_id) => if found_user_id == user_id { /* proceed */ },
+            Some(session) if session.user_id == user_id => session,
             _ => return Err(AuthError::TokenInvalid),
-        }
+        };
 
         // Issue new JTI + tokens
         let new_jti = Self::new_jti();
 
         let (access_token, access_exp) = self
             .token_codec
-            .issue_access_token(user_id, Some(new_jti.clone()))
+            .issue_access_token(user_id, Some(new_jti.clone()), &self.grants_for(user_id))
             .await?;
         let (refresh_token, refresh_exp) = self
             .token_codec
-            .issue_refresh_token(user_id, new_jti.clone())
+            .issue_refresh_token(user_id, new_jti.clone(), previous.started_at)
             .await?;
 
+        let session = RefreshSession {
+            jti: new_jti,
+            user_id,
+            device: previous.device,
+            issued_at: Utc::now(),
+            started_at: previous.started_at,
+        };
         let ttl_secs = Self::ttl_secs(refresh_exp);
         self.session_store
-            .save_refresh_jti(user_id, &jti, ttl_secs)
+            .save_refresh_jti(&session, ttl_secs)
             .await?;
 
         Ok(AuthTokens {
@@ -420,4 +543,69 @@ impl AuthService for RealAuthService {
             refresh_token_expires_at: refresh_exp,
         })
     }
+
+    async fn list_sessions(
+        &self,
+        user_id: UserId,
+    ) -> std::result::Result<Vec<RefreshSession>, AuthError> {
+        self.session_store.list_jtis(user_id).await
+    }
+
+    async fn revoke_sessions(&self, user_id: UserId) -> std::result::Result<u64, AuthError> {
+        self.session_store.revoke_all(user_id).await
+    }
+
+    async fn start_qr_login(
+        &self,
+        device: Option<String>,
+    ) -> std::result::Result<QrLoginTicket, AuthError> {
+        let nonce = nanoid::nanoid!(32);
+        let poll_secret = nanoid::nanoid!(32);
+        let login = QrLogin {
+            secret_hash: Self::secret_hash(&poll_secret),
+            device,
+            approved_by: None,
+        };
+        self.qr_logins
+            .create(&nonce, &login, self.qr_login_ttl.as_secs())
+            .await?;
+
+        Ok(QrLoginTicket {
+            nonce,
+            poll_secret: Secret::new(poll_secret),
+            expires_at: Utc::now() + self.qr_login_ttl,
+        })
+    }
+
+    async fn approve_qr_login(
+        &self,
+        user_id: UserId,
+        nonce: &str,
+    ) -> std::result::Result<(), AuthError> {
+        if !self.qr_logins.approve(nonce, user_id).await? {
+            return Err(AuthError::QrLoginNotFound);
+        }
+        Ok(())
+    }
+
+    async fn poll_qr_login(
+        &self,
+        nonce: &str,
+        poll_secret: &str,
+    ) -> std::result::Result<Option<LoginResult>, AuthError> {
+        let Some(login) = self
+            .qr_logins
+            .take_if_approved(nonce, &Self::secret_hash(poll_secret))
+            .await?
+        else {
+            return Ok(None);
+        };
+        let user_id = login.approved_by.ok_or(AuthError::QrLoginNotFound)?;
+
+        // the approver may have been deactivated since
+        if !self.user_repo.id_exists(user_id).await? {
+            return Err(AuthError::QrLoginNotFound);
+        }
+        self.open_session(user_id, login.device).await.map(Some)
+    }
 }
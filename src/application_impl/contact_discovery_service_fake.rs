@@ -0,0 +1,102 @@
+use crate::application_impl::discovery_limiter::{DiscoveryLimiter, distinct};
+use crate::application_impl::fake_store::FakeStore;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use std::sync::Arc;
+
+/// In-memory `ContactDiscoveryService`; see `FakeStore`.
+pub struct FakeContactDiscoveryService {
+    store: Arc<FakeStore>,
+    limiter: DiscoveryLimiter,
+    salt: String,
+}
+
+impl FakeContactDiscoveryService {
+    pub fn new(
+        store: Arc<FakeStore>,
+        rate_limiter: Arc<dyn RateLimiter>,
+        limits: DiscoveryLimits,
+        salt: String,
+    ) -> Self {
+        Self {
+            store,
+            limiter: DiscoveryLimiter::new(rate_limiter, limits),
+            salt,
+        }
+    }
+
+    fn settings_for(&self, identifiers: usize) -> DiscoverySettings {
+        DiscoverySettings {
+            discoverable: identifiers > 0,
+            identifiers,
+            salt: self.salt.clone(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ContactDiscoveryService for FakeContactDiscoveryService {
+    async fn settings(&self, user_id: UserId) -> Result<DiscoverySettings, DiscoveryError> {
+        let identifiers = self
+            .store
+            .state()
+            .contact_hashes
+            .get(&user_id)
+            .map_or(0, Vec::len);
+        Ok(self.settings_for(identifiers))
+    }
+
+    async fn set_identifiers(
+        &self,
+        user_id: UserId,
+        hashes: &[ContactHash],
+    ) -> Result<DiscoverySettings, DiscoveryError> {
+        let hashes = distinct(hashes);
+        if hashes.len() > MAX_OWN_CONTACT_HASHES {
+            return Err(DiscoveryError::TooMany("identifiers"));
+        }
+
+        let identifiers = hashes.len();
+        let mut state = self.store.state();
+        if hashes.is_empty() {
+            state.contact_hashes.remove(&user_id);
+        } else {
+            state.contact_hashes.insert(user_id, hashes);
+        }
+        Ok(self.settings_for(identifiers))
+    }
+
+    async fn discover(
+        &self,
+        user_id: UserId,
+        hashes: &[ContactHash],
+    ) -> Result<Vec<ContactMatch>, DiscoveryError> {
+        let hashes = distinct(hashes);
+        if hashes.len() > MAX_DISCOVERY_BATCH {
+            return Err(DiscoveryError::TooMany("lookup"));
+        }
+        if !self.store.state().contact_hashes.contains_key(&user_id) {
+            return Err(DiscoveryError::NotDiscoverable);
+        }
+        self.limiter.acquire(user_id).await?;
+
+        let state = self.store.state();
+        let mut matches: Vec<ContactMatch> = state
+            .contact_hashes
+            .iter()
+            .filter(|(other, _)| **other != user_id && state.is_active(**other))
+            .flat_map(|(other, own)| {
+                own.iter()
+                    .filter(|hash| hashes.binary_search(hash).is_ok())
+                    .map(|hash| ContactMatch {
+                        contact_hash: *hash,
+                        user_id: *other,
+                        username: state.username(*other).unwrap_or_default(),
+                    })
+            })
+            .collect();
+        matches.sort_by_key(|m| (m.contact_hash, m.user_id));
+        Ok(matches)
+    }
+}
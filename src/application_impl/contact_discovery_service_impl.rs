@@ -0,0 +1,121 @@
+use crate::application_impl::discovery_limiter::{DiscoveryLimiter, distinct};
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use std::sync::Arc;
+
+pub struct RealContactDiscoveryService {
+    contact_hash_repo: Arc<dyn ContactHashRepo>,
+    tx_manager: Arc<dyn TxManager>,
+    limiter: DiscoveryLimiter,
+    salt: String,
+}
+
+impl RealContactDiscoveryService {
+    pub fn new(
+        contact_hash_repo: Arc<dyn ContactHashRepo>,
+        tx_manager: Arc<dyn TxManager>,
+        rate_limiter: Arc<dyn RateLimiter>,
+        limits: DiscoveryLimits,
+        salt: String,
+    ) -> Self {
+        Self {
+            contact_hash_repo,
+            tx_manager,
+            limiter: DiscoveryLimiter::new(rate_limiter, limits),
+            salt,
+        }
+    }
+
+    fn settings_for(&self, identifiers: usize) -> DiscoverySettings {
+        DiscoverySettings {
+            discoverable: identifiers > 0,
+            identifiers,
+            salt: self.salt.clone(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ContactDiscoveryService for RealContactDiscoveryService {
+    async fn settings(&self, user_id: UserId) -> Result<DiscoverySettings, DiscoveryError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| DiscoveryError::Store(e.to_string()))?;
+        let identifiers = self
+            .contact_hash_repo
+            .count_in_tx(&mut *tx, user_id)
+            .await
+            .map_err(|e| DiscoveryError::Store(format!("count contact hashes: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| DiscoveryError::Store(e.to_string()))?;
+
+        Ok(self.settings_for(identifiers))
+    }
+
+    async fn set_identifiers(
+        &self,
+        user_id: UserId,
+        hashes: &[ContactHash],
+    ) -> Result<DiscoverySettings, DiscoveryError> {
+        let hashes = distinct(hashes);
+        if hashes.len() > MAX_OWN_CONTACT_HASHES {
+            return Err(DiscoveryError::TooMany("identifiers"));
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| DiscoveryError::Store(e.to_string()))?;
+        self.contact_hash_repo
+            .replace_in_tx(&mut *tx, user_id, &hashes)
+            .await
+            .map_err(|e| DiscoveryError::Store(format!("store contact hashes: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| DiscoveryError::Store(e.to_string()))?;
+
+        Ok(self.settings_for(hashes.len()))
+    }
+
+    async fn discover(
+        &self,
+        user_id: UserId,
+        hashes: &[ContactHash],
+    ) -> Result<Vec<ContactMatch>, DiscoveryError> {
+        let hashes = distinct(hashes);
+        if hashes.len() > MAX_DISCOVERY_BATCH {
+            return Err(DiscoveryError::TooMany("lookup"));
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| DiscoveryError::Store(e.to_string()))?;
+        let own = self
+            .contact_hash_repo
+            .count_in_tx(&mut *tx, user_id)
+            .await
+            .map_err(|e| DiscoveryError::Store(format!("count contact hashes: {e}")))?;
+        if own == 0 {
+            return Err(DiscoveryError::NotDiscoverable);
+        }
+        self.limiter.acquire(user_id).await?;
+
+        let matches = self
+            .contact_hash_repo
+            .find_in_tx(&mut *tx, user_id, &hashes)
+            .await
+            .map_err(|e| DiscoveryError::Store(format!("match contact hashes: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| DiscoveryError::Store(e.to_string()))?;
+
+        Ok(matches)
+    }
+}
@@ -0,0 +1,90 @@
+use crate::application_impl::fake_store::FakeStore;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::EventType;
+use chrono::Utc;
+use std::sync::Arc;
+
+/// In-memory `ConversationMetaService`; see `FakeStore`.
+pub struct FakeConversationMetaService {
+    store: Arc<FakeStore>,
+}
+
+impl FakeConversationMetaService {
+    pub fn new(store: Arc<FakeStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl ConversationMetaService for FakeConversationMetaService {
+    async fn list(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<ConversationMeta>, ChatError> {
+        let state = self.store.state();
+        let is_member = state
+            .members(conversation_id)
+            .is_some_and(|members| members.contains(&user_id));
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+
+        Ok(state
+            .meta
+            .get(&conversation_id)
+            .map(|entries| entries.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn set(
+        &self,
+        conversation_id: ConversationId,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<(), ChatError> {
+        check_meta_entry(key, value)?;
+
+        let members = {
+            let mut state = self.store.state();
+            let members = state
+                .members(conversation_id)
+                .ok_or(ChatError::ConversationNotFound)?;
+            let entries = state.meta.entry(conversation_id).or_default();
+            match value {
+                Some(value) => {
+                    if entries.len() >= MAX_META_KEYS && !entries.contains_key(key) {
+                        return Err(ChatError::InvalidMeta("too many keys"));
+                    }
+                    entries.insert(
+                        key.to_owned(),
+                        ConversationMeta {
+                            key: key.to_owned(),
+                            value: value.to_owned(),
+                            updated_at: Utc::now(),
+                        },
+                    );
+                }
+                None => {
+                    if entries.remove(key).is_none() {
+                        return Ok(());
+                    }
+                }
+            }
+            members
+        };
+
+        self.store.publish(
+            EventType::ConversationMetaChanged,
+            conversation_id.0,
+            members,
+            &S2CEvent::ConversationMetaChanged(ConversationMetaChanged {
+                conversation_id,
+                key: key.to_owned(),
+                value: value.map(str::to_owned),
+            }),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use std::sync::Arc;
+
+pub struct RealConversationMetaService {
+    meta_repo: Arc<dyn ConversationMetaRepo>,
+    conversation_repo: Arc<dyn ConversationRepo>,
+    conversation_role_repo: Arc<dyn ConversationRoleRepo>,
+    outbox_repo: Arc<dyn OutboxRepo>,
+    tx_manager: Arc<dyn TxManager>,
+}
+
+impl RealConversationMetaService {
+    pub fn new(
+        meta_repo: Arc<dyn ConversationMetaRepo>,
+        conversation_repo: Arc<dyn ConversationRepo>,
+        conversation_role_repo: Arc<dyn ConversationRoleRepo>,
+        outbox_repo: Arc<dyn OutboxRepo>,
+        tx_manager: Arc<dyn TxManager>,
+    ) -> Self {
+        Self {
+            meta_repo,
+            conversation_repo,
+            conversation_role_repo,
+            outbox_repo,
+            tx_manager,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ConversationMetaService for RealConversationMetaService {
+    async fn list(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<ConversationMeta>, ChatError> {
+        let is_member = self
+            .conversation_role_repo
+            .membership_exists(conversation_id, user_id)
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let entries = self.meta_repo.list_in_tx(&mut *tx, conversation_id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(entries)
+    }
+
+    async fn set(
+        &self,
+        conversation_id: ConversationId,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<(), ChatError> {
+        check_meta_entry(key, value)?;
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let members = self
+            .conversation_repo
+            .get_conversation_member_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| ChatError::Store(format!("query chat members: {e}")))?;
+        // every conversation is created with its first members
+        if members.is_empty() {
+            return Err(ChatError::ConversationNotFound);
+        }
+
+        match value {
+            Some(value) => {
+                let keys = self
+                    .meta_repo
+                    .lock_keys_in_tx(&mut *tx, conversation_id)
+                    .await?;
+                if keys.len() >= MAX_META_KEYS && !keys.iter().any(|k| k == key) {
+                    return Err(ChatError::InvalidMeta("too many keys"));
+                }
+                self.meta_repo
+                    .put_in_tx(&mut *tx, conversation_id, key, value)
+                    .await?;
+            }
+            None => {
+                if !self
+                    .meta_repo
+                    .delete_in_tx(&mut *tx, conversation_id, key)
+                    .await?
+                {
+                    return Ok(());
+                }
+            }
+        }
+
+        let event = OutboxEvent::new(
+            EventType::ConversationMetaChanged,
+            Some(conversation_id.0),
+            members,
+            &S2CEvent::ConversationMetaChanged(ConversationMetaChanged {
+                conversation_id,
+                key: key.to_owned(),
+                value: value.map(str::to_owned),
+            }),
+        )
+        .map_err(|e| ChatError::Store(format!("compose conversation.meta.changed event: {e}")))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| {
+                ChatError::Store(format!("enqueue conversation.meta.changed event: {e}"))
+            })?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(())
+    }
+}
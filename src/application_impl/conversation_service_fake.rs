@@ -0,0 +1,981 @@
+use crate::application_impl::fake_store::{FakePeer, FakeStore};
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::EventType;
+use crate::logger::Secret;
+use chrono::{DateTime, SubsecRound, Utc};
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+/// In-memory `ConversationService`; see `FakeStore`.
+pub struct FakeConversationService {
+    store: Arc<FakeStore>,
+    retention: RetentionPolicy,
+}
+
+impl FakeConversationService {
+    /// Nothing is pruned in memory; `retention` only shapes what is reported.
+    pub fn new(store: Arc<FakeStore>, retention: RetentionPolicy) -> Self {
+        Self { store, retention }
+    }
+}
+
+#[async_trait::async_trait]
+impl ConversationService for FakeConversationService {
+    async fn send_message(
+        &self,
+        conversation_id: ConversationId,
+        sender: UserId,
+        content: &str,
+        message_id: MessageId,
+        reply_to: Option<MessageId>,
+        attachments: &[AttachmentId],
+    ) -> Result<SentMessage, ChatError> {
+        if attachments.len() > MAX_MESSAGE_ATTACHMENTS {
+            return Err(ChatError::InvalidAttachment);
+        }
+        let (record, sender_seq, receivers, mentioned, username) = {
+            let mut state = self.store.state();
+            let members = state
+                .members(conversation_id)
+                .ok_or(ChatError::ConversationNotFound)?;
+            if !members.contains(&sender) {
+                return Err(ChatError::NotMember);
+            }
+            let username = state.username(sender).unwrap_or_default();
+            // a retried send finds them tied to its own message already
+            let mut uploads: Vec<&AttachmentRecord> = state
+                .attachments
+                .values()
+                .filter(|a| attachments.contains(&a.attachment_id))
+                .collect();
+            // duplicate ids in `attachments` leave `uploads` short
+            if uploads.len() != attachments.len()
+                || !uploads.iter().all(|a| {
+                    a.conversation_id == conversation_id
+                        && a.uploader == sender
+                        && a.message_id.is_none_or(|id| id == message_id)
+                })
+            {
+                return Err(ChatError::InvalidAttachment);
+            }
+            uploads.sort_by_key(|a| (a.created_at, a.attachment_id));
+            let uploads: Vec<Attachment> = uploads.iter().map(|a| a.attachment()).collect();
+
+            let Some(conversation) = state.conversations.get_mut(&conversation_id) else {
+                return Err(ChatError::ConversationNotFound);
+            };
+            if conversation.frozen {
+                return Err(ChatError::Frozen);
+            }
+            // same message id: hand back what was stored, like the unique key does
+            if let Some(existing) = conversation
+                .messages
+                .iter()
+                .find(|m| m.message_id == message_id)
+            {
+                return Ok(SentMessage {
+                    record: existing.clone(),
+                    sender_seq: sender_seq(
+                        &conversation.messages,
+                        existing.sender,
+                        existing.message_offset,
+                    ),
+                    duplicate: true,
+                });
+            }
+            if let Some(reply_to) = reply_to {
+                let target = conversation
+                    .messages
+                    .iter()
+                    .find(|m| m.message_id == reply_to);
+                if target.is_none_or(|target| target.deleted_at.is_some()) {
+                    return Err(ChatError::InvalidReply);
+                }
+            }
+            let record = MessageRecord {
+                message_id,
+                conversation_id,
+                // imported history can leave gaps
+                message_offset: MessageOffset(
+                    conversation
+                        .messages
+                        .last()
+                        .map_or(0, |m| m.message_offset.0)
+                        + 1,
+                ),
+                sender,
+                content: Secret::new(content.to_owned()),
+                content_encrypted: false,
+                created_at: Utc::now().trunc_subsecs(6),
+                edited_at: None,
+                edit_count: 0,
+                deleted_at: None,
+                kind: MessageKind::User,
+                reply_to,
+                attachments: uploads,
+            };
+            conversation.messages.push(record.clone());
+            let sender_seq = sender_seq(&conversation.messages, sender, record.message_offset);
+            let read = state
+                .read_offsets
+                .entry((sender, conversation_id))
+                .or_insert(MessageOffset(0));
+            *read = (*read).max(record.message_offset);
+
+            for attachment_id in attachments {
+                if let Some(upload) = state.attachments.get_mut(attachment_id) {
+                    upload.message_id = Some(message_id);
+                }
+            }
+
+            let mut mentioned = Vec::new();
+            for name in mentioned_usernames(content) {
+                let Some(&user_id) = state.usernames.get(&name) else {
+                    continue;
+                };
+                if user_id != sender && state.is_active(user_id) && members.contains(&user_id) {
+                    state.mentions.insert((user_id, message_id));
+                    mentioned.push(user_id);
+                }
+            }
+
+            let receivers: Vec<UserId> = members.into_iter().filter(|m| *m != sender).collect();
+            (record, sender_seq, receivers, mentioned, username)
+        };
+
+        self.store.publish(
+            EventType::ChatMessageNew,
+            conversation_id.0,
+            receivers,
+            &S2CEvent::ChatMessageNew(ChatMessageNew {
+                conversation_id: record.conversation_id,
+                message_id: record.message_id,
+                message_offset: record.message_offset,
+                content: record.content.clone(),
+                sender: record.sender,
+                username: username.clone(),
+                created_at: record.created_at,
+                truncated: false,
+                reply_to: record.reply_to,
+                attachments: record.attachments.clone(),
+            }),
+        );
+        if !mentioned.is_empty() {
+            self.store.publish(
+                EventType::ChatMentionNew,
+                conversation_id.0,
+                mentioned,
+                &S2CEvent::ChatMentionNew(ChatMentionNew {
+                    conversation_id: record.conversation_id,
+                    message_id: record.message_id,
+                    message_offset: record.message_offset,
+                    sender: record.sender,
+                    username,
+                    created_at: record.created_at,
+                }),
+            );
+        }
+        Ok(SentMessage {
+            record,
+            sender_seq,
+            duplicate: false,
+        })
+    }
+
+    async fn edit_message(
+        &self,
+        conversation_id: ConversationId,
+        editor: UserId,
+        message_id: MessageId,
+        new_content: &str,
+    ) -> Result<MessageRecord, ChatError> {
+        let (record, members) = {
+            let mut state = self.store.state();
+            let members = state
+                .members(conversation_id)
+                .ok_or(ChatError::ConversationNotFound)?;
+            if !members.contains(&editor) {
+                return Err(ChatError::NotMember);
+            }
+            let Some(conversation) = state.conversations.get_mut(&conversation_id) else {
+                return Err(ChatError::ConversationNotFound);
+            };
+            if conversation.frozen {
+                return Err(ChatError::Frozen);
+            }
+            let record = conversation
+                .messages
+                .iter_mut()
+                .find(|m| m.message_id == message_id && m.deleted_at.is_none())
+                .ok_or(ChatError::MessageNotFound)?;
+            if record.sender != editor || record.kind != MessageKind::User {
+                return Err(ChatError::Forbidden("only the sender can edit"));
+            }
+            record.content = Secret::new(new_content.to_owned());
+            record.edited_at = Some(Utc::now().trunc_subsecs(6));
+            record.edit_count += 1;
+            (record.clone(), members)
+        };
+
+        self.store.publish(
+            EventType::ChatMessageEdited,
+            conversation_id.0,
+            members,
+            &S2CEvent::ChatMessageEdited(ChatMessageEdited {
+                conversation_id,
+                message_id,
+                message_offset: record.message_offset,
+                content: record.content.clone(),
+                edited_at: record.edited_at.unwrap_or(record.created_at),
+                edit_count: record.edit_count,
+            }),
+        );
+        Ok(record)
+    }
+
+    async fn delete_message(
+        &self,
+        conversation_id: ConversationId,
+        deleter: UserId,
+        message_id: MessageId,
+    ) -> Result<(), ChatError> {
+        let (record, members) = {
+            let mut state = self.store.state();
+            let members = state
+                .members(conversation_id)
+                .ok_or(ChatError::ConversationNotFound)?;
+            if !members.contains(&deleter) {
+                return Err(ChatError::NotMember);
+            }
+            let owns = match state.conversations[&conversation_id].peer {
+                FakePeer::Group(group_id) => state.groups[&group_id].owner == deleter,
+                FakePeer::Direct(..) => false,
+            };
+            let Some(conversation) = state.conversations.get_mut(&conversation_id) else {
+                return Err(ChatError::ConversationNotFound);
+            };
+            let record = conversation
+                .messages
+                .iter_mut()
+                .find(|m| m.message_id == message_id)
+                .ok_or(ChatError::MessageNotFound)?;
+            if record.deleted_at.is_some() {
+                return Ok(());
+            }
+            if record.sender != deleter && !owns {
+                return Err(ChatError::Forbidden(
+                    "only the sender or the owner can delete",
+                ));
+            }
+            // the fake has no one to keep the content for
+            record.content = Secret::new(String::new());
+            record.deleted_at = Some(Utc::now().trunc_subsecs(6));
+            record.attachments.clear();
+            (record.clone(), members)
+        };
+
+        self.store.publish(
+            EventType::ChatMessageDeleted,
+            conversation_id.0,
+            members,
+            &S2CEvent::ChatMessageDeleted(ChatMessageDeleted {
+                conversation_id,
+                message_id,
+                message_offset: record.message_offset,
+                deleted_at: record.deleted_at.unwrap_or(record.created_at),
+            }),
+        );
+        Ok(())
+    }
+
+    async fn pin_message(
+        &self,
+        conversation_id: ConversationId,
+        by: UserId,
+        message_id: MessageId,
+        pinned: bool,
+    ) -> Result<(), ChatError> {
+        let (record, members, at) = {
+            let mut state = self.store.state();
+            let members = state
+                .members(conversation_id)
+                .ok_or(ChatError::ConversationNotFound)?;
+            if !members.contains(&by) {
+                return Err(ChatError::NotMember);
+            }
+            let conversation = &state.conversations[&conversation_id];
+            if conversation.frozen {
+                return Err(ChatError::Frozen);
+            }
+            // only owners hold `PIN_PERMISSION`; direct conversations have no roles
+            let may_pin = match conversation.peer {
+                FakePeer::Group(group_id) => state.groups[&group_id].owner == by,
+                FakePeer::Direct(..) => true,
+            };
+            if !may_pin {
+                return Err(ChatError::Forbidden("role may not pin messages"));
+            }
+            let live = |id: MessageId| {
+                conversation
+                    .messages
+                    .iter()
+                    .find(|m| m.message_id == id && m.deleted_at.is_none())
+            };
+            let record = live(message_id)
+                .cloned()
+                .ok_or(ChatError::MessageNotFound)?;
+            let pins = state.pinned_messages.get(&conversation_id);
+            let is_pinned = pins.is_some_and(|pins| pins.iter().any(|(id, ..)| *id == message_id));
+            if is_pinned == pinned {
+                return Ok(());
+            }
+            let live_pins = pins.map_or(0, |pins| {
+                pins.iter().filter(|(id, ..)| live(*id).is_some()).count()
+            });
+            if pinned && live_pins >= MAX_PINNED_MESSAGES {
+                return Err(ChatError::TooManyPins);
+            }
+
+            let at = Utc::now().trunc_subsecs(6);
+            let pins = state.pinned_messages.entry(conversation_id).or_default();
+            if pinned {
+                pins.push((message_id, by, at));
+            } else {
+                pins.retain(|(id, ..)| *id != message_id);
+            }
+            (record, members, at)
+        };
+
+        self.store.publish(
+            EventType::MessagePinned,
+            conversation_id.0,
+            members,
+            &S2CEvent::MessagePinned(MessagePinned {
+                conversation_id,
+                message_id,
+                message_offset: record.message_offset,
+                pinned,
+                by,
+                at,
+            }),
+        );
+        Ok(())
+    }
+
+    async fn pinned_messages(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<PinnedMessage>, ChatError> {
+        let state = self.store.state();
+        let members = state
+            .members(conversation_id)
+            .ok_or(ChatError::ConversationNotFound)?;
+        if !members.contains(&user_id) {
+            return Err(ChatError::NotMember);
+        }
+        let messages = &state.conversations[&conversation_id].messages;
+        let pins = state
+            .pinned_messages
+            .get(&conversation_id)
+            .map(|pins| pins.as_slice())
+            .unwrap_or_default();
+        Ok(pins
+            .iter()
+            .rev()
+            .filter_map(|(message_id, pinned_by, pinned_at)| {
+                let message = messages
+                    .iter()
+                    .find(|m| m.message_id == *message_id && m.deleted_at.is_none())?;
+                Some(PinnedMessage {
+                    message: message.clone(),
+                    pinned_by: *pinned_by,
+                    pinned_at: *pinned_at,
+                })
+            })
+            .collect())
+    }
+
+    async fn typing_receivers(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<Vec<UserId>, ChatError> {
+        let state = self.store.state();
+        let members = state
+            .members(conversation_id)
+            .ok_or(ChatError::ConversationNotFound)?;
+        if !members.contains(&user_id) {
+            return Err(ChatError::NotMember);
+        }
+        Ok(members.into_iter().filter(|m| *m != user_id).collect())
+    }
+
+    async fn history_summary(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<HistorySummary, ChatError> {
+        let state = self.store.state();
+        let is_member = state
+            .members(conversation_id)
+            .is_some_and(|members| members.contains(&user_id));
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+
+        let messages = &state.conversations[&conversation_id].messages;
+        Ok(HistorySummary {
+            conversation_id,
+            first_offset: messages.first().map(|m| m.message_offset),
+            last_offset: messages.last().map(|m| m.message_offset),
+            message_count: messages.len() as u64,
+        })
+    }
+
+    async fn offset_at(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        at: DateTime<Utc>,
+    ) -> Result<Option<MessageOffset>, ChatError> {
+        let state = self.store.state();
+        let is_member = state
+            .members(conversation_id)
+            .is_some_and(|members| members.contains(&user_id));
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+
+        let messages = &state.conversations[&conversation_id].messages;
+        Ok(messages
+            .iter()
+            .min_by_key(|m| ((m.created_at - at).abs(), m.created_at < at))
+            .map(|m| m.message_offset))
+    }
+
+    async fn get_history(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        page_size: PageSize,
+        before: Option<OffsetCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let state = self.store.state();
+        let is_member = state
+            .members(conversation_id)
+            .is_some_and(|members| members.contains(&user_id));
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+
+        let messages = &state.conversations[&conversation_id].messages;
+        Ok(messages
+            .iter()
+            .rev()
+            .filter(|m| before.is_none_or(|b| m.message_offset < b.offset))
+            .take(page_size.0 as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn messages_since(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        after: MessageOffset,
+        page_size: PageSize,
+    ) -> Result<Vec<ChatMessageNew>, ChatError> {
+        let state = self.store.state();
+        let is_member = state
+            .members(conversation_id)
+            .is_some_and(|members| members.contains(&user_id));
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+
+        let messages = &state.conversations[&conversation_id].messages;
+        Ok(messages
+            .iter()
+            .filter(|m| m.message_offset > after && m.deleted_at.is_none())
+            .take(page_size.0 as usize)
+            .map(|m| ChatMessageNew {
+                conversation_id: m.conversation_id,
+                message_id: m.message_id,
+                message_offset: m.message_offset,
+                content: m.content.clone(),
+                sender: m.sender,
+                username: state.username(m.sender).unwrap_or_default(),
+                created_at: m.created_at,
+                truncated: false,
+                reply_to: m.reply_to,
+                attachments: m.attachments.clone(),
+            })
+            .collect())
+    }
+
+    async fn thread_history(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        root: MessageId,
+        page_size: PageSize,
+        after: Option<OffsetCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let state = self.store.state();
+        let is_member = state
+            .members(conversation_id)
+            .is_some_and(|members| members.contains(&user_id));
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+
+        let messages = &state.conversations[&conversation_id].messages;
+        Ok(messages
+            .iter()
+            .filter(|m| m.reply_to == Some(root))
+            .filter(|m| after.is_none_or(|a| m.message_offset > a.offset))
+            .take(page_size.0 as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn mentions(
+        &self,
+        user_id: UserId,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let state = self.store.state();
+        let mut found: Vec<MessageRecord> = state
+            .conversations
+            .keys()
+            .filter(|id| {
+                state
+                    .members(**id)
+                    .is_some_and(|members| members.contains(&user_id))
+            })
+            .flat_map(|id| state.conversations[id].messages.iter())
+            .filter(|m| m.deleted_at.is_none())
+            .filter(|m| state.mentions.contains(&(user_id, m.message_id)))
+            .filter(|m| {
+                before.is_none_or(|b| (m.created_at, m.message_id) < (b.created_at, b.message_id))
+            })
+            .cloned()
+            .collect();
+        found.sort_by_key(|m| Reverse((m.created_at, m.message_id)));
+        found.truncate(page_size.0 as usize);
+        Ok(found)
+    }
+
+    async fn search_messages(
+        &self,
+        user_id: UserId,
+        query: &str,
+        filters: &SearchFilters,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let terms = search_terms(query)?;
+        let state = self.store.state();
+
+        // whole words, like the full-text index
+        let matches = |content: &str| {
+            let words: Vec<String> = content
+                .split(|c: char| !c.is_alphanumeric())
+                .map(str::to_lowercase)
+                .collect();
+            terms.iter().all(|term| words.contains(term))
+        };
+        let mut found: Vec<MessageRecord> = state
+            .conversations
+            .iter()
+            .filter(|(id, _)| filters.conversation_id.is_none_or(|c| c == **id))
+            .filter(|(id, _)| {
+                state
+                    .members(**id)
+                    .is_some_and(|members| members.contains(&user_id))
+            })
+            .flat_map(|(_, conversation)| conversation.messages.iter())
+            .filter(|m| m.deleted_at.is_none())
+            .filter(|m| filters.sender.is_none_or(|s| m.sender == s))
+            .filter(|m| filters.since.is_none_or(|t| m.created_at >= t))
+            .filter(|m| filters.until.is_none_or(|t| m.created_at < t))
+            .filter(|m| {
+                before.is_none_or(|b| (m.created_at, m.message_id) < (b.created_at, b.message_id))
+            })
+            .filter(|m| matches(m.content.expose()))
+            .cloned()
+            .collect();
+        found.sort_by_key(|m| Reverse((m.created_at, m.message_id)));
+        found.truncate(page_size.0 as usize);
+        Ok(found)
+    }
+
+    async fn recent_conversations(
+        &self,
+        user_id: UserId,
+        page_size: PageSize,
+        after: Option<TimeCursor>,
+    ) -> Result<Vec<RecentConversation>, ChatError> {
+        let state = self.store.state();
+
+        // like the real query: only conversations with at least one message
+        let mut recent: Vec<RecentConversation> = state
+            .conversations
+            .iter()
+            .filter(|(id, _)| {
+                state
+                    .members(**id)
+                    .is_some_and(|members| members.contains(&user_id))
+            })
+            .filter_map(|(id, conversation)| {
+                let last = conversation.messages.last()?;
+                let peer = match conversation.peer {
+                    FakePeer::Direct(a, b) => {
+                        let other_user = if a == user_id { b } else { a };
+                        ConversationPeer::Direct {
+                            other_user,
+                            name: state.username(other_user).unwrap_or_default(),
+                        }
+                    }
+                    FakePeer::Group(group_id) => ConversationPeer::Group {
+                        group_id,
+                        name: state.groups[&group_id].name.clone(),
+                        appearance: state.groups[&group_id].appearance.clone(),
+                    },
+                };
+                let last_read_off = state.last_read_off(user_id, *id);
+                Some(RecentConversation {
+                    conversation_id: *id,
+                    peer,
+                    last_msg_off: last.message_offset,
+                    last_msg_at: Some(last.created_at),
+                    pinned: state.pinned.contains(&(user_id, *id)),
+                    frozen: conversation.frozen,
+                    retention_days: self
+                        .retention
+                        .apply(conversation.retention_days)
+                        .effective_days,
+                    last_read_off,
+                    unread_count: state.unread(user_id, *id),
+                })
+            })
+            .collect();
+
+        recent.sort_by_key(|c| Reverse((c.pinned, c.last_msg_at, c.conversation_id)));
+        Ok(recent
+            .into_iter()
+            .filter(|c| {
+                after.is_none_or(|cur| {
+                    (c.pinned, c.last_msg_at, c.conversation_id)
+                        < (cur.pinned, Some(cur.last_msg_at), cur.conversation_id)
+                })
+            })
+            .take(page_size.0 as usize)
+            .collect())
+    }
+
+    async fn unread_summary(
+        &self,
+        user_id: UserId,
+        top: PageSize,
+    ) -> Result<UnreadSummary, ChatError> {
+        let state = self.store.state();
+
+        let mut unread: Vec<UnreadConversation> = state
+            .conversations
+            .iter()
+            .filter(|(id, _)| {
+                state
+                    .members(**id)
+                    .is_some_and(|members| members.contains(&user_id))
+            })
+            .filter_map(|(id, conversation)| {
+                let last = conversation.messages.last()?;
+                let unread = state.unread(user_id, *id);
+                if unread == 0 {
+                    return None;
+                }
+                Some(UnreadConversation {
+                    conversation_id: *id,
+                    unread,
+                    last_msg_at: Some(last.created_at),
+                })
+            })
+            .collect();
+
+        unread.sort_by_key(|c| Reverse((c.last_msg_at, c.conversation_id)));
+        let total_unread = unread.iter().map(|c| c.unread).sum();
+        unread.truncate(top.0 as usize);
+        Ok(UnreadSummary {
+            total_unread,
+            conversations: unread,
+        })
+    }
+
+    async fn set_frozen(
+        &self,
+        conversation_id: ConversationId,
+        by: Option<UserId>,
+        frozen: bool,
+    ) -> Result<(), ChatError> {
+        let members = {
+            let mut state = self.store.state();
+            let members = state
+                .members(conversation_id)
+                .ok_or(ChatError::ConversationNotFound)?;
+            if let Some(user_id) = by {
+                if !members.contains(&user_id) {
+                    return Err(ChatError::NotMember);
+                }
+                let owns = match state.conversations[&conversation_id].peer {
+                    FakePeer::Group(group_id) => state.groups[&group_id].owner == user_id,
+                    FakePeer::Direct(..) => false,
+                };
+                if !owns {
+                    return Err(ChatError::Forbidden("only the owner can freeze"));
+                }
+            }
+            let Some(conversation) = state.conversations.get_mut(&conversation_id) else {
+                return Err(ChatError::ConversationNotFound);
+            };
+            if conversation.frozen == frozen {
+                return Ok(());
+            }
+            conversation.frozen = frozen;
+            members
+        };
+
+        self.store.publish(
+            EventType::ConversationFrozen,
+            conversation_id.0,
+            members,
+            &S2CEvent::ConversationFrozen(ConversationFrozen {
+                conversation_id,
+                frozen,
+            }),
+        );
+        Ok(())
+    }
+
+    async fn retention(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<ConversationRetention, ChatError> {
+        let state = self.store.state();
+        let members = state
+            .members(conversation_id)
+            .ok_or(ChatError::ConversationNotFound)?;
+        if !members.contains(&user_id) {
+            return Err(ChatError::NotMember);
+        }
+        let days = state.conversations[&conversation_id].retention_days;
+        Ok(self.retention.apply(days))
+    }
+
+    async fn set_retention(
+        &self,
+        conversation_id: ConversationId,
+        by: UserId,
+        days: Option<u32>,
+    ) -> Result<ConversationRetention, ChatError> {
+        let mut state = self.store.state();
+        let members = state
+            .members(conversation_id)
+            .ok_or(ChatError::ConversationNotFound)?;
+        if !members.contains(&by) {
+            return Err(ChatError::NotMember);
+        }
+        let owns = match state.conversations[&conversation_id].peer {
+            FakePeer::Group(group_id) => state.groups[&group_id].owner == by,
+            FakePeer::Direct(..) => false,
+        };
+        if !owns {
+            return Err(ChatError::Forbidden("only the owner can set retention"));
+        }
+        let days = days.map(|days| self.retention.clamp(days));
+        let Some(conversation) = state.conversations.get_mut(&conversation_id) else {
+            return Err(ChatError::ConversationNotFound);
+        };
+        conversation.retention_days = days;
+        Ok(self.retention.apply(days))
+    }
+
+    async fn mark_read(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+    ) -> Result<MessageOffset, ChatError> {
+        let (last_read_off, members) = {
+            let mut state = self.store.state();
+            let members = state
+                .members(conversation_id)
+                .filter(|members| members.contains(&user_id))
+                .ok_or(ChatError::NotMember)?;
+            let newest = state.conversations[&conversation_id]
+                .messages
+                .last()
+                .map_or(MessageOffset(0), |m| m.message_offset);
+            let current = state.last_read_off(user_id, conversation_id);
+            let target = offset.min(newest);
+            if target <= current {
+                return Ok(current);
+            }
+            state
+                .read_offsets
+                .insert((user_id, conversation_id), target);
+            (target, members)
+        };
+
+        self.store.publish(
+            EventType::ChatRead,
+            conversation_id.0,
+            members,
+            &S2CEvent::ChatRead(ChatRead {
+                conversation_id,
+                user_id,
+                last_read_off,
+            }),
+        );
+        Ok(last_read_off)
+    }
+
+    async fn mark_delivered(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+    ) -> Result<(), ChatError> {
+        let (sender, message_offset, delivered_at) = {
+            let mut state = self.store.state();
+            state
+                .members(conversation_id)
+                .filter(|members| members.contains(&user_id))
+                .ok_or(ChatError::NotMember)?;
+            let Some(message) = state.conversations[&conversation_id]
+                .messages
+                .iter()
+                .find(|m| m.message_id == message_id && m.deleted_at.is_none())
+            else {
+                return Ok(());
+            };
+            let (sender, message_offset) = (message.sender, message.message_offset);
+            if sender == user_id {
+                return Ok(());
+            }
+            let deliveries = state.deliveries.entry(message_id).or_default();
+            if deliveries.iter().any(|d| d.user_id == user_id) {
+                return Ok(());
+            }
+            let delivered_at = Utc::now().trunc_subsecs(6);
+            deliveries.push(MessageDelivery {
+                user_id,
+                delivered_at,
+            });
+            (sender, message_offset, delivered_at)
+        };
+
+        self.store.publish(
+            EventType::ChatMessageDelivered,
+            conversation_id.0,
+            vec![sender],
+            &S2CEvent::ChatMessageDelivered(ChatMessageDelivered {
+                conversation_id,
+                message_id,
+                message_offset,
+                user_id,
+                delivered_at,
+            }),
+        );
+        Ok(())
+    }
+
+    async fn deliveries(
+        &self,
+        sender: UserId,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageDelivery>, ChatError> {
+        let state = self.store.state();
+        state
+            .members(conversation_id)
+            .filter(|members| members.contains(&sender))
+            .ok_or(ChatError::NotMember)?;
+        let sent = state.conversations[&conversation_id]
+            .messages
+            .iter()
+            .any(|m| m.message_id == message_id && m.sender == sender);
+        if !sent {
+            return Err(ChatError::MessageNotFound);
+        }
+        Ok(state
+            .deliveries
+            .get(&message_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn set_pinned(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        pinned: bool,
+    ) -> Result<(), ChatError> {
+        let mut state = self.store.state();
+        let is_member = state
+            .members(conversation_id)
+            .is_some_and(|members| members.contains(&user_id));
+        if !is_member {
+            return Err(ChatError::NotMember);
+        }
+
+        if pinned {
+            state.pinned.insert((user_id, conversation_id));
+        } else {
+            state.pinned.remove(&(user_id, conversation_id));
+        }
+        Ok(())
+    }
+
+    async fn members_since(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        since_version: Option<u64>,
+    ) -> Result<MembersDelta, ChatError> {
+        let state = self.store.state();
+        let members = state
+            .versioned_members(conversation_id)
+            .filter(|members| members.iter().any(|(member, _)| *member == user_id))
+            .ok_or(ChatError::NotMember)?;
+
+        let members_version = members.iter().map(|(_, added)| *added).max().unwrap_or(0);
+        let since_version = since_version.filter(|since| *since <= members_version);
+        Ok(MembersDelta {
+            members_version,
+            snapshot: since_version.is_none(),
+            members: members
+                .into_iter()
+                .filter(|(_, added)| since_version.is_none_or(|since| *added > since))
+                .map(|(member, added_version)| ConversationMember {
+                    user_id: member,
+                    username: state.username(member).unwrap_or_default(),
+                    added_version,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Messages are never removed, so a sender's sequence is their count up to
+/// and including the message.
+fn sender_seq(messages: &[MessageRecord], sender: UserId, upto: MessageOffset) -> u64 {
+    messages
+        .iter()
+        .filter(|m| m.sender == sender && m.kind == MessageKind::User && m.message_offset <= upto)
+        .count() as u64
+}
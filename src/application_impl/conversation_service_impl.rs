@@ -1,46 +1,144 @@
 use crate::application_port::*;
 use crate::domain_model::*;
 use crate::domain_port::*;
+use crate::logger::Secret;
+use crate::metrics;
+use chrono::{DateTime, SubsecRound, Utc};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub struct RealConversationService {
     user_repo: Arc<dyn UserRepo>,
     message_repo: Arc<dyn MessageRepo>,
+    delivery_repo: Arc<dyn DeliveryRepo>,
+    attachment_repo: Arc<dyn AttachmentRepo>,
+    offset_allocator: Arc<dyn MessageOffsetAllocator>,
     conversation_repo: Arc<dyn ConversationRepo>,
     conversation_role_repo: Arc<dyn ConversationRoleRepo>,
     outbox_repo: Arc<dyn OutboxRepo>,
     tx_manager: Arc<dyn TxManager>,
+    retention: RetentionPolicy,
 }
 
 impl RealConversationService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_repo: Arc<dyn UserRepo>,
         message_repo: Arc<dyn MessageRepo>,
+        delivery_repo: Arc<dyn DeliveryRepo>,
+        attachment_repo: Arc<dyn AttachmentRepo>,
+        offset_allocator: Arc<dyn MessageOffsetAllocator>,
         conversation_repo: Arc<dyn ConversationRepo>,
         conversation_role_repo: Arc<dyn ConversationRoleRepo>,
         outbox_repo: Arc<dyn OutboxRepo>,
         tx_manager: Arc<dyn TxManager>,
+        retention: RetentionPolicy,
     ) -> Self {
         Self {
             user_repo,
             message_repo,
+            delivery_repo,
+            attachment_repo,
+            offset_allocator,
             conversation_repo,
             conversation_role_repo,
             outbox_repo,
             tx_manager,
+            retention,
         }
     }
-}
 
-#[async_trait::async_trait]
-impl ConversationService for RealConversationService {
-    async fn send_message(
+    /// `Forbidden(why)` unless `user_id` owns the group; direct
+    /// conversations have no roles, so no owner either.
+    async fn require_owner(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+        why: &'static str,
+    ) -> Result<(), ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+        let role = self
+            .conversation_role_repo
+            .get_role_by_conversation_id(user_id, conversation_id)
+            .await;
+        match role {
+            Ok(GroupMemberRole::Owner) => Ok(()),
+            Ok(GroupMemberRole::Member) | Err(RelationError::NotMember) => {
+                Err(ChatError::Forbidden(why))
+            }
+            Err(e) => Err(ChatError::Store(e.to_string())),
+        }
+    }
+
+    async fn check_membership_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<bool, ChatError> {
+        let timer = std::time::Instant::now();
+        let result = self
+            .conversation_role_repo
+            .membership_exists_in_tx(tx, conversation_id, user_id)
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()));
+        observe_membership_check(timer, &result);
+        result
+    }
+
+    /// Read paths check before opening a transaction; a cache hit costs no
+    /// round trip at all.
+    async fn check_membership(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<bool, ChatError> {
+        let timer = std::time::Instant::now();
+        let result = self
+            .conversation_role_repo
+            .membership_exists(conversation_id, user_id)
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()));
+        observe_membership_check(timer, &result);
+        result
+    }
+
+    /// The members `content` mentions, the sender aside. A name that is no
+    /// active user, or someone outside the conversation, stays plain text.
+    async fn resolve_mentions(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        content: &str,
+        sender: UserId,
+        members: &[UserId],
+    ) -> Result<Vec<UserId>, ChatError> {
+        let mut mentioned = Vec::new();
+        for username in mentioned_usernames(content) {
+            match self.user_repo.get_id_by_username_in_tx(tx, &username).await {
+                Ok(user_id) if user_id != sender && members.contains(&user_id) => {
+                    mentioned.push(user_id);
+                }
+                Ok(_) | Err(AuthError::UserNotFound) => {}
+                Err(e) => return Err(ChatError::Store(format!("resolve mention: {e}"))),
+            }
+        }
+        Ok(mentioned)
+    }
+
+    async fn try_send_message(
         &self,
         conversation_id: ConversationId,
         sender: UserId,
         content: &str,
         message_id: MessageId,
-    ) -> Result<MessageRecord, ChatError> {
+        reply_to: Option<MessageId>,
+        attachments: &[AttachmentId],
+    ) -> Result<SentMessage, ChatError> {
+        if attachments.len() > MAX_MESSAGE_ATTACHMENTS {
+            return Err(ChatError::InvalidAttachment);
+        }
         let mut tx = self
             .tx_manager
             .begin()
@@ -48,18 +146,91 @@ impl ConversationService for RealConversationService {
             .map_err(|e| ChatError::Store(e.to_string()))?;
 
         let is_member = self
-            .conversation_role_repo
-            .membership_exists_in_tx(&mut *tx, conversation_id, sender)
-            .await
-            .map_err(|e| ChatError::Store(e.to_string()))?;
+            .check_membership_in_tx(&mut *tx, conversation_id, sender)
+            .await?;
         if !is_member {
             tracing::trace!("membership check failed when sending message");
             return Err(ChatError::NotMember);
         }
+        if self
+            .conversation_repo
+            .is_frozen_in_tx(&mut *tx, conversation_id)
+            .await?
+        {
+            return Err(ChatError::Frozen);
+        }
+        // a resend is answered before it takes an offset, which would move
+        // the conversation's last-message pointer past a message that isn't there
+        if let Some(sent) = self
+            .message_repo
+            .get_sent_in_tx(&mut *tx, message_id)
+            .await?
+        {
+            tx.rollback()
+                .await
+                .map_err(|e| ChatError::Store(e.to_string()))?;
+            return Ok(sent);
+        }
+        if let Some(reply_to) = reply_to {
+            // locked so a delete can't land between the check and the insert
+            let target = self
+                .message_repo
+                .lock_in_tx(&mut *tx, conversation_id, reply_to)
+                .await?;
+            if target.is_none_or(|target| target.deleted_at.is_some()) {
+                return Err(ChatError::InvalidReply);
+            }
+        }
+        let attachments = self
+            .attachment_repo
+            .attach_in_tx(&mut *tx, conversation_id, sender, message_id, attachments)
+            .await
+            .map_err(|e| ChatError::Store(format!("attach files: {e}")))?
+            .ok_or(ChatError::InvalidAttachment)?;
 
-        let record = self
+        // stored with microsecond precision; truncate once so every copy agrees
+        let created_at = Utc::now().trunc_subsecs(6);
+        let message_offset = self
+            .offset_allocator
+            .allocate_in_tx(&mut *tx, conversation_id, created_at)
+            .await?;
+        let sender_seq = self
+            .conversation_repo
+            .next_sender_seq_in_tx(&mut *tx, conversation_id, sender)
+            .await?;
+        let sent = self
             .message_repo
-            .insert_in_tx(&mut *tx, conversation_id, sender, content, message_id)
+            .insert_in_tx(
+                &mut *tx,
+                &MessageRecord {
+                    message_id,
+                    conversation_id,
+                    message_offset,
+                    sender,
+                    content: Secret::new(content.to_owned()),
+                    content_encrypted: false,
+                    created_at,
+                    edited_at: None,
+                    edit_count: 0,
+                    deleted_at: None,
+                    kind: MessageKind::User,
+                    reply_to,
+                    attachments: attachments.iter().map(|a| a.attachment()).collect(),
+                },
+                sender_seq,
+            )
+            .await?;
+        if sent.duplicate {
+            // a concurrent resend got in first and already notified everyone;
+            // rolling back returns the offset and sequence number this one took
+            tx.rollback()
+                .await
+                .map_err(|e| ChatError::Store(e.to_string()))?;
+            return Ok(sent);
+        }
+        let record = &sent.record;
+        self.conversation_repo
+            .advance_read_in_tx(&mut *tx, sender, conversation_id, record.message_offset)
             .await?;
 
         let mut members = self
@@ -67,6 +238,9 @@ impl ConversationService for RealConversationService {
             .get_conversation_member_in_tx(&mut *tx, conversation_id)
             .await
             .map_err(|e| ChatError::Store(format!("query chat members: {e}")))?;
+        let mentioned = self
+            .resolve_mentions(&mut *tx, content, sender, &members)
+            .await?;
         let mut receivers = Vec::with_capacity(members.len());
         for member in members {
             if member != sender {
@@ -90,8 +264,11 @@ impl ConversationService for RealConversationService {
                 message_offset: record.message_offset,
                 content: record.content.clone(),
                 sender: record.sender,
-                username,
+                username: username.clone(),
                 created_at: record.created_at,
+                truncated: false,
+                reply_to: record.reply_to,
+                attachments: record.attachments.clone(),
             }),
         )
         .map_err(|e| ChatError::Store(format!("compose chat.message.new event: {e}")))?;
@@ -100,77 +277,995 @@ impl ConversationService for RealConversationService {
             .await
             .map_err(|e| ChatError::Store(format!("enqueue chat.message.new event: {e}")))?;
 
+        if !mentioned.is_empty() {
+            self.message_repo
+                .insert_mentions_in_tx(&mut *tx, record, &mentioned)
+                .await?;
+            let event = OutboxEvent::new(
+                EventType::ChatMentionNew,
+                Some(conversation_id.0),
+                mentioned,
+                &S2CEvent::ChatMentionNew(ChatMentionNew {
+                    conversation_id: record.conversation_id,
+                    message_id: record.message_id,
+                    message_offset: record.message_offset,
+                    sender: record.sender,
+                    username,
+                    created_at: record.created_at,
+                }),
+            )
+            .map_err(|e| ChatError::Store(format!("compose chat.mention.new event: {e}")))?;
+            self.outbox_repo
+                .enqueue_in_tx(&mut *tx, &event)
+                .await
+                .map_err(|e| ChatError::Store(format!("enqueue chat.mention.new event: {e}")))?;
+        }
+
         tx.commit()
             .await
             .map_err(|e| ChatError::Store(e.to_string()))?;
 
-        Ok(record)
+        // the message is in; failing the send now would only make it resent
+        if let Err(e) = self
+            .offset_allocator
+            .record_sent(
+                conversation_id,
+                sent.record.message_offset,
+                sent.record.created_at,
+            )
+            .await
+        {
+            tracing::warn!("record sent offset for {}: {e}", conversation_id.0);
+        }
+
+        Ok(sent)
     }
 
-    async fn get_history(
+    async fn try_get_history(
         &self,
         user_id: UserId,
         conversation_id: ConversationId,
         page_size: PageSize,
         before: Option<OffsetCursor>,
     ) -> Result<Vec<MessageRecord>, ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
         let mut tx = self
             .tx_manager
             .begin()
             .await
             .map_err(|e| ChatError::Store(e.to_string()))?;
 
-        let ok = self
-            .conversation_role_repo
-            .membership_exists_in_tx(&mut *tx, conversation_id, user_id)
+        // an empty conversation, or a cursor already at the oldest message,
+        // costs one primary-key range instead of a page query
+        let summary = self
+            .message_repo
+            .summarize_in_tx(&mut *tx, conversation_id)
+            .await?;
+        let page = if summary.has_messages_before(before) {
+            self.message_repo
+                .list_before_in_tx(&mut *tx, conversation_id, page_size, before)
+                .await?
+        } else {
+            Vec::new()
+        };
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(page)
+    }
+}
+
+#[async_trait::async_trait]
+impl ConversationService for RealConversationService {
+    async fn send_message(
+        &self,
+        conversation_id: ConversationId,
+        sender: UserId,
+        content: &str,
+        message_id: MessageId,
+        reply_to: Option<MessageId>,
+        attachments: &[AttachmentId],
+    ) -> Result<SentMessage, ChatError> {
+        let result = self
+            .tx_manager
+            .with_retry(move || {
+                self.try_send_message(
+                    conversation_id,
+                    sender,
+                    content,
+                    message_id,
+                    reply_to,
+                    attachments,
+                )
+            })
+            .await;
+        let outcome = match &result {
+            Err(ChatError::NotMember) => "not_member",
+            Err(ChatError::Frozen) => "frozen",
+            Err(ChatError::InvalidReply) => "invalid_reply",
+            Err(ChatError::InvalidAttachment) => "invalid_attachment",
+            other => metrics::outcome(other),
+        };
+        metrics::MESSAGES_SENT.with_label_values(&[outcome]).inc();
+        result
+    }
+
+    async fn edit_message(
+        &self,
+        conversation_id: ConversationId,
+        editor: UserId,
+        message_id: MessageId,
+        new_content: &str,
+    ) -> Result<MessageRecord, ChatError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
             .await
             .map_err(|e| ChatError::Store(e.to_string()))?;
-        if !ok {
+
+        if !self
+            .check_membership_in_tx(&mut *tx, conversation_id, editor)
+            .await?
+        {
             return Err(ChatError::NotMember);
         }
+        if self
+            .conversation_repo
+            .is_frozen_in_tx(&mut *tx, conversation_id)
+            .await?
+        {
+            return Err(ChatError::Frozen);
+        }
 
-        let page = self
+        let record = self
+            .message_repo
+            .lock_in_tx(&mut *tx, conversation_id, message_id)
+            .await?
+            .filter(|record| record.deleted_at.is_none())
+            .ok_or(ChatError::MessageNotFound)?;
+        if record.sender != editor || record.kind != MessageKind::User {
+            return Err(ChatError::Forbidden("only the sender can edit"));
+        }
+        let edited_at = Utc::now().trunc_subsecs(6);
+        let record = MessageRecord {
+            content: Secret::new(new_content.to_owned()),
+            edited_at: Some(edited_at),
+            edit_count: record.edit_count + 1,
+            ..record
+        };
+        self.message_repo.update_in_tx(&mut *tx, &record).await?;
+
+        let members = self
+            .conversation_repo
+            .get_conversation_member_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| ChatError::Store(format!("query chat members: {e}")))?;
+        let event = OutboxEvent::new(
+            EventType::ChatMessageEdited,
+            Some(conversation_id.0),
+            members,
+            &S2CEvent::ChatMessageEdited(ChatMessageEdited {
+                conversation_id,
+                message_id,
+                message_offset: record.message_offset,
+                content: record.content.clone(),
+                edited_at,
+                edit_count: record.edit_count,
+            }),
+        )
+        .map_err(|e| ChatError::Store(format!("compose chat.message.edited event: {e}")))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| ChatError::Store(format!("enqueue chat.message.edited event: {e}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(record)
+    }
+
+    async fn delete_message(
+        &self,
+        conversation_id: ConversationId,
+        deleter: UserId,
+        message_id: MessageId,
+    ) -> Result<(), ChatError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        if !self
+            .check_membership_in_tx(&mut *tx, conversation_id, deleter)
+            .await?
+        {
+            return Err(ChatError::NotMember);
+        }
+
+        let record = self
             .message_repo
-            .list_before_in_tx(&mut *tx, conversation_id, page_size, before)
+            .lock_in_tx(&mut *tx, conversation_id, message_id)
+            .await?
+            .ok_or(ChatError::MessageNotFound)?;
+        if record.deleted_at.is_some() {
+            return Ok(());
+        }
+        if record.sender != deleter {
+            // direct conversations have no roles, so no owner either
+            let role = self
+                .conversation_role_repo
+                .get_role_by_conversation_id(deleter, conversation_id)
+                .await;
+            match role {
+                Ok(GroupMemberRole::Owner) => {}
+                Ok(GroupMemberRole::Member) | Err(RelationError::NotMember) => {
+                    return Err(ChatError::Forbidden(
+                        "only the sender or the owner can delete",
+                    ));
+                }
+                Err(e) => return Err(ChatError::Store(e.to_string())),
+            }
+        }
+        let deleted_at = Utc::now().trunc_subsecs(6);
+        self.message_repo
+            .mark_deleted_in_tx(&mut *tx, conversation_id, record.message_offset, deleted_at)
             .await?;
 
+        let members = self
+            .conversation_repo
+            .get_conversation_member_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| ChatError::Store(format!("query chat members: {e}")))?;
+        let event = OutboxEvent::new(
+            EventType::ChatMessageDeleted,
+            Some(conversation_id.0),
+            members,
+            &S2CEvent::ChatMessageDeleted(ChatMessageDeleted {
+                conversation_id,
+                message_id,
+                message_offset: record.message_offset,
+                deleted_at,
+            }),
+        )
+        .map_err(|e| ChatError::Store(format!("compose chat.message.deleted event: {e}")))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| ChatError::Store(format!("enqueue chat.message.deleted event: {e}")))?;
+
         tx.commit()
             .await
             .map_err(|e| ChatError::Store(e.to_string()))?;
 
-        Ok(page)
+        Ok(())
     }
 
-    async fn recent_conversations(
+    async fn pin_message(
         &self,
-        user_id: UserId,
-        page_size: PageSize,
-        after: Option<TimeCursor>,
-    ) -> std::result::Result<Vec<RecentConversation>, ChatError> {
+        conversation_id: ConversationId,
+        by: UserId,
+        message_id: MessageId,
+        pinned: bool,
+    ) -> Result<(), ChatError> {
         let mut tx = self
             .tx_manager
             .begin()
             .await
             .map_err(|e| ChatError::Store(e.to_string()))?;
 
-        let ids = self
+        if !self
+            .check_membership_in_tx(&mut *tx, conversation_id, by)
+            .await?
+        {
+            return Err(ChatError::NotMember);
+        }
+        if self
             .conversation_repo
-            .list_for_user_recent_in_tx(&mut *tx, user_id, page_size, after)
-            .await?;
-        tracing::trace!("recent conversation ids: {:?}", ids);
+            .is_frozen_in_tx(&mut *tx, conversation_id)
+            .await?
+        {
+            return Err(ChatError::Frozen);
+        }
+        // direct conversations have no roles, so both members may
+        let allowed = self
+            .conversation_role_repo
+            .has_permission_in_tx(&mut *tx, conversation_id, by, PIN_PERMISSION)
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        if allowed == Some(false) {
+            return Err(ChatError::Forbidden("role may not pin messages"));
+        }
 
-        let conversations = if ids.is_empty() {
-            vec![]
+        let record = self
+            .message_repo
+            .lock_in_tx(&mut *tx, conversation_id, message_id)
+            .await?
+            .filter(|record| record.deleted_at.is_none())
+            .ok_or(ChatError::MessageNotFound)?;
+        let at = Utc::now().trunc_subsecs(6);
+        let changed = if pinned {
+            let added = self
+                .message_repo
+                .pin_in_tx(&mut *tx, conversation_id, record.message_offset, by, at)
+                .await?;
+            // counted after the insert, so the rollback undoes it
+            if added
+                && self
+                    .message_repo
+                    .list_pinned_in_tx(&mut *tx, conversation_id)
+                    .await?
+                    .len()
+                    > MAX_PINNED_MESSAGES
+            {
+                return Err(ChatError::TooManyPins);
+            }
+            added
         } else {
-            self.conversation_repo
-                .hydrate_conversation_in_tx(&mut *tx, user_id, ids)
+            self.message_repo
+                .unpin_in_tx(&mut *tx, conversation_id, record.message_offset)
                 .await?
         };
+        if !changed {
+            return Ok(());
+        }
+
+        let members = self
+            .conversation_repo
+            .get_conversation_member_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| ChatError::Store(format!("query chat members: {e}")))?;
+        let event = OutboxEvent::new(
+            EventType::MessagePinned,
+            Some(conversation_id.0),
+            members,
+            &S2CEvent::MessagePinned(MessagePinned {
+                conversation_id,
+                message_id,
+                message_offset: record.message_offset,
+                pinned,
+                by,
+                at,
+            }),
+        )
+        .map_err(|e| ChatError::Store(format!("compose message.pinned event: {e}")))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| ChatError::Store(format!("enqueue message.pinned event: {e}")))?;
 
         tx.commit()
             .await
             .map_err(|e| ChatError::Store(e.to_string()))?;
 
-        Ok(conversations)
+        Ok(())
+    }
+
+    async fn pinned_messages(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<PinnedMessage>, ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let pins = self
+            .message_repo
+            .list_pinned_in_tx(&mut *tx, conversation_id)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(pins)
+    }
+
+    async fn typing_receivers(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<Vec<UserId>, ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let members = self
+            .conversation_repo
+            .get_conversation_member_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| ChatError::Store(format!("query chat members: {e}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(members.into_iter().filter(|m| *m != user_id).collect())
     }
+
+    async fn history_summary(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<HistorySummary, ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let summary = self
+            .message_repo
+            .summarize_in_tx(&mut *tx, conversation_id)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(summary)
+    }
+
+    async fn offset_at(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        at: DateTime<Utc>,
+    ) -> Result<Option<MessageOffset>, ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let offset = self
+            .message_repo
+            .offset_at_in_tx(&mut *tx, conversation_id, at)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(offset)
+    }
+
+    async fn get_history(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        page_size: PageSize,
+        before: Option<OffsetCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let page = self
+            .try_get_history(user_id, conversation_id, page_size, before)
+            .await?;
+        metrics::HISTORY_PAGE_SIZE.observe(page.len() as f64);
+        Ok(page)
+    }
+
+    async fn messages_since(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        after: MessageOffset,
+        page_size: PageSize,
+    ) -> Result<Vec<ChatMessageNew>, ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        let records = self
+            .message_repo
+            .list_after_in_tx(&mut *tx, conversation_id, after, page_size)
+            .await?;
+        // a page usually comes from a handful of senders
+        let mut usernames: HashMap<UserId, String> = HashMap::new();
+        let mut page = Vec::with_capacity(records.len());
+        for record in records {
+            let username = match usernames.get(&record.sender) {
+                Some(username) => username.clone(),
+                None => {
+                    let username = self
+                        .user_repo
+                        .get_username_in_tx(&mut *tx, record.sender)
+                        .await
+                        .map_err(|e| ChatError::Store(format!("query sender username: {e}")))?;
+                    usernames.insert(record.sender, username.clone());
+                    username
+                }
+            };
+            page.push(ChatMessageNew {
+                conversation_id: record.conversation_id,
+                message_id: record.message_id,
+                message_offset: record.message_offset,
+                content: record.content,
+                sender: record.sender,
+                username,
+                created_at: record.created_at,
+                truncated: false,
+                reply_to: record.reply_to,
+                attachments: record.attachments,
+            });
+        }
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(page)
+    }
+
+    async fn thread_history(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        root: MessageId,
+        page_size: PageSize,
+        after: Option<OffsetCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        // the root isn't looked up: replies outlive a deleted or pruned root,
+        // and an unknown one simply has none
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        let page = self
+            .message_repo
+            .list_thread_in_tx(&mut *tx, conversation_id, root, page_size, after)
+            .await?;
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(page)
+    }
+
+    async fn mentions(
+        &self,
+        user_id: UserId,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        // membership is part of the query, like search
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        let page = self
+            .message_repo
+            .list_mentions_in_tx(&mut *tx, user_id, page_size, before)
+            .await?;
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(page)
+    }
+
+    async fn search_messages(
+        &self,
+        user_id: UserId,
+        query: &str,
+        filters: &SearchFilters,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let terms = search_terms(query)?;
+
+        // membership is part of the query: a conversation the user isn't in
+        // simply has no matches
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+        let page = self
+            .message_repo
+            .search_in_tx(&mut *tx, user_id, &terms, filters, page_size, before)
+            .await?;
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(page)
+    }
+
+    async fn recent_conversations(
+        &self,
+        user_id: UserId,
+        page_size: PageSize,
+        after: Option<TimeCursor>,
+    ) -> std::result::Result<Vec<RecentConversation>, ChatError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let ids = self
+            .conversation_repo
+            .list_for_user_recent_in_tx(&mut *tx, user_id, page_size, after)
+            .await?;
+        tracing::trace!("recent conversation ids: {:?}", ids);
+
+        let mut conversations = if ids.is_empty() {
+            vec![]
+        } else {
+            self.conversation_repo
+                .hydrate_conversation_in_tx(&mut *tx, user_id, ids)
+                .await?
+        };
+        // hydrated with the stored override
+        for conversation in &mut conversations {
+            conversation.retention_days = self
+                .retention
+                .apply(conversation.retention_days)
+                .effective_days;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(conversations)
+    }
+
+    async fn unread_summary(
+        &self,
+        user_id: UserId,
+        top: PageSize,
+    ) -> Result<UnreadSummary, ChatError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let summary = self
+            .conversation_repo
+            .unread_summary_in_tx(&mut *tx, user_id, top)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(summary)
+    }
+
+    async fn set_frozen(
+        &self,
+        conversation_id: ConversationId,
+        by: Option<UserId>,
+        frozen: bool,
+    ) -> Result<(), ChatError> {
+        if let Some(user_id) = by {
+            self.require_owner(conversation_id, user_id, "only the owner can freeze")
+                .await?;
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        if !self
+            .conversation_repo
+            .set_frozen_in_tx(&mut *tx, conversation_id, frozen)
+            .await?
+        {
+            return Ok(());
+        }
+
+        let members = self
+            .conversation_repo
+            .get_conversation_member_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| ChatError::Store(format!("query chat members: {e}")))?;
+        let event = OutboxEvent::new(
+            EventType::ConversationFrozen,
+            Some(conversation_id.0),
+            members,
+            &S2CEvent::ConversationFrozen(ConversationFrozen {
+                conversation_id,
+                frozen,
+            }),
+        )
+        .map_err(|e| ChatError::Store(format!("compose conversation.frozen event: {e}")))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| ChatError::Store(format!("enqueue conversation.frozen event: {e}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn retention(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<ConversationRetention, ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let days = self
+            .conversation_repo
+            .retention_days_in_tx(&mut *tx, conversation_id)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(self.retention.apply(days))
+    }
+
+    async fn set_retention(
+        &self,
+        conversation_id: ConversationId,
+        by: UserId,
+        days: Option<u32>,
+    ) -> Result<ConversationRetention, ChatError> {
+        self.require_owner(conversation_id, by, "only the owner can set retention")
+            .await?;
+
+        let days = days.map(|days| self.retention.clamp(days));
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        self.conversation_repo
+            .set_retention_days_in_tx(&mut *tx, conversation_id, days)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(self.retention.apply(days))
+    }
+
+    async fn mark_read(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+    ) -> Result<MessageOffset, ChatError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let mark = self
+            .conversation_repo
+            .mark_read_in_tx(&mut *tx, user_id, conversation_id, offset)
+            .await?;
+        if mark.advanced {
+            let members = self
+                .conversation_repo
+                .get_conversation_member_in_tx(&mut *tx, conversation_id)
+                .await
+                .map_err(|e| ChatError::Store(format!("query chat members: {e}")))?;
+            let event = OutboxEvent::new(
+                EventType::ChatRead,
+                Some(conversation_id.0),
+                members,
+                &S2CEvent::ChatRead(ChatRead {
+                    conversation_id,
+                    user_id,
+                    last_read_off: mark.last_read_off,
+                }),
+            )
+            .map_err(|e| ChatError::Store(format!("compose chat.read event: {e}")))?;
+            self.outbox_repo
+                .enqueue_in_tx(&mut *tx, &event)
+                .await
+                .map_err(|e| ChatError::Store(format!("enqueue chat.read event: {e}")))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(mark.last_read_off)
+    }
+
+    async fn mark_delivered(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+    ) -> Result<(), ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let delivered_at = Utc::now().trunc_subsecs(6);
+        let Some((sender, message_offset)) = self
+            .delivery_repo
+            .insert_in_tx(&mut *tx, conversation_id, message_id, user_id, delivered_at)
+            .await?
+        else {
+            return Ok(());
+        };
+        let event = OutboxEvent::new(
+            EventType::ChatMessageDelivered,
+            Some(conversation_id.0),
+            vec![sender],
+            &S2CEvent::ChatMessageDelivered(ChatMessageDelivered {
+                conversation_id,
+                message_id,
+                message_offset,
+                user_id,
+                delivered_at,
+            }),
+        )
+        .map_err(|e| ChatError::Store(format!("compose chat.message.delivered event: {e}")))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| ChatError::Store(format!("enqueue chat.message.delivered event: {e}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn deliveries(
+        &self,
+        sender: UserId,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageDelivery>, ChatError> {
+        if !self.check_membership(conversation_id, sender).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let deliveries = self
+            .delivery_repo
+            .list_in_tx(&mut *tx, conversation_id, message_id, sender)
+            .await?
+            .ok_or(ChatError::MessageNotFound)?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(deliveries)
+    }
+
+    async fn set_pinned(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        pinned: bool,
+    ) -> Result<(), ChatError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        if !self
+            .conversation_repo
+            .set_pinned_in_tx(&mut *tx, user_id, conversation_id, pinned)
+            .await?
+        {
+            return Err(ChatError::NotMember);
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn members_since(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        since_version: Option<u64>,
+    ) -> Result<MembersDelta, ChatError> {
+        if !self.check_membership(conversation_id, user_id).await? {
+            return Err(ChatError::NotMember);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        let delta = self
+            .conversation_repo
+            .members_since_in_tx(&mut *tx, conversation_id, since_version)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(e.to_string()))?;
+
+        Ok(delta)
+    }
+}
+
+fn observe_membership_check(timer: std::time::Instant, result: &Result<bool, ChatError>) {
+    let outcome = match result {
+        Ok(true) => "member",
+        Ok(false) => "not_member",
+        Err(_) => "error",
+    };
+    metrics::MEMBERSHIP_CHECK_SECONDS
+        .with_label_values(&[outcome])
+        .observe(timer.elapsed().as_secs_f64());
 }
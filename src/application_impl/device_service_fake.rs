@@ -0,0 +1,85 @@
+use crate::application_impl::fake_store::FakeStore;
+use crate::application_port::*;
+use crate::domain_model::*;
+use chrono::{SubsecRound, Utc};
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+/// In-memory `DeviceService`; see `FakeStore`.
+pub struct FakeDeviceService {
+    store: Arc<FakeStore>,
+}
+
+impl FakeDeviceService {
+    pub fn new(store: Arc<FakeStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceService for FakeDeviceService {
+    async fn register(
+        &self,
+        user_id: UserId,
+        registration: DeviceRegistration,
+    ) -> Result<Device, DeviceError> {
+        check_registration(&registration)?;
+        let device = Device {
+            user_id,
+            platform: registration.platform,
+            token: registration.token,
+            locale: registration.locale,
+            registered_at: Utc::now().trunc_subsecs(6),
+        };
+
+        let mut state = self.store.state();
+        let devices = &mut state.devices;
+        devices.retain(|d| d.platform != device.platform || d.token != device.token);
+        devices.push(device.clone());
+
+        // like the real trim: the user's newest are kept
+        let mut own: Vec<&Device> = devices.iter().filter(|d| d.user_id == user_id).collect();
+        own.sort_by_key(|d| Reverse(d.registered_at));
+        if let Some(cutoff) = own.get(MAX_DEVICES_PER_USER).map(|d| d.registered_at) {
+            devices.retain(|d| d.user_id != user_id || d.registered_at > cutoff);
+        }
+
+        Ok(device)
+    }
+
+    async fn unregister(
+        &self,
+        user_id: UserId,
+        platform: PushPlatform,
+        token: &str,
+    ) -> Result<(), DeviceError> {
+        let mut state = self.store.state();
+        let before = state.devices.len();
+        state
+            .devices
+            .retain(|d| d.user_id != user_id || d.platform != platform || d.token != token);
+        if state.devices.len() == before {
+            return Err(DeviceError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn devices_of(&self, user_ids: &[UserId]) -> Result<Vec<Device>, DeviceError> {
+        let state = self.store.state();
+        Ok(state
+            .devices
+            .iter()
+            .filter(|d| user_ids.contains(&d.user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn prune(&self, platform: PushPlatform, tokens: &[String]) -> Result<u64, DeviceError> {
+        let mut state = self.store.state();
+        let before = state.devices.len();
+        state
+            .devices
+            .retain(|d| d.platform != platform || !tokens.contains(&d.token));
+        Ok((before - state.devices.len()) as u64)
+    }
+}
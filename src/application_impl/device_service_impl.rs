@@ -0,0 +1,118 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{SubsecRound, Utc};
+use std::sync::Arc;
+
+pub struct RealDeviceService {
+    device_repo: Arc<dyn DeviceRepo>,
+    tx_manager: Arc<dyn TxManager>,
+}
+
+impl RealDeviceService {
+    pub fn new(device_repo: Arc<dyn DeviceRepo>, tx_manager: Arc<dyn TxManager>) -> Self {
+        Self {
+            device_repo,
+            tx_manager,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceService for RealDeviceService {
+    async fn register(
+        &self,
+        user_id: UserId,
+        registration: DeviceRegistration,
+    ) -> Result<Device, DeviceError> {
+        check_registration(&registration)?;
+        let device = Device {
+            user_id,
+            platform: registration.platform,
+            token: registration.token,
+            locale: registration.locale,
+            registered_at: Utc::now().trunc_subsecs(6),
+        };
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| DeviceError::Store(e.to_string()))?;
+        self.device_repo
+            .upsert_in_tx(&mut *tx, &device)
+            .await
+            .map_err(|e| DeviceError::Store(format!("register device: {e}")))?;
+        self.device_repo
+            .trim_in_tx(&mut *tx, user_id, MAX_DEVICES_PER_USER)
+            .await
+            .map_err(|e| DeviceError::Store(format!("trim devices: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| DeviceError::Store(e.to_string()))?;
+
+        Ok(device)
+    }
+
+    async fn unregister(
+        &self,
+        user_id: UserId,
+        platform: PushPlatform,
+        token: &str,
+    ) -> Result<(), DeviceError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| DeviceError::Store(e.to_string()))?;
+        let deleted = self
+            .device_repo
+            .delete_in_tx(&mut *tx, user_id, platform, token)
+            .await
+            .map_err(|e| DeviceError::Store(format!("unregister device: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| DeviceError::Store(e.to_string()))?;
+
+        if !deleted {
+            return Err(DeviceError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn devices_of(&self, user_ids: &[UserId]) -> Result<Vec<Device>, DeviceError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| DeviceError::Store(e.to_string()))?;
+        let devices = self
+            .device_repo
+            .list_for_users_in_tx(&mut *tx, user_ids)
+            .await
+            .map_err(|e| DeviceError::Store(format!("list devices: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| DeviceError::Store(e.to_string()))?;
+
+        Ok(devices)
+    }
+
+    async fn prune(&self, platform: PushPlatform, tokens: &[String]) -> Result<u64, DeviceError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| DeviceError::Store(e.to_string()))?;
+        let pruned = self
+            .device_repo
+            .delete_tokens_in_tx(&mut *tx, platform, tokens)
+            .await
+            .map_err(|e| DeviceError::Store(format!("prune devices: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| DeviceError::Store(e.to_string()))?;
+
+        Ok(pruned)
+    }
+}
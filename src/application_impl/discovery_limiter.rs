@@ -0,0 +1,53 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Applies `DiscoveryLimits` for both discovery services.
+///
+/// Fails closed, unlike `FriendRequestLimiter`: unmetered lookups are how
+/// hashes get matched against a whole number range.
+pub(crate) struct DiscoveryLimiter {
+    rate_limiter: Arc<dyn RateLimiter>,
+    windows: Vec<RateWindow>,
+}
+
+impl DiscoveryLimiter {
+    pub(crate) fn new(rate_limiter: Arc<dyn RateLimiter>, limits: DiscoveryLimits) -> Self {
+        let windows = [(limits.per_hour, HOUR), (limits.per_day, DAY)]
+            .into_iter()
+            .filter(|(limit, _)| *limit > 0)
+            .map(|(limit, period)| RateWindow { limit, period })
+            .collect();
+        Self {
+            rate_limiter,
+            windows,
+        }
+    }
+
+    pub(crate) async fn acquire(&self, seeker: UserId) -> Result<(), DiscoveryError> {
+        match self
+            .rate_limiter
+            .acquire(&format!("discovery:{seeker}"), &self.windows)
+            .await
+        {
+            Ok(RateDecision::Allowed) => Ok(()),
+            Ok(RateDecision::Limited { reset_at }) => Err(DiscoveryError::RateLimited { reset_at }),
+            Err(e) => Err(DiscoveryError::Store(format!(
+                "check discovery limit: {e:#}"
+            ))),
+        }
+    }
+}
+
+/// Sorted and without duplicates, so repeats neither count nor match twice.
+pub(crate) fn distinct(hashes: &[ContactHash]) -> Vec<ContactHash> {
+    let mut hashes = hashes.to_vec();
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes
+}
@@ -0,0 +1,20 @@
+use crate::application_port::{EventReplayService, ReplayError};
+use crate::domain_port::ReplaySelection;
+
+/// The fake store delivers events as they happen and keeps none, so there is
+/// never anything to replay.
+#[derive(Default)]
+pub struct FakeEventReplayService;
+
+impl FakeEventReplayService {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl EventReplayService for FakeEventReplayService {
+    async fn replay(&self, _selection: ReplaySelection) -> Result<u64, ReplayError> {
+        Ok(0)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::application_port::{EventReplayService, ReplayError};
+use crate::domain_port::{OutboxRepo, ReplaySelection, TxManager};
+use chrono::Utc;
+use std::sync::Arc;
+
+/// Upper bound on ids in one replay request.
+const MAX_REPLAY_IDS: usize = 1000;
+
+pub struct RealEventReplayService {
+    outbox_repo: Arc<dyn OutboxRepo>,
+    tx_manager: Arc<dyn TxManager>,
+}
+
+impl RealEventReplayService {
+    pub fn new(outbox_repo: Arc<dyn OutboxRepo>, tx_manager: Arc<dyn TxManager>) -> Self {
+        Self {
+            outbox_repo,
+            tx_manager,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventReplayService for RealEventReplayService {
+    async fn replay(&self, selection: ReplaySelection) -> Result<u64, ReplayError> {
+        match &selection {
+            ReplaySelection::Events(ids) if ids.is_empty() => {
+                return Err(ReplayError::EmptySelection);
+            }
+            ReplaySelection::Events(ids) if ids.len() > MAX_REPLAY_IDS => {
+                return Err(ReplayError::TooManyEvents(ids.len()));
+            }
+            ReplaySelection::CreatedBetween { from, to } if to <= from => {
+                return Err(ReplayError::InvertedRange);
+            }
+            _ => {}
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ReplayError::Store(e.to_string()))?;
+
+        let requeued = self
+            .outbox_repo
+            .requeue_delivered_in_tx(&mut *tx, &selection, Utc::now())
+            .await
+            .map_err(|e| ReplayError::Store(format!("requeue outbox events: {e}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ReplayError::Store(e.to_string()))?;
+
+        Ok(requeued)
+    }
+}
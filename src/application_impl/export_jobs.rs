@@ -0,0 +1,513 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use aes_gcm::aead::{KeyInit, OsRng, Payload};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use futures_util::stream::{self, BoxStream};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Archives are sealed with the STREAM construction: a random nonce prefix,
+/// then the records in chunks of this many bytes, each sealed on its own.
+const NONCE_PREFIX_LEN: usize = 7;
+const CHUNK_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+const SEALED_CHUNK_LEN: usize = CHUNK_LEN + TAG_LEN;
+const ARCHIVE_CONTENT_TYPE: &str = "application/octet-stream";
+/// A running job saves its progress at least this often, which is also how
+/// other nodes tell it is still alive.
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+/// An unfinished job not saved for this long belonged to a node that went
+/// away; the next request starts over.
+const ABANDONED_AFTER: chrono::Duration = chrono::Duration::minutes(10);
+/// Jobs removed per round by `ExportJobs::expire`.
+const EXPIRE_BATCH: u32 = 100;
+
+/// What the real and fake export services differ in: where the records
+/// come from.
+#[async_trait::async_trait]
+pub(crate) trait ExportSource: Send + Sync + 'static {
+    async fn write_archive(
+        &self,
+        user_id: UserId,
+        archive: &mut ArchiveWriter,
+    ) -> anyhow::Result<()>;
+}
+
+/// What the real and fake export services differ in: where jobs are kept.
+/// Each call stands on its own, as in `ExportJobRepo`.
+#[async_trait::async_trait]
+pub(crate) trait ExportJobStore: Send + Sync + 'static {
+    async fn get(&self, user_id: UserId) -> anyhow::Result<Option<ExportJobRecord>>;
+    async fn insert(&self, job: &ExportJobRecord) -> anyhow::Result<bool>;
+    async fn update_progress(
+        &self,
+        job: ExportJobId,
+        progress: &ExportProgress,
+    ) -> anyhow::Result<bool>;
+    async fn delete(&self, job: ExportJobId) -> anyhow::Result<bool>;
+    async fn take_expired(
+        &self,
+        finished_before: DateTime<Utc>,
+        updated_before: DateTime<Utc>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<ExportJobRecord>>;
+}
+
+/// Seals records into the archive a chunk at a time and uploads them as it
+/// goes, so only the chunk being filled is held; saves the job's progress
+/// along the way, and fails once the job it belongs to has been removed.
+pub(crate) struct ArchiveWriter {
+    job: ExportJobId,
+    blob_key: String,
+    chunk: Vec<u8>,
+    /// `None` once the last chunk is sealed.
+    sealer: Option<EncryptorBE32<Aes256Gcm>>,
+    upload: Box<dyn BlobUpload>,
+    progress: ExportProgress,
+    store: Arc<dyn ExportJobStore>,
+    dirty: bool,
+    saved_at: Instant,
+}
+
+impl ArchiveWriter {
+    async fn new(
+        job: &ExportJobRecord,
+        key: &[u8],
+        store: Arc<dyn ExportJobStore>,
+        mut upload: Box<dyn BlobUpload>,
+    ) -> anyhow::Result<Self> {
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut prefix);
+        let sealer = EncryptorBE32::from_aead(
+            Aes256Gcm::new_from_slice(key)?,
+            GenericArray::from_slice(&prefix),
+        );
+        upload.write(&prefix).await?;
+        Ok(Self {
+            job: job.id(),
+            blob_key: job.blob_key.clone(),
+            chunk: Vec::with_capacity(CHUNK_LEN),
+            sealer: Some(sealer),
+            upload,
+            progress: job.progress.clone(),
+            store,
+            dirty: false,
+            saved_at: Instant::now(),
+        })
+    }
+
+    pub fn stage(&mut self, stage: ExportStage) {
+        if self.progress.stage != stage {
+            self.progress.stage = stage;
+            self.dirty = true;
+        }
+    }
+
+    pub fn messages_total(&mut self, total: u64) {
+        self.progress.messages_total = Some(total);
+        self.dirty = true;
+    }
+
+    pub async fn write(&mut self, record: &ExportRecord) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.chunk, record)?;
+        self.chunk.push(b'\n');
+        // the last chunk is sealed apart, so a full one waits for more
+        while self.chunk.len() > CHUNK_LEN {
+            let rest = self.chunk.split_off(CHUNK_LEN);
+            let full = std::mem::replace(&mut self.chunk, rest);
+            let sealed = self
+                .sealer
+                .as_mut()
+                .ok_or_else(|| anyhow::anyhow!("export archive already finished"))?
+                .encrypt_next(Payload {
+                    msg: &full,
+                    aad: self.blob_key.as_bytes(),
+                })
+                .map_err(|_| anyhow::anyhow!("encrypt export archive"))?;
+            self.upload.write(&sealed).await?;
+        }
+
+        self.progress.records_written += 1;
+        if matches!(record, ExportRecord::Message { .. }) {
+            self.progress.messages_written += 1;
+        }
+        if self.dirty || self.saved_at.elapsed() >= PROGRESS_SAVE_INTERVAL {
+            self.save().await?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> anyhow::Result<()> {
+        let sealer = self
+            .sealer
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("export archive already finished"))?;
+        let sealed = sealer
+            .encrypt_last(Payload {
+                msg: &self.chunk,
+                aad: self.blob_key.as_bytes(),
+            })
+            .map_err(|_| anyhow::anyhow!("encrypt export archive"))?;
+        self.upload.write(&sealed).await?;
+        self.upload.finish().await
+    }
+
+    async fn save(&mut self) -> anyhow::Result<()> {
+        if !self.store.update_progress(self.job, &self.progress).await? {
+            anyhow::bail!("export job was removed");
+        }
+        self.dirty = false;
+        self.saved_at = Instant::now();
+        Ok(())
+    }
+}
+
+fn store_error(context: &str) -> impl Fn(anyhow::Error) -> ExportError + '_ {
+    move |e| ExportError::Store(format!("{context}: {e:#}"))
+}
+
+/// Opens what `ArchiveWriter` sealed a chunk at a time, reading each from the
+/// `BlobStore` as it is asked for, so only the chunk being opened and the one
+/// after it are held. The key it was stored under is bound into every chunk,
+/// so one user's archive can't be served as another's.
+struct ArchiveReader {
+    blobs: Arc<dyn BlobStore>,
+    blob_key: String,
+    /// `None` once the last chunk is opened.
+    opener: Option<DecryptorBE32<Aes256Gcm>>,
+    /// The chunk to open next, still sealed.
+    sealed: Vec<u8>,
+    /// Where the chunk after it starts.
+    offset: u64,
+}
+
+impl ArchiveReader {
+    async fn new(blobs: Arc<dyn BlobStore>, blob_key: String, key: &[u8]) -> anyhow::Result<Self> {
+        let mut head = blobs
+            .get_range(&blob_key, 0, (NONCE_PREFIX_LEN + SEALED_CHUNK_LEN) as u64)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("export archive {blob_key} missing"))?;
+        if head.len() < NONCE_PREFIX_LEN + TAG_LEN {
+            anyhow::bail!("export archive too short");
+        }
+        let offset = head.len() as u64;
+        let sealed = head.split_off(NONCE_PREFIX_LEN);
+        let opener = DecryptorBE32::from_aead(
+            Aes256Gcm::new_from_slice(key)?,
+            GenericArray::from_slice(&head),
+        );
+        Ok(Self {
+            blobs,
+            blob_key,
+            opener: Some(opener),
+            sealed,
+            offset,
+        })
+    }
+
+    /// The next chunk opened; `None` after the last.
+    async fn next(&mut self) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(opener) = self.opener.as_mut() else {
+            return Ok(None);
+        };
+        // a full chunk may still be the last; only what follows it tells
+        let following = if self.sealed.len() < SEALED_CHUNK_LEN {
+            Vec::new()
+        } else {
+            self.blobs
+                .get_range(&self.blob_key, self.offset, SEALED_CHUNK_LEN as u64)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("export archive {} gone", self.blob_key))?
+        };
+        self.offset += following.len() as u64;
+        let sealed = std::mem::replace(&mut self.sealed, following);
+        let payload = Payload {
+            msg: &sealed,
+            aad: self.blob_key.as_bytes(),
+        };
+        let opened = if self.sealed.is_empty() {
+            self.opener
+                .take()
+                .expect("checked above")
+                .decrypt_last(payload)
+        } else {
+            opener.decrypt_next(payload)
+        };
+        opened
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("decrypt export archive"))
+    }
+}
+
+/// Builds the archive for `job` into the `BlobStore`, and returns how far
+/// it got whether or not it succeeded.
+async fn build_archive(
+    job: &ExportJobRecord,
+    key: &[u8],
+    source: Arc<dyn ExportSource>,
+    store: Arc<dyn ExportJobStore>,
+    blobs: Arc<dyn BlobStore>,
+) -> (ExportProgress, anyhow::Result<()>) {
+    let upload = match blobs
+        .start_upload(&job.blob_key, ARCHIVE_CONTENT_TYPE)
+        .await
+    {
+        Ok(upload) => upload,
+        Err(e) => return (job.progress.clone(), Err(e)),
+    };
+    let mut writer = match ArchiveWriter::new(job, key, store, upload).await {
+        Ok(writer) => writer,
+        Err(e) => return (job.progress.clone(), Err(e)),
+    };
+    let mut result = source.write_archive(job.user_id, &mut writer).await;
+    if result.is_ok() {
+        result = writer.finish().await;
+    }
+    if result.is_err() {
+        writer.upload.abort().await;
+    }
+    (writer.progress, result)
+}
+
+/// One export job per user, each run as a spawned task on the node that
+/// started it. Jobs are kept in an `ExportJobStore` and archives in the
+/// `BlobStore`, encrypted with a key of their own that the job keeps
+/// wrapped by the KMS, or as is without one.
+///
+/// Finished archives go `retention` after they are done, and jobs whose node
+/// stopped saving progress are dropped; `expire` removes both and is run on
+/// a timer, and a request that finds either starts over.
+pub(crate) struct ExportJobs {
+    store: Arc<dyn ExportJobStore>,
+    blobs: Arc<dyn BlobStore>,
+    kms: Option<Arc<dyn Kms>>,
+    retention: chrono::Duration,
+}
+
+impl ExportJobs {
+    pub fn new(
+        store: Arc<dyn ExportJobStore>,
+        blobs: Arc<dyn BlobStore>,
+        kms: Option<Arc<dyn Kms>>,
+        retention: Duration,
+    ) -> Self {
+        Self {
+            store,
+            blobs,
+            kms,
+            // longer than chrono can hold: as good as never
+            retention: chrono::Duration::from_std(retention)
+                .unwrap_or(chrono::Duration::days(365 * 100)),
+        }
+    }
+
+    pub async fn status_or_start(
+        &self,
+        user_id: UserId,
+        source: Arc<dyn ExportSource>,
+    ) -> Result<ExportStatus, ExportError> {
+        let job = self
+            .store
+            .get(user_id)
+            .await
+            .map_err(store_error("load export job"))?;
+
+        match job {
+            Some(job) if self.is_expired(&job, Utc::now()) => {
+                self.discard(&job).await?;
+                self.start(user_id, source).await
+            }
+            Some(job) if job.progress.stage == ExportStage::Failed => {
+                self.discard(&job).await?;
+                Ok(ExportStatus {
+                    progress: job.progress,
+                    archive: None,
+                })
+            }
+            Some(job) if job.progress.stage == ExportStage::Ready => {
+                let content = self.read_archive(&job).await?;
+                Ok(ExportStatus {
+                    progress: job.progress,
+                    archive: Some(ExportArchive {
+                        file_name: job.file_name,
+                        content,
+                    }),
+                })
+            }
+            Some(job) => Ok(ExportStatus {
+                progress: job.progress,
+                archive: None,
+            }),
+            None => self.start(user_id, source).await,
+        }
+    }
+
+    pub async fn expire(&self) -> Result<u64, ExportError> {
+        let mut expired = 0;
+        loop {
+            let now = Utc::now();
+            let jobs = self
+                .store
+                .take_expired(now - self.retention, now - ABANDONED_AFTER, EXPIRE_BATCH)
+                .await
+                .map_err(store_error("expire export jobs"))?;
+            for job in &jobs {
+                self.delete_archive(job).await;
+            }
+            expired += jobs.len() as u64;
+            if jobs.len() < EXPIRE_BATCH as usize {
+                return Ok(expired);
+            }
+        }
+    }
+
+    fn is_expired(&self, job: &ExportJobRecord, now: DateTime<Utc>) -> bool {
+        match job.progress.finished_at {
+            Some(at) => now - at >= self.retention,
+            None => now - job.updated_at >= ABANDONED_AFTER,
+        }
+    }
+
+    async fn discard(&self, job: &ExportJobRecord) -> Result<(), ExportError> {
+        let deleted = self
+            .store
+            .delete(job.id())
+            .await
+            .map_err(store_error("delete export job"))?;
+        if deleted {
+            self.delete_archive(job).await;
+        }
+        Ok(())
+    }
+
+    async fn delete_archive(&self, job: &ExportJobRecord) {
+        // unfinished jobs have nothing stored yet; that's no error either
+        if let Err(e) = self.blobs.delete(&job.blob_key).await {
+            tracing::warn!("delete export archive {}: {e:#}", job.blob_key);
+        }
+    }
+
+    async fn read_archive(
+        &self,
+        job: &ExportJobRecord,
+    ) -> Result<BoxStream<'static, Result<Vec<u8>, ExportError>>, ExportError> {
+        let key = match (&job.key_id, &self.kms) {
+            (None, _) => job.archive_key.clone(),
+            (Some(key_id), Some(kms)) => kms
+                .unwrap(&WrappedKey {
+                    key_id: key_id.clone(),
+                    wrapped: job.archive_key.clone(),
+                })
+                .await
+                .map_err(store_error("unwrap export key"))?,
+            (Some(key_id), None) => {
+                return Err(ExportError::Store(format!(
+                    "export key wrapped by {key_id}, but no KMS is configured"
+                )));
+            }
+        };
+        let reader = ArchiveReader::new(self.blobs.clone(), job.blob_key.clone(), &key)
+            .await
+            .map_err(store_error("open export archive"))?;
+        Ok(stream::try_unfold(reader, |mut reader| async move {
+            let chunk = reader.next().await.map_err(|e| {
+                tracing::warn!("read export archive {}: {e:#}", reader.blob_key);
+                store_error("read export archive")(e)
+            })?;
+            Ok(chunk.map(|chunk| (chunk, reader)))
+        })
+        .boxed())
+    }
+
+    async fn start(
+        &self,
+        user_id: UserId,
+        source: Arc<dyn ExportSource>,
+    ) -> Result<ExportStatus, ExportError> {
+        let started_at = Utc::now();
+        let key = Aes256Gcm::generate_key(OsRng).to_vec();
+        let (archive_key, key_id) = match &self.kms {
+            Some(kms) => {
+                let wrapped = kms
+                    .wrap(&key)
+                    .await
+                    .map_err(store_error("wrap export key"))?;
+                (wrapped.wrapped, Some(wrapped.key_id))
+            }
+            None => (key.clone(), None),
+        };
+        let progress = ExportProgress {
+            stage: ExportStage::Queued,
+            records_written: 0,
+            messages_written: 0,
+            messages_total: None,
+            started_at,
+            finished_at: None,
+        };
+        let job = ExportJobRecord {
+            user_id,
+            progress: progress.clone(),
+            updated_at: started_at,
+            file_name: format!("counterpoint-export-{}.jsonl", started_at.format("%Y%m%d")),
+            blob_key: format!("exports/{}/{}", user_id.0, started_at.timestamp_millis()),
+            archive_key,
+            key_id,
+        };
+
+        let inserted = self
+            .store
+            .insert(&job)
+            .await
+            .map_err(store_error("save export job"))?;
+        if !inserted {
+            // another request started one first; report that one
+            let progress = self
+                .store
+                .get(user_id)
+                .await
+                .map_err(store_error("load export job"))?
+                .map_or(progress, |job| job.progress);
+            return Ok(ExportStatus {
+                progress,
+                archive: None,
+            });
+        }
+
+        let store = self.store.clone();
+        let blobs = self.blobs.clone();
+        tokio::spawn(async move {
+            let (mut progress, result) =
+                build_archive(&job, &key, source, store.clone(), blobs.clone()).await;
+            progress.stage = match result {
+                Ok(()) => ExportStage::Ready,
+                Err(e) => {
+                    tracing::warn!("export for [{}] failed: {e:#}", user_id);
+                    ExportStage::Failed
+                }
+            };
+            progress.finished_at = Some(Utc::now());
+            let saved = store.update_progress(job.id(), &progress).await;
+            if !matches!(saved, Ok(true)) || progress.stage == ExportStage::Failed {
+                if let Err(e) = &saved {
+                    tracing::warn!("save export for [{}]: {e:#}", user_id);
+                }
+                // nothing will point at it; a failed save leaves the job to
+                // be dropped as abandoned
+                if let Err(e) = blobs.delete(&job.blob_key).await {
+                    tracing::debug!("delete export archive {}: {e:#}", job.blob_key);
+                }
+            }
+        });
+
+        Ok(ExportStatus {
+            progress,
+            archive: None,
+        })
+    }
+}
@@ -0,0 +1,205 @@
+use crate::application_impl::export_jobs::{
+    ArchiveWriter, ExportJobStore, ExportJobs, ExportSource,
+};
+use crate::application_impl::fake_store::FakeStore;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use crate::infra_memory::MemoryBlobStore;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// In-memory `ExportService`; see `FakeStore`. Archives are kept in a
+/// `MemoryBlobStore` of its own, encrypted without a KMS.
+pub struct FakeExportService {
+    jobs: ExportJobs,
+    source: Arc<FakeExportSource>,
+}
+
+impl FakeExportService {
+    pub fn new(store: Arc<FakeStore>, retention: Duration) -> Self {
+        Self {
+            jobs: ExportJobs::new(
+                Arc::new(FakeExportJobStore {
+                    store: store.clone(),
+                }),
+                Arc::new(MemoryBlobStore::new()),
+                None,
+                retention,
+            ),
+            source: Arc::new(FakeExportSource { store }),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ExportService for FakeExportService {
+    async fn export(&self, user_id: UserId) -> Result<ExportStatus, ExportError> {
+        self.jobs
+            .status_or_start(user_id, self.source.clone())
+            .await
+    }
+
+    async fn expire(&self) -> Result<u64, ExportError> {
+        self.jobs.expire().await
+    }
+}
+
+struct FakeExportJobStore {
+    store: Arc<FakeStore>,
+}
+
+#[async_trait::async_trait]
+impl ExportJobStore for FakeExportJobStore {
+    async fn get(&self, user_id: UserId) -> anyhow::Result<Option<ExportJobRecord>> {
+        Ok(self.store.state().export_jobs.get(&user_id).cloned())
+    }
+
+    async fn insert(&self, job: &ExportJobRecord) -> anyhow::Result<bool> {
+        let mut state = self.store.state();
+        if state.export_jobs.contains_key(&job.user_id) {
+            return Ok(false);
+        }
+        state.export_jobs.insert(job.user_id, job.clone());
+        Ok(true)
+    }
+
+    async fn update_progress(
+        &self,
+        job: ExportJobId,
+        progress: &ExportProgress,
+    ) -> anyhow::Result<bool> {
+        let mut state = self.store.state();
+        match state.export_jobs.get_mut(&job.user_id) {
+            Some(record) if record.id() == job => {
+                record.progress = progress.clone();
+                record.updated_at = Utc::now();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn delete(&self, job: ExportJobId) -> anyhow::Result<bool> {
+        let mut state = self.store.state();
+        if state
+            .export_jobs
+            .get(&job.user_id)
+            .is_some_and(|record| record.id() == job)
+        {
+            state.export_jobs.remove(&job.user_id);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    async fn take_expired(
+        &self,
+        finished_before: DateTime<Utc>,
+        updated_before: DateTime<Utc>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<ExportJobRecord>> {
+        let mut state = self.store.state();
+        let expired: Vec<UserId> = state
+            .export_jobs
+            .values()
+            .filter(|job| match job.progress.finished_at {
+                Some(at) => at < finished_before,
+                None => job.updated_at < updated_before,
+            })
+            .map(|job| job.user_id)
+            .take(limit as usize)
+            .collect();
+        Ok(expired
+            .into_iter()
+            .filter_map(|user_id| state.export_jobs.remove(&user_id))
+            .collect())
+    }
+}
+
+struct FakeExportSource {
+    store: Arc<FakeStore>,
+}
+
+impl FakeExportSource {
+    /// Everything at once, so the lock isn't held across writes.
+    fn collect(&self, user_id: UserId) -> anyhow::Result<Vec<ExportRecord>> {
+        let state = self.store.state();
+        let username = state.username(user_id).ok_or(AuthError::UserNotFound)?;
+        // fake users don't record when they signed up
+        let mut records = vec![ExportRecord::Profile {
+            user_id,
+            username,
+            created_at: None,
+            exported_at: Utc::now(),
+        }];
+
+        for ((a, b), friendship) in &state.friendships {
+            let other = match (*a == user_id, *b == user_id) {
+                (true, _) => *b,
+                (_, true) => *a,
+                _ => continue,
+            };
+            if let Some(username) = state.username(other) {
+                records.push(ExportRecord::Friend {
+                    user_id: other,
+                    username,
+                    conversation_id: friendship.conversation_id,
+                    since: friendship.since,
+                });
+            }
+        }
+
+        for (group_id, group) in &state.groups {
+            if group.disbanded || !group.members.iter().any(|(m, _)| *m == user_id) {
+                continue;
+            }
+            records.push(ExportRecord::Group {
+                group_id: *group_id,
+                name: group.name.clone(),
+                owner: group.owner == user_id,
+                conversation_id: group.conversation_id,
+                created_at: group.created_at,
+            });
+        }
+
+        let mut messages: Vec<&MessageRecord> = state
+            .conversations
+            .values()
+            .flat_map(|conversation| &conversation.messages)
+            .filter(|message| message.sender == user_id)
+            .collect();
+        messages.sort_by_key(|message| (message.created_at, message.message_id));
+        records.extend(messages.into_iter().cloned().map(ExportRecord::from));
+
+        Ok(records)
+    }
+}
+
+#[async_trait::async_trait]
+impl ExportSource for FakeExportSource {
+    async fn write_archive(
+        &self,
+        user_id: UserId,
+        archive: &mut ArchiveWriter,
+    ) -> anyhow::Result<()> {
+        let records = self.collect(user_id)?;
+        let messages = records
+            .iter()
+            .filter(|record| matches!(record, ExportRecord::Message { .. }))
+            .count();
+        archive.messages_total(messages as u64);
+
+        for record in &records {
+            archive.stage(match record {
+                ExportRecord::Profile { .. } => ExportStage::Profile,
+                ExportRecord::Friend { .. } => ExportStage::Friends,
+                ExportRecord::Group { .. } => ExportStage::Groups,
+                ExportRecord::Message { .. } => ExportStage::Messages,
+            });
+            archive.write(record).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use crate::application_impl::export_jobs::{
+    ArchiveWriter, ExportJobStore, ExportJobs, ExportSource,
+};
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Friends and groups are read a page at a time; messages are streamed.
+const EXPORT_PAGE: PageSize = PageSize(200);
+
+pub struct RealExportService {
+    jobs: ExportJobs,
+    source: Arc<RepoExportSource>,
+}
+
+impl RealExportService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_repo: Arc<dyn UserRepo>,
+        friendship_repo: Arc<dyn FriendshipRepo>,
+        group_repo: Arc<dyn GroupRepo>,
+        message_repo: Arc<dyn MessageRepo>,
+        export_job_repo: Arc<dyn ExportJobRepo>,
+        tx_manager: Arc<dyn TxManager>,
+        blob_store: Arc<dyn BlobStore>,
+        kms: Option<Arc<dyn Kms>>,
+        retention: Duration,
+    ) -> Self {
+        Self {
+            jobs: ExportJobs::new(
+                Arc::new(RepoExportJobStore {
+                    export_job_repo,
+                    tx_manager: tx_manager.clone(),
+                }),
+                blob_store,
+                kms,
+                retention,
+            ),
+            source: Arc::new(RepoExportSource {
+                user_repo,
+                friendship_repo,
+                group_repo,
+                message_repo,
+                tx_manager,
+            }),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ExportService for RealExportService {
+    async fn export(&self, user_id: UserId) -> Result<ExportStatus, ExportError> {
+        self.jobs
+            .status_or_start(user_id, self.source.clone())
+            .await
+    }
+
+    async fn expire(&self) -> Result<u64, ExportError> {
+        self.jobs.expire().await
+    }
+}
+
+struct RepoExportJobStore {
+    export_job_repo: Arc<dyn ExportJobRepo>,
+    tx_manager: Arc<dyn TxManager>,
+}
+
+#[async_trait::async_trait]
+impl ExportJobStore for RepoExportJobStore {
+    async fn get(&self, user_id: UserId) -> anyhow::Result<Option<ExportJobRecord>> {
+        let mut tx = self.tx_manager.begin().await?;
+        let job = self.export_job_repo.get_in_tx(&mut *tx, user_id).await?;
+        tx.commit().await?;
+        Ok(job)
+    }
+
+    async fn insert(&self, job: &ExportJobRecord) -> anyhow::Result<bool> {
+        let mut tx = self.tx_manager.begin().await?;
+        let inserted = self.export_job_repo.insert_in_tx(&mut *tx, job).await?;
+        tx.commit().await?;
+        Ok(inserted)
+    }
+
+    async fn update_progress(
+        &self,
+        job: ExportJobId,
+        progress: &ExportProgress,
+    ) -> anyhow::Result<bool> {
+        let mut tx = self.tx_manager.begin().await?;
+        let updated = self
+            .export_job_repo
+            .update_progress_in_tx(&mut *tx, job, progress)
+            .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    async fn delete(&self, job: ExportJobId) -> anyhow::Result<bool> {
+        let mut tx = self.tx_manager.begin().await?;
+        let deleted = self.export_job_repo.delete_in_tx(&mut *tx, job).await?;
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    async fn take_expired(
+        &self,
+        finished_before: DateTime<Utc>,
+        updated_before: DateTime<Utc>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<ExportJobRecord>> {
+        let mut tx = self.tx_manager.begin().await?;
+        let expired = self
+            .export_job_repo
+            .delete_expired_in_tx(&mut *tx, finished_before, updated_before, limit)
+            .await?;
+        tx.commit().await?;
+        Ok(expired)
+    }
+}
+
+struct RepoExportSource {
+    user_repo: Arc<dyn UserRepo>,
+    friendship_repo: Arc<dyn FriendshipRepo>,
+    group_repo: Arc<dyn GroupRepo>,
+    message_repo: Arc<dyn MessageRepo>,
+    tx_manager: Arc<dyn TxManager>,
+}
+
+impl RepoExportSource {
+    async fn write_profile(
+        &self,
+        user_id: UserId,
+        archive: &mut ArchiveWriter,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.tx_manager.begin().await?;
+        let user = self.user_repo.get_in_tx(&mut *tx, user_id).await?;
+        tx.commit().await?;
+
+        let user = user.ok_or(AuthError::UserNotFound)?;
+        archive
+            .write(&ExportRecord::Profile {
+                user_id,
+                username: user.username,
+                created_at: Some(user.created_at),
+                exported_at: Utc::now(),
+            })
+            .await
+    }
+
+    async fn write_friends(
+        &self,
+        user_id: UserId,
+        archive: &mut ArchiveWriter,
+    ) -> anyhow::Result<()> {
+        let mut after = None;
+        loop {
+            let page = self
+                .friendship_repo
+                .list_friends_with_conversations(user_id, EXPORT_PAGE, after)
+                .await?;
+            let full = page.len() >= EXPORT_PAGE.0 as usize;
+            after = page.last().map(|friend| FriendCursor {
+                since: friend.since,
+                other_user: friend.user_id,
+            });
+            for friend in page {
+                archive.write(&friend.into()).await?;
+            }
+            if !full {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn write_groups(
+        &self,
+        user_id: UserId,
+        archive: &mut ArchiveWriter,
+    ) -> anyhow::Result<()> {
+        let mut after = None;
+        loop {
+            let page = self
+                .group_repo
+                .list_groups(user_id, EXPORT_PAGE, after, GroupProjection::Lite)
+                .await?;
+            let full = page.len() >= EXPORT_PAGE.0 as usize;
+            after = page.last().map(|group| GroupCursor {
+                created_at: group.created_at,
+                group_id: group.group_id,
+            });
+            for group in page {
+                archive.write(&group.into()).await?;
+            }
+            if !full {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn write_messages(
+        &self,
+        user_id: UserId,
+        archive: &mut ArchiveWriter,
+    ) -> anyhow::Result<()> {
+        archive.messages_total(self.message_repo.count_by_sender(user_id).await?);
+
+        let mut messages = self.message_repo.stream_by_sender(user_id);
+        while let Some(message) = messages.try_next().await? {
+            archive.write(&message.into()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ExportSource for RepoExportSource {
+    async fn write_archive(
+        &self,
+        user_id: UserId,
+        archive: &mut ArchiveWriter,
+    ) -> anyhow::Result<()> {
+        archive.stage(ExportStage::Profile);
+        self.write_profile(user_id, archive).await?;
+        archive.stage(ExportStage::Friends);
+        self.write_friends(user_id, archive).await?;
+        archive.stage(ExportStage::Groups);
+        self.write_groups(user_id, archive).await?;
+        archive.stage(ExportStage::Messages);
+        self.write_messages(user_id, archive).await
+    }
+}
@@ -0,0 +1,235 @@
+use crate::domain_model::*;
+use crate::domain_port::{EventType, ExportJobRecord, OutboxEvent};
+use crate::logger::Secret;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::mpsc;
+
+pub(crate) struct FakeUser {
+    pub username: String,
+    pub password: Secret<String>,
+    pub active: bool,
+    pub legal_hold: bool,
+    pub is_bot: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+pub(crate) enum FakePeer {
+    Direct(UserId, UserId),
+    Group(GroupId),
+}
+
+pub(crate) struct FakeConversation {
+    pub peer: FakePeer,
+    /// In offset order; offsets start at 1 and only imports leave gaps.
+    pub messages: Vec<MessageRecord>,
+    pub deleted: bool,
+    pub frozen: bool,
+    /// The owner's override, already clamped.
+    pub retention_days: Option<u32>,
+}
+
+pub(crate) struct FakeGroup {
+    pub name: String,
+    pub owner: UserId,
+    pub conversation_id: ConversationId,
+    pub created_at: DateTime<Utc>,
+    pub members: Vec<(UserId, DateTime<Utc>)>,
+    pub disbanded: bool,
+    pub appearance: GroupAppearance,
+}
+
+pub(crate) struct FakeFriendship {
+    pub conversation_id: ConversationId,
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Default)]
+pub(crate) struct FakeState {
+    pub users: HashMap<UserId, FakeUser>,
+    pub usernames: HashMap<String, UserId>,
+    /// Keyed by the ordered pair, like the `friendship` table.
+    pub friendships: HashMap<(UserId, UserId), FakeFriendship>,
+    pub friend_keys: HashMap<(UserId, IdempotencyKey), ConversationId>,
+    pub groups: HashMap<GroupId, FakeGroup>,
+    pub group_keys: HashMap<(UserId, IdempotencyKey), (GroupId, ConversationId)>,
+    pub conversations: HashMap<ConversationId, FakeConversation>,
+    pub pinned: HashSet<(UserId, ConversationId)>,
+    /// Who each message mentions, as (mentioned, message).
+    pub mentions: HashSet<(UserId, MessageId)>,
+    /// Each conversation's pinned messages, who pinned them and when; oldest
+    /// pin first.
+    pub pinned_messages: HashMap<ConversationId, Vec<(MessageId, UserId, DateTime<Utc>)>>,
+    /// Who got each message, earliest first.
+    pub deliveries: HashMap<MessageId, Vec<MessageDelivery>>,
+    /// Each member's `last_read_off`; missing means nothing read yet.
+    pub read_offsets: HashMap<(UserId, ConversationId), MessageOffset>,
+    /// Keyed by `ConversationMeta::key`, so listing comes out ordered.
+    pub meta: HashMap<ConversationId, BTreeMap<String, ConversationMeta>>,
+    /// Keyed by `InviteCode::code`.
+    pub invites: HashMap<String, InviteCode>,
+    /// `coarse_location`s each user logged in from.
+    pub login_locations: HashMap<UserId, HashSet<String>>,
+    /// Uploaded files' metadata; the content is in the fake `BlobStore`.
+    pub attachments: HashMap<AttachmentId, AttachmentRecord>,
+    /// Identifiers each discoverable user can be found by.
+    pub contact_hashes: HashMap<UserId, Vec<ContactHash>>,
+    /// Registered for push, one per platform and token.
+    pub devices: Vec<Device>,
+    /// Outbound webhooks and their secrets, oldest first.
+    pub webhooks: Vec<(Webhook, String)>,
+    /// Incoming webhooks and the hashes of their tokens, oldest first.
+    pub incoming_webhooks: Vec<(IncomingWebhook, String)>,
+    /// Account exports; the archives are in the fake `BlobStore`.
+    pub export_jobs: HashMap<UserId, ExportJobRecord>,
+}
+
+impl FakeState {
+    pub fn username(&self, user_id: UserId) -> Option<String> {
+        self.users.get(&user_id).map(|u| u.username.clone())
+    }
+
+    pub fn is_active(&self, user_id: UserId) -> bool {
+        self.users.get(&user_id).is_some_and(|u| u.active)
+    }
+
+    pub fn last_read_off(&self, user_id: UserId, conversation_id: ConversationId) -> MessageOffset {
+        self.read_offsets
+            .get(&(user_id, conversation_id))
+            .copied()
+            .unwrap_or(MessageOffset(0))
+    }
+
+    /// Counted like the real store: live messages from others past the read
+    /// position, at most `MAX_UNREAD_COUNT`.
+    pub fn unread(&self, user_id: UserId, conversation_id: ConversationId) -> u64 {
+        let last_read_off = self.last_read_off(user_id, conversation_id);
+        self.conversations
+            .get(&conversation_id)
+            .map_or(0, |conversation| {
+                conversation
+                    .messages
+                    .iter()
+                    .filter(|m| {
+                        m.message_offset > last_read_off
+                            && m.sender != user_id
+                            && m.deleted_at.is_none()
+                    })
+                    .take(MAX_UNREAD_COUNT as usize)
+                    .count() as u64
+            })
+    }
+
+    /// The direct conversation between `a` and `b`, whether or not they are
+    /// friends.
+    pub fn direct_conversation(&self, a: UserId, b: UserId) -> Option<ConversationId> {
+        self.conversations
+            .iter()
+            .find(|(_, conversation)| {
+                matches!(conversation.peer, FakePeer::Direct(x, y) if ordered(x, y) == ordered(a, b))
+            })
+            .map(|(conversation_id, _)| *conversation_id)
+    }
+
+    /// Members of a live conversation; `None` if it doesn't exist or was deleted.
+    pub fn members(&self, conversation_id: ConversationId) -> Option<Vec<UserId>> {
+        let conversation = self.conversations.get(&conversation_id)?;
+        if conversation.deleted {
+            return None;
+        }
+        match conversation.peer {
+            FakePeer::Direct(a, b) => Some(vec![a, b]),
+            FakePeer::Group(group_id) => self
+                .groups
+                .get(&group_id)
+                .map(|g| g.members.iter().map(|(user_id, _)| *user_id).collect()),
+        }
+    }
+
+    /// `members` with the `members_version` that added each. Groups gain one
+    /// member per change and a direct conversation starts with both, so the
+    /// versions follow from the order.
+    pub fn versioned_members(&self, conversation_id: ConversationId) -> Option<Vec<(UserId, u64)>> {
+        let members = self.members(conversation_id)?;
+        let direct = matches!(
+            self.conversations.get(&conversation_id)?.peer,
+            FakePeer::Direct(..)
+        );
+        Some(
+            members
+                .into_iter()
+                .enumerate()
+                .map(|(i, user_id)| (user_id, if direct { 1 } else { i as u64 + 1 }))
+                .collect(),
+        )
+    }
+}
+
+/// The state shared by the in-memory services of `storage.backend = "fake"`.
+///
+/// Events the real services would put in the outbox are handed to the
+/// receiver returned by `new`; the server feeds them straight to fan-out.
+pub struct FakeStore {
+    state: Mutex<FakeState>,
+    events: mpsc::UnboundedSender<OutboxEvent>,
+}
+
+impl FakeStore {
+    pub fn new() -> (Arc<Self>, mpsc::UnboundedReceiver<OutboxEvent>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+        let store = Arc::new(Self {
+            state: Mutex::new(FakeState::default()),
+            events,
+        });
+        (store, receiver)
+    }
+
+    pub(crate) fn state(&self) -> MutexGuard<'_, FakeState> {
+        // a panic mid-update leaves nothing worth protecting in a fake
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn publish(
+        &self,
+        event_type: EventType,
+        partition_key: uuid::Uuid,
+        receivers: Vec<UserId>,
+        event: &S2CEvent,
+    ) {
+        if receivers.is_empty() {
+            return;
+        }
+        match OutboxEvent::new(event_type, Some(partition_key), receivers, event) {
+            Ok(event) => self.send(event),
+            Err(e) => tracing::warn!("fake store: compose event: {e}"),
+        }
+    }
+
+    /// Hands over an event as composed, even one with no receivers; one with
+    /// a `publish_at` still ahead is held until then, as the outbox would.
+    pub(crate) fn send(&self, event: OutboxEvent) {
+        let delay = event
+            .publish_at
+            .and_then(|at| (at - Utc::now()).to_std().ok());
+        let events = self.events.clone();
+        let send = move || {
+            if events.send(event).is_err() {
+                tracing::debug!("fake store: no one is listening for events");
+            }
+        };
+        match delay {
+            Some(delay) => {
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    send();
+                });
+            }
+            None => send(),
+        }
+    }
+}
+
+pub(crate) fn ordered(a: UserId, b: UserId) -> (UserId, UserId) {
+    if a < b { (a, b) } else { (b, a) }
+}
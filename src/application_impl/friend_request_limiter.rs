@@ -0,0 +1,61 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Applies `FriendRequestLimits` for both relationship services.
+///
+/// Fails open: when the counters can't be reached, requests go through
+/// rather than friendships stalling on them.
+pub(crate) struct FriendRequestLimiter {
+    rate_limiter: Arc<dyn RateLimiter>,
+    windows: Vec<RateWindow>,
+}
+
+impl FriendRequestLimiter {
+    pub(crate) fn new(rate_limiter: Arc<dyn RateLimiter>, limits: FriendRequestLimits) -> Self {
+        let windows = [(limits.per_hour, HOUR), (limits.per_day, DAY)]
+            .into_iter()
+            .filter(|(limit, _)| *limit > 0)
+            .map(|(limit, period)| RateWindow { limit, period })
+            .collect();
+        Self {
+            rate_limiter,
+            windows,
+        }
+    }
+
+    fn subject(sender: UserId) -> String {
+        format!("friend:{sender}")
+    }
+
+    pub(crate) async fn acquire(&self, sender: UserId) -> Result<(), RelationError> {
+        match self
+            .rate_limiter
+            .acquire(&Self::subject(sender), &self.windows)
+            .await
+        {
+            Ok(RateDecision::Allowed) => Ok(()),
+            Ok(RateDecision::Limited { reset_at }) => Err(RelationError::RateLimited { reset_at }),
+            Err(e) => {
+                tracing::warn!("friend request limit unchecked for [{sender}]: {e:#}");
+                Ok(())
+            }
+        }
+    }
+
+    /// For a request that turned out to be a retry or between friends.
+    pub(crate) async fn release(&self, sender: UserId) {
+        if let Err(e) = self
+            .rate_limiter
+            .release(&Self::subject(sender), &self.windows)
+            .await
+        {
+            tracing::warn!("friend request limit not refunded for [{sender}]: {e:#}");
+        }
+    }
+}
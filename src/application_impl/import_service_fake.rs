@@ -0,0 +1,157 @@
+use crate::application_impl::fake_store::{
+    FakeConversation, FakeFriendship, FakeGroup, FakePeer, FakeStore, FakeUser, ordered,
+};
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::logger::Secret;
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+
+/// In-memory `ImportService`; see `FakeStore`. The fake keeps passwords in
+/// the clear and cannot check an Argon2 hash, so imported users get a
+/// random password nobody knows.
+pub struct FakeImportService {
+    store: Arc<FakeStore>,
+}
+
+impl FakeImportService {
+    pub fn new(store: Arc<FakeStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImportService for FakeImportService {
+    async fn import(&self, batch: ImportBatch) -> Result<ImportReport, ImportError> {
+        check_import_batch(&batch)?;
+
+        let mut state = self.store.state();
+        let mut report = ImportReport::default();
+
+        let mut users = 0;
+        for user in &batch.users {
+            if state.users.contains_key(&user.user_id)
+                || state.usernames.contains_key(&user.username)
+            {
+                continue;
+            }
+            state.users.insert(
+                user.user_id,
+                FakeUser {
+                    username: user.username.clone(),
+                    password: Secret::new(uuid::Uuid::new_v4().to_string()),
+                    active: true,
+                    legal_hold: false,
+                    is_bot: false,
+                    created_at: user.created_at,
+                },
+            );
+            state.usernames.insert(user.username.clone(), user.user_id);
+            users += 1;
+        }
+        report.users = ImportCount::of(batch.users.len(), users);
+
+        let mut friendships = 0;
+        for friendship in &batch.friendships {
+            let pair = ordered(friendship.a, friendship.b);
+            if !state.users.contains_key(&pair.0)
+                || !state.users.contains_key(&pair.1)
+                || state.friendships.contains_key(&pair)
+                || state
+                    .conversations
+                    .contains_key(&friendship.conversation_id)
+            {
+                continue;
+            }
+            state.friendships.insert(
+                pair,
+                FakeFriendship {
+                    conversation_id: friendship.conversation_id,
+                    since: friendship.since,
+                },
+            );
+            state.conversations.insert(
+                friendship.conversation_id,
+                FakeConversation {
+                    peer: FakePeer::Direct(pair.0, pair.1),
+                    messages: Vec::new(),
+                    deleted: false,
+                    frozen: false,
+                    retention_days: None,
+                },
+            );
+            friendships += 1;
+        }
+        report.friendships = ImportCount::of(batch.friendships.len(), friendships);
+
+        let mut groups = 0;
+        for group in &batch.groups {
+            if !state.users.contains_key(&group.owner)
+                || state.groups.contains_key(&group.group_id)
+                || state.conversations.contains_key(&group.conversation_id)
+            {
+                continue;
+            }
+            let mut members = vec![(group.owner, group.created_at)];
+            for user_id in &group.members {
+                if state.users.contains_key(user_id) && members.iter().all(|(m, _)| m != user_id) {
+                    members.push((*user_id, group.created_at));
+                }
+            }
+            state.groups.insert(
+                group.group_id,
+                FakeGroup {
+                    name: group.name.clone(),
+                    owner: group.owner,
+                    conversation_id: group.conversation_id,
+                    created_at: group.created_at,
+                    members,
+                    disbanded: false,
+                    appearance: GroupAppearance::default(),
+                },
+            );
+            state.conversations.insert(
+                group.conversation_id,
+                FakeConversation {
+                    peer: FakePeer::Group(group.group_id),
+                    messages: Vec::new(),
+                    deleted: false,
+                    frozen: false,
+                    retention_days: None,
+                },
+            );
+            groups += 1;
+        }
+        report.groups = ImportCount::of(batch.groups.len(), groups);
+
+        let mut messages = 0;
+        for message in &batch.messages {
+            let taken = state.conversations.values().any(|c| {
+                c.messages
+                    .iter()
+                    .any(|m| m.message_id == message.message_id)
+            });
+            if taken || !state.users.contains_key(&message.sender) {
+                continue;
+            }
+            let Entry::Occupied(mut conversation) =
+                state.conversations.entry(message.conversation_id)
+            else {
+                continue;
+            };
+            let history = &mut conversation.get_mut().messages;
+            let at = history.partition_point(|m| m.message_offset < message.message_offset);
+            if history
+                .get(at)
+                .is_some_and(|m| m.message_offset == message.message_offset)
+            {
+                continue;
+            }
+            history.insert(at, message.clone().into());
+            messages += 1;
+        }
+        report.messages = ImportCount::of(batch.messages.len(), messages);
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,165 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+pub struct RealImportService {
+    import_repo: Arc<dyn ImportRepo>,
+    message_repo: Arc<dyn MessageRepo>,
+    conversation_role_repo: Arc<dyn ConversationRoleRepo>,
+    tx_manager: Arc<dyn TxManager>,
+}
+
+impl RealImportService {
+    pub fn new(
+        import_repo: Arc<dyn ImportRepo>,
+        message_repo: Arc<dyn MessageRepo>,
+        conversation_role_repo: Arc<dyn ConversationRoleRepo>,
+        tx_manager: Arc<dyn TxManager>,
+    ) -> Self {
+        Self {
+            import_repo,
+            message_repo,
+            conversation_role_repo,
+            tx_manager,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImportService for RealImportService {
+    async fn import(&self, batch: ImportBatch) -> Result<ImportReport, ImportError> {
+        check_import_batch(&batch)?;
+
+        // one transaction per batch, so a failed batch can simply be resent
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| ImportError::Store(e.to_string()))?;
+        let mut report = ImportReport::default();
+
+        let users = self
+            .import_repo
+            .insert_users_in_tx(&mut *tx, &batch.users)
+            .await?;
+        report.users = ImportCount::of(batch.users.len(), users);
+
+        // rows naming a missing user would only half insert, so they are
+        // dropped here rather than left to the foreign keys
+        let referenced: Vec<UserId> = batch
+            .friendships
+            .iter()
+            .flat_map(|f| [f.a, f.b])
+            .chain(
+                batch
+                    .groups
+                    .iter()
+                    .flat_map(|g| std::iter::once(g.owner).chain(g.members.iter().copied())),
+            )
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let known = self
+            .import_repo
+            .existing_users_in_tx(&mut *tx, &referenced)
+            .await?;
+
+        let friendships: Vec<ImportFriendship> = batch
+            .friendships
+            .iter()
+            .filter(|f| known.contains(&f.a) && known.contains(&f.b))
+            .cloned()
+            .collect();
+        let created = self
+            .import_repo
+            .insert_friendships_in_tx(&mut *tx, &friendships)
+            .await?;
+        report.friendships = ImportCount::of(batch.friendships.len(), created);
+
+        let groups: Vec<ImportGroup> = batch
+            .groups
+            .iter()
+            .filter(|g| known.contains(&g.owner))
+            .map(|g| ImportGroup {
+                members: g
+                    .members
+                    .iter()
+                    .copied()
+                    .filter(|user_id| known.contains(user_id) && *user_id != g.owner)
+                    .collect(),
+                ..g.clone()
+            })
+            .collect();
+        let created = self
+            .import_repo
+            .insert_groups_in_tx(&mut *tx, &groups)
+            .await?;
+        report.groups = ImportCount::of(batch.groups.len(), created.len() as u64);
+        let created: HashSet<GroupId> = created.into_iter().collect();
+        for group in groups.iter().filter(|g| created.contains(&g.group_id)) {
+            let roles = async {
+                self.conversation_role_repo
+                    .ensure_defaults_in_tx(&mut *tx, group.conversation_id)
+                    .await?;
+                self.conversation_role_repo
+                    .assign_role_by_name_in_tx(
+                        &mut *tx,
+                        group.conversation_id,
+                        group.owner,
+                        "owner",
+                    )
+                    .await?;
+                self.conversation_role_repo
+                    .assign_roles_bulk_in_tx(
+                        &mut *tx,
+                        group.conversation_id,
+                        &group.members,
+                        "member",
+                    )
+                    .await
+            };
+            roles
+                .await
+                .map_err(|e| ImportError::Store(format!("assign group roles: {e}")))?;
+        }
+
+        let conversation_ids: Vec<ConversationId> = batch
+            .messages
+            .iter()
+            .map(|m| m.conversation_id)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let conversations = self
+            .import_repo
+            .existing_conversations_in_tx(&mut *tx, &conversation_ids)
+            .await?;
+        let records: Vec<MessageRecord> = batch
+            .messages
+            .iter()
+            .filter(|m| conversations.contains(&m.conversation_id))
+            .cloned()
+            .map(MessageRecord::from)
+            .collect();
+        let messages = self
+            .message_repo
+            .insert_batch_in_tx(&mut *tx, &records)
+            .await
+            .map_err(|e| ImportError::Store(e.to_string()))?;
+        report.messages = ImportCount::of(batch.messages.len(), messages);
+        if messages > 0 {
+            let settled: Vec<ConversationId> = conversations.into_iter().collect();
+            self.import_repo
+                .settle_messages_in_tx(&mut *tx, &settled)
+                .await?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ImportError::Store(e.to_string()))?;
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,43 @@
+use crate::application_impl::fake_store::FakeStore;
+use crate::application_port::*;
+use crate::domain_model::*;
+use chrono::Utc;
+use std::sync::Arc;
+
+/// In-memory `InviteService`; see `FakeStore`, where `FakeAuthService`
+/// redeems the codes.
+pub struct FakeInviteService {
+    store: Arc<FakeStore>,
+}
+
+impl FakeInviteService {
+    pub fn new(store: Arc<FakeStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl InviteService for FakeInviteService {
+    async fn mint(
+        &self,
+        request: MintInvites,
+        minted_by: String,
+    ) -> Result<Vec<InviteCode>, AuthError> {
+        let codes = mint_invite_codes(&request, &minted_by, Utc::now())?;
+        let mut state = self.store.state();
+        for invite in &codes {
+            state.invites.insert(invite.code.clone(), invite.clone());
+        }
+        Ok(codes)
+    }
+
+    async fn list(&self) -> Result<Vec<InviteCode>, AuthError> {
+        let mut codes: Vec<InviteCode> = self.store.state().invites.values().cloned().collect();
+        codes.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| a.code.cmp(&b.code))
+        });
+        Ok(codes)
+    }
+}
@@ -0,0 +1,32 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::Utc;
+use std::sync::Arc;
+
+pub struct RealInviteService {
+    invite_code_repo: Arc<dyn InviteCodeRepo>,
+}
+
+impl RealInviteService {
+    pub fn new(invite_code_repo: Arc<dyn InviteCodeRepo>) -> Self {
+        Self { invite_code_repo }
+    }
+}
+
+#[async_trait::async_trait]
+impl InviteService for RealInviteService {
+    async fn mint(
+        &self,
+        request: MintInvites,
+        minted_by: String,
+    ) -> Result<Vec<InviteCode>, AuthError> {
+        let codes = mint_invite_codes(&request, &minted_by, Utc::now())?;
+        self.invite_code_repo.insert(&codes).await?;
+        Ok(codes)
+    }
+
+    async fn list(&self) -> Result<Vec<InviteCode>, AuthError> {
+        self.invite_code_repo.list().await
+    }
+}
@@ -0,0 +1,48 @@
+use crate::application_impl::fake_store::FakeStore;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::EventType;
+use std::sync::Arc;
+
+/// In-memory `LoginRiskEvaluator`; see `FakeStore`. Judges the same way as
+/// `RealLoginRiskEvaluator`.
+pub struct FakeLoginRiskEvaluator {
+    store: Arc<FakeStore>,
+}
+
+impl FakeLoginRiskEvaluator {
+    pub fn new(store: Arc<FakeStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginRiskEvaluator for FakeLoginRiskEvaluator {
+    async fn evaluate(&self, attempt: LoginAttempt) -> Result<LoginRisk, AuthError> {
+        let location = coarse_location(attempt.ip);
+        let unusual = {
+            let mut state = self.store.state();
+            let known = state.login_locations.entry(attempt.user_id).or_default();
+            // the first login has nothing to compare against
+            let first = known.is_empty();
+            known.insert(location.clone()) && !first
+        };
+        if !unusual {
+            return Ok(LoginRisk::Usual);
+        }
+
+        self.store.publish(
+            EventType::SecurityAlert,
+            attempt.user_id.0,
+            vec![attempt.user_id],
+            &S2CEvent::SecurityAlert(SecurityAlert {
+                kind: SecurityAlertKind::UnusualLogin,
+                ip: attempt.ip,
+                location: location.clone(),
+                device: attempt.device,
+                at: attempt.at,
+            }),
+        );
+        Ok(LoginRisk::Unusual { location })
+    }
+}
@@ -0,0 +1,75 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use std::sync::Arc;
+
+/// Judges by `coarse_location` against the `login_location` history.
+pub struct RealLoginRiskEvaluator {
+    login_location_repo: Arc<dyn LoginLocationRepo>,
+    outbox_repo: Arc<dyn OutboxRepo>,
+    tx_manager: Arc<dyn TxManager>,
+}
+
+impl RealLoginRiskEvaluator {
+    pub fn new(
+        login_location_repo: Arc<dyn LoginLocationRepo>,
+        outbox_repo: Arc<dyn OutboxRepo>,
+        tx_manager: Arc<dyn TxManager>,
+    ) -> Self {
+        Self {
+            login_location_repo,
+            outbox_repo,
+            tx_manager,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginRiskEvaluator for RealLoginRiskEvaluator {
+    async fn evaluate(&self, attempt: LoginAttempt) -> Result<LoginRisk, AuthError> {
+        let location = coarse_location(attempt.ip);
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+        let known = self
+            .login_location_repo
+            .list_in_tx(&mut *tx, attempt.user_id)
+            .await?;
+        self.login_location_repo
+            .record_in_tx(&mut *tx, attempt.user_id, &location, attempt.ip, attempt.at)
+            .await?;
+
+        // the first login has nothing to compare against
+        let risk = if known.is_empty() || known.contains(&location) {
+            LoginRisk::Usual
+        } else {
+            let event = OutboxEvent::new(
+                EventType::SecurityAlert,
+                Some(attempt.user_id.0),
+                vec![attempt.user_id],
+                &S2CEvent::SecurityAlert(SecurityAlert {
+                    kind: SecurityAlertKind::UnusualLogin,
+                    ip: attempt.ip,
+                    location: location.clone(),
+                    device: attempt.device,
+                    at: attempt.at,
+                }),
+            )
+            .map_err(|e| AuthError::Store(format!("compose security.alert event: {e}")))?;
+            self.outbox_repo
+                .enqueue_in_tx(&mut *tx, &event)
+                .await
+                .map_err(|e| AuthError::Store(format!("enqueue security.alert event: {e}")))?;
+            LoginRisk::Unusual { location }
+        };
+
+        tx.commit()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        Ok(risk)
+    }
+}
@@ -1,15 +1,78 @@
+mod attachment_service_fake;
+mod attachment_service_impl;
 mod auth_service_fake;
 mod auth_service_impl;
 mod captcha_service_fake;
 mod captcha_service_impl;
+mod contact_discovery_service_fake;
+mod contact_discovery_service_impl;
+mod conversation_meta_service_fake;
+mod conversation_meta_service_impl;
+mod conversation_service_fake;
 mod conversation_service_impl;
+mod device_service_fake;
+mod device_service_impl;
+mod discovery_limiter;
+mod event_replay_service_fake;
+mod event_replay_service_impl;
+mod export_jobs;
+mod export_service_fake;
+mod export_service_impl;
+mod fake_store;
+mod friend_request_limiter;
+mod import_service_fake;
+mod import_service_impl;
+mod invite_service_fake;
+mod invite_service_impl;
+mod login_risk_evaluator_fake;
+mod login_risk_evaluator_impl;
+mod notice_service_fake;
+mod notice_service_impl;
+mod relationship_service_fake;
 mod relationship_service_impl;
+mod stats_service_fake;
+mod stats_service_impl;
+mod user_service_fake;
 mod user_service_impl;
+mod username_policy_service_fake;
+mod username_policy_service_impl;
+mod webhook_service_fake;
+mod webhook_service_impl;
 
+pub use attachment_service_fake::*;
+pub use attachment_service_impl::*;
 pub use auth_service_fake::*;
 pub use auth_service_impl::*;
 pub use captcha_service_fake::*;
 pub use captcha_service_impl::*;
+pub use contact_discovery_service_fake::*;
+pub use contact_discovery_service_impl::*;
+pub use conversation_meta_service_fake::*;
+pub use conversation_meta_service_impl::*;
+pub use conversation_service_fake::*;
 pub use conversation_service_impl::*;
+pub use device_service_fake::*;
+pub use device_service_impl::*;
+pub use event_replay_service_fake::*;
+pub use event_replay_service_impl::*;
+pub use export_service_fake::*;
+pub use export_service_impl::*;
+pub use fake_store::FakeStore;
+pub use import_service_fake::*;
+pub use import_service_impl::*;
+pub use invite_service_fake::*;
+pub use invite_service_impl::*;
+pub use login_risk_evaluator_fake::*;
+pub use login_risk_evaluator_impl::*;
+pub use notice_service_fake::*;
+pub use notice_service_impl::*;
+pub use relationship_service_fake::*;
 pub use relationship_service_impl::*;
+pub use stats_service_fake::*;
+pub use stats_service_impl::*;
+pub use user_service_fake::*;
 pub use user_service_impl::*;
+pub use username_policy_service_fake::*;
+pub use username_policy_service_impl::*;
+pub use webhook_service_fake::*;
+pub use webhook_service_impl::*;
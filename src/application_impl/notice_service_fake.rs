@@ -0,0 +1,36 @@
+use crate::application_impl::fake_store::FakeStore;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::{EventType, OutboxEvent};
+use chrono::{SubsecRound, Utc};
+use std::sync::Arc;
+
+/// In-memory `NoticeService`; see `FakeStore`.
+pub struct FakeNoticeService {
+    store: Arc<FakeStore>,
+}
+
+impl FakeNoticeService {
+    pub fn new(store: Arc<FakeStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl NoticeService for FakeNoticeService {
+    async fn publish(&self, draft: NoticeDraft) -> Result<SystemNotice, NoticeError> {
+        let (notice, receivers) = compose_notice(draft, Utc::now().trunc_subsecs(6))?;
+
+        let event = OutboxEvent::new(
+            EventType::SystemNotice,
+            None,
+            receivers,
+            &S2CEvent::SystemNotice(notice.clone()),
+        )
+        .map_err(|e| NoticeError::Store(format!("compose system.notice event: {e}")))?
+        .with_publish_at(notice.published_at);
+        self.store.send(event);
+
+        Ok(notice)
+    }
+}
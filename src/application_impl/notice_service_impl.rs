@@ -0,0 +1,51 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{SubsecRound, Utc};
+use std::sync::Arc;
+
+pub struct RealNoticeService {
+    outbox_repo: Arc<dyn OutboxRepo>,
+    tx_manager: Arc<dyn TxManager>,
+}
+
+impl RealNoticeService {
+    pub fn new(outbox_repo: Arc<dyn OutboxRepo>, tx_manager: Arc<dyn TxManager>) -> Self {
+        Self {
+            outbox_repo,
+            tx_manager,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl NoticeService for RealNoticeService {
+    async fn publish(&self, draft: NoticeDraft) -> Result<SystemNotice, NoticeError> {
+        let (notice, receivers) = compose_notice(draft, Utc::now().trunc_subsecs(6))?;
+
+        // no partition key: nothing needs to stay in order with a notice
+        let event = OutboxEvent::new(
+            EventType::SystemNotice,
+            None,
+            receivers,
+            &S2CEvent::SystemNotice(notice.clone()),
+        )
+        .map_err(|e| NoticeError::Store(format!("compose system.notice event: {e}")))?
+        .with_publish_at(notice.published_at);
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| NoticeError::Store(e.to_string()))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| NoticeError::Store(format!("enqueue system.notice event: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| NoticeError::Store(e.to_string()))?;
+
+        Ok(notice)
+    }
+}
@@ -0,0 +1,518 @@
+use crate::application_impl::fake_store::{
+    FakeConversation, FakeFriendship, FakeGroup, FakePeer, FakeState, FakeStore, ordered,
+};
+use crate::application_impl::friend_request_limiter::FriendRequestLimiter;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::{EventType, RateLimiter};
+use crate::logger::Secret;
+use chrono::{SubsecRound, Utc};
+use std::cmp::Reverse;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// In-memory `RelationshipService`; see `FakeStore`.
+pub struct FakeRelationshipService {
+    store: Arc<FakeStore>,
+    open_direct: bool,
+    welcome: WelcomeMessages,
+    friend_requests: FriendRequestLimiter,
+}
+
+impl FakeRelationshipService {
+    pub fn new(
+        store: Arc<FakeStore>,
+        open_direct: bool,
+        welcome: WelcomeMessages,
+        rate_limiter: Arc<dyn RateLimiter>,
+        friend_request_limits: FriendRequestLimits,
+    ) -> Self {
+        Self {
+            store,
+            open_direct,
+            welcome,
+            friend_requests: FriendRequestLimiter::new(rate_limiter, friend_request_limits),
+        }
+    }
+}
+
+fn ensure_relatable(state: &FakeState, me: UserId, other: UserId) -> Result<(), RelationError> {
+    if me == other {
+        return Err(RelationError::SelfRelation);
+    }
+    if !state.is_active(other) {
+        return Err(RelationError::UserNotFound);
+    }
+    Ok(())
+}
+
+/// A new conversation's messages: the welcome, if there is one.
+fn welcome(
+    conversation_id: ConversationId,
+    sender: UserId,
+    text: Option<String>,
+) -> Vec<MessageRecord> {
+    text.map(|text| MessageRecord {
+        message_id: MessageId(Uuid::new_v4()),
+        conversation_id,
+        message_offset: MessageOffset(1),
+        sender,
+        content: Secret::new(text),
+        content_encrypted: false,
+        created_at: Utc::now().trunc_subsecs(6),
+        edited_at: None,
+        edit_count: 0,
+        deleted_at: None,
+        kind: MessageKind::System,
+        reply_to: None,
+        attachments: Vec::new(),
+    })
+    .into_iter()
+    .collect()
+}
+
+fn new_direct(
+    state: &mut FakeState,
+    a: UserId,
+    b: UserId,
+    welcome_text: Option<String>,
+) -> ConversationId {
+    let conversation_id = ConversationId(Uuid::new_v4());
+    state.conversations.insert(
+        conversation_id,
+        FakeConversation {
+            peer: FakePeer::Direct(a, b),
+            messages: welcome(conversation_id, a, welcome_text),
+            deleted: false,
+            frozen: false,
+            retention_days: None,
+        },
+    );
+    conversation_id
+}
+
+/// The live group and whether `user` owns it.
+fn owned_group(
+    state: &FakeState,
+    group: GroupId,
+    user: UserId,
+) -> Result<&FakeGroup, RelationError> {
+    let group = state
+        .groups
+        .get(&group)
+        .filter(|g| !g.disbanded)
+        .ok_or(RelationError::GroupNotFound)?;
+    if !group.members.iter().any(|(member, _)| *member == user) {
+        return Err(RelationError::NotMember);
+    }
+    if group.owner != user {
+        return Err(RelationError::NotOwner);
+    }
+    Ok(group)
+}
+
+#[async_trait::async_trait]
+impl RelationshipService for FakeRelationshipService {
+    async fn add_friend(
+        &self,
+        me: UserId,
+        other: UserId,
+        idempotency_key: IdempotencyKey,
+    ) -> Result<AddFriendOutcome, RelationError> {
+        ensure_relatable(&self.store.state(), me, other)?;
+        self.friend_requests.acquire(me).await?;
+
+        // `Err` is a retry or an existing friendship, which isn't counted
+        let created = {
+            let mut state = self.store.state();
+            if let Some(conversation_id) = state.friend_keys.get(&(me, idempotency_key)) {
+                Err(AddFriendOutcome::Created(*conversation_id))
+            } else if let Some(friendship) = state.friendships.get(&ordered(me, other)) {
+                Err(AddFriendOutcome::AlreadyFriends(friendship.conversation_id))
+            } else {
+                let conversation_id = match state.direct_conversation(me, other) {
+                    Some(conversation_id) => conversation_id,
+                    None => new_direct(&mut state, me, other, self.welcome.direct.clone()),
+                };
+                state.friendships.insert(
+                    ordered(me, other),
+                    FakeFriendship {
+                        conversation_id,
+                        since: Utc::now(),
+                    },
+                );
+                state
+                    .friend_keys
+                    .insert((me, idempotency_key), conversation_id);
+                Ok((conversation_id, state.username(me).unwrap_or_default()))
+            }
+        };
+        let (conversation_id, username) = match created {
+            Ok(created) => created,
+            Err(outcome) => {
+                self.friend_requests.release(me).await;
+                return Ok(outcome);
+            }
+        };
+
+        self.store.publish(
+            EventType::FriendshipNew,
+            conversation_id.0,
+            vec![other],
+            &S2CEvent::FriendshipNew(FriendshipNew {
+                conversation_id,
+                other: me,
+                username,
+            }),
+        );
+        Ok(AddFriendOutcome::Created(conversation_id))
+    }
+
+    async fn open_direct(
+        &self,
+        me: UserId,
+        other: UserId,
+    ) -> Result<ConversationId, RelationError> {
+        let mut state = self.store.state();
+        ensure_relatable(&state, me, other)?;
+        if let Some(conversation_id) = state.direct_conversation(me, other) {
+            return Ok(conversation_id);
+        }
+        if !self.open_direct {
+            return Err(RelationError::NotFriends);
+        }
+        Ok(new_direct(
+            &mut state,
+            me,
+            other,
+            self.welcome.direct.clone(),
+        ))
+    }
+
+    async fn list_friends(
+        &self,
+        user_id: UserId,
+        page_size: PageSize,
+        after: Option<FriendCursor>,
+    ) -> Result<Vec<FriendSummary>, RelationError> {
+        let state = self.store.state();
+        let mut friends: Vec<FriendSummary> = state
+            .friendships
+            .iter()
+            .filter_map(|((a, b), friendship)| {
+                let other = match user_id {
+                    id if id == *a => *b,
+                    id if id == *b => *a,
+                    _ => return None,
+                };
+                if !state.is_active(other) {
+                    return None;
+                }
+                Some(FriendSummary {
+                    user_id: other,
+                    username: state.username(other)?,
+                    conversation_id: friendship.conversation_id,
+                    since: friendship.since,
+                })
+            })
+            .collect();
+
+        friends.sort_by_key(|f| Reverse((f.since, f.user_id)));
+        Ok(friends
+            .into_iter()
+            .filter(|f| after.is_none_or(|cur| (f.since, f.user_id) < (cur.since, cur.other_user)))
+            .take(page_size.0 as usize)
+            .collect())
+    }
+
+    async fn create_group(
+        &self,
+        owner: UserId,
+        name: &str,
+        _description: Option<&str>,
+        members: &[UserId],
+        idempotency_key: IdempotencyKey,
+    ) -> Result<(GroupId, ConversationId), RelationError> {
+        let members = group_members(owner, members);
+        let mut state = self.store.state();
+        if let Some(pair) = state.group_keys.get(&(owner, idempotency_key)) {
+            return Ok(*pair);
+        }
+        for member in &members {
+            ensure_relatable(&state, owner, *member)?;
+        }
+
+        let group_id = GroupId(Uuid::new_v4());
+        let conversation_id = ConversationId(Uuid::new_v4());
+        let now = Utc::now();
+        state.conversations.insert(
+            conversation_id,
+            FakeConversation {
+                peer: FakePeer::Group(group_id),
+                messages: welcome(conversation_id, owner, self.welcome.for_group(name)),
+                deleted: false,
+                frozen: false,
+                retention_days: None,
+            },
+        );
+        state.groups.insert(
+            group_id,
+            FakeGroup {
+                name: name.to_owned(),
+                owner,
+                conversation_id,
+                created_at: now,
+                members: std::iter::once(owner)
+                    .chain(members.iter().copied())
+                    .map(|member| (member, now))
+                    .collect(),
+                disbanded: false,
+                appearance: GroupAppearance::default(),
+            },
+        );
+        state
+            .group_keys
+            .insert((owner, idempotency_key), (group_id, conversation_id));
+        drop(state);
+
+        self.store.publish(
+            EventType::GroupNew,
+            conversation_id.0,
+            members,
+            &S2CEvent::GroupNew(GroupNew {
+                conversation_id,
+                group_id,
+                group_name: name.to_owned(),
+            }),
+        );
+        Ok((group_id, conversation_id))
+    }
+
+    async fn invite_to_group(
+        &self,
+        group: GroupId,
+        host: UserId,
+        guest: UserId,
+    ) -> Result<(), RelationError> {
+        let (conversation_id, group_name, username, receivers, members_version) = {
+            let mut state = self.store.state();
+            ensure_relatable(&state, host, guest)?;
+            owned_group(&state, group, host)?;
+            let username = state.username(guest).unwrap_or_default();
+
+            let Some(chat_group) = state.groups.get_mut(&group) else {
+                return Err(RelationError::GroupNotFound);
+            };
+            if chat_group
+                .members
+                .iter()
+                .any(|(member, _)| *member == guest)
+            {
+                return Err(RelationError::AlreadyMember);
+            }
+            chat_group.members.push((guest, Utc::now()));
+            let members_version = chat_group.members.len() as u64;
+
+            // same audience as the real service: everyone but the host
+            let receivers: Vec<UserId> = chat_group
+                .members
+                .iter()
+                .map(|(member, _)| *member)
+                .filter(|member| *member != host)
+                .collect();
+            (
+                chat_group.conversation_id,
+                chat_group.name.clone(),
+                username,
+                receivers,
+                members_version,
+            )
+        };
+
+        self.store.publish(
+            EventType::GroupNew,
+            conversation_id.0,
+            vec![guest],
+            &S2CEvent::GroupNew(GroupNew {
+                conversation_id,
+                group_id: group,
+                group_name,
+            }),
+        );
+        self.store.publish(
+            EventType::GroupMemberNew,
+            conversation_id.0,
+            receivers,
+            &S2CEvent::GroupMemberNew(GroupMemberNew {
+                conversation_id,
+                group_id: group,
+                member_id: guest,
+                username,
+                members_version,
+            }),
+        );
+        Ok(())
+    }
+
+    async fn disband_group(&self, group: GroupId, owner: UserId) -> Result<(), RelationError> {
+        let mut state = self.store.state();
+        let conversation_id = owned_group(&state, group, owner)?.conversation_id;
+
+        if let Some(chat_group) = state.groups.get_mut(&group) {
+            chat_group.disbanded = true;
+        }
+        if let Some(conversation) = state.conversations.get_mut(&conversation_id) {
+            conversation.deleted = true;
+        }
+        Ok(())
+    }
+
+    async fn list_groups(
+        &self,
+        user_id: UserId,
+        page_size: PageSize,
+        after: Option<GroupCursor>,
+        projection: GroupProjection,
+    ) -> Result<Vec<GroupSummary>, RelationError> {
+        let state = self.store.state();
+        let mut groups: Vec<GroupSummary> = state
+            .groups
+            .iter()
+            .filter(|(_, g)| !g.disbanded && g.members.iter().any(|(m, _)| *m == user_id))
+            .map(|(group_id, g)| GroupSummary {
+                group_id: *group_id,
+                name: g.name.clone(),
+                my_role: if g.owner == user_id {
+                    GroupMemberRole::Owner
+                } else {
+                    GroupMemberRole::Member
+                },
+                conversation_id: g.conversation_id,
+                member_count: match projection {
+                    GroupProjection::Lite => None,
+                    GroupProjection::WithMemberCount => Some(g.members.len() as u32),
+                },
+                created_at: g.created_at,
+                appearance: g.appearance.clone(),
+            })
+            .collect();
+
+        groups.sort_by_key(|g| Reverse((g.created_at, g.group_id)));
+        Ok(groups
+            .into_iter()
+            .filter(|g| {
+                after.is_none_or(|cur| (g.created_at, g.group_id) < (cur.created_at, cur.group_id))
+            })
+            .take(page_size.0 as usize)
+            .collect())
+    }
+
+    async fn group_member_counts(
+        &self,
+        user_id: UserId,
+        group_ids: &[GroupId],
+    ) -> Result<Vec<GroupMemberCount>, RelationError> {
+        let state = self.store.state();
+        Ok(group_ids
+            .iter()
+            .filter_map(|group_id| {
+                let g = state.groups.get(group_id)?;
+                let visible = !g.disbanded && g.members.iter().any(|(m, _)| *m == user_id);
+                visible.then_some(GroupMemberCount {
+                    group_id: *group_id,
+                    member_count: g.members.len() as u32,
+                })
+            })
+            .collect())
+    }
+
+    async fn list_group_members(
+        &self,
+        _user_id: UserId,
+        group: GroupId,
+        page_size: PageSize,
+        after: Option<MemberCursor>,
+    ) -> Result<Vec<MemberSummary>, RelationError> {
+        let state = self.store.state();
+        let g = state
+            .groups
+            .get(&group)
+            .filter(|g| !g.disbanded)
+            .ok_or(RelationError::GroupNotFound)?;
+
+        let mut members: Vec<MemberSummary> = g
+            .members
+            .iter()
+            .map(|(user_id, joined_at)| MemberSummary {
+                user_id: *user_id,
+                username: state.username(*user_id).unwrap_or_default(),
+                joined_at: *joined_at,
+            })
+            .collect();
+
+        members.sort_by_key(|m| Reverse((m.joined_at, m.user_id)));
+        Ok(members
+            .into_iter()
+            .filter(|m| {
+                after.is_none_or(|cur| (m.joined_at, m.user_id) < (cur.joined_at, cur.user))
+            })
+            .take(page_size.0 as usize)
+            .collect())
+    }
+
+    async fn group_appearance(
+        &self,
+        user_id: UserId,
+        group: GroupId,
+    ) -> Result<GroupAppearance, RelationError> {
+        let state = self.store.state();
+        let g = state
+            .groups
+            .get(&group)
+            .filter(|g| !g.disbanded)
+            .ok_or(RelationError::GroupNotFound)?;
+        if !g.members.iter().any(|(member, _)| *member == user_id) {
+            return Err(RelationError::NotMember);
+        }
+        Ok(g.appearance.clone())
+    }
+
+    async fn set_group_appearance(
+        &self,
+        group: GroupId,
+        owner: UserId,
+        appearance: GroupAppearance,
+    ) -> Result<(), RelationError> {
+        check_group_appearance(&appearance)?;
+        let (conversation_id, members) = {
+            let mut state = self.store.state();
+            let conversation_id = owned_group(&state, group, owner)?.conversation_id;
+            let Some(chat_group) = state.groups.get_mut(&group) else {
+                return Err(RelationError::GroupNotFound);
+            };
+            if chat_group.appearance == appearance {
+                return Ok(());
+            }
+            chat_group.appearance = appearance.clone();
+            let members = chat_group
+                .members
+                .iter()
+                .map(|(member, _)| *member)
+                .collect();
+            (conversation_id, members)
+        };
+
+        self.store.publish(
+            EventType::GroupUpdated,
+            conversation_id.0,
+            members,
+            &S2CEvent::GroupUpdated(GroupUpdated {
+                conversation_id,
+                group_id: group,
+                avatar_url: appearance.avatar_url,
+                theme_color: appearance.theme_color,
+            }),
+        );
+        Ok(())
+    }
+}
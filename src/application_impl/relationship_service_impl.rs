@@ -1,9 +1,17 @@
+use crate::application_impl::friend_request_limiter::FriendRequestLimiter;
 use crate::application_port::*;
 use crate::domain_model::*;
 use crate::domain_port::*;
+use crate::logger::Secret;
+use crate::metrics;
+use chrono::{SubsecRound, Utc};
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
+/// A pending group claim older than this is assumed to belong to a crashed winner.
+const GROUP_CLAIM_STALE_AFTER: Duration = Duration::from_secs(60);
+
 pub struct RealRelationshipService {
     user_repo: Arc<dyn UserRepo>,
     friendship_repo: Arc<dyn FriendshipRepo>,
@@ -12,10 +20,19 @@ pub struct RealRelationshipService {
     conversation_repo: Arc<dyn ConversationRepo>,
     conversation_role_repo: Arc<dyn ConversationRoleRepo>,
     outbox_repo: Arc<dyn OutboxRepo>,
+    message_repo: Arc<dyn MessageRepo>,
+    /// Must allocate inside the transaction: the welcome goes into a
+    /// conversation that isn't committed yet.
+    offset_allocator: Arc<dyn MessageOffsetAllocator>,
     tx_manager: Arc<dyn TxManager>,
+    /// Direct conversations without a friendship; see `open_direct`.
+    open_direct: bool,
+    welcome: WelcomeMessages,
+    friend_requests: FriendRequestLimiter,
 }
 
 impl RealRelationshipService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_repo: Arc<dyn UserRepo>,
         friendship_repo: Arc<dyn FriendshipRepo>,
@@ -24,7 +41,13 @@ impl RealRelationshipService {
         conversation_repo: Arc<dyn ConversationRepo>,
         conversation_role_repo: Arc<dyn ConversationRoleRepo>,
         outbox_repo: Arc<dyn OutboxRepo>,
+        message_repo: Arc<dyn MessageRepo>,
+        offset_allocator: Arc<dyn MessageOffsetAllocator>,
         tx_manager: Arc<dyn TxManager>,
+        open_direct: bool,
+        welcome: WelcomeMessages,
+        rate_limiter: Arc<dyn RateLimiter>,
+        friend_request_limits: FriendRequestLimits,
     ) -> Self {
         Self {
             user_repo,
@@ -34,15 +57,237 @@ impl RealRelationshipService {
             conversation_repo,
             conversation_role_repo,
             outbox_repo,
+            message_repo,
+            offset_allocator,
             tx_manager,
+            open_direct,
+            welcome,
+            friend_requests: FriendRequestLimiter::new(rate_limiter, friend_request_limits),
+        }
+    }
+
+    /// Writes `text` into a conversation created in `tx` as its first,
+    /// system message; `sender` is whoever created it.
+    async fn welcome_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        sender: UserId,
+        text: Option<String>,
+    ) -> Result<(), RelationError> {
+        let Some(text) = text else {
+            return Ok(());
+        };
+        let created_at = Utc::now().trunc_subsecs(6);
+        let message_offset = self
+            .offset_allocator
+            .allocate_in_tx(&mut *tx, conversation_id, created_at)
+            .await
+            .map_err(|e| RelationError::Store(format!("allocate welcome offset: {e}")))?;
+        // not one of the sender's own messages, so no sender_seq either
+        self.message_repo
+            .insert_in_tx(
+                &mut *tx,
+                &MessageRecord {
+                    message_id: MessageId(Uuid::new_v4()),
+                    conversation_id,
+                    message_offset,
+                    sender,
+                    content: Secret::new(text),
+                    content_encrypted: false,
+                    created_at,
+                    edited_at: None,
+                    edit_count: 0,
+                    deleted_at: None,
+                    kind: MessageKind::System,
+                    reply_to: None,
+                    attachments: Vec::new(),
+                },
+                0,
+            )
+            .await
+            .map_err(|e| RelationError::Store(format!("insert welcome message: {e}")))?;
+        self.conversation_repo
+            .advance_read_in_tx(&mut *tx, sender, conversation_id, message_offset)
+            .await
+            .map_err(|e| RelationError::Store(format!("mark welcome read: {e}")))?;
+        Ok(())
+    }
+
+    /// The pair's direct conversation, which an open DM may have created
+    /// before any friendship; `None` if there is none yet.
+    async fn direct_conversation(
+        &self,
+        a: UserId,
+        b: UserId,
+    ) -> Result<Option<ConversationId>, RelationError> {
+        match self
+            .friendship_repo
+            .get_conversation_id_by_friendship(a, b)
+            .await
+        {
+            Ok(conversation_id) => Ok(Some(conversation_id)),
+            Err(RelationError::NotFriends) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn create_direct_internal(
+        &self,
+        me: UserId,
+        other: UserId,
+    ) -> Result<ConversationId, RelationError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+        let conversation_id = ConversationId(Uuid::new_v4());
+
+        self.conversation_repo
+            .create_direct_conversation_in_tx(&mut *tx, me, other, conversation_id)
+            .await?;
+        self.friendship_repo
+            .insert_friendship_in_tx(&mut *tx, me, other, conversation_id)
+            .await?;
+        self.welcome_in_tx(&mut *tx, conversation_id, me, self.welcome.direct.clone())
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+
+        Ok(conversation_id)
+    }
+
+    /// Rejects relating a user to themself or to a missing/deactivated user
+    /// before any claim or write is made.
+    async fn ensure_relatable(&self, me: UserId, other: UserId) -> Result<(), RelationError> {
+        if me == other {
+            return Err(RelationError::SelfRelation);
+        }
+        let exists = self
+            .user_repo
+            .id_exists(other)
+            .await
+            .map_err(|e| RelationError::Store(format!("check user exists: {e}")))?;
+        if !exists {
+            return Err(RelationError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    async fn finish_group_claim(
+        &self,
+        owner: UserId,
+        name: &str,
+        description: Option<&str>,
+        members: &[UserId],
+        idempotency_key: IdempotencyKey,
+        group_id: GroupId,
+    ) -> Result<(GroupId, ConversationId), RelationError> {
+        let result = self
+            .tx_manager
+            .with_retry(move || {
+                self.create_group_internal(
+                    owner,
+                    name,
+                    description,
+                    members,
+                    idempotency_key,
+                    group_id,
+                )
+            })
+            .await;
+        match result {
+            // best-effort mark
+            Ok(pair) => {
+                let _ = self
+                    .group_idem_repo
+                    .mark_succeeded(owner, idempotency_key, pair.0, pair.1)
+                    .await;
+                Ok(pair)
+            }
+            Err(e) => {
+                let _ = self
+                    .group_idem_repo
+                    .mark_failed(
+                        owner,
+                        idempotency_key,
+                        group_id,
+                        &e.to_string().chars().take(64).collect::<String>(),
+                    )
+                    .await;
+                Err(RelationError::Store("group creation failed".to_owned()))
+            }
         }
     }
 
+    async fn create_friendship_internal(
+        &self,
+        me: UserId,
+        other: UserId,
+    ) -> Result<ConversationId, RelationError> {
+        let existing = self.direct_conversation(me, other).await?;
+
+        // Winner: all writes in ONE tx
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+        let proposed_conv_id = existing.unwrap_or_else(|| ConversationId(Uuid::new_v4()));
+
+        // order matters: conversation -> friendship
+        if existing.is_none() {
+            self.conversation_repo
+                .create_direct_conversation_in_tx(&mut *tx, me, other, proposed_conv_id)
+                .await?;
+            self.friendship_repo
+                .insert_friendship_in_tx(&mut *tx, me, other, proposed_conv_id)
+                .await?;
+            self.welcome_in_tx(&mut *tx, proposed_conv_id, me, self.welcome.direct.clone())
+                .await?;
+        }
+
+        let username = self
+            .user_repo
+            .get_username_in_tx(&mut *tx, me)
+            .await
+            .map_err(|e| {
+                tracing::warn!("query username: {e}");
+                RelationError::UserNotFound
+            })?;
+
+        let event = OutboxEvent::new(
+            EventType::FriendshipNew,
+            Some(proposed_conv_id.0),
+            vec![other],
+            &S2CEvent::FriendshipNew(FriendshipNew {
+                conversation_id: proposed_conv_id,
+                other: me,
+                username,
+            }),
+        )
+        .map_err(|e| RelationError::Store(e.to_string()))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+
+        Ok(proposed_conv_id)
+    }
+
     async fn create_group_internal(
         &self,
         owner: UserId,
         name: &str,
         description: Option<&str>,
+        members: &[UserId],
         _idempotency_key: IdempotencyKey,
         group_id: GroupId,
     ) -> Result<(GroupId, ConversationId), RelationError> {
@@ -74,6 +319,36 @@ impl RealRelationshipService {
         self.conversation_role_repo
             .assign_role_by_name_in_tx(&mut *tx, conversation_id, owner, "owner")
             .await?;
+        self.welcome_in_tx(
+            &mut *tx,
+            conversation_id,
+            owner,
+            self.welcome.for_group(name),
+        )
+        .await?;
+
+        if !members.is_empty() {
+            self.conversation_role_repo
+                .assign_roles_bulk_in_tx(&mut *tx, conversation_id, members, "member")
+                .await?;
+            let event = OutboxEvent::new(
+                EventType::GroupNew,
+                Some(conversation_id.0),
+                members.to_vec(),
+                &S2CEvent::GroupNew(GroupNew {
+                    conversation_id,
+                    group_id,
+                    group_name: name.to_owned(),
+                }),
+            )
+            .map_err(|e| RelationError::Store(format!("compose group.new event: {e}")))?;
+            self.outbox_repo
+                .enqueue_in_tx(&mut *tx, &event)
+                .await
+                .map_err(|e| {
+                    RelationError::Store(format!("enqueue group.new event to outbox: {e}"))
+                })?;
+        }
 
         tx.commit()
             .await
@@ -81,101 +356,60 @@ impl RealRelationshipService {
 
         Ok((group_id, conversation_id))
     }
-}
 
-#[async_trait::async_trait]
-impl RelationshipService for RealRelationshipService {
-    async fn add_friend(
+    async fn try_add_friend(
         &self,
         me: UserId,
         other: UserId,
-        _idempotency_key: IdempotencyKey,
-    ) -> std::result::Result<ConversationId, RelationError> {
+        idempotency_key: IdempotencyKey,
+    ) -> Result<AddFriendOutcome, RelationError> {
+        self.ensure_relatable(me, other).await?;
+        self.friend_requests.acquire(me).await?;
+
         // claim friendship
-        match self.friendship_repo.claim(me, other, me).await? {
+        match self
+            .friendship_repo
+            .claim(me, other, me, idempotency_key)
+            .await?
+        {
             FriendshipIdemClaim::Won => {
-                // Winner: all writes in ONE tx
-                let mut tx = self
+                let conv_id = self
                     .tx_manager
-                    .begin()
-                    .await
-                    .map_err(|e| RelationError::Store(e.to_string()))?;
-                let proposed_conv_id = ConversationId(Uuid::new_v4());
-
-                // order matters: conversation -> friendship
-                self.conversation_repo
-                    .create_direct_conversation_in_tx(&mut *tx, me, other, proposed_conv_id)
-                    .await?;
-                self.friendship_repo
-                    .insert_friendship_in_tx(&mut *tx, me, other, proposed_conv_id)
+                    .with_retry(move || self.create_friendship_internal(me, other))
                     .await?;
-
-                let username = self
-                    .user_repo
-                    .get_username_in_tx(&mut *tx, me)
-                    .await
-                    .map_err(|e| {
-                        tracing::warn!("query username: {e}");
-                        RelationError::UserNotFound
-                    })?;
-
-                let event = OutboxEvent::new(
-                    EventType::FriendshipNew,
-                    Some(proposed_conv_id.0),
-                    vec![other],
-                    &S2CEvent::FriendshipNew(FriendshipNew {
-                        conversation_id: proposed_conv_id,
-                        other: me,
-                        username,
-                    }),
-                )
-                .map_err(|e| RelationError::Store(e.to_string()))?;
-                self.outbox_repo
-                    .enqueue_in_tx(&mut *tx, &event)
-                    .await
-                    .map_err(|e| RelationError::Store(e.to_string()))?;
-
-                tx.commit()
-                    .await
-                    .map_err(|e| RelationError::Store(e.to_string()))?;
-
-                Ok(proposed_conv_id)
+                Ok(AddFriendOutcome::Created(conv_id))
             }
-            FriendshipIdemClaim::Existing => {
+            FriendshipIdemClaim::Existing { replay } => {
+                self.friend_requests.release(me).await;
                 // follower: read source of truth
                 match self
                     .friendship_repo
                     .get_conversation_id_by_friendship(me, other)
                     .await
                 {
-                    Ok(conv_id) => Ok(conv_id),
-                    Err(_) => Err(RelationError::Store(
+                    Ok(conv_id) if replay => Ok(AddFriendOutcome::Created(conv_id)),
+                    Ok(conv_id) => Ok(AddFriendOutcome::AlreadyFriends(conv_id)),
+                    Err(RelationError::NotFriends) => Err(RelationError::Store(
                         "inconsistent friendship state".to_string(),
                     )),
+                    Err(e) => Err(e),
                 }
             }
         }
     }
 
-    async fn list_friends(
-        &self,
-        user_id: UserId,
-        page_size: PageSize,
-        after: Option<FriendCursor>,
-    ) -> std::result::Result<Vec<FriendSummary>, RelationError> {
-        Ok(self
-            .friendship_repo
-            .list_friends_with_conversations(user_id, page_size, after)
-            .await?)
-    }
-
-    async fn create_group(
+    async fn try_create_group(
         &self,
         owner: UserId,
         name: &str,
         description: Option<&str>,
+        members: &[UserId],
         idempotency_key: IdempotencyKey,
     ) -> Result<(GroupId, ConversationId), RelationError> {
+        for member in members {
+            self.ensure_relatable(owner, *member).await?;
+        }
+
         // claim key
         let proposed_gid = GroupId(uuid::Uuid::new_v4());
         match self
@@ -184,31 +418,15 @@ impl RelationshipService for RealRelationshipService {
             .await?
         {
             GroupIdemClaim::Won { group_id } => {
-                let result = self
-                    .create_group_internal(owner, name, description, idempotency_key, group_id)
-                    .await;
-                match result {
-                    // best-effort mark
-                    Ok(pair) => {
-                        let _ = self
-                            .group_idem_repo
-                            .mark_succeeded(owner, idempotency_key, pair.0, pair.1)
-                            .await;
-                        Ok(pair)
-                    }
-                    Err(e) => {
-                        let _ = self
-                            .group_idem_repo
-                            .mark_failed(
-                                owner,
-                                idempotency_key,
-                                group_id,
-                                &e.to_string().chars().take(64).collect::<String>(),
-                            )
-                            .await;
-                        Err(RelationError::Store("group creation failed".to_owned()))
-                    }
-                }
+                self.finish_group_claim(
+                    owner,
+                    name,
+                    description,
+                    members,
+                    idempotency_key,
+                    group_id,
+                )
+                .await
             }
             GroupIdemClaim::Existing {
                 group_id,
@@ -240,6 +458,23 @@ impl RelationshipService for RealRelationshipService {
                         .await;
                     return Ok((group_id, conv_id));
                 }
+                // the winner may have crashed before creating anything
+                if self
+                    .group_idem_repo
+                    .retake_stale(owner, idempotency_key, group_id, GROUP_CLAIM_STALE_AFTER)
+                    .await?
+                {
+                    return self
+                        .finish_group_claim(
+                            owner,
+                            name,
+                            description,
+                            members,
+                            idempotency_key,
+                            group_id,
+                        )
+                        .await;
+                }
                 Err(RelationError::Store(
                     "inconsistent idempotency state".to_string(),
                 ))
@@ -255,19 +490,19 @@ impl RelationshipService for RealRelationshipService {
         }
     }
 
-    async fn invite_to_group(
+    async fn try_invite_to_group(
         &self,
         group: GroupId,
         host: UserId,
         guest: UserId,
-    ) -> std::result::Result<(), RelationError> {
+    ) -> Result<(), RelationError> {
+        self.ensure_relatable(host, guest).await?;
+
         let conversation_id = self
             .group_repo
             .get_conversation_id_by_group(group)
             .await?
-            .ok_or(RelationError::Store(format!(
-                "inconsistent group conversation state for {group}"
-            )))?;
+            .ok_or(RelationError::GroupNotFound)?;
         let role = self
             .conversation_role_repo
             .get_role_by_conversation_id(host, conversation_id)
@@ -285,6 +520,10 @@ impl RelationshipService for RealRelationshipService {
         self.conversation_role_repo
             .assign_role_by_name_in_tx(&mut *tx, conversation_id, guest, "member")
             .await?;
+        let members_version = self
+            .conversation_repo
+            .members_version_in_tx(&mut *tx, conversation_id)
+            .await?;
 
         // push to guest
         let group_summary = self
@@ -333,6 +572,7 @@ impl RelationshipService for RealRelationshipService {
                     group_id: group,
                     member_id: guest,
                     username,
+                    members_version,
                 }),
             )
             .map_err(|e| RelationError::Store(format!("compose group.member.new event: {e}")))?;
@@ -352,13 +592,231 @@ impl RelationshipService for RealRelationshipService {
         Ok(())
     }
 
+    async fn try_disband_group(&self, group: GroupId, owner: UserId) -> Result<(), RelationError> {
+        let conversation_id = self
+            .group_repo
+            .get_conversation_id_by_group(group)
+            .await?
+            .ok_or(RelationError::GroupNotFound)?;
+        let role = self
+            .conversation_role_repo
+            .get_role_by_conversation_id(owner, conversation_id)
+            .await?;
+        if !matches!(role, GroupMemberRole::Owner) {
+            return Err(RelationError::NotOwner);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+
+        // a concurrent disband already won
+        if !self.group_repo.disband_in_tx(&mut *tx, group).await? {
+            return Err(RelationError::GroupNotFound);
+        }
+        self.conversation_repo
+            .soft_delete_in_tx(&mut *tx, conversation_id)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// The group's conversation and `user_id`'s role in it; `NotMember` if
+    /// they have none.
+    async fn group_role(
+        &self,
+        group: GroupId,
+        user_id: UserId,
+    ) -> Result<(ConversationId, GroupMemberRole), RelationError> {
+        let conversation_id = self
+            .group_repo
+            .get_conversation_id_by_group(group)
+            .await?
+            .ok_or(RelationError::GroupNotFound)?;
+        let role = self
+            .conversation_role_repo
+            .get_role_by_conversation_id(user_id, conversation_id)
+            .await?;
+        Ok((conversation_id, role))
+    }
+
+    async fn try_set_group_appearance(
+        &self,
+        group: GroupId,
+        owner: UserId,
+        appearance: &GroupAppearance,
+    ) -> Result<(), RelationError> {
+        let (conversation_id, role) = self.group_role(group, owner).await?;
+        if !matches!(role, GroupMemberRole::Owner) {
+            return Err(RelationError::NotOwner);
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+
+        if !self
+            .group_repo
+            .set_appearance_in_tx(&mut *tx, group, appearance)
+            .await?
+        {
+            return Ok(());
+        }
+
+        let members = self
+            .conversation_repo
+            .get_conversation_member_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| RelationError::Store(format!("query group members: {e}")))?;
+        let event = OutboxEvent::new(
+            EventType::GroupUpdated,
+            Some(conversation_id.0),
+            members,
+            &S2CEvent::GroupUpdated(GroupUpdated {
+                conversation_id,
+                group_id: group,
+                avatar_url: appearance.avatar_url.clone(),
+                theme_color: appearance.theme_color.clone(),
+            }),
+        )
+        .map_err(|e| RelationError::Store(format!("compose group.updated event: {e}")))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| RelationError::Store(format!("enqueue group.updated event: {e}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl RelationshipService for RealRelationshipService {
+    async fn add_friend(
+        &self,
+        me: UserId,
+        other: UserId,
+        idempotency_key: IdempotencyKey,
+    ) -> Result<AddFriendOutcome, RelationError> {
+        let result = self.try_add_friend(me, other, idempotency_key).await;
+        let outcome = match &result {
+            Ok(AddFriendOutcome::Created(_)) => "created",
+            Ok(AddFriendOutcome::AlreadyFriends(_)) => "already_friends",
+            Err(RelationError::UserNotFound | RelationError::SelfRelation) => "rejected",
+            Err(RelationError::RateLimited { .. }) => "rate_limited",
+            Err(_) => "error",
+        };
+        metrics::FRIENDSHIPS.with_label_values(&[outcome]).inc();
+        result
+    }
+
+    async fn open_direct(
+        &self,
+        me: UserId,
+        other: UserId,
+    ) -> Result<ConversationId, RelationError> {
+        self.ensure_relatable(me, other).await?;
+        if let Some(conversation_id) = self.direct_conversation(me, other).await? {
+            return Ok(conversation_id);
+        }
+        if !self.open_direct {
+            return Err(RelationError::NotFriends);
+        }
+
+        match self
+            .tx_manager
+            .with_retry(move || self.create_direct_internal(me, other))
+            .await
+        {
+            Ok(conversation_id) => Ok(conversation_id),
+            // a concurrent open took the pair first
+            Err(e) => self.direct_conversation(me, other).await?.ok_or(e),
+        }
+    }
+
+    async fn list_friends(
+        &self,
+        user_id: UserId,
+        page_size: PageSize,
+        after: Option<FriendCursor>,
+    ) -> std::result::Result<Vec<FriendSummary>, RelationError> {
+        Ok(self
+            .friendship_repo
+            .list_friends_with_conversations(user_id, page_size, after)
+            .await?)
+    }
+
+    async fn create_group(
+        &self,
+        owner: UserId,
+        name: &str,
+        description: Option<&str>,
+        members: &[UserId],
+        idempotency_key: IdempotencyKey,
+    ) -> Result<(GroupId, ConversationId), RelationError> {
+        let members = group_members(owner, members);
+        let result = self
+            .try_create_group(owner, name, description, &members, idempotency_key)
+            .await;
+        metrics::GROUPS_CREATED
+            .with_label_values(&[metrics::outcome(&result)])
+            .inc();
+        result
+    }
+
+    async fn invite_to_group(
+        &self,
+        group: GroupId,
+        host: UserId,
+        guest: UserId,
+    ) -> Result<(), RelationError> {
+        let result = self
+            .tx_manager
+            .with_retry(move || self.try_invite_to_group(group, host, guest))
+            .await;
+        let outcome = match &result {
+            Err(RelationError::NotOwner) => "not_owner",
+            Err(RelationError::UserNotFound | RelationError::SelfRelation) => "rejected",
+            other => metrics::outcome(other),
+        };
+        metrics::GROUP_INVITES.with_label_values(&[outcome]).inc();
+        result
+    }
+
+    async fn disband_group(&self, group: GroupId, owner: UserId) -> Result<(), RelationError> {
+        self.tx_manager
+            .with_retry(move || self.try_disband_group(group, owner))
+            .await
+    }
     async fn list_groups(
         &self,
         user_id: UserId,
         page_size: PageSize,
         after: Option<GroupCursor>,
+        projection: GroupProjection,
     ) -> std::result::Result<Vec<GroupSummary>, RelationError> {
-        self.group_repo.list_groups(user_id, page_size, after).await
+        self.group_repo
+            .list_groups(user_id, page_size, after, projection)
+            .await
+    }
+
+    async fn group_member_counts(
+        &self,
+        user_id: UserId,
+        group_ids: &[GroupId],
+    ) -> std::result::Result<Vec<GroupMemberCount>, RelationError> {
+        self.group_repo.count_members(user_id, group_ids).await
     }
 
     async fn list_group_members(
@@ -385,4 +843,41 @@ impl RelationshipService for RealRelationshipService {
 
         Ok(summary)
     }
+
+    async fn group_appearance(
+        &self,
+        user_id: UserId,
+        group: GroupId,
+    ) -> Result<GroupAppearance, RelationError> {
+        self.group_role(group, user_id).await?;
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+
+        let appearance = self
+            .group_repo
+            .get_appearance_in_tx(&mut *tx, group)
+            .await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| RelationError::Store(e.to_string()))?;
+
+        Ok(appearance)
+    }
+
+    async fn set_group_appearance(
+        &self,
+        group: GroupId,
+        owner: UserId,
+        appearance: GroupAppearance,
+    ) -> Result<(), RelationError> {
+        check_group_appearance(&appearance)?;
+        self.tx_manager
+            .with_retry(|| self.try_set_group_appearance(group, owner, &appearance))
+            .await
+    }
 }
@@ -0,0 +1,87 @@
+use crate::application_impl::fake_store::{FakePeer, FakeStore};
+use crate::application_port::*;
+use crate::domain_model::*;
+use chrono::{NaiveTime, Utc};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+/// In-memory `StatsService`; see `FakeStore`. Counts by walking every
+/// user and message, which is fine for the handful a fake holds.
+pub struct FakeStatsService {
+    store: Arc<FakeStore>,
+}
+
+impl FakeStatsService {
+    pub fn new(store: Arc<FakeStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl StatsService for FakeStatsService {
+    async fn daily(&self, days: u32) -> Result<Vec<DailyStats>, StatsError> {
+        check_days(days)?;
+        let today = Utc::now().date_naive();
+        let since = first_day(today, days).and_time(NaiveTime::MIN).and_utc();
+
+        let state = self.store.state();
+        let mut by_day = BTreeMap::new();
+        let mut senders = HashSet::new();
+        let messages = state
+            .conversations
+            .values()
+            .flat_map(|c| &c.messages)
+            .filter(|m| m.kind == MessageKind::User && m.created_at >= since);
+        for message in messages {
+            let day = message.created_at.date_naive();
+            let stats = by_day.entry(day).or_insert_with(|| DailyStats::empty(day));
+            stats.messages += 1;
+            if senders.insert((day, message.sender)) {
+                stats.active_users += 1;
+            }
+        }
+        for user in state.users.values().filter(|u| u.created_at >= since) {
+            let day = user.created_at.date_naive();
+            by_day
+                .entry(day)
+                .or_insert_with(|| DailyStats::empty(day))
+                .signups += 1;
+        }
+
+        Ok(fill_days(today, days, by_day.into_values().collect()))
+    }
+
+    async fn top_groups(&self, days: u32, limit: u32) -> Result<Vec<GroupTraffic>, StatsError> {
+        check_days(days)?;
+        check_top_groups_limit(limit)?;
+        let today = Utc::now().date_naive();
+        let since = first_day(today, days).and_time(NaiveTime::MIN).and_utc();
+
+        let state = self.store.state();
+        let mut traffic: Vec<GroupTraffic> = state
+            .conversations
+            .iter()
+            .filter_map(|(conversation_id, conversation)| {
+                let FakePeer::Group(group_id) = conversation.peer else {
+                    return None;
+                };
+                let group = state.groups.get(&group_id).filter(|g| !g.disbanded)?;
+                let messages = conversation
+                    .messages
+                    .iter()
+                    .filter(|m| m.kind == MessageKind::User && m.created_at >= since)
+                    .count() as u64;
+                (messages > 0).then(|| GroupTraffic {
+                    group_id,
+                    conversation_id: *conversation_id,
+                    name: group.name.clone(),
+                    messages,
+                })
+            })
+            .collect();
+        traffic.sort_by_key(|t| (Reverse(t.messages), t.group_id));
+        traffic.truncate(limit as usize);
+        Ok(traffic)
+    }
+}
@@ -0,0 +1,36 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{NaiveTime, Utc};
+use std::sync::Arc;
+
+pub struct RealStatsService {
+    stats_repo: Arc<dyn StatsRepo>,
+}
+
+impl RealStatsService {
+    pub fn new(stats_repo: Arc<dyn StatsRepo>) -> Self {
+        Self { stats_repo }
+    }
+}
+
+#[async_trait::async_trait]
+impl StatsService for RealStatsService {
+    async fn daily(&self, days: u32) -> Result<Vec<DailyStats>, StatsError> {
+        check_days(days)?;
+        let today = Utc::now().date_naive();
+        let since = first_day(today, days).and_time(NaiveTime::MIN).and_utc();
+
+        let rows = self.stats_repo.daily(since).await?;
+        Ok(fill_days(today, days, rows))
+    }
+
+    async fn top_groups(&self, days: u32, limit: u32) -> Result<Vec<GroupTraffic>, StatsError> {
+        check_days(days)?;
+        check_top_groups_limit(limit)?;
+        let today = Utc::now().date_naive();
+        let since = first_day(today, days).and_time(NaiveTime::MIN).and_utc();
+
+        self.stats_repo.top_groups(since, limit).await
+    }
+}
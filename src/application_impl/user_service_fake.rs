@@ -0,0 +1,94 @@
+use crate::application_impl::fake_store::FakeStore;
+use crate::application_port::{AuthError, UserService};
+use crate::domain_model::*;
+use crate::domain_port::EventType;
+use std::sync::Arc;
+
+/// In-memory `UserService`; see `FakeStore`.
+pub struct FakeUserService {
+    store: Arc<FakeStore>,
+}
+
+impl FakeUserService {
+    pub fn new(store: Arc<FakeStore>) -> Self {
+        Self { store }
+    }
+
+    /// Deleting and deactivating look the same here: an inactive user is
+    /// already left out of every list. Only deleting respects a legal hold.
+    fn retire_account(&self, user_id: UserId, delete: bool) -> Result<(), AuthError> {
+        {
+            let mut state = self.store.state();
+            let Some(user) = state.users.get_mut(&user_id).filter(|u| u.active) else {
+                return Err(AuthError::UserNotFound);
+            };
+            if delete && user.legal_hold {
+                return Err(AuthError::LegalHold);
+            }
+            user.active = false;
+        }
+
+        self.store.publish(
+            EventType::SessionTerminated,
+            user_id.0,
+            vec![user_id],
+            &S2CEvent::SessionTerminated(SessionTerminated {
+                reason: TerminationReason::Deactivated,
+            }),
+        );
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl UserService for FakeUserService {
+    async fn resolve_username(&self, username: &str) -> Result<UserId, AuthError> {
+        let state = self.store.state();
+        state
+            .usernames
+            .get(username)
+            .copied()
+            .filter(|user_id| state.is_active(*user_id))
+            .ok_or(AuthError::UserNotFound)
+    }
+
+    async fn deactivate_account(&self, user_id: UserId) -> Result<(), AuthError> {
+        self.retire_account(user_id, false)
+    }
+
+    async fn delete_account(&self, user_id: UserId) -> Result<(), AuthError> {
+        self.retire_account(user_id, true)
+    }
+
+    async fn disconnect_sessions(&self, user_id: UserId) -> Result<(), AuthError> {
+        self.store.publish(
+            EventType::SessionTerminated,
+            user_id.0,
+            vec![user_id],
+            &S2CEvent::SessionTerminated(SessionTerminated {
+                reason: TerminationReason::AdminDisconnect,
+            }),
+        );
+        Ok(())
+    }
+
+    async fn set_legal_hold(&self, user_id: UserId, hold: bool) -> Result<(), AuthError> {
+        let mut state = self.store.state();
+        let user = state
+            .users
+            .get_mut(&user_id)
+            .ok_or(AuthError::UserNotFound)?;
+        user.legal_hold = hold;
+        Ok(())
+    }
+
+    async fn set_bot(&self, user_id: UserId, bot: bool) -> Result<(), AuthError> {
+        let mut state = self.store.state();
+        let user = state
+            .users
+            .get_mut(&user_id)
+            .ok_or(AuthError::UserNotFound)?;
+        user.is_bot = bot;
+        Ok(())
+    }
+}
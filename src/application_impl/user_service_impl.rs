@@ -1,20 +1,80 @@
 use crate::application_port::{AuthError, UserService};
-use crate::domain_model::UserId;
-use crate::domain_port::{TxManager, UserRepo};
+use crate::domain_model::*;
+use crate::domain_port::{AuthRepo, EventType, OutboxEvent, OutboxRepo, TxManager, UserRepo};
 use std::sync::Arc;
 
 pub struct RealUserService {
     user_repo: Arc<dyn UserRepo>,
+    auth_repo: Arc<dyn AuthRepo>,
+    outbox_repo: Arc<dyn OutboxRepo>,
     tx_manager: Arc<dyn TxManager>,
 }
 
 impl RealUserService {
-    pub fn new(user_repo: Arc<dyn UserRepo>, tx_manager: Arc<dyn TxManager>) -> RealUserService {
+    pub fn new(
+        user_repo: Arc<dyn UserRepo>,
+        auth_repo: Arc<dyn AuthRepo>,
+        outbox_repo: Arc<dyn OutboxRepo>,
+        tx_manager: Arc<dyn TxManager>,
+    ) -> RealUserService {
         RealUserService {
             user_repo,
+            auth_repo,
+            outbox_repo,
             tx_manager,
         }
     }
+
+    async fn retire_account(&self, user_id: UserId, delete: bool) -> Result<(), AuthError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        let changed = if delete {
+            self.user_repo.soft_delete_in_tx(&mut *tx, user_id).await?
+        } else {
+            self.user_repo.deactivate_in_tx(&mut *tx, user_id).await?
+        };
+        if !changed {
+            let held = delete
+                && self
+                    .user_repo
+                    .get_in_tx(&mut *tx, user_id)
+                    .await?
+                    .is_some_and(|user| user.legal_hold);
+            return Err(if held {
+                AuthError::LegalHold
+            } else {
+                AuthError::UserNotFound
+            });
+        }
+        self.auth_repo
+            .deactivate_credentials_in_tx(&mut *tx, user_id)
+            .await?;
+
+        // Every node consumes this and closes the user's local sessions.
+        let event = OutboxEvent::new(
+            EventType::SessionTerminated,
+            Some(user_id.0),
+            vec![user_id],
+            &S2CEvent::SessionTerminated(SessionTerminated {
+                reason: TerminationReason::Deactivated,
+            }),
+        )
+        .map_err(|e| AuthError::Store(format!("compose session.terminated event: {e}")))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| AuthError::Store(format!("enqueue session.terminated event: {e}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        Ok(())
+    }
 }
 
 #[async_trait::async_trait]
@@ -37,4 +97,85 @@ impl UserService for RealUserService {
 
         Ok(user_id)
     }
+
+    async fn deactivate_account(&self, user_id: UserId) -> Result<(), AuthError> {
+        self.tx_manager
+            .with_retry(move || self.retire_account(user_id, false))
+            .await
+    }
+
+    async fn delete_account(&self, user_id: UserId) -> Result<(), AuthError> {
+        self.tx_manager
+            .with_retry(move || self.retire_account(user_id, true))
+            .await
+    }
+
+    async fn disconnect_sessions(&self, user_id: UserId) -> Result<(), AuthError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        // every node consumes this and closes the user's local sessions
+        let event = OutboxEvent::new(
+            EventType::SessionTerminated,
+            Some(user_id.0),
+            vec![user_id],
+            &S2CEvent::SessionTerminated(SessionTerminated {
+                reason: TerminationReason::AdminDisconnect,
+            }),
+        )
+        .map_err(|e| AuthError::Store(format!("compose session.terminated event: {e}")))?;
+        self.outbox_repo
+            .enqueue_in_tx(&mut *tx, &event)
+            .await
+            .map_err(|e| AuthError::Store(format!("enqueue session.terminated event: {e}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn set_legal_hold(&self, user_id: UserId, hold: bool) -> Result<(), AuthError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        if !self
+            .user_repo
+            .set_legal_hold_in_tx(&mut *tx, user_id, hold)
+            .await?
+        {
+            return Err(AuthError::UserNotFound);
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn set_bot(&self, user_id: UserId, bot: bool) -> Result<(), AuthError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        if !self.user_repo.set_bot_in_tx(&mut *tx, user_id, bot).await? {
+            return Err(AuthError::UserNotFound);
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        Ok(())
+    }
 }
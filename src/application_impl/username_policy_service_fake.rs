@@ -0,0 +1,64 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use std::sync::Mutex;
+
+/// In-memory `UsernamePolicyService`; managed rules last as long as the
+/// process.
+pub struct FakeUsernamePolicyService {
+    configured: Vec<UsernameRule>,
+    managed: Mutex<Vec<UsernameRule>>,
+}
+
+impl FakeUsernamePolicyService {
+    /// Fails on a configured pattern that isn't a regex.
+    pub fn new(configured: Vec<UsernameRule>) -> Result<Self, AuthError> {
+        UsernamePolicy::compile(&configured)?;
+        Ok(Self {
+            configured,
+            managed: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn managed(&self) -> Vec<UsernameRule> {
+        self.managed
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl UsernamePolicyService for FakeUsernamePolicyService {
+    async fn check(&self, username: &str) -> Result<(), AuthError> {
+        let managed = self.managed();
+        let policy = UsernamePolicy::compile(self.configured.iter().chain(&managed))?;
+        if !policy.allows(username) {
+            return Err(AuthError::UsernameNotAllowed);
+        }
+        Ok(())
+    }
+
+    async fn rules(&self) -> Result<UsernameRules, AuthError> {
+        Ok(UsernameRules {
+            configured: self.configured.clone(),
+            managed: self.managed(),
+        })
+    }
+
+    async fn add_rule(&self, rule: UsernameRule) -> Result<bool, AuthError> {
+        check_username_rule(&rule)?;
+        let mut managed = self.managed.lock().unwrap_or_else(|e| e.into_inner());
+        if managed.contains(&rule) {
+            return Ok(false);
+        }
+        managed.push(rule);
+        Ok(true)
+    }
+
+    async fn remove_rule(&self, rule: UsernameRule) -> Result<bool, AuthError> {
+        let mut managed = self.managed.lock().unwrap_or_else(|e| e.into_inner());
+        let before = managed.len();
+        managed.retain(|r| *r != rule);
+        Ok(managed.len() < before)
+    }
+}
@@ -0,0 +1,53 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use std::sync::Arc;
+
+pub struct RealUsernamePolicyService {
+    configured: Vec<UsernameRule>,
+    username_rule_repo: Arc<dyn UsernameRuleRepo>,
+}
+
+impl RealUsernamePolicyService {
+    /// Fails on a configured pattern that isn't a regex.
+    pub fn new(
+        configured: Vec<UsernameRule>,
+        username_rule_repo: Arc<dyn UsernameRuleRepo>,
+    ) -> Result<Self, AuthError> {
+        UsernamePolicy::compile(&configured)?;
+        Ok(Self {
+            configured,
+            username_rule_repo,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl UsernamePolicyService for RealUsernamePolicyService {
+    async fn check(&self, username: &str) -> Result<(), AuthError> {
+        // signups are rare enough to read and compile the rules each time,
+        // which keeps every node current without invalidation
+        let managed = self.username_rule_repo.list().await?;
+        let policy = UsernamePolicy::compile(self.configured.iter().chain(&managed))?;
+        if !policy.allows(username) {
+            return Err(AuthError::UsernameNotAllowed);
+        }
+        Ok(())
+    }
+
+    async fn rules(&self) -> Result<UsernameRules, AuthError> {
+        Ok(UsernameRules {
+            configured: self.configured.clone(),
+            managed: self.username_rule_repo.list().await?,
+        })
+    }
+
+    async fn add_rule(&self, rule: UsernameRule) -> Result<bool, AuthError> {
+        check_username_rule(&rule)?;
+        self.username_rule_repo.add(&rule).await
+    }
+
+    async fn remove_rule(&self, rule: UsernameRule) -> Result<bool, AuthError> {
+        self.username_rule_repo.remove(&rule).await
+    }
+}
@@ -0,0 +1,241 @@
+use crate::application_impl::fake_store::{FakePeer, FakeState, FakeStore};
+use crate::application_port::*;
+use crate::domain_model::*;
+use chrono::{SubsecRound, Utc};
+use std::sync::Arc;
+
+/// In-memory `WebhookService`; see `FakeStore`.
+pub struct FakeWebhookService {
+    store: Arc<FakeStore>,
+    conversation_service: Arc<dyn ConversationService>,
+    allow_http: bool,
+    allow_private: bool,
+}
+
+impl FakeWebhookService {
+    pub fn new(
+        store: Arc<FakeStore>,
+        conversation_service: Arc<dyn ConversationService>,
+        allow_http: bool,
+        allow_private: bool,
+    ) -> Self {
+        Self {
+            store,
+            conversation_service,
+            allow_http,
+            allow_private,
+        }
+    }
+}
+
+fn require_owner(
+    state: &FakeState,
+    conversation_id: ConversationId,
+    user_id: UserId,
+) -> Result<(), WebhookError> {
+    let members = state.members(conversation_id).unwrap_or_default();
+    if !members.contains(&user_id) {
+        return Err(WebhookError::NotMember);
+    }
+    match state.conversations[&conversation_id].peer {
+        FakePeer::Group(group_id) if state.groups[&group_id].owner == user_id => Ok(()),
+        _ => Err(WebhookError::Forbidden),
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookService for FakeWebhookService {
+    async fn register(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+        url: &str,
+    ) -> Result<NewWebhook, WebhookError> {
+        check_webhook_url(url, self.allow_http, self.allow_private)?;
+        let mut state = self.store.state();
+        require_owner(&state, conversation_id, by)?;
+
+        let existing = state
+            .webhooks
+            .iter()
+            .filter(|(w, _)| w.conversation_id == conversation_id)
+            .count();
+        if existing >= MAX_WEBHOOKS_PER_CONVERSATION {
+            return Err(WebhookError::Invalid("too many webhooks"));
+        }
+        let webhook = Webhook {
+            webhook_id: WebhookId(uuid::Uuid::new_v4()),
+            conversation_id,
+            url: url.to_string(),
+            created_by: by,
+            created_at: Utc::now().trunc_subsecs(6),
+        };
+        let secret = nanoid::nanoid!(32);
+        state.webhooks.push((webhook.clone(), secret.clone()));
+
+        Ok(NewWebhook { webhook, secret })
+    }
+
+    async fn list(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<Webhook>, WebhookError> {
+        let state = self.store.state();
+        require_owner(&state, conversation_id, by)?;
+        Ok(state
+            .webhooks
+            .iter()
+            .filter(|(w, _)| w.conversation_id == conversation_id)
+            .map(|(w, _)| w.clone())
+            .collect())
+    }
+
+    async fn remove(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+        webhook_id: WebhookId,
+    ) -> Result<(), WebhookError> {
+        let mut state = self.store.state();
+        require_owner(&state, conversation_id, by)?;
+        let before = state.webhooks.len();
+        state
+            .webhooks
+            .retain(|(w, _)| w.conversation_id != conversation_id || w.webhook_id != webhook_id);
+        if state.webhooks.len() == before {
+            return Err(WebhookError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn targets(
+        &self,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<WebhookTarget>, WebhookError> {
+        let state = self.store.state();
+        Ok(state
+            .webhooks
+            .iter()
+            .filter(|(w, _)| w.conversation_id == conversation_id)
+            .map(|(w, secret)| WebhookTarget {
+                webhook_id: w.webhook_id,
+                url: w.url.clone(),
+                secret: secret.clone(),
+            })
+            .collect())
+    }
+
+    async fn issue_incoming(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+        name: &str,
+        bot_user_id: UserId,
+    ) -> Result<NewIncomingWebhook, WebhookError> {
+        check_incoming_name(name)?;
+        let mut state = self.store.state();
+        require_owner(&state, conversation_id, by)?;
+        let members = state.members(conversation_id).unwrap_or_default();
+        if !members.contains(&bot_user_id) {
+            return Err(WebhookError::Invalid("bot is not a member"));
+        }
+        if !state
+            .users
+            .get(&bot_user_id)
+            .is_some_and(|user| user.is_bot && user.active)
+        {
+            return Err(WebhookError::Invalid("not a bot account"));
+        }
+
+        let existing = state
+            .incoming_webhooks
+            .iter()
+            .filter(|(w, _)| w.conversation_id == conversation_id)
+            .count();
+        if existing >= MAX_WEBHOOKS_PER_CONVERSATION {
+            return Err(WebhookError::Invalid("too many webhooks"));
+        }
+        let webhook = IncomingWebhook {
+            incoming_webhook_id: IncomingWebhookId(uuid::Uuid::new_v4()),
+            conversation_id,
+            name: name.trim().to_string(),
+            bot_user_id,
+            created_by: by,
+            created_at: Utc::now().trunc_subsecs(6),
+        };
+        let token = nanoid::nanoid!(40);
+        state
+            .incoming_webhooks
+            .push((webhook.clone(), incoming_token_hash(&token)));
+
+        Ok(NewIncomingWebhook { webhook, token })
+    }
+
+    async fn list_incoming(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<IncomingWebhook>, WebhookError> {
+        let state = self.store.state();
+        require_owner(&state, conversation_id, by)?;
+        Ok(state
+            .incoming_webhooks
+            .iter()
+            .filter(|(w, _)| w.conversation_id == conversation_id)
+            .map(|(w, _)| w.clone())
+            .collect())
+    }
+
+    async fn revoke_incoming(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+        incoming_webhook_id: IncomingWebhookId,
+    ) -> Result<(), WebhookError> {
+        let mut state = self.store.state();
+        require_owner(&state, conversation_id, by)?;
+        let before = state.incoming_webhooks.len();
+        state.incoming_webhooks.retain(|(w, _)| {
+            w.conversation_id != conversation_id || w.incoming_webhook_id != incoming_webhook_id
+        });
+        if state.incoming_webhooks.len() == before {
+            return Err(WebhookError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn post_incoming(
+        &self,
+        token: &str,
+        content: &str,
+        key: Option<IdempotencyKey>,
+    ) -> Result<SentMessage, WebhookError> {
+        if content.trim().is_empty() {
+            return Err(WebhookError::Invalid("empty message"));
+        }
+        let token_hash = incoming_token_hash(token);
+        // the lock is released before sending, which takes it again
+        let webhook = self
+            .store
+            .state()
+            .incoming_webhooks
+            .iter()
+            .find(|(_, hash)| *hash == token_hash)
+            .map(|(w, _)| w.clone())
+            .ok_or(WebhookError::NotFound)?;
+
+        let sent = self
+            .conversation_service
+            .send_message(
+                webhook.conversation_id,
+                webhook.bot_user_id,
+                content,
+                incoming_message_id(webhook.incoming_webhook_id, key),
+                None,
+                &[],
+            )
+            .await?;
+        Ok(sent)
+    }
+}
@@ -0,0 +1,341 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{SubsecRound, Utc};
+use std::sync::Arc;
+
+pub struct RealWebhookService {
+    webhook_repo: Arc<dyn WebhookRepo>,
+    incoming_webhook_repo: Arc<dyn IncomingWebhookRepo>,
+    conversation_role_repo: Arc<dyn ConversationRoleRepo>,
+    user_repo: Arc<dyn UserRepo>,
+    conversation_service: Arc<dyn ConversationService>,
+    tx_manager: Arc<dyn TxManager>,
+    allow_http: bool,
+    allow_private: bool,
+}
+
+impl RealWebhookService {
+    /// `allow_http` accepts plain `http` URLs and `allow_private` hosts on
+    /// private networks, both for development.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        webhook_repo: Arc<dyn WebhookRepo>,
+        incoming_webhook_repo: Arc<dyn IncomingWebhookRepo>,
+        conversation_role_repo: Arc<dyn ConversationRoleRepo>,
+        user_repo: Arc<dyn UserRepo>,
+        conversation_service: Arc<dyn ConversationService>,
+        tx_manager: Arc<dyn TxManager>,
+        allow_http: bool,
+        allow_private: bool,
+    ) -> Self {
+        Self {
+            webhook_repo,
+            incoming_webhook_repo,
+            conversation_role_repo,
+            user_repo,
+            conversation_service,
+            tx_manager,
+            allow_http,
+            allow_private,
+        }
+    }
+
+    async fn require_owner(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<(), WebhookError> {
+        let is_member = self
+            .conversation_role_repo
+            .membership_exists(conversation_id, user_id)
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        if !is_member {
+            return Err(WebhookError::NotMember);
+        }
+        // direct conversations have no roles, so nobody owns them
+        match self
+            .conversation_role_repo
+            .get_role_by_conversation_id(user_id, conversation_id)
+            .await
+        {
+            Ok(GroupMemberRole::Owner) => Ok(()),
+            Ok(GroupMemberRole::Member) | Err(RelationError::NotMember) => {
+                Err(WebhookError::Forbidden)
+            }
+            Err(e) => Err(WebhookError::Store(e.to_string())),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookService for RealWebhookService {
+    async fn register(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+        url: &str,
+    ) -> Result<NewWebhook, WebhookError> {
+        check_webhook_url(url, self.allow_http, self.allow_private)?;
+        self.require_owner(conversation_id, by).await?;
+
+        let webhook = Webhook {
+            webhook_id: WebhookId(uuid::Uuid::new_v4()),
+            conversation_id,
+            url: url.to_string(),
+            created_by: by,
+            created_at: Utc::now().trunc_subsecs(6),
+        };
+        let secret = nanoid::nanoid!(32);
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        let existing = self
+            .webhook_repo
+            .list_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| WebhookError::Store(format!("list webhooks: {e}")))?;
+        if existing.len() >= MAX_WEBHOOKS_PER_CONVERSATION {
+            return Err(WebhookError::Invalid("too many webhooks"));
+        }
+        self.webhook_repo
+            .insert_in_tx(&mut *tx, &webhook, &secret)
+            .await
+            .map_err(|e| WebhookError::Store(format!("insert webhook: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+
+        Ok(NewWebhook { webhook, secret })
+    }
+
+    async fn list(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<Webhook>, WebhookError> {
+        self.require_owner(conversation_id, by).await?;
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        let webhooks = self
+            .webhook_repo
+            .list_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| WebhookError::Store(format!("list webhooks: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+
+        Ok(webhooks)
+    }
+
+    async fn remove(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+        webhook_id: WebhookId,
+    ) -> Result<(), WebhookError> {
+        self.require_owner(conversation_id, by).await?;
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        let deleted = self
+            .webhook_repo
+            .delete_in_tx(&mut *tx, conversation_id, webhook_id)
+            .await
+            .map_err(|e| WebhookError::Store(format!("delete webhook: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+
+        if !deleted {
+            return Err(WebhookError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn targets(
+        &self,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<WebhookTarget>, WebhookError> {
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        let targets = self
+            .webhook_repo
+            .targets_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| WebhookError::Store(format!("webhook targets: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+
+        Ok(targets)
+    }
+
+    async fn issue_incoming(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+        name: &str,
+        bot_user_id: UserId,
+    ) -> Result<NewIncomingWebhook, WebhookError> {
+        check_incoming_name(name)?;
+        self.require_owner(conversation_id, by).await?;
+        let bot_is_member = self
+            .conversation_role_repo
+            .membership_exists(conversation_id, bot_user_id)
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        if !bot_is_member {
+            return Err(WebhookError::Invalid("bot is not a member"));
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        // a plain member would let the owner post in a person's name
+        let bot = self
+            .user_repo
+            .get_in_tx(&mut *tx, bot_user_id)
+            .await
+            .map_err(|e| WebhookError::Store(format!("get bot user: {e}")))?;
+        if !bot.is_some_and(|user| user.is_bot && user.is_active) {
+            return Err(WebhookError::Invalid("not a bot account"));
+        }
+
+        let webhook = IncomingWebhook {
+            incoming_webhook_id: IncomingWebhookId(uuid::Uuid::new_v4()),
+            conversation_id,
+            name: name.trim().to_string(),
+            bot_user_id,
+            created_by: by,
+            created_at: Utc::now().trunc_subsecs(6),
+        };
+        let token = nanoid::nanoid!(40);
+
+        let existing = self
+            .incoming_webhook_repo
+            .list_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| WebhookError::Store(format!("list incoming webhooks: {e}")))?;
+        if existing.len() >= MAX_WEBHOOKS_PER_CONVERSATION {
+            return Err(WebhookError::Invalid("too many webhooks"));
+        }
+        self.incoming_webhook_repo
+            .insert_in_tx(&mut *tx, &webhook, &incoming_token_hash(&token))
+            .await
+            .map_err(|e| WebhookError::Store(format!("insert incoming webhook: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+
+        Ok(NewIncomingWebhook { webhook, token })
+    }
+
+    async fn list_incoming(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<IncomingWebhook>, WebhookError> {
+        self.require_owner(conversation_id, by).await?;
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        let webhooks = self
+            .incoming_webhook_repo
+            .list_in_tx(&mut *tx, conversation_id)
+            .await
+            .map_err(|e| WebhookError::Store(format!("list incoming webhooks: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+
+        Ok(webhooks)
+    }
+
+    async fn revoke_incoming(
+        &self,
+        by: UserId,
+        conversation_id: ConversationId,
+        incoming_webhook_id: IncomingWebhookId,
+    ) -> Result<(), WebhookError> {
+        self.require_owner(conversation_id, by).await?;
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        let deleted = self
+            .incoming_webhook_repo
+            .delete_in_tx(&mut *tx, conversation_id, incoming_webhook_id)
+            .await
+            .map_err(|e| WebhookError::Store(format!("delete incoming webhook: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+
+        if !deleted {
+            return Err(WebhookError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn post_incoming(
+        &self,
+        token: &str,
+        content: &str,
+        key: Option<IdempotencyKey>,
+    ) -> Result<SentMessage, WebhookError> {
+        if content.trim().is_empty() {
+            return Err(WebhookError::Invalid("empty message"));
+        }
+
+        let mut tx = self
+            .tx_manager
+            .begin()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        let webhook = self
+            .incoming_webhook_repo
+            .find_by_token_in_tx(&mut *tx, &incoming_token_hash(token))
+            .await
+            .map_err(|e| WebhookError::Store(format!("find incoming webhook: {e}")))?;
+        tx.commit()
+            .await
+            .map_err(|e| WebhookError::Store(e.to_string()))?;
+        let webhook = webhook.ok_or(WebhookError::NotFound)?;
+
+        let sent = self
+            .conversation_service
+            .send_message(
+                webhook.conversation_id,
+                webhook.bot_user_id,
+                content,
+                incoming_message_id(webhook.incoming_webhook_id, key),
+                None,
+                &[],
+            )
+            .await?;
+        Ok(sent)
+    }
+}
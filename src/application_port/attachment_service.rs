@@ -0,0 +1,54 @@
+use crate::application_port::ChatError;
+use crate::domain_model::*;
+use crate::domain_port::Blob;
+
+/// Largest upload, in bytes.
+pub const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+/// Longest content type, as the `attachment.mime` column holds it.
+pub const MAX_MIME_LEN: usize = 127;
+
+/// Uploads are non-empty, within `MAX_ATTACHMENT_BYTES`, and typed
+/// `type/subtype` in plain ASCII, so the type can be served back as is.
+pub fn check_upload(mime: &str, size: u64) -> Result<(), ChatError> {
+    if size == 0 || size > MAX_ATTACHMENT_BYTES {
+        return Err(ChatError::InvalidUpload("size"));
+    }
+    let token = |part: &str| {
+        !part.is_empty()
+            && part
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$&^_.+-".contains(&b))
+    };
+    let well_formed = mime.len() <= MAX_MIME_LEN
+        && mime
+            .split_once('/')
+            .is_some_and(|(kind, subtype)| token(kind) && token(subtype));
+    if !well_formed {
+        return Err(ChatError::InvalidUpload("content type"));
+    }
+    Ok(())
+}
+
+/// Files members upload to a conversation, to send with a message; see
+/// `ConversationService::send_message`.
+#[async_trait::async_trait]
+pub trait AttachmentService: Send + Sync {
+    /// Stores `content` for `uploader` to send. No one else sees it until a
+    /// message carries it.
+    async fn upload(
+        &self,
+        uploader: UserId,
+        conversation_id: ConversationId,
+        mime: &str,
+        content: Vec<u8>,
+    ) -> Result<Attachment, ChatError>;
+    /// For members only. The files of a deleted message, and uploads no
+    /// message carries yet unless they are the caller's, are
+    /// `AttachmentNotFound`.
+    async fn download(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        attachment_id: AttachmentId,
+    ) -> Result<Blob, ChatError>;
+}
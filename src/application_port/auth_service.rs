@@ -1,6 +1,7 @@
 use crate::domain_model::UserId;
+use crate::logger::Secret;
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, thiserror::Error)]
 pub enum AuthError {
@@ -8,12 +9,26 @@ pub enum AuthError {
     InvalidCredentials,
     #[error("user already exists")]
     UserExists,
+    #[error("username not allowed")]
+    UsernameNotAllowed,
+    #[error("invalid username rule: {0}")]
+    InvalidUsernameRule(String),
+    #[error("signup needs an invite code")]
+    InviteRequired,
+    #[error("invite code is unknown, used up or expired")]
+    InvalidInvite,
+    #[error("invalid invite request: {0}")]
+    InvalidInviteMint(String),
     #[error("user not found")]
     UserNotFound,
+    #[error("user is under legal hold")]
+    LegalHold,
     #[error("token invalid")]
     TokenInvalid,
     #[error("token expired")]
     TokenExpired,
+    #[error("QR login unknown, expired or already approved")]
+    QrLoginNotFound,
     #[error("captcha error: {0}")]
     Captcha(String),
     #[error("store error: {0}")]
@@ -25,13 +40,16 @@ pub enum AuthError {
 #[derive(Debug, Clone)]
 pub struct SignupInput {
     pub username: String,
-    pub password: String,
+    pub password: Secret<String>,
+    /// Only read while `auth.invite_only` is set.
+    pub invite_code: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct LoginInput {
     pub username: String,
-    pub password: String,
+    pub password: Secret<String>,
+    pub device: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -41,10 +59,10 @@ pub struct LoginResult {
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct AccessToken(pub String);
+pub struct AccessToken(pub Secret<String>);
 
 #[derive(Debug, Clone, Serialize)]
-pub struct RefreshToken(pub String);
+pub struct RefreshToken(pub Secret<String>);
 
 #[derive(Debug, Clone, Serialize)]
 pub struct AuthTokens {
@@ -54,10 +72,60 @@ pub struct AuthTokens {
     pub refresh_token_expires_at: DateTime<Utc>,
 }
 
+/// A live refresh JTI and what is known about the login that created it.
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshSession {
+    pub jti: String,
+    pub user_id: UserId,
+    pub device: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    /// Login time; carried across rotations.
+    pub started_at: DateTime<Utc>,
+}
+
+/// A desktop's pending QR login. `nonce` goes into the QR code; `poll_secret`
+/// stays with the desktop, so whoever else sees the code can approve it at
+/// most, never collect the tokens.
+#[derive(Debug, Clone, Serialize)]
+pub struct QrLoginTicket {
+    pub nonce: String,
+    pub poll_secret: Secret<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Admin,
+}
+
+pub const SCOPE_CHAT: &str = "chat";
+pub const SCOPE_ADMIN: &str = "admin";
+
+/// Authorization carried inside an access token.
+#[derive(Debug, Clone, Default)]
+pub struct Grants {
+    pub roles: Vec<Role>,
+    pub scopes: Vec<String>,
+}
+
+impl Grants {
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.contains(&role)
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TokenVerifyResult {
     pub user_id: UserId,
     pub jti: Option<String>,
+    /// Empty for refresh tokens.
+    pub grants: Grants,
 }
 
 #[async_trait::async_trait]
@@ -66,11 +134,13 @@ pub trait TokenCodec: Send + Sync {
         &self,
         user: UserId,
         jti: Option<String>,
+        grants: &Grants,
     ) -> Result<(AccessToken, DateTime<Utc>), AuthError>;
     async fn issue_refresh_token(
         &self,
         user: UserId,
         jti: String,
+        session_started_at: DateTime<Utc>,
     ) -> Result<(RefreshToken, DateTime<Utc>), AuthError>;
     async fn verify_access_token(
         &self,
@@ -94,5 +164,25 @@ pub trait AuthService: Send + Sync {
     async fn signup(&self, request: SignupInput) -> Result<UserId, AuthError>;
     async fn login(&self, request: LoginInput) -> Result<LoginResult, AuthError>;
     async fn verify_token(&self, token: &str) -> Result<UserId, AuthError>;
+    /// Decode an access token and return its claims, without a store lookup.
+    async fn verify_claims(&self, token: &str) -> Result<TokenVerifyResult, AuthError>;
+    /// Whether the user holds `role` now. An access token carries the roles
+    /// it was issued with for as long as it lives, taken away or not.
+    async fn has_role(&self, user_id: UserId, role: Role) -> Result<bool, AuthError>;
     async fn refresh_token(&self, refresh_token: &str) -> Result<AuthTokens, AuthError>;
+    /// Live refresh sessions of the user, oldest first.
+    async fn list_sessions(&self, user_id: UserId) -> Result<Vec<RefreshSession>, AuthError>;
+    /// Invalidate every refresh token of the user. Returns how many were live.
+    async fn revoke_sessions(&self, user_id: UserId) -> Result<u64, AuthError>;
+    /// Opens a QR login for a client without credentials, typically a desktop.
+    async fn start_qr_login(&self, device: Option<String>) -> Result<QrLoginTicket, AuthError>;
+    /// A signed-in user, typically on mobile, lets the QR login with `nonce`
+    /// sign in as them. Fails with `QrLoginNotFound` once approved or expired.
+    async fn approve_qr_login(&self, user_id: UserId, nonce: &str) -> Result<(), AuthError>;
+    /// `None` until approved; then the new session, handed out only once.
+    async fn poll_qr_login(
+        &self,
+        nonce: &str,
+        poll_secret: &str,
+    ) -> Result<Option<LoginResult>, AuthError>;
 }
@@ -0,0 +1,62 @@
+use crate::domain_model::*;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use thiserror::Error;
+
+/// Most identifiers one user may be found by.
+pub const MAX_OWN_CONTACT_HASHES: usize = 10;
+/// Most hashes one lookup may carry; about an address book.
+pub const MAX_DISCOVERY_BATCH: usize = 1000;
+
+#[derive(Debug, Error)]
+pub enum DiscoveryError {
+    /// Only users who can be found may look others up.
+    #[error("contact discovery is off for this user")]
+    NotDiscoverable,
+    #[error("too many hashes: {0}")]
+    TooMany(&'static str),
+    #[error("rate limited until {reset_at}")]
+    RateLimited { reset_at: DateTime<Utc> },
+    #[error("store error: {0}")]
+    Store(String),
+}
+
+/// Lookups per user; 0 lifts that cap.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiscoveryLimits {
+    pub per_hour: u32,
+    pub per_day: u32,
+}
+
+/// A user's discovery privacy setting, and the salt their client hashes
+/// identifiers with.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoverySettings {
+    pub discoverable: bool,
+    /// How many identifiers they can be found by.
+    pub identifiers: usize,
+    pub salt: String,
+}
+
+/// Finds users by phone number or email without the server learning either:
+/// clients send `ContactHash`es. Opt-in on both sides, so no one is found,
+/// or can look anyone up, until they have stored identifiers of their own.
+#[async_trait::async_trait]
+pub trait ContactDiscoveryService: Send + Sync {
+    async fn settings(&self, user_id: UserId) -> Result<DiscoverySettings, DiscoveryError>;
+    /// Replaces the identifiers `user_id` can be found by, duplicates
+    /// dropped; none turns discovery off and forgets the old ones.
+    async fn set_identifiers(
+        &self,
+        user_id: UserId,
+        hashes: &[ContactHash],
+    ) -> Result<DiscoverySettings, DiscoveryError>;
+    /// Discoverable users stored under any of `hashes`, the caller aside.
+    /// Each call counts against `DiscoveryLimits`, and fails with
+    /// `RateLimited` past them.
+    async fn discover(
+        &self,
+        user_id: UserId,
+        hashes: &[ContactHash],
+    ) -> Result<Vec<ContactMatch>, DiscoveryError>;
+}
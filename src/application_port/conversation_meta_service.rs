@@ -0,0 +1,49 @@
+use crate::application_port::ChatError;
+use crate::domain_model::*;
+
+/// Longest key, in bytes.
+pub const MAX_META_KEY_LEN: usize = 64;
+/// Longest value, in bytes.
+pub const MAX_META_VALUE_LEN: usize = 1024;
+/// Most keys one conversation carries.
+pub const MAX_META_KEYS: usize = 32;
+
+/// Keys are lowercase ASCII letters, digits, `.`, `_` and `-`, so
+/// integrations can namespace them (`jira.ticket`).
+pub fn check_meta_entry(key: &str, value: Option<&str>) -> Result<(), ChatError> {
+    if key.is_empty() || key.len() > MAX_META_KEY_LEN {
+        return Err(ChatError::InvalidMeta("key length"));
+    }
+    if !key
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"._-".contains(&b))
+    {
+        return Err(ChatError::InvalidMeta("key characters"));
+    }
+    if value.is_some_and(|value| value.len() > MAX_META_VALUE_LEN) {
+        return Err(ChatError::InvalidMeta("value length"));
+    }
+    Ok(())
+}
+
+/// Small key-value metadata integrations attach to a conversation, such as
+/// a linked ticket id. Members read it; admins and service principals write
+/// it, which the API checks before calling `set`.
+#[async_trait::async_trait]
+pub trait ConversationMetaService: Send + Sync {
+    /// Ordered by key.
+    async fn list(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<ConversationMeta>, ChatError>;
+    /// Sets `key`, or removes it when `value` is `None`, and sends the
+    /// members `ConversationMetaChanged`. Removing a missing key sends
+    /// nothing.
+    async fn set(
+        &self,
+        conversation_id: ConversationId,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<(), ChatError>;
+}
@@ -1,10 +1,86 @@
 use crate::domain_model::*;
 use chrono::{DateTime, Utc};
 
+/// Most files one message can carry.
+pub const MAX_MESSAGE_ATTACHMENTS: usize = 10;
+
+/// Most users one message can mention; later mentions are ignored.
+pub const MAX_MENTIONS: usize = 20;
+/// Longest username, in bytes; the `user.username` column.
+const MAX_MENTION_NAME_LEN: usize = 32;
+
+/// The usernames `content` mentions as `@username`, lowercased, without
+/// duplicates and in order of appearance. An `@` right after a letter or
+/// digit, as in an email address, doesn't start one.
+pub fn mentioned_usernames(content: &str) -> Vec<String> {
+    let bytes = content.as_bytes();
+    let is_name = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut names: Vec<String> = Vec::new();
+    for (at, _) in content.match_indices('@') {
+        if at > 0 && is_name(bytes[at - 1]) {
+            continue;
+        }
+        let len = bytes[at + 1..].iter().take_while(|b| is_name(**b)).count();
+        if len == 0 || len > MAX_MENTION_NAME_LEN {
+            continue;
+        }
+        let name = content[at + 1..at + 1 + len].to_ascii_lowercase();
+        if !names.contains(&name) {
+            names.push(name);
+            if names.len() == MAX_MENTIONS {
+                break;
+            }
+        }
+    }
+    names
+}
+
+/// Most messages one conversation keeps pinned.
+pub const MAX_PINNED_MESSAGES: usize = 50;
+/// What a member's role must allow to pin messages; group owners have it.
+pub const PIN_PERMISSION: &str = "member.pin";
+
+/// Longest search query, in bytes.
+pub const MAX_SEARCH_QUERY_LEN: usize = 256;
+/// Most words one search matches on.
+pub const MAX_SEARCH_TERMS: usize = 8;
+/// Shorter words are dropped; MySQL's full-text index doesn't hold them
+/// (`innodb_ft_min_token_size`).
+pub const MIN_SEARCH_TERM_CHARS: usize = 3;
+
+/// The words of `query`, lowercased and without duplicates; anything but a
+/// letter or digit separates them. A message matches when it has them all.
+pub fn search_terms(query: &str) -> Result<Vec<String>, ChatError> {
+    if query.len() > MAX_SEARCH_QUERY_LEN {
+        return Err(ChatError::InvalidSearch("query length"));
+    }
+    let mut terms: Vec<String> = Vec::new();
+    for word in query.split(|c: char| !c.is_alphanumeric()) {
+        let word = word.to_lowercase();
+        if word.chars().count() >= MIN_SEARCH_TERM_CHARS && !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    if terms.is_empty() {
+        return Err(ChatError::InvalidSearch("no words to search for"));
+    }
+    if terms.len() > MAX_SEARCH_TERMS {
+        return Err(ChatError::InvalidSearch("too many words"));
+    }
+    Ok(terms)
+}
+
 #[derive(Debug, Clone)]
 pub enum ConversationPeer {
-    Direct { other_user: UserId, name: String },
-    Group { group_id: GroupId, name: String },
+    Direct {
+        other_user: UserId,
+        name: String,
+    },
+    Group {
+        group_id: GroupId,
+        name: String,
+        appearance: GroupAppearance,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -13,6 +89,18 @@ pub struct RecentConversation {
     pub peer: ConversationPeer,
     pub last_msg_off: MessageOffset,
     pub last_msg_at: Option<DateTime<Utc>>, // NULL before first message
+    /// Pinned by the user the list is for.
+    pub pinned: bool,
+    /// No one can send; see `ConversationService::set_frozen`.
+    pub frozen: bool,
+    /// Messages older than this many days are deleted; `None` keeps them
+    /// forever. See `ConversationService::retention`.
+    pub retention_days: Option<u32>,
+    /// The newest offset the user the list is for has read.
+    pub last_read_off: MessageOffset,
+    /// Messages from others past `last_read_off`, counted like
+    /// `UnreadConversation::unread`.
+    pub unread_count: u64,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -21,6 +109,18 @@ pub enum ChatError {
     ConversationNotFound,
     #[error("user not a member of conversation")]
     NotMember,
+    #[error("conversation is frozen")]
+    Frozen,
+    #[error("message not found")]
+    MessageNotFound,
+    #[error("reply target not found in conversation")]
+    InvalidReply,
+    #[error("attachment not found or already sent")]
+    InvalidAttachment,
+    #[error("attachment not found")]
+    AttachmentNotFound,
+    #[error("invalid upload: {0}")]
+    InvalidUpload(&'static str),
     #[error("permission denied: {0}")]
     Forbidden(&'static str),
     #[error("idempotency conflict")]
@@ -29,19 +129,95 @@ pub enum ChatError {
     BadCursor,
     #[error("conflict: direct conversation already exists")]
     AlreadyExists,
+    #[error("invalid metadata: {0}")]
+    InvalidMeta(&'static str),
+    #[error("too many pinned messages")]
+    TooManyPins,
+    #[error("invalid search: {0}")]
+    InvalidSearch(&'static str),
+    /// Message content is encrypted at rest, so there is nothing to index.
+    #[error("search unavailable")]
+    SearchUnavailable,
     #[error("store error: {0}")]
     Store(String),
 }
 
 #[async_trait::async_trait]
 pub trait ConversationService: Send + Sync {
+    /// Sending a `message_id` again returns the stored message, marked
+    /// `duplicate`, and notifies no one. A `reply_to` outside the
+    /// conversation, or deleted, is `InvalidReply`; `attachments` must be
+    /// the sender's unsent uploads to the conversation, at most
+    /// `MAX_MESSAGE_ATTACHMENTS`, or it is `InvalidAttachment`. Members
+    /// mentioned as `@username`, see `mentioned_usernames`, also get a
+    /// `ChatMentionNew`.
     async fn send_message(
         &self,
         conversation_id: ConversationId,
         sender: UserId,
         content: &str,
         message_id: MessageId,
+        reply_to: Option<MessageId>,
+        attachments: &[AttachmentId],
+    ) -> Result<SentMessage, ChatError>;
+    /// Replaces the content of a message `editor` sent, and sends every
+    /// member `ChatMessageEdited`. Refused in a frozen conversation, like a
+    /// send.
+    async fn edit_message(
+        &self,
+        conversation_id: ConversationId,
+        editor: UserId,
+        message_id: MessageId,
+        new_content: &str,
     ) -> Result<MessageRecord, ChatError>;
+    /// Leaves a tombstone in place of a message, so history offsets don't
+    /// shift, and sends every member `ChatMessageDeleted`. Allowed for the
+    /// sender and the group owner; deleting twice is a no-op.
+    async fn delete_message(
+        &self,
+        conversation_id: ConversationId,
+        deleter: UserId,
+        message_id: MessageId,
+    ) -> Result<(), ChatError>;
+    /// Pins a message to the top of the conversation for every member, or
+    /// unpins it, and sends them `MessagePinned`. `by` needs a role allowing
+    /// `PIN_PERMISSION`; in direct conversations either member may. Refused
+    /// in a frozen conversation, and past `MAX_PINNED_MESSAGES` with
+    /// `TooManyPins`. Setting the state it already has sends nothing.
+    async fn pin_message(
+        &self,
+        conversation_id: ConversationId,
+        by: UserId,
+        message_id: MessageId,
+        pinned: bool,
+    ) -> Result<(), ChatError>;
+    /// Most recently pinned first; any member may ask.
+    async fn pinned_messages(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<PinnedMessage>, ChatError>;
+    /// The other members, who hear when `user_id` types. Only reads; the
+    /// caller relays the notice itself.
+    async fn typing_receivers(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<Vec<UserId>, ChatError>;
+    /// Offsets and count of the stored history, for sizing pagination.
+    async fn history_summary(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<HistorySummary, ChatError>;
+    /// The offset of the message sent nearest `at`, for jumping to a date
+    /// without paging through history; `None` if there are no messages.
+    async fn offset_at(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        at: DateTime<Utc>,
+    ) -> Result<Option<MessageOffset>, ChatError>;
     async fn get_history(
         &self,
         user_id: UserId,
@@ -49,10 +225,122 @@ pub trait ConversationService: Send + Sync {
         page_size: PageSize,
         before: Option<OffsetCursor>,
     ) -> Result<Vec<MessageRecord>, ChatError>;
+    /// Messages sent after `after`, oldest first, as the `ChatMessageNew`
+    /// each member got; deleted ones excluded. For a client catching up on
+    /// what it missed while disconnected.
+    async fn messages_since(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        after: MessageOffset,
+        page_size: PageSize,
+    ) -> Result<Vec<ChatMessageNew>, ChatError>;
+    /// The direct replies to `root`, oldest first, starting after the
+    /// cursor; `root` itself is not included.
+    async fn thread_history(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        root: MessageId,
+        page_size: PageSize,
+        after: Option<OffsetCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError>;
+    /// Messages mentioning `user_id` in conversations they are still in,
+    /// newest first, starting before the cursor; deleted ones excluded.
+    async fn mentions(
+        &self,
+        user_id: UserId,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError>;
+    /// Messages containing every word of `query` from conversations
+    /// `user_id` is a member of, newest first, starting before the cursor;
+    /// see `search_terms`. Deleted messages are never found.
+    async fn search_messages(
+        &self,
+        user_id: UserId,
+        query: &str,
+        filters: &SearchFilters,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError>;
+    /// Pinned conversations first, then by latest message.
     async fn recent_conversations(
         &self,
         user_id: UserId,
         page_size: PageSize,
         after: Option<TimeCursor>,
     ) -> Result<Vec<RecentConversation>, ChatError>;
+    /// Total unread and the `top` most recently active conversations with
+    /// unread messages.
+    async fn unread_summary(
+        &self,
+        user_id: UserId,
+        top: PageSize,
+    ) -> Result<UnreadSummary, ChatError>;
+    /// Freezes the conversation so no one can send to it, or thaws it, and
+    /// sends the members `ConversationFrozen`. `by` must own the group; `None`
+    /// is an admin or service principal, which the API checks. Setting the
+    /// state it already has sends nothing.
+    async fn set_frozen(
+        &self,
+        conversation_id: ConversationId,
+        by: Option<UserId>,
+        frozen: bool,
+    ) -> Result<(), ChatError>;
+    /// How long the conversation keeps its messages; any member may ask.
+    async fn retention(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<ConversationRetention, ChatError>;
+    /// Overrides how long the conversation keeps its messages, clamped to
+    /// the server's bounds, or returns it to the server default with `None`.
+    /// Only the group owner may; direct conversations follow the default.
+    async fn set_retention(
+        &self,
+        conversation_id: ConversationId,
+        by: UserId,
+        days: Option<u32>,
+    ) -> Result<ConversationRetention, ChatError>;
+    /// Moves the user's read position up to `offset`, capped at the newest
+    /// message; never backwards. When it moves, every member, the reader
+    /// included, is sent a `ChatRead`. Returns the position after the call.
+    async fn mark_read(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+    ) -> Result<MessageOffset, ChatError>;
+    /// Records that a client of `user_id`, a member, got the message, and
+    /// sends its sender a `ChatMessageDelivered`. Does nothing when that was
+    /// recorded already, or the message is `user_id`'s own, deleted or
+    /// missing.
+    async fn mark_delivered(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+    ) -> Result<(), ChatError>;
+    /// Who got the message, earliest first; only its sender may ask.
+    async fn deliveries(
+        &self,
+        sender: UserId,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageDelivery>, ChatError>;
+    async fn set_pinned(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        pinned: bool,
+    ) -> Result<(), ChatError>;
+    /// Members added since `since_version`, or every member when the caller
+    /// has no version yet; see `MembersDelta::snapshot`.
+    async fn members_since(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        since_version: Option<u64>,
+    ) -> Result<MembersDelta, ChatError>;
 }
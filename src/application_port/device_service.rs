@@ -0,0 +1,72 @@
+use crate::domain_model::*;
+use thiserror::Error;
+
+/// Longest push token; APNs tokens are 64 hex digits, FCM ones a few hundred
+/// characters.
+pub const MAX_PUSH_TOKEN_LEN: usize = 512;
+/// Longest locale tag.
+pub const MAX_LOCALE_LEN: usize = 35;
+/// Most devices one user keeps; registering another forgets the one
+/// registered longest ago.
+pub const MAX_DEVICES_PER_USER: usize = 10;
+
+#[derive(Debug, Error)]
+pub enum DeviceError {
+    #[error("invalid device: {0}")]
+    Invalid(&'static str),
+    #[error("device not found")]
+    NotFound,
+    #[error("store error: {0}")]
+    Store(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct DeviceRegistration {
+    pub platform: PushPlatform,
+    pub token: String,
+    pub locale: String,
+}
+
+/// Printable ASCII, so tokens fit the `device` table's key.
+pub fn check_registration(registration: &DeviceRegistration) -> Result<(), DeviceError> {
+    let token = &registration.token;
+    if token.is_empty() || token.len() > MAX_PUSH_TOKEN_LEN {
+        return Err(DeviceError::Invalid("token length"));
+    }
+    if !token.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(DeviceError::Invalid("token characters"));
+    }
+    let locale = &registration.locale;
+    if locale.is_empty() || locale.len() > MAX_LOCALE_LEN {
+        return Err(DeviceError::Invalid("locale length"));
+    }
+    if !locale
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    {
+        return Err(DeviceError::Invalid("locale characters"));
+    }
+    Ok(())
+}
+
+/// Devices that get push notifications, registered by their users and read
+/// by the push gateway.
+#[async_trait::async_trait]
+pub trait DeviceService: Send + Sync {
+    /// Registers the device for `user_id`, taking it over from whoever had it.
+    async fn register(
+        &self,
+        user_id: UserId,
+        registration: DeviceRegistration,
+    ) -> Result<Device, DeviceError>;
+    /// `NotFound` unless the device is registered to `user_id`.
+    async fn unregister(
+        &self,
+        user_id: UserId,
+        platform: PushPlatform,
+        token: &str,
+    ) -> Result<(), DeviceError>;
+    async fn devices_of(&self, user_ids: &[UserId]) -> Result<Vec<Device>, DeviceError>;
+    /// Forgets tokens a push service rejected. Returns how many were known.
+    async fn prune(&self, platform: PushPlatform, tokens: &[String]) -> Result<u64, DeviceError>;
+}
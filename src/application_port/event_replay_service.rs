@@ -0,0 +1,22 @@
+use crate::domain_port::ReplaySelection;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("nothing selected")]
+    EmptySelection,
+    #[error("too many event ids: {0}")]
+    TooManyEvents(usize),
+    #[error("time range ends before it starts")]
+    InvertedRange,
+    #[error("store error: {0}")]
+    Store(String),
+}
+
+/// Operator recovery: puts already delivered outbox events back in front of
+/// the notifier so they are published to Kafka again.
+#[async_trait::async_trait]
+pub trait EventReplayService: Send + Sync {
+    /// Returns how many events were requeued. Events still waiting for their
+    /// first delivery are left alone.
+    async fn replay(&self, selection: ReplaySelection) -> Result<u64, ReplayError>;
+}
@@ -0,0 +1,49 @@
+use crate::domain_model::*;
+use futures_util::stream::BoxStream;
+use std::fmt;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("store error: {0}")]
+    Store(String),
+}
+
+/// A finished archive, decrypted a chunk at a time as it is read back from
+/// the blob store.
+pub struct ExportArchive {
+    /// What the download should be saved as.
+    pub file_name: String,
+    /// Fails partway if the stored archive can't be read or opened.
+    pub content: BoxStream<'static, Result<Vec<u8>, ExportError>>,
+}
+
+impl fmt::Debug for ExportArchive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExportArchive")
+            .field("file_name", &self.file_name)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+pub struct ExportStatus {
+    pub progress: ExportProgress,
+    /// Set once `progress.stage` is `Ready`.
+    pub archive: Option<ExportArchive>,
+}
+
+/// Account data export: everything stored about a user, as one archive.
+///
+/// A background job on the node that got the first request builds it; the
+/// job's progress is stored and the archive goes to the blob store, so any
+/// node can answer a poll.
+#[async_trait::async_trait]
+pub trait ExportService: Send + Sync {
+    /// Where the user's export stands. Starts one when there is none yet, or
+    /// the last one expired or was abandoned by a node that went away; a
+    /// failed one is reported once, and the next call starts over.
+    async fn export(&self, user_id: UserId) -> Result<ExportStatus, ExportError>;
+    /// Removes archives past their retention and jobs nobody is running any
+    /// more; returns how many went.
+    async fn expire(&self) -> Result<u64, ExportError>;
+}
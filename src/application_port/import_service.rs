@@ -0,0 +1,83 @@
+use crate::domain_model::*;
+use argon2::password_hash::PasswordHash;
+
+/// Most rows, of all kinds together, in one `ImportBatch`.
+pub const MAX_IMPORT_ROWS: usize = 5000;
+/// Longest username, in bytes; the `user.username` column.
+pub const MAX_IMPORT_USERNAME_LEN: usize = 32;
+/// Longest group name, in bytes; the `chat_group.group_name` column.
+pub const MAX_IMPORT_GROUP_NAME_LEN: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("too many rows in one batch: {0}")]
+    TooLarge(usize),
+    #[error("invalid import row: {0}")]
+    Invalid(String),
+    #[error("store error: {0}")]
+    Store(String),
+}
+
+/// Rejects the whole batch over one bad row, naming it, so nothing is half
+/// imported. Whether referenced rows exist is left to the import.
+pub fn check_import_batch(batch: &ImportBatch) -> Result<(), ImportError> {
+    if batch.len() > MAX_IMPORT_ROWS {
+        return Err(ImportError::TooLarge(batch.len()));
+    }
+    for user in &batch.users {
+        let username = &user.username;
+        if username.is_empty()
+            || username.len() > MAX_IMPORT_USERNAME_LEN
+            || !username
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+        {
+            return Err(ImportError::Invalid(format!(
+                "user {}: bad username",
+                user.user_id
+            )));
+        }
+        if let Some(hash) = &user.password_hash {
+            let argon2 = PasswordHash::new(hash.expose())
+                .is_ok_and(|hash| hash.algorithm.as_str().starts_with("argon2"));
+            if !argon2 {
+                return Err(ImportError::Invalid(format!(
+                    "user {}: password hash is not an Argon2 PHC string",
+                    user.user_id
+                )));
+            }
+        }
+    }
+    for friendship in &batch.friendships {
+        if friendship.a == friendship.b {
+            return Err(ImportError::Invalid(format!(
+                "friendship {}: both sides are the same user",
+                friendship.conversation_id
+            )));
+        }
+    }
+    for group in &batch.groups {
+        if group.name.is_empty() || group.name.len() > MAX_IMPORT_GROUP_NAME_LEN {
+            return Err(ImportError::Invalid(format!(
+                "group {}: bad name",
+                group.group_id
+            )));
+        }
+    }
+    for message in &batch.messages {
+        if message.message_offset.0 == 0 {
+            return Err(ImportError::Invalid(format!(
+                "message {}: offsets start at 1",
+                message.message_id.0
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Operator migration from another chat system. Imported history is stored
+/// as it was and announced to no one: nothing goes through the outbox.
+#[async_trait::async_trait]
+pub trait ImportService: Send + Sync {
+    async fn import(&self, batch: ImportBatch) -> Result<ImportReport, ImportError>;
+}
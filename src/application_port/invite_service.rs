@@ -0,0 +1,65 @@
+use crate::application_port::AuthError;
+use crate::domain_model::*;
+use chrono::{DateTime, Utc};
+use nanoid::nanoid;
+use serde::Deserialize;
+
+/// Most codes minted by one request.
+pub const MAX_INVITES_PER_MINT: u32 = 100;
+
+/// No `0`/`O` or `1`/`l`/`I`, so codes survive being read out loud.
+const INVITE_ALPHABET: [char; 31] = [
+    '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'j', 'k', 'm',
+    'n', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MintInvites {
+    pub count: u32,
+    /// Signups each code allows.
+    pub max_uses: u32,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Fresh codes for `request`, or `AuthError::InvalidInviteMint` saying what
+/// is wrong with it.
+pub fn mint_invite_codes(
+    request: &MintInvites,
+    minted_by: &str,
+    now: DateTime<Utc>,
+) -> Result<Vec<InviteCode>, AuthError> {
+    if request.count == 0 || request.count > MAX_INVITES_PER_MINT {
+        return Err(AuthError::InvalidInviteMint("count".to_string()));
+    }
+    if request.max_uses == 0 {
+        return Err(AuthError::InvalidInviteMint("max_uses".to_string()));
+    }
+    if request.expires_at.is_some_and(|at| at <= now) {
+        return Err(AuthError::InvalidInviteMint("expires_at".to_string()));
+    }
+
+    Ok((0..request.count)
+        .map(|_| InviteCode {
+            code: nanoid!(12, &INVITE_ALPHABET),
+            max_uses: request.max_uses,
+            uses: 0,
+            expires_at: request.expires_at,
+            minted_by: minted_by.to_string(),
+            created_at: now,
+        })
+        .collect())
+}
+
+/// Invite codes for `auth.invite_only`; signup redeems them. The API checks
+/// the caller is an admin first.
+#[async_trait::async_trait]
+pub trait InviteService: Send + Sync {
+    async fn mint(
+        &self,
+        request: MintInvites,
+        minted_by: String,
+    ) -> Result<Vec<InviteCode>, AuthError>;
+    /// Newest first, used up and expired ones included.
+    async fn list(&self) -> Result<Vec<InviteCode>, AuthError>;
+}
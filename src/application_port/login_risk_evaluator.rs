@@ -0,0 +1,49 @@
+use crate::application_port::AuthError;
+use crate::domain_model::UserId;
+use chrono::{DateTime, Utc};
+use std::net::IpAddr;
+
+/// A login that passed the password check.
+#[derive(Debug, Clone)]
+pub struct LoginAttempt {
+    pub user_id: UserId,
+    pub ip: IpAddr,
+    pub device: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginRisk {
+    /// A location the account used before, or its first login.
+    Usual,
+    /// New for the account; the user has been alerted.
+    Unusual { location: String },
+}
+
+/// The network an address belongs to, as a stand-in for a geo lookup: the
+/// /16 for IPv4 and the /32 for IPv6, or `local` for loopback and private
+/// ranges.
+pub fn coarse_location(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) if v4.is_loopback() || v4.is_private() || v4.is_link_local() => {
+            "local".to_string()
+        }
+        IpAddr::V6(v6) if v6.is_loopback() || v6.is_unique_local() => "local".to_string(),
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            format!("{a}.{b}.0.0/16")
+        }
+        IpAddr::V6(v6) => {
+            let [a, b, ..] = v6.segments();
+            format!("{a:x}:{b:x}::/32")
+        }
+    }
+}
+
+/// Judges each login against where the account logged in from before,
+/// remembers it, and raises a `SecurityAlert` to the account's sessions when
+/// it looks unusual. Swap in another implementation for a real geo lookup.
+#[async_trait::async_trait]
+pub trait LoginRiskEvaluator: Send + Sync {
+    async fn evaluate(&self, attempt: LoginAttempt) -> Result<LoginRisk, AuthError>;
+}
@@ -1,11 +1,37 @@
+mod attachment_service;
 mod auth_service;
 mod captcha_service;
+mod contact_discovery_service;
+mod conversation_meta_service;
 mod conversation_service;
+mod device_service;
+mod event_replay_service;
+mod export_service;
+mod import_service;
+mod invite_service;
+mod login_risk_evaluator;
+mod notice_service;
 mod relationship_service;
+mod stats_service;
 mod user_service;
+mod username_policy_service;
+mod webhook_service;
 
+pub use attachment_service::*;
 pub use auth_service::*;
 pub use captcha_service::*;
+pub use contact_discovery_service::*;
+pub use conversation_meta_service::*;
 pub use conversation_service::*;
+pub use device_service::*;
+pub use event_replay_service::*;
+pub use export_service::*;
+pub use import_service::*;
+pub use invite_service::*;
+pub use login_risk_evaluator::*;
+pub use notice_service::*;
 pub use relationship_service::*;
+pub use stats_service::*;
 pub use user_service::*;
+pub use username_policy_service::*;
+pub use webhook_service::*;
@@ -0,0 +1,92 @@
+use crate::domain_model::*;
+use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+
+/// Longest notice title, in bytes.
+pub const MAX_NOTICE_TITLE_LEN: usize = 120;
+/// Longest notice body, in bytes.
+pub const MAX_NOTICE_BODY_LEN: usize = 2000;
+/// Most users one notice may name; a wider audience gets everyone.
+pub const MAX_NOTICE_RECEIVERS: usize = 1000;
+/// Furthest ahead a notice can be scheduled.
+pub const MAX_NOTICE_LEAD_DAYS: i64 = 30;
+
+#[derive(Debug, Error)]
+pub enum NoticeError {
+    #[error("invalid notice: {0}")]
+    Invalid(&'static str),
+    #[error("store error: {0}")]
+    Store(String),
+}
+
+/// What an operator wants announced, to whom and when.
+#[derive(Debug, Clone)]
+pub struct NoticeDraft {
+    pub kind: NoticeKind,
+    pub title: String,
+    pub body: String,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// `None` for everyone online.
+    pub receivers: Option<Vec<UserId>>,
+    /// `None`, or a time already past, publishes it right away.
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+/// Checks the draft and turns it into the notice clients get, with the
+/// receivers it goes to, empty for everyone.
+pub fn compose_notice(
+    draft: NoticeDraft,
+    now: DateTime<Utc>,
+) -> Result<(SystemNotice, Vec<UserId>), NoticeError> {
+    let title = draft.title.trim();
+    if title.is_empty() || title.len() > MAX_NOTICE_TITLE_LEN {
+        return Err(NoticeError::Invalid("title length"));
+    }
+    if draft.body.len() > MAX_NOTICE_BODY_LEN {
+        return Err(NoticeError::Invalid("body length"));
+    }
+    if let (Some(starts_at), Some(ends_at)) = (draft.starts_at, draft.ends_at)
+        && ends_at <= starts_at
+    {
+        return Err(NoticeError::Invalid("window ends before it starts"));
+    }
+    let published_at = draft.publish_at.unwrap_or(now).max(now);
+    if published_at > now + Duration::days(MAX_NOTICE_LEAD_DAYS) {
+        return Err(NoticeError::Invalid("scheduled too far ahead"));
+    }
+    let receivers = match draft.receivers {
+        None => Vec::new(),
+        Some(receivers) if receivers.is_empty() => {
+            return Err(NoticeError::Invalid("no receivers"));
+        }
+        Some(receivers) if receivers.len() > MAX_NOTICE_RECEIVERS => {
+            return Err(NoticeError::Invalid("too many receivers"));
+        }
+        Some(mut receivers) => {
+            receivers.sort();
+            receivers.dedup();
+            receivers
+        }
+    };
+
+    let notice = SystemNotice {
+        notice_id: NoticeId(uuid::Uuid::new_v4()),
+        kind: draft.kind,
+        title: title.to_string(),
+        body: draft.body,
+        starts_at: draft.starts_at,
+        ends_at: draft.ends_at,
+        published_at,
+    };
+    Ok((notice, receivers))
+}
+
+/// Operator notices such as maintenance windows, sent to clients as
+/// `SystemNotice` through the outbox.
+#[async_trait::async_trait]
+pub trait NoticeService: Send + Sync {
+    /// Queues the notice for its `published_at`; it reaches the receivers
+    /// online then.
+    async fn publish(&self, draft: NoticeDraft) -> Result<SystemNotice, NoticeError>;
+}
@@ -1,13 +1,18 @@
 use crate::domain_model::*;
+use chrono::{DateTime, Utc};
 
 #[derive(Debug, thiserror::Error)]
 pub enum RelationError {
     #[error("user not found")]
     UserNotFound,
+    #[error("cannot relate to yourself")]
+    SelfRelation,
     #[error("friend request already exists")]
     FriendRequestExists,
     #[error("friendship already established")]
     AlreadyFriends,
+    #[error("not friends")]
+    NotFriends,
     #[error("group not found")]
     GroupNotFound,
     #[error("already a member")]
@@ -18,29 +23,105 @@ pub enum RelationError {
     NotOwner,
     #[error("role not found: {0}")]
     RoleNotFound(String),
+    #[error("invalid group appearance: {0}")]
+    InvalidAppearance(&'static str),
+    #[error("rate limited until {reset_at}")]
+    RateLimited { reset_at: DateTime<Utc> },
     #[error("store error: {0}")]
     Store(String),
 }
 
+/// The system messages a new conversation starts with; `None` leaves it
+/// empty.
+#[derive(Debug, Clone, Default)]
+pub struct WelcomeMessages {
+    pub direct: Option<String>,
+    /// `{group_name}` is replaced with the group's name.
+    pub group: Option<String>,
+}
+
+impl WelcomeMessages {
+    pub fn for_group(&self, group_name: &str) -> Option<String> {
+        self.group
+            .as_ref()
+            .map(|template| template.replace("{group_name}", group_name))
+    }
+}
+
+/// How many friend requests one user may send; 0 lifts that cap.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FriendRequestLimits {
+    pub per_hour: u32,
+    pub per_day: u32,
+}
+
+/// Longest `GroupAppearance::avatar_url`, in bytes.
+pub const MAX_AVATAR_URL_LEN: usize = 512;
+
+/// The avatar must be an `https` URL, so clients never fetch it in the
+/// clear, and the theme a `#rrggbb` color.
+pub fn check_group_appearance(appearance: &GroupAppearance) -> Result<(), RelationError> {
+    if let Some(url) = &appearance.avatar_url {
+        if url.len() > MAX_AVATAR_URL_LEN {
+            return Err(RelationError::InvalidAppearance("avatar url length"));
+        }
+        if !url.starts_with("https://") || url.chars().any(char::is_whitespace) {
+            return Err(RelationError::InvalidAppearance("avatar url"));
+        }
+    }
+    if let Some(color) = &appearance.theme_color {
+        let hex = color.strip_prefix('#').unwrap_or_default();
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(RelationError::InvalidAppearance("theme color"));
+        }
+    }
+    Ok(())
+}
+
+/// The members of a new group besides `owner`, without repeats.
+pub fn group_members(owner: UserId, members: &[UserId]) -> Vec<UserId> {
+    let mut members: Vec<UserId> = members
+        .iter()
+        .copied()
+        .filter(|member| *member != owner)
+        .collect();
+    members.sort();
+    members.dedup();
+    members
+}
+
 #[async_trait::async_trait]
 pub trait RelationshipService: Send + Sync {
+    /// `FriendshipNew` is only emitted when the outcome is `Created`.
+    /// Retrying with the same key reports `Created` again. Requests that
+    /// change nothing don't count against `FriendRequestLimits`; past them
+    /// the call fails with `RateLimited`.
     async fn add_friend(
         &self,
         me: UserId,
         other: UserId,
-        _idempotency_key: IdempotencyKey,
-    ) -> Result<ConversationId, RelationError>;
+        idempotency_key: IdempotencyKey,
+    ) -> Result<AddFriendOutcome, RelationError>;
+    /// The direct conversation between `me` and `other`, created if they
+    /// have none yet. Unless `chat.open_direct` is on, only friends have one,
+    /// and anyone else gets `NotFriends`.
+    async fn open_direct(&self, me: UserId, other: UserId)
+    -> Result<ConversationId, RelationError>;
     async fn list_friends(
         &self,
         user_id: UserId,
         page_size: PageSize,
         after: Option<FriendCursor>,
     ) -> Result<Vec<FriendSummary>, RelationError>;
+    /// `members` join with the owner in the same transaction and hear about
+    /// the group from one `GroupNew` between them. The owner and repeats are
+    /// dropped from `members`; any other missing user fails the whole call.
     async fn create_group(
         &self,
         owner: UserId,
         name: &str,
         description: Option<&str>,
+        members: &[UserId],
         idempotency_key: IdempotencyKey,
     ) -> Result<(GroupId, ConversationId), RelationError>;
     async fn invite_to_group(
@@ -49,12 +130,20 @@ pub trait RelationshipService: Send + Sync {
         host: UserId,
         guest: UserId,
     ) -> Result<(), RelationError>;
+    /// Owner only. Hides the group and its conversation; history is kept.
+    async fn disband_group(&self, group: GroupId, owner: UserId) -> Result<(), RelationError>;
     async fn list_groups(
         &self,
         user_id: UserId,
         page_size: PageSize,
         after: Option<GroupCursor>,
+        projection: GroupProjection,
     ) -> Result<Vec<GroupSummary>, RelationError>;
+    async fn group_member_counts(
+        &self,
+        user_id: UserId,
+        group_ids: &[GroupId],
+    ) -> Result<Vec<GroupMemberCount>, RelationError>;
     async fn list_group_members(
         &self,
         user_id: UserId,
@@ -62,4 +151,18 @@ pub trait RelationshipService: Send + Sync {
         page_size: PageSize,
         after: Option<MemberCursor>,
     ) -> Result<Vec<MemberSummary>, RelationError>;
+    /// Members only.
+    async fn group_appearance(
+        &self,
+        user_id: UserId,
+        group: GroupId,
+    ) -> Result<GroupAppearance, RelationError>;
+    /// Owner only. Replaces both fields, so `None` clears one, and sends
+    /// every member `GroupUpdated` if anything changed.
+    async fn set_group_appearance(
+        &self,
+        group: GroupId,
+        owner: UserId,
+        appearance: GroupAppearance,
+    ) -> Result<(), RelationError>;
 }
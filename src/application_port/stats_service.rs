@@ -0,0 +1,64 @@
+use crate::domain_model::*;
+use chrono::{Days, NaiveDate};
+use thiserror::Error;
+
+/// Longest stretch the stats cover, in days, today included.
+pub const MAX_STATS_DAYS: u32 = 90;
+/// Most groups `top_groups` ranks.
+pub const MAX_TOP_GROUPS: u32 = 50;
+
+#[derive(Debug, Error)]
+pub enum StatsError {
+    #[error("invalid range: {0}")]
+    InvalidRange(&'static str),
+    #[error("store error: {0}")]
+    Store(String),
+}
+
+pub fn check_days(days: u32) -> Result<(), StatsError> {
+    if days == 0 || days > MAX_STATS_DAYS {
+        return Err(StatsError::InvalidRange("days"));
+    }
+    Ok(())
+}
+
+pub fn check_top_groups_limit(limit: u32) -> Result<(), StatsError> {
+    if limit == 0 || limit > MAX_TOP_GROUPS {
+        return Err(StatsError::InvalidRange("limit"));
+    }
+    Ok(())
+}
+
+/// The first day of the `days` ending with `today`.
+pub fn first_day(today: NaiveDate, days: u32) -> NaiveDate {
+    today
+        .checked_sub_days(Days::new(u64::from(days.saturating_sub(1))))
+        .unwrap_or(NaiveDate::MIN)
+}
+
+/// One entry for each of the `days` ending with `today`, oldest first, with
+/// days missing from `rows` left at zero.
+pub fn fill_days(today: NaiveDate, days: u32, rows: Vec<DailyStats>) -> Vec<DailyStats> {
+    let mut filled: Vec<DailyStats> = first_day(today, days)
+        .iter_days()
+        .take(days as usize)
+        .map(DailyStats::empty)
+        .collect();
+    for row in rows {
+        if let Some(slot) = filled.iter_mut().find(|slot| slot.day == row.day) {
+            *slot = row;
+        }
+    }
+    filled
+}
+
+/// Aggregate activity for the operators' dashboard, counted from the
+/// stored users and messages. The API checks the caller is an admin.
+#[async_trait::async_trait]
+pub trait StatsService: Send + Sync {
+    /// The `days` ending today, oldest first.
+    async fn daily(&self, days: u32) -> Result<Vec<DailyStats>, StatsError>;
+    /// The groups with the most messages over the `days` ending today,
+    /// busiest first; disbanded groups are left out.
+    async fn top_groups(&self, days: u32, limit: u32) -> Result<Vec<GroupTraffic>, StatsError>;
+}
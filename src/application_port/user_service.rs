@@ -4,4 +4,19 @@ use crate::domain_model::UserId;
 #[async_trait::async_trait]
 pub trait UserService: Send + Sync {
     async fn resolve_username(&self, username: &str) -> Result<UserId, AuthError>;
+    /// Deactivate the account and disconnect its sessions on every node.
+    async fn deactivate_account(&self, user_id: UserId) -> Result<(), AuthError>;
+    /// Like `deactivate_account`, and also hides the user from lists. Their
+    /// messages and memberships are kept. Fails with `LegalHold` while the
+    /// user is held.
+    async fn delete_account(&self, user_id: UserId) -> Result<(), AuthError>;
+    /// Closes the user's sessions on every node, telling them an operator
+    /// did; the account stays as it is.
+    async fn disconnect_sessions(&self, user_id: UserId) -> Result<(), AuthError>;
+    /// Places or lifts a legal hold, which exempts the account and its
+    /// messages from deletion and pruning until lifted.
+    async fn set_legal_hold(&self, user_id: UserId, hold: bool) -> Result<(), AuthError>;
+    /// Marks the account as a bot or not; incoming webhooks can only post as
+    /// bots.
+    async fn set_bot(&self, user_id: UserId, bot: bool) -> Result<(), AuthError>;
 }
@@ -0,0 +1,76 @@
+use crate::application_port::AuthError;
+use crate::domain_model::*;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Longest rule value, in bytes; the `username_rule.value` column.
+pub const MAX_USERNAME_RULE_LEN: usize = 255;
+
+/// A set of `UsernameRule`s, compiled for checking.
+pub struct UsernamePolicy {
+    reserved: HashSet<String>,
+    patterns: Vec<Regex>,
+}
+
+impl UsernamePolicy {
+    /// `AuthError::InvalidUsernameRule` for a pattern that isn't a regex.
+    pub fn compile<'a>(
+        rules: impl IntoIterator<Item = &'a UsernameRule>,
+    ) -> Result<Self, AuthError> {
+        let mut policy = Self {
+            reserved: HashSet::new(),
+            patterns: Vec::new(),
+        };
+        for rule in rules {
+            match rule {
+                UsernameRule::Reserved(name) => {
+                    policy.reserved.insert(name.to_lowercase());
+                }
+                UsernameRule::Pattern(pattern) => policy.patterns.push(
+                    Regex::new(pattern)
+                        .map_err(|e| AuthError::InvalidUsernameRule(e.to_string()))?,
+                ),
+            }
+        }
+        Ok(policy)
+    }
+
+    pub fn allows(&self, username: &str) -> bool {
+        !self.reserved.contains(&username.to_lowercase())
+            && !self.patterns.iter().any(|p| p.is_match(username))
+    }
+}
+
+/// Rejects a rule before it is stored.
+pub fn check_username_rule(rule: &UsernameRule) -> Result<(), AuthError> {
+    let value = match rule {
+        UsernameRule::Reserved(value) | UsernameRule::Pattern(value) => value,
+    };
+    if value.is_empty() || value.len() > MAX_USERNAME_RULE_LEN {
+        return Err(AuthError::InvalidUsernameRule("length".to_string()));
+    }
+    UsernamePolicy::compile([rule]).map(|_| ())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsernameRules {
+    /// From `auth.reserved_usernames` and `auth.username_denylist`; these
+    /// can't be removed at runtime.
+    pub configured: Vec<UsernameRule>,
+    /// Added by admins.
+    pub managed: Vec<UsernameRule>,
+}
+
+/// Which usernames signup refuses: the configured rules plus those admins
+/// manage here. The API checks the caller is an admin before the writes.
+#[async_trait::async_trait]
+pub trait UsernamePolicyService: Send + Sync {
+    /// `AuthError::UsernameNotAllowed` if any rule bars `username`.
+    async fn check(&self, username: &str) -> Result<(), AuthError>;
+    async fn rules(&self) -> Result<UsernameRules, AuthError>;
+    /// Returns `false` if the rule was already there.
+    async fn add_rule(&self, rule: UsernameRule) -> Result<bool, AuthError>;
+    /// Returns `false` if there was no such managed rule.
+    async fn remove_rule(&self, rule: UsernameRule) -> Result<bool, AuthError>;
+}
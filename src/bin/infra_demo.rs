@@ -10,17 +10,19 @@
 /// ```
 ///
 /// This is intended only for manual testing and should not be enabled in production.
-
 use counterpoint::application_impl::*;
 use counterpoint::application_port::*;
 use counterpoint::domain_model::*;
 use counterpoint::domain_port::*;
 use counterpoint::infra_mysql::*;
 use counterpoint::infra_redis::*;
+use counterpoint::logger::Secret;
+use counterpoint::protocol::*;
 use counterpoint::server::*;
 use futures_util::future::join_all;
 use nanoid::nanoid;
 use sqlx::{MySql, Pool};
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::io;
@@ -82,6 +84,8 @@ async fn main() -> anyhow::Result<()> {
         audience: "chat-client".to_string(),
         access_ttl: Duration::from_secs(15 * 60), // 15 minutes
         refresh_ttl: Duration::from_secs(7 * 24 * 60 * 60), // 7 days
+        sliding_refresh: true,
+        refresh_max_lifetime: Duration::from_secs(30 * 24 * 60 * 60), // 30 days
         signing_key: key,
     }));
 
@@ -89,6 +93,10 @@ async fn main() -> anyhow::Result<()> {
         redis_manager.clone(),
         format!("auth:{}", run_id),
     ));
+    let qr_login_store: Arc<dyn QrLoginStore> = Arc::new(RedisQrLoginStore::new(
+        redis_manager.clone(),
+        format!("qr_login:{}", run_id),
+    ));
 
     let tx_manager: Arc<dyn TxManager> = Arc::new(MySqlTxManager::new(pool.clone()));
 
@@ -111,6 +119,11 @@ async fn main() -> anyhow::Result<()> {
         token_codec,
         session_store,
         tx_manager.clone(),
+        HashSet::new(),
+        Arc::new(FakeUsernamePolicyService::new(Vec::new())?),
+        None,
+        qr_login_store,
+        Duration::from_secs(120),
     ));
     let relationship_service: Arc<dyn RelationshipService> =
         Arc::new(RealRelationshipService::new(
@@ -121,61 +134,116 @@ async fn main() -> anyhow::Result<()> {
             conversation_repo.clone(),
             conversation_role_repo.clone(),
             outbox_repo.clone(),
+            message_repo.clone(),
+            Arc::new(MySqlOffsetAllocator::new()),
             tx_manager.clone(),
+            false,
+            WelcomeMessages::default(),
+            Arc::new(RedisRateLimiter::new(
+                redis_manager.clone(),
+                format!("ratelimit:{}", run_id),
+            )),
+            FriendRequestLimits {
+                per_hour: 20,
+                per_day: 100,
+            },
         ));
     let conversation_service: Arc<dyn ConversationService> =
         Arc::new(RealConversationService::new(
             user_repo.clone(),
             message_repo,
+            Arc::new(MySqlDeliveryRepo::new()),
+            Arc::new(MySqlAttachmentRepo::new()),
+            Arc::new(MySqlOffsetAllocator::new()),
             conversation_repo,
             conversation_role_repo,
             outbox_repo.clone(),
             tx_manager.clone(),
+            RetentionPolicy {
+                default_days: None,
+                min_days: 1,
+                max_days: 3650,
+            },
         ));
 
     let cancel = CancellationToken::new();
 
-    let topic = format!("chat.event.{}", run_id);
+    let message_topic = TopicSpec {
+        name: format!("chat.message.{}", run_id),
+        partitions: 1,
+        replication: 1,
+    };
+    let presence_topic = TopicSpec {
+        name: format!("chat.presence.{}", run_id),
+        partitions: 1,
+        replication: 1,
+    };
 
     let publisher: Arc<dyn EventPublisher> = Arc::new(KafkaPublisher::new(
         "localhost:9092",
         &format!("chat-pub-{}", run_id),
+        &ProducerConfig::default(),
     )?);
+    let health = Arc::new(HealthMonitor::new());
     let consumer: Arc<dyn EventConsumer> = Arc::new(KafkaConsumer::new(
         "localhost:9092",
         &format!("chat-sub-{}", run_id),
+        health.clone(),
         cancel.clone(),
     ));
 
+    let command_dedupe_store: Arc<dyn CommandDedupeStore> = Arc::new(RedisCommandDedupeStore::new(
+        redis_manager.clone(),
+        format!("dedupe:{}", run_id),
+    ));
     let service_registry = Arc::new(ServiceRegistry {
         conversation_service: conversation_service.clone(),
+        command_dedupe_store,
+        ephemeral_relay: Arc::new(EphemeralRelay::new(publisher.clone(), &presence_topic.name)),
     });
-    let session_hub = Arc::new(SessionHub::new(service_registry.clone()));
+    // histograms only; the demo doesn't run the p99 alarm
+    let sla = Arc::new(DeliverySla::new(
+        Duration::ZERO,
+        Duration::from_secs(60),
+        cancel.clone(),
+    ));
+    // demo clients all connect from localhost
+    let session_hub = Arc::new(SessionHub::new(service_registry.clone(), sla, 0));
     let connection_acceptor: Arc<dyn ConnectionAcceptor> = session_hub.clone();
+    let session_control: Arc<dyn SessionControl> = session_hub.clone();
     let outbound_queue: Arc<dyn OutboundQueue> = session_hub.clone();
 
-    let fanout_handler: Arc<dyn EventHandler> =
-        Arc::new(ConnFanoutHandler::new(outbound_queue.clone()));
+    let fanout_handler: Arc<dyn EventHandler> = Arc::new(ConnFanoutHandler::new(
+        outbound_queue.clone(),
+        session_control,
+    ));
     let notifier = Notifier::new(
         tx_manager.clone(),
         outbox_repo.clone(),
         publisher.clone(),
-        &topic,
+        &message_topic.name,
+        &presence_topic.name,
+        health.clone(),
         cancel.clone(),
     );
 
     let run_id_clone = run_id.clone();
     let _fanout_handle = tokio::spawn(async move {
-        let _ = consumer
+        if let Err(e) = consumer
             .run(
                 &format!("ws-fanout-{}", run_id_clone),
-                &[&topic],
+                &[message_topic, presence_topic],
                 fanout_handler,
             )
-            .await;
+            .await
+        {
+            tracing::error!(error = ?e, "consumer stopped");
+        }
     });
     let _notifier_handle = tokio::spawn(async move {
-        let _ = notifier.run().await;
+        if let Err(e) = notifier.run().await {
+            tracing::error!(error = ?e, "notifier stopped");
+        }
     });
 
     // endregion
@@ -198,7 +266,8 @@ async fn main() -> anyhow::Result<()> {
         let id = auth_service
             .signup(SignupInput {
                 username: format!("{}{}_{}", USERNAME_PREFIX, i, run_id),
-                password: PASSWORD.to_string(),
+                password: Secret::new(PASSWORD.to_string()),
+                invite_code: None,
             })
             .await?;
         tracing::debug!("user_id: {}", id);
@@ -206,7 +275,8 @@ async fn main() -> anyhow::Result<()> {
         let result = auth_service
             .login(LoginInput {
                 username: format!("{}{}_{}", USERNAME_PREFIX, i, run_id),
-                password: PASSWORD.to_string(),
+                password: Secret::new(PASSWORD.to_string()),
+                device: None,
             })
             .await?;
         tracing::debug!("login_result: {:?}", result);
@@ -215,12 +285,12 @@ async fn main() -> anyhow::Result<()> {
     }
 
     let verify_result = auth_service
-        .verify_token(users[0].1.tokens.access_token.0.as_str())
+        .verify_token(users[0].1.tokens.access_token.0.expose().as_str())
         .await?;
     tracing::debug!("verify_result: {}", verify_result);
 
     let refresh_result = auth_service
-        .refresh_token(users[0].1.tokens.refresh_token.0.as_str())
+        .refresh_token(users[0].1.tokens.refresh_token.0.expose().as_str())
         .await?;
     tracing::debug!("refresh_result: {:?}", refresh_result);
 
@@ -232,7 +302,13 @@ async fn main() -> anyhow::Result<()> {
         let c2s_channel: Box<dyn ConnReceiver> = Box::new(c2s_rx);
         let s2c_channel: Box<dyn ConnSender> = Box::new(s2c_tx);
         connection_acceptor
-            .accept_connection(s2c_channel, c2s_channel, users[i].1.user_id)
+            .accept_connection(
+                s2c_channel,
+                c2s_channel,
+                users[i].1.user_id,
+                ProtocolVersion::CURRENT,
+                ConnectionMeta::default(),
+            )
             .await?;
         c2s.push(c2s_tx.clone());
         let handle = tokio::spawn(async move {
@@ -252,7 +328,7 @@ async fn main() -> anyhow::Result<()> {
             IdempotencyKey(uuid::Uuid::new_v4()),
         )
         .await?;
-    conversations.push(conv);
+    conversations.push(conv.conversation_id());
     let conv = relationship_service
         .add_friend(
             users[0].1.user_id,
@@ -260,7 +336,7 @@ async fn main() -> anyhow::Result<()> {
             IdempotencyKey(uuid::Uuid::new_v4()),
         )
         .await?;
-    conversations.push(conv);
+    conversations.push(conv.conversation_id());
     let conv = relationship_service
         .add_friend(
             users[1].1.user_id,
@@ -268,7 +344,7 @@ async fn main() -> anyhow::Result<()> {
             IdempotencyKey(uuid::Uuid::new_v4()),
         )
         .await?;
-    conversations.push(conv);
+    conversations.push(conv.conversation_id());
 
     let friends = relationship_service
         .list_friends(users[0].1.user_id, PageSize(10), None)
@@ -280,6 +356,7 @@ async fn main() -> anyhow::Result<()> {
             users[0].1.user_id,
             &format!("group012_{}", run_id),
             None,
+            &[],
             IdempotencyKey(uuid::Uuid::new_v4()),
         )
         .await?;
@@ -293,7 +370,12 @@ async fn main() -> anyhow::Result<()> {
         .await?;
 
     let groups = relationship_service
-        .list_groups(users[0].1.user_id, PageSize(10), None)
+        .list_groups(
+            users[0].1.user_id,
+            PageSize(10),
+            None,
+            GroupProjection::WithMemberCount,
+        )
         .await?;
     tracing::debug!("groups of testuser0: {:?}", groups);
 
@@ -307,7 +389,9 @@ async fn main() -> anyhow::Result<()> {
         let command = C2SCommand::ChatMessageSend(ChatMessageSend {
             conversation_id: conversations[i],
             message_id: MessageId(uuid::Uuid::new_v4()),
-            content: format!("hello from testuser0 ({run_id})"),
+            content: Secret::new(format!("hello from testuser0 ({run_id})")),
+            reply_to: None,
+            attachments: Vec::new(),
         });
         let s = serde_json::to_string(&command)?;
         c2s[0].send(ConnMessage::Text(s)).await?;
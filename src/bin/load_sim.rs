@@ -0,0 +1,274 @@
+/// Load simulation for the session hub and the delivery pipeline.
+///
+/// Signs up `--clients` users, puts them in groups of `--group-size`, connects
+/// each over the in-process `ConnSender`/`ConnReceiver` channels (like
+/// `infra_demo`) and has every client send `--rate` messages per second into
+/// its group for `--duration` seconds. Reports ACK latency (command sent to
+/// ACK received) and delivery latency (message stored to `chat.message.new`
+/// received by another member).
+///
+/// The server is built from the settings file, so the pipeline under test is
+/// whatever `storage.backend` selects; `"fake"` needs no MySQL, Redis or Kafka.
+///
+/// ```text
+/// cargo run --release --bin load_sim -- --settings settings/dev.toml \
+///     --clients 200 --group-size 20 --rate 2 --duration 60
+/// ```
+use chrono::Utc;
+use clap::Parser;
+use counterpoint::application_port::*;
+use counterpoint::domain_model::*;
+use counterpoint::logger::Secret;
+use counterpoint::protocol::*;
+use counterpoint::server::*;
+use counterpoint::settings::parse_settings;
+use dashmap::DashMap;
+use futures_util::future::{join_all, try_join_all};
+use nanoid::nanoid;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, fmt};
+
+/// How long to keep listening for ACKs and deliveries once sending stops.
+const DRAIN: Duration = Duration::from_secs(3);
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long)]
+    settings: Option<String>,
+    #[arg(long, default_value_t = 100)]
+    clients: usize,
+    #[arg(long, default_value_t = 10)]
+    group_size: usize,
+    /// Messages per second, per client.
+    #[arg(long, default_value_t = 1.0)]
+    rate: f64,
+    /// Seconds of sending.
+    #[arg(long, default_value_t = 30)]
+    duration: u64,
+}
+
+#[derive(Default)]
+struct Samples {
+    sent: AtomicU64,
+    /// Deliveries the ACKed messages should cause.
+    expected: AtomicU64,
+    /// Frames that were not events, e.g. "Too many messages".
+    rejected: AtomicU64,
+    ack: Mutex<Vec<Duration>>,
+    delivery: Mutex<Vec<Duration>>,
+}
+
+impl Samples {
+    fn record(samples: &Mutex<Vec<Duration>>, latency: Duration) {
+        if let Ok(mut samples) = samples.lock() {
+            samples.push(latency);
+        }
+    }
+
+    fn report(name: &str, samples: &Mutex<Vec<Duration>>) {
+        let mut samples = samples.lock().map(|s| s.clone()).unwrap_or_default();
+        samples.sort();
+        println!(
+            "{name:<9} n={:<8} p50={:>9.2?} p90={:>9.2?} p99={:>9.2?} max={:>9.2?}",
+            samples.len(),
+            percentile(&samples, 0.50),
+            percentile(&samples, 0.90),
+            percentile(&samples, 0.99),
+            samples.last().copied().unwrap_or_default(),
+        );
+    }
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (sorted.len() as f64 * p).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+struct SimClient {
+    user_id: UserId,
+    conversation_id: ConversationId,
+    group_len: usize,
+}
+
+async fn setup_clients(
+    server: &Server,
+    args: &Args,
+    run_id: &str,
+) -> anyhow::Result<Vec<SimClient>> {
+    let signups = (0..args.clients).map(|i| {
+        server.auth_service.signup(SignupInput {
+            username: format!("sim{i}_{run_id}"),
+            password: Secret::new("simpass".to_string()),
+            invite_code: None,
+        })
+    });
+    let users = try_join_all(signups).await?;
+
+    let groups = users
+        .chunks(args.group_size.max(1))
+        .map(|members| async move {
+            let owner = members[0];
+            let (_, conversation_id) = server
+                .relationship_service
+                .create_group(
+                    owner,
+                    &format!("sim_{}", owner.0),
+                    None,
+                    &members[1..],
+                    IdempotencyKey(uuid::Uuid::new_v4()),
+                )
+                .await?;
+            Ok::<_, anyhow::Error>(
+                members
+                    .iter()
+                    .map(|user_id| SimClient {
+                        user_id: *user_id,
+                        conversation_id,
+                        group_len: members.len(),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        });
+
+    Ok(try_join_all(groups).await?.into_iter().flatten().collect())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::new("load_sim=info,warn"))
+        .with(fmt::layer())
+        .init();
+
+    let alphabet: [char; 16] = [
+        '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'a', 'b', 'c', 'd', 'e', 'f',
+    ];
+    let run_id = nanoid!(10, &alphabet);
+
+    let settings = parse_settings(args.settings.as_deref())?;
+    let server = Server::try_new(&settings).await?;
+
+    let clients = setup_clients(&server, &args, &run_id).await?;
+    tracing::info!(
+        clients = clients.len(),
+        group_size = args.group_size,
+        rate = args.rate,
+        "clients ready"
+    );
+
+    let samples = Arc::new(Samples::default());
+    let pending: Arc<DashMap<MessageId, Instant>> = Arc::new(DashMap::new());
+    let send_for = Duration::from_secs(args.duration);
+    let period = Duration::from_secs_f64(1.0 / args.rate.max(0.001));
+
+    let mut readers = Vec::new();
+    let mut senders = Vec::new();
+    for client in clients {
+        let (c2s_tx, c2s_rx) = mpsc::channel::<ConnMessage>(256);
+        let (s2c_tx, mut s2c_rx) = mpsc::channel::<ConnMessage>(256);
+        server
+            .connection_acceptor
+            .accept_connection(
+                Box::new(s2c_tx),
+                Box::new(c2s_rx),
+                client.user_id,
+                ProtocolVersion::CURRENT,
+                ConnectionMeta::default(),
+            )
+            .await?;
+
+        let reader_samples = samples.clone();
+        let reader_pending = pending.clone();
+        let receivers = client.group_len.saturating_sub(1) as u64;
+        readers.push(tokio::spawn(async move {
+            while let Some(message) = s2c_rx.recv().await {
+                let ConnMessage::Text(text) = message else {
+                    continue;
+                };
+                match serde_json::from_str::<S2CEvent>(&text) {
+                    Ok(S2CEvent::ChatMessageACK(ack)) => {
+                        if let Some((_, sent_at)) = reader_pending.remove(&ack.message_id) {
+                            Samples::record(&reader_samples.ack, sent_at.elapsed());
+                            reader_samples
+                                .expected
+                                .fetch_add(receivers, Ordering::Relaxed);
+                        }
+                    }
+                    Ok(S2CEvent::ChatMessageNew(new)) => {
+                        let latency = (Utc::now() - new.created_at).to_std().unwrap_or_default();
+                        Samples::record(&reader_samples.delivery, latency);
+                    }
+                    Ok(_) => {}
+                    Err(_) => {
+                        reader_samples.rejected.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        }));
+
+        let sender_samples = samples.clone();
+        let sender_pending = pending.clone();
+        senders.push(tokio::spawn(async move {
+            let deadline = Instant::now() + send_for;
+            let mut tick = tokio::time::interval(period);
+            tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            while Instant::now() < deadline {
+                tick.tick().await;
+                let message_id = MessageId(uuid::Uuid::new_v4());
+                let command = C2SCommand::ChatMessageSend(ChatMessageSend {
+                    conversation_id: client.conversation_id,
+                    message_id,
+                    content: Secret::new(format!("load {}", message_id.0)),
+                    reply_to: None,
+                    attachments: Vec::new(),
+                });
+                let Ok(text) = serde_json::to_string(&command) else {
+                    break;
+                };
+                sender_pending.insert(message_id, Instant::now());
+                if c2s_tx.send(ConnMessage::Text(text)).await.is_err() {
+                    break;
+                }
+                sender_samples.sent.fetch_add(1, Ordering::Relaxed);
+            }
+            // dropping it later closes the connection
+            c2s_tx
+        }));
+    }
+
+    let started = Instant::now();
+    let connections: Vec<_> = join_all(senders).await.into_iter().flatten().collect();
+    let elapsed = started.elapsed();
+    tokio::time::sleep(DRAIN).await;
+
+    drop(connections);
+    server.shutdown().await;
+    join_all(readers).await;
+
+    let sent = samples.sent.load(Ordering::Relaxed);
+    println!(
+        "sent={} acked={} delivered={}/{} rejected={} in {:.1?} ({:.0} msg/s)",
+        sent,
+        samples.ack.lock().map(|s| s.len()).unwrap_or_default(),
+        samples.delivery.lock().map(|s| s.len()).unwrap_or_default(),
+        samples.expected.load(Ordering::Relaxed),
+        samples.rejected.load(Ordering::Relaxed),
+        elapsed,
+        sent as f64 / elapsed.as_secs_f64(),
+    );
+    Samples::report("ack", &samples.ack);
+    Samples::report("delivery", &samples.delivery);
+
+    Ok(())
+}
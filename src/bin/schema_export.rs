@@ -0,0 +1,51 @@
+/// Writes the WebSocket protocol JSON Schemas to a directory.
+///
+/// $ cargo run --bin schema_export -- [OUT_DIR]
+/// $ cargo run --bin schema_export -- --check [OUT_DIR]
+///
+/// With `--check` nothing is written; the command exits non-zero when the
+/// files on disk differ from the current protocol types.
+use counterpoint::protocol::*;
+use std::path::PathBuf;
+
+const DEFAULT_OUT_DIR: &str = "schema";
+
+fn main() -> anyhow::Result<()> {
+    let mut check = false;
+    let mut out_dir = PathBuf::from(DEFAULT_OUT_DIR);
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            other => out_dir = PathBuf::from(other),
+        }
+    }
+
+    if !check {
+        std::fs::create_dir_all(&out_dir)?;
+    }
+
+    let mut drifted = Vec::new();
+    for document in protocol_schemas() {
+        let path = out_dir.join(format!("{}.json", document.name));
+        let rendered = serde_json::to_string_pretty(&document.schema)? + "\n";
+
+        if check {
+            let current = std::fs::read_to_string(&path).unwrap_or_default();
+            if current != rendered {
+                drifted.push(path);
+            }
+        } else {
+            std::fs::write(&path, rendered)?;
+            println!("wrote {}", path.display());
+        }
+    }
+
+    if !drifted.is_empty() {
+        for path in &drifted {
+            eprintln!("schema out of date: {}", path.display());
+        }
+        anyhow::bail!("{} schema file(s) out of date", drifted.len());
+    }
+
+    Ok(())
+}
@@ -1,11 +1,14 @@
 use counterpoint::domain_model::*;
+use counterpoint::logger::Secret;
 use uuid::Uuid;
 
 fn main() {
     let c2s = C2SCommand::ChatMessageSend(ChatMessageSend {
         conversation_id: ConversationId(Uuid::nil()),
         message_id: MessageId(Uuid::nil()),
-        content: "Hello".to_string(),
+        content: Secret::new("Hello".to_string()),
+        reply_to: None,
+        attachments: Vec::new(),
     });
     println!("{}", serde_json::to_string(&c2s).unwrap());
 }
@@ -0,0 +1,76 @@
+use crate::domain_model::*;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialOrd,
+    Eq,
+    PartialEq,
+    Hash,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    JsonSchema,
+)]
+#[sqlx(transparent)]
+pub struct AttachmentId(pub uuid::Uuid);
+
+impl FromStr for AttachmentId {
+    type Err = uuid::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        uuid::Uuid::from_str(s).map(Self)
+    }
+}
+
+/// A file sent with a message, as clients see it. `url` downloads it, for
+/// members of the conversation only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Attachment {
+    pub attachment_id: AttachmentId,
+    pub mime: String,
+    /// In bytes.
+    pub size: u64,
+    pub url: String,
+}
+
+/// An uploaded file's metadata; the content is in the `BlobStore` under
+/// `blob_key`.
+#[derive(Debug, Clone)]
+pub struct AttachmentRecord {
+    pub attachment_id: AttachmentId,
+    pub conversation_id: ConversationId,
+    pub uploader: UserId,
+    pub mime: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+    /// The message it was sent with; `None` until then. Each upload goes
+    /// out with one message at most.
+    pub message_id: Option<MessageId>,
+}
+
+impl AttachmentRecord {
+    pub fn blob_key(&self) -> String {
+        format!(
+            "attachments/{}/{}",
+            self.conversation_id.0, self.attachment_id.0
+        )
+    }
+
+    pub fn attachment(&self) -> Attachment {
+        Attachment {
+            attachment_id: self.attachment_id,
+            mime: self.mime.clone(),
+            size: self.size,
+            url: format!(
+                "/api/v1/conversations/{}/attachments/{}",
+                self.conversation_id.0, self.attachment_id.0
+            ),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::domain_model::UserId;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// SHA-256 of a normalized phone number or email address, salted with the
+/// deployment's discovery salt, so the server never learns the identifier
+/// itself. Sent as 64 hex digits.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub struct ContactHash(pub [u8; 32]);
+
+impl fmt::Debug for ContactHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ContactHash({self})")
+    }
+}
+
+impl fmt::Display for ContactHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl FromStr for ContactHash {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(s, &mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl Serialize for ContactHash {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ContactHash {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A user who can be found by one of the hashes looked up.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContactMatch {
+    pub contact_hash: ContactHash,
+    pub user_id: UserId,
+    pub username: String,
+}
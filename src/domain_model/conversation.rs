@@ -1,13 +1,106 @@
+use crate::domain_model::UserId;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(
-    Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize, sqlx::Type,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialOrd,
+    Eq,
+    PartialEq,
+    Hash,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    JsonSchema,
 )]
 #[sqlx(transparent)]
 pub struct ConversationId(pub uuid::Uuid);
 
+impl fmt::Display for ConversationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::str::FromStr for ConversationId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        uuid::Uuid::from_str(s).map(ConversationId)
+    }
+}
+
 #[repr(u8)]
 pub enum ConversationKind {
     Direct = 1,
     Group = 2,
 }
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationMember {
+    pub user_id: UserId,
+    pub username: String,
+    /// The `members_version` that added this member.
+    pub added_version: u64,
+}
+
+/// Membership changes past a version the client already has. Membership only
+/// grows for now, so a delta is just the members added since.
+#[derive(Debug, Clone, Serialize)]
+pub struct MembersDelta {
+    /// Bumped once per membership change; clients keep it for the next call.
+    pub members_version: u64,
+    /// `true` when `members` is the whole list rather than a delta, because
+    /// the client had no version or one this conversation never reached.
+    pub snapshot: bool,
+    pub members: Vec<ConversationMember>,
+}
+
+/// One entry of the key-value metadata integrations attach to a conversation.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationMeta {
+    pub key: String,
+    pub value: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The server's bounds on how long messages are kept, from `[chat.retention]`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    /// For conversations without an override; `None` keeps messages forever.
+    pub default_days: Option<u32>,
+    pub min_days: u32,
+    pub max_days: u32,
+}
+
+impl RetentionPolicy {
+    /// `days` moved into `min_days..=max_days`.
+    pub fn clamp(&self, days: u32) -> u32 {
+        days.clamp(self.min_days, self.max_days.max(self.min_days))
+    }
+
+    /// Clamped again on read, so an override stored under looser bounds
+    /// follows the current ones.
+    pub fn apply(&self, override_days: Option<u32>) -> ConversationRetention {
+        let override_days = override_days.map(|days| self.clamp(days));
+        ConversationRetention {
+            override_days,
+            effective_days: override_days.or(self.default_days),
+        }
+    }
+}
+
+/// How long a conversation keeps its messages.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+pub struct ConversationRetention {
+    /// What the owner set; `None` follows the server default.
+    pub override_days: Option<u32>,
+    /// Messages older than this many days are deleted; `None` keeps them
+    /// forever.
+    pub effective_days: Option<u32>,
+}
@@ -0,0 +1,49 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever a cursor's fields change; older cursors are rejected.
+const CURSOR_VERSION: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CursorError {
+    #[error("cursor is not valid base64")]
+    Encoding,
+    #[error("cursor version {0} is not supported")]
+    Version(u8),
+    #[error("cursor is malformed")]
+    Malformed,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    v: u8,
+    #[serde(flatten)]
+    cursor: T,
+}
+
+/// Opaque keyset cursor handed to clients: URL-safe base64 of a versioned JSON object.
+pub trait Cursor: Serialize + DeserializeOwned {
+    fn encode(&self) -> String {
+        let envelope = Envelope {
+            v: CURSOR_VERSION,
+            cursor: self,
+        };
+        // serializing plain data into JSON cannot fail
+        let json = serde_json::to_vec(&envelope).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    fn decode(s: &str) -> Result<Self, CursorError> {
+        let json = URL_SAFE_NO_PAD
+            .decode(s)
+            .map_err(|_| CursorError::Encoding)?;
+        let envelope: Envelope<serde_json::Value> =
+            serde_json::from_slice(&json).map_err(|_| CursorError::Malformed)?;
+        if envelope.v != CURSOR_VERSION {
+            return Err(CursorError::Version(envelope.v));
+        }
+        serde_json::from_value(envelope.cursor).map_err(|_| CursorError::Malformed)
+    }
+}
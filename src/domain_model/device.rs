@@ -0,0 +1,43 @@
+use crate::domain_model::UserId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The push service a device is reached through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PushPlatform {
+    /// Apple Push Notification service.
+    Apns,
+    /// Firebase Cloud Messaging.
+    Fcm,
+}
+
+impl PushPlatform {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PushPlatform::Apns => "apns",
+            PushPlatform::Fcm => "fcm",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "apns" => Some(PushPlatform::Apns),
+            "fcm" => Some(PushPlatform::Fcm),
+            _ => None,
+        }
+    }
+}
+
+/// A device that gets push notifications for its user. The token, from the
+/// platform's push service, names one app install: registering it again,
+/// for whichever user, replaces the old registration.
+#[derive(Debug, Clone, Serialize)]
+pub struct Device {
+    pub user_id: UserId,
+    pub platform: PushPlatform,
+    pub token: String,
+    /// BCP 47, e.g. `de-AT`; what notifications are rendered in.
+    pub locale: String,
+    pub registered_at: DateTime<Utc>,
+}
@@ -0,0 +1,110 @@
+use crate::domain_model::*;
+use crate::logger::Secret;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// One line of an account export archive, which is JSON Lines: the profile
+/// first, then friends, groups, and the messages the user sent.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportRecord {
+    Profile {
+        user_id: UserId,
+        username: String,
+        created_at: Option<DateTime<Utc>>,
+        exported_at: DateTime<Utc>,
+    },
+    Friend {
+        user_id: UserId,
+        username: String,
+        conversation_id: ConversationId,
+        since: DateTime<Utc>,
+    },
+    Group {
+        group_id: GroupId,
+        name: String,
+        owner: bool,
+        conversation_id: ConversationId,
+        created_at: DateTime<Utc>,
+    },
+    Message {
+        message_id: MessageId,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+        content: Secret<String>,
+        created_at: DateTime<Utc>,
+    },
+}
+
+impl From<FriendSummary> for ExportRecord {
+    fn from(friend: FriendSummary) -> Self {
+        ExportRecord::Friend {
+            user_id: friend.user_id,
+            username: friend.username,
+            conversation_id: friend.conversation_id,
+            since: friend.since,
+        }
+    }
+}
+
+impl From<GroupSummary> for ExportRecord {
+    fn from(group: GroupSummary) -> Self {
+        ExportRecord::Group {
+            group_id: group.group_id,
+            name: group.name,
+            owner: matches!(group.my_role, GroupMemberRole::Owner),
+            conversation_id: group.conversation_id,
+            created_at: group.created_at,
+        }
+    }
+}
+
+impl From<MessageRecord> for ExportRecord {
+    fn from(message: MessageRecord) -> Self {
+        ExportRecord::Message {
+            message_id: message.message_id,
+            conversation_id: message.conversation_id,
+            message_offset: message.message_offset,
+            content: message.content,
+            created_at: message.created_at,
+        }
+    }
+}
+
+/// Where an export job is; stages run in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportStage {
+    Queued,
+    Profile,
+    Friends,
+    Groups,
+    Messages,
+    Ready,
+    Failed,
+}
+
+impl ExportStage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportStage::Queued => "queued",
+            ExportStage::Profile => "profile",
+            ExportStage::Friends => "friends",
+            ExportStage::Groups => "groups",
+            ExportStage::Messages => "messages",
+            ExportStage::Ready => "ready",
+            ExportStage::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub stage: ExportStage,
+    pub records_written: u64,
+    pub messages_written: u64,
+    /// Counted before the messages stage starts; `None` until then.
+    pub messages_total: Option<u64>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
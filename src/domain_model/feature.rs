@@ -0,0 +1,48 @@
+use crate::domain_model::UserId;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// How far one feature is rolled out, from `[features.<name>]`.
+#[derive(Debug, Clone)]
+pub struct FeatureRollout {
+    /// Share of users who get the feature, 0 to 100.
+    pub percent: u8,
+    /// Mixed into the user's hash, so each feature picks its own cohort.
+    /// Changing it reshuffles who is in.
+    pub salt: String,
+}
+
+impl FeatureRollout {
+    /// Whether the user falls in the cohort. The same user always lands in
+    /// the same bucket, so raising `percent` only ever adds users.
+    pub fn includes(&self, user_id: UserId) -> bool {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update(user_id.0.as_bytes());
+        let hash = hasher.finalize();
+        let bucket = u64::from_be_bytes(hash[..8].try_into().expect("sha256 is 32 bytes")) % 100;
+        bucket < u64::from(self.percent)
+    }
+}
+
+/// The features being soft launched, by name.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlags {
+    pub rollouts: BTreeMap<String, FeatureRollout>,
+}
+
+impl FeatureFlags {
+    /// Every feature, and whether this user has it.
+    pub fn for_user(&self, user_id: UserId) -> BTreeMap<String, bool> {
+        self.rollouts
+            .iter()
+            .map(|(name, rollout)| (name.clone(), rollout.includes(user_id)))
+            .collect()
+    }
+
+    pub fn enabled(&self, name: &str, user_id: UserId) -> bool {
+        self.rollouts
+            .get(name)
+            .is_some_and(|rollout| rollout.includes(user_id))
+    }
+}
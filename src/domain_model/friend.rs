@@ -1,30 +1,14 @@
-use crate::domain_model::{ConversationId, UserId};
+use crate::domain_model::{ConversationId, Cursor, UserId};
 use chrono::{DateTime, Utc};
-use serde::Serialize;
-use std::str::FromStr;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct FriendCursor {
     pub since: DateTime<Utc>,
     pub other_user: UserId, // tiebreaker
 }
 
-impl FromStr for FriendCursor {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (date_str, user_str) = s.split_once('~').ok_or("invalid cursor format")?;
-
-        let since = date_str
-            .parse::<DateTime<Utc>>()
-            .map_err(|e| e.to_string())?;
-
-        let other_user = uuid::Uuid::parse_str(user_str)
-            .map(UserId)
-            .map_err(|e| e.to_string())?;
-
-        Ok(FriendCursor { since, other_user })
-    }
-}
+impl Cursor for FriendCursor {}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct FriendSummary {
@@ -33,3 +17,19 @@ pub struct FriendSummary {
     pub conversation_id: ConversationId,
     pub since: DateTime<Utc>,
 }
+
+/// Result of `add_friend`: whether this call created the friendship.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "status", content = "conversation_id", rename_all = "snake_case")]
+pub enum AddFriendOutcome {
+    Created(ConversationId),
+    AlreadyFriends(ConversationId),
+}
+
+impl AddFriendOutcome {
+    pub fn conversation_id(&self) -> ConversationId {
+        match self {
+            AddFriendOutcome::Created(id) | AddFriendOutcome::AlreadyFriends(id) => *id,
+        }
+    }
+}
@@ -1,11 +1,24 @@
-use crate::domain_model::{ConversationId, UserId};
+use crate::domain_model::{ConversationId, Cursor, UserId};
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
 
 // region relationship service
 #[derive(
-    Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize, sqlx::Type,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialOrd,
+    Eq,
+    PartialEq,
+    Hash,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    JsonSchema,
 )]
 #[sqlx(transparent)]
 pub struct GroupId(pub uuid::Uuid);
@@ -16,31 +29,70 @@ impl fmt::Display for GroupId {
     }
 }
 
-#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+impl FromStr for GroupId {
+    type Err = uuid::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        uuid::Uuid::from_str(s).map(Self)
+    }
+}
+
+/// How clients draw a group; unset fields fall back to the client's own.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GroupAppearance {
+    /// An `https` image URL.
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    /// `#rrggbb`.
+    #[serde(default)]
+    pub theme_color: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct GroupCursor {
     pub created_at: DateTime<Utc>,
     pub group_id: GroupId, // tiebreaker
 }
 
+impl Cursor for GroupCursor {}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct MemberCursor {
     pub joined_at: DateTime<Utc>,
     pub user: UserId, // tiebreaker
 }
 
+impl Cursor for MemberCursor {}
+
 #[derive(Debug, Clone)]
 pub enum GroupMemberRole {
     Owner,
     Member,
 }
 
+/// Which computed fields `list_groups` fills in.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum GroupProjection {
+    /// Skips member counting; `member_count` is `None`.
+    #[default]
+    Lite,
+    WithMemberCount,
+}
+
 #[derive(Debug, Clone)]
 pub struct GroupSummary {
     pub group_id: GroupId,
     pub name: String,
     pub my_role: GroupMemberRole, // smell hint: this field seems redundant
     pub conversation_id: ConversationId,
-    pub member_count: u32, // smell hint: this field seems redundant
+    pub member_count: Option<u32>, // only with `GroupProjection::WithMemberCount`
     pub created_at: DateTime<Utc>,
+    pub appearance: GroupAppearance,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupMemberCount {
+    pub group_id: GroupId,
+    pub member_count: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,119 @@
+use crate::domain_model::*;
+use crate::logger::Secret;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Part of the history of another chat system, with its ids, timestamps and
+/// offsets kept as they were. Rows that already exist are skipped, so a
+/// batch can be sent again after a failure.
+///
+/// Send a migration oldest data first: users before the friendships and
+/// groups that name them, and those before their messages.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ImportBatch {
+    #[serde(default)]
+    pub users: Vec<ImportUser>,
+    #[serde(default)]
+    pub friendships: Vec<ImportFriendship>,
+    #[serde(default)]
+    pub groups: Vec<ImportGroup>,
+    #[serde(default)]
+    pub messages: Vec<ImportMessage>,
+}
+
+impl ImportBatch {
+    pub fn len(&self) -> usize {
+        self.users.len() + self.friendships.len() + self.groups.len() + self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportUser {
+    pub user_id: UserId,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+    /// An Argon2 PHC string. Users imported without one cannot log in.
+    #[serde(default)]
+    pub password_hash: Option<Secret<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportFriendship {
+    pub a: UserId,
+    pub b: UserId,
+    pub conversation_id: ConversationId,
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportGroup {
+    pub group_id: GroupId,
+    pub conversation_id: ConversationId,
+    pub owner: UserId,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Everyone besides `owner`.
+    #[serde(default)]
+    pub members: Vec<UserId>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportMessage {
+    pub message_id: MessageId,
+    pub conversation_id: ConversationId,
+    pub message_offset: MessageOffset,
+    pub sender: UserId,
+    pub content: Secret<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ImportMessage> for MessageRecord {
+    fn from(message: ImportMessage) -> Self {
+        Self {
+            message_id: message.message_id,
+            conversation_id: message.conversation_id,
+            message_offset: message.message_offset,
+            sender: message.sender,
+            content: message.content,
+            content_encrypted: false,
+            created_at: message.created_at,
+            edited_at: None,
+            edit_count: 0,
+            deleted_at: None,
+            kind: MessageKind::User,
+            reply_to: None,
+            attachments: Vec::new(),
+        }
+    }
+}
+
+/// What one `ImportBatch` added.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub users: ImportCount,
+    pub friendships: ImportCount,
+    pub groups: ImportCount,
+    pub messages: ImportCount,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ImportCount {
+    pub imported: u64,
+    /// Already there, or naming a user or conversation that isn't.
+    pub skipped: u64,
+}
+
+impl ImportCount {
+    pub fn of(total: usize, imported: u64) -> Self {
+        Self {
+            imported,
+            skipped: (total as u64).saturating_sub(imported),
+        }
+    }
+}
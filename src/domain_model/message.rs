@@ -1,16 +1,47 @@
 use crate::domain_model::*;
+use crate::logger::Secret;
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
 #[derive(
-    Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize, sqlx::Type,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialOrd,
+    Eq,
+    PartialEq,
+    Hash,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    JsonSchema,
 )]
 #[sqlx(transparent)]
 pub struct MessageId(pub uuid::Uuid);
 
+impl FromStr for MessageId {
+    type Err = uuid::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        uuid::Uuid::from_str(s).map(Self)
+    }
+}
+
 #[derive(
-    Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize, sqlx::Type,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialOrd,
+    Eq,
+    PartialEq,
+    Hash,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    JsonSchema,
 )]
 #[sqlx(transparent)]
 pub struct MessageOffset(pub u64);
@@ -24,26 +55,64 @@ impl FromStr for MessageOffset {
 }
 
 /// Cursor for time-ordered lists (recent convos)
-#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct TimeCursor {
+    /// Pinned conversations come first; cursors from before pinning decode
+    /// as unpinned.
+    #[serde(default)]
+    pub pinned: bool,
     pub last_msg_at: DateTime<Utc>,
     pub conversation_id: ConversationId, // tie-breaker for stable pagination
 }
 
+impl Cursor for TimeCursor {}
+
 /// Cursor for offset-ordered lists (history)
-#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct OffsetCursor {
     pub offset: MessageOffset,
 }
 
-impl FromStr for OffsetCursor {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let offset = s
-            .parse::<MessageOffset>()
-            .map_err(|e| format!("invalid offset: {}", e))?;
+impl Cursor for OffsetCursor {}
+
+/// Cursor for messages across conversations, newest first: search results
+/// and mentions.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SearchCursor {
+    pub created_at: DateTime<Utc>,
+    pub message_id: MessageId, // tie-breaker
+}
 
-        Ok(OffsetCursor { offset })
+impl Cursor for SearchCursor {}
+
+/// Narrows a message search; every filter set must hold.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SearchFilters {
+    pub conversation_id: Option<ConversationId>,
+    pub sender: Option<UserId>,
+    /// Sent at or after.
+    pub since: Option<DateTime<Utc>>,
+    /// Sent before.
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Who wrote a message. A `System` message is written by the server, such
+/// as the welcome a new conversation starts with; its `sender` is the user
+/// whose action caused it.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageKind {
+    #[default]
+    User,
+    System,
+}
+
+impl MessageKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MessageKind::User => "user",
+            MessageKind::System => "system",
+        }
     }
 }
 
@@ -53,6 +122,100 @@ pub struct MessageRecord {
     pub conversation_id: ConversationId,
     pub message_offset: MessageOffset,
     pub sender: UserId,
-    pub content: String,
+    pub content: Secret<String>,
+    /// Whether `content` is sealed as stored; only storage sets it, and
+    /// `EncryptedMessageRepo` opens it before anyone else sees the record.
+    #[serde(skip)]
+    pub content_encrypted: bool,
     pub created_at: DateTime<Utc>,
+    /// When `content` was last replaced; `None` if it never was.
+    pub edited_at: Option<DateTime<Utc>>,
+    pub edit_count: u32,
+    /// Set on a tombstone, whose `content` is empty.
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub kind: MessageKind,
+    /// The message in the same conversation this one answers; it may since
+    /// have been deleted or pruned.
+    pub reply_to: Option<MessageId>,
+    /// Oldest upload first.
+    pub attachments: Vec<Attachment>,
+}
+
+/// A message pinned to the top of its conversation, for every member.
+#[derive(Debug, Clone, Serialize)]
+pub struct PinnedMessage {
+    pub message: MessageRecord,
+    pub pinned_by: UserId,
+    pub pinned_at: DateTime<Utc>,
+}
+
+/// A receiver's client confirmed it got a message.
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageDelivery {
+    pub user_id: UserId,
+    pub delivered_at: DateTime<Utc>,
+}
+
+/// What a send left stored.
+#[derive(Debug, Clone)]
+pub struct SentMessage {
+    pub record: MessageRecord,
+    /// The sender's own count of messages in the conversation, this one
+    /// included. Gaps are possible; order is not.
+    pub sender_seq: u64,
+    /// The `message_id` was already stored: `record` is the original and
+    /// nothing was sent again.
+    pub duplicate: bool,
+}
+
+/// The stored extent of one conversation's history. Offsets can have gaps,
+/// so `message_count` may be smaller than the span between them.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistorySummary {
+    pub conversation_id: ConversationId,
+    /// `None` for a conversation with no messages.
+    pub first_offset: Option<MessageOffset>,
+    pub last_offset: Option<MessageOffset>,
+    pub message_count: u64,
+}
+
+impl HistorySummary {
+    /// Whether anything is stored below `before`, or at all without it.
+    pub fn has_messages_before(&self, before: Option<OffsetCursor>) -> bool {
+        match (self.first_offset, before) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(first), Some(before)) => first < before.offset,
+        }
+    }
+}
+
+/// Each conversation's unread count stops here; a badge shows no more anyway,
+/// and counting a long backlog row by row would cost a scan per list.
+pub const MAX_UNREAD_COUNT: u64 = 999;
+
+/// What a user has not read yet, for badging. Counts are the live messages
+/// from others past the member's `last_read_off`, each at most
+/// `MAX_UNREAD_COUNT`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreadSummary {
+    /// Across every conversation, not only those listed.
+    pub total_unread: u64,
+    /// Most recently active first.
+    pub conversations: Vec<UnreadConversation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreadConversation {
+    pub conversation_id: ConversationId,
+    pub unread: u64,
+    pub last_msg_at: Option<DateTime<Utc>>,
+}
+
+/// A member's read position after `mark_read_in_tx`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadMark {
+    pub last_read_off: MessageOffset,
+    /// `false` when the position was already at or past the requested offset.
+    pub advanced: bool,
 }
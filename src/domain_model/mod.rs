@@ -1,19 +1,39 @@
+mod attachment;
 mod captcha;
+mod contact;
 mod conversation;
+mod cursor;
+mod device;
+mod export;
+mod feature;
 mod friend;
 mod group;
+mod import;
 mod key;
 mod message;
+mod stats;
 mod stream;
+mod trace;
 mod unit;
 mod user;
+mod webhook;
 
+pub use attachment::*;
 pub use captcha::*;
+pub use contact::*;
 pub use conversation::*;
+pub use cursor::*;
+pub use device::*;
+pub use export::*;
+pub use feature::*;
 pub use friend::*;
 pub use group::*;
+pub use import::*;
 pub use key::*;
 pub use message::*;
+pub use stats::*;
 pub use stream::*;
+pub use trace::*;
 pub use unit::*;
 pub use user::*;
+pub use webhook::*;
@@ -0,0 +1,35 @@
+use crate::domain_model::*;
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// Activity on one UTC day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DailyStats {
+    pub day: NaiveDate,
+    /// Users who sent at least one message that day.
+    pub active_users: u64,
+    /// Sent by users, deleted ones included; welcomes and other system
+    /// messages don't count.
+    pub messages: u64,
+    pub signups: u64,
+}
+
+impl DailyStats {
+    pub fn empty(day: NaiveDate) -> Self {
+        Self {
+            day,
+            active_users: 0,
+            messages: 0,
+            signups: 0,
+        }
+    }
+}
+
+/// How many messages a group's members sent over a stretch of days.
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupTraffic {
+    pub group_id: GroupId,
+    pub conversation_id: ConversationId,
+    pub name: String,
+    pub messages: u64,
+}
@@ -1,33 +1,104 @@
 use crate::domain_model::*;
+use crate::logger::Secret;
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct C2SEnvelope {
     pub sender: UserId,
     pub body: C2SCommand,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", content = "content", rename_all = "lowercase")]
 pub enum C2SCommand {
     ChatMessageSend(ChatMessageSend),
+    ChatTyping(ChatTypingSend),
+    TypingStart(TypingSignal),
+    TypingStop(TypingSignal),
+    SetActiveConversations(SetActiveConversations),
+    Delivered(DeliveryAck),
+    HistorySince(HistorySince),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ChatMessageSend {
     pub conversation_id: ConversationId,
     pub message_id: MessageId,
-    pub content: String,
+    pub content: Secret<String>,
+    /// Makes the message a reply; the target must be in the same
+    /// conversation and not deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<MessageId>,
+    /// Files the sender uploaded to the conversation and has not sent yet.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<AttachmentId>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The sender is typing in the conversation. Clients repeat it every few
+/// seconds while typing continues; there is no "stopped" command. Kept for
+/// older clients and handled as `TypingStart`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ChatTypingSend {
+    pub conversation_id: ConversationId,
+}
+
+/// `TypingStart` while the sender types, repeated every few seconds as
+/// `ChatTypingSend` was, and one `TypingStop` when they stop or send. Neither
+/// is stored or acknowledged.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TypingSignal {
+    pub conversation_id: ConversationId,
+}
+
+/// The conversations the client is rendering right now, replacing any earlier
+/// list; empty when none is on screen. Until a client sends this, every
+/// conversation counts as active.
+///
+/// New messages for the other conversations are sent behind everything else,
+/// and their typing notices are not sent at all.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SetActiveConversations {
+    pub conversation_ids: Vec<ConversationId>,
+}
+
+/// The client got the message as a `ChatMessageNew`. The sender hears of it
+/// through `ChatMessageDelivered`, once per receiver; repeats, and acks for
+/// the client's own messages, are ignored. Nothing is sent back.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DeliveryAck {
+    pub conversation_id: ConversationId,
+    pub message_id: MessageId,
+}
+
+/// Sent after a reconnect: the server replies with the messages after
+/// `after_offset`, the newest the client has, as `ChatMessageNew` events in
+/// offset order, at most 500 per command; a client that got that many asks
+/// again from the last. Deleted messages are left out. Events that arrive
+/// live meanwhile may repeat some, so clients drop offsets they already have.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct HistorySince {
+    pub conversation_id: ConversationId,
+    pub after_offset: MessageOffset,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct S2CEnvelope {
     pub receivers: Vec<UserId>,
     pub body: S2CEvent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<TraceId>,
+    /// When the outbox row was written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the notifier handed the event to the broker.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type", content = "content", rename_all = "lowercase")]
 pub enum S2CEvent {
     ChatMessageACK(ChatMessageACK),
@@ -35,45 +106,287 @@ pub enum S2CEvent {
     FriendshipNew(FriendshipNew),
     GroupNew(GroupNew),
     GroupMemberNew(GroupMemberNew),
+    SessionTerminated(SessionTerminated),
+    ChatTyping(ChatTyping),
+    ConversationMetaChanged(ConversationMetaChanged),
+    SecurityAlert(SecurityAlert),
+    ChatRead(ChatRead),
+    ConversationFrozen(ConversationFrozen),
+    ChatMessageEdited(ChatMessageEdited),
+    ChatMessageDeleted(ChatMessageDeleted),
+    GroupUpdated(GroupUpdated),
+    TypingEvent(TypingEvent),
+    MessagePinned(MessagePinned),
+    ChatMentionNew(ChatMentionNew),
+    ChatMessageDelivered(ChatMessageDelivered),
+    SystemNotice(SystemNotice),
+    ConversationSnapshot(ConversationSnapshot),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ChatMessageACK {
     pub conversation_id: ConversationId,
     pub message_id: MessageId,
     pub message_offset: MessageOffset,
     pub created_at: DateTime<Utc>,
+    /// The sender's own count of messages in the conversation, this one
+    /// included; the same on every ACK for one `message_id`.
+    #[serde(default)]
+    pub sequence: u64,
+    /// Set when the send was a retry of a message already stored.
+    #[serde(default)]
+    pub duplicate: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ChatMessageNew {
     pub conversation_id: ConversationId,
     pub message_id: MessageId,
     pub message_offset: MessageOffset,
-    pub content: String,
+    pub content: Secret<String>,
+    pub sender: UserId,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+    /// Set when the connection is in data-saver mode and `content` was cut to
+    /// a preview; the full message is in the conversation history.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// The message this one replies to; its replies are listed by the
+    /// thread history API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<MessageId>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+}
+
+/// The sender replaced the content of a message. Sent to every member, the
+/// sender included; `edit_count` only grows, so a client can drop an edit
+/// older than what it shows.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ChatMessageEdited {
+    pub conversation_id: ConversationId,
+    pub message_id: MessageId,
+    pub message_offset: MessageOffset,
+    pub content: Secret<String>,
+    pub edited_at: DateTime<Utc>,
+    pub edit_count: u32,
+}
+
+/// A message was deleted; history keeps it at `message_offset` as a
+/// tombstone with no content. Sent to every member, the deleter included.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ChatMessageDeleted {
+    pub conversation_id: ConversationId,
+    pub message_id: MessageId,
+    pub message_offset: MessageOffset,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// A message was pinned to the top of its conversation, or unpinned when
+/// `pinned` is false. Sent to every member, the one who did it included.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MessagePinned {
+    pub conversation_id: ConversationId,
+    pub message_id: MessageId,
+    pub message_offset: MessageOffset,
+    pub pinned: bool,
+    pub by: UserId,
+    pub at: DateTime<Utc>,
+}
+
+/// The receiver was mentioned in a new message, which reaches them as a
+/// `ChatMessageNew` too. Sent only to the members mentioned, never the sender.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ChatMentionNew {
+    pub conversation_id: ConversationId,
+    pub message_id: MessageId,
+    pub message_offset: MessageOffset,
     pub sender: UserId,
     pub username: String,
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A client of `user_id` got the receiver's message. Sent only to the
+/// message's sender, once per member who acked it.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ChatMessageDelivered {
+    pub conversation_id: ConversationId,
+    pub message_id: MessageId,
+    pub message_offset: MessageOffset,
+    pub user_id: UserId,
+    pub delivered_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct FriendshipNew {
     pub conversation_id: ConversationId,
     pub other: UserId,
     pub username: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct GroupNew {
     pub conversation_id: ConversationId,
     pub group_id: GroupId,
     pub group_name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct GroupMemberNew {
     pub conversation_id: ConversationId,
     pub group_id: GroupId,
     pub member_id: UserId,
     pub username: String,
+    /// The conversation's `members_version` after this member joined. A
+    /// client that holds anything but the version before it should fetch the
+    /// delta instead of applying the event.
+    #[serde(default)]
+    pub members_version: u64,
+}
+
+/// The owner changed how the group looks; the fields are its whole new
+/// appearance, not a patch.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GroupUpdated {
+    pub conversation_id: ConversationId,
+    pub group_id: GroupId,
+    pub avatar_url: Option<String>,
+    pub theme_color: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ConversationMetaChanged {
+    pub conversation_id: ConversationId,
+    pub key: String,
+    /// `None` when the key was removed.
+    pub value: Option<String>,
+}
+
+/// The conversation was frozen, so sends to it are refused until it is
+/// thawed, or it was thawed.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ConversationFrozen {
+    pub conversation_id: ConversationId,
+    pub frozen: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityAlertKind {
+    /// A login from a location the account hasn't logged in from before.
+    UnusualLogin,
+}
+
+/// Sent to every session of the account it concerns.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SecurityAlert {
+    pub kind: SecurityAlertKind,
+    pub ip: IpAddr,
+    /// Coarse, as `LoginRiskEvaluator` judged it; not a street address.
+    pub location: String,
+    pub device: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct NoticeId(pub uuid::Uuid);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeKind {
+    /// The service will be down or degraded between `starts_at` and
+    /// `ends_at`.
+    Maintenance,
+    /// The terms or policies changed.
+    Policy,
+    Announcement,
+}
+
+/// From the operators, not any conversation: sent to everyone online, or to
+/// the users an operator picked who are. Nobody offline gets it later.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SystemNotice {
+    /// The same on every copy, for clients that see one twice.
+    pub notice_id: NoticeId,
+    pub kind: NoticeKind,
+    pub title: String,
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<DateTime<Utc>>,
+    /// When it went out, or is scheduled to.
+    pub published_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminationReason {
+    /// The same user opened a newer session.
+    Replaced,
+    /// An administrator closed the session.
+    AdminDisconnect,
+    /// The account was deactivated or banned.
+    Deactivated,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SessionTerminated {
+    pub reason: TerminationReason,
+}
+
+/// The first event on a connection opened with `snapshot=true`: the user's
+/// most recent conversations, pinned ones first, so the client can render
+/// its list before asking for anything. Not sent if it can't be read.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ConversationSnapshot {
+    pub conversations: Vec<SnapshotConversation>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotConversation {
+    pub conversation_id: ConversationId,
+    /// The other member's username, or the group's name.
+    pub name: String,
+    /// Set for a direct conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub other_user: Option<UserId>,
+    /// Set for a group's conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<GroupId>,
+    pub last_msg_off: MessageOffset,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_msg_at: Option<DateTime<Utc>>,
+    pub unread_count: u64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub frozen: bool,
+}
+
+/// A member read the conversation up to `last_read_off`. Sent to every
+/// member, the reader included, so their other sessions can clear the badge.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ChatRead {
+    pub conversation_id: ConversationId,
+    pub user_id: UserId,
+    pub last_read_off: MessageOffset,
+}
+
+/// Members who started or kept typing in the conversation since the last
+/// `ChatTyping` for it. May include the receiver.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ChatTyping {
+    pub conversation_id: ConversationId,
+    pub users: Vec<UserId>,
+}
+
+/// `user_id` started or stopped typing in the conversation. Relayed as it
+/// happens and never stored, so a client that misses the stop should let the
+/// indicator lapse on its own after a few seconds without a start.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TypingEvent {
+    pub conversation_id: ConversationId,
+    pub user_id: UserId,
+    pub typing: bool,
 }
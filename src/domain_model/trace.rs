@@ -0,0 +1,51 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+tokio::task_local! {
+    static CURRENT_TRACE: TraceId;
+}
+
+/// Ties a client request to the outbox events it causes and to their
+/// delivery, so one send can be followed from HTTP/WS to the receivers.
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize, sqlx::Type, JsonSchema,
+)]
+#[sqlx(transparent)]
+pub struct TraceId(pub uuid::Uuid);
+
+impl TraceId {
+    pub fn new() -> Self {
+        Self(uuid::Uuid::new_v4())
+    }
+
+    /// The trace of the request this task is serving, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT_TRACE.try_with(|trace_id| *trace_id).ok()
+    }
+
+    /// Runs `f` with `self` as the current trace.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT_TRACE.scope(self, f).await
+    }
+}
+
+impl Default for TraceId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.simple().fmt(f)
+    }
+}
+
+impl FromStr for TraceId {
+    type Err = uuid::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.parse()?))
+    }
+}
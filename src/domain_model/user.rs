@@ -1,8 +1,21 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
 #[derive(
-    Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize, sqlx::Type,
+    Debug,
+    Clone,
+    Copy,
+    Ord,
+    PartialOrd,
+    Eq,
+    PartialEq,
+    Hash,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    JsonSchema,
 )]
 #[sqlx(transparent)]
 pub struct UserId(pub uuid::Uuid);
@@ -36,3 +49,33 @@ impl UserPair {
         self.1
     }
 }
+
+/// Bars usernames at signup, on top of the configured ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum UsernameRule {
+    /// One exact name, compared case-insensitively.
+    Reserved(String),
+    /// A regex; unanchored, so add `^`/`$` to match whole names.
+    Pattern(String),
+}
+
+/// Lets signups through while `auth.invite_only` is set, up to `max_uses`
+/// times.
+#[derive(Debug, Clone, Serialize)]
+pub struct InviteCode {
+    pub code: String,
+    pub max_uses: u32,
+    pub uses: u32,
+    /// `None` never expires.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The admin caller, as logged.
+    pub minted_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl InviteCode {
+    pub fn redeemable(&self, now: DateTime<Utc>) -> bool {
+        self.uses < self.max_uses && self.expires_at.is_none_or(|at| at > now)
+    }
+}
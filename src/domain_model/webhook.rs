@@ -0,0 +1,77 @@
+use crate::domain_model::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct WebhookId(pub uuid::Uuid);
+
+impl FromStr for WebhookId {
+    type Err = uuid::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        uuid::Uuid::from_str(s).map(Self)
+    }
+}
+
+/// An endpoint a group owner registered for the group's conversation. Every
+/// new message in it is POSTed there, signed with the hook's secret.
+#[derive(Debug, Clone, Serialize)]
+pub struct Webhook {
+    pub webhook_id: WebhookId,
+    pub conversation_id: ConversationId,
+    pub url: String,
+    pub created_by: UserId,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A hook just registered: the one time its secret is shown.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    /// Keys the HMAC-SHA256 in each delivery's signature header.
+    pub secret: String,
+}
+
+/// Where the dispatcher delivers a conversation's messages.
+#[derive(Debug, Clone)]
+pub struct WebhookTarget {
+    pub webhook_id: WebhookId,
+    pub url: String,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct IncomingWebhookId(pub uuid::Uuid);
+
+impl FromStr for IncomingWebhookId {
+    type Err = uuid::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        uuid::Uuid::from_str(s).map(Self)
+    }
+}
+
+/// A URL integrations such as CI or alerting post to; each post becomes a
+/// message in the conversation from `bot_user_id`, a member the group owner
+/// picked.
+#[derive(Debug, Clone, Serialize)]
+pub struct IncomingWebhook {
+    pub incoming_webhook_id: IncomingWebhookId,
+    pub conversation_id: ConversationId,
+    /// What the owner calls it, e.g. `CI`.
+    pub name: String,
+    pub bot_user_id: UserId,
+    pub created_by: UserId,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An incoming hook just issued: the one time its token is shown. Only a
+/// hash of it is kept.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewIncomingWebhook {
+    #[serde(flatten)]
+    pub webhook: IncomingWebhook,
+    pub token: String,
+}
@@ -0,0 +1,41 @@
+use crate::domain_model::*;
+use crate::domain_port::repo_tx::StorageTx;
+use chrono::{DateTime, Utc};
+
+#[async_trait::async_trait]
+pub trait AttachmentRepo: Send + Sync {
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        record: &AttachmentRecord,
+    ) -> anyhow::Result<()>;
+    /// `None` if `conversation_id` has no attachment with that id that
+    /// `viewer` may see: one a message carries, or their own upload.
+    async fn get_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        attachment_id: AttachmentId,
+        viewer: UserId,
+    ) -> anyhow::Result<Option<AttachmentRecord>>;
+    /// Ties `attachment_ids` to `message_id` and returns them, oldest upload
+    /// first. Only uploads of `uploader` in `conversation_id` that are not
+    /// tied to another message qualify; `None`, with nothing changed, if any
+    /// of them doesn't.
+    async fn attach_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        uploader: UserId,
+        message_id: MessageId,
+        attachment_ids: &[AttachmentId],
+    ) -> anyhow::Result<Option<Vec<AttachmentRecord>>>;
+    /// Deletes up to `limit` uploads made before `before` that never went
+    /// out with a message, and returns them so their blobs can follow.
+    async fn delete_unattached_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        before: DateTime<Utc>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<AttachmentRecord>>;
+}
@@ -28,4 +28,11 @@ pub trait AuthRepo: Send + Sync {
         &self,
         username: &str,
     ) -> Result<Option<AuthCredentialsRecord>, AuthError>;
+
+    /// Mark credentials inactive so the user can no longer log in.
+    async fn deactivate_credentials_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<(), AuthError>;
 }
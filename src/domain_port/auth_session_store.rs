@@ -6,8 +6,7 @@ pub trait AuthSessionStore: Send + Sync {
     /// Save a refresh token jti for a user with TTL.
     async fn save_refresh_jti(
         &self,
-        user_id: UserId,
-        jti: &str,
+        session: &RefreshSession,
         ttl_secs: u64,
     ) -> Result<(), AuthError>;
     /// Check if JTI is present (valid). If valid and consume=true, delete it (rotation).
@@ -16,5 +15,11 @@ pub trait AuthSessionStore: Send + Sync {
         user_id: UserId,
         jti: &str,
         consume: bool,
-    ) -> Result<Option<UserId>, AuthError>;
+    ) -> Result<Option<RefreshSession>, AuthError>;
+    /// Live refresh sessions of the user, oldest first, whichever node issued
+    /// them.
+    async fn list_jtis(&self, user_id: UserId) -> Result<Vec<RefreshSession>, AuthError>;
+    /// Delete every refresh JTI issued to the user, by any node. Returns how
+    /// many were live.
+    async fn revoke_all(&self, user_id: UserId) -> Result<u64, AuthError>;
 }
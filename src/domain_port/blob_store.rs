@@ -0,0 +1,40 @@
+/// Stored content and the type it was stored with.
+#[derive(Debug, Clone)]
+pub struct Blob {
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// Opaque files by key, such as attachments; an object store in production.
+#[async_trait::async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Overwrites whatever `key` held.
+    async fn put(&self, key: &str, content_type: &str, content: &[u8]) -> anyhow::Result<()>;
+    /// Starts storing content too large to hold at once under `key`; it is
+    /// stored once the upload is finished.
+    async fn start_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+    ) -> anyhow::Result<Box<dyn BlobUpload>>;
+    /// `None` if nothing is stored under `key`.
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Blob>>;
+    /// Up to `len` bytes of what `key` holds, starting at `offset`; fewer
+    /// near its end and none past it. `None` if nothing is stored under
+    /// `key`.
+    async fn get_range(&self, key: &str, offset: u64, len: u64) -> anyhow::Result<Option<Vec<u8>>>;
+    /// Removes `key`; nothing stored under it is no error.
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+}
+
+/// Content handed to a `BlobStore` a piece at a time. An upload neither
+/// finished nor aborted leaves nothing under its key, though the store may
+/// hold on to the pieces for a while.
+#[async_trait::async_trait]
+pub trait BlobUpload: Send {
+    async fn write(&mut self, content: &[u8]) -> anyhow::Result<()>;
+    /// Stores everything written, overwriting whatever the key held.
+    async fn finish(&mut self) -> anyhow::Result<()>;
+    /// Drops everything written.
+    async fn abort(&mut self);
+}
@@ -0,0 +1,20 @@
+use crate::domain_model::*;
+
+/// Short-lived memory of the ACKs already sent for client commands, so a
+/// retried `ChatMessageSend` is answered without touching the database.
+#[async_trait::async_trait]
+pub trait CommandDedupeStore: Send + Sync {
+    /// Look up the ACK previously sent to `user_id` for `message_id`.
+    async fn get_ack(
+        &self,
+        user_id: UserId,
+        message_id: MessageId,
+    ) -> anyhow::Result<Option<ChatMessageACK>>;
+    /// Remember the ACK for `ttl_secs`.
+    async fn save_ack(
+        &self,
+        user_id: UserId,
+        ack: &ChatMessageACK,
+        ttl_secs: u64,
+    ) -> anyhow::Result<()>;
+}
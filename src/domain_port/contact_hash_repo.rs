@@ -0,0 +1,29 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+
+/// Hashes of users' own phone numbers and email addresses, for contact
+/// discovery. Storing any makes the user discoverable; a user with none
+/// can't be found.
+#[async_trait::async_trait]
+pub trait ContactHashRepo: Send + Sync {
+    /// How many `user_id` has stored.
+    async fn count_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> anyhow::Result<usize>;
+    /// Replaces `user_id`'s hashes; an empty slice removes them all.
+    async fn replace_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        hashes: &[ContactHash],
+    ) -> anyhow::Result<()>;
+    /// Active users other than `seeker` stored under any of `hashes`.
+    async fn find_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        seeker: UserId,
+        hashes: &[ContactHash],
+    ) -> anyhow::Result<Vec<ContactMatch>>;
+}
@@ -0,0 +1,34 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::repo_tx::StorageTx;
+
+#[async_trait::async_trait]
+pub trait ConversationMetaRepo: Send + Sync {
+    /// Ordered by key.
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<ConversationMeta>, ChatError>;
+    /// The conversation's keys, locked against other writers until the
+    /// transaction ends.
+    async fn lock_keys_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<String>, ChatError>;
+    async fn put_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        key: &str,
+        value: &str,
+    ) -> Result<(), ChatError>;
+    /// Returns `false` if there was no such key.
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        key: &str,
+    ) -> Result<bool, ChatError>;
+}
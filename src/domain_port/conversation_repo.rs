@@ -1,6 +1,7 @@
 use crate::application_port::*;
 use crate::domain_model::*;
 use crate::domain_port::repo_tx::StorageTx;
+use chrono::{DateTime, Utc};
 
 #[async_trait::async_trait]
 pub trait ConversationRepo: Send + Sync {
@@ -22,7 +23,29 @@ pub trait ConversationRepo: Send + Sync {
         conversation_id: ConversationId,
     ) -> Result<(), RelationError>;
 
-    /// Recent for a user, order by (last_msg_at DESC, conversation_id DESC)
+    /// Hides the conversation from recent lists and stops new offsets being
+    /// allocated; members and messages stay. Returns `false` if already gone.
+    async fn soft_delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<bool, RelationError>;
+    async fn members_version_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<u64, RelationError>;
+    /// Members added after `since_version`, or all of them when it is `None`
+    /// or ahead of the conversation.
+    async fn members_since_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        since_version: Option<u64>,
+    ) -> Result<MembersDelta, ChatError>;
+
+    /// Recent for a user, order by (pinned DESC, last_msg_at DESC,
+    /// conversation_id DESC)
     async fn list_for_user_recent_in_tx<'t>(
         &self,
         tx: &mut dyn StorageTx<'t>,
@@ -36,4 +59,92 @@ pub trait ConversationRepo: Send + Sync {
         user_id: UserId,
         conversation_ids: Vec<ConversationId>,
     ) -> Result<Vec<RecentConversation>, ChatError>;
+    /// Conversations with unread messages, up to `top` of them, and the total
+    /// across all, in one query.
+    async fn unread_summary_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        top: PageSize,
+    ) -> Result<UnreadSummary, ChatError>;
+    /// `ConversationNotFound` if missing or deleted.
+    async fn is_frozen_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<bool, ChatError>;
+    /// Returns `false` if the conversation was already in that state.
+    async fn set_frozen_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        frozen: bool,
+    ) -> Result<bool, ChatError>;
+    /// The owner's retention override, unclamped; `ConversationNotFound` if
+    /// missing or deleted.
+    async fn retention_days_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<Option<u32>, ChatError>;
+    /// `ConversationNotFound` if missing or deleted.
+    async fn set_retention_days_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        days: Option<u32>,
+    ) -> Result<(), ChatError>;
+    /// Raises the member's `last_read_off` to `offset` without the cap
+    /// `mark_read_in_tx` applies, for a sender, who has read what they sent;
+    /// never lowers it.
+    async fn advance_read_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+    ) -> Result<(), ChatError>;
+    /// Raises the member's `last_read_off` to `offset`, capped at the
+    /// conversation's `last_msg_off`; never lowers it. `NotMember` if
+    /// `user_id` has no member row.
+    async fn mark_read_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+    ) -> Result<ReadMark, ChatError>;
+    /// Returns `false` if `user_id` is not a member.
+    async fn set_pinned_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        pinned: bool,
+    ) -> Result<bool, ChatError>;
+    /// Hands out the sender's next `sender_seq`, holding their member row
+    /// until the transaction ends.
+    async fn next_sender_seq_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        sender: UserId,
+    ) -> Result<u64, ChatError>;
+
+    /// Raises the persisted offset reservation to at least `floor` (and past
+    /// every offset already used) plus `block`, returning the new ceiling.
+    /// Runs outside any transaction so a rollback can never shrink it.
+    async fn reserve_offsets(
+        &self,
+        conversation_id: ConversationId,
+        floor: u64,
+        block: u64,
+    ) -> Result<u64, ChatError>;
+    /// Moves `last_msg_off`/`last_msg_at` forward; never backwards.
+    async fn advance_last_message(
+        &self,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+        at: DateTime<Utc>,
+    ) -> Result<(), ChatError>;
 }
@@ -21,10 +21,36 @@ pub trait ConversationRoleRepo: Send + Sync {
         user_id: UserId,
         role_name: &str,
     ) -> Result<(), RelationError>;
+    /// Same as `assign_role_by_name_in_tx` for every user in `user_ids`,
+    /// using multi-row inserts.
+    async fn assign_roles_bulk_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        user_ids: &[UserId],
+        role_name: &str,
+    ) -> Result<(), RelationError>;
+    /// Membership check outside a transaction, for read paths that would
+    /// otherwise open one just to be turned away.
+    async fn membership_exists(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<bool, RelationError>;
     async fn membership_exists_in_tx<'t>(
         &self,
         tx: &mut dyn StorageTx<'t>,
         conversation_id: ConversationId,
         user_id: UserId,
     ) -> Result<bool, RelationError>;
+    /// Whether `user_id`'s role in the conversation allows `perm_key`, an
+    /// explicit deny winning; `None` if they hold no role there, as in
+    /// direct conversations.
+    async fn has_permission_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        user_id: UserId,
+        perm_key: &str,
+    ) -> Result<Option<bool>, RelationError>;
 }
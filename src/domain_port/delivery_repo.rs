@@ -0,0 +1,30 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+
+/// Which receivers' clients confirmed getting which messages, one row per
+/// message and receiver.
+#[async_trait::async_trait]
+pub trait DeliveryRepo: Send + Sync {
+    /// Records that `user_id`'s client got the message, unless that was
+    /// recorded already, `user_id` sent it, or it is deleted or missing.
+    /// Returns the message's sender and offset when the row is new.
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+        user_id: UserId,
+        at: DateTime<Utc>,
+    ) -> Result<Option<(UserId, MessageOffset)>, ChatError>;
+    /// Who got the message, earliest first; `None` if `sender` didn't send
+    /// a message with that id in the conversation.
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+        sender: UserId,
+    ) -> Result<Option<Vec<MessageDelivery>>, ChatError>;
+}
@@ -0,0 +1,41 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+
+/// Devices registered for push notifications, one row per token.
+#[async_trait::async_trait]
+pub trait DeviceRepo: Send + Sync {
+    /// Adds the device, or takes its token over from whoever had it.
+    async fn upsert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        device: &Device,
+    ) -> anyhow::Result<()>;
+    /// Drops `user_id`'s devices past the `keep` registered most recently.
+    /// Returns how many went.
+    async fn trim_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        keep: usize,
+    ) -> anyhow::Result<u64>;
+    /// False if `user_id` has no such device.
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        platform: PushPlatform,
+        token: &str,
+    ) -> anyhow::Result<bool>;
+    async fn list_for_users_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_ids: &[UserId],
+    ) -> anyhow::Result<Vec<Device>>;
+    /// Forgets `tokens` whoever they belong to. Returns how many were known.
+    async fn delete_tokens_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        platform: PushPlatform,
+        tokens: &[String],
+    ) -> anyhow::Result<u64>;
+}
@@ -0,0 +1,79 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+
+/// An account export job as stored, so any node can report on it or serve
+/// its archive.
+#[derive(Debug, Clone)]
+pub struct ExportJobRecord {
+    pub user_id: UserId,
+    pub progress: ExportProgress,
+    /// When the node running it last saved `progress`.
+    pub updated_at: DateTime<Utc>,
+    /// What the download should be saved as.
+    pub file_name: String,
+    /// Where the archive goes in the `BlobStore` once it is built.
+    pub blob_key: String,
+    /// The archive is encrypted with this key, wrapped by the KMS key
+    /// `key_id`, or stored as is when there is no KMS.
+    pub archive_key: Vec<u8>,
+    pub key_id: Option<String>,
+}
+
+impl ExportJobRecord {
+    pub fn id(&self) -> ExportJobId {
+        ExportJobId {
+            user_id: self.user_id,
+            started_at: self.progress.started_at,
+        }
+    }
+}
+
+/// A user's job as opposed to their earlier or later ones, so work left
+/// over from one never lands on another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportJobId {
+    pub user_id: UserId,
+    pub started_at: DateTime<Utc>,
+}
+
+/// One export job per user; a job is told apart from the user's earlier
+/// ones by its `ExportJobId`.
+#[async_trait::async_trait]
+pub trait ExportJobRepo: Send + Sync {
+    async fn get_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> anyhow::Result<Option<ExportJobRecord>>;
+    /// False, with nothing changed, if the user has a job already.
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        job: &ExportJobRecord,
+    ) -> anyhow::Result<bool>;
+    /// Saves the progress of `job` and stamps `updated_at`; false if that
+    /// job is gone.
+    async fn update_progress_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        job: ExportJobId,
+        progress: &ExportProgress,
+    ) -> anyhow::Result<bool>;
+    /// False if `job` is gone already.
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        job: ExportJobId,
+    ) -> anyhow::Result<bool>;
+    /// Deletes up to `limit` jobs that finished before `finished_before`, or
+    /// never finished and were last updated before `updated_before`, and
+    /// returns them so their archives can follow.
+    async fn delete_expired_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        finished_before: DateTime<Utc>,
+        updated_before: DateTime<Utc>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<ExportJobRecord>>;
+}
@@ -4,16 +4,24 @@ use crate::domain_port::repo_tx::StorageTx;
 
 pub enum FriendshipIdemClaim {
     Won,
-    Existing,
+    /// `replay` is true when the row was claimed by the same requester with
+    /// the same idempotency key, i.e. this call is a client retry.
+    Existing {
+        replay: bool,
+    },
 }
 
+/// Friendships are undirected: every method accepts the two users in either
+/// order and normalizes them to `(user_min, user_max)` itself.
 #[async_trait::async_trait]
 pub trait FriendshipRepo: Send + Sync {
+    /// `Won` if this call inserted the friendship row, `Existing` otherwise.
     async fn claim(
         &self,
         a: UserId,
         b: UserId,
         requested_by: UserId,
+        key: IdempotencyKey,
     ) -> Result<FriendshipIdemClaim, RelationError>;
     async fn insert_friendship_in_tx(
         &self,
@@ -22,6 +30,7 @@ pub trait FriendshipRepo: Send + Sync {
         b: UserId,
         conversation_id: ConversationId,
     ) -> Result<(), RelationError>;
+    /// `RelationError::NotFriends` if no direct conversation exists for the pair.
     async fn get_conversation_id_by_friendship(
         &self,
         a: UserId,
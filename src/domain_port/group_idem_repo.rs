@@ -1,5 +1,6 @@
 use crate::application_port::*;
 use crate::domain_model::*;
+use std::time::Duration;
 
 pub enum GroupIdemClaim {
     Won {
@@ -41,4 +42,15 @@ pub trait GroupIdemRepo: Send + Sync {
         group_id: GroupId,
         _err: &str,
     ) -> Result<(), RelationError>;
+    /// Takes over a pending claim whose winner has not finished within
+    /// `stale_after`, keeping its `group_id`. Only one caller can win a retake.
+    async fn retake_stale(
+        &self,
+        owner: UserId,
+        key: IdempotencyKey,
+        group_id: GroupId,
+        stale_after: Duration,
+    ) -> Result<bool, RelationError>;
+    /// Deletes claims created more than `older_than` ago; returns the row count.
+    async fn purge_expired(&self, older_than: Duration) -> Result<u64, RelationError>;
 }
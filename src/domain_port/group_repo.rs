@@ -9,6 +9,8 @@ pub struct GroupShortSummary {
     pub conversation_id: ConversationId,
 }
 
+/// Disbanded groups (`deleted_at` set) are invisible to every read here; the
+/// row itself is kept so old messages still resolve.
 #[async_trait::async_trait]
 pub trait GroupRepo: Send + Sync {
     async fn get_group_summary_in_tx(
@@ -34,7 +36,24 @@ pub trait GroupRepo: Send + Sync {
         user_id: UserId,
         page_size: PageSize,
         after: Option<GroupCursor>,
+        projection: GroupProjection,
     ) -> Result<Vec<GroupSummary>, RelationError>;
+    /// Counts only groups `user_id` belongs to; other ids are left out.
+    async fn count_members(
+        &self,
+        user_id: UserId,
+        group_ids: &[GroupId],
+    ) -> Result<Vec<GroupMemberCount>, RelationError>;
+    /// Rewrites every `member_count` that drifted from the membership table,
+    /// a group at a time, returning how many groups were fixed. Only one
+    /// node runs it at once; the others return 0 straight away.
+    async fn reconcile_member_counts(&self) -> Result<u64, RelationError>;
+    /// Returns `false` if the group is missing or already disbanded.
+    async fn disband_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        group_id: GroupId,
+    ) -> Result<bool, RelationError>;
     async fn list_group_members_in_tx(
         &self,
         tx: &mut dyn StorageTx<'_>,
@@ -42,4 +61,16 @@ pub trait GroupRepo: Send + Sync {
         page_size: PageSize,
         after: Option<MemberCursor>,
     ) -> Result<Vec<MemberSummary>, RelationError>;
+    async fn get_appearance_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        group_id: GroupId,
+    ) -> Result<GroupAppearance, RelationError>;
+    /// Returns `false` when the group already looked like that.
+    async fn set_appearance_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        group_id: GroupId,
+        appearance: &GroupAppearance,
+    ) -> Result<bool, RelationError>;
 }
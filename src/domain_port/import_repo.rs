@@ -0,0 +1,51 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::repo_tx::StorageTx;
+use std::collections::HashSet;
+
+/// Multi-row writes for `ImportService`. Each insert skips rows whose key
+/// is already taken and returns how many were new.
+#[async_trait::async_trait]
+pub trait ImportRepo: Send + Sync {
+    /// The ones among `user_ids` that exist, active or not.
+    async fn existing_users_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_ids: &[UserId],
+    ) -> Result<HashSet<UserId>, ImportError>;
+    /// The ones among `conversation_ids` that exist, deleted or not.
+    async fn existing_conversations_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_ids: &[ConversationId],
+    ) -> Result<HashSet<ConversationId>, ImportError>;
+    /// Users, and credentials for those with a password hash. A username
+    /// already held by another user skips the row.
+    async fn insert_users_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        users: &[ImportUser],
+    ) -> Result<u64, ImportError>;
+    /// The direct conversation, its two members and the accepted friendship.
+    async fn insert_friendships_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        friendships: &[ImportFriendship],
+    ) -> Result<u64, ImportError>;
+    /// The group conversation, the group and its memberships, all as of the
+    /// group's `created_at`; every member must exist. Roles are left to
+    /// `ConversationRoleRepo`. Returns the groups that were new.
+    async fn insert_groups_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        groups: &[ImportGroup],
+    ) -> Result<Vec<GroupId>, ImportError>;
+    /// Brings what sending keeps up to date in line with messages written
+    /// by `MessageRepo::insert_batch_in_tx`: sender sequences, members'
+    /// sent counts, and the conversation's last message and offset counter.
+    async fn settle_messages_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_ids: &[ConversationId],
+    ) -> Result<(), ImportError>;
+}
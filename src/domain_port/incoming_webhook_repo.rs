@@ -0,0 +1,31 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+
+/// Incoming webhooks, found by the SHA-256 of their token.
+#[async_trait::async_trait]
+pub trait IncomingWebhookRepo: Send + Sync {
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        webhook: &IncomingWebhook,
+        token_hash: &str,
+    ) -> anyhow::Result<()>;
+    /// Oldest first.
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> anyhow::Result<Vec<IncomingWebhook>>;
+    /// False if the conversation has no such hook.
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        incoming_webhook_id: IncomingWebhookId,
+    ) -> anyhow::Result<bool>;
+    async fn find_by_token_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        token_hash: &str,
+    ) -> anyhow::Result<Option<IncomingWebhook>>;
+}
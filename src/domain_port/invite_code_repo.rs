@@ -0,0 +1,17 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+
+#[async_trait::async_trait]
+pub trait InviteCodeRepo: Send + Sync {
+    async fn insert(&self, codes: &[InviteCode]) -> Result<(), AuthError>;
+    /// Newest first.
+    async fn list(&self) -> Result<Vec<InviteCode>, AuthError>;
+    /// Takes one use of `code`. Returns `false` if it doesn't exist, is used
+    /// up, or has expired; the use is given back if `tx` rolls back.
+    async fn redeem_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        code: &str,
+    ) -> Result<bool, AuthError>;
+}
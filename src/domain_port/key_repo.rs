@@ -0,0 +1,36 @@
+use crate::domain_model::*;
+use crate::domain_port::repo_tx::StorageTx;
+
+/// A conversation's data key as stored: encrypted by the KMS key `key_id`.
+#[derive(Debug, Clone)]
+pub struct WrappedKey {
+    pub key_id: String,
+    pub wrapped: Vec<u8>,
+}
+
+#[async_trait::async_trait]
+pub trait KeyRepo: Send + Sync {
+    async fn get(&self, conversation_id: ConversationId) -> anyhow::Result<Option<WrappedKey>>;
+    async fn get_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> anyhow::Result<Option<WrappedKey>>;
+    /// Returns the key that ends up stored, which is the existing one if
+    /// another writer got there first.
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        key: &WrappedKey,
+    ) -> anyhow::Result<WrappedKey>;
+}
+
+/// Wraps and unwraps data keys with a master key that stays in the KMS.
+#[async_trait::async_trait]
+pub trait Kms: Send + Sync {
+    /// The master key new data keys are wrapped with.
+    fn key_id(&self) -> &str;
+    async fn wrap(&self, data_key: &[u8]) -> anyhow::Result<WrappedKey>;
+    async fn unwrap(&self, key: &WrappedKey) -> anyhow::Result<Vec<u8>>;
+}
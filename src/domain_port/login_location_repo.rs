@@ -0,0 +1,24 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+use std::net::IpAddr;
+
+/// Where each account has logged in from, as `coarse_location`s.
+#[async_trait::async_trait]
+pub trait LoginLocationRepo: Send + Sync {
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<Vec<String>, AuthError>;
+    /// Adds the location, or bumps its count and last seen address.
+    async fn record_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        location: &str,
+        ip: IpAddr,
+        at: DateTime<Utc>,
+    ) -> Result<(), AuthError>;
+}
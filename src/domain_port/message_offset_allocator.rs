@@ -0,0 +1,32 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::repo_tx::StorageTx;
+use chrono::{DateTime, Utc};
+
+/// Hands out per-conversation message offsets.
+///
+/// Offsets only ever grow within a conversation, but may have gaps.
+#[async_trait::async_trait]
+pub trait MessageOffsetAllocator: Send + Sync {
+    /// `at` is the send time; the conversation's last-message pointers move to
+    /// it, either within `tx` or, once `record_sent` says so, in the
+    /// background.
+    async fn allocate_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        at: DateTime<Utc>,
+    ) -> Result<MessageOffset, ChatError>;
+
+    /// Called once the transaction that took `offset` committed, so pointers
+    /// kept outside it never move to a send that rolled back. Nothing to do
+    /// for an allocator that moves them within `tx`.
+    async fn record_sent(
+        &self,
+        _conversation_id: ConversationId,
+        _offset: MessageOffset,
+        _at: DateTime<Utc>,
+    ) -> Result<(), ChatError> {
+        Ok(())
+    }
+}
@@ -1,17 +1,43 @@
 use crate::application_port::*;
 use crate::domain_model::*;
 use crate::domain_port::repo_tx::StorageTx;
+use chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
+
+/// What one `prune_expired` batch deleted.
+#[derive(Debug, Default)]
+pub struct PrunedMessages {
+    pub messages: u64,
+    /// Their rows are gone with the messages; the blobs are left for the
+    /// caller to delete once the batch is committed.
+    pub attachments: Vec<AttachmentRecord>,
+}
 
 #[async_trait::async_trait]
 pub trait MessageRepo: Send + Sync {
+    /// Returns the stored message, which is the existing one, marked
+    /// `duplicate`, if `message_id` was already inserted.
     async fn insert_in_tx<'t>(
         &self,
         tx: &mut dyn StorageTx<'t>,
-        conversation_id: ConversationId,
-        sender: UserId,
-        content: &str,
+        record: &MessageRecord,
+        sender_seq: u64,
+    ) -> Result<SentMessage, ChatError>;
+    /// Multi-row insert for imports, with `sender_seq` left at 0. Rows whose
+    /// `message_id` or offset is taken, or whose conversation or sender is
+    /// missing, are skipped; returns how many were inserted.
+    /// The message `message_id`, marked `duplicate`, if it was already
+    /// inserted; lets a resend be answered before it takes an offset.
+    async fn get_sent_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
         message_id: MessageId,
-    ) -> Result<MessageRecord, ChatError>;
+    ) -> Result<Option<SentMessage>, ChatError>;
+    async fn insert_batch_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        records: &[MessageRecord],
+    ) -> Result<u64, ChatError>;
     async fn list_before_in_tx<'t>(
         &self,
         tx: &mut dyn StorageTx<'t>,
@@ -19,4 +45,121 @@ pub trait MessageRepo: Send + Sync {
         page_size: PageSize,
         before: Option<OffsetCursor>,
     ) -> Result<Vec<MessageRecord>, ChatError>;
+    /// Messages after `after`, in offset order; deleted ones excluded.
+    async fn list_after_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        after: MessageOffset,
+        page_size: PageSize,
+    ) -> Result<Vec<MessageRecord>, ChatError>;
+    /// Messages replying to `root`, in offset order, after the cursor.
+    async fn list_thread_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        root: MessageId,
+        page_size: PageSize,
+        after: Option<OffsetCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError>;
+    /// Messages with every one of `terms` in conversations `user_id` is a
+    /// member of, newest first, before the cursor; deleted ones excluded.
+    async fn search_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        terms: &[String],
+        filters: &SearchFilters,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError>;
+    /// The message, locked until the transaction ends; `None` if
+    /// `conversation_id` has no message with that id.
+    async fn lock_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+    ) -> Result<Option<MessageRecord>, ChatError>;
+    /// Writes `content`, `edited_at` and `edit_count` back to the stored
+    /// message; the rest of `record` only identifies it.
+    async fn update_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        record: &MessageRecord,
+    ) -> Result<(), ChatError>;
+    /// Turns the message into a tombstone; its content is kept but no
+    /// longer returned.
+    async fn mark_deleted_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+        at: DateTime<Utc>,
+    ) -> Result<(), ChatError>;
+    /// Records that `record` mentions each of `mentioned`.
+    async fn insert_mentions_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        record: &MessageRecord,
+        mentioned: &[UserId],
+    ) -> Result<(), ChatError>;
+    /// Messages mentioning `user_id` in conversations they are a member of,
+    /// newest first, before the cursor; deleted ones excluded.
+    async fn list_mentions_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError>;
+    /// Pins the message unless it already is; returns whether it wasn't.
+    async fn pin_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+        pinned_by: UserId,
+        at: DateTime<Utc>,
+    ) -> Result<bool, ChatError>;
+    /// Returns whether the message was pinned.
+    async fn unpin_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+    ) -> Result<bool, ChatError>;
+    /// The conversation's pinned messages, most recently pinned first;
+    /// deleted ones excluded.
+    async fn list_pinned_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<PinnedMessage>, ChatError>;
+    async fn summarize_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<HistorySummary, ChatError>;
+    /// The offset of the message whose `created_at` is nearest `at`, the
+    /// later one on a tie; `None` if the conversation has no messages.
+    async fn offset_at_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        at: DateTime<Utc>,
+    ) -> Result<Option<MessageOffset>, ChatError>;
+    /// Deletes up to `limit` messages older than their conversation's
+    /// retention under `policy`, keeping those whose sender is under legal
+    /// hold, and the attachments they went out with. Runs in a transaction
+    /// of its own.
+    async fn prune_expired(
+        &self,
+        policy: &RetentionPolicy,
+        limit: u32,
+    ) -> Result<PrunedMessages, ChatError>;
+    async fn count_by_sender(&self, sender: UserId) -> Result<u64, ChatError>;
+    /// Everything `sender` wrote, oldest first, read from the store as the
+    /// stream is polled, without attachments. Runs outside any transaction.
+    fn stream_by_sender(&self, sender: UserId) -> BoxStream<'_, Result<MessageRecord, ChatError>>;
 }
@@ -1,33 +1,75 @@
 // store
 
 mod auth_session_store;
+mod blob_store;
 mod captcha_store;
+mod command_dedupe_store;
+mod push_provider;
+mod qr_login_store;
+mod rate_limiter;
+mod webhook_client;
 
 pub use auth_session_store::*;
+pub use blob_store::*;
 pub use captcha_store::*;
+pub use command_dedupe_store::*;
+pub use push_provider::*;
+pub use qr_login_store::*;
+pub use rate_limiter::*;
+pub use webhook_client::*;
 
 // repo
 
+mod attachment_repo;
 mod auth_repo;
+mod contact_hash_repo;
+mod conversation_meta_repo;
 mod conversation_repo;
 mod conversation_role_repo;
+mod delivery_repo;
+mod device_repo;
+mod export_job_repo;
 mod friendship_repo;
 mod group_idem_repo;
 mod group_repo;
+mod import_repo;
+mod incoming_webhook_repo;
+mod invite_code_repo;
+mod key_repo;
+mod login_location_repo;
+mod message_offset_allocator;
 mod message_repo;
 mod outbox_repo;
+mod stats_repo;
 mod user_repo;
+mod username_rule_repo;
+mod webhook_repo;
 
 mod repo_tx;
 
+pub use attachment_repo::*;
 pub use auth_repo::*;
+pub use contact_hash_repo::*;
+pub use conversation_meta_repo::*;
 pub use conversation_repo::*;
 pub use conversation_role_repo::*;
+pub use delivery_repo::*;
+pub use device_repo::*;
+pub use export_job_repo::*;
 pub use friendship_repo::*;
 pub use group_idem_repo::*;
 pub use group_repo::*;
+pub use import_repo::*;
+pub use incoming_webhook_repo::*;
+pub use invite_code_repo::*;
+pub use key_repo::*;
+pub use login_location_repo::*;
+pub use message_offset_allocator::*;
 pub use message_repo::*;
 pub use outbox_repo::*;
+pub use stats_repo::*;
 pub use user_repo::*;
+pub use username_rule_repo::*;
+pub use webhook_repo::*;
 
 pub use repo_tx::*;
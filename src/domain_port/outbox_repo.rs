@@ -19,6 +19,32 @@ pub enum EventType {
     GroupNew,
     #[serde(rename = "group.member.new")]
     GroupMemberNew,
+    #[serde(rename = "session.terminated")]
+    SessionTerminated,
+    #[serde(rename = "chat.typing")]
+    ChatTyping,
+    #[serde(rename = "conversation.meta.changed")]
+    ConversationMetaChanged,
+    #[serde(rename = "security.alert")]
+    SecurityAlert,
+    #[serde(rename = "chat.read")]
+    ChatRead,
+    #[serde(rename = "conversation.frozen")]
+    ConversationFrozen,
+    #[serde(rename = "chat.message.edited")]
+    ChatMessageEdited,
+    #[serde(rename = "chat.message.deleted")]
+    ChatMessageDeleted,
+    #[serde(rename = "group.updated")]
+    GroupUpdated,
+    #[serde(rename = "message.pinned")]
+    MessagePinned,
+    #[serde(rename = "chat.mention.new")]
+    ChatMentionNew,
+    #[serde(rename = "chat.message.delivered")]
+    ChatMessageDelivered,
+    #[serde(rename = "system.notice")]
+    SystemNotice,
 }
 
 #[derive(Debug, Clone)]
@@ -30,6 +56,10 @@ pub struct OutboxEvent {
     pub receivers_json: serde_json::Value,
     pub payload_json: serde_json::Value,
     pub created_at: DateTime<Utc>,
+    pub trace_id: Option<TraceId>,
+    /// Held back until then; `None` publishes it right away. Only read on
+    /// enqueue; see `with_publish_at`.
+    pub publish_at: Option<DateTime<Utc>>,
 }
 
 impl OutboxEvent {
@@ -39,6 +69,7 @@ impl OutboxEvent {
         receivers: Vec<UserId>,
         payload: &T,
     ) -> anyhow::Result<Self> {
+        // picked up from the request being served, see `TraceId::scope`
         Ok(Self {
             event_id: EventId(uuid::Uuid::new_v4()),
             event_type,
@@ -46,8 +77,29 @@ impl OutboxEvent {
             receivers_json: serde_json::to_value(receivers)?,
             payload_json: serde_json::to_value(payload)?,
             created_at: Utc::now(),
+            trace_id: TraceId::current(),
+            publish_at: None,
         })
     }
+
+    /// Also moves `created_at` to `publish_at`, so delivery latency counts
+    /// from when the event was due rather than when it was queued.
+    pub fn with_publish_at(mut self, publish_at: DateTime<Utc>) -> Self {
+        self.created_at = publish_at;
+        self.publish_at = Some(publish_at);
+        self
+    }
+}
+
+/// Which delivered events an operator wants published again.
+#[derive(Debug, Clone)]
+pub enum ReplaySelection {
+    Events(Vec<EventId>),
+    /// `from` inclusive, `to` exclusive, on `created_at`.
+    CreatedBetween {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    },
 }
 
 #[async_trait::async_trait]
@@ -79,4 +131,18 @@ pub trait OutboxRepo: Send + Sync {
         next_attempt_at: DateTime<Utc>,
         last_error: &str,
     ) -> anyhow::Result<()>;
+
+    /// Clears `delivered_at` on the selected delivered events and stamps
+    /// `redelivered_at`, so the notifier picks them up again. Returns the
+    /// number requeued.
+    async fn requeue_delivered_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        selection: &ReplaySelection,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<u64>;
+
+    /// Events due and not yet delivered; those waiting for a retry or for
+    /// the time they are scheduled at aren't counted.
+    async fn count_undelivered(&self) -> anyhow::Result<u64>;
 }
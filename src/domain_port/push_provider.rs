@@ -0,0 +1,34 @@
+use crate::domain_model::*;
+use serde::Serialize;
+
+/// New messages for a device's user, named by the newest of them. Data
+/// only: the client fetches the messages itself, so contents never pass
+/// through a push service.
+#[derive(Debug, Clone, Serialize)]
+pub struct PushNotification {
+    pub conversation_id: ConversationId,
+    pub message_id: MessageId,
+    pub sender_name: String,
+    /// More than 1 for a digest, which the client shows as "5 new messages
+    /// in 2 chats".
+    pub message_count: u32,
+    pub conversation_count: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Delivered,
+    /// The push service won't take the token again: the app was
+    /// uninstalled, or the token was never valid.
+    InvalidToken,
+}
+
+/// Sends through the push service of `device.platform`.
+#[async_trait::async_trait]
+pub trait PushProvider: Send + Sync {
+    async fn send(
+        &self,
+        device: &Device,
+        notification: &PushNotification,
+    ) -> anyhow::Result<PushOutcome>;
+}
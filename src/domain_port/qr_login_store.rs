@@ -0,0 +1,30 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+
+/// A QR login waiting to be approved, or approved and waiting to be
+/// collected.
+#[derive(Debug, Clone)]
+pub struct QrLogin {
+    /// Hex SHA-256 of the poll secret only the starting client knows.
+    pub secret_hash: String,
+    pub device: Option<String>,
+    pub approved_by: Option<UserId>,
+}
+
+#[async_trait::async_trait]
+pub trait QrLoginStore: Send + Sync {
+    /// Save a pending login under `nonce`; it's forgotten after `ttl_secs`,
+    /// approved or not.
+    async fn create(&self, nonce: &str, login: &QrLogin, ttl_secs: u64) -> Result<(), AuthError>;
+    /// Approve a pending login as `user_id`. False if it's unknown, expired or
+    /// already approved.
+    async fn approve(&self, nonce: &str, user_id: UserId) -> Result<bool, AuthError>;
+    /// `None` while pending; once approved, the login, deleted so only one
+    /// caller ever sees it. `QrLoginNotFound` if unknown, expired or
+    /// `secret_hash` doesn't match.
+    async fn take_if_approved(
+        &self,
+        nonce: &str,
+        secret_hash: &str,
+    ) -> Result<Option<QrLogin>, AuthError>;
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// At most `limit` uses per `period`. Windows are aligned to the Unix epoch,
+/// so an hour runs from :00 to :00 and a day from midnight UTC.
+#[derive(Debug, Clone, Copy)]
+pub struct RateWindow {
+    pub limit: u32,
+    pub period: Duration,
+}
+
+impl RateWindow {
+    /// The window `at` falls in, and when it ends.
+    pub fn bucket(&self, at: DateTime<Utc>) -> (i64, DateTime<Utc>) {
+        let period = self.period.as_secs().max(1) as i64;
+        let index = at.timestamp().div_euclid(period);
+        let ends_at = DateTime::from_timestamp((index + 1) * period, 0).unwrap_or(at);
+        (index, ends_at)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Allowed,
+    /// Nothing was counted; retry after `reset_at`.
+    Limited {
+        reset_at: DateTime<Utc>,
+    },
+}
+
+/// Fixed-window usage counters shared by every node.
+#[async_trait::async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// Counts one use by `subject` in every window, or in none if any of them
+    /// is full.
+    async fn acquire(&self, subject: &str, windows: &[RateWindow]) -> anyhow::Result<RateDecision>;
+    /// Takes back a use `acquire` counted, for attempts that turned out to
+    /// change nothing.
+    async fn release(&self, subject: &str, windows: &[RateWindow]) -> anyhow::Result<()>;
+}
@@ -1,6 +1,47 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Attempts made by [`TxManager::with_retry`], including the first one.
+const TX_RETRY_ATTEMPTS: u32 = 3;
+/// Backoff before the first retry; doubled on every further retry.
+const TX_RETRY_BACKOFF: Duration = Duration::from_millis(20);
+
 #[async_trait::async_trait]
 pub trait TxManager: Send + Sync {
     async fn begin<'t>(&'t self) -> anyhow::Result<Box<dyn StorageTx<'t> + 't>>;
+
+    /// Whether a failed transaction can simply be run again, e.g. it was
+    /// picked as a deadlock victim. Repos flatten store errors into strings,
+    /// so this sees the rendered error.
+    fn is_retryable(&self, _error: &str) -> bool {
+        false
+    }
+}
+
+impl dyn TxManager {
+    /// Runs `f` and runs it again, with backoff, while it fails with an
+    /// error [`TxManager::is_retryable`] accepts. `f` must open and commit
+    /// its own transaction so every attempt starts clean.
+    pub async fn with_retry<T, E, F, Fut>(&self, mut f: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Display,
+    {
+        let mut backoff = TX_RETRY_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            match f().await {
+                Err(e) if attempt < TX_RETRY_ATTEMPTS && self.is_retryable(&e.to_string()) => {
+                    tracing::debug!("retrying transaction (attempt {attempt}): {e}");
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
 }
 
 #[async_trait::async_trait]
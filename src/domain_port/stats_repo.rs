@@ -0,0 +1,17 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use chrono::{DateTime, Utc};
+
+/// Aggregates over the whole store, for admins. Each call stands alone;
+/// none take a transaction.
+#[async_trait::async_trait]
+pub trait StatsRepo: Send + Sync {
+    /// One row per UTC day from `since` on that had any activity.
+    async fn daily(&self, since: DateTime<Utc>) -> Result<Vec<DailyStats>, StatsError>;
+    /// Busiest first; disbanded groups are left out.
+    async fn top_groups(
+        &self,
+        since: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<GroupTraffic>, StatsError>;
+}
@@ -8,6 +8,11 @@ pub struct UserRecord {
     pub user_id: UserId,
     pub username: String,
     pub is_active: bool,
+    /// Set by an operator; the account and its messages must not be deleted
+    /// or pruned while it is.
+    pub legal_hold: bool,
+    /// Set by an operator; only bot accounts can back an incoming webhook.
+    pub is_bot: bool,
     pub created_at: DateTime<Utc>,
 }
 
@@ -20,6 +25,13 @@ pub trait UserRepo: Send + Sync {
         username: &str,
     ) -> Result<(), AuthError>;
 
+    /// Active or not; `None` only if the user never existed.
+    async fn get_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<Option<UserRecord>, AuthError>;
+
     async fn get_username_in_tx<'t>(
         &self,
         tx: &mut dyn StorageTx<'t>,
@@ -32,7 +44,47 @@ pub trait UserRepo: Send + Sync {
         username: &str,
     ) -> Result<UserId, AuthError>;
 
+    /// Returns `false` if the user was already inactive.
+    async fn deactivate_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<bool, AuthError>;
+
+    /// Deactivates and marks the user deleted; the row stays so message
+    /// history keeps resolving. Returns `false` if already deleted or under
+    /// legal hold.
+    async fn soft_delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<bool, AuthError>;
+
+    /// Deleted users can be held too. Returns `false` only if the user never
+    /// existed.
+    async fn set_legal_hold_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        hold: bool,
+    ) -> Result<bool, AuthError>;
+
+    /// Returns `false` only if the user never existed.
+    async fn set_bot_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        bot: bool,
+    ) -> Result<bool, AuthError>;
+
     async fn username_exists(&self, username: &str) -> Result<bool, AuthError>;
 
+    /// True only for active users.
     async fn id_exists(&self, user_id: UserId) -> Result<bool, AuthError>;
 }
+
+/// Drops cached answers about a user once a change to them has committed,
+/// so a node stops serving the old answer before its TTL runs out.
+pub trait UserCacheInvalidator: Send + Sync {
+    fn forget(&self, user_id: UserId);
+}
@@ -0,0 +1,14 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+
+/// Admin-managed `UsernameRule`s. Each call stands alone; none take a
+/// transaction.
+#[async_trait::async_trait]
+pub trait UsernameRuleRepo: Send + Sync {
+    /// In the order they were added.
+    async fn list(&self) -> Result<Vec<UsernameRule>, AuthError>;
+    /// Returns `false` if the rule already exists.
+    async fn add(&self, rule: &UsernameRule) -> Result<bool, AuthError>;
+    /// Returns `false` if there was no such rule.
+    async fn remove(&self, rule: &UsernameRule) -> Result<bool, AuthError>;
+}
@@ -0,0 +1,14 @@
+/// One POST to a webhook: the JSON body and the headers that sign it.
+#[derive(Debug, Clone)]
+pub struct WebhookRequest {
+    pub url: String,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Sends webhook deliveries over HTTP.
+#[async_trait::async_trait]
+pub trait WebhookClient: Send + Sync {
+    /// The response's status code; `Err` when no response came back.
+    async fn post(&self, request: &WebhookRequest) -> anyhow::Result<u16>;
+}
@@ -0,0 +1,31 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+
+/// Outbound webhooks, with their signing secrets.
+#[async_trait::async_trait]
+pub trait WebhookRepo: Send + Sync {
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        webhook: &Webhook,
+        secret: &str,
+    ) -> anyhow::Result<()>;
+    /// Oldest first.
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> anyhow::Result<Vec<Webhook>>;
+    /// False if the conversation has no such hook.
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        webhook_id: WebhookId,
+    ) -> anyhow::Result<bool>;
+    async fn targets_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> anyhow::Result<Vec<WebhookTarget>>;
+}
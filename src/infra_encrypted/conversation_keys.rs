@@ -0,0 +1,164 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{AeadCore, Aes256Gcm, Nonce};
+use dashmap::DashMap;
+use std::sync::Arc;
+
+const NONCE_LEN: usize = 12;
+
+/// The per-conversation data keys content is encrypted with, shared by every
+/// decorator that seals content of a conversation.
+///
+/// Data keys are generated on a conversation's first sealed content, stored
+/// wrapped by the `Kms` through `KeyRepo`, and kept unwrapped in memory next
+/// to the stored form they came from. Inside a transaction the stored form is
+/// read through it and the cache only spares the unwrap, as the key may be
+/// this transaction's own and roll back; once read back outside one, the key
+/// is trusted as is.
+pub struct ConversationKeys {
+    keys: Arc<dyn KeyRepo>,
+    kms: Arc<dyn Kms>,
+    ciphers: DashMap<ConversationId, CachedCipher>,
+}
+
+struct CachedCipher {
+    wrapped: Vec<u8>,
+    cipher: Arc<Aes256Gcm>,
+    /// Read back outside any transaction, so it can no longer roll back.
+    committed: bool,
+}
+
+impl ConversationKeys {
+    pub fn new(keys: Arc<dyn KeyRepo>, kms: Arc<dyn Kms>) -> Self {
+        Self {
+            keys,
+            kms,
+            ciphers: DashMap::new(),
+        }
+    }
+
+    /// The conversation's cipher; with `create`, a missing key is generated
+    /// in `tx`. Reads only through `tx`, so a caller holding one never waits
+    /// on a second connection.
+    pub(crate) async fn cipher_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        create: bool,
+    ) -> anyhow::Result<Option<Arc<Aes256Gcm>>> {
+        if let Some(cipher) = self.committed_cipher(conversation_id) {
+            return Ok(Some(cipher));
+        }
+
+        // only `tx` may see this one yet, and it may still roll back
+        let stored = self
+            .keys
+            .get_in_tx(tx, conversation_id)
+            .await
+            .map_err(|e| e.context("load conversation key"))?;
+        let wrapped = match stored {
+            Some(wrapped) => wrapped,
+            None if create => {
+                let data_key = Aes256Gcm::generate_key(OsRng);
+                let wrapped = self
+                    .kms
+                    .wrap(&data_key)
+                    .await
+                    .map_err(|e| e.context("wrap conversation key"))?;
+                self.keys
+                    .insert_in_tx(tx, conversation_id, &wrapped)
+                    .await
+                    .map_err(|e| e.context("store conversation key"))?
+            }
+            None => return Ok(None),
+        };
+
+        self.unwrap_cached(conversation_id, &wrapped, false)
+            .await
+            .map(Some)
+    }
+
+    /// Like `cipher_in_tx` without `create`, outside any transaction. Reads
+    /// only committed keys, so it is the one place that marks them so.
+    pub(crate) async fn cipher(
+        &self,
+        conversation_id: ConversationId,
+    ) -> anyhow::Result<Option<Arc<Aes256Gcm>>> {
+        if let Some(cipher) = self.committed_cipher(conversation_id) {
+            return Ok(Some(cipher));
+        }
+        match self
+            .keys
+            .get(conversation_id)
+            .await
+            .map_err(|e| e.context("load conversation key"))?
+        {
+            Some(wrapped) => self
+                .unwrap_cached(conversation_id, &wrapped, true)
+                .await
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn committed_cipher(&self, conversation_id: ConversationId) -> Option<Arc<Aes256Gcm>> {
+        self.ciphers
+            .get(&conversation_id)
+            .filter(|cached| cached.committed)
+            .map(|cached| cached.cipher.clone())
+    }
+
+    /// Unwraps `wrapped` unless the cache holds the same stored form.
+    async fn unwrap_cached(
+        &self,
+        conversation_id: ConversationId,
+        wrapped: &WrappedKey,
+        committed: bool,
+    ) -> anyhow::Result<Arc<Aes256Gcm>> {
+        if let Some(mut cached) = self.ciphers.get_mut(&conversation_id)
+            && cached.wrapped == wrapped.wrapped
+        {
+            cached.committed |= committed;
+            return Ok(cached.cipher.clone());
+        }
+
+        let data_key = self
+            .kms
+            .unwrap(wrapped)
+            .await
+            .map_err(|e| e.context("unwrap conversation key"))?;
+        let cipher = Aes256Gcm::new_from_slice(&data_key)
+            .map(Arc::new)
+            .map_err(|e| anyhow::anyhow!("conversation key: {e}"))?;
+        self.ciphers.insert(
+            conversation_id,
+            CachedCipher {
+                wrapped: wrapped.wrapped.clone(),
+                cipher: cipher.clone(),
+                committed,
+            },
+        );
+        Ok(cipher)
+    }
+}
+
+/// A fresh nonce followed by `plain` sealed under it, bound to `aad`.
+pub(crate) fn seal(cipher: &Aes256Gcm, plain: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let sealed = cipher
+        .encrypt(&nonce, Payload { msg: plain, aad })
+        .map_err(|e| anyhow::anyhow!("encrypt: {e}"))?;
+    Ok([nonce.as_slice(), &sealed].concat())
+}
+
+/// Reverses `seal`; fails unless `aad` is the one it was sealed with.
+pub(crate) fn open(cipher: &Aes256Gcm, sealed: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        anyhow::bail!("sealed content too short");
+    }
+    let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: sealed, aad })
+        .map_err(|e| anyhow::anyhow!("decrypt: {e}"))
+}
@@ -0,0 +1,373 @@
+use super::conversation_keys::{ConversationKeys, open, seal};
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use crate::logger::Secret;
+use aes_gcm::Aes256Gcm;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use std::sync::Arc;
+
+/// Encrypts `content` with the key of its conversation on the way in and
+/// decrypts it on the way out; callers see plaintext either way.
+///
+/// Stored records say whether their content is sealed, so rows written
+/// before encryption was turned on still read as they are.
+pub struct EncryptedMessageRepo {
+    inner: Arc<dyn MessageRepo>,
+    keys: Arc<ConversationKeys>,
+}
+
+fn store_error(e: anyhow::Error) -> ChatError {
+    ChatError::Store(format!("{e:#}"))
+}
+
+/// Binds the ciphertext to its row, so content can't be moved between
+/// messages or conversations without failing to decrypt.
+fn associated_data(record: &MessageRecord) -> Vec<u8> {
+    [
+        record.conversation_id.0.as_bytes().as_slice(),
+        record.message_id.0.as_bytes().as_slice(),
+    ]
+    .concat()
+}
+
+impl EncryptedMessageRepo {
+    pub fn new(inner: Arc<dyn MessageRepo>, keys: Arc<ConversationKeys>) -> Self {
+        Self { inner, keys }
+    }
+
+    async fn cipher_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        create: bool,
+    ) -> Result<Option<Arc<Aes256Gcm>>, ChatError> {
+        self.keys
+            .cipher_in_tx(tx, conversation_id, create)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn cipher(
+        &self,
+        conversation_id: ConversationId,
+    ) -> Result<Option<Arc<Aes256Gcm>>, ChatError> {
+        self.keys.cipher(conversation_id).await.map_err(store_error)
+    }
+
+    fn encrypt(cipher: &Aes256Gcm, record: &MessageRecord) -> Result<MessageRecord, ChatError> {
+        let sealed = seal(
+            cipher,
+            record.content.expose().as_bytes(),
+            &associated_data(record),
+        )
+        .map_err(|e| store_error(e.context("encrypt message")))?;
+        Ok(MessageRecord {
+            content: Secret::new(STANDARD.encode(sealed)),
+            content_encrypted: true,
+            ..record.clone()
+        })
+    }
+
+    fn decrypt(
+        cipher: Option<&Aes256Gcm>,
+        mut record: MessageRecord,
+    ) -> Result<MessageRecord, ChatError> {
+        if !record.content_encrypted {
+            return Ok(record);
+        }
+        let cipher = cipher.ok_or_else(|| {
+            ChatError::Store("encrypted message without a conversation key".to_string())
+        })?;
+
+        let sealed = STANDARD
+            .decode(record.content.expose())
+            .map_err(|e| ChatError::Store(format!("decode message: {e}")))?;
+        let plain = open(cipher, &sealed, &associated_data(&record))
+            .map_err(|e| store_error(e.context("decrypt message")))?;
+        record.content = String::from_utf8(plain)
+            .map(Secret::new)
+            .map_err(|e| ChatError::Store(format!("decrypted message: {e}")))?;
+        record.content_encrypted = false;
+        Ok(record)
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageRepo for EncryptedMessageRepo {
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        record: &MessageRecord,
+        sender_seq: u64,
+    ) -> Result<SentMessage, ChatError> {
+        let cipher = self
+            .cipher_in_tx(tx, record.conversation_id, true)
+            .await?
+            .ok_or_else(|| ChatError::Store("conversation key not created".to_string()))?;
+
+        let sealed = Self::encrypt(&cipher, record)?;
+        let stored = self.inner.insert_in_tx(tx, &sealed, sender_seq).await?;
+        Ok(SentMessage {
+            record: Self::decrypt(Some(&cipher), stored.record)?,
+            ..stored
+        })
+    }
+
+    async fn get_sent_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        message_id: MessageId,
+    ) -> Result<Option<SentMessage>, ChatError> {
+        let Some(stored) = self.inner.get_sent_in_tx(tx, message_id).await? else {
+            return Ok(None);
+        };
+        let cipher = self
+            .cipher_in_tx(tx, stored.record.conversation_id, false)
+            .await?;
+        Ok(Some(SentMessage {
+            record: Self::decrypt(cipher.as_deref(), stored.record)?,
+            ..stored
+        }))
+    }
+
+    async fn insert_batch_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        records: &[MessageRecord],
+    ) -> Result<u64, ChatError> {
+        let mut sealed = Vec::with_capacity(records.len());
+        for record in records {
+            let cipher = self
+                .cipher_in_tx(tx, record.conversation_id, true)
+                .await?
+                .ok_or_else(|| ChatError::Store("conversation key not created".to_string()))?;
+            sealed.push(Self::encrypt(&cipher, record)?);
+        }
+        self.inner.insert_batch_in_tx(tx, &sealed).await
+    }
+
+    async fn list_before_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        page_size: PageSize,
+        before: Option<OffsetCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let cipher = self.cipher_in_tx(tx, conversation_id, false).await?;
+        self.inner
+            .list_before_in_tx(tx, conversation_id, page_size, before)
+            .await?
+            .into_iter()
+            .map(|record| Self::decrypt(cipher.as_deref(), record))
+            .collect()
+    }
+
+    async fn list_after_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        after: MessageOffset,
+        page_size: PageSize,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let cipher = self.cipher_in_tx(tx, conversation_id, false).await?;
+        self.inner
+            .list_after_in_tx(tx, conversation_id, after, page_size)
+            .await?
+            .into_iter()
+            .map(|record| Self::decrypt(cipher.as_deref(), record))
+            .collect()
+    }
+
+    async fn list_thread_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        root: MessageId,
+        page_size: PageSize,
+        after: Option<OffsetCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let cipher = self.cipher_in_tx(tx, conversation_id, false).await?;
+        self.inner
+            .list_thread_in_tx(tx, conversation_id, root, page_size, after)
+            .await?
+            .into_iter()
+            .map(|record| Self::decrypt(cipher.as_deref(), record))
+            .collect()
+    }
+
+    /// The index only holds ciphertext.
+    async fn search_in_tx<'t>(
+        &self,
+        _tx: &mut dyn StorageTx<'t>,
+        _user_id: UserId,
+        _terms: &[String],
+        _filters: &SearchFilters,
+        _page_size: PageSize,
+        _before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        Err(ChatError::SearchUnavailable)
+    }
+
+    async fn lock_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+    ) -> Result<Option<MessageRecord>, ChatError> {
+        let Some(record) = self
+            .inner
+            .lock_in_tx(tx, conversation_id, message_id)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let cipher = self.cipher_in_tx(tx, conversation_id, false).await?;
+        Self::decrypt(cipher.as_deref(), record).map(Some)
+    }
+
+    async fn update_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        record: &MessageRecord,
+    ) -> Result<(), ChatError> {
+        // a message stored in the clear is sealed when edited
+        let cipher = self
+            .cipher_in_tx(tx, record.conversation_id, true)
+            .await?
+            .ok_or_else(|| ChatError::Store("conversation key not created".to_string()))?;
+
+        let sealed = Self::encrypt(&cipher, record)?;
+        self.inner.update_in_tx(tx, &sealed).await
+    }
+
+    async fn mark_deleted_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+        at: DateTime<Utc>,
+    ) -> Result<(), ChatError> {
+        self.inner
+            .mark_deleted_in_tx(tx, conversation_id, message_offset, at)
+            .await
+    }
+
+    async fn insert_mentions_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        record: &MessageRecord,
+        mentioned: &[UserId],
+    ) -> Result<(), ChatError> {
+        self.inner
+            .insert_mentions_in_tx(tx, record, mentioned)
+            .await
+    }
+
+    /// Each message under the key of its own conversation.
+    async fn list_mentions_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let records = self
+            .inner
+            .list_mentions_in_tx(tx, user_id, page_size, before)
+            .await?;
+        let mut decrypted = Vec::with_capacity(records.len());
+        for record in records {
+            let cipher = self.cipher_in_tx(tx, record.conversation_id, false).await?;
+            decrypted.push(Self::decrypt(cipher.as_deref(), record)?);
+        }
+        Ok(decrypted)
+    }
+
+    async fn pin_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+        pinned_by: UserId,
+        at: DateTime<Utc>,
+    ) -> Result<bool, ChatError> {
+        self.inner
+            .pin_in_tx(tx, conversation_id, message_offset, pinned_by, at)
+            .await
+    }
+
+    async fn unpin_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+    ) -> Result<bool, ChatError> {
+        self.inner
+            .unpin_in_tx(tx, conversation_id, message_offset)
+            .await
+    }
+
+    async fn list_pinned_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<PinnedMessage>, ChatError> {
+        let cipher = self.cipher_in_tx(tx, conversation_id, false).await?;
+        self.inner
+            .list_pinned_in_tx(tx, conversation_id)
+            .await?
+            .into_iter()
+            .map(|pin| {
+                Ok(PinnedMessage {
+                    message: Self::decrypt(cipher.as_deref(), pin.message)?,
+                    ..pin
+                })
+            })
+            .collect()
+    }
+
+    async fn summarize_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<HistorySummary, ChatError> {
+        self.inner.summarize_in_tx(tx, conversation_id).await
+    }
+
+    async fn offset_at_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        at: DateTime<Utc>,
+    ) -> Result<Option<MessageOffset>, ChatError> {
+        self.inner.offset_at_in_tx(tx, conversation_id, at).await
+    }
+
+    async fn prune_expired(
+        &self,
+        policy: &RetentionPolicy,
+        limit: u32,
+    ) -> Result<PrunedMessages, ChatError> {
+        self.inner.prune_expired(policy, limit).await
+    }
+
+    async fn count_by_sender(&self, sender: UserId) -> Result<u64, ChatError> {
+        self.inner.count_by_sender(sender).await
+    }
+
+    fn stream_by_sender(&self, sender: UserId) -> BoxStream<'_, Result<MessageRecord, ChatError>> {
+        self.inner
+            .stream_by_sender(sender)
+            .then(move |record| async move {
+                let record = record?;
+                let cipher = self.cipher(record.conversation_id).await?;
+                Self::decrypt(cipher.as_deref(), record)
+            })
+            .boxed()
+    }
+}
@@ -0,0 +1,11 @@
+//! The `infra_encrypted` module wraps repo ports in decorators that keep
+//! message content encrypted at rest, whatever backend stores it. Enabled with
+//! `storage.encryption.enabled`.
+
+mod conversation_keys;
+mod message_repo_encrypted;
+mod outbox_repo_encrypted;
+
+pub use conversation_keys::ConversationKeys;
+pub use message_repo_encrypted::*;
+pub use outbox_repo_encrypted::*;
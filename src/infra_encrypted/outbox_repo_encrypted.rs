@@ -0,0 +1,160 @@
+use super::conversation_keys::{ConversationKeys, open, seal};
+use crate::domain_model::*;
+use crate::domain_port::*;
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::sync::Arc;
+
+/// The one field of a sealed payload. Event payloads are tagged with their
+/// `type`, so no plain one has it.
+const SEALED: &str = "sealed";
+
+/// Seals the payloads of events that carry message content with the key of
+/// their conversation, so the outbox doesn't keep in the clear what
+/// `EncryptedMessageRepo` keeps sealed. Claimed events come back opened, so
+/// the notifier publishes them as they were enqueued, replays included.
+pub struct EncryptedOutboxRepo {
+    inner: Arc<dyn OutboxRepo>,
+    keys: Arc<ConversationKeys>,
+}
+
+/// Binds the ciphertext to its event.
+fn associated_data(event_id: EventId, conversation_id: ConversationId) -> Vec<u8> {
+    [
+        event_id.0.as_bytes().as_slice(),
+        conversation_id.0.as_bytes().as_slice(),
+    ]
+    .concat()
+}
+
+impl EncryptedOutboxRepo {
+    pub fn new(inner: Arc<dyn OutboxRepo>, keys: Arc<ConversationKeys>) -> Self {
+        Self { inner, keys }
+    }
+
+    /// The conversation whose key seals the event, if it is sealed at all.
+    fn sealed_under(event: &OutboxEvent) -> Option<ConversationId> {
+        match event.event_type {
+            EventType::ChatMessageNew | EventType::ChatMessageEdited => {
+                event.partition_key.map(ConversationId)
+            }
+            _ => None,
+        }
+    }
+
+    async fn open_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        mut event: OutboxEvent,
+    ) -> anyhow::Result<OutboxEvent> {
+        let Some(encoded) = event.payload_json.get(SEALED).and_then(|v| v.as_str()) else {
+            return Ok(event);
+        };
+        let conversation_id = event.partition_key.map(ConversationId).with_context(|| {
+            format!("sealed event [{}] without a conversation", event.event_id.0)
+        })?;
+        let cipher = self
+            .keys
+            .cipher_in_tx(tx, conversation_id, false)
+            .await?
+            .with_context(|| {
+                format!(
+                    "sealed event [{}] without a conversation key",
+                    event.event_id.0
+                )
+            })?;
+
+        let sealed = STANDARD.decode(encoded).context("decode event payload")?;
+        let plain = open(
+            &cipher,
+            &sealed,
+            &associated_data(event.event_id, conversation_id),
+        )
+        .context("open event payload")?;
+        event.payload_json = serde_json::from_slice(&plain).context("opened event payload")?;
+        Ok(event)
+    }
+}
+
+#[async_trait::async_trait]
+impl OutboxRepo for EncryptedOutboxRepo {
+    async fn enqueue_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        event: &OutboxEvent,
+    ) -> anyhow::Result<()> {
+        let Some(conversation_id) = Self::sealed_under(event) else {
+            return self.inner.enqueue_in_tx(tx, event).await;
+        };
+        let cipher = self
+            .keys
+            .cipher_in_tx(tx, conversation_id, true)
+            .await?
+            .context("conversation key not created")?;
+
+        let sealed = seal(
+            &cipher,
+            &serde_json::to_vec(&event.payload_json)?,
+            &associated_data(event.event_id, conversation_id),
+        )
+        .context("seal event payload")?;
+        let sealed = OutboxEvent {
+            payload_json: json!({ SEALED: STANDARD.encode(sealed) }),
+            ..event.clone()
+        };
+        self.inner.enqueue_in_tx(tx, &sealed).await
+    }
+
+    async fn claim_ready_batch_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<OutboxEvent>> {
+        let events = self.inner.claim_ready_batch_in_tx(tx, now, limit).await?;
+        let mut opened = Vec::with_capacity(events.len());
+        for event in events {
+            opened.push(self.open_in_tx(tx, event).await?);
+        }
+        Ok(opened)
+    }
+
+    async fn mark_delivered_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        event_id: EventId,
+        delivered_at: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        self.inner
+            .mark_delivered_in_tx(tx, event_id, delivered_at)
+            .await
+    }
+
+    async fn reschedule_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        event_id: EventId,
+        next_attempt_at: DateTime<Utc>,
+        last_error: &str,
+    ) -> anyhow::Result<()> {
+        self.inner
+            .reschedule_in_tx(tx, event_id, next_attempt_at, last_error)
+            .await
+    }
+
+    async fn requeue_delivered_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        selection: &ReplaySelection,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<u64> {
+        self.inner.requeue_delivered_in_tx(tx, selection, now).await
+    }
+
+    async fn count_undelivered(&self) -> anyhow::Result<u64> {
+        self.inner.count_undelivered().await
+    }
+}
@@ -0,0 +1,169 @@
+//! The `infra_flaky` module wraps any repo or store port, and the event
+//! publisher, in a decorator that delays every call and fails a share of them.
+//! Enabled with `chaos.enabled`; one seed drives every decision, so a run can
+//! be repeated to exercise the same retry, backoff and outbox paths again.
+//! See `repos.rs` for the covered ports.
+
+mod publisher;
+mod repos;
+
+pub use publisher::*;
+
+use crate::application_port::*;
+use crate::domain_port::CaptchaStoreError;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct FaultConfig {
+    /// Added to every call.
+    pub latency: Duration,
+    /// Up to this much more, drawn per call.
+    pub jitter: Duration,
+    /// Share of calls, from 0.0 to 1.0, that fail after the delay.
+    pub error_rate: f64,
+    pub seed: u64,
+}
+
+/// The fault source shared by every wrapped port.
+pub struct Faults {
+    config: FaultConfig,
+    rng: Mutex<u64>,
+}
+
+impl Faults {
+    pub fn new(config: FaultConfig) -> Self {
+        Self {
+            rng: Mutex::new(config.seed),
+            config,
+        }
+    }
+
+    /// splitmix64, mapped to `[0, 1)`.
+    fn next_unit(&self) -> f64 {
+        let mut state = self.rng.lock().unwrap_or_else(PoisonError::into_inner);
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Sleeps, then decides whether the call fails; `Some` is the message
+    /// for the error to return instead of calling through.
+    async fn inject(&self, port: &'static str, method: &'static str) -> Option<String> {
+        let jitter = self.next_unit();
+        let roll = self.next_unit();
+
+        let delay = self.config.latency + self.config.jitter.mul_f64(jitter);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        if roll >= self.config.error_rate {
+            return None;
+        }
+        tracing::debug!(port, method, "injected fault");
+        Some(format!("injected fault in {port}::{method}"))
+    }
+}
+
+pub struct FlakyRepo<T: ?Sized> {
+    inner: Arc<T>,
+    faults: Arc<Faults>,
+}
+
+/// Implemented for every port `repos.rs` covers.
+pub trait Flaky {
+    fn flaky(self: Arc<Self>, faults: Arc<Faults>) -> Arc<Self>;
+}
+
+/// Wraps `repo` when there are faults to inject, otherwise hands it back untouched.
+pub fn flaky<T: Flaky + ?Sized>(faults: Option<&Arc<Faults>>, repo: Arc<T>) -> Arc<T> {
+    match faults {
+        Some(faults) => repo.flaky(faults.clone()),
+        None => repo,
+    }
+}
+
+/// The error a port returns for an injected fault; always the store variant,
+/// as if the backend had failed.
+pub trait InjectedError {
+    fn injected(message: String) -> Self;
+}
+
+macro_rules! store_injected_error {
+    ($($error:ident),* $(,)?) => {
+        $(
+            impl InjectedError for $error {
+                fn injected(message: String) -> Self {
+                    $error::Store(message)
+                }
+            }
+        )*
+    };
+}
+
+store_injected_error!(
+    AuthError,
+    RelationError,
+    ChatError,
+    CaptchaStoreError,
+    ImportError,
+    StatsError
+);
+
+impl InjectedError for anyhow::Error {
+    fn injected(message: String) -> Self {
+        anyhow::anyhow!(message)
+    }
+}
+
+/// Implements `$port` for `FlakyRepo<dyn $port>` by forwarding each listed
+/// method behind `Faults::inject`. The method list must mirror the trait; the
+/// compiler enforces it.
+///
+/// Plain `fn`s, listed after the `async` ones, can't be delayed and are
+/// forwarded as they are.
+macro_rules! flaky_port {
+    ($port:ident {
+        $(
+            async fn $method:ident $(<$lt:lifetime>)? (&self $(, $arg:ident : $ty:ty)* $(,)?) -> $ret:ty;
+        )*
+        $(
+            fn $plain:ident (&self $(, $plain_arg:ident : $plain_ty:ty)* $(,)?) -> $plain_ret:ty;
+        )*
+    }) => {
+        impl $crate::infra_flaky::Flaky for dyn $port {
+            fn flaky(
+                self: ::std::sync::Arc<Self>,
+                faults: ::std::sync::Arc<$crate::infra_flaky::Faults>,
+            ) -> ::std::sync::Arc<Self> {
+                ::std::sync::Arc::new($crate::infra_flaky::FlakyRepo { inner: self, faults })
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $port for $crate::infra_flaky::FlakyRepo<dyn $port> {
+            $(
+                async fn $method $(<$lt>)? (&self $(, $arg: $ty)*) -> $ret {
+                    if let Some(message) = self
+                        .faults
+                        .inject(stringify!($port), stringify!($method))
+                        .await
+                    {
+                        return Err($crate::infra_flaky::InjectedError::injected(message));
+                    }
+                    self.inner.$method($($arg),*).await
+                }
+            )*
+            $(
+                fn $plain(&self $(, $plain_arg: $plain_ty)*) -> $plain_ret {
+                    self.inner.$plain($($plain_arg),*)
+                }
+            )*
+        }
+    };
+}
+
+use flaky_port;
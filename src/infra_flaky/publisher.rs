@@ -0,0 +1,27 @@
+use crate::infra_flaky::Faults;
+use crate::server::EventPublisher;
+use std::sync::Arc;
+
+/// `EventPublisher` behind `Faults`. A failed publish never reaches the
+/// broker, so the notifier reschedules the outbox row as it would for a
+/// Kafka error.
+pub struct FlakyPublisher {
+    inner: Arc<dyn EventPublisher>,
+    faults: Arc<Faults>,
+}
+
+impl FlakyPublisher {
+    pub fn new(inner: Arc<dyn EventPublisher>, faults: Arc<Faults>) -> Self {
+        Self { inner, faults }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventPublisher for FlakyPublisher {
+    async fn publish(&self, topic: &str, key: &[u8], payload: &[u8]) -> anyhow::Result<()> {
+        if let Some(message) = self.faults.inject("EventPublisher", "publish").await {
+            return Err(anyhow::anyhow!(message));
+        }
+        self.inner.publish(topic, key, payload).await
+    }
+}
@@ -0,0 +1,3 @@
+mod webhook_client_hyper;
+
+pub use webhook_client_hyper::*;
@@ -0,0 +1,110 @@
+use crate::application_port::is_public_address;
+use crate::domain_port::*;
+use anyhow::Context;
+use futures_util::future::BoxFuture;
+use hyper::client::HttpConnector;
+use hyper::client::connect::dns::Name;
+use hyper::service::Service;
+use hyper::{Body, Client, Method, Request, Uri};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use std::net::{IpAddr, SocketAddr};
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+
+/// Posts over one shared connection pool, trusting the platform's root
+/// certificates.
+///
+/// Unless `allow_private` is set, every new connection resolves its host
+/// afresh and only goes to addresses `is_public_address` accepts, so a name
+/// that starts pointing inside the network, DNS rebinding included, is
+/// refused on the attempt that would have reached it. Pooled connections
+/// were made that way too.
+pub struct HyperWebhookClient {
+    client: Client<HttpsConnector<HttpConnector<PublicResolver>>, Body>,
+    timeout: Duration,
+    allow_private: bool,
+}
+
+impl HyperWebhookClient {
+    /// `timeout` covers the whole exchange, connecting included.
+    pub fn new(timeout: Duration, allow_private: bool) -> Self {
+        let mut http = HttpConnector::new_with_resolver(PublicResolver { allow_private });
+        http.enforce_http(false);
+        let connector = HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .https_or_http()
+            .enable_http1()
+            .wrap_connector(http);
+        Self {
+            client: Client::builder().build(connector),
+            timeout,
+            allow_private,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookClient for HyperWebhookClient {
+    async fn post(&self, request: &WebhookRequest) -> anyhow::Result<u16> {
+        let uri: Uri = request.url.parse()?;
+        // the connector skips the resolver for hosts that are addresses already
+        if !self.allow_private
+            && let Some(ip) = uri
+                .host()
+                .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+                .and_then(|host| host.parse::<IpAddr>().ok())
+            && !is_public_address(ip)
+        {
+            anyhow::bail!("webhook address {ip} is not public");
+        }
+
+        let mut builder = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .header(hyper::header::USER_AGENT, "counterpoint-webhooks");
+        for (name, value) in &request.headers {
+            builder = builder.header(*name, value);
+        }
+        let http_request = builder.body(Body::from(request.body.clone()))?;
+
+        let response = tokio::time::timeout(self.timeout, self.client.request(http_request))
+            .await
+            .context("webhook timed out")??;
+        Ok(response.status().as_u16())
+    }
+}
+
+/// Resolves with the system resolver and drops the addresses webhooks may
+/// not reach; a name left with none fails to connect.
+#[derive(Clone)]
+pub struct PublicResolver {
+    allow_private: bool,
+}
+
+impl Service<Name> for PublicResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = std::io::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let allow_private = self.allow_private;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| allow_private || is_public_address(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    format!("{name} has no public address"),
+                ));
+            }
+            Ok(addrs.into_iter())
+        })
+    }
+}
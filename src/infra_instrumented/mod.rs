@@ -0,0 +1,138 @@
+//! The `infra_instrumented` module wraps any repo or store port in a decorator
+//! that times every call, logs failures, and feeds `repo_call_seconds`.
+//! Enabled with `log.instrument_repos`; see `repos.rs` for the covered ports.
+
+mod repos;
+
+use crate::application_port::*;
+use crate::domain_port::CaptchaStoreError;
+use crate::metrics;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub struct Instrumented<T: ?Sized> {
+    inner: Arc<T>,
+}
+
+/// Implemented for every port `repos.rs` instruments.
+pub trait Instrument {
+    fn instrument(self: Arc<Self>) -> Arc<Self>;
+}
+
+/// Wraps `repo` when `enabled`, otherwise hands it back untouched.
+pub fn instrument<T: Instrument + ?Sized>(enabled: bool, repo: Arc<T>) -> Arc<T> {
+    if enabled { repo.instrument() } else { repo }
+}
+
+/// A short, low-cardinality name for an error, e.g. `Store` or `NotFound`.
+pub trait ErrorClass {
+    fn class(&self) -> String;
+}
+
+macro_rules! variant_error_class {
+    ($($error:ty),* $(,)?) => {
+        $(
+            impl ErrorClass for $error {
+                fn class(&self) -> String {
+                    variant_name(self)
+                }
+            }
+        )*
+    };
+}
+
+variant_error_class!(
+    AuthError,
+    RelationError,
+    ChatError,
+    CaptchaStoreError,
+    ImportError,
+    StatsError
+);
+
+impl ErrorClass for anyhow::Error {
+    fn class(&self) -> String {
+        match self.downcast_ref::<sqlx::Error>() {
+            Some(e) => format!("sqlx::{}", variant_name(e)),
+            None => "other".to_owned(),
+        }
+    }
+}
+
+fn variant_name<E: Debug>(error: &E) -> String {
+    let debug = format!("{error:?}");
+    debug
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
+
+fn observe<T, E: ErrorClass>(
+    repo: &'static str,
+    method: &'static str,
+    started: Instant,
+    result: &Result<T, E>,
+) {
+    let elapsed = started.elapsed();
+    metrics::REPO_CALL_SECONDS
+        .with_label_values(&[repo, method, metrics::outcome(result)])
+        .observe(elapsed.as_secs_f64());
+    match result {
+        Ok(_) => tracing::trace!(repo, method, elapsed_us = elapsed.as_micros() as u64, "ok"),
+        Err(e) => tracing::debug!(
+            repo,
+            method,
+            elapsed_us = elapsed.as_micros() as u64,
+            error_class = e.class(),
+            "failed"
+        ),
+    }
+}
+
+/// Implements `$port` for `Instrumented<dyn $port>` by forwarding each listed
+/// method. The method list must mirror the trait; the compiler enforces it.
+///
+/// Plain `fn`s, listed after the `async` ones, return streams that do their
+/// work after the call returns, so they are forwarded untimed.
+macro_rules! instrument_port {
+    ($port:ident {
+        $(
+            async fn $method:ident $(<$lt:lifetime>)? (&self $(, $arg:ident : $ty:ty)* $(,)?) -> $ret:ty;
+        )*
+        $(
+            fn $plain:ident (&self $(, $plain_arg:ident : $plain_ty:ty)* $(,)?) -> $plain_ret:ty;
+        )*
+    }) => {
+        impl $crate::infra_instrumented::Instrument for dyn $port {
+            fn instrument(self: ::std::sync::Arc<Self>) -> ::std::sync::Arc<Self> {
+                ::std::sync::Arc::new($crate::infra_instrumented::Instrumented { inner: self })
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $port for $crate::infra_instrumented::Instrumented<dyn $port> {
+            $(
+                async fn $method $(<$lt>)? (&self $(, $arg: $ty)*) -> $ret {
+                    let started = ::std::time::Instant::now();
+                    let result = self.inner.$method($($arg),*).await;
+                    $crate::infra_instrumented::observe(
+                        stringify!($port),
+                        stringify!($method),
+                        started,
+                        &result,
+                    );
+                    result
+                }
+            )*
+            $(
+                fn $plain(&self $(, $plain_arg: $plain_ty)*) -> $plain_ret {
+                    self.inner.$plain($($plain_arg),*)
+                }
+            )*
+        }
+    };
+}
+
+use instrument_port;
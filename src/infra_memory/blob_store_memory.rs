@@ -0,0 +1,104 @@
+use crate::domain_port::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// `BlobStore` for a single node with no object store behind it; everything
+/// is lost on restart.
+#[derive(Default)]
+pub struct MemoryBlobStore {
+    blobs: Arc<Mutex<HashMap<String, Blob>>>,
+}
+
+impl MemoryBlobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobStore for MemoryBlobStore {
+    async fn put(&self, key: &str, content_type: &str, content: &[u8]) -> anyhow::Result<()> {
+        let blob = Blob {
+            content_type: content_type.to_owned(),
+            content: content.to_vec(),
+        };
+        self.blobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.to_owned(), blob);
+        Ok(())
+    }
+
+    async fn start_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+    ) -> anyhow::Result<Box<dyn BlobUpload>> {
+        Ok(Box::new(MemoryUpload {
+            blobs: self.blobs.clone(),
+            key: key.to_owned(),
+            blob: Blob {
+                content_type: content_type.to_owned(),
+                content: Vec::new(),
+            },
+        }))
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Blob>> {
+        Ok(self
+            .blobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(key)
+            .cloned())
+    }
+
+    async fn get_range(&self, key: &str, offset: u64, len: u64) -> anyhow::Result<Option<Vec<u8>>> {
+        let blobs = self.blobs.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(blobs.get(key).map(|blob| {
+            let stored = blob.content.len() as u64;
+            let start = offset.min(stored) as usize;
+            let end = offset.saturating_add(len).min(stored) as usize;
+            blob.content[start..end].to_vec()
+        }))
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        self.blobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key);
+        Ok(())
+    }
+}
+
+/// Holds everything written until it is finished.
+struct MemoryUpload {
+    blobs: Arc<Mutex<HashMap<String, Blob>>>,
+    key: String,
+    blob: Blob,
+}
+
+#[async_trait::async_trait]
+impl BlobUpload for MemoryUpload {
+    async fn write(&mut self, content: &[u8]) -> anyhow::Result<()> {
+        self.blob.content.extend_from_slice(content);
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> anyhow::Result<()> {
+        let blob = Blob {
+            content_type: self.blob.content_type.clone(),
+            content: std::mem::take(&mut self.blob.content),
+        };
+        self.blobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(self.key.clone(), blob);
+        Ok(())
+    }
+
+    async fn abort(&mut self) {
+        self.blob.content = Vec::new();
+    }
+}
@@ -0,0 +1,51 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+
+/// Expired entries are only swept once the map grows past this.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// `CommandDedupeStore` for a single node with no Redis behind it.
+#[derive(Default)]
+pub struct MemoryCommandDedupeStore {
+    acks: DashMap<(UserId, MessageId), (String, Instant)>,
+}
+
+impl MemoryCommandDedupeStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandDedupeStore for MemoryCommandDedupeStore {
+    async fn get_ack(
+        &self,
+        user_id: UserId,
+        message_id: MessageId,
+    ) -> anyhow::Result<Option<ChatMessageACK>> {
+        match self.acks.get(&(user_id, message_id)) {
+            Some(entry) if entry.1 > Instant::now() => Ok(Some(serde_json::from_str(&entry.0)?)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn save_ack(
+        &self,
+        user_id: UserId,
+        ack: &ChatMessageACK,
+        ttl_secs: u64,
+    ) -> anyhow::Result<()> {
+        if self.acks.len() > SWEEP_THRESHOLD {
+            let now = Instant::now();
+            self.acks.retain(|_, (_, expires_at)| *expires_at > now);
+        }
+
+        let json = serde_json::to_string(ack)?;
+        let expires_at = Instant::now() + Duration::from_secs(ttl_secs);
+        self.acks
+            .insert((user_id, ack.message_id), (json, expires_at));
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use crate::domain_port::*;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{AeadCore, Aes256Gcm, Nonce};
+use anyhow::{Context, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+const NONCE_LEN: usize = 12;
+
+/// `Kms` holding the master key in process, for deployments without a
+/// managed KMS. Rotating the master key needs a new `key_id`; keys wrapped
+/// under the old one stop unwrapping.
+pub struct LocalKms {
+    key_id: String,
+    cipher: Aes256Gcm,
+}
+
+impl LocalKms {
+    pub fn new(key_id: String, master_key: &[u8]) -> anyhow::Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(master_key)
+            .map_err(|_| anyhow!("master key must be 32 bytes"))?;
+        Ok(Self { key_id, cipher })
+    }
+
+    /// The master key from `var`, base64; its key id is the variable's name.
+    pub fn from_env(var: &str) -> anyhow::Result<Self> {
+        let encoded = std::env::var(var).with_context(|| format!("{var} is not set"))?;
+        let master_key = STANDARD
+            .decode(encoded.trim())
+            .with_context(|| format!("{var} is not base64"))?;
+        Self::new(var.to_string(), &master_key)
+    }
+}
+
+#[async_trait::async_trait]
+impl Kms for LocalKms {
+    fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    async fn wrap(&self, data_key: &[u8]) -> anyhow::Result<WrappedKey> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let sealed = self
+            .cipher
+            .encrypt(&nonce, data_key)
+            .map_err(|e| anyhow!("wrap data key: {e}"))?;
+        Ok(WrappedKey {
+            key_id: self.key_id.clone(),
+            wrapped: [nonce.as_slice(), &sealed].concat(),
+        })
+    }
+
+    async fn unwrap(&self, key: &WrappedKey) -> anyhow::Result<Vec<u8>> {
+        if key.key_id != self.key_id {
+            bail!(
+                "data key wrapped by [{}], not [{}]",
+                key.key_id,
+                self.key_id
+            );
+        }
+        if key.wrapped.len() < NONCE_LEN {
+            bail!("wrapped data key too short");
+        }
+        let (nonce, sealed) = key.wrapped.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|e| anyhow!("unwrap data key: {e}"))
+    }
+}
@@ -0,0 +1,11 @@
+mod blob_store_memory;
+mod command_dedupe_store_memory;
+mod kms_local;
+mod push_provider_log;
+mod rate_limiter_memory;
+
+pub use blob_store_memory::*;
+pub use command_dedupe_store_memory::*;
+pub use kms_local::*;
+pub use push_provider_log::*;
+pub use rate_limiter_memory::*;
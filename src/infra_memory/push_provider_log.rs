@@ -0,0 +1,40 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+
+/// `PushProvider` for development: logs each push instead of sending it.
+/// Tokens APNs would turn down as malformed, anything but hex digits, come
+/// back as `InvalidToken`, so pruning can be tried without a push service.
+#[derive(Default)]
+pub struct LogPushProvider;
+
+impl LogPushProvider {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl PushProvider for LogPushProvider {
+    async fn send(
+        &self,
+        device: &Device,
+        notification: &PushNotification,
+    ) -> anyhow::Result<PushOutcome> {
+        if device.platform == PushPlatform::Apns
+            && !device.token.bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            return Ok(PushOutcome::InvalidToken);
+        }
+        tracing::info!(
+            user_id = %device.user_id,
+            platform = device.platform.as_str(),
+            locale = %device.locale,
+            conversation_id = %notification.conversation_id,
+            message_id = %notification.message_id.0,
+            messages = notification.message_count,
+            conversations = notification.conversation_count,
+            "push"
+        );
+        Ok(PushOutcome::Delivered)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::domain_port::*;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Counters from windows that ended are only swept once the map grows past
+/// this.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// `RateLimiter` for a single node with no Redis behind it.
+#[derive(Default)]
+pub struct MemoryRateLimiter {
+    /// (subject, period in seconds) to (window index, uses).
+    counters: Mutex<HashMap<(String, u64), (i64, u32)>>,
+}
+
+impl MemoryRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimiter for MemoryRateLimiter {
+    async fn acquire(&self, subject: &str, windows: &[RateWindow]) -> anyhow::Result<RateDecision> {
+        let now = Utc::now();
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        if counters.len() > SWEEP_THRESHOLD {
+            counters.retain(|(_, period), (index, _)| {
+                now.timestamp().div_euclid((*period).max(1) as i64) == *index
+            });
+        }
+
+        let used = |window: &RateWindow| {
+            let (index, _) = window.bucket(now);
+            match counters.get(&(subject.to_owned(), window.period.as_secs())) {
+                Some((at, uses)) if *at == index => *uses,
+                _ => 0,
+            }
+        };
+        if let Some(full) = windows.iter().rev().find(|w| used(w) >= w.limit) {
+            return Ok(RateDecision::Limited {
+                reset_at: full.bucket(now).1,
+            });
+        }
+
+        for window in windows {
+            let (index, _) = window.bucket(now);
+            let entry = counters
+                .entry((subject.to_owned(), window.period.as_secs()))
+                .or_insert((index, 0));
+            if entry.0 != index {
+                *entry = (index, 0);
+            }
+            entry.1 += 1;
+        }
+        Ok(RateDecision::Allowed)
+    }
+
+    async fn release(&self, subject: &str, windows: &[RateWindow]) -> anyhow::Result<()> {
+        let now = Utc::now();
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        for window in windows {
+            let (index, _) = window.bucket(now);
+            if let Some((at, uses)) =
+                counters.get_mut(&(subject.to_owned(), window.period.as_secs()))
+                && *at == index
+            {
+                *uses = uses.saturating_sub(1);
+            }
+        }
+        Ok(())
+    }
+}
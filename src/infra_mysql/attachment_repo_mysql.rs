@@ -0,0 +1,287 @@
+use super::util::{downcast, placeholders};
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+use sqlx::MySqlConnection;
+use std::collections::HashMap;
+
+#[derive(sqlx::FromRow)]
+struct AttachmentRow {
+    attachment_id: AttachmentId,
+    conversation_id: ConversationId,
+    uploader_id: UserId,
+    mime: String,
+    size: u64,
+    created_at: DateTime<Utc>,
+    message_id: Option<MessageId>,
+}
+
+impl From<AttachmentRow> for AttachmentRecord {
+    fn from(r: AttachmentRow) -> Self {
+        AttachmentRecord {
+            attachment_id: r.attachment_id,
+            conversation_id: r.conversation_id,
+            uploader: r.uploader_id,
+            mime: r.mime,
+            size: r.size,
+            created_at: r.created_at,
+            message_id: r.message_id,
+        }
+    }
+}
+
+/// What each of `message_ids` went out with, oldest upload first; for
+/// `MySqlMessageRepo`, which hands out messages with their attachments.
+pub(super) async fn attachments_of(
+    conn: &mut MySqlConnection,
+    message_ids: &[MessageId],
+) -> sqlx::Result<HashMap<MessageId, Vec<Attachment>>> {
+    let mut by_message: HashMap<MessageId, Vec<Attachment>> = HashMap::new();
+    if message_ids.is_empty() {
+        return Ok(by_message);
+    }
+
+    let sql = format!(
+        r#"
+SELECT attachment_id, conversation_id, uploader_id, mime, size, created_at, message_id
+FROM attachment
+WHERE message_id IN ({})
+ORDER BY created_at, attachment_id
+"#,
+        placeholders(message_ids.len())
+    );
+    let mut query = sqlx::query_as::<_, AttachmentRow>(&sql);
+    for message_id in message_ids {
+        query = query.bind(*message_id);
+    }
+    for row in query.fetch_all(conn).await? {
+        let record = AttachmentRecord::from(row);
+        if let Some(message_id) = record.message_id {
+            by_message
+                .entry(message_id)
+                .or_default()
+                .push(record.attachment());
+        }
+    }
+    Ok(by_message)
+}
+
+/// Deletes what each of `message_ids` went out with and returns it, for
+/// `MySqlMessageRepo` to prune attachments with their messages.
+pub(super) async fn delete_attachments_of(
+    conn: &mut MySqlConnection,
+    message_ids: &[MessageId],
+) -> sqlx::Result<Vec<AttachmentRecord>> {
+    if message_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sql = format!(
+        r#"
+SELECT attachment_id, conversation_id, uploader_id, mime, size, created_at, message_id
+FROM attachment
+WHERE message_id IN ({})
+FOR UPDATE
+"#,
+        placeholders(message_ids.len())
+    );
+    let mut query = sqlx::query_as::<_, AttachmentRow>(&sql);
+    for message_id in message_ids {
+        query = query.bind(*message_id);
+    }
+    let rows = query.fetch_all(&mut *conn).await?;
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sql = format!(
+        "DELETE FROM attachment WHERE message_id IN ({})",
+        placeholders(message_ids.len())
+    );
+    let mut query = sqlx::query(&sql);
+    for message_id in message_ids {
+        query = query.bind(*message_id);
+    }
+    query.execute(&mut *conn).await?;
+
+    Ok(rows.into_iter().map(AttachmentRecord::from).collect())
+}
+
+/// Every call runs in the caller's transaction, so there is no pool.
+#[derive(Default)]
+pub struct MySqlAttachmentRepo;
+
+impl MySqlAttachmentRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl AttachmentRepo for MySqlAttachmentRepo {
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        record: &AttachmentRecord,
+    ) -> anyhow::Result<()> {
+        let tx = downcast(tx);
+
+        sqlx::query(
+            r#"
+INSERT INTO attachment (attachment_id, conversation_id, uploader_id, mime, size, created_at)
+VALUES (?, ?, ?, ?, ?, ?)
+"#,
+        )
+        .bind(record.attachment_id)
+        .bind(record.conversation_id)
+        .bind(record.uploader)
+        .bind(&record.mime)
+        .bind(record.size)
+        .bind(record.created_at)
+        .execute(tx.conn())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        attachment_id: AttachmentId,
+        viewer: UserId,
+    ) -> anyhow::Result<Option<AttachmentRecord>> {
+        let tx = downcast(tx);
+
+        // a deleted message takes its attachments with it, as far as
+        // members can tell; an unsent upload is its uploader's alone
+        let row: Option<AttachmentRow> = sqlx::query_as(
+            r#"
+SELECT a.attachment_id, a.conversation_id, a.uploader_id, a.mime, a.size, a.created_at,
+       a.message_id
+FROM attachment AS a
+WHERE a.attachment_id = ? AND a.conversation_id = ?
+  AND (a.message_id IS NOT NULL OR a.uploader_id = ?)
+  AND NOT EXISTS (SELECT 1
+                  FROM message AS m
+                  WHERE m.message_id = a.message_id AND m.deleted_at IS NOT NULL)
+"#,
+        )
+        .bind(attachment_id)
+        .bind(conversation_id)
+        .bind(viewer)
+        .fetch_optional(tx.conn())
+        .await?;
+
+        Ok(row.map(AttachmentRecord::from))
+    }
+
+    async fn attach_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        uploader: UserId,
+        message_id: MessageId,
+        attachment_ids: &[AttachmentId],
+    ) -> anyhow::Result<Option<Vec<AttachmentRecord>>> {
+        if attachment_ids.is_empty() {
+            return Ok(Some(Vec::new()));
+        }
+        let tx = downcast(tx);
+
+        let sql = format!(
+            r#"
+SELECT attachment_id, conversation_id, uploader_id, mime, size, created_at, message_id
+FROM attachment
+WHERE attachment_id IN ({})
+ORDER BY created_at, attachment_id
+FOR UPDATE
+"#,
+            placeholders(attachment_ids.len())
+        );
+        let mut query = sqlx::query_as::<_, AttachmentRow>(&sql);
+        for attachment_id in attachment_ids {
+            query = query.bind(*attachment_id);
+        }
+        let records: Vec<AttachmentRecord> = query
+            .fetch_all(tx.conn())
+            .await?
+            .into_iter()
+            .map(AttachmentRecord::from)
+            .collect();
+
+        // a retried send finds them tied to its own message already
+        let usable = records.len() == attachment_ids.len()
+            && records.iter().all(|record| {
+                record.conversation_id == conversation_id
+                    && record.uploader == uploader
+                    && record.message_id.is_none_or(|id| id == message_id)
+            });
+        if !usable {
+            return Ok(None);
+        }
+
+        let sql = format!(
+            "UPDATE attachment SET message_id = ? WHERE attachment_id IN ({})",
+            placeholders(attachment_ids.len())
+        );
+        let mut query = sqlx::query(&sql).bind(message_id);
+        for attachment_id in attachment_ids {
+            query = query.bind(*attachment_id);
+        }
+        query.execute(tx.conn()).await?;
+
+        Ok(Some(
+            records
+                .into_iter()
+                .map(|record| AttachmentRecord {
+                    message_id: Some(message_id),
+                    ..record
+                })
+                .collect(),
+        ))
+    }
+
+    async fn delete_unattached_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        before: DateTime<Utc>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<AttachmentRecord>> {
+        let tx = downcast(tx);
+
+        // locked, so an upload being attached right now either goes out
+        // first or finds itself gone
+        let records: Vec<AttachmentRecord> = sqlx::query_as::<_, AttachmentRow>(
+            r#"
+SELECT attachment_id, conversation_id, uploader_id, mime, size, created_at, message_id
+FROM attachment
+WHERE message_id IS NULL AND created_at < ?
+LIMIT ?
+FOR UPDATE
+"#,
+        )
+        .bind(before)
+        .bind(limit)
+        .fetch_all(tx.conn())
+        .await?
+        .into_iter()
+        .map(AttachmentRecord::from)
+        .collect();
+        if records.is_empty() {
+            return Ok(records);
+        }
+
+        let sql = format!(
+            "DELETE FROM attachment WHERE attachment_id IN ({})",
+            placeholders(records.len())
+        );
+        let mut query = sqlx::query(&sql);
+        for record in &records {
+            query = query.bind(record.attachment_id);
+        }
+        query.execute(tx.conn()).await?;
+
+        Ok(records)
+    }
+}
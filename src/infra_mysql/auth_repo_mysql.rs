@@ -103,4 +103,20 @@ WHERE username = ?
 
         row_opt.map(Self::row_to_record).transpose()
     }
+
+    async fn deactivate_credentials_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<(), AuthError> {
+        let tx = downcast(tx);
+
+        sqlx::query("UPDATE auth_credential SET is_active = 0 WHERE user_id = ?")
+            .bind(Self::uid_as_bytes(&user_id))
+            .execute(tx.conn())
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,110 @@
+use super::util::{downcast, placeholders};
+use crate::domain_model::*;
+use crate::domain_port::*;
+
+#[derive(sqlx::FromRow)]
+struct MatchRow {
+    contact_hash: Vec<u8>,
+    user_id: UserId,
+    username: String,
+}
+
+/// Works only through the caller's transaction, so it needs no pool.
+#[derive(Default)]
+pub struct MySqlContactHashRepo;
+
+impl MySqlContactHashRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl ContactHashRepo for MySqlContactHashRepo {
+    async fn count_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> anyhow::Result<usize> {
+        let tx = downcast(tx);
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM contact_hash WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_one(tx.conn())
+            .await?;
+
+        Ok(count as usize)
+    }
+
+    async fn replace_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        hashes: &[ContactHash],
+    ) -> anyhow::Result<()> {
+        let tx = downcast(tx);
+
+        sqlx::query("DELETE FROM contact_hash WHERE user_id = ?")
+            .bind(user_id)
+            .execute(tx.conn())
+            .await?;
+        if hashes.is_empty() {
+            return Ok(());
+        }
+
+        let sql = format!(
+            "INSERT IGNORE INTO contact_hash (contact_hash, user_id) VALUES {}",
+            std::iter::repeat_n("(?, ?)", hashes.len())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let mut query = sqlx::query(&sql);
+        for hash in hashes {
+            query = query.bind(&hash.0[..]).bind(user_id);
+        }
+        query.execute(tx.conn()).await?;
+
+        Ok(())
+    }
+
+    async fn find_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        seeker: UserId,
+        hashes: &[ContactHash],
+    ) -> anyhow::Result<Vec<ContactMatch>> {
+        if hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let tx = downcast(tx);
+
+        let sql = format!(
+            r#"
+SELECT c.contact_hash, c.user_id, u.username
+FROM contact_hash AS c
+JOIN user AS u ON u.user_id = c.user_id
+WHERE c.contact_hash IN ({})
+  AND c.user_id <> ?
+  AND u.is_active AND u.deleted_at IS NULL
+ORDER BY c.contact_hash, c.user_id
+"#,
+            placeholders(hashes.len())
+        );
+        let mut query = sqlx::query_as::<_, MatchRow>(&sql);
+        for hash in hashes {
+            query = query.bind(&hash.0[..]);
+        }
+        let rows = query.bind(seeker).fetch_all(tx.conn()).await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let contact_hash = ContactHash(row.contact_hash.as_slice().try_into()?);
+                Ok(ContactMatch {
+                    contact_hash,
+                    user_id: row.user_id,
+                    username: row.username,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,110 @@
+use super::util::downcast;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+
+/// Works only through the caller's transaction, so it needs no pool.
+#[derive(Default)]
+pub struct MySqlConversationMetaRepo;
+
+impl MySqlConversationMetaRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl ConversationMetaRepo for MySqlConversationMetaRepo {
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<ConversationMeta>, ChatError> {
+        let tx = downcast(tx);
+
+        let rows: Vec<(String, String, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+SELECT meta_key, meta_value, updated_at
+FROM conversation_meta
+WHERE conversation_id = ?
+ORDER BY meta_key
+"#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("list conversation meta: {e}")))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(key, value, updated_at)| ConversationMeta {
+                key,
+                value,
+                updated_at,
+            })
+            .collect())
+    }
+
+    async fn lock_keys_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<String>, ChatError> {
+        let tx = downcast(tx);
+
+        // the next-key locks on the primary key range also hold off inserts
+        sqlx::query_scalar(
+            "SELECT meta_key FROM conversation_meta WHERE conversation_id = ? FOR UPDATE",
+        )
+        .bind(conversation_id)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("lock conversation meta: {e}")))
+    }
+
+    async fn put_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        key: &str,
+        value: &str,
+    ) -> Result<(), ChatError> {
+        let tx = downcast(tx);
+
+        sqlx::query(
+            r#"
+INSERT INTO conversation_meta (conversation_id, meta_key, meta_value)
+VALUES (?, ?, ?)
+ON DUPLICATE KEY UPDATE meta_value = VALUES(meta_value)
+"#,
+        )
+        .bind(conversation_id)
+        .bind(key)
+        .bind(value)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("put conversation meta: {e}")))?;
+
+        Ok(())
+    }
+
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        key: &str,
+    ) -> Result<bool, ChatError> {
+        let tx = downcast(tx);
+
+        let result =
+            sqlx::query("DELETE FROM conversation_meta WHERE conversation_id = ? AND meta_key = ?")
+                .bind(conversation_id)
+                .bind(key)
+                .execute(tx.conn())
+                .await
+                .map_err(|e| ChatError::Store(format!("delete conversation meta: {e}")))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -44,25 +44,20 @@ impl ConversationRepo for MySqlConversationRepo {
     ) -> Result<(), RelationError> {
         let tx = downcast(tx);
 
-        sqlx::query("INSERT INTO conversation (conversation_id, kind_id) VALUES (?, 1)")
-            .bind(conversation_id)
-            .execute(tx.conn())
-            .await
-            .map_err(|e| RelationError::Store(format!("insert direct conversation: {e}")))?;
-
+        // both members arrive together, as the first membership change
         sqlx::query(
-            "INSERT INTO conversation_counter (conversation_id, next_offset) VALUES (?, 1)",
+            "INSERT INTO conversation (conversation_id, kind_id, members_version) VALUES (?, 1, 1)",
         )
         .bind(conversation_id)
         .execute(tx.conn())
         .await
-        .map_err(|e| RelationError::Store(format!("insert conversation_counter: {e}")))?;
+        .map_err(|e| RelationError::Store(format!("insert direct conversation: {e}")))?;
 
         sqlx::query(
             r#"
-INSERT INTO conversation_member (conversation_id, user_id)
-VALUES (?, ?),
-       (?, ?)
+INSERT INTO conversation_member (conversation_id, user_id, added_version)
+VALUES (?, ?, 1),
+       (?, ?, 1)
 "#,
         )
         .bind(conversation_id)
@@ -89,15 +84,94 @@ VALUES (?, ?),
             .await
             .map_err(|e| RelationError::Store(format!("insert group conversation: {e}")))?;
 
-        sqlx::query(
-            "INSERT INTO conversation_counter (conversation_id, next_offset) VALUES (?, 1)",
+        Ok(())
+    }
+
+    async fn soft_delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<bool, RelationError> {
+        let tx = downcast(tx);
+
+        let result = sqlx::query(
+            r#"
+UPDATE conversation SET deleted_at = CURRENT_TIMESTAMP(6)
+WHERE conversation_id = ? AND deleted_at IS NULL
+"#,
         )
         .bind(conversation_id)
         .execute(tx.conn())
         .await
-        .map_err(|e| RelationError::Store(format!("insert conversation_counter: {e}")))?;
+        .map_err(|e| RelationError::Store(format!("soft delete conversation: {e}")))?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn members_version_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<u64, RelationError> {
+        let tx = downcast(tx);
+
+        sqlx::query_scalar("SELECT members_version FROM conversation WHERE conversation_id = ?")
+            .bind(conversation_id)
+            .fetch_optional(tx.conn())
+            .await
+            .map_err(|e| RelationError::Store(format!("query members version: {e}")))?
+            .ok_or_else(|| RelationError::Store(format!("missing conversation: {conversation_id}")))
+    }
+
+    async fn members_since_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        since_version: Option<u64>,
+    ) -> Result<MembersDelta, ChatError> {
+        let tx = downcast(tx);
+
+        let members_version: u64 = sqlx::query_scalar(
+            "SELECT members_version FROM conversation WHERE conversation_id = ? AND deleted_at IS NULL",
+        )
+        .bind(conversation_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("query members version: {e}")))?
+        .ok_or(ChatError::ConversationNotFound)?;
+
+        // a version from the future means the client's list can't be trusted
+        let since_version = since_version.filter(|since| *since <= members_version);
+
+        let rows: Vec<(UserId, String, u64)> = sqlx::query_as(
+            r#"
+SELECT cm.user_id, u.username, cm.added_version
+FROM conversation_member AS cm
+JOIN user AS u ON u.user_id = cm.user_id
+WHERE cm.conversation_id = ?
+  AND cm.added_version > ?
+ORDER BY cm.added_version, cm.user_id
+"#,
+        )
+        .bind(conversation_id)
+        // rows from before versioning carry 0, so a snapshot can't filter on it
+        .bind(since_version.map_or(-1, |since| since as i64))
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("query members since: {e}")))?;
+
+        Ok(MembersDelta {
+            members_version,
+            snapshot: since_version.is_none(),
+            members: rows
+                .into_iter()
+                .map(|(user_id, username, added_version)| ConversationMember {
+                    user_id,
+                    username,
+                    added_version,
+                })
+                .collect(),
+        })
     }
 
     async fn list_for_user_recent_in_tx<'t>(
@@ -119,12 +193,17 @@ SELECT c.conversation_id
 FROM conversation_member cm
 JOIN conversation c ON c.conversation_id = cm.conversation_id
 WHERE cm.user_id = ?
-  AND (c.last_msg_at < ? OR (c.last_msg_at = ? AND c.conversation_id < ?))
-ORDER BY c.last_msg_at DESC, c.conversation_id DESC
+  AND c.deleted_at IS NULL
+  AND c.last_msg_at IS NOT NULL
+  AND (cm.pinned < ? OR (cm.pinned = ? AND
+      (c.last_msg_at < ? OR (c.last_msg_at = ? AND c.conversation_id < ?))))
+ORDER BY cm.pinned DESC, c.last_msg_at DESC, c.conversation_id DESC
 LIMIT ?
 "#,
             )
             .bind(user_id)
+            .bind(cur.pinned)
+            .bind(cur.pinned)
             .bind(cur.last_msg_at)
             .bind(cur.last_msg_at)
             .bind(cur.conversation_id)
@@ -139,8 +218,9 @@ SELECT c.conversation_id
 FROM conversation_member cm
 JOIN conversation c ON c.conversation_id = cm.conversation_id
 WHERE cm.user_id = ?
+  AND c.deleted_at IS NULL
   AND c.last_msg_at IS NOT NULL
-ORDER BY c.last_msg_at DESC, c.conversation_id DESC
+ORDER BY cm.pinned DESC, c.last_msg_at DESC, c.conversation_id DESC
 LIMIT ?
 "#,
             )
@@ -166,8 +246,15 @@ LIMIT ?
             kind_id: u8,
             last_msg_off: u64,
             last_msg_at: Option<DateTime<Utc>>,
+            pinned: bool,
+            frozen: bool,
+            retention_days: Option<u32>,
+            last_read_off: u64,
+            unread_count: u64,
             group_id: Option<GroupId>,
             group_name: Option<String>,
+            avatar_url: Option<String>,
+            theme_color: Option<String>,
             other_user: Option<UserId>,
             other_username: Option<String>,
         }
@@ -188,8 +275,22 @@ SELECT
     c.kind_id,
     c.last_msg_off,
     c.last_msg_at,
+    COALESCE(me.pinned, FALSE) AS pinned,
+    c.frozen,
+    c.retention_days,
+    COALESCE(me.last_read_off, 0) AS last_read_off,
+    (SELECT CAST(COUNT(*) AS UNSIGNED)
+     FROM (SELECT 1
+           FROM message AS m
+           WHERE m.conversation_id = c.conversation_id
+             AND m.message_offset > COALESCE(me.last_read_off, 0)
+             AND m.sender_id <> ?
+             AND m.deleted_at IS NULL
+           LIMIT ?) AS pending) AS unread_count,
     cg.group_id,
     cg.group_name,
+    cg.avatar_url,
+    cg.theme_color,
     ou.user_id     AS other_user,
     ou.username    AS other_username
 FROM conversation AS c
@@ -205,7 +306,10 @@ FROM conversation AS c
     ) AS cu ON TRUE
          LEFT JOIN user AS ou
                    ON ou.user_id = cu.user_id
+         LEFT JOIN conversation_member AS me
+                   ON me.conversation_id = c.conversation_id AND me.user_id = ?
 WHERE c.conversation_id IN ({in_list})
+  AND c.deleted_at IS NULL
 ORDER BY FIELD(c.conversation_id, {field_list})
 "#,
             in_list = placeholders,
@@ -214,7 +318,11 @@ ORDER BY FIELD(c.conversation_id, {field_list})
 
         tracing::trace!("query string in hydrate_conversation_in_tx: {}", sql);
 
-        let mut q = sqlx::query_as::<_, RecentHydrateRow>(&sql).bind(user_id);
+        let mut q = sqlx::query_as::<_, RecentHydrateRow>(&sql)
+            .bind(user_id)
+            .bind(MAX_UNREAD_COUNT)
+            .bind(user_id)
+            .bind(user_id);
         // IN list
         for id in &conversation_ids {
             q = q.bind(*id);
@@ -245,6 +353,10 @@ ORDER BY FIELD(c.conversation_id, {field_list})
                         ConversationPeer::Group {
                             group_id: gid,
                             name,
+                            appearance: GroupAppearance {
+                                avatar_url: r.avatar_url,
+                                theme_color: r.theme_color,
+                            },
                         }
                     }
                     kind if kind == ConversationKind::Direct as u8 => {
@@ -269,10 +381,353 @@ ORDER BY FIELD(c.conversation_id, {field_list})
                     peer,
                     last_msg_off: MessageOffset(r.last_msg_off as u64),
                     last_msg_at: r.last_msg_at,
+                    pinned: r.pinned,
+                    frozen: r.frozen,
+                    // the override; the service turns it into the effective value
+                    retention_days: r.retention_days,
+                    last_read_off: MessageOffset(r.last_read_off),
+                    unread_count: r.unread_count,
                 })
             })
             .collect::<Result<Vec<_>, ChatError>>()?;
 
         Ok(out)
     }
+
+    async fn unread_summary_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        top: PageSize,
+    ) -> Result<UnreadSummary, ChatError> {
+        #[derive(sqlx::FromRow)]
+        struct UnreadRow {
+            conversation_id: ConversationId,
+            unread: u64,
+            last_msg_at: Option<DateTime<Utc>>,
+            total_unread: u64,
+        }
+
+        let tx = downcast(tx);
+
+        // counts real rows, since offsets have gaps; the window sum is taken
+        // before LIMIT, so it covers every row
+        let rows: Vec<UnreadRow> = sqlx::query_as(
+            r#"
+SELECT c.conversation_id,
+       u.unread,
+       c.last_msg_at,
+       CAST(SUM(u.unread) OVER () AS UNSIGNED) AS total_unread
+FROM conversation_member cm
+JOIN conversation c ON c.conversation_id = cm.conversation_id
+JOIN LATERAL (
+    SELECT CAST(COUNT(*) AS UNSIGNED) AS unread
+    FROM (SELECT 1
+          FROM message AS m
+          WHERE m.conversation_id = cm.conversation_id
+            AND m.message_offset > cm.last_read_off
+            AND m.sender_id <> cm.user_id
+            AND m.deleted_at IS NULL
+          LIMIT ?) AS pending
+    ) AS u ON TRUE
+WHERE cm.user_id = ?
+  AND c.deleted_at IS NULL
+  AND c.last_msg_off > cm.last_read_off
+  AND u.unread > 0
+ORDER BY c.last_msg_at DESC, c.conversation_id DESC
+LIMIT ?
+"#,
+        )
+        .bind(MAX_UNREAD_COUNT)
+        .bind(user_id)
+        .bind(top.0 as i64)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("unread summary: {e}")))?;
+
+        Ok(UnreadSummary {
+            total_unread: rows.first().map_or(0, |r| r.total_unread),
+            conversations: rows
+                .into_iter()
+                .map(|r| UnreadConversation {
+                    conversation_id: r.conversation_id,
+                    unread: r.unread,
+                    last_msg_at: r.last_msg_at,
+                })
+                .collect(),
+        })
+    }
+
+    async fn is_frozen_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<bool, ChatError> {
+        let tx = downcast(tx);
+
+        let frozen: Option<bool> = sqlx::query_scalar(
+            "SELECT frozen FROM conversation WHERE conversation_id = ? AND deleted_at IS NULL",
+        )
+        .bind(conversation_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("read frozen: {e}")))?;
+
+        frozen.ok_or(ChatError::ConversationNotFound)
+    }
+
+    async fn set_frozen_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        frozen: bool,
+    ) -> Result<bool, ChatError> {
+        let tx = downcast(tx);
+
+        // matched rows count as affected, so compare first
+        let current: Option<bool> = sqlx::query_scalar(
+            "SELECT frozen FROM conversation WHERE conversation_id = ? AND deleted_at IS NULL FOR UPDATE",
+        )
+        .bind(conversation_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("lock frozen: {e}")))?;
+        match current {
+            None => return Err(ChatError::ConversationNotFound),
+            Some(current) if current == frozen => return Ok(false),
+            Some(_) => {}
+        }
+
+        sqlx::query("UPDATE conversation SET frozen = ? WHERE conversation_id = ?")
+            .bind(frozen)
+            .bind(conversation_id)
+            .execute(tx.conn())
+            .await
+            .map_err(|e| ChatError::Store(format!("set frozen: {e}")))?;
+
+        Ok(true)
+    }
+
+    async fn retention_days_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<Option<u32>, ChatError> {
+        let tx = downcast(tx);
+
+        let row: Option<(Option<u32>,)> = sqlx::query_as(
+            "SELECT retention_days FROM conversation WHERE conversation_id = ? AND deleted_at IS NULL",
+        )
+        .bind(conversation_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("read retention: {e}")))?;
+
+        row.map(|(days,)| days)
+            .ok_or(ChatError::ConversationNotFound)
+    }
+
+    async fn set_retention_days_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        days: Option<u32>,
+    ) -> Result<(), ChatError> {
+        let tx = downcast(tx);
+
+        // matched rows count as affected, so setting the same value still finds it
+        let res = sqlx::query(
+            "UPDATE conversation SET retention_days = ? WHERE conversation_id = ? AND deleted_at IS NULL",
+        )
+        .bind(days)
+        .bind(conversation_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("set retention: {e}")))?;
+        if res.rows_affected() == 0 {
+            return Err(ChatError::ConversationNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn advance_read_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+    ) -> Result<(), ChatError> {
+        let tx = downcast(tx);
+
+        sqlx::query(
+            r#"
+UPDATE conversation_member
+SET last_read_off = GREATEST(last_read_off, ?)
+WHERE conversation_id = ? AND user_id = ?
+"#,
+        )
+        .bind(offset)
+        .bind(conversation_id)
+        .bind(user_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("advance read position: {e}")))?;
+
+        Ok(())
+    }
+
+    async fn mark_read_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+    ) -> Result<ReadMark, ChatError> {
+        let tx = downcast(tx);
+
+        // only the member row is locked; senders keep advancing last_msg_off
+        let row: Option<(u64, u64)> = sqlx::query_as(
+            r#"
+SELECT cm.last_read_off, c.last_msg_off
+FROM conversation_member cm
+JOIN conversation c ON c.conversation_id = cm.conversation_id
+WHERE cm.conversation_id = ? AND cm.user_id = ?
+FOR UPDATE OF cm
+"#,
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("lock read position: {e}")))?;
+        let Some((last_read_off, last_msg_off)) = row else {
+            return Err(ChatError::NotMember);
+        };
+
+        let target = offset.0.min(last_msg_off);
+        if target <= last_read_off {
+            return Ok(ReadMark {
+                last_read_off: MessageOffset(last_read_off),
+                advanced: false,
+            });
+        }
+
+        sqlx::query(
+            "UPDATE conversation_member SET last_read_off = ? WHERE conversation_id = ? AND user_id = ?",
+        )
+        .bind(target)
+        .bind(conversation_id)
+        .bind(user_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("mark read: {e}")))?;
+
+        Ok(ReadMark {
+            last_read_off: MessageOffset(target),
+            advanced: true,
+        })
+    }
+
+    async fn set_pinned_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        conversation_id: ConversationId,
+        pinned: bool,
+    ) -> Result<bool, ChatError> {
+        let tx = downcast(tx);
+
+        // rows_affected counts matched rows, so re-pinning is not a miss
+        let result = sqlx::query(
+            "UPDATE conversation_member SET pinned = ? WHERE conversation_id = ? AND user_id = ?",
+        )
+        .bind(pinned)
+        .bind(conversation_id)
+        .bind(user_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("set pinned: {e}")))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn next_sender_seq_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        sender: UserId,
+    ) -> Result<u64, ChatError> {
+        let tx = downcast(tx);
+
+        let res = sqlx::query(
+            r#"
+UPDATE conversation_member
+SET sent_count = LAST_INSERT_ID(sent_count + 1)
+WHERE conversation_id = ? AND user_id = ?
+"#,
+        )
+        .bind(conversation_id)
+        .bind(sender)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("next sender seq: {e}")))?;
+        if res.rows_affected() == 0 {
+            return Err(ChatError::NotMember);
+        }
+
+        Ok(res.last_insert_id())
+    }
+
+    async fn reserve_offsets(
+        &self,
+        conversation_id: ConversationId,
+        floor: u64,
+        block: u64,
+    ) -> Result<u64, ChatError> {
+        let res = sqlx::query(
+            r#"
+UPDATE conversation
+SET offset_ceiling = LAST_INSERT_ID(GREATEST(offset_ceiling, last_msg_off, ?) + ?)
+WHERE conversation_id = ? AND deleted_at IS NULL
+"#,
+        )
+        .bind(floor)
+        .bind(block)
+        .bind(conversation_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ChatError::Store(format!("reserve offsets: {e}")))?;
+        if res.rows_affected() == 0 {
+            // missing or soft-deleted
+            return Err(ChatError::ConversationNotFound);
+        }
+
+        Ok(res.last_insert_id())
+    }
+
+    async fn advance_last_message(
+        &self,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+        at: DateTime<Utc>,
+    ) -> Result<(), ChatError> {
+        // NOTE: last_msg_at can be NULL
+        sqlx::query(
+            r#"
+UPDATE conversation
+SET last_msg_off = GREATEST(last_msg_off, ?),
+    last_msg_at  = GREATEST(COALESCE(last_msg_at, TIMESTAMP('1970-01-01 00:00:00')), ?)
+WHERE conversation_id = ?
+"#,
+        )
+        .bind(offset)
+        .bind(at)
+        .bind(conversation_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ChatError::Store(format!("advance conversation last: {e}")))?;
+
+        Ok(())
+    }
 }
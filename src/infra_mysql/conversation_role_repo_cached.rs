@@ -0,0 +1,139 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use moka::sync::Cache;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Most memberships cached at once; past it, the least used are evicted.
+const MAX_CACHED_MEMBERSHIPS: u64 = 200_000;
+
+/// Caches positive membership answers, which are checked on every send and
+/// history read, for a short TTL. Everything else passes through.
+///
+/// Only "is a member" is remembered: an invite can never make a cached answer
+/// wrong, and a non-member asking again still reaches the database. Removing
+/// a member must drop the entry here; other nodes see it once theirs expires.
+pub struct CachedConversationRoleRepo {
+    inner: Arc<dyn ConversationRoleRepo>,
+    members: Option<Cache<(ConversationId, UserId), ()>>,
+}
+
+impl CachedConversationRoleRepo {
+    /// A zero `ttl` disables caching.
+    pub fn new(inner: Arc<dyn ConversationRoleRepo>, ttl: Duration) -> Self {
+        let members = (!ttl.is_zero()).then(|| {
+            Cache::builder()
+                .max_capacity(MAX_CACHED_MEMBERSHIPS)
+                .time_to_live(ttl)
+                .build()
+        });
+        Self { inner, members }
+    }
+
+    fn cached(&self, conversation_id: ConversationId, user_id: UserId) -> bool {
+        self.members
+            .as_ref()
+            .is_some_and(|members| members.contains_key(&(conversation_id, user_id)))
+    }
+
+    fn remember(&self, conversation_id: ConversationId, user_id: UserId, is_member: bool) {
+        if let Some(members) = &self.members
+            && is_member
+        {
+            members.insert((conversation_id, user_id), ());
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ConversationRoleRepo for CachedConversationRoleRepo {
+    async fn get_role_by_conversation_id(
+        &self,
+        user_id: UserId,
+        conversation_id: ConversationId,
+    ) -> Result<GroupMemberRole, RelationError> {
+        self.inner
+            .get_role_by_conversation_id(user_id, conversation_id)
+            .await
+    }
+
+    async fn ensure_defaults_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+    ) -> Result<(), RelationError> {
+        self.inner.ensure_defaults_in_tx(tx, conversation_id).await
+    }
+
+    async fn assign_role_by_name_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        user_id: UserId,
+        role_name: &str,
+    ) -> Result<(), RelationError> {
+        self.inner
+            .assign_role_by_name_in_tx(tx, conversation_id, user_id, role_name)
+            .await
+    }
+
+    async fn assign_roles_bulk_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        user_ids: &[UserId],
+        role_name: &str,
+    ) -> Result<(), RelationError> {
+        self.inner
+            .assign_roles_bulk_in_tx(tx, conversation_id, user_ids, role_name)
+            .await
+    }
+
+    async fn membership_exists(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<bool, RelationError> {
+        if self.cached(conversation_id, user_id) {
+            return Ok(true);
+        }
+
+        let is_member = self
+            .inner
+            .membership_exists(conversation_id, user_id)
+            .await?;
+        self.remember(conversation_id, user_id, is_member);
+        Ok(is_member)
+    }
+
+    async fn membership_exists_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<bool, RelationError> {
+        if self.cached(conversation_id, user_id) {
+            return Ok(true);
+        }
+
+        let is_member = self
+            .inner
+            .membership_exists_in_tx(tx, conversation_id, user_id)
+            .await?;
+        self.remember(conversation_id, user_id, is_member);
+        Ok(is_member)
+    }
+
+    async fn has_permission_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        user_id: UserId,
+        perm_key: &str,
+    ) -> Result<Option<bool>, RelationError> {
+        self.inner
+            .has_permission_in_tx(tx, conversation_id, user_id, perm_key)
+            .await
+    }
+}
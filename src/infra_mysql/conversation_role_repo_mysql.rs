@@ -2,7 +2,10 @@ use super::util::downcast;
 use crate::application_port::*;
 use crate::domain_model::*;
 use crate::domain_port::*;
-use sqlx::{MySqlPool, Row};
+use sqlx::{MySql, MySqlPool, QueryBuilder, Row};
+
+/// Rows per multi-row insert; keeps each statement well under the placeholder limit.
+const BULK_CHUNK: usize = 500;
 
 pub struct MySqlConversationRoleRepo {
     pool: MySqlPool,
@@ -104,11 +107,11 @@ WHERE conversation_id = ?
             .map_err(|e| RelationError::Store(format!("i64 role decode: {e}")))?;
 
         // 4) Seed permissions.
-        // owner: allow both 'message.send' and 'member.invite'
+        // owner: allow 'message.send', 'member.invite' and 'member.pin'
         sqlx::query(
             r#"
 INSERT INTO conversation_role_perm (role_id, perm_id, effect)
-SELECT ?, p.perm_id, 'allow' FROM permission p WHERE p.perm_key IN ('message.send', 'member.invite')
+SELECT ?, p.perm_id, 'allow' FROM permission p WHERE p.perm_key IN ('message.send', 'member.invite', 'member.pin')
 ON DUPLICATE KEY UPDATE effect = VALUES(effect)
 "#,
         )
@@ -140,6 +143,21 @@ ON DUPLICATE KEY UPDATE effect = VALUES(effect)
         user_id: UserId,
         role_name: &str,
     ) -> Result<(), RelationError> {
+        self.assign_roles_bulk_in_tx(tx, conversation_id, &[user_id], role_name)
+            .await
+    }
+
+    async fn assign_roles_bulk_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        user_ids: &[UserId],
+        role_name: &str,
+    ) -> Result<(), RelationError> {
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+
         let tx = downcast(tx);
 
         // 1) Resolve role_id by (conversation_id, name)
@@ -155,36 +173,139 @@ ON DUPLICATE KEY UPDATE effect = VALUES(effect)
             .try_get("role_id")
             .map_err(|e| RelationError::Store(format!("i64 role decode: {e}")))?;
 
-        // 2) Ensure membership record exists.
+        // Lock the group row first so membership changes to one group are
+        // serialized and the pre-insert count below stays exact. Direct
+        // conversations have no group row and skip the bookkeeping.
+        let is_group = sqlx::query(
+            "UPDATE chat_group SET member_count = member_count WHERE conversation_id = ?",
+        )
+        .bind(conversation_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| RelationError::Store(format!("lock group member count: {e}")))?
+        .rows_affected()
+            > 0;
+
+        // New members all land in the next membership version; the row lock
+        // also serializes direct conversations.
+        let members_version: u64 = sqlx::query_scalar(
+            "SELECT members_version FROM conversation WHERE conversation_id = ? FOR UPDATE",
+        )
+        .bind(conversation_id)
+        .fetch_one(tx.conn())
+        .await
+        .map_err(|e| RelationError::Store(format!("lock members version: {e}")))?;
+        let next_version = members_version + 1;
+
+        let mut user_ids = user_ids.to_vec();
+        user_ids.sort_unstable_by_key(|u| u.0);
+        user_ids.dedup();
+
+        for chunk in user_ids.chunks(BULK_CHUNK) {
+            let existing = if is_group {
+                let mut query = QueryBuilder::<MySql>::new(
+                    "SELECT COUNT(*) FROM conversation_member WHERE conversation_id = ",
+                );
+                query.push_bind(conversation_id);
+                query.push(" AND user_id IN (");
+                let mut ids = query.separated(", ");
+                for user_id in chunk {
+                    ids.push_bind(*user_id);
+                }
+                query.push(")");
+                query
+                    .build_query_scalar::<i64>()
+                    .fetch_one(tx.conn())
+                    .await
+                    .map_err(|e| RelationError::Store(format!("count existing members: {e}")))?
+            } else {
+                0
+            };
+
+            // 2) Ensure membership records exist.
+            let mut members = QueryBuilder::<MySql>::new(
+                "INSERT INTO conversation_member (conversation_id, user_id, added_version) ",
+            );
+            members.push_values(chunk, |mut b, user_id| {
+                b.push_bind(conversation_id)
+                    .push_bind(*user_id)
+                    .push_bind(next_version);
+            });
+            members.push(" ON DUPLICATE KEY UPDATE last_read_off = last_read_off");
+            members
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(|e| RelationError::Store(format!("ensure membership: {e}")))?;
+
+            // 3) Assign role to members
+            let mut roles = QueryBuilder::<MySql>::new(
+                "INSERT INTO conversation_member_role (conversation_id, user_id, role_id) ",
+            );
+            roles.push_values(chunk, |mut b, user_id| {
+                b.push_bind(conversation_id)
+                    .push_bind(*user_id)
+                    .push_bind(role_id);
+            });
+            roles.push(" ON DUPLICATE KEY UPDATE role_id = VALUES(role_id)");
+            roles
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(|e| RelationError::Store(format!("assign role: {e}")))?;
+
+            let added = chunk.len() as i64 - existing;
+            if added > 0 {
+                sqlx::query(
+                    "UPDATE chat_group SET member_count = member_count + ? WHERE conversation_id = ?",
+                )
+                .bind(added)
+                .bind(conversation_id)
+                .execute(tx.conn())
+                .await
+                .map_err(|e| RelationError::Store(format!("bump group member count: {e}")))?;
+            }
+        }
+
+        // Existing members keep their `added_version`, so only bump when a
+        // row above was actually new.
         sqlx::query(
             r#"
-INSERT INTO conversation_member (conversation_id, user_id)
-VALUES (?, ?)
-ON DUPLICATE KEY UPDATE last_read_off = last_read_off
+UPDATE conversation SET members_version = ?
+WHERE conversation_id = ?
+  AND EXISTS (SELECT 1 FROM conversation_member WHERE conversation_id = ? AND added_version = ?)
 "#,
         )
+        .bind(next_version)
         .bind(conversation_id)
-        .bind(user_id)
+        .bind(conversation_id)
+        .bind(next_version)
         .execute(tx.conn())
         .await
-        .map_err(|e| RelationError::Store(format!("ensure membership: {e}")))?;
+        .map_err(|e| RelationError::Store(format!("bump members version: {e}")))?;
 
-        // 3) Assign role to member
-        sqlx::query(
+        Ok(())
+    }
+
+    async fn membership_exists(
+        &self,
+        conversation_id: ConversationId,
+        user_id: UserId,
+    ) -> Result<bool, RelationError> {
+        let found: Option<i32> = sqlx::query_scalar(
             r#"
-INSERT INTO conversation_member_role (conversation_id, user_id, role_id)
-VALUES (?, ?, ?)
-ON DUPLICATE KEY UPDATE role_id = VALUES(role_id)
+SELECT 1
+FROM conversation_member
+WHERE conversation_id = ? AND user_id = ?
 "#,
         )
         .bind(conversation_id)
         .bind(user_id)
-        .bind(role_id)
-        .execute(tx.conn())
+        .fetch_optional(&self.pool)
         .await
-        .map_err(|e| RelationError::Store(format!("assign role: {e}")))?;
+        .map_err(|e| RelationError::Store(format!("membership check: {e}")))?;
 
-        Ok(())
+        Ok(found.is_some())
     }
 
     async fn membership_exists_in_tx<'t>(
@@ -216,4 +337,33 @@ WHERE conversation_id = ? AND user_id = ?
 
         if cnt > 0 { Ok(true) } else { Ok(false) }
     }
+
+    async fn has_permission_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        user_id: UserId,
+        perm_key: &str,
+    ) -> Result<Option<bool>, RelationError> {
+        let tx = downcast(tx);
+
+        // one role per member; no grant for the key leaves `effect` NULL
+        let effect: Option<Option<String>> = sqlx::query_scalar(
+            r#"
+SELECT CAST(rp.effect AS CHAR)
+FROM conversation_member_role m
+LEFT JOIN (conversation_role_perm rp JOIN permission p ON p.perm_id = rp.perm_id AND p.perm_key = ?)
+  ON rp.role_id = m.role_id
+WHERE m.conversation_id = ? AND m.user_id = ?
+"#,
+        )
+        .bind(perm_key)
+        .bind(conversation_id)
+        .bind(user_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| RelationError::Store(format!("permission check: {e}")))?;
+
+        Ok(effect.map(|effect| effect.as_deref() == Some("allow")))
+    }
 }
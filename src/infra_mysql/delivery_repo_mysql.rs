@@ -0,0 +1,120 @@
+use super::util::downcast;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+
+/// Works only through the caller's transaction, so it needs no pool.
+#[derive(Default)]
+pub struct MySqlDeliveryRepo;
+
+impl MySqlDeliveryRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl DeliveryRepo for MySqlDeliveryRepo {
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+        user_id: UserId,
+        at: DateTime<Utc>,
+    ) -> Result<Option<(UserId, MessageOffset)>, ChatError> {
+        let tx = downcast(tx);
+
+        // a plain read of the message, so acks from a whole group don't
+        // queue on its row lock
+        let inserted = sqlx::query(
+            r#"
+INSERT IGNORE INTO message_delivery (conversation_id, message_offset, user_id, delivered_at)
+SELECT conversation_id, message_offset, ?, ?
+FROM message
+WHERE conversation_id = ?
+  AND message_id = ?
+  AND sender_id <> ?
+  AND deleted_at IS NULL
+"#,
+        )
+        .bind(user_id)
+        .bind(at)
+        .bind(conversation_id)
+        .bind(message_id)
+        .bind(user_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("insert delivery: {e}")))?
+        .rows_affected()
+            > 0;
+        if !inserted {
+            return Ok(None);
+        }
+
+        let row: (UserId, MessageOffset) = sqlx::query_as(
+            "SELECT sender_id, message_offset FROM message WHERE conversation_id = ? AND message_id = ?",
+        )
+        .bind(conversation_id)
+        .bind(message_id)
+        .fetch_one(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("query delivered message: {e}")))?;
+
+        Ok(Some(row))
+    }
+
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+        sender: UserId,
+    ) -> Result<Option<Vec<MessageDelivery>>, ChatError> {
+        let tx = downcast(tx);
+
+        let offset: Option<MessageOffset> = sqlx::query_scalar(
+            r#"
+SELECT message_offset
+FROM message
+WHERE conversation_id = ?
+  AND message_id = ?
+  AND sender_id = ?
+"#,
+        )
+        .bind(conversation_id)
+        .bind(message_id)
+        .bind(sender)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("query delivered message: {e}")))?;
+        let Some(offset) = offset else {
+            return Ok(None);
+        };
+
+        let rows: Vec<(UserId, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+SELECT user_id, delivered_at
+FROM message_delivery
+WHERE conversation_id = ?
+  AND message_offset = ?
+ORDER BY delivered_at, user_id
+"#,
+        )
+        .bind(conversation_id)
+        .bind(offset)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("list deliveries: {e}")))?;
+
+        Ok(Some(
+            rows.into_iter()
+                .map(|(user_id, delivered_at)| MessageDelivery {
+                    user_id,
+                    delivered_at,
+                })
+                .collect(),
+        ))
+    }
+}
@@ -0,0 +1,174 @@
+use super::util::{downcast, placeholders};
+use crate::domain_model::*;
+use crate::domain_port::*;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+
+#[derive(sqlx::FromRow)]
+struct DeviceRow {
+    user_id: UserId,
+    platform: String,
+    token: String,
+    locale: String,
+    registered_at: DateTime<Utc>,
+}
+
+impl TryFrom<DeviceRow> for Device {
+    type Error = anyhow::Error;
+
+    fn try_from(r: DeviceRow) -> Result<Self, Self::Error> {
+        let platform = PushPlatform::parse(&r.platform)
+            .ok_or_else(|| anyhow!("unknown push platform [{}]", r.platform))?;
+        Ok(Device {
+            user_id: r.user_id,
+            platform,
+            token: r.token,
+            locale: r.locale,
+            registered_at: r.registered_at,
+        })
+    }
+}
+
+/// Works only through the caller's transaction, so it needs no pool.
+#[derive(Default)]
+pub struct MySqlDeviceRepo;
+
+impl MySqlDeviceRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceRepo for MySqlDeviceRepo {
+    async fn upsert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        device: &Device,
+    ) -> anyhow::Result<()> {
+        let tx = downcast(tx);
+
+        sqlx::query(
+            r#"
+INSERT INTO device (platform, token, user_id, locale, registered_at)
+VALUES (?, ?, ?, ?, ?)
+ON DUPLICATE KEY UPDATE user_id       = VALUES(user_id),
+                        locale        = VALUES(locale),
+                        registered_at = VALUES(registered_at)
+"#,
+        )
+        .bind(device.platform.as_str())
+        .bind(&device.token)
+        .bind(device.user_id)
+        .bind(&device.locale)
+        .bind(device.registered_at)
+        .execute(tx.conn())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn trim_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        keep: usize,
+    ) -> anyhow::Result<u64> {
+        let tx = downcast(tx);
+
+        // the newest registration past the ones kept; it and anything older go
+        let cutoff: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r#"
+SELECT registered_at
+FROM device
+WHERE user_id = ?
+ORDER BY registered_at DESC
+LIMIT 1 OFFSET ?
+"#,
+        )
+        .bind(user_id)
+        .bind(keep as u64)
+        .fetch_optional(tx.conn())
+        .await?;
+        let Some(cutoff) = cutoff else {
+            return Ok(0);
+        };
+
+        let deleted = sqlx::query("DELETE FROM device WHERE user_id = ? AND registered_at <= ?")
+            .bind(user_id)
+            .bind(cutoff)
+            .execute(tx.conn())
+            .await?
+            .rows_affected();
+        Ok(deleted)
+    }
+
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        platform: PushPlatform,
+        token: &str,
+    ) -> anyhow::Result<bool> {
+        let tx = downcast(tx);
+
+        let deleted =
+            sqlx::query("DELETE FROM device WHERE platform = ? AND token = ? AND user_id = ?")
+                .bind(platform.as_str())
+                .bind(token)
+                .bind(user_id)
+                .execute(tx.conn())
+                .await?
+                .rows_affected();
+        Ok(deleted > 0)
+    }
+
+    async fn list_for_users_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_ids: &[UserId],
+    ) -> anyhow::Result<Vec<Device>> {
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let tx = downcast(tx);
+
+        let sql = format!(
+            r#"
+SELECT user_id, platform, token, locale, registered_at
+FROM device
+WHERE user_id IN ({})
+"#,
+            placeholders(user_ids.len())
+        );
+        let mut query = sqlx::query_as::<_, DeviceRow>(&sql);
+        for user_id in user_ids {
+            query = query.bind(*user_id);
+        }
+        let rows = query.fetch_all(tx.conn()).await?;
+
+        rows.into_iter().map(Device::try_from).collect()
+    }
+
+    async fn delete_tokens_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        platform: PushPlatform,
+        tokens: &[String],
+    ) -> anyhow::Result<u64> {
+        if tokens.is_empty() {
+            return Ok(0);
+        }
+        let tx = downcast(tx);
+
+        let sql = format!(
+            "DELETE FROM device WHERE platform = ? AND token IN ({})",
+            placeholders(tokens.len())
+        );
+        let mut query = sqlx::query(&sql).bind(platform.as_str());
+        for token in tokens {
+            query = query.bind(token);
+        }
+        Ok(query.execute(tx.conn()).await?.rows_affected())
+    }
+}
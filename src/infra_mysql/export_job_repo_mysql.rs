@@ -0,0 +1,202 @@
+use super::util::{downcast, placeholders};
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+
+#[derive(sqlx::FromRow)]
+struct ExportJobRow {
+    user_id: UserId,
+    stage: String,
+    records_written: u64,
+    messages_written: u64,
+    messages_total: Option<u64>,
+    started_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+    updated_at: DateTime<Utc>,
+    file_name: String,
+    blob_key: String,
+    archive_key: Vec<u8>,
+    key_id: Option<String>,
+}
+
+impl From<ExportJobRow> for ExportJobRecord {
+    fn from(r: ExportJobRow) -> Self {
+        ExportJobRecord {
+            user_id: r.user_id,
+            progress: ExportProgress {
+                stage: match r.stage.as_str() {
+                    "queued" => ExportStage::Queued,
+                    "profile" => ExportStage::Profile,
+                    "friends" => ExportStage::Friends,
+                    "groups" => ExportStage::Groups,
+                    "messages" => ExportStage::Messages,
+                    "ready" => ExportStage::Ready,
+                    // written by a newer build; nothing this one can finish
+                    _ => ExportStage::Failed,
+                },
+                records_written: r.records_written,
+                messages_written: r.messages_written,
+                messages_total: r.messages_total,
+                started_at: r.started_at,
+                finished_at: r.finished_at,
+            },
+            updated_at: r.updated_at,
+            file_name: r.file_name,
+            blob_key: r.blob_key,
+            archive_key: r.archive_key,
+            key_id: r.key_id,
+        }
+    }
+}
+
+const SELECT_JOB: &str = r#"
+SELECT user_id, stage, records_written, messages_written, messages_total, started_at,
+       finished_at, updated_at, file_name, blob_key, archive_key, key_id
+FROM export_job
+"#;
+
+/// Works only through the caller's transaction, so it needs no pool.
+#[derive(Default)]
+pub struct MySqlExportJobRepo;
+
+impl MySqlExportJobRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl ExportJobRepo for MySqlExportJobRepo {
+    async fn get_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> anyhow::Result<Option<ExportJobRecord>> {
+        let tx = downcast(tx);
+
+        let row: Option<ExportJobRow> = sqlx::query_as(&format!("{SELECT_JOB} WHERE user_id = ?"))
+            .bind(user_id)
+            .fetch_optional(tx.conn())
+            .await?;
+
+        Ok(row.map(ExportJobRecord::from))
+    }
+
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        job: &ExportJobRecord,
+    ) -> anyhow::Result<bool> {
+        let tx = downcast(tx);
+
+        let result = sqlx::query(
+            r#"
+INSERT IGNORE INTO export_job (user_id, stage, records_written, messages_written, messages_total,
+                               started_at, finished_at, updated_at, file_name, blob_key,
+                               archive_key, key_id)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+"#,
+        )
+        .bind(job.user_id)
+        .bind(job.progress.stage.as_str())
+        .bind(job.progress.records_written)
+        .bind(job.progress.messages_written)
+        .bind(job.progress.messages_total)
+        .bind(job.progress.started_at)
+        .bind(job.progress.finished_at)
+        .bind(job.updated_at)
+        .bind(&job.file_name)
+        .bind(&job.blob_key)
+        .bind(&job.archive_key)
+        .bind(&job.key_id)
+        .execute(tx.conn())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn update_progress_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        job: ExportJobId,
+        progress: &ExportProgress,
+    ) -> anyhow::Result<bool> {
+        let tx = downcast(tx);
+
+        // updated_at always moves, so a match always counts as a change
+        let result = sqlx::query(
+            r#"
+UPDATE export_job
+SET stage = ?, records_written = ?, messages_written = ?, messages_total = ?, finished_at = ?,
+    updated_at = CURRENT_TIMESTAMP(6)
+WHERE user_id = ? AND started_at = ?
+"#,
+        )
+        .bind(progress.stage.as_str())
+        .bind(progress.records_written)
+        .bind(progress.messages_written)
+        .bind(progress.messages_total)
+        .bind(progress.finished_at)
+        .bind(job.user_id)
+        .bind(job.started_at)
+        .execute(tx.conn())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        job: ExportJobId,
+    ) -> anyhow::Result<bool> {
+        let tx = downcast(tx);
+
+        let result = sqlx::query("DELETE FROM export_job WHERE user_id = ? AND started_at = ?")
+            .bind(job.user_id)
+            .bind(job.started_at)
+            .execute(tx.conn())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_expired_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        finished_before: DateTime<Utc>,
+        updated_before: DateTime<Utc>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<ExportJobRecord>> {
+        let tx = downcast(tx);
+
+        let rows: Vec<ExportJobRow> = sqlx::query_as(&format!(
+            r#"{SELECT_JOB}
+WHERE finished_at < ?
+   OR (finished_at IS NULL AND updated_at < ?)
+LIMIT ?
+FOR UPDATE
+"#
+        ))
+        .bind(finished_before)
+        .bind(updated_before)
+        .bind(limit)
+        .fetch_all(tx.conn())
+        .await?;
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let sql = format!(
+            "DELETE FROM export_job WHERE user_id IN ({})",
+            placeholders(rows.len())
+        );
+        let mut query = sqlx::query(&sql);
+        for row in &rows {
+            query = query.bind(row.user_id);
+        }
+        query.execute(tx.conn()).await?;
+
+        Ok(rows.into_iter().map(ExportJobRecord::from).collect())
+    }
+}
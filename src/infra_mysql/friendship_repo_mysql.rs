@@ -22,6 +22,7 @@ impl FriendshipRepo for MySqlFriendshipRepo {
         a: UserId,
         b: UserId,
         requested_by: UserId,
+        key: IdempotencyKey,
     ) -> Result<FriendshipIdemClaim, RelationError> {
         if a == b {
             return Err(RelationError::Store(
@@ -36,19 +37,40 @@ impl FriendshipRepo for MySqlFriendshipRepo {
 
         let res = sqlx::query(
             r#"
-INSERT INTO friendship (user_min, user_max, status, requested_by)
-VALUES (?, ?, 'accepted', ?)
+INSERT INTO friendship (user_min, user_max, status, requested_by, idem_key)
+VALUES (?, ?, 'accepted', ?, ?)
 "#,
         )
         .bind(pair.min())
         .bind(pair.max())
         .bind(requested_by)
+        .bind(key)
         .execute(&self.pool)
         .await;
 
         match res {
             Ok(_) => Ok(FriendshipIdemClaim::Won),
-            Err(e) if is_dup_key(&e) => Ok(FriendshipIdemClaim::Existing),
+            Err(e) if is_dup_key(&e) => {
+                let row = sqlx::query(
+                    "SELECT requested_by, idem_key FROM friendship WHERE user_min=? AND user_max=?",
+                )
+                .bind(pair.min())
+                .bind(pair.max())
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| RelationError::Store(format!("friendship idem select: {e}")))?;
+
+                let claimed_by = row
+                    .try_get::<UserId, _>("requested_by")
+                    .map_err(|e| RelationError::Store(format!("uuid decode: {e}")))?;
+                let claimed_key = row
+                    .try_get::<Option<IdempotencyKey>, _>("idem_key")
+                    .map_err(|e| RelationError::Store(format!("uuid decode: {e}")))?;
+
+                Ok(FriendshipIdemClaim::Existing {
+                    replay: claimed_by == requested_by && claimed_key == Some(key),
+                })
+            }
             Err(e) => Err(RelationError::Store(format!("friendship idem insert: {e}"))),
         }
     }
@@ -88,13 +110,16 @@ VALUES (?, ?, 'accepted', ?)
         a: UserId,
         b: UserId,
     ) -> Result<ConversationId, RelationError> {
+        let pair = UserPair::new(a, b);
+
         let row =
             sqlx::query("SELECT conversation_id FROM direct_pair WHERE user_min=? AND user_max=?")
-                .bind(a)
-                .bind(b)
-                .fetch_one(&self.pool)
+                .bind(pair.min())
+                .bind(pair.max())
+                .fetch_optional(&self.pool)
                 .await
-                .map_err(|e| RelationError::Store(format!("select direct conversation: {e}")))?;
+                .map_err(|e| RelationError::Store(format!("select direct conversation: {e}")))?
+                .ok_or(RelationError::NotFriends)?;
 
         let conv_id = row
             .try_get::<ConversationId, _>("conversation_id")
@@ -110,6 +135,7 @@ VALUES (?, ?, 'accepted', ?)
         after: Option<FriendCursor>,
     ) -> Result<Vec<FriendSummary>, RelationError> {
         // Mapped from SQL
+        #[derive(sqlx::FromRow)]
         struct Row {
             other_user: UserId,
             username: String,
@@ -117,16 +143,19 @@ VALUES (?, ?, 'accepted', ?)
             since: DateTime<Utc>,
         }
 
-        // Without cursor
-        if after.is_none() {
-            let rows = sqlx::query_as!(
-                Row,
-                r#"
+        // deleted accounts drop out of the list; the friendship row stays for history
+        let cursor_filter = if after.is_some() {
+            "AND (f.created_at < ? OR (f.created_at = ? AND u.user_id < ?))"
+        } else {
+            ""
+        };
+        let sql = format!(
+            r#"
 SELECT
-    IF(? = f.user_min, f.user_max, f.user_min) AS "other_user: UserId",
-    u.username                                 AS username,
-    dp.conversation_id                         AS "conversation_id: ConversationId",
-    f.created_at                               AS "since: DateTime<Utc>"
+    u.user_id          AS other_user,
+    u.username         AS username,
+    dp.conversation_id AS conversation_id,
+    f.created_at       AS since
 FROM friendship f
 JOIN direct_pair dp
   ON dp.user_min = f.user_min AND dp.user_max = f.user_max
@@ -134,72 +163,26 @@ JOIN user u
   ON u.user_id = IF(? = f.user_min, f.user_max, f.user_min)
 WHERE f.status = 'accepted'
   AND (? = f.user_min OR ? = f.user_max)
+  AND u.deleted_at IS NULL
+  {cursor_filter}
 ORDER BY f.created_at DESC,
-         u.username ASC
+         u.user_id DESC
 LIMIT ?
-"#,
-                user_id,
-                user_id,
-                user_id,
-                user_id,
-                page_size.0 as i64
-            )
+"#
+        );
+
+        let mut query = sqlx::query_as::<_, Row>(&sql)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id);
+        if let Some(cur) = after {
+            query = query.bind(cur.since).bind(cur.since).bind(cur.other_user);
+        }
+        let rows = query
+            .bind(page_size.0 as i64)
             .fetch_all(&self.pool)
             .await
-            .map_err(|e| RelationError::Store(format!("list friends (no cursor): {e}")))?;
-
-            let out = rows
-                .into_iter()
-                .map(|r| FriendSummary {
-                    user_id: r.other_user,
-                    username: r.username,
-                    conversation_id: r.conversation_id,
-                    since: r.since,
-                })
-                .collect();
-
-            return Ok(out);
-        }
-
-        // With cursor
-        let cur = after.unwrap();
-
-        let rows = sqlx::query_as!(
-            Row,
-            r#"
-SELECT
-    IF(? = f.user_min, f.user_max, f.user_min) AS "other_user: UserId",
-    u.username                                 AS username,
-    dp.conversation_id                         AS "conversation_id: ConversationId",
-    f.created_at                               AS "since: DateTime<Utc>"
-FROM friendship f
-JOIN direct_pair dp
-  ON dp.user_min = f.user_min AND dp.user_max = f.user_max
-JOIN user u
-ON u.user_id = IF(? = f.user_min, f.user_max, f.user_min)
-WHERE f.status = 'accepted'
-  AND (? = f.user_min OR ? = f.user_max)
-  AND (
-      f.created_at < ?
-      OR (f.created_at = ? AND IF(? = f.user_min, f.user_max, f.user_min) < ?)
-  )
-ORDER BY f.created_at DESC,
-         u.username ASC
-LIMIT ?
-"#,
-            user_id,
-            user_id,
-            user_id,
-            cur.since,
-            cur.since,
-            user_id,
-            cur.other_user,
-            user_id,
-            page_size.0 as i64
-        )
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| RelationError::Store(format!("list friends (with cursor): {e}")))?;
+            .map_err(|e| RelationError::Store(format!("list friends: {e}")))?;
 
         let out = rows
             .into_iter()
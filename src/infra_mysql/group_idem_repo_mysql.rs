@@ -3,6 +3,7 @@ use crate::application_port::*;
 use crate::domain_model::*;
 use crate::domain_port::*;
 use sqlx::{MySqlPool, Row};
+use std::time::Duration;
 
 pub struct MySqlGroupIdemRepo {
     pool: MySqlPool,
@@ -52,7 +53,7 @@ WHERE owner_id=? AND idem_key=?
                 .map_err(|e| RelationError::Store(format!("idem select: {e}")))?;
 
                 let gid = row
-                    .try_get::<GroupId, _>("group_id")
+                    .try_get::<GroupId, _>("proposed_group")
                     .map_err(|e| RelationError::Store(format!("uuid decode: {e}")))?;
 
                 let status = match row
@@ -88,14 +89,14 @@ WHERE owner_id=? AND idem_key=?
     ) -> Result<(), RelationError> {
         sqlx::query(
             r#"
-UPDATE group_create_idem SET status='succeeded'
-WHERE owner_id=? AND idem_key=? AND proposed_group=? AND conversation_id=?
+UPDATE group_create_idem SET status='succeeded', conversation_id=?
+WHERE owner_id=? AND idem_key=? AND proposed_group=?
 "#,
         )
+        .bind(conversation_id)
         .bind(owner)
         .bind(key)
         .bind(group_id)
-        .bind(conversation_id)
         .execute(&self.pool)
         .await
         .map_err(|e| RelationError::Store(format!("idem mark_succeeded: {e}")))?;
@@ -125,4 +126,45 @@ WHERE owner_id=? AND idem_key=? AND proposed_group=?
 
         Ok(())
     }
+
+    async fn retake_stale(
+        &self,
+        owner: UserId,
+        key: IdempotencyKey,
+        group_id: GroupId,
+        stale_after: Duration,
+    ) -> Result<bool, RelationError> {
+        let res = sqlx::query(
+            r#"
+UPDATE group_create_idem SET claimed_at=CURRENT_TIMESTAMP(6)
+WHERE owner_id=? AND idem_key=? AND proposed_group=? AND status='pending'
+  AND claimed_at < CURRENT_TIMESTAMP(6) - INTERVAL ? MICROSECOND
+"#,
+        )
+        .bind(owner)
+        .bind(key)
+        .bind(group_id)
+        .bind(stale_after.as_micros() as u64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RelationError::Store(format!("idem retake: {e}")))?;
+
+        Ok(res.rows_affected() == 1)
+    }
+
+    async fn purge_expired(&self, older_than: Duration) -> Result<u64, RelationError> {
+        let res = sqlx::query(
+            r#"
+DELETE FROM group_create_idem
+WHERE created_at < CURRENT_TIMESTAMP(6) - INTERVAL ? MICROSECOND
+LIMIT 1000
+"#,
+        )
+        .bind(older_than.as_micros() as u64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RelationError::Store(format!("idem purge: {e}")))?;
+
+        Ok(res.rows_affected())
+    }
 }
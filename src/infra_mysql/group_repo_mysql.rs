@@ -3,7 +3,12 @@ use crate::application_port::*;
 use crate::domain_model::*;
 use crate::domain_port::*;
 use chrono::{DateTime, Utc};
-use sqlx::{MySqlPool, Row};
+use sqlx::{Connection, MySql, MySqlConnection, MySqlPool, QueryBuilder, Row};
+
+/// MySQL advisory lock held by the node running `reconcile_member_counts`.
+const RECONCILE_LOCK: &str = "counterpoint.reconcile_member_counts";
+/// Groups listed per round trip while reconciling.
+const RECONCILE_PAGE: u32 = 500;
 
 pub struct MySqlGroupRepo {
     pool: MySqlPool,
@@ -13,6 +18,71 @@ impl MySqlGroupRepo {
     pub fn new(pool: MySqlPool) -> Self {
         Self { pool }
     }
+
+    /// One transaction per group, locking its row before counting, the same
+    /// order membership changes take; the count is then exact, as a change
+    /// that already inserted members waits for the row lock to bump it.
+    async fn reconcile_each_group(conn: &mut MySqlConnection) -> Result<u64, RelationError> {
+        let store = |context: &'static str| {
+            move |e: sqlx::Error| RelationError::Store(format!("{context}: {e}"))
+        };
+
+        let mut fixed = 0;
+        let mut after: Option<GroupId> = None;
+        loop {
+            let page: Vec<GroupId> = sqlx::query_scalar(
+                r#"
+SELECT group_id FROM chat_group
+WHERE ? IS NULL OR group_id > ?
+ORDER BY group_id
+LIMIT ?
+"#,
+            )
+            .bind(after)
+            .bind(after)
+            .bind(RECONCILE_PAGE)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(store("list groups to reconcile"))?;
+
+            for group_id in &page {
+                let mut tx = conn.begin().await.map_err(store("begin reconcile"))?;
+                let stored: Option<(u32, ConversationId)> = sqlx::query_as(
+                    "SELECT member_count, conversation_id FROM chat_group WHERE group_id = ? FOR UPDATE",
+                )
+                .bind(group_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(store("lock group member count"))?;
+
+                if let Some((member_count, conversation_id)) = stored {
+                    let actual: i64 = sqlx::query_scalar(
+                        "SELECT COUNT(*) FROM conversation_member WHERE conversation_id = ?",
+                    )
+                    .bind(conversation_id)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(store("count group members"))?;
+
+                    if i64::from(member_count) != actual {
+                        sqlx::query("UPDATE chat_group SET member_count = ? WHERE group_id = ?")
+                            .bind(actual)
+                            .bind(group_id)
+                            .execute(&mut *tx)
+                            .await
+                            .map_err(store("fix group member count"))?;
+                        fixed += 1;
+                    }
+                }
+                tx.commit().await.map_err(store("commit reconcile"))?;
+            }
+
+            if page.len() < RECONCILE_PAGE as usize {
+                return Ok(fixed);
+            }
+            after = page.last().copied();
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -31,17 +101,17 @@ impl GroupRepo for MySqlGroupRepo {
 
         let tx = downcast(tx);
 
-        let row = sqlx::query_as!(
-            GroupRow,
+        let row = sqlx::query_as::<_, GroupRow>(
             r#"
-SELECT group_id AS "group_id: GroupId", group_name AS "group_name: String", conversation_id AS "conversation_id: ConversationId"
+SELECT group_id, group_name, conversation_id
 FROM chat_group
-WHERE group_id = ?
+WHERE group_id = ? AND deleted_at IS NULL
 "#,
-            group_id
-        ).fetch_optional(tx.conn())
-            .await
-            .map_err(|e| RelationError::Store(format!("group details of {group_id}: {e}")))?;
+        )
+        .bind(group_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| RelationError::Store(format!("group details of {group_id}: {e}")))?;
 
         row.map(|r| GroupShortSummary {
             group_id: r.group_id,
@@ -84,11 +154,13 @@ VALUES (?, ?, ?, ?, ?)
         &self,
         group_id: GroupId,
     ) -> Result<Option<ConversationId>, RelationError> {
-        let row = sqlx::query("SELECT conversation_id FROM chat_group WHERE group_id = ?")
-            .bind(group_id)
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(|e| RelationError::Store(format!("select chat_group: {e}")))?;
+        let row = sqlx::query(
+            "SELECT conversation_id FROM chat_group WHERE group_id = ? AND deleted_at IS NULL",
+        )
+        .bind(group_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RelationError::Store(format!("select chat_group: {e}")))?;
 
         let cid = row
             .map(|r| {
@@ -105,6 +177,7 @@ VALUES (?, ?, ?, ?, ?)
         user_id: UserId,
         page_size: PageSize,
         after: Option<GroupCursor>,
+        projection: GroupProjection,
     ) -> Result<Vec<GroupSummary>, RelationError> {
         #[derive(sqlx::FromRow)]
         struct GroupRow {
@@ -112,78 +185,59 @@ VALUES (?, ?, ?, ?, ?)
             group_name: String,
             conversation_id: ConversationId,
             created_at: DateTime<Utc>,
-            is_owner: i32,     // (cg.owner_id = ?) -> 0 or 1
-            member_count: i64, // COUNT(*) is BIGINT
+            is_owner: i32, // (cg.owner_id = ?) -> 0 or 1
+            member_count: Option<u32>,
+            avatar_url: Option<String>,
+            theme_color: Option<String>,
         }
 
         let ps = page_size.0 as i64;
 
-        let rows: Vec<GroupRow> = if let Some(cursor) = after {
-            // With cursor
-            sqlx::query_as::<_, GroupRow>(
-                r#"
+        let member_count = match projection {
+            GroupProjection::Lite => "NULL",
+            GroupProjection::WithMemberCount => "cg.member_count",
+        };
+        let cursor_filter = if after.is_some() {
+            "AND ((cg.created_at < ?) OR (cg.created_at = ? AND cg.group_id < ?))"
+        } else {
+            ""
+        };
+        let sql = format!(
+            r#"
 SELECT
     cg.group_id,
     cg.group_name,
     cg.conversation_id,
     cg.created_at,
     (cg.owner_id = ?) AS is_owner,
-    mc.member_count
+    {member_count} AS member_count,
+    cg.avatar_url,
+    cg.theme_color
 FROM chat_group cg
 JOIN conversation_member cm
   ON cm.conversation_id = cg.conversation_id
  AND cm.user_id = ?
-JOIN (
-    SELECT conversation_id, COUNT(*) AS member_count
-    FROM conversation_member
-    GROUP BY conversation_id
-) mc ON mc.conversation_id = cg.conversation_id
-WHERE
-    (cg.created_at < ?)
-    OR (cg.created_at = ? AND cg.group_id < ?)
+WHERE cg.deleted_at IS NULL
+{cursor_filter}
 ORDER BY cg.created_at DESC, cg.group_id DESC
 LIMIT ?
-"#,
-            )
-            .bind(user_id) // for (cg.owner_id = ?)
-            .bind(user_id) // for cm.user_id = ?
-            .bind(cursor.created_at) // cg.created_at < ?
-            .bind(cursor.created_at) // cg.created_at = ?
-            .bind(cursor.group_id) // cg.group_id < ?
-            .bind(ps)
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| RelationError::Store(format!("list_groups(after) query: {e}")))?
-        } else {
-            sqlx::query_as::<_, GroupRow>(
-                r#"
-SELECT
-  cg.group_id,
-  cg.group_name,
-  cg.conversation_id,
-  cg.created_at,
-  (cg.owner_id = ?) AS is_owner,
-  mc.member_count
-FROM chat_group cg
-JOIN conversation_member cm
-  ON cm.conversation_id = cg.conversation_id
- AND cm.user_id = ?
-JOIN (
-  SELECT conversation_id, COUNT(*) AS member_count
-  FROM conversation_member
-  GROUP BY conversation_id
-) mc ON mc.conversation_id = cg.conversation_id
-ORDER BY cg.created_at DESC, cg.group_id DESC
-LIMIT ?
-                "#,
-            )
+"#
+        );
+
+        let mut query = sqlx::query_as::<_, GroupRow>(&sql)
             .bind(user_id) // for (cg.owner_id = ?)
-            .bind(user_id) // for cm.user_id = ?
+            .bind(user_id); // for cm.user_id = ?
+        if let Some(cursor) = after {
+            query = query
+                .bind(cursor.created_at) // cg.created_at < ?
+                .bind(cursor.created_at) // cg.created_at = ?
+                .bind(cursor.group_id); // cg.group_id < ?
+        }
+        let rows = query
             .bind(ps)
             .fetch_all(&self.pool)
             .await
-            .map_err(|e| RelationError::Store(format!("list_groups(first) query: {e}")))?
-        };
+            .map_err(|e| RelationError::Store(format!("list_groups query: {e}")))?;
 
         let mut out = Vec::with_capacity(rows.len());
         for r in rows {
@@ -193,23 +247,121 @@ LIMIT ?
                 GroupMemberRole::Member
             };
 
-            let member_count = u32::try_from(r.member_count).map_err(|_| {
-                RelationError::Store(format!("member_count overflow: {}", r.member_count))
-            })?;
-
             out.push(GroupSummary {
                 group_id: r.group_id,
                 name: r.group_name,
                 my_role,
                 conversation_id: r.conversation_id,
-                member_count,
+                member_count: r.member_count,
                 created_at: r.created_at,
+                appearance: GroupAppearance {
+                    avatar_url: r.avatar_url,
+                    theme_color: r.theme_color,
+                },
             })
         }
 
         Ok(out)
     }
 
+    async fn count_members(
+        &self,
+        user_id: UserId,
+        group_ids: &[GroupId],
+    ) -> Result<Vec<GroupMemberCount>, RelationError> {
+        if group_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(
+            r#"
+SELECT cg.group_id, cg.member_count
+FROM chat_group cg
+WHERE EXISTS (
+    SELECT 1 FROM conversation_member me
+    WHERE me.conversation_id = cg.conversation_id AND me.user_id = "#,
+        );
+        query.push_bind(user_id);
+        query.push(") AND cg.deleted_at IS NULL AND cg.group_id IN (");
+        let mut ids = query.separated(", ");
+        for group_id in group_ids {
+            ids.push_bind(*group_id);
+        }
+        query.push(")");
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| RelationError::Store(format!("count_members query: {e}")))?;
+
+        rows.into_iter()
+            .map(|r| {
+                Ok(GroupMemberCount {
+                    group_id: r
+                        .try_get("group_id")
+                        .map_err(|e| RelationError::Store(format!("uuid group decode: {e}")))?,
+                    member_count: r
+                        .try_get("member_count")
+                        .map_err(|e| RelationError::Store(format!("u32 count decode: {e}")))?,
+                })
+            })
+            .collect()
+    }
+
+    async fn reconcile_member_counts(&self) -> Result<u64, RelationError> {
+        // an advisory lock lasts as long as the connection that took it, so
+        // one node reconciles at a time and a node that dies lets go of it
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| RelationError::Store(format!("reconcile member counts: {e}")))?;
+        let locked: Option<i64> = sqlx::query_scalar("SELECT GET_LOCK(?, 0)")
+            .bind(RECONCILE_LOCK)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| RelationError::Store(format!("take reconcile lock: {e}")))?;
+        if locked != Some(1) {
+            tracing::debug!("member counts are being reconciled elsewhere");
+            return Ok(0);
+        }
+
+        let fixed = Self::reconcile_each_group(&mut conn).await;
+
+        if let Err(e) = sqlx::query("DO RELEASE_LOCK(?)")
+            .bind(RECONCILE_LOCK)
+            .execute(&mut *conn)
+            .await
+        {
+            tracing::warn!("release reconcile lock: {e}");
+            // closing the connection releases it instead
+            conn.close_on_drop();
+        }
+        fixed
+    }
+
+    async fn disband_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        group_id: GroupId,
+    ) -> Result<bool, RelationError> {
+        let tx = downcast(tx);
+
+        let result = sqlx::query(
+            r#"
+UPDATE chat_group SET deleted_at = CURRENT_TIMESTAMP(6)
+WHERE group_id = ? AND deleted_at IS NULL
+"#,
+        )
+        .bind(group_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| RelationError::Store(format!("disband group {group_id}: {e}")))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     async fn list_group_members_in_tx(
         &self,
         tx: &mut dyn StorageTx<'_>,
@@ -294,4 +446,71 @@ LIMIT ?
 
         Ok(out)
     }
+
+    async fn get_appearance_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        group_id: GroupId,
+    ) -> Result<GroupAppearance, RelationError> {
+        let tx = downcast(tx);
+
+        let row: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+SELECT avatar_url, theme_color
+FROM chat_group
+WHERE group_id = ? AND deleted_at IS NULL
+"#,
+        )
+        .bind(group_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| RelationError::Store(format!("get appearance of {group_id}: {e}")))?;
+
+        let (avatar_url, theme_color) = row.ok_or(RelationError::GroupNotFound)?;
+        Ok(GroupAppearance {
+            avatar_url,
+            theme_color,
+        })
+    }
+
+    async fn set_appearance_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        group_id: GroupId,
+        appearance: &GroupAppearance,
+    ) -> Result<bool, RelationError> {
+        let tx = downcast(tx);
+
+        let row: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+SELECT avatar_url, theme_color
+FROM chat_group
+WHERE group_id = ? AND deleted_at IS NULL
+FOR UPDATE
+"#,
+        )
+        .bind(group_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| RelationError::Store(format!("lock group {group_id}: {e}")))?;
+        let (avatar_url, theme_color) = row.ok_or(RelationError::GroupNotFound)?;
+        if avatar_url == appearance.avatar_url && theme_color == appearance.theme_color {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            r#"
+UPDATE chat_group SET avatar_url = ?, theme_color = ?
+WHERE group_id = ?
+"#,
+        )
+        .bind(&appearance.avatar_url)
+        .bind(&appearance.theme_color)
+        .bind(group_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| RelationError::Store(format!("set appearance of {group_id}: {e}")))?;
+
+        Ok(true)
+    }
 }
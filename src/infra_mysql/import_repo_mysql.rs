@@ -0,0 +1,466 @@
+use super::util::downcast;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use sqlx::{MySql, QueryBuilder};
+use std::collections::HashSet;
+
+/// Rows per multi-row statement; keeps each well under the placeholder limit.
+const IMPORT_CHUNK: usize = 500;
+
+/// Works only through the caller's transaction, so it needs no pool.
+#[derive(Default)]
+pub struct MySqlImportRepo;
+
+impl MySqlImportRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+fn store_error(context: &'static str) -> impl Fn(sqlx::Error) -> ImportError {
+    move |e| ImportError::Store(format!("{context}: {e}"))
+}
+
+#[async_trait::async_trait]
+impl ImportRepo for MySqlImportRepo {
+    async fn existing_users_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_ids: &[UserId],
+    ) -> Result<HashSet<UserId>, ImportError> {
+        let tx = downcast(tx);
+
+        let mut existing = HashSet::new();
+        for chunk in user_ids.chunks(IMPORT_CHUNK) {
+            let mut query =
+                QueryBuilder::<MySql>::new("SELECT user_id FROM user WHERE user_id IN (");
+            let mut ids = query.separated(", ");
+            for user_id in chunk {
+                ids.push_bind(*user_id);
+            }
+            query.push(")");
+            let found: Vec<UserId> = query
+                .build_query_scalar()
+                .fetch_all(tx.conn())
+                .await
+                .map_err(store_error("select existing users"))?;
+            existing.extend(found);
+        }
+        Ok(existing)
+    }
+
+    async fn existing_conversations_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_ids: &[ConversationId],
+    ) -> Result<HashSet<ConversationId>, ImportError> {
+        let tx = downcast(tx);
+
+        let mut existing = HashSet::new();
+        for chunk in conversation_ids.chunks(IMPORT_CHUNK) {
+            let mut query = QueryBuilder::<MySql>::new(
+                "SELECT conversation_id FROM conversation WHERE conversation_id IN (",
+            );
+            let mut ids = query.separated(", ");
+            for conversation_id in chunk {
+                ids.push_bind(*conversation_id);
+            }
+            query.push(")");
+            let found: Vec<ConversationId> = query
+                .build_query_scalar()
+                .fetch_all(tx.conn())
+                .await
+                .map_err(store_error("select existing conversations"))?;
+            existing.extend(found);
+        }
+        Ok(existing)
+    }
+
+    async fn insert_users_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        users: &[ImportUser],
+    ) -> Result<u64, ImportError> {
+        let tx = downcast(tx);
+
+        let mut inserted = 0;
+        for chunk in users.chunks(IMPORT_CHUNK) {
+            // `user.username` has no unique key; only signup checks it
+            let mut query =
+                QueryBuilder::<MySql>::new("SELECT username FROM user WHERE username IN (");
+            let mut names = query.separated(", ");
+            for user in chunk {
+                names.push_bind(&user.username);
+            }
+            query.push(")");
+            let mut taken: HashSet<String> = query
+                .build_query_scalar()
+                .fetch_all(tx.conn())
+                .await
+                .map_err(store_error("select taken usernames"))?
+                .into_iter()
+                .collect();
+            let fresh: Vec<&ImportUser> = chunk
+                .iter()
+                .filter(|user| taken.insert(user.username.clone()))
+                .collect();
+            if fresh.is_empty() {
+                continue;
+            }
+
+            let mut rows = QueryBuilder::<MySql>::new(
+                "INSERT IGNORE INTO user (user_id, username, created_at) ",
+            );
+            rows.push_values(&fresh, |mut b, user| {
+                b.push_bind(user.user_id)
+                    .push_bind(&user.username)
+                    .push_bind(user.created_at);
+            });
+            inserted += rows
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("insert users"))?
+                .rows_affected();
+
+            let with_password: Vec<&&ImportUser> = fresh
+                .iter()
+                .filter(|user| user.password_hash.is_some())
+                .collect();
+            if with_password.is_empty() {
+                continue;
+            }
+            let mut credentials = QueryBuilder::<MySql>::new(
+                "INSERT IGNORE INTO auth_credential (user_id, username, password_hash, created_at) ",
+            );
+            credentials.push_values(with_password, |mut b, user| {
+                b.push_bind(user.user_id)
+                    .push_bind(&user.username)
+                    .push_bind(
+                        user.password_hash
+                            .as_ref()
+                            .map(|hash| hash.expose().clone()),
+                    )
+                    .push_bind(user.created_at);
+            });
+            credentials
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("insert credentials"))?;
+        }
+        Ok(inserted)
+    }
+
+    async fn insert_friendships_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        friendships: &[ImportFriendship],
+    ) -> Result<u64, ImportError> {
+        let tx = downcast(tx);
+
+        let mut inserted = 0;
+        for chunk in friendships.chunks(IMPORT_CHUNK) {
+            // a pair that already has a conversation keeps it; importing a
+            // second would leave an orphan
+            let mut query = QueryBuilder::<MySql>::new(
+                "SELECT user_min, user_max FROM direct_pair WHERE (user_min, user_max) IN (",
+            );
+            let mut pairs = query.separated(", ");
+            for friendship in chunk {
+                let pair = UserPair::new(friendship.a, friendship.b);
+                pairs.push("(");
+                pairs.push_bind_unseparated(pair.min());
+                pairs.push_unseparated(", ");
+                pairs.push_bind_unseparated(pair.max());
+                pairs.push_unseparated(")");
+            }
+            query.push(")");
+            let mut seen: HashSet<(UserId, UserId)> = query
+                .build_query_as::<(UserId, UserId)>()
+                .fetch_all(tx.conn())
+                .await
+                .map_err(store_error("select existing pairs"))?
+                .into_iter()
+                .collect();
+            let fresh: Vec<(UserPair, &ImportFriendship)> = chunk
+                .iter()
+                .map(|friendship| (UserPair::new(friendship.a, friendship.b), friendship))
+                .filter(|(pair, _)| seen.insert((pair.min(), pair.max())))
+                .collect();
+            if fresh.is_empty() {
+                continue;
+            }
+
+            let mut conversations = QueryBuilder::<MySql>::new(
+                "INSERT IGNORE INTO conversation (conversation_id, kind_id, created_at, members_version) ",
+            );
+            conversations.push_values(&fresh, |mut b, (_, friendship)| {
+                b.push_bind(friendship.conversation_id)
+                    .push("1")
+                    .push_bind(friendship.since)
+                    .push("1");
+            });
+            conversations
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("insert direct conversations"))?;
+
+            let mut members = QueryBuilder::<MySql>::new(
+                "INSERT IGNORE INTO conversation_member (conversation_id, user_id, joined_at, added_version) ",
+            );
+            let both = fresh.iter().flat_map(|(pair, friendship)| {
+                [(pair.min(), *friendship), (pair.max(), *friendship)]
+            });
+            members.push_values(both, |mut b, (user_id, friendship)| {
+                b.push_bind(friendship.conversation_id)
+                    .push_bind(user_id)
+                    .push_bind(friendship.since)
+                    .push("1");
+            });
+            members
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("insert direct members"))?;
+
+            let mut direct = QueryBuilder::<MySql>::new(
+                "INSERT IGNORE INTO direct_pair (user_min, user_max, conversation_id) ",
+            );
+            direct.push_values(&fresh, |mut b, (pair, friendship)| {
+                b.push_bind(pair.min())
+                    .push_bind(pair.max())
+                    .push_bind(friendship.conversation_id);
+            });
+            direct
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("insert direct pairs"))?;
+
+            let mut rows = QueryBuilder::<MySql>::new(
+                "INSERT IGNORE INTO friendship (user_min, user_max, status, requested_by, created_at) ",
+            );
+            rows.push_values(&fresh, |mut b, (pair, friendship)| {
+                b.push_bind(pair.min())
+                    .push_bind(pair.max())
+                    .push("'accepted'")
+                    .push_bind(pair.min())
+                    .push_bind(friendship.since);
+            });
+            inserted += rows
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("insert friendships"))?
+                .rows_affected();
+        }
+        Ok(inserted)
+    }
+
+    async fn insert_groups_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        groups: &[ImportGroup],
+    ) -> Result<Vec<GroupId>, ImportError> {
+        let tx = downcast(tx);
+
+        let mut inserted = Vec::new();
+        for chunk in groups.chunks(IMPORT_CHUNK) {
+            let mut query =
+                QueryBuilder::<MySql>::new("SELECT group_id FROM chat_group WHERE group_id IN (");
+            let mut ids = query.separated(", ");
+            for group in chunk {
+                ids.push_bind(group.group_id);
+            }
+            query.push(")");
+            let mut seen: HashSet<GroupId> = query
+                .build_query_scalar()
+                .fetch_all(tx.conn())
+                .await
+                .map_err(store_error("select existing groups"))?
+                .into_iter()
+                .collect();
+            let fresh: Vec<&ImportGroup> = chunk
+                .iter()
+                .filter(|group| seen.insert(group.group_id))
+                .collect();
+            if fresh.is_empty() {
+                continue;
+            }
+
+            let mut conversations = QueryBuilder::<MySql>::new(
+                "INSERT IGNORE INTO conversation (conversation_id, kind_id, created_at, members_version) ",
+            );
+            conversations.push_values(&fresh, |mut b, group| {
+                b.push_bind(group.conversation_id)
+                    .push("2")
+                    .push_bind(group.created_at)
+                    .push("1");
+            });
+            conversations
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("insert group conversations"))?;
+
+            let mut rows = QueryBuilder::<MySql>::new(
+                "INSERT IGNORE INTO chat_group (group_id, owner_id, group_name, description, created_at, conversation_id, member_count) ",
+            );
+            rows.push_values(&fresh, |mut b, group| {
+                b.push_bind(group.group_id)
+                    .push_bind(group.owner)
+                    .push_bind(&group.name)
+                    .push_bind(&group.description)
+                    .push_bind(group.created_at)
+                    .push_bind(group.conversation_id)
+                    .push_bind(group_members(group).count() as u32);
+            });
+            rows.build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("insert groups"))?;
+
+            // none of these existed before, so every one found is new; one
+            // whose conversation id was taken is not found
+            let mut query =
+                QueryBuilder::<MySql>::new("SELECT group_id FROM chat_group WHERE group_id IN (");
+            let mut ids = query.separated(", ");
+            for group in &fresh {
+                ids.push_bind(group.group_id);
+            }
+            query.push(")");
+            let created: HashSet<GroupId> = query
+                .build_query_scalar()
+                .fetch_all(tx.conn())
+                .await
+                .map_err(store_error("select inserted groups"))?
+                .into_iter()
+                .collect();
+            let created: Vec<&ImportGroup> = fresh
+                .into_iter()
+                .filter(|group| created.contains(&group.group_id))
+                .collect();
+
+            let memberships: Vec<(&ImportGroup, UserId)> = created
+                .iter()
+                .flat_map(|group| group_members(group).map(move |user_id| (*group, user_id)))
+                .collect();
+            for members in memberships.chunks(IMPORT_CHUNK) {
+                let mut rows = QueryBuilder::<MySql>::new(
+                    "INSERT IGNORE INTO conversation_member (conversation_id, user_id, joined_at, added_version) ",
+                );
+                rows.push_values(members, |mut b, (group, user_id)| {
+                    b.push_bind(group.conversation_id)
+                        .push_bind(*user_id)
+                        .push_bind(group.created_at)
+                        .push("1");
+                });
+                rows.build()
+                    .execute(tx.conn())
+                    .await
+                    .map_err(store_error("insert group members"))?;
+            }
+
+            inserted.extend(created.iter().map(|group| group.group_id));
+        }
+        Ok(inserted)
+    }
+
+    async fn settle_messages_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_ids: &[ConversationId],
+    ) -> Result<(), ImportError> {
+        let tx = downcast(tx);
+
+        for chunk in conversation_ids.chunks(IMPORT_CHUNK) {
+            let in_chunk = |query: &mut QueryBuilder<'_, MySql>| {
+                query.push("conversation_id IN (");
+                let mut ids = query.separated(", ");
+                for conversation_id in chunk {
+                    ids.push_bind(*conversation_id);
+                }
+                query.push(")");
+            };
+
+            // an imported row gets its place among the sender's messages by
+            // offset; rows already numbered keep theirs. The window function
+            // keeps the derived table from being merged into the update
+            let mut seqs = QueryBuilder::<MySql>::new(
+                r#"
+UPDATE message m
+JOIN (SELECT conversation_id, message_offset,
+             ROW_NUMBER() OVER (PARTITION BY conversation_id, sender_id ORDER BY message_offset) AS seq
+      FROM message
+      WHERE "#,
+            );
+            in_chunk(&mut seqs);
+            seqs.push(
+                r#") s
+  ON s.conversation_id = m.conversation_id AND s.message_offset = m.message_offset
+SET m.sender_seq = s.seq
+WHERE m.sender_seq = 0
+"#,
+            );
+            seqs.build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("number sender sequences"))?;
+
+            let mut counts = QueryBuilder::<MySql>::new(
+                r#"
+UPDATE conversation_member cm
+JOIN (SELECT conversation_id, sender_id, MAX(sender_seq) AS seq
+      FROM message
+      WHERE "#,
+            );
+            in_chunk(&mut counts);
+            counts.push(
+                r#"
+      GROUP BY conversation_id, sender_id) s
+  ON s.conversation_id = cm.conversation_id AND s.sender_id = cm.user_id
+SET cm.sent_count = GREATEST(cm.sent_count, s.seq)
+"#,
+            );
+            counts
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("raise sent counts"))?;
+
+            let mut last = QueryBuilder::<MySql>::new(
+                r#"
+UPDATE conversation c
+JOIN (SELECT conversation_id, MAX(message_offset) AS last_off, MAX(created_at) AS last_at
+      FROM message
+      WHERE "#,
+            );
+            in_chunk(&mut last);
+            last.push(
+                r#"
+      GROUP BY conversation_id) s
+  ON s.conversation_id = c.conversation_id
+SET c.last_msg_off   = GREATEST(c.last_msg_off, s.last_off),
+    c.offset_ceiling = GREATEST(c.offset_ceiling, s.last_off),
+    c.last_msg_at    = GREATEST(COALESCE(c.last_msg_at, s.last_at), s.last_at)
+"#,
+            );
+            last.build()
+                .execute(tx.conn())
+                .await
+                .map_err(store_error("advance last message"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The owner, then everyone else listed, once each.
+fn group_members(group: &ImportGroup) -> impl Iterator<Item = UserId> + '_ {
+    let mut seen = HashSet::new();
+    std::iter::once(group.owner)
+        .chain(group.members.iter().copied())
+        .filter(move |user_id| seen.insert(*user_id))
+}
@@ -0,0 +1,133 @@
+use super::util::downcast;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+
+#[derive(sqlx::FromRow)]
+struct IncomingWebhookRow {
+    incoming_webhook_id: IncomingWebhookId,
+    conversation_id: ConversationId,
+    name: String,
+    bot_user_id: UserId,
+    created_by: UserId,
+    created_at: DateTime<Utc>,
+}
+
+impl From<IncomingWebhookRow> for IncomingWebhook {
+    fn from(r: IncomingWebhookRow) -> Self {
+        IncomingWebhook {
+            incoming_webhook_id: r.incoming_webhook_id,
+            conversation_id: r.conversation_id,
+            name: r.name,
+            bot_user_id: r.bot_user_id,
+            created_by: r.created_by,
+            created_at: r.created_at,
+        }
+    }
+}
+
+/// Works only through the caller's transaction, so it needs no pool.
+#[derive(Default)]
+pub struct MySqlIncomingWebhookRepo;
+
+impl MySqlIncomingWebhookRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl IncomingWebhookRepo for MySqlIncomingWebhookRepo {
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        webhook: &IncomingWebhook,
+        token_hash: &str,
+    ) -> anyhow::Result<()> {
+        let tx = downcast(tx);
+
+        sqlx::query(
+            r#"
+INSERT INTO incoming_webhook (incoming_webhook_id, conversation_id, name, bot_user_id, token_hash,
+                              created_by, created_at)
+VALUES (?, ?, ?, ?, ?, ?, ?)
+"#,
+        )
+        .bind(webhook.incoming_webhook_id)
+        .bind(webhook.conversation_id)
+        .bind(&webhook.name)
+        .bind(webhook.bot_user_id)
+        .bind(token_hash)
+        .bind(webhook.created_by)
+        .bind(webhook.created_at)
+        .execute(tx.conn())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> anyhow::Result<Vec<IncomingWebhook>> {
+        let tx = downcast(tx);
+
+        let rows: Vec<IncomingWebhookRow> = sqlx::query_as(
+            r#"
+SELECT incoming_webhook_id, conversation_id, name, bot_user_id, created_by, created_at
+FROM incoming_webhook
+WHERE conversation_id = ?
+ORDER BY created_at, incoming_webhook_id
+"#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx.conn())
+        .await?;
+
+        Ok(rows.into_iter().map(IncomingWebhook::from).collect())
+    }
+
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        incoming_webhook_id: IncomingWebhookId,
+    ) -> anyhow::Result<bool> {
+        let tx = downcast(tx);
+
+        let result = sqlx::query(
+            r#"
+DELETE FROM incoming_webhook
+WHERE conversation_id = ? AND incoming_webhook_id = ?
+"#,
+        )
+        .bind(conversation_id)
+        .bind(incoming_webhook_id)
+        .execute(tx.conn())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_by_token_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        token_hash: &str,
+    ) -> anyhow::Result<Option<IncomingWebhook>> {
+        let tx = downcast(tx);
+
+        let row: Option<IncomingWebhookRow> = sqlx::query_as(
+            r#"
+SELECT incoming_webhook_id, conversation_id, name, bot_user_id, created_by, created_at
+FROM incoming_webhook
+WHERE token_hash = ?
+"#,
+        )
+        .bind(token_hash)
+        .fetch_optional(tx.conn())
+        .await?;
+
+        Ok(row.map(IncomingWebhook::from))
+    }
+}
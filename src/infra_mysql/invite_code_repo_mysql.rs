@@ -0,0 +1,104 @@
+use super::util::downcast;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+use sqlx::{MySql, MySqlPool, QueryBuilder};
+
+pub struct MySqlInviteCodeRepo {
+    pool: MySqlPool,
+}
+
+impl MySqlInviteCodeRepo {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl InviteCodeRepo for MySqlInviteCodeRepo {
+    async fn insert(&self, codes: &[InviteCode]) -> Result<(), AuthError> {
+        if codes.is_empty() {
+            return Ok(());
+        }
+
+        let mut query = QueryBuilder::<MySql>::new(
+            "INSERT INTO invite_code (code, max_uses, uses, expires_at, minted_by, created_at) ",
+        );
+        query.push_values(codes, |mut row, invite| {
+            row.push_bind(&invite.code)
+                .push_bind(invite.max_uses)
+                .push_bind(invite.uses)
+                .push_bind(invite.expires_at)
+                .push_bind(&invite.minted_by)
+                .push_bind(invite.created_at);
+        });
+        query
+            .build()
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AuthError::Store(format!("insert invite codes: {e}")))?;
+
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<InviteCode>, AuthError> {
+        #[allow(clippy::type_complexity)]
+        let rows: Vec<(
+            String,
+            u32,
+            u32,
+            Option<DateTime<Utc>>,
+            String,
+            DateTime<Utc>,
+        )> = sqlx::query_as(
+            r#"
+SELECT code, max_uses, uses, expires_at, minted_by, created_at
+FROM invite_code
+ORDER BY created_at DESC, code
+"#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuthError::Store(format!("list invite codes: {e}")))?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(code, max_uses, uses, expires_at, minted_by, created_at)| InviteCode {
+                    code,
+                    max_uses,
+                    uses,
+                    expires_at,
+                    minted_by,
+                    created_at,
+                },
+            )
+            .collect())
+    }
+
+    async fn redeem_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        code: &str,
+    ) -> Result<bool, AuthError> {
+        let tx = downcast(tx);
+
+        // one conditional update, so concurrent signups can't overdraw a code
+        let result = sqlx::query(
+            r#"
+UPDATE invite_code
+SET uses = uses + 1
+WHERE code = ?
+  AND uses < max_uses
+  AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP(6))
+"#,
+        )
+        .bind(code)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| AuthError::Store(format!("redeem invite code: {e}")))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,84 @@
+use super::util::downcast;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use sqlx::{MySqlExecutor, MySqlPool, Row};
+
+pub struct MySqlKeyRepo {
+    pool: MySqlPool,
+}
+
+impl MySqlKeyRepo {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// With `lock`, a locking read: it sees the latest committed row, which a
+/// transaction's snapshot may predate.
+async fn select_key<'c>(
+    conn: impl MySqlExecutor<'c>,
+    conversation_id: ConversationId,
+    lock: bool,
+) -> anyhow::Result<Option<WrappedKey>> {
+    let sql = format!(
+        r#"
+SELECT kms_key_id, wrapped_key FROM conversation_key
+WHERE conversation_id = ?
+{}"#,
+        if lock { "FOR SHARE" } else { "" }
+    );
+    let row = sqlx::query(&sql)
+        .bind(conversation_id)
+        .fetch_optional(conn)
+        .await?;
+
+    row.map(|row| {
+        Ok(WrappedKey {
+            key_id: row.try_get("kms_key_id")?,
+            wrapped: row.try_get("wrapped_key")?,
+        })
+    })
+    .transpose()
+}
+
+#[async_trait::async_trait]
+impl KeyRepo for MySqlKeyRepo {
+    async fn get(&self, conversation_id: ConversationId) -> anyhow::Result<Option<WrappedKey>> {
+        select_key(&self.pool, conversation_id, false).await
+    }
+
+    async fn get_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> anyhow::Result<Option<WrappedKey>> {
+        select_key(downcast(tx).conn(), conversation_id, false).await
+    }
+
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        key: &WrappedKey,
+    ) -> anyhow::Result<WrappedKey> {
+        let tx = downcast(tx);
+
+        // a concurrent insert waits on the row lock, then is ignored
+        sqlx::query(
+            r#"
+INSERT IGNORE INTO conversation_key (conversation_id, kms_key_id, wrapped_key)
+VALUES (?, ?, ?)
+"#,
+        )
+        .bind(conversation_id)
+        .bind(&key.key_id)
+        .bind(&key.wrapped)
+        .execute(tx.conn())
+        .await?;
+
+        // the winner's row, when a racing insert made this one a no-op
+        select_key(tx.conn(), conversation_id, true)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("conversation key vanished after insert"))
+    }
+}
@@ -0,0 +1,72 @@
+use super::util::downcast;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+use std::net::IpAddr;
+
+/// Works only through the caller's transaction, so it needs no pool.
+#[derive(Default)]
+pub struct MySqlLoginLocationRepo;
+
+impl MySqlLoginLocationRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginLocationRepo for MySqlLoginLocationRepo {
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<Vec<String>, AuthError> {
+        let tx = downcast(tx);
+
+        // locked, so two logins from one new place alert only once
+        sqlx::query_scalar(
+            r#"
+SELECT location
+FROM login_location
+WHERE user_id = ?
+FOR UPDATE
+"#,
+        )
+        .bind(user_id.0.as_bytes() as &[u8])
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| AuthError::Store(format!("list login locations: {e}")))
+    }
+
+    async fn record_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        location: &str,
+        ip: IpAddr,
+        at: DateTime<Utc>,
+    ) -> Result<(), AuthError> {
+        let tx = downcast(tx);
+
+        sqlx::query(
+            r#"
+INSERT INTO login_location (user_id, location, last_ip, first_seen_at, last_seen_at, logins)
+VALUES (?, ?, ?, ?, ?, 1)
+ON DUPLICATE KEY UPDATE last_ip      = VALUES(last_ip),
+                        last_seen_at = VALUES(last_seen_at),
+                        logins       = logins + 1
+"#,
+        )
+        .bind(user_id.0.as_bytes() as &[u8])
+        .bind(location)
+        .bind(ip.to_string())
+        .bind(at)
+        .bind(at)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| AuthError::Store(format!("record login location: {e}")))?;
+
+        Ok(())
+    }
+}
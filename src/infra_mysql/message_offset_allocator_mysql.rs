@@ -0,0 +1,51 @@
+use super::util::downcast;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+
+/// Allocates offsets from the conversation row itself, inside the send
+/// transaction. The row lock serializes senders of the same conversation.
+#[derive(Default)]
+pub struct MySqlOffsetAllocator;
+
+impl MySqlOffsetAllocator {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageOffsetAllocator for MySqlOffsetAllocator {
+    async fn allocate_in_tx(
+        &self,
+        tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        at: DateTime<Utc>,
+    ) -> Result<MessageOffset, ChatError> {
+        let tx = downcast(tx);
+
+        // Grab the next offset and advance the last-message pointers in one statement.
+        // offset_ceiling covers offsets handed out by an external allocator.
+        // NOTE: last_msg_at can be NULL
+        let res = sqlx::query(
+            r#"
+UPDATE conversation
+SET last_msg_off = LAST_INSERT_ID(GREATEST(last_msg_off, offset_ceiling) + 1),
+    last_msg_at  = GREATEST(COALESCE(last_msg_at, TIMESTAMP('1970-01-01 00:00:00')), ?)
+WHERE conversation_id = ? AND deleted_at IS NULL
+"#,
+        )
+        .bind(at)
+        .bind(conversation_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("advance conversation offset: {e}")))?;
+        if res.rows_affected() == 0 {
+            // missing or soft-deleted
+            return Err(ChatError::ConversationNotFound);
+        }
+
+        Ok(MessageOffset(res.last_insert_id()))
+    }
+}
@@ -1,9 +1,16 @@
+use super::attachment_repo_mysql::{attachments_of, delete_attachments_of};
 use super::util::{downcast, is_dup_key};
 use crate::application_port::*;
 use crate::domain_model::*;
 use crate::domain_port::*;
+use crate::logger::Secret;
 use chrono::{DateTime, Utc};
-use sqlx::MySqlPool;
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+use sqlx::{MySql, MySqlConnection, MySqlPool, QueryBuilder};
+
+/// Rows per multi-row insert; keeps each statement well under the placeholder limit.
+const BULK_CHUNK: usize = 500;
 
 #[derive(sqlx::FromRow)]
 struct MessageRow {
@@ -12,7 +19,117 @@ struct MessageRow {
     message_offset: u64,
     sender_id: UserId,
     content: String,
+    content_encrypted: bool,
     created_at: DateTime<Utc>,
+    edited_at: Option<DateTime<Utc>>,
+    edit_count: u32,
+    deleted_at: Option<DateTime<Utc>>,
+    kind: String,
+    reply_to: Option<MessageId>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PinnedRow {
+    #[sqlx(flatten)]
+    message: MessageRow,
+    pinned_by: UserId,
+    pinned_at: DateTime<Utc>,
+}
+
+/// A deleted message comes back as a tombstone, with empty content.
+impl From<MessageRow> for MessageRecord {
+    fn from(r: MessageRow) -> Self {
+        let content = if r.deleted_at.is_some() {
+            String::new()
+        } else {
+            r.content
+        };
+        MessageRecord {
+            message_id: r.message_id,
+            conversation_id: r.conversation_id,
+            message_offset: MessageOffset(r.message_offset),
+            sender: r.sender_id,
+            content: Secret::new(content),
+            content_encrypted: r.content_encrypted && r.deleted_at.is_none(),
+            created_at: r.created_at,
+            edited_at: r.edited_at,
+            edit_count: r.edit_count,
+            deleted_at: r.deleted_at,
+            kind: match r.kind.as_str() {
+                "system" => MessageKind::System,
+                _ => MessageKind::User,
+            },
+            reply_to: r.reply_to,
+            attachments: Vec::new(),
+        }
+    }
+}
+
+/// `rows` as records with their attachments; tombstones keep theirs hidden.
+async fn hydrate(
+    conn: &mut MySqlConnection,
+    rows: Vec<MessageRow>,
+) -> Result<Vec<MessageRecord>, ChatError> {
+    let mut records: Vec<MessageRecord> = rows.into_iter().map(MessageRecord::from).collect();
+    let live: Vec<MessageId> = records
+        .iter()
+        .filter(|record| record.deleted_at.is_none())
+        .map(|record| record.message_id)
+        .collect();
+    let mut attachments = attachments_of(conn, &live)
+        .await
+        .map_err(|e| ChatError::Store(format!("load attachments: {e}")))?;
+    for record in &mut records {
+        if let Some(found) = attachments.remove(&record.message_id) {
+            record.attachments = found;
+        }
+    }
+    Ok(records)
+}
+
+/// The stored message `message_id`, as a resend of it gets it back.
+async fn sent_message(
+    conn: &mut MySqlConnection,
+    message_id: MessageId,
+) -> Result<Option<SentMessage>, ChatError> {
+    let row = sqlx::query_as!(
+        MessageRow,
+        r#"
+SELECT message_id AS "message_id: MessageId",
+       conversation_id AS "conversation_id: ConversationId",
+       message_offset,
+       sender_id AS "sender_id: UserId",
+       content,
+       content_encrypted AS "content_encrypted: bool",
+       created_at AS "created_at: DateTime<Utc>",
+       edited_at AS "edited_at: DateTime<Utc>",
+       edit_count,
+       deleted_at AS "deleted_at: DateTime<Utc>",
+       kind,
+       reply_to AS "reply_to: MessageId"
+FROM message
+WHERE message_id = ?
+"#,
+        message_id,
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| ChatError::Store(format!("fetch sent message: {e}")))?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let sender_seq: u64 = sqlx::query_scalar("SELECT sender_seq FROM message WHERE message_id = ?")
+        .bind(message_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| ChatError::Store(format!("fetch sender seq: {e}")))?;
+
+    let record = hydrate(conn, vec![row]).await?.remove(0);
+    Ok(Some(SentMessage {
+        record,
+        sender_seq,
+        duplicate: true,
+    }))
 }
 
 pub struct MySqlMessageRepo {
@@ -30,113 +147,89 @@ impl MessageRepo for MySqlMessageRepo {
     async fn insert_in_tx<'t>(
         &self,
         tx: &mut dyn StorageTx<'t>,
-        conversation_id: ConversationId,
-        sender: UserId,
-        content: &str,
-        message_id: MessageId,
-    ) -> Result<MessageRecord, ChatError> {
-        let mut tx = downcast(tx);
-
-        // 1) Grab the next offset
-        let res = sqlx::query!(
-            r#"
-INSERT INTO conversation_counter (conversation_id, next_offset)
-VALUES (?, 1)
-ON DUPLICATE KEY UPDATE next_offset = LAST_INSERT_ID(next_offset + 1)
-"#,
-            conversation_id,
-        )
-        .execute(tx.conn())
-        .await
-        .map_err(|e| ChatError::Store(format!("update counter: {e}")))?;
-
-        let assigned_off = MessageOffset(res.last_insert_id());
+        record: &MessageRecord,
+        sender_seq: u64,
+    ) -> Result<SentMessage, ChatError> {
+        let tx = downcast(tx);
 
-        // 2) Insert message row
-        let insert_res = sqlx::query!(
+        let insert_res = sqlx::query(
             r#"
-INSERT INTO message (message_id, conversation_id, message_offset, sender_id, content)
-VALUES (?, ?, ?, ?, ?)
+INSERT INTO message (message_id, conversation_id, message_offset, sender_id, content, content_encrypted, created_at, sender_seq, kind, reply_to)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
 "#,
-            message_id,
-            conversation_id,
-            assigned_off,
-            sender,
-            content
         )
+        .bind(record.message_id)
+        .bind(record.conversation_id)
+        .bind(record.message_offset)
+        .bind(record.sender)
+        .bind(record.content.expose())
+        .bind(record.content_encrypted)
+        .bind(record.created_at)
+        .bind(sender_seq)
+        .bind(record.kind.as_str())
+        .bind(record.reply_to)
         .execute(tx.conn())
         .await;
 
-        // 3) Handle idempotency
-        let row: MessageRow = match insert_res {
-            Ok(_) => {
-                // Insert succeeded
-                sqlx::query_as!(
-                    MessageRow,
-                    r#"
-SELECT message_id AS "message_id: MessageId",
-       conversation_id AS "conversation_id: ConversationId",
-       message_offset,
-       sender_id AS "sender_id: UserId",
-       content,
-       created_at AS "created_at: DateTime<Utc>"
-FROM message
-WHERE message_id = ?
-"#,
-                    message_id,
-                )
-                .fetch_one(tx.conn())
-                .await
-                .map_err(|e| ChatError::Store(format!("fetch inserted message: {e}")))?
-            }
+        match insert_res {
+            // everything in the record is already known, no need to read it back
+            Ok(_) => Ok(SentMessage {
+                record: record.clone(),
+                sender_seq,
+                duplicate: false,
+            }),
             Err(e) if is_dup_key(&e) => {
-                // Existing message
-                sqlx::query_as!(
-                    MessageRow,
-                    r#"
-SELECT message_id AS "message_id: MessageId",
-       conversation_id AS "conversation_id: ConversationId",
-       message_offset,
-       sender_id AS "sender_id: UserId",
-       content,
-       created_at AS "created_at: DateTime<Utc>"
-FROM message
-WHERE message_id = ?
-"#,
-                    message_id,
-                )
-                .fetch_one(tx.conn())
-                .await
-                .map_err(|e| ChatError::Store(format!("fetch inserted message: {e}")))?
+                // a concurrent retry inserted it first; the caller rolls back,
+                // which returns the offset this attempt took
+                let sent = sent_message(tx.conn(), record.message_id)
+                    .await?
+                    .ok_or_else(|| {
+                        ChatError::Store("duplicate message vanished after insert".to_string())
+                    })?;
+                Ok(sent)
             }
-            Err(e) => return Err(ChatError::Store(format!("insert into message: {e}"))),
-        };
+            Err(e) => Err(ChatError::Store(format!("insert into message: {e}"))),
+        }
+    }
 
-        // 4) Advance conversation last pointers
-        // NOTE: last_msg_at can be NULL
-        sqlx::query!(
-            r#"
-UPDATE conversation
-SET last_msg_off = GREATEST(last_msg_off, ?),
-    last_msg_at  = GREATEST(COALESCE(last_msg_at, TIMESTAMP('1970-01-01 00:00:00')), ?)
-WHERE conversation_id = ?
-"#,
-            row.message_offset,
-            row.created_at,
-            row.conversation_id,
-        )
-        .execute(tx.conn())
-        .await
-        .map_err(|e| ChatError::Store(format!("advance conversation last: {e}")))?;
-
-        Ok(MessageRecord {
-            message_id: row.message_id,
-            conversation_id: row.conversation_id,
-            message_offset: MessageOffset(row.message_offset),
-            sender: row.sender_id,
-            content: row.content,
-            created_at: row.created_at,
-        })
+    async fn get_sent_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        message_id: MessageId,
+    ) -> Result<Option<SentMessage>, ChatError> {
+        let tx = downcast(tx);
+        sent_message(tx.conn(), message_id).await
+    }
+
+    async fn insert_batch_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        records: &[MessageRecord],
+    ) -> Result<u64, ChatError> {
+        let tx = downcast(tx);
+
+        let mut inserted = 0;
+        for chunk in records.chunks(BULK_CHUNK) {
+            let mut query = QueryBuilder::<MySql>::new(
+                "INSERT IGNORE INTO message (message_id, conversation_id, message_offset, sender_id, content, content_encrypted, created_at) ",
+            );
+            query.push_values(chunk, |mut b, record| {
+                b.push_bind(record.message_id)
+                    .push_bind(record.conversation_id)
+                    .push_bind(record.message_offset)
+                    .push_bind(record.sender)
+                    .push_bind(record.content.expose())
+                    .push_bind(record.content_encrypted)
+                    .push_bind(record.created_at);
+            });
+            inserted += query
+                .build()
+                .execute(tx.conn())
+                .await
+                .map_err(|e| ChatError::Store(format!("insert message batch: {e}")))?
+                .rows_affected();
+        }
+        Ok(inserted)
     }
 
     async fn list_before_in_tx<'t>(
@@ -165,7 +258,13 @@ SELECT message_id AS "message_id: MessageId",
        message_offset,
        sender_id AS "sender_id: UserId",
        content,
-       created_at AS "created_at: DateTime<Utc>"
+       content_encrypted AS "content_encrypted: bool",
+       created_at AS "created_at: DateTime<Utc>",
+       edited_at AS "edited_at: DateTime<Utc>",
+       edit_count,
+       deleted_at AS "deleted_at: DateTime<Utc>",
+       kind,
+       reply_to AS "reply_to: MessageId"
 FROM message
 WHERE conversation_id = ?
   AND message_offset < ?
@@ -191,7 +290,13 @@ SELECT message_id AS "message_id: MessageId",
        message_offset,
        sender_id AS "sender_id: UserId",
        content,
-       created_at AS "created_at: DateTime<Utc>"
+       content_encrypted AS "content_encrypted: bool",
+       created_at AS "created_at: DateTime<Utc>",
+       edited_at AS "edited_at: DateTime<Utc>",
+       edit_count,
+       deleted_at AS "deleted_at: DateTime<Utc>",
+       kind,
+       reply_to AS "reply_to: MessageId"
 FROM message
 WHERE conversation_id = ?
 ORDER BY message_offset DESC
@@ -208,18 +313,533 @@ LIMIT ?
             v
         };
 
-        let out = rows
+        hydrate(tx.conn(), rows).await
+    }
+
+    async fn list_after_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        after: MessageOffset,
+        page_size: PageSize,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let tx = downcast(tx);
+
+        let rows: Vec<MessageRow> = sqlx::query_as(
+            r#"
+SELECT message_id, conversation_id, message_offset, sender_id, content, content_encrypted, created_at,
+       edited_at, edit_count, deleted_at, kind, reply_to
+FROM message
+WHERE conversation_id = ? AND message_offset > ? AND deleted_at IS NULL
+ORDER BY message_offset
+LIMIT ?
+"#,
+        )
+        .bind(conversation_id)
+        .bind(after)
+        .bind(page_size.0 as i64)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("list after: {e}")))?;
+
+        hydrate(tx.conn(), rows).await
+    }
+
+    async fn list_thread_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        root: MessageId,
+        page_size: PageSize,
+        after: Option<OffsetCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let tx = downcast(tx);
+
+        // one range over ix_message_thread
+        let after = after.map(|cursor| cursor.offset);
+        let rows: Vec<MessageRow> = sqlx::query_as(
+            r#"
+SELECT message_id, conversation_id, message_offset, sender_id, content, content_encrypted, created_at,
+       edited_at, edit_count, deleted_at, kind, reply_to
+FROM message
+WHERE conversation_id = ? AND reply_to = ? AND (? IS NULL OR message_offset > ?)
+ORDER BY message_offset
+LIMIT ?
+"#,
+        )
+        .bind(conversation_id)
+        .bind(root)
+        .bind(after)
+        .bind(after)
+        .bind(page_size.0 as i64)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("list thread: {e}")))?;
+
+        hydrate(tx.conn(), rows).await
+    }
+
+    async fn search_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        terms: &[String],
+        filters: &SearchFilters,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let tx = downcast(tx);
+
+        // every term required; quoted, so operators in a term are literal
+        let against = terms
+            .iter()
+            .map(|term| format!("+\"{term}\""))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let before_at = before.map(|cursor| cursor.created_at);
+        let before_id = before.map(|cursor| cursor.message_id);
+        let rows: Vec<MessageRow> = sqlx::query_as(
+            r#"
+SELECT m.message_id, m.conversation_id, m.message_offset, m.sender_id, m.content, m.content_encrypted, m.created_at,
+       m.edited_at, m.edit_count, m.deleted_at, m.kind, m.reply_to
+FROM message m
+JOIN conversation_member cm ON cm.conversation_id = m.conversation_id AND cm.user_id = ?
+JOIN conversation c ON c.conversation_id = m.conversation_id AND c.deleted_at IS NULL
+WHERE MATCH (m.content) AGAINST (? IN BOOLEAN MODE)
+  AND m.deleted_at IS NULL
+  AND (? IS NULL OR m.conversation_id = ?)
+  AND (? IS NULL OR m.sender_id = ?)
+  AND (? IS NULL OR m.created_at >= ?)
+  AND (? IS NULL OR m.created_at < ?)
+  AND (? IS NULL OR m.created_at < ? OR (m.created_at = ? AND m.message_id < ?))
+ORDER BY m.created_at DESC, m.message_id DESC
+LIMIT ?
+"#,
+        )
+        .bind(user_id)
+        .bind(against)
+        .bind(filters.conversation_id)
+        .bind(filters.conversation_id)
+        .bind(filters.sender)
+        .bind(filters.sender)
+        .bind(filters.since)
+        .bind(filters.since)
+        .bind(filters.until)
+        .bind(filters.until)
+        .bind(before_at)
+        .bind(before_at)
+        .bind(before_at)
+        .bind(before_id)
+        .bind(page_size.0 as i64)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("search messages: {e}")))?;
+
+        hydrate(tx.conn(), rows).await
+    }
+
+    async fn lock_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_id: MessageId,
+    ) -> Result<Option<MessageRecord>, ChatError> {
+        let tx = downcast(tx);
+
+        let row: Option<MessageRow> = sqlx::query_as(
+            r#"
+SELECT message_id, conversation_id, message_offset, sender_id, content, content_encrypted, created_at,
+       edited_at, edit_count, deleted_at, kind, reply_to
+FROM message
+WHERE message_id = ? AND conversation_id = ?
+FOR UPDATE
+"#,
+        )
+        .bind(message_id)
+        .bind(conversation_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("lock message: {e}")))?;
+
+        match row {
+            Some(row) => Ok(hydrate(tx.conn(), vec![row]).await?.pop()),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        record: &MessageRecord,
+    ) -> Result<(), ChatError> {
+        let tx = downcast(tx);
+
+        sqlx::query(
+            r#"
+UPDATE message
+SET content = ?, content_encrypted = ?, edited_at = ?, edit_count = ?
+WHERE conversation_id = ? AND message_offset = ?
+"#,
+        )
+        .bind(record.content.expose())
+        .bind(record.content_encrypted)
+        .bind(record.edited_at)
+        .bind(record.edit_count)
+        .bind(record.conversation_id)
+        .bind(record.message_offset)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("update message: {e}")))?;
+
+        Ok(())
+    }
+
+    async fn mark_deleted_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+        at: DateTime<Utc>,
+    ) -> Result<(), ChatError> {
+        let tx = downcast(tx);
+
+        sqlx::query(
+            r#"
+UPDATE message
+SET deleted_at = ?
+WHERE conversation_id = ? AND message_offset = ? AND deleted_at IS NULL
+"#,
+        )
+        .bind(at)
+        .bind(conversation_id)
+        .bind(message_offset)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("delete message: {e}")))?;
+
+        Ok(())
+    }
+
+    async fn insert_mentions_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        record: &MessageRecord,
+        mentioned: &[UserId],
+    ) -> Result<(), ChatError> {
+        if mentioned.is_empty() {
+            return Ok(());
+        }
+        let tx = downcast(tx);
+
+        let mut query = QueryBuilder::<MySql>::new(
+            "INSERT IGNORE INTO message_mention (conversation_id, message_offset, user_id, message_id, created_at) ",
+        );
+        query.push_values(mentioned, |mut b, user_id| {
+            b.push_bind(record.conversation_id)
+                .push_bind(record.message_offset)
+                .push_bind(*user_id)
+                .push_bind(record.message_id)
+                .push_bind(record.created_at);
+        });
+        query
+            .build()
+            .execute(tx.conn())
+            .await
+            .map_err(|e| ChatError::Store(format!("insert mentions: {e}")))?;
+
+        Ok(())
+    }
+
+    async fn list_mentions_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        page_size: PageSize,
+        before: Option<SearchCursor>,
+    ) -> Result<Vec<MessageRecord>, ChatError> {
+        let tx = downcast(tx);
+
+        // a range over ix_mention_user, newest first
+        let before_at = before.map(|cursor| cursor.created_at);
+        let before_id = before.map(|cursor| cursor.message_id);
+        let rows: Vec<MessageRow> = sqlx::query_as(
+            r#"
+SELECT m.message_id, m.conversation_id, m.message_offset, m.sender_id, m.content, m.content_encrypted, m.created_at,
+       m.edited_at, m.edit_count, m.deleted_at, m.kind, m.reply_to
+FROM message_mention mm
+JOIN message m ON m.conversation_id = mm.conversation_id AND m.message_offset = mm.message_offset
+JOIN conversation_member cm ON cm.conversation_id = mm.conversation_id AND cm.user_id = mm.user_id
+JOIN conversation c ON c.conversation_id = mm.conversation_id AND c.deleted_at IS NULL
+WHERE mm.user_id = ?
+  AND m.deleted_at IS NULL
+  AND (? IS NULL OR mm.created_at < ? OR (mm.created_at = ? AND mm.message_id < ?))
+ORDER BY mm.created_at DESC, mm.message_id DESC
+LIMIT ?
+"#,
+        )
+        .bind(user_id)
+        .bind(before_at)
+        .bind(before_at)
+        .bind(before_at)
+        .bind(before_id)
+        .bind(page_size.0 as i64)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("list mentions: {e}")))?;
+
+        hydrate(tx.conn(), rows).await
+    }
+
+    async fn pin_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+        pinned_by: UserId,
+        at: DateTime<Utc>,
+    ) -> Result<bool, ChatError> {
+        let tx = downcast(tx);
+
+        let pinned = sqlx::query(
+            r#"
+INSERT IGNORE INTO pinned_message (conversation_id, message_offset, pinned_by, pinned_at)
+VALUES (?, ?, ?, ?)
+"#,
+        )
+        .bind(conversation_id)
+        .bind(message_offset)
+        .bind(pinned_by)
+        .bind(at)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("pin message: {e}")))?
+        .rows_affected()
+            > 0;
+
+        Ok(pinned)
+    }
+
+    async fn unpin_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        message_offset: MessageOffset,
+    ) -> Result<bool, ChatError> {
+        let tx = downcast(tx);
+
+        let unpinned = sqlx::query(
+            "DELETE FROM pinned_message WHERE conversation_id = ? AND message_offset = ?",
+        )
+        .bind(conversation_id)
+        .bind(message_offset)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("unpin message: {e}")))?
+        .rows_affected()
+            > 0;
+
+        Ok(unpinned)
+    }
+
+    async fn list_pinned_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<Vec<PinnedMessage>, ChatError> {
+        let tx = downcast(tx);
+
+        let rows: Vec<PinnedRow> = sqlx::query_as(
+            r#"
+SELECT m.message_id, m.conversation_id, m.message_offset, m.sender_id, m.content, m.content_encrypted, m.created_at,
+       m.edited_at, m.edit_count, m.deleted_at, m.kind, m.reply_to,
+       p.pinned_by, p.pinned_at
+FROM pinned_message p
+JOIN message m ON m.conversation_id = p.conversation_id AND m.message_offset = p.message_offset
+WHERE p.conversation_id = ? AND m.deleted_at IS NULL
+ORDER BY p.pinned_at DESC, p.message_offset DESC
+"#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("list pinned messages: {e}")))?;
+
+        let (messages, pins): (Vec<MessageRow>, Vec<(UserId, DateTime<Utc>)>) = rows
+            .into_iter()
+            .map(|row| (row.message, (row.pinned_by, row.pinned_at)))
+            .unzip();
+        let records = hydrate(tx.conn(), messages).await?;
+        Ok(records
             .into_iter()
-            .map(|r| MessageRecord {
-                message_id: r.message_id,
-                conversation_id: r.conversation_id,
-                message_offset: MessageOffset(r.message_offset),
-                sender: r.sender_id,
-                content: r.content,
-                created_at: r.created_at,
+            .zip(pins)
+            .map(|(message, (pinned_by, pinned_at))| PinnedMessage {
+                message,
+                pinned_by,
+                pinned_at,
             })
-            .collect();
+            .collect())
+    }
+
+    async fn summarize_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> Result<HistorySummary, ChatError> {
+        let tx = downcast(tx);
+
+        // one range over the primary key
+        let (first_offset, last_offset, message_count): (Option<u64>, Option<u64>, i64) =
+            sqlx::query_as(
+                r#"
+SELECT MIN(message_offset), MAX(message_offset), COUNT(*)
+FROM message
+WHERE conversation_id = ?
+"#,
+            )
+            .bind(conversation_id)
+            .fetch_one(tx.conn())
+            .await
+            .map_err(|e| ChatError::Store(format!("summarize_in_tx: {e}")))?;
+
+        Ok(HistorySummary {
+            conversation_id,
+            first_offset: first_offset.map(MessageOffset),
+            last_offset: last_offset.map(MessageOffset),
+            message_count: message_count as u64,
+        })
+    }
+
+    async fn offset_at_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        at: DateTime<Utc>,
+    ) -> Result<Option<MessageOffset>, ChatError> {
+        let tx = downcast(tx);
+
+        // the nearest neighbour on each side of `at`, each one index probe
+        let candidates: Vec<(u64, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+(SELECT message_offset, created_at
+ FROM message
+ WHERE conversation_id = ? AND created_at >= ?
+ ORDER BY created_at, message_offset
+ LIMIT 1)
+UNION ALL
+(SELECT message_offset, created_at
+ FROM message
+ WHERE conversation_id = ? AND created_at < ?
+ ORDER BY created_at DESC, message_offset DESC
+ LIMIT 1)
+"#,
+        )
+        .bind(conversation_id)
+        .bind(at)
+        .bind(conversation_id)
+        .bind(at)
+        .fetch_all(tx.conn())
+        .await
+        .map_err(|e| ChatError::Store(format!("offset_at_in_tx: {e}")))?;
+
+        Ok(candidates
+            .into_iter()
+            .min_by_key(|(_, created_at)| ((*created_at - at).abs(), *created_at < at))
+            .map(|(offset, _)| MessageOffset(offset)))
+    }
+
+    async fn prune_expired(
+        &self,
+        policy: &RetentionPolicy,
+        limit: u32,
+    ) -> Result<PrunedMessages, ChatError> {
+        // a multi-table DELETE takes no LIMIT, so pick the batch first; an
+        // unset override and no default leave the interval NULL, which
+        // matches nothing
+        let expired: Vec<(MessageId, ConversationId, u64)> = sqlx::query_as(
+            r#"
+SELECT m.message_id, m.conversation_id, m.message_offset
+FROM message AS m
+         JOIN conversation AS c ON c.conversation_id = m.conversation_id
+         JOIN user AS u ON u.user_id = m.sender_id
+WHERE u.legal_hold = 0
+  AND m.created_at < CURRENT_TIMESTAMP(6)
+      - INTERVAL COALESCE(LEAST(GREATEST(c.retention_days, ?), ?), ?) DAY
+LIMIT ?
+"#,
+        )
+        .bind(policy.min_days)
+        .bind(policy.max_days.max(policy.min_days))
+        .bind(policy.default_days)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ChatError::Store(format!("find expired messages: {e}")))?;
 
-        Ok(out)
+        if expired.is_empty() {
+            return Ok(PrunedMessages::default());
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ChatError::Store(format!("begin prune: {e}")))?;
+
+        let message_ids: Vec<MessageId> = expired.iter().map(|(id, _, _)| *id).collect();
+        let attachments = delete_attachments_of(&mut tx, &message_ids)
+            .await
+            .map_err(|e| ChatError::Store(format!("prune attachments: {e}")))?;
+
+        let placeholders = std::iter::repeat_n("(?, ?)", expired.len())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "DELETE FROM message WHERE (conversation_id, message_offset) IN ({placeholders})"
+        );
+        let mut q = sqlx::query(&sql);
+        for (_, conversation_id, message_offset) in &expired {
+            q = q.bind(*conversation_id).bind(*message_offset);
+        }
+        let res = q
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ChatError::Store(format!("prune messages: {e}")))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ChatError::Store(format!("commit prune: {e}")))?;
+
+        Ok(PrunedMessages {
+            messages: res.rows_affected(),
+            attachments,
+        })
+    }
+
+    async fn count_by_sender(&self, sender: UserId) -> Result<u64, ChatError> {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM message WHERE sender_id = ?")
+            .bind(sender)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ChatError::Store(format!("count messages by sender: {e}")))?;
+        Ok(count as u64)
+    }
+
+    fn stream_by_sender(&self, sender: UserId) -> BoxStream<'_, Result<MessageRecord, ChatError>> {
+        sqlx::query_as::<_, MessageRow>(
+            r#"
+SELECT message_id, conversation_id, message_offset, sender_id, content, content_encrypted, created_at,
+       edited_at, edit_count, deleted_at, kind, reply_to
+FROM message
+WHERE sender_id = ?
+ORDER BY created_at, message_id
+"#,
+        )
+        .bind(sender)
+        .fetch(&self.pool)
+        .map(|row| {
+            row.map(MessageRecord::from)
+                .map_err(|e| ChatError::Store(format!("stream messages by sender: {e}")))
+        })
+        .boxed()
     }
 }
@@ -1,22 +1,56 @@
+mod attachment_repo_mysql;
 mod auth_repo_mysql;
+mod contact_hash_repo_mysql;
+mod conversation_meta_repo_mysql;
 mod conversation_repo_mysql;
+mod conversation_role_repo_cached;
 mod conversation_role_repo_mysql;
+mod delivery_repo_mysql;
+mod device_repo_mysql;
+mod export_job_repo_mysql;
 mod friendship_repo_mysql;
 mod group_idem_repo_mysql;
 mod group_repo_mysql;
+mod import_repo_mysql;
+mod incoming_webhook_repo_mysql;
+mod invite_code_repo_mysql;
+mod key_repo_mysql;
+mod login_location_repo_mysql;
+mod message_offset_allocator_mysql;
 mod message_repo_mysql;
 mod outbox_repo_mysql;
+mod stats_repo_mysql;
+mod user_repo_cached;
 mod user_repo_mysql;
+mod username_rule_repo_mysql;
+mod webhook_repo_mysql;
 
+pub use attachment_repo_mysql::*;
 pub use auth_repo_mysql::*;
+pub use contact_hash_repo_mysql::*;
+pub use conversation_meta_repo_mysql::*;
 pub use conversation_repo_mysql::*;
+pub use conversation_role_repo_cached::*;
 pub use conversation_role_repo_mysql::*;
+pub use delivery_repo_mysql::*;
+pub use device_repo_mysql::*;
+pub use export_job_repo_mysql::*;
 pub use friendship_repo_mysql::*;
 pub use group_idem_repo_mysql::*;
 pub use group_repo_mysql::*;
+pub use import_repo_mysql::*;
+pub use incoming_webhook_repo_mysql::*;
+pub use invite_code_repo_mysql::*;
+pub use key_repo_mysql::*;
+pub use login_location_repo_mysql::*;
+pub use message_offset_allocator_mysql::*;
 pub use message_repo_mysql::*;
 pub use outbox_repo_mysql::*;
+pub use stats_repo_mysql::*;
+pub use user_repo_cached::*;
 pub use user_repo_mysql::*;
+pub use username_rule_repo_mysql::*;
+pub use webhook_repo_mysql::*;
 
 mod repo_tx_mysql;
 
@@ -1,11 +1,12 @@
 use super::util::downcast;
+use crate::domain_model::TraceId;
 use crate::domain_port::*;
 use chrono::{DateTime, Utc};
 use sqlx::encode::IsNull;
 use sqlx::error::BoxDynError;
 use sqlx::mysql::MySqlRow;
 use sqlx::types::JsonValue;
-use sqlx::{Database, Decode, Encode, MySqlPool, Row, Type};
+use sqlx::{Database, Decode, Encode, MySql, MySqlPool, QueryBuilder, Row, Type};
 use std::fmt;
 use std::str::FromStr;
 use uuid::Uuid;
@@ -17,6 +18,19 @@ impl fmt::Display for EventType {
             EventType::FriendshipNew => "friendship.new",
             EventType::GroupNew => "group.new",
             EventType::GroupMemberNew => "group.member.new",
+            EventType::SessionTerminated => "session.terminated",
+            EventType::ChatTyping => "chat.typing",
+            EventType::ConversationMetaChanged => "conversation.meta.changed",
+            EventType::SecurityAlert => "security.alert",
+            EventType::ChatRead => "chat.read",
+            EventType::ConversationFrozen => "conversation.frozen",
+            EventType::ChatMessageEdited => "chat.message.edited",
+            EventType::ChatMessageDeleted => "chat.message.deleted",
+            EventType::GroupUpdated => "group.updated",
+            EventType::MessagePinned => "message.pinned",
+            EventType::ChatMentionNew => "chat.mention.new",
+            EventType::ChatMessageDelivered => "chat.message.delivered",
+            EventType::SystemNotice => "system.notice",
         };
         f.write_str(s)
     }
@@ -30,6 +44,19 @@ impl FromStr for EventType {
             "friendship.new" => Ok(Self::FriendshipNew),
             "group.new" => Ok(Self::GroupNew),
             "group.member.new" => Ok(Self::GroupMemberNew),
+            "session.terminated" => Ok(Self::SessionTerminated),
+            "chat.typing" => Ok(Self::ChatTyping),
+            "conversation.meta.changed" => Ok(Self::ConversationMetaChanged),
+            "security.alert" => Ok(Self::SecurityAlert),
+            "chat.read" => Ok(Self::ChatRead),
+            "conversation.frozen" => Ok(Self::ConversationFrozen),
+            "chat.message.edited" => Ok(Self::ChatMessageEdited),
+            "chat.message.deleted" => Ok(Self::ChatMessageDeleted),
+            "group.updated" => Ok(Self::GroupUpdated),
+            "message.pinned" => Ok(Self::MessagePinned),
+            "chat.mention.new" => Ok(Self::ChatMentionNew),
+            "chat.message.delivered" => Ok(Self::ChatMessageDelivered),
+            "system.notice" => Ok(Self::SystemNotice),
             _ => anyhow::bail!("unknown event type: {}", s),
         }
     }
@@ -85,6 +112,7 @@ impl MySqlOutboxRepo {
         let payload_json: JsonValue = r.get("payload_json");
 
         let created_at = r.get::<DateTime<Utc>, _>("created_at");
+        let trace_id = r.get::<Option<TraceId>, _>("trace_id");
 
         OutboxEvent {
             event_id,
@@ -93,6 +121,8 @@ impl MySqlOutboxRepo {
             receivers_json,
             payload_json,
             created_at,
+            trace_id,
+            publish_at: None,
         }
     }
 }
@@ -108,8 +138,9 @@ impl OutboxRepo for MySqlOutboxRepo {
 
         sqlx::query(
             r#"
-INSERT INTO outbox (event_id, event_type, partition_key, receivers_json, payload_json)
-VALUES (?, ?, ?, ?, ?)
+INSERT INTO outbox (event_id, event_type, partition_key, receivers_json, payload_json, trace_id,
+                    created_at, next_attempt_at)
+VALUES (?, ?, ?, ?, ?, ?, COALESCE(?, CURRENT_TIMESTAMP(6)), COALESCE(?, CURRENT_TIMESTAMP(6)))
 ON DUPLICATE KEY UPDATE event_id = event_id
 "#,
         )
@@ -118,6 +149,9 @@ ON DUPLICATE KEY UPDATE event_id = event_id
         .bind(event.partition_key)
         .bind(&event.receivers_json)
         .bind(&event.payload_json)
+        .bind(event.trace_id)
+        .bind(event.publish_at)
+        .bind(event.publish_at)
         .execute(tx.conn())
         .await?;
 
@@ -134,7 +168,7 @@ ON DUPLICATE KEY UPDATE event_id = event_id
 
         let rows = sqlx::query(
             r#"
-SELECT event_id, event_type, partition_key, receivers_json, payload_json, created_at
+SELECT event_id, event_type, partition_key, receivers_json, payload_json, created_at, trace_id
 FROM outbox
 WHERE delivered_at IS NULL
   AND next_attempt_at <= ?
@@ -201,4 +235,55 @@ WHERE event_id = ?
 
         Ok(())
     }
+
+    async fn requeue_delivered_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        selection: &ReplaySelection,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<u64> {
+        let tx = downcast(tx);
+
+        let mut query = QueryBuilder::<MySql>::new(
+            "UPDATE outbox SET delivered_at = NULL, attempt_count = 0, last_error = NULL, next_attempt_at = ",
+        );
+        query.push_bind(now);
+        query.push(", redelivered_at = ");
+        query.push_bind(now);
+        query.push(" WHERE delivered_at IS NOT NULL AND ");
+        match selection {
+            ReplaySelection::Events(ids) => {
+                query.push("event_id IN (");
+                let mut separated = query.separated(", ");
+                for id in ids {
+                    separated.push_bind(*id);
+                }
+                query.push(")");
+            }
+            ReplaySelection::CreatedBetween { from, to } => {
+                query.push("created_at >= ");
+                query.push_bind(*from);
+                query.push(" AND created_at < ");
+                query.push_bind(*to);
+            }
+        }
+
+        let res = query.build().execute(tx.conn()).await?;
+        Ok(res.rows_affected())
+    }
+
+    async fn count_undelivered(&self) -> anyhow::Result<u64> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+SELECT COUNT(*)
+FROM outbox
+WHERE delivered_at IS NULL
+  AND next_attempt_at <= CURRENT_TIMESTAMP(6)
+"#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count as u64)
+    }
 }
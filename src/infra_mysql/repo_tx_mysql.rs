@@ -1,3 +1,4 @@
+use super::util::is_transient_message;
 use crate::domain_port::{StorageTx, TxManager};
 use anyhow::anyhow;
 use sqlx::{MySql, MySqlConnection, MySqlPool, Transaction};
@@ -18,6 +19,10 @@ impl TxManager for MySqlTxManager {
         let tx = self.pool.begin().await.map_err(|e| anyhow!(e))?;
         Ok(Box::new(MySqlTx::new(tx)))
     }
+
+    fn is_retryable(&self, error: &str) -> bool {
+        is_transient_message(error)
+    }
 }
 
 pub struct MySqlTx<'t> {
@@ -0,0 +1,115 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::MySqlPool;
+
+pub struct MySqlStatsRepo {
+    pool: MySqlPool,
+}
+
+impl MySqlStatsRepo {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl StatsRepo for MySqlStatsRepo {
+    async fn daily(&self, since: DateTime<Utc>) -> Result<Vec<DailyStats>, StatsError> {
+        #[derive(sqlx::FromRow)]
+        struct DailyRow {
+            day: NaiveDate,
+            active_users: u64,
+            messages: u64,
+            signups: u64,
+        }
+
+        // days are UTC whatever the session's time zone; both halves read
+        // their table by the `created_at` index
+        let rows: Vec<DailyRow> = sqlx::query_as(
+            r#"
+SELECT day,
+       CAST(SUM(active_users) AS UNSIGNED) AS active_users,
+       CAST(SUM(messages) AS UNSIGNED)     AS messages,
+       CAST(SUM(signups) AS UNSIGNED)      AS signups
+FROM (SELECT DATE(CONVERT_TZ(created_at, @@session.time_zone, '+00:00')) AS day,
+             COUNT(DISTINCT sender_id)                                 AS active_users,
+             COUNT(*)                                                  AS messages,
+             0                                                         AS signups
+      FROM message
+      WHERE created_at >= ?
+        AND kind = 'user'
+      GROUP BY day
+      UNION ALL
+      SELECT DATE(CONVERT_TZ(created_at, @@session.time_zone, '+00:00')), 0, 0, COUNT(*)
+      FROM user
+      WHERE created_at >= ?
+      GROUP BY 1) AS activity
+GROUP BY day
+ORDER BY day
+"#,
+        )
+        .bind(since)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StatsError::Store(format!("daily stats: {e}")))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| DailyStats {
+                day: r.day,
+                active_users: r.active_users,
+                messages: r.messages,
+                signups: r.signups,
+            })
+            .collect())
+    }
+
+    async fn top_groups(
+        &self,
+        since: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<GroupTraffic>, StatsError> {
+        #[derive(sqlx::FromRow)]
+        struct TrafficRow {
+            group_id: GroupId,
+            conversation_id: ConversationId,
+            group_name: String,
+            messages: u64,
+        }
+
+        let rows: Vec<TrafficRow> = sqlx::query_as(
+            r#"
+SELECT g.group_id,
+       g.conversation_id,
+       g.group_name,
+       CAST(COUNT(*) AS UNSIGNED) AS messages
+FROM message m
+JOIN chat_group g ON g.conversation_id = m.conversation_id
+WHERE m.created_at >= ?
+  AND m.kind = 'user'
+  AND g.deleted_at IS NULL
+GROUP BY g.group_id, g.conversation_id, g.group_name
+ORDER BY messages DESC, g.group_id
+LIMIT ?
+"#,
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StatsError::Store(format!("top groups: {e}")))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| GroupTraffic {
+                group_id: r.group_id,
+                conversation_id: r.conversation_id,
+                name: r.group_name,
+                messages: r.messages,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,133 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use moka::sync::Cache;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Most users whose existence is cached at once; past it, the least used
+/// are evicted.
+const MAX_CACHED_USERS: u64 = 100_000;
+
+/// Caches `id_exists` answers, which are checked on every authenticated
+/// request, for a short TTL. Everything else passes through.
+///
+/// A write through this repo drops the entry, but only `forget` after the
+/// commit keeps a concurrent check from caching the old answer again; the
+/// `session.terminated` fan-out calls it on every node once a deactivation
+/// is committed.
+pub struct CachedUserRepo {
+    inner: Arc<dyn UserRepo>,
+    existence: Option<Cache<UserId, bool>>,
+}
+
+impl CachedUserRepo {
+    /// A zero `ttl` disables caching.
+    pub fn new(inner: Arc<dyn UserRepo>, ttl: Duration) -> Self {
+        let existence = (!ttl.is_zero()).then(|| {
+            Cache::builder()
+                .max_capacity(MAX_CACHED_USERS)
+                .time_to_live(ttl)
+                .build()
+        });
+        Self { inner, existence }
+    }
+}
+
+impl UserCacheInvalidator for CachedUserRepo {
+    fn forget(&self, user_id: UserId) {
+        if let Some(existence) = &self.existence {
+            existence.invalidate(&user_id);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserRepo for CachedUserRepo {
+    async fn create_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        username: &str,
+    ) -> Result<(), AuthError> {
+        self.forget(user_id);
+        self.inner.create_in_tx(tx, user_id, username).await
+    }
+
+    async fn get_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<Option<UserRecord>, AuthError> {
+        self.inner.get_in_tx(tx, user_id).await
+    }
+
+    async fn get_username_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<String, AuthError> {
+        self.inner.get_username_in_tx(tx, user_id).await
+    }
+
+    async fn get_id_by_username_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        username: &str,
+    ) -> Result<UserId, AuthError> {
+        self.inner.get_id_by_username_in_tx(tx, username).await
+    }
+
+    async fn deactivate_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<bool, AuthError> {
+        self.forget(user_id);
+        self.inner.deactivate_in_tx(tx, user_id).await
+    }
+
+    async fn soft_delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<bool, AuthError> {
+        self.forget(user_id);
+        self.inner.soft_delete_in_tx(tx, user_id).await
+    }
+
+    async fn set_legal_hold_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        hold: bool,
+    ) -> Result<bool, AuthError> {
+        self.inner.set_legal_hold_in_tx(tx, user_id, hold).await
+    }
+
+    async fn set_bot_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        bot: bool,
+    ) -> Result<bool, AuthError> {
+        self.inner.set_bot_in_tx(tx, user_id, bot).await
+    }
+
+    async fn username_exists(&self, username: &str) -> Result<bool, AuthError> {
+        self.inner.username_exists(username).await
+    }
+
+    async fn id_exists(&self, user_id: UserId) -> Result<bool, AuthError> {
+        let Some(existence) = &self.existence else {
+            return self.inner.id_exists(user_id).await;
+        };
+        if let Some(exists) = existence.get(&user_id) {
+            return Ok(exists);
+        }
+
+        let exists = self.inner.id_exists(user_id).await?;
+        existence.insert(user_id, exists);
+        Ok(exists)
+    }
+}
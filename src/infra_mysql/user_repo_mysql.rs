@@ -39,6 +39,31 @@ VALUES (?, ?, ?)
         Ok(())
     }
 
+    async fn get_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<Option<UserRecord>, AuthError> {
+        let tx = downcast(tx);
+
+        let row = sqlx::query(
+            "SELECT user_id, username, is_active, legal_hold, is_bot, created_at FROM user WHERE user_id = ?",
+        )
+        .bind(user_id)
+        .fetch_optional(tx.conn())
+        .await
+        .map_err(|e| AuthError::Store(format!("query user: {e}")))?;
+
+        Ok(row.map(|row| UserRecord {
+            user_id: row.get("user_id"),
+            username: row.get("username"),
+            is_active: row.get("is_active"),
+            legal_hold: row.get("legal_hold"),
+            is_bot: row.get("is_bot"),
+            created_at: row.get("created_at"),
+        }))
+    }
+
     async fn get_username_in_tx<'t>(
         &self,
         tx: &mut dyn StorageTx<'t>,
@@ -79,6 +104,102 @@ VALUES (?, ?, ?)
         Err(AuthError::UserNotFound)
     }
 
+    async fn deactivate_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<bool, AuthError> {
+        let tx = downcast(tx);
+
+        let result =
+            sqlx::query("UPDATE user SET is_active = 0 WHERE user_id = ? AND is_active = 1")
+                .bind(user_id)
+                .execute(tx.conn())
+                .await
+                .map_err(|e| AuthError::Store(format!("deactivate user: {e}")))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn soft_delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+    ) -> Result<bool, AuthError> {
+        let tx = downcast(tx);
+
+        let result = sqlx::query(
+            r#"
+UPDATE user SET is_active = 0, deleted_at = CURRENT_TIMESTAMP(6)
+WHERE user_id = ? AND deleted_at IS NULL AND legal_hold = 0
+"#,
+        )
+        .bind(user_id)
+        .execute(tx.conn())
+        .await
+        .map_err(|e| AuthError::Store(format!("soft delete user: {e}")))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_legal_hold_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        hold: bool,
+    ) -> Result<bool, AuthError> {
+        let tx = downcast(tx);
+
+        // matched rather than changed rows, so setting the current value is no miss
+        let found: Option<i64> =
+            sqlx::query_scalar("SELECT 1 FROM user WHERE user_id = ? FOR UPDATE")
+                .bind(user_id)
+                .fetch_optional(tx.conn())
+                .await
+                .map_err(|e| AuthError::Store(format!("lock user: {e}")))?;
+        if found.is_none() {
+            return Ok(false);
+        }
+
+        sqlx::query("UPDATE user SET legal_hold = ? WHERE user_id = ?")
+            .bind(hold)
+            .bind(user_id)
+            .execute(tx.conn())
+            .await
+            .map_err(|e| AuthError::Store(format!("set legal hold: {e}")))?;
+
+        Ok(true)
+    }
+
+    async fn set_bot_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        user_id: UserId,
+        bot: bool,
+    ) -> Result<bool, AuthError> {
+        let tx = downcast(tx);
+
+        // matched rather than changed rows, as for the legal hold
+        let found: Option<i64> =
+            sqlx::query_scalar("SELECT 1 FROM user WHERE user_id = ? FOR UPDATE")
+                .bind(user_id)
+                .fetch_optional(tx.conn())
+                .await
+                .map_err(|e| AuthError::Store(format!("lock user: {e}")))?;
+        if found.is_none() {
+            return Ok(false);
+        }
+
+        sqlx::query("UPDATE user SET is_bot = ? WHERE user_id = ?")
+            .bind(bot)
+            .bind(user_id)
+            .execute(tx.conn())
+            .await
+            .map_err(|e| AuthError::Store(format!("set bot: {e}")))?;
+
+        Ok(true)
+    }
+
     async fn username_exists(&self, username: &str) -> Result<bool, AuthError> {
         let count: i64 = sqlx::query_scalar(r#"SELECT COUNT(*) FROM user WHERE username = ?"#)
             .bind(username)
@@ -94,7 +215,7 @@ VALUES (?, ?, ?)
             r#"
 SELECT COUNT(1)
 FROM user
-WHERE user_id = UUID_TO_BIN(?)
+WHERE user_id = UUID_TO_BIN(?) AND is_active = 1
 "#,
         )
         .bind(user_id.to_string())
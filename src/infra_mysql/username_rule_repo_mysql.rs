@@ -0,0 +1,64 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use sqlx::MySqlPool;
+
+pub struct MySqlUsernameRuleRepo {
+    pool: MySqlPool,
+}
+
+impl MySqlUsernameRuleRepo {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn columns(rule: &UsernameRule) -> (&'static str, &str) {
+    match rule {
+        UsernameRule::Reserved(value) => ("reserved", value),
+        UsernameRule::Pattern(value) => ("pattern", value),
+    }
+}
+
+#[async_trait::async_trait]
+impl UsernameRuleRepo for MySqlUsernameRuleRepo {
+    async fn list(&self) -> Result<Vec<UsernameRule>, AuthError> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT kind, value FROM username_rule ORDER BY rule_id")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| AuthError::Store(format!("list username rules: {e}")))?;
+
+        rows.into_iter()
+            .map(|(kind, value)| match kind.as_str() {
+                "reserved" => Ok(UsernameRule::Reserved(value)),
+                "pattern" => Ok(UsernameRule::Pattern(value)),
+                other => Err(AuthError::Store(format!("bad username rule kind: {other}"))),
+            })
+            .collect()
+    }
+
+    async fn add(&self, rule: &UsernameRule) -> Result<bool, AuthError> {
+        let (kind, value) = columns(rule);
+        let result = sqlx::query("INSERT IGNORE INTO username_rule (kind, value) VALUES (?, ?)")
+            .bind(kind)
+            .bind(value)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AuthError::Store(format!("insert username rule: {e}")))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn remove(&self, rule: &UsernameRule) -> Result<bool, AuthError> {
+        let (kind, value) = columns(rule);
+        let result = sqlx::query("DELETE FROM username_rule WHERE kind = ? AND value = ?")
+            .bind(kind)
+            .bind(value)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AuthError::Store(format!("delete username rule: {e}")))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
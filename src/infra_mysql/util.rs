@@ -10,6 +10,11 @@ pub fn downcast<'a, 't>(tx: &'a mut dyn StorageTx<'t>) -> &'a mut MySqlTx<'t> {
     }
 }
 
+/// `?, ?, ...` for an `IN (...)` of `n` values.
+pub fn placeholders(n: usize) -> String {
+    std::iter::repeat_n("?", n).collect::<Vec<_>>().join(", ")
+}
+
 pub fn is_dup_key(err: &sqlx::Error) -> bool {
     if let sqlx::Error::Database(db) = err {
         if let Some(mysql_err) = db.try_downcast_ref::<MySqlDatabaseError>() {
@@ -19,3 +24,13 @@ pub fn is_dup_key(err: &sqlx::Error) -> bool {
 
     false
 }
+
+/// Matches a rendered `MySqlDatabaseError` ("<number> (<sqlstate>): ...") for
+/// errors that roll back the transaction and are worth a retry.
+pub fn is_transient_message(error: &str) -> bool {
+    const MARKERS: [&str; 2] = [
+        "1213 (40001)", // ER_LOCK_DEADLOCK
+        "1205 (HY000)", // ER_LOCK_WAIT_TIMEOUT
+    ];
+    MARKERS.iter().any(|marker| error.contains(marker))
+}
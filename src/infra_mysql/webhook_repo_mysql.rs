@@ -0,0 +1,143 @@
+use super::util::downcast;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+
+#[derive(sqlx::FromRow)]
+struct WebhookRow {
+    webhook_id: WebhookId,
+    conversation_id: ConversationId,
+    url: String,
+    created_by: UserId,
+    created_at: DateTime<Utc>,
+}
+
+impl From<WebhookRow> for Webhook {
+    fn from(r: WebhookRow) -> Self {
+        Webhook {
+            webhook_id: r.webhook_id,
+            conversation_id: r.conversation_id,
+            url: r.url,
+            created_by: r.created_by,
+            created_at: r.created_at,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct TargetRow {
+    webhook_id: WebhookId,
+    url: String,
+    secret: String,
+}
+
+/// Works only through the caller's transaction, so it needs no pool.
+#[derive(Default)]
+pub struct MySqlWebhookRepo;
+
+impl MySqlWebhookRepo {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookRepo for MySqlWebhookRepo {
+    async fn insert_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        webhook: &Webhook,
+        secret: &str,
+    ) -> anyhow::Result<()> {
+        let tx = downcast(tx);
+
+        sqlx::query(
+            r#"
+INSERT INTO webhook (webhook_id, conversation_id, url, secret, created_by, created_at)
+VALUES (?, ?, ?, ?, ?, ?)
+"#,
+        )
+        .bind(webhook.webhook_id)
+        .bind(webhook.conversation_id)
+        .bind(&webhook.url)
+        .bind(secret)
+        .bind(webhook.created_by)
+        .bind(webhook.created_at)
+        .execute(tx.conn())
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> anyhow::Result<Vec<Webhook>> {
+        let tx = downcast(tx);
+
+        let rows: Vec<WebhookRow> = sqlx::query_as(
+            r#"
+SELECT webhook_id, conversation_id, url, created_by, created_at
+FROM webhook
+WHERE conversation_id = ?
+ORDER BY created_at, webhook_id
+"#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx.conn())
+        .await?;
+
+        Ok(rows.into_iter().map(Webhook::from).collect())
+    }
+
+    async fn delete_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+        webhook_id: WebhookId,
+    ) -> anyhow::Result<bool> {
+        let tx = downcast(tx);
+
+        let result = sqlx::query(
+            r#"
+DELETE FROM webhook
+WHERE conversation_id = ? AND webhook_id = ?
+"#,
+        )
+        .bind(conversation_id)
+        .bind(webhook_id)
+        .execute(tx.conn())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn targets_in_tx<'t>(
+        &self,
+        tx: &mut dyn StorageTx<'t>,
+        conversation_id: ConversationId,
+    ) -> anyhow::Result<Vec<WebhookTarget>> {
+        let tx = downcast(tx);
+
+        let rows: Vec<TargetRow> = sqlx::query_as(
+            r#"
+SELECT webhook_id, url, secret
+FROM webhook
+WHERE conversation_id = ?
+"#,
+        )
+        .bind(conversation_id)
+        .fetch_all(tx.conn())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| WebhookTarget {
+                webhook_id: r.webhook_id,
+                url: r.url,
+                secret: r.secret,
+            })
+            .collect())
+    }
+}
@@ -1,11 +1,16 @@
 use crate::application_port::*;
 use crate::domain_model::*;
 use crate::domain_port::*;
+use chrono::{DateTime, Utc};
 use redis::aio::ConnectionManager;
 use redis::{
     AsyncCommands, FromRedisValue, RedisError, RedisResult, RedisWrite, ToRedisArgs, Value,
 };
+use std::collections::HashMap;
 
+/// Keeps each refresh session under `{prefix}:{jti}` and indexes them per
+/// user under `{prefix}:user:{id}`. Every node must share `prefix` for the
+/// index to cover sessions issued by all of them.
 pub struct RedisAuthSessionStore {
     conn: ConnectionManager,
     prefix: String,
@@ -22,6 +27,10 @@ impl RedisAuthSessionStore {
     fn key(&self, jti: &str) -> String {
         format!("{}:{}", self.prefix, jti)
     }
+
+    fn user_key(&self, user_id: UserId) -> String {
+        format!("{}:user:{}", self.prefix, user_id)
+    }
 }
 
 impl ToRedisArgs for UserId {
@@ -47,18 +56,100 @@ impl FromRedisValue for UserId {
     }
 }
 
+fn parse_session(
+    jti: &str,
+    fields: HashMap<String, String>,
+) -> Result<Option<RefreshSession>, AuthError> {
+    let Some(user) = fields.get("user") else {
+        return Ok(None);
+    };
+    let user_id = user
+        .parse::<UserId>()
+        .map_err(|e| AuthError::Store(format!("invalid session user: {e}")))?;
+    let timestamp = |field: &str| {
+        fields
+            .get(field)
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|t| t.with_timezone(&Utc))
+    };
+    let issued_at = timestamp("issued_at").unwrap_or_default();
+    let started_at = timestamp("started_at").unwrap_or(issued_at);
+    Ok(Some(RefreshSession {
+        jti: jti.to_owned(),
+        user_id,
+        device: fields.get("device").cloned(),
+        issued_at,
+        started_at,
+    }))
+}
+
+/// Sessions saved before they became hashes are plain strings holding the
+/// user id. They still refresh, as sessions that start now with no device
+/// known, and the rotation replaces them with a hash.
+async fn load_session(
+    conn: &mut ConnectionManager,
+    key: &str,
+    jti: &str,
+) -> Result<Option<RefreshSession>, AuthError> {
+    match conn.hgetall::<_, HashMap<String, String>>(key).await {
+        Ok(fields) => parse_session(jti, fields),
+        Err(e) if e.code() == Some("WRONGTYPE") => {
+            let user: Option<String> = conn
+                .get(key)
+                .await
+                .map_err(|e| AuthError::Store(e.to_string()))?;
+            // anything else under the key is no session of ours
+            let Some(user_id) = user.and_then(|user| user.parse::<UserId>().ok()) else {
+                return Ok(None);
+            };
+            let now = Utc::now();
+            Ok(Some(RefreshSession {
+                jti: jti.to_owned(),
+                user_id,
+                device: None,
+                issued_at: now,
+                started_at: now,
+            }))
+        }
+        Err(e) => Err(AuthError::Store(e.to_string())),
+    }
+}
+
 #[async_trait::async_trait]
 impl AuthSessionStore for RedisAuthSessionStore {
     async fn save_refresh_jti(
         &self,
-        user_id: UserId,
-        jti: &str,
+        session: &RefreshSession,
         ttl_secs: u64,
     ) -> Result<(), AuthError> {
-        let key = self.key(&jti);
+        let key = self.key(&session.jti);
+        let user_key = self.user_key(session.user_id);
+
+        let mut fields = vec![
+            ("user", session.user_id.to_string()),
+            ("issued_at", session.issued_at.to_rfc3339()),
+            ("started_at", session.started_at.to_rfc3339()),
+        ];
+        if let Some(device) = &session.device {
+            fields.push(("device", device.clone()));
+        }
+
+        // The per-user index lives as long as its longest-lived member.
         let mut conn = self.conn.clone();
-        let _: () = conn
-            .set_ex(&key, &user_id, ttl_secs)
+        let _: () = redis::pipe()
+            .atomic()
+            .hset_multiple(&key, &fields)
+            .expire(&key, ttl_secs as i64)
+            .sadd(&user_key, &session.jti)
+            .cmd("EXPIRE")
+            .arg(&user_key)
+            .arg(ttl_secs)
+            .arg("NX")
+            .cmd("EXPIRE")
+            .arg(&user_key)
+            .arg(ttl_secs)
+            .arg("GT")
+            .query_async(&mut conn)
             .await
             .map_err(|e| AuthError::Store(e.to_string()))?;
         Ok(())
@@ -66,26 +157,70 @@ impl AuthSessionStore for RedisAuthSessionStore {
 
     async fn check_refresh_jti(
         &self,
-        _user_id: UserId,
+        user_id: UserId,
         jti: &str,
         consume: bool,
-    ) -> Result<Option<UserId>, AuthError> {
+    ) -> Result<Option<RefreshSession>, AuthError> {
         let key = self.key(&jti);
         let mut conn = self.conn.clone();
-        let val: Option<UserId> = conn
-            .get(&key)
+        let session = load_session(&mut conn, &key, jti).await?;
+        if session.is_some() && consume {
+            let _: () = redis::pipe()
+                .atomic()
+                .del(&key)
+                .srem(self.user_key(user_id), jti)
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| AuthError::Store(e.to_string()))?;
+        }
+        Ok(session)
+    }
+
+    async fn list_jtis(&self, user_id: UserId) -> Result<Vec<RefreshSession>, AuthError> {
+        let user_key = self.user_key(user_id);
+        let mut conn = self.conn.clone();
+        let jtis: Vec<String> = conn
+            .smembers(&user_key)
             .await
             .map_err(|e| AuthError::Store(e.to_string()))?;
-        if let Some(user_id) = val {
-            if consume {
-                let _: () = conn
-                    .del(&key)
-                    .await
-                    .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        let mut sessions = Vec::with_capacity(jtis.len());
+        for jti in &jtis {
+            match load_session(&mut conn, &self.key(jti), jti).await? {
+                Some(session) => sessions.push(session),
+                // expired: drop the stale index entry
+                None => {
+                    let _: () = conn
+                        .srem(&user_key, jti)
+                        .await
+                        .map_err(|e| AuthError::Store(e.to_string()))?;
+                }
             }
-            Ok(Some(user_id))
-        } else {
-            Ok(None)
         }
+        sessions.sort_by_key(|s| s.issued_at);
+        Ok(sessions)
+    }
+
+    async fn revoke_all(&self, user_id: UserId) -> Result<u64, AuthError> {
+        let user_key = self.user_key(user_id);
+        let mut conn = self.conn.clone();
+        let jtis: Vec<String> = conn
+            .smembers(&user_key)
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        let mut revoked = 0;
+        for jti in &jtis {
+            let n: u64 = conn
+                .del(self.key(jti))
+                .await
+                .map_err(|e| AuthError::Store(e.to_string()))?;
+            revoked += n;
+        }
+        let _: () = conn
+            .del(&user_key)
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+        Ok(revoked)
     }
 }
@@ -0,0 +1,52 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+
+pub struct RedisCommandDedupeStore {
+    conn: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisCommandDedupeStore {
+    pub fn new(conn: ConnectionManager, prefix: impl Into<String>) -> Self {
+        RedisCommandDedupeStore {
+            conn,
+            prefix: prefix.into(),
+        }
+    }
+
+    fn key(&self, user_id: UserId, message_id: MessageId) -> String {
+        format!("{}:{}:{}", self.prefix, user_id, message_id.0)
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandDedupeStore for RedisCommandDedupeStore {
+    async fn get_ack(
+        &self,
+        user_id: UserId,
+        message_id: MessageId,
+    ) -> anyhow::Result<Option<ChatMessageACK>> {
+        let key = self.key(user_id, message_id);
+        let mut conn = self.conn.clone();
+        let val: Option<String> = conn.get(&key).await?;
+        match val {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn save_ack(
+        &self,
+        user_id: UserId,
+        ack: &ChatMessageACK,
+        ttl_secs: u64,
+    ) -> anyhow::Result<()> {
+        let key = self.key(user_id, ack.message_id);
+        let json = serde_json::to_string(ack)?;
+        let mut conn = self.conn.clone();
+        let _: () = conn.set_ex(&key, json, ttl_secs).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use redis::Script;
+use redis::aio::ConnectionManager;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+const OFFSET_ALLOCATE: &str = include_str!("offset_allocate.lua");
+const OFFSET_RESERVE: &str = include_str!("offset_reserve.lua");
+
+/// Idle conversations fall out of Redis and are reseeded from MySQL.
+const COUNTER_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Allocates offsets with a Redis counter so senders never lock the
+/// conversation row.
+///
+/// Every offset handed out is first covered by a block reserved in MySQL
+/// (`offset_ceiling`), so a lost counter is reseeded above anything already
+/// used. Last-message pointers are kept in memory and written back by
+/// [`RedisOffsetAllocator::run_flusher`].
+pub struct RedisOffsetAllocator {
+    conn: ConnectionManager,
+    prefix: String,
+    conversation_repo: Arc<dyn ConversationRepo>,
+    block_size: u64,
+    high_water: DashMap<ConversationId, (MessageOffset, DateTime<Utc>)>,
+}
+
+impl RedisOffsetAllocator {
+    pub fn new(
+        conn: ConnectionManager,
+        prefix: impl Into<String>,
+        conversation_repo: Arc<dyn ConversationRepo>,
+        block_size: u64,
+    ) -> Self {
+        Self {
+            conn,
+            prefix: prefix.into(),
+            conversation_repo,
+            block_size: block_size.max(1),
+            high_water: DashMap::new(),
+        }
+    }
+
+    fn key(&self, conversation_id: ConversationId) -> String {
+        format!("{}:{}", self.prefix, conversation_id.0)
+    }
+
+    async fn reserve(&self, conversation_id: ConversationId, floor: u64) -> Result<(), ChatError> {
+        let ceiling = self
+            .conversation_repo
+            .reserve_offsets(conversation_id, floor, self.block_size)
+            .await?;
+
+        let mut conn = self.conn.clone();
+        let _: i64 = Script::new(OFFSET_RESERVE)
+            .key(self.key(conversation_id))
+            .arg(ceiling - self.block_size)
+            .arg(ceiling)
+            .arg(COUNTER_TTL_SECS)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| ChatError::Store(format!("record offset reservation: {e}")))?;
+
+        Ok(())
+    }
+
+    fn mark(&self, conversation_id: ConversationId, offset: MessageOffset, at: DateTime<Utc>) {
+        self.high_water
+            .entry(conversation_id)
+            .and_modify(|(off, last_at)| {
+                *off = (*off).max(offset);
+                *last_at = (*last_at).max(at);
+            })
+            .or_insert((offset, at));
+    }
+
+    /// Writes the buffered last-message pointers back to MySQL.
+    pub async fn flush(&self) {
+        let ids: Vec<ConversationId> = self.high_water.iter().map(|e| *e.key()).collect();
+        for conversation_id in ids {
+            let Some((_, (offset, at))) = self.high_water.remove(&conversation_id) else {
+                continue;
+            };
+            if let Err(e) = self
+                .conversation_repo
+                .advance_last_message(conversation_id, offset, at)
+                .await
+            {
+                tracing::warn!("flush offset high-water for {}: {e}", conversation_id.0);
+                self.mark(conversation_id, offset, at);
+            }
+        }
+    }
+
+    pub async fn run_flusher(&self, interval: Duration, cancellation_token: CancellationToken) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                biased;
+                _ = cancellation_token.cancelled() => {
+                    self.flush().await;
+                    tracing::info!("Offset flusher shutting down...");
+                    break;
+                }
+                _ = ticker.tick() => self.flush().await,
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageOffsetAllocator for RedisOffsetAllocator {
+    async fn allocate_in_tx(
+        &self,
+        _tx: &mut dyn StorageTx<'_>,
+        conversation_id: ConversationId,
+        _at: DateTime<Utc>,
+    ) -> Result<MessageOffset, ChatError> {
+        let key = self.key(conversation_id);
+        let mut conn = self.conn.clone();
+
+        // at most one reseed: a second miss means Redis is dropping our keys
+        for _ in 0..2 {
+            let (offset, ceiling): (i64, i64) = Script::new(OFFSET_ALLOCATE)
+                .key(&key)
+                .arg(COUNTER_TTL_SECS)
+                .invoke_async(&mut conn)
+                .await
+                .map_err(|e| ChatError::Store(format!("allocate offset: {e}")))?;
+
+            if offset < 0 {
+                self.reserve(conversation_id, 0).await?;
+                continue;
+            }
+
+            let offset = offset as u64;
+            if offset > ceiling as u64 {
+                self.reserve(conversation_id, offset).await?;
+            }
+
+            return Ok(MessageOffset(offset));
+        }
+
+        Err(ChatError::Store(format!(
+            "offset counter for {} vanished after reseed",
+            conversation_id.0
+        )))
+    }
+
+    async fn record_sent(
+        &self,
+        conversation_id: ConversationId,
+        offset: MessageOffset,
+        at: DateTime<Utc>,
+    ) -> Result<(), ChatError> {
+        self.mark(conversation_id, offset, at);
+        Ok(())
+    }
+}
@@ -1,5 +1,15 @@
 mod auth_session_store_redis;
 mod captcha_store_redis;
+mod command_dedupe_store_redis;
+mod message_offset_allocator_redis;
+mod qr_login_store_redis;
+mod rate_limiter_redis;
+mod session_directory_redis;
 
 pub use auth_session_store_redis::*;
 pub use captcha_store_redis::*;
+pub use command_dedupe_store_redis::*;
+pub use message_offset_allocator_redis::*;
+pub use qr_login_store_redis::*;
+pub use rate_limiter_redis::*;
+pub use session_directory_redis::*;
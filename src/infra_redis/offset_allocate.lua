@@ -0,0 +1,16 @@
+-- Lua: if counter missing -> {-1,0}
+-- Otherwise -> {next offset, reserved ceiling}; the caller must extend the
+-- reservation before using an offset above the ceiling
+
+local key = KEYS[1]
+local ttl = tonumber(ARGV[1])
+
+if redis.call('EXISTS', key) == 0 then
+    return {-1, 0}
+end
+
+local off = redis.call('HINCRBY', key, 'next', 1)
+local ceil = tonumber(redis.call('HGET', key, 'ceil')) or 0
+redis.call('EXPIRE', key, ttl)
+
+return {off, ceil}
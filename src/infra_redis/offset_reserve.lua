@@ -0,0 +1,16 @@
+-- Lua: record a reservation made in MySQL
+-- Seeds the counter at ARGV[1] if missing, raises the ceiling to ARGV[2] if higher
+
+local key = KEYS[1]
+local base = tonumber(ARGV[1])
+local ceil = tonumber(ARGV[2])
+local ttl = tonumber(ARGV[3])
+
+redis.call('HSETNX', key, 'next', base)
+local cur = tonumber(redis.call('HGET', key, 'ceil')) or 0
+if ceil > cur then
+    redis.call('HSET', key, 'ceil', ceil)
+end
+redis.call('EXPIRE', key, ttl)
+
+return 1
@@ -0,0 +1,12 @@
+-- Lua: if key missing -> 0 (expired or never started)
+-- If already approved -> 0
+-- Else record the approving user -> 1; the TTL is left as it is
+
+local key = KEYS[1]
+local user = ARGV[1]
+
+if redis.call('EXISTS', key) == 0 then
+    return 0
+end
+
+return redis.call('HSETNX', key, 'approved_by', user)
@@ -0,0 +1,92 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use redis::Script;
+use redis::aio::ConnectionManager;
+
+const QR_APPROVE: &str = include_str!("qr_approve.lua");
+const QR_TAKE: &str = include_str!("qr_take.lua");
+
+pub struct RedisQrLoginStore {
+    conn: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisQrLoginStore {
+    pub fn new(conn: ConnectionManager, prefix: impl Into<String>) -> Self {
+        RedisQrLoginStore {
+            conn,
+            prefix: prefix.into(),
+        }
+    }
+
+    fn key(&self, nonce: &str) -> String {
+        format!("{}:{}", self.prefix, nonce)
+    }
+}
+
+#[async_trait::async_trait]
+impl QrLoginStore for RedisQrLoginStore {
+    async fn create(&self, nonce: &str, login: &QrLogin, ttl_secs: u64) -> Result<(), AuthError> {
+        let key = self.key(nonce);
+
+        let mut fields = vec![("secret", login.secret_hash.clone())];
+        if let Some(device) = &login.device {
+            fields.push(("device", device.clone()));
+        }
+        if let Some(user_id) = login.approved_by {
+            fields.push(("approved_by", user_id.to_string()));
+        }
+
+        let mut conn = self.conn.clone();
+        let _: () = redis::pipe()
+            .atomic()
+            .hset_multiple(&key, &fields)
+            .expire(&key, ttl_secs as i64)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn approve(&self, nonce: &str, user_id: UserId) -> Result<bool, AuthError> {
+        let mut conn = self.conn.clone();
+        let approved: i64 = Script::new(QR_APPROVE)
+            .key(self.key(nonce))
+            .arg(user_id)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+        Ok(approved == 1)
+    }
+
+    async fn take_if_approved(
+        &self,
+        nonce: &str,
+        secret_hash: &str,
+    ) -> Result<Option<QrLogin>, AuthError> {
+        let mut conn = self.conn.clone();
+        let reply: Vec<String> = Script::new(QR_TAKE)
+            .key(self.key(nonce))
+            .arg(secret_hash)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| AuthError::Store(e.to_string()))?;
+
+        match reply.as_slice() {
+            [status] if status == "-1" => Err(AuthError::QrLoginNotFound),
+            [status] if status == "0" => Ok(None),
+            [status, user, device] if status == "1" => {
+                let user_id = user
+                    .parse::<UserId>()
+                    .map_err(|e| AuthError::Store(format!("invalid approving user: {e}")))?;
+                Ok(Some(QrLogin {
+                    secret_hash: secret_hash.to_owned(),
+                    device: Some(device.clone()).filter(|d| !d.is_empty()),
+                    approved_by: Some(user_id),
+                }))
+            }
+            _ => Err(AuthError::Store("unknown script reply".to_string())),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+-- Lua: if key missing or secret mismatch -> {-1}
+-- If not approved yet -> {0}
+-- If approved -> delete, {1, user, device}; device is '' when unknown
+
+local key = KEYS[1]
+local provided = ARGV[1]
+
+local h = redis.call('HGET', key, 'secret')
+if not h or h ~= provided then
+    return {-1}
+end
+
+local user = redis.call('HGET', key, 'approved_by')
+if not user then
+    return {0}
+end
+
+local device = redis.call('HGET', key, 'device') or ''
+redis.call('DEL', key)
+return {1, user, device}
@@ -0,0 +1,22 @@
+-- Lua: count one use in every fixed window, or in none if one is full
+-- KEYS[i] is window i's counter, ARGV[2i-1] its limit, ARGV[2i] its TTL in seconds
+-- Returns 0 if counted, else the index of the last full window
+
+local full = 0
+for i, key in ipairs(KEYS) do
+    local used = tonumber(redis.call('GET', key)) or 0
+    if used >= tonumber(ARGV[2 * i - 1]) then
+        full = i
+    end
+end
+if full > 0 then
+    return full
+end
+
+for i, key in ipairs(KEYS) do
+    if redis.call('INCR', key) == 1 then
+        redis.call('EXPIRE', key, tonumber(ARGV[2 * i]))
+    end
+end
+
+return 0
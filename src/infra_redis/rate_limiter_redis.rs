@@ -0,0 +1,82 @@
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+use redis::Script;
+use redis::aio::ConnectionManager;
+
+const RATE_ACQUIRE: &str = include_str!("rate_acquire.lua");
+const RATE_RELEASE: &str = include_str!("rate_release.lua");
+
+pub struct RedisRateLimiter {
+    conn: ConnectionManager,
+    prefix: String,
+}
+
+impl RedisRateLimiter {
+    pub fn new(conn: ConnectionManager, prefix: impl Into<String>) -> Self {
+        RedisRateLimiter {
+            conn,
+            prefix: prefix.into(),
+        }
+    }
+
+    /// One counter per window, named after its length and index, so it
+    /// starts over on its own once the window ends.
+    fn keys(&self, subject: &str, windows: &[RateWindow], now: DateTime<Utc>) -> Vec<String> {
+        windows
+            .iter()
+            .map(|window| {
+                let (index, _) = window.bucket(now);
+                format!(
+                    "{}:{}:{}:{}",
+                    self.prefix,
+                    subject,
+                    window.period.as_secs(),
+                    index
+                )
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimiter for RedisRateLimiter {
+    async fn acquire(&self, subject: &str, windows: &[RateWindow]) -> anyhow::Result<RateDecision> {
+        if windows.is_empty() {
+            return Ok(RateDecision::Allowed);
+        }
+
+        let now = Utc::now();
+        let script = Script::new(RATE_ACQUIRE);
+        let mut invocation = script.prepare_invoke();
+        for (key, window) in self.keys(subject, windows, now).iter().zip(windows) {
+            invocation
+                .key(key)
+                .arg(window.limit)
+                .arg(window.period.as_secs());
+        }
+        let mut conn = self.conn.clone();
+        let full: usize = invocation.invoke_async(&mut conn).await?;
+
+        Ok(match full.checked_sub(1).and_then(|i| windows.get(i)) {
+            None => RateDecision::Allowed,
+            Some(window) => RateDecision::Limited {
+                reset_at: window.bucket(now).1,
+            },
+        })
+    }
+
+    async fn release(&self, subject: &str, windows: &[RateWindow]) -> anyhow::Result<()> {
+        if windows.is_empty() {
+            return Ok(());
+        }
+
+        let script = Script::new(RATE_RELEASE);
+        let mut invocation = script.prepare_invoke();
+        for key in self.keys(subject, windows, Utc::now()) {
+            invocation.key(key);
+        }
+        let mut conn = self.conn.clone();
+        let _: i64 = invocation.invoke_async(&mut conn).await?;
+        Ok(())
+    }
+}
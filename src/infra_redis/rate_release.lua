@@ -0,0 +1,11 @@
+-- Lua: take back one use from every window that still counts it
+-- A counter that already expired is left alone rather than recreated at -1
+
+for _, key in ipairs(KEYS) do
+    local used = tonumber(redis.call('GET', key)) or 0
+    if used > 0 then
+        redis.call('DECR', key)
+    end
+end
+
+return 1
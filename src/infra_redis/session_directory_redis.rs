@@ -0,0 +1,71 @@
+use crate::server::{SessionDirectory, SessionInfo};
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// What one node last reported.
+#[derive(Serialize, Deserialize)]
+struct NodeReport {
+    expires_at: DateTime<Utc>,
+    sessions: Vec<SessionInfo>,
+}
+
+/// Keeps every node's report in one hash under `key`, by node. Reports
+/// past their expiry are skipped and cleared out when listing.
+pub struct RedisSessionDirectory {
+    conn: ConnectionManager,
+    key: String,
+}
+
+impl RedisSessionDirectory {
+    pub fn new(conn: ConnectionManager, key: String) -> Self {
+        Self { conn, key }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionDirectory for RedisSessionDirectory {
+    async fn report(
+        &self,
+        node: &str,
+        sessions: &[SessionInfo],
+        ttl: Duration,
+    ) -> anyhow::Result<()> {
+        let mut conn = self.conn.clone();
+        if sessions.is_empty() {
+            let _: () = conn.hdel(&self.key, node).await?;
+            return Ok(());
+        }
+        let report = NodeReport {
+            expires_at: Utc::now() + chrono::Duration::from_std(ttl)?,
+            sessions: sessions.to_vec(),
+        };
+        let _: () = conn
+            .hset(&self.key, node, serde_json::to_string(&report)?)
+            .await?;
+        Ok(())
+    }
+
+    async fn sessions(&self) -> anyhow::Result<Vec<SessionInfo>> {
+        let mut conn = self.conn.clone();
+        let reports: HashMap<String, String> = conn.hgetall(&self.key).await?;
+
+        let now = Utc::now();
+        let mut sessions = Vec::new();
+        let mut lapsed = Vec::new();
+        for (node, report) in reports {
+            match serde_json::from_str::<NodeReport>(&report) {
+                Ok(report) if report.expires_at > now => sessions.extend(report.sessions),
+                Ok(_) => lapsed.push(node),
+                Err(e) => tracing::warn!("unreadable session report from [{node}]: {e}"),
+            }
+        }
+        if !lapsed.is_empty() {
+            let _: () = conn.hdel(&self.key, &lapsed).await?;
+        }
+        Ok(sessions)
+    }
+}
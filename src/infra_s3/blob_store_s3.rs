@@ -0,0 +1,210 @@
+use crate::domain_port::*;
+use anyhow::anyhow;
+use s3::Bucket;
+use s3::Region;
+use s3::creds::Credentials;
+use s3::serde_types::Part;
+
+/// Bytes sent per part of an upload, and all of it that is held at once;
+/// S3 wants at least 5 MiB in every part but the last.
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// `BlobStore` on an S3-compatible bucket, such as MinIO in development.
+pub struct S3BlobStore {
+    bucket: Box<Bucket>,
+}
+
+impl S3BlobStore {
+    /// `path_style` addresses the bucket as `endpoint/bucket`, which MinIO
+    /// needs; AWS takes the bucket as a subdomain.
+    pub fn new(
+        endpoint: &str,
+        region: &str,
+        bucket: &str,
+        path_style: bool,
+        access_key: &str,
+        secret_key: &str,
+    ) -> anyhow::Result<Self> {
+        let region = Region::Custom {
+            region: region.to_owned(),
+            endpoint: endpoint.to_owned(),
+        };
+        let credentials = Credentials::new(Some(access_key), Some(secret_key), None, None, None)?;
+        let mut bucket = Bucket::new(bucket, region, credentials)?;
+        if path_style {
+            bucket = bucket.with_path_style();
+        }
+        Ok(Self { bucket })
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, content_type: &str, content: &[u8]) -> anyhow::Result<()> {
+        let response = self
+            .bucket
+            .put_object_with_content_type(key, content, content_type)
+            .await?;
+        match response.status_code() {
+            200..=299 => Ok(()),
+            status => Err(anyhow!("put [{key}]: status {status}")),
+        }
+    }
+
+    async fn start_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+    ) -> anyhow::Result<Box<dyn BlobUpload>> {
+        Ok(Box::new(S3Upload {
+            bucket: self.bucket.clone(),
+            key: key.to_owned(),
+            content_type: content_type.to_owned(),
+            upload_id: None,
+            parts: Vec::new(),
+            pending: Vec::new(),
+        }))
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Blob>> {
+        let response = self.bucket.get_object(key).await?;
+        match response.status_code() {
+            200..=299 => {}
+            404 => return Ok(None),
+            status => return Err(anyhow!("get [{key}]: status {status}")),
+        }
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .cloned()
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        Ok(Some(Blob {
+            content_type,
+            content: response.to_vec(),
+        }))
+    }
+
+    async fn get_range(&self, key: &str, offset: u64, len: u64) -> anyhow::Result<Option<Vec<u8>>> {
+        // the end is inclusive, and the client wants it past the start
+        let end = offset.saturating_add(len.max(2) - 1);
+        let response = self.bucket.get_object_range(key, offset, Some(end)).await?;
+        match response.status_code() {
+            200..=299 => {
+                let mut content = response.to_vec();
+                content.truncate(usize::try_from(len).unwrap_or(usize::MAX));
+                Ok(Some(content))
+            }
+            404 => Ok(None),
+            // starts past the end
+            416 => Ok(Some(Vec::new())),
+            status => Err(anyhow!("get [{key}]: status {status}")),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let response = self.bucket.delete_object(key).await?;
+        match response.status_code() {
+            200..=299 | 404 => Ok(()),
+            status => Err(anyhow!("delete [{key}]: status {status}")),
+        }
+    }
+}
+
+/// A multipart upload, started once there is more than one part to send;
+/// anything smaller is put in one go when it is finished.
+struct S3Upload {
+    bucket: Box<Bucket>,
+    key: String,
+    content_type: String,
+    upload_id: Option<String>,
+    parts: Vec<Part>,
+    pending: Vec<u8>,
+}
+
+impl S3Upload {
+    async fn send_part(&mut self, chunk: Vec<u8>) -> anyhow::Result<()> {
+        let upload_id = match &self.upload_id {
+            Some(upload_id) => upload_id.clone(),
+            None => {
+                let started = self
+                    .bucket
+                    .initiate_multipart_upload(&self.key, &self.content_type)
+                    .await?;
+                self.upload_id.insert(started.upload_id).clone()
+            }
+        };
+        let part = self
+            .bucket
+            .put_multipart_chunk(
+                chunk,
+                &self.key,
+                self.parts.len() as u32 + 1,
+                &upload_id,
+                &self.content_type,
+            )
+            .await?;
+        self.parts.push(part);
+        Ok(())
+    }
+
+    async fn complete(&mut self) -> anyhow::Result<()> {
+        let pending = std::mem::take(&mut self.pending);
+        let Some(upload_id) = self.upload_id.clone() else {
+            let response = self
+                .bucket
+                .put_object_with_content_type(&self.key, &pending, &self.content_type)
+                .await?;
+            return match response.status_code() {
+                200..=299 => Ok(()),
+                status => Err(anyhow!("put [{}]: status {status}", self.key)),
+            };
+        };
+        if !pending.is_empty() {
+            self.send_part(pending).await?;
+        }
+        let response = self
+            .bucket
+            .complete_multipart_upload(&self.key, &upload_id, std::mem::take(&mut self.parts))
+            .await?;
+        self.upload_id = None;
+        match response.status_code() {
+            200..=299 => Ok(()),
+            status => Err(anyhow!("complete upload [{}]: status {status}", self.key)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobUpload for S3Upload {
+    async fn write(&mut self, content: &[u8]) -> anyhow::Result<()> {
+        self.pending.extend_from_slice(content);
+        while self.pending.len() >= PART_SIZE {
+            let rest = self.pending.split_off(PART_SIZE);
+            let chunk = std::mem::replace(&mut self.pending, rest);
+            if let Err(e) = self.send_part(chunk).await {
+                self.abort().await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> anyhow::Result<()> {
+        let result = self.complete().await;
+        if result.is_err() {
+            self.abort().await;
+        }
+        result
+    }
+
+    async fn abort(&mut self) {
+        self.pending = Vec::new();
+        self.parts.clear();
+        if let Some(upload_id) = self.upload_id.take()
+            && let Err(e) = self.bucket.abort_upload(&self.key, &upload_id).await
+        {
+            // the bucket's lifecycle rules clean up after it
+            tracing::warn!("abort upload [{}]: {e:#}", self.key);
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod blob_store_s3;
+
+pub use blob_store_s3::*;
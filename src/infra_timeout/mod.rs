@@ -0,0 +1,119 @@
+//! The `infra_timeout` module wraps any repo or store port in a decorator
+//! that gives every call a time limit, so a hung MySQL or Redis connection
+//! fails the request fast with `Store("timeout")` instead of stalling the
+//! worker behind it. Set with `storage.call_timeout_ms`; see `repos.rs` for
+//! the covered ports.
+
+mod repos;
+
+use crate::application_port::*;
+use crate::domain_port::CaptchaStoreError;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct TimeLimited<T: ?Sized> {
+    inner: Arc<T>,
+    limit: Duration,
+}
+
+/// Implemented for every port `repos.rs` covers.
+pub trait TimeLimit {
+    fn time_limit(self: Arc<Self>, limit: Duration) -> Arc<Self>;
+}
+
+/// Wraps `repo` when there is a limit, otherwise hands it back untouched.
+pub fn time_limit<T: TimeLimit + ?Sized>(limit: Option<Duration>, repo: Arc<T>) -> Arc<T> {
+    match limit {
+        Some(limit) => repo.time_limit(limit),
+        None => repo,
+    }
+}
+
+/// The error a port returns for a call that ran out of time; always the
+/// store variant, as if the backend had failed.
+pub trait TimedOut {
+    fn timed_out() -> Self;
+}
+
+macro_rules! store_timed_out {
+    ($($error:ident),* $(,)?) => {
+        $(
+            impl TimedOut for $error {
+                fn timed_out() -> Self {
+                    $error::Store("timeout".to_owned())
+                }
+            }
+        )*
+    };
+}
+
+store_timed_out!(
+    AuthError,
+    RelationError,
+    ChatError,
+    CaptchaStoreError,
+    ImportError,
+    StatsError
+);
+
+impl TimedOut for anyhow::Error {
+    fn timed_out() -> Self {
+        anyhow::anyhow!("timeout")
+    }
+}
+
+/// Implements `$port` for `TimeLimited<dyn $port>` by forwarding each listed
+/// method under `tokio::time::timeout`. The method list must mirror the
+/// trait; the compiler enforces it.
+///
+/// A call that runs out of time is dropped mid-flight; inside a transaction
+/// the caller then fails and rolls back, as for any other store error.
+///
+/// Plain `fn`s, listed after the `async` ones, return streams that do their
+/// work after the call returns, so they are forwarded unlimited.
+macro_rules! time_limit_port {
+    ($port:ident {
+        $(
+            async fn $method:ident $(<$lt:lifetime>)? (&self $(, $arg:ident : $ty:ty)* $(,)?) -> $ret:ty;
+        )*
+        $(
+            fn $plain:ident (&self $(, $plain_arg:ident : $plain_ty:ty)* $(,)?) -> $plain_ret:ty;
+        )*
+    }) => {
+        impl $crate::infra_timeout::TimeLimit for dyn $port {
+            fn time_limit(
+                self: ::std::sync::Arc<Self>,
+                limit: ::std::time::Duration,
+            ) -> ::std::sync::Arc<Self> {
+                ::std::sync::Arc::new($crate::infra_timeout::TimeLimited { inner: self, limit })
+            }
+        }
+
+        #[async_trait::async_trait]
+        impl $port for $crate::infra_timeout::TimeLimited<dyn $port> {
+            $(
+                async fn $method $(<$lt>)? (&self $(, $arg: $ty)*) -> $ret {
+                    match ::tokio::time::timeout(self.limit, self.inner.$method($($arg),*)).await {
+                        Ok(result) => result,
+                        Err(_) => {
+                            tracing::warn!(
+                                port = stringify!($port),
+                                method = stringify!($method),
+                                limit_ms = self.limit.as_millis() as u64,
+                                "store call timed out"
+                            );
+                            Err($crate::infra_timeout::TimedOut::timed_out())
+                        }
+                    }
+                }
+            )*
+            $(
+                fn $plain(&self $(, $plain_arg: $plain_ty)*) -> $plain_ret {
+                    self.inner.$plain($($plain_arg),*)
+                }
+            )*
+        }
+    };
+}
+
+use time_limit_port;
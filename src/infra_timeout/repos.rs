@@ -0,0 +1,245 @@
+use super::time_limit_port;
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::Duration;
+
+time_limit_port!(AttachmentRepo {
+    async fn insert_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, record: &AttachmentRecord) -> anyhow::Result<()>;
+    async fn get_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, attachment_id: AttachmentId, viewer: UserId) -> anyhow::Result<Option<AttachmentRecord>>;
+    async fn attach_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, uploader: UserId, message_id: MessageId, attachment_ids: &[AttachmentId]) -> anyhow::Result<Option<Vec<AttachmentRecord>>>;
+    async fn delete_unattached_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, before: DateTime<Utc>, limit: u32) -> anyhow::Result<Vec<AttachmentRecord>>;
+});
+
+time_limit_port!(AuthRepo {
+    async fn create_credentials_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, username: &str, password_hash: &str) -> Result<(), AuthError>;
+    async fn get_by_username(&self, username: &str) -> Result<Option<AuthCredentialsRecord>, AuthError>;
+    async fn deactivate_credentials_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId) -> Result<(), AuthError>;
+});
+
+time_limit_port!(AuthSessionStore {
+    async fn save_refresh_jti(&self, session: &RefreshSession, ttl_secs: u64) -> Result<(), AuthError>;
+    async fn check_refresh_jti(&self, user_id: UserId, jti: &str, consume: bool) -> Result<Option<RefreshSession>, AuthError>;
+    async fn list_jtis(&self, user_id: UserId) -> Result<Vec<RefreshSession>, AuthError>;
+    async fn revoke_all(&self, user_id: UserId) -> Result<u64, AuthError>;
+});
+
+time_limit_port!(CaptchaStore {
+    async fn save(&self, id: &CaptchaId, code_hash_hex: &str, expire_at: DateTime<Utc>, max_attempts: u32) -> Result<(), CaptchaStoreError>;
+    async fn verify_and_consume(&self, id: &CaptchaId, provided_hash_hex: &str) -> Result<(), CaptchaStoreError>;
+});
+
+time_limit_port!(CommandDedupeStore {
+    async fn get_ack(&self, user_id: UserId, message_id: MessageId) -> anyhow::Result<Option<ChatMessageACK>>;
+    async fn save_ack(&self, user_id: UserId, ack: &ChatMessageACK, ttl_secs: u64) -> anyhow::Result<()>;
+});
+
+time_limit_port!(ContactHashRepo {
+    async fn count_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId) -> anyhow::Result<usize>;
+    async fn replace_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, hashes: &[ContactHash]) -> anyhow::Result<()>;
+    async fn find_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, seeker: UserId, hashes: &[ContactHash]) -> anyhow::Result<Vec<ContactMatch>>;
+});
+
+time_limit_port!(ConversationMetaRepo {
+    async fn list_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> Result<Vec<ConversationMeta>, ChatError>;
+    async fn lock_keys_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> Result<Vec<String>, ChatError>;
+    async fn put_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, key: &str, value: &str) -> Result<(), ChatError>;
+    async fn delete_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, key: &str) -> Result<bool, ChatError>;
+});
+
+time_limit_port!(ConversationRepo {
+    async fn get_conversation_member_in_tx(&self, tx: &mut dyn StorageTx<'_>, conversation_id: ConversationId) -> Result<Vec<UserId>, RelationError>;
+    async fn create_direct_conversation_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, a: UserId, b: UserId, conversation_id: ConversationId) -> Result<(), RelationError>;
+    async fn create_group_conversation_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> Result<(), RelationError>;
+    async fn soft_delete_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> Result<bool, RelationError>;
+    async fn members_version_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> Result<u64, RelationError>;
+    async fn members_since_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, since_version: Option<u64>) -> Result<MembersDelta, ChatError>;
+    async fn list_for_user_recent_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, page_size: PageSize, after: Option<TimeCursor>) -> Result<Vec<ConversationId>, ChatError>;
+    async fn hydrate_conversation_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, conversation_ids: Vec<ConversationId>) -> Result<Vec<RecentConversation>, ChatError>;
+    async fn unread_summary_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, top: PageSize) -> Result<UnreadSummary, ChatError>;
+    async fn is_frozen_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> Result<bool, ChatError>;
+    async fn set_frozen_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, frozen: bool) -> Result<bool, ChatError>;
+    async fn retention_days_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> Result<Option<u32>, ChatError>;
+    async fn set_retention_days_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, days: Option<u32>) -> Result<(), ChatError>;
+    async fn advance_read_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, conversation_id: ConversationId, offset: MessageOffset) -> Result<(), ChatError>;
+    async fn mark_read_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, conversation_id: ConversationId, offset: MessageOffset) -> Result<ReadMark, ChatError>;
+    async fn set_pinned_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, conversation_id: ConversationId, pinned: bool) -> Result<bool, ChatError>;
+    async fn next_sender_seq_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, sender: UserId) -> Result<u64, ChatError>;
+    async fn reserve_offsets(&self, conversation_id: ConversationId, floor: u64, block: u64) -> Result<u64, ChatError>;
+    async fn advance_last_message(&self, conversation_id: ConversationId, offset: MessageOffset, at: DateTime<Utc>) -> Result<(), ChatError>;
+});
+
+time_limit_port!(ConversationRoleRepo {
+    async fn get_role_by_conversation_id(&self, user_id: UserId, conversation_id: ConversationId) -> Result<GroupMemberRole, RelationError>;
+    async fn ensure_defaults_in_tx(&self, tx: &mut dyn StorageTx<'_>, conversation_id: ConversationId) -> Result<(), RelationError>;
+    async fn assign_role_by_name_in_tx(&self, tx: &mut dyn StorageTx<'_>, conversation_id: ConversationId, user_id: UserId, role_name: &str) -> Result<(), RelationError>;
+    async fn assign_roles_bulk_in_tx(&self, tx: &mut dyn StorageTx<'_>, conversation_id: ConversationId, user_ids: &[UserId], role_name: &str) -> Result<(), RelationError>;
+    async fn membership_exists(&self, conversation_id: ConversationId, user_id: UserId) -> Result<bool, RelationError>;
+    async fn membership_exists_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, user_id: UserId) -> Result<bool, RelationError>;
+    async fn has_permission_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, user_id: UserId, perm_key: &str) -> Result<Option<bool>, RelationError>;
+});
+
+time_limit_port!(DeliveryRepo {
+    async fn insert_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, message_id: MessageId, user_id: UserId, at: DateTime<Utc>) -> Result<Option<(UserId, MessageOffset)>, ChatError>;
+    async fn list_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, message_id: MessageId, sender: UserId) -> Result<Option<Vec<MessageDelivery>>, ChatError>;
+});
+
+time_limit_port!(DeviceRepo {
+    async fn upsert_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, device: &Device) -> anyhow::Result<()>;
+    async fn trim_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, keep: usize) -> anyhow::Result<u64>;
+    async fn delete_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, platform: PushPlatform, token: &str) -> anyhow::Result<bool>;
+    async fn list_for_users_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_ids: &[UserId]) -> anyhow::Result<Vec<Device>>;
+    async fn delete_tokens_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, platform: PushPlatform, tokens: &[String]) -> anyhow::Result<u64>;
+});
+
+time_limit_port!(ExportJobRepo {
+    async fn get_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId) -> anyhow::Result<Option<ExportJobRecord>>;
+    async fn insert_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, job: &ExportJobRecord) -> anyhow::Result<bool>;
+    async fn update_progress_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, job: ExportJobId, progress: &ExportProgress) -> anyhow::Result<bool>;
+    async fn delete_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, job: ExportJobId) -> anyhow::Result<bool>;
+    async fn delete_expired_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, finished_before: DateTime<Utc>, updated_before: DateTime<Utc>, limit: u32) -> anyhow::Result<Vec<ExportJobRecord>>;
+});
+
+time_limit_port!(FriendshipRepo {
+    async fn claim(&self, a: UserId, b: UserId, requested_by: UserId, key: IdempotencyKey) -> Result<FriendshipIdemClaim, RelationError>;
+    async fn insert_friendship_in_tx(&self, tx: &mut dyn StorageTx<'_>, a: UserId, b: UserId, conversation_id: ConversationId) -> Result<(), RelationError>;
+    async fn get_conversation_id_by_friendship(&self, a: UserId, b: UserId) -> Result<ConversationId, RelationError>;
+    async fn list_friends_with_conversations(&self, user_id: UserId, page_size: PageSize, after: Option<FriendCursor>) -> Result<Vec<FriendSummary>, RelationError>;
+});
+
+time_limit_port!(GroupIdemRepo {
+    async fn claim(&self, owner: UserId, key: IdempotencyKey, proposed_group: GroupId) -> Result<GroupIdemClaim, RelationError>;
+    async fn mark_succeeded(&self, owner: UserId, key: IdempotencyKey, group_id: GroupId, conversation_id: ConversationId) -> Result<(), RelationError>;
+    async fn mark_failed(&self, owner: UserId, key: IdempotencyKey, group_id: GroupId, _err: &str) -> Result<(), RelationError>;
+    async fn retake_stale(&self, owner: UserId, key: IdempotencyKey, group_id: GroupId, stale_after: Duration) -> Result<bool, RelationError>;
+    async fn purge_expired(&self, older_than: Duration) -> Result<u64, RelationError>;
+});
+
+time_limit_port!(GroupRepo {
+    async fn get_group_summary_in_tx(&self, tx: &mut dyn StorageTx<'_>, group_id: GroupId) -> Result<GroupShortSummary, RelationError>;
+    async fn insert_chat_group_in_tx(&self, tx: &mut dyn StorageTx<'_>, group_id: GroupId, owner: UserId, name: &str, description: Option<&str>, conversation_id: ConversationId) -> Result<(), RelationError>;
+    async fn get_conversation_id_by_group(&self, group_id: GroupId) -> Result<Option<ConversationId>, RelationError>;
+    async fn list_groups(&self, user_id: UserId, page_size: PageSize, after: Option<GroupCursor>, projection: GroupProjection) -> Result<Vec<GroupSummary>, RelationError>;
+    async fn count_members(&self, user_id: UserId, group_ids: &[GroupId]) -> Result<Vec<GroupMemberCount>, RelationError>;
+    async fn reconcile_member_counts(&self) -> Result<u64, RelationError>;
+    async fn disband_in_tx(&self, tx: &mut dyn StorageTx<'_>, group_id: GroupId) -> Result<bool, RelationError>;
+    async fn list_group_members_in_tx(&self, tx: &mut dyn StorageTx<'_>, group: GroupId, page_size: PageSize, after: Option<MemberCursor>) -> Result<Vec<MemberSummary>, RelationError>;
+    async fn get_appearance_in_tx(&self, tx: &mut dyn StorageTx<'_>, group_id: GroupId) -> Result<GroupAppearance, RelationError>;
+    async fn set_appearance_in_tx(&self, tx: &mut dyn StorageTx<'_>, group_id: GroupId, appearance: &GroupAppearance) -> Result<bool, RelationError>;
+});
+
+time_limit_port!(ImportRepo {
+    async fn existing_users_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_ids: &[UserId]) -> Result<HashSet<UserId>, ImportError>;
+    async fn existing_conversations_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_ids: &[ConversationId]) -> Result<HashSet<ConversationId>, ImportError>;
+    async fn insert_users_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, users: &[ImportUser]) -> Result<u64, ImportError>;
+    async fn insert_friendships_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, friendships: &[ImportFriendship]) -> Result<u64, ImportError>;
+    async fn insert_groups_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, groups: &[ImportGroup]) -> Result<Vec<GroupId>, ImportError>;
+    async fn settle_messages_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_ids: &[ConversationId]) -> Result<(), ImportError>;
+});
+
+time_limit_port!(KeyRepo {
+    async fn get(&self, conversation_id: ConversationId) -> anyhow::Result<Option<WrappedKey>>;
+    async fn get_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> anyhow::Result<Option<WrappedKey>>;
+    async fn insert_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, key: &WrappedKey) -> anyhow::Result<WrappedKey>;
+});
+
+time_limit_port!(MessageOffsetAllocator {
+    async fn allocate_in_tx(&self, tx: &mut dyn StorageTx<'_>, conversation_id: ConversationId, at: DateTime<Utc>) -> Result<MessageOffset, ChatError>;
+    async fn record_sent(&self, conversation_id: ConversationId, offset: MessageOffset, at: DateTime<Utc>) -> Result<(), ChatError>;
+});
+
+time_limit_port!(MessageRepo {
+    async fn insert_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, record: &MessageRecord, sender_seq: u64) -> Result<SentMessage, ChatError>;
+    async fn get_sent_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, message_id: MessageId) -> Result<Option<SentMessage>, ChatError>;
+    async fn insert_batch_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, records: &[MessageRecord]) -> Result<u64, ChatError>;
+    async fn list_before_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, page_size: PageSize, before: Option<OffsetCursor>) -> Result<Vec<MessageRecord>, ChatError>;
+    async fn list_after_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, after: MessageOffset, page_size: PageSize) -> Result<Vec<MessageRecord>, ChatError>;
+    async fn list_thread_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, root: MessageId, page_size: PageSize, after: Option<OffsetCursor>) -> Result<Vec<MessageRecord>, ChatError>;
+    async fn search_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, terms: &[String], filters: &SearchFilters, page_size: PageSize, before: Option<SearchCursor>) -> Result<Vec<MessageRecord>, ChatError>;
+    async fn lock_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, message_id: MessageId) -> Result<Option<MessageRecord>, ChatError>;
+    async fn update_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, record: &MessageRecord) -> Result<(), ChatError>;
+    async fn mark_deleted_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, message_offset: MessageOffset, at: DateTime<Utc>) -> Result<(), ChatError>;
+    async fn insert_mentions_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, record: &MessageRecord, mentioned: &[UserId]) -> Result<(), ChatError>;
+    async fn list_mentions_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, page_size: PageSize, before: Option<SearchCursor>) -> Result<Vec<MessageRecord>, ChatError>;
+    async fn pin_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, message_offset: MessageOffset, pinned_by: UserId, at: DateTime<Utc>) -> Result<bool, ChatError>;
+    async fn unpin_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, message_offset: MessageOffset) -> Result<bool, ChatError>;
+    async fn list_pinned_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> Result<Vec<PinnedMessage>, ChatError>;
+    async fn summarize_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> Result<HistorySummary, ChatError>;
+    async fn offset_at_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, at: DateTime<Utc>) -> Result<Option<MessageOffset>, ChatError>;
+    async fn prune_expired(&self, policy: &RetentionPolicy, limit: u32) -> Result<PrunedMessages, ChatError>;
+    async fn count_by_sender(&self, sender: UserId) -> Result<u64, ChatError>;
+    fn stream_by_sender(&self, sender: UserId) -> BoxStream<'_, Result<MessageRecord, ChatError>>;
+});
+
+time_limit_port!(OutboxRepo {
+    async fn enqueue_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, event: &OutboxEvent) -> anyhow::Result<()>;
+    async fn claim_ready_batch_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, now: DateTime<Utc>, limit: u32) -> anyhow::Result<Vec<OutboxEvent>>;
+    async fn mark_delivered_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, event_id: EventId, delivered_at: DateTime<Utc>) -> anyhow::Result<()>;
+    async fn reschedule_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, event_id: EventId, next_attempt_at: DateTime<Utc>, last_error: &str) -> anyhow::Result<()>;
+    async fn requeue_delivered_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, selection: &ReplaySelection, now: DateTime<Utc>) -> anyhow::Result<u64>;
+    async fn count_undelivered(&self) -> anyhow::Result<u64>;
+});
+
+time_limit_port!(QrLoginStore {
+    async fn create(&self, nonce: &str, login: &QrLogin, ttl_secs: u64) -> Result<(), AuthError>;
+    async fn approve(&self, nonce: &str, user_id: UserId) -> Result<bool, AuthError>;
+    async fn take_if_approved(&self, nonce: &str, secret_hash: &str) -> Result<Option<QrLogin>, AuthError>;
+});
+
+time_limit_port!(RateLimiter {
+    async fn acquire(&self, subject: &str, windows: &[RateWindow]) -> anyhow::Result<RateDecision>;
+    async fn release(&self, subject: &str, windows: &[RateWindow]) -> anyhow::Result<()>;
+});
+
+time_limit_port!(UserRepo {
+    async fn create_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, username: &str) -> Result<(), AuthError>;
+    async fn get_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId) -> Result<Option<UserRecord>, AuthError>;
+    async fn get_username_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId) -> Result<String, AuthError>;
+    async fn get_id_by_username_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, username: &str) -> Result<UserId, AuthError>;
+    async fn deactivate_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId) -> Result<bool, AuthError>;
+    async fn soft_delete_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId) -> Result<bool, AuthError>;
+    async fn set_legal_hold_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, hold: bool) -> Result<bool, AuthError>;
+    async fn set_bot_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, bot: bool) -> Result<bool, AuthError>;
+    async fn username_exists(&self, username: &str) -> Result<bool, AuthError>;
+    async fn id_exists(&self, user_id: UserId) -> Result<bool, AuthError>;
+});
+
+time_limit_port!(InviteCodeRepo {
+    async fn insert(&self, codes: &[InviteCode]) -> Result<(), AuthError>;
+    async fn list(&self) -> Result<Vec<InviteCode>, AuthError>;
+    async fn redeem_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, code: &str) -> Result<bool, AuthError>;
+});
+
+time_limit_port!(LoginLocationRepo {
+    async fn list_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId) -> Result<Vec<String>, AuthError>;
+    async fn record_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, user_id: UserId, location: &str, ip: IpAddr, at: DateTime<Utc>) -> Result<(), AuthError>;
+});
+
+time_limit_port!(UsernameRuleRepo {
+    async fn list(&self) -> Result<Vec<UsernameRule>, AuthError>;
+    async fn add(&self, rule: &UsernameRule) -> Result<bool, AuthError>;
+    async fn remove(&self, rule: &UsernameRule) -> Result<bool, AuthError>;
+});
+
+time_limit_port!(StatsRepo {
+    async fn daily(&self, since: DateTime<Utc>) -> Result<Vec<DailyStats>, StatsError>;
+    async fn top_groups(&self, since: DateTime<Utc>, limit: u32) -> Result<Vec<GroupTraffic>, StatsError>;
+});
+
+time_limit_port!(WebhookRepo {
+    async fn insert_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, webhook: &Webhook, secret: &str) -> anyhow::Result<()>;
+    async fn list_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> anyhow::Result<Vec<Webhook>>;
+    async fn delete_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, webhook_id: WebhookId) -> anyhow::Result<bool>;
+    async fn targets_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> anyhow::Result<Vec<WebhookTarget>>;
+});
+
+time_limit_port!(IncomingWebhookRepo {
+    async fn insert_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, webhook: &IncomingWebhook, token_hash: &str) -> anyhow::Result<()>;
+    async fn list_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId) -> anyhow::Result<Vec<IncomingWebhook>>;
+    async fn delete_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, conversation_id: ConversationId, incoming_webhook_id: IncomingWebhookId) -> anyhow::Result<bool>;
+    async fn find_by_token_in_tx<'t>(&self, tx: &mut dyn StorageTx<'t>, token_hash: &str) -> anyhow::Result<Option<IncomingWebhook>>;
+});
@@ -0,0 +1,104 @@
+use crate::domain_model::*;
+use crate::it::TestServer;
+use crate::logger::Secret;
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs docker"]
+async fn friends_exchange_a_message() -> anyhow::Result<()> {
+    let test = TestServer::start().await?;
+    let alice = test.user("alice").await?;
+    let bob = test.user("bob").await?;
+
+    let outcome = test
+        .server
+        .relationship_service
+        .add_friend(
+            alice.user_id,
+            bob.user_id,
+            IdempotencyKey(uuid::Uuid::new_v4()),
+        )
+        .await?;
+    let AddFriendOutcome::Created(conversation_id) = outcome else {
+        anyhow::bail!("expected a new friendship, got {:?}", outcome);
+    };
+
+    let mut alice_conn = test.connect(&alice).await?;
+    let mut bob_conn = test.connect(&bob).await?;
+
+    let message_id = MessageId(uuid::Uuid::new_v4());
+    alice_conn
+        .send(&C2SCommand::ChatMessageSend(ChatMessageSend {
+            conversation_id,
+            message_id,
+            content: Secret::new("hello bob".to_string()),
+            reply_to: None,
+            attachments: Vec::new(),
+        }))
+        .await?;
+
+    let ack = alice_conn
+        .expect(|event| match event {
+            S2CEvent::ChatMessageACK(ack) => Some(ack),
+            _ => None,
+        })
+        .await?;
+    assert_eq!(ack.message_id, message_id);
+
+    let new = bob_conn
+        .expect(|event| match event {
+            S2CEvent::ChatMessageNew(new) => Some(new),
+            _ => None,
+        })
+        .await?;
+    assert_eq!(new.message_id, message_id);
+    assert_eq!(new.sender, alice.user_id);
+    assert_eq!(new.username, alice.username);
+    assert_eq!(new.content.expose(), "hello bob");
+
+    let history = test
+        .server
+        .conversation_service
+        .get_history(bob.user_id, conversation_id, PageSize(10), None)
+        .await?;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].message_id, message_id);
+
+    test.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs docker"]
+async fn invited_guest_hears_about_the_group() -> anyhow::Result<()> {
+    let test = TestServer::start().await?;
+    let owner = test.user("owner").await?;
+    let guest = test.user("guest").await?;
+    let mut guest_conn = test.connect(&guest).await?;
+
+    let relationships = &test.server.relationship_service;
+    let (group_id, conversation_id) = relationships
+        .create_group(
+            owner.user_id,
+            "it_group",
+            None,
+            &[],
+            IdempotencyKey(uuid::Uuid::new_v4()),
+        )
+        .await?;
+    relationships
+        .invite_to_group(group_id, owner.user_id, guest.user_id)
+        .await?;
+
+    let group_new = guest_conn
+        .expect(|event| match event {
+            S2CEvent::GroupNew(group_new) => Some(group_new),
+            _ => None,
+        })
+        .await?;
+    assert_eq!(group_new.group_id, group_id);
+    assert_eq!(group_new.conversation_id, conversation_id);
+    assert_eq!(group_new.group_name, "it_group");
+
+    test.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use crate::domain_model::*;
+use crate::protocol::*;
+use crate::server::*;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// How long `next_event` waits before failing the test.
+const EVENT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// One connection over the in-process channels, speaking the typed protocol.
+pub(crate) struct TestClient {
+    c2s: mpsc::Sender<ConnMessage>,
+    s2c: mpsc::Receiver<ConnMessage>,
+}
+
+impl TestClient {
+    pub async fn connect(
+        acceptor: &dyn ConnectionAcceptor,
+        user_id: UserId,
+    ) -> anyhow::Result<Self> {
+        let (c2s_tx, c2s_rx) = mpsc::channel::<ConnMessage>(64);
+        let (s2c_tx, s2c_rx) = mpsc::channel::<ConnMessage>(64);
+        acceptor
+            .accept_connection(
+                Box::new(s2c_tx),
+                Box::new(c2s_rx),
+                user_id,
+                ProtocolVersion::CURRENT,
+                ConnectionMeta::default(),
+            )
+            .await?;
+        Ok(Self {
+            c2s: c2s_tx,
+            s2c: s2c_rx,
+        })
+    }
+
+    pub async fn send(&self, command: &C2SCommand) -> anyhow::Result<()> {
+        let text = serde_json::to_string(command)?;
+        self.c2s.send(ConnMessage::Text(text)).await?;
+        Ok(())
+    }
+
+    /// The next event, skipping pings and frames that are not events.
+    pub async fn next_event(&mut self) -> anyhow::Result<S2CEvent> {
+        loop {
+            let message = tokio::time::timeout(EVENT_TIMEOUT, self.s2c.recv())
+                .await
+                .map_err(|_| anyhow::anyhow!("no event within {:?}", EVENT_TIMEOUT))?
+                .ok_or_else(|| anyhow::anyhow!("connection closed"))?;
+            if let ConnMessage::Text(text) = message
+                && let Ok(event) = serde_json::from_str::<S2CEvent>(&text)
+            {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Skips events until `pick` accepts one.
+    pub async fn expect<T>(
+        &mut self,
+        mut pick: impl FnMut(S2CEvent) -> Option<T>,
+    ) -> anyhow::Result<T> {
+        loop {
+            if let Some(found) = pick(self.next_event().await?) {
+                return Ok(found);
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::application_port::*;
+use crate::domain_model::UserId;
+use crate::it::TestClient;
+use crate::logger::Secret;
+use crate::server::Server;
+use crate::settings::{Settings, parse_settings};
+use sqlx::{Connection, MySql, MySqlConnection, Pool};
+use testcontainers_modules::kafka::apache::{KAFKA_PORT, Kafka};
+use testcontainers_modules::mysql::Mysql;
+use testcontainers_modules::redis::Redis;
+use testcontainers_modules::testcontainers::core::IntoContainerPort;
+use testcontainers_modules::testcontainers::runners::AsyncRunner;
+use testcontainers_modules::testcontainers::{ContainerAsync, ImageExt};
+
+const SCHEMA: &str = include_str!("../../sql/create_tables.sql");
+
+// same images as docker-compose.yaml
+const MYSQL_TAG: &str = "9.5.0-oraclelinux9";
+const REDIS_TAG: &str = "8.4.0-bookworm";
+
+pub(crate) const TEST_PASSWORD: &str = "test_password";
+
+/// MySQL, Redis and Kafka for one test; the containers go away on drop.
+pub(crate) struct TestInfra {
+    pub mysql_dsn: String,
+    pub redis_dsn: String,
+    pub bootstrap_servers: String,
+    _mysql: ContainerAsync<Mysql>,
+    _redis: ContainerAsync<Redis>,
+    _kafka: ContainerAsync<Kafka>,
+}
+
+impl TestInfra {
+    /// Starts all three containers and applies `sql/create_tables.sql`.
+    pub async fn start() -> anyhow::Result<Self> {
+        let (mysql, redis, kafka) = tokio::try_join!(
+            Mysql::default().with_tag(MYSQL_TAG).start(),
+            Redis::default().with_tag(REDIS_TAG).start(),
+            Kafka::default().start(),
+        )?;
+
+        let mysql_addr = format!(
+            "{}:{}",
+            mysql.get_host().await?,
+            mysql.get_host_port_ipv4(3306.tcp()).await?
+        );
+        let mut conn = MySqlConnection::connect(&format!("mysql://root@{mysql_addr}/test")).await?;
+        sqlx::raw_sql(SCHEMA).execute(&mut conn).await?;
+        conn.close().await?;
+
+        Ok(Self {
+            mysql_dsn: format!("mysql://root@{mysql_addr}/counterpoint_db"),
+            redis_dsn: format!(
+                "redis://{}:{}",
+                redis.get_host().await?,
+                redis.get_host_port_ipv4(6379.tcp()).await?
+            ),
+            bootstrap_servers: format!("127.0.0.1:{}", kafka.get_host_port_ipv4(KAFKA_PORT).await?),
+            _mysql: mysql,
+            _redis: redis,
+            _kafka: kafka,
+        })
+    }
+
+    pub async fn pool(&self) -> anyhow::Result<Pool<MySql>> {
+        Ok(Pool::<MySql>::connect(&self.mysql_dsn).await?)
+    }
+
+    /// `settings/dev.toml` pointed at these containers, with real storage,
+    /// auth and users.
+    pub fn settings(&self) -> anyhow::Result<Settings> {
+        let mut settings = parse_settings(Some(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/settings/dev.toml"
+        )))?;
+        settings.storage.backend = "real".to_string();
+        settings.storage.mysql_dsn = self.mysql_dsn.clone();
+        settings.storage.redis_dsn = self.redis_dsn.clone();
+        settings.events.bootstrap_servers = self.bootstrap_servers.clone();
+        settings.auth.backend = "real".to_string();
+        settings.user.backend = "real".to_string();
+        Ok(settings)
+    }
+}
+
+/// A user signed up and logged in through the real auth service.
+pub(crate) struct TestUser {
+    pub user_id: UserId,
+    pub username: String,
+}
+
+/// A real `Server` wired to its own `TestInfra`.
+pub(crate) struct TestServer {
+    pub server: Server,
+    _infra: TestInfra,
+}
+
+impl TestServer {
+    pub async fn start() -> anyhow::Result<Self> {
+        let infra = TestInfra::start().await?;
+        let server = Server::try_new(&infra.settings()?).await?;
+        Ok(Self {
+            server,
+            _infra: infra,
+        })
+    }
+
+    /// Signs up `username` with `TEST_PASSWORD` and logs in.
+    pub async fn user(&self, username: &str) -> anyhow::Result<TestUser> {
+        let auth = &self.server.auth_service;
+        auth.signup(SignupInput {
+            username: username.to_string(),
+            password: Secret::new(TEST_PASSWORD.to_string()),
+            invite_code: None,
+        })
+        .await?;
+        let login = auth
+            .login(LoginInput {
+                username: username.to_string(),
+                password: Secret::new(TEST_PASSWORD.to_string()),
+                device: None,
+            })
+            .await?;
+        Ok(TestUser {
+            user_id: login.user_id,
+            username: username.to_string(),
+        })
+    }
+
+    pub async fn connect(&self, user: &TestUser) -> anyhow::Result<TestClient> {
+        TestClient::connect(self.server.connection_acceptor.as_ref(), user.user_id).await
+    }
+
+    pub async fn shutdown(self) {
+        self.server.shutdown().await;
+    }
+}
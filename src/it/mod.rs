@@ -0,0 +1,12 @@
+//! The `it` module holds integration tests against real MySQL, Redis and Kafka,
+//! started per test with testcontainers. They need a Docker daemon, so they are
+//! ignored by default: `cargo test it:: -- --ignored`.
+
+mod client;
+mod harness;
+
+mod chat_flow;
+mod mysql_repos;
+
+pub(crate) use client::*;
+pub(crate) use harness::*;
@@ -0,0 +1,75 @@
+use crate::domain_model::*;
+use crate::domain_port::*;
+use crate::infra_mysql::*;
+use crate::it::TestInfra;
+use chrono::Utc;
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs docker"]
+async fn user_repo_create_and_deactivate() -> anyhow::Result<()> {
+    let infra = TestInfra::start().await?;
+    let pool = infra.pool().await?;
+    let tx_manager = MySqlTxManager::new(pool.clone());
+    let users = MySqlUserRepo::new(pool);
+    let user_id = UserId(uuid::Uuid::new_v4());
+
+    let mut tx = tx_manager.begin().await?;
+    users.create_in_tx(&mut *tx, user_id, "carol").await?;
+    tx.commit().await?;
+    assert!(users.username_exists("carol").await?);
+    assert!(users.id_exists(user_id).await?);
+
+    let mut tx = tx_manager.begin().await?;
+    assert_eq!(
+        users.get_id_by_username_in_tx(&mut *tx, "carol").await?,
+        user_id
+    );
+    assert!(users.deactivate_in_tx(&mut *tx, user_id).await?);
+    assert!(!users.deactivate_in_tx(&mut *tx, user_id).await?);
+    tx.commit().await?;
+    assert!(!users.id_exists(user_id).await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs docker"]
+async fn outbox_event_is_claimed_until_delivered() -> anyhow::Result<()> {
+    let infra = TestInfra::start().await?;
+    let pool = infra.pool().await?;
+    let tx_manager = MySqlTxManager::new(pool.clone());
+    let outbox = MySqlOutboxRepo::new(pool);
+    let receiver = UserId(uuid::Uuid::new_v4());
+    let event = OutboxEvent::new(
+        EventType::SessionTerminated,
+        Some(receiver.0),
+        vec![receiver],
+        &S2CEvent::SessionTerminated(SessionTerminated {
+            reason: TerminationReason::Deactivated,
+        }),
+    )?;
+
+    let mut tx = tx_manager.begin().await?;
+    outbox.enqueue_in_tx(&mut *tx, &event).await?;
+    tx.commit().await?;
+
+    let mut tx = tx_manager.begin().await?;
+    let claimed = outbox
+        .claim_ready_batch_in_tx(&mut *tx, Utc::now(), 10)
+        .await?;
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].event_id, event.event_id);
+    outbox
+        .mark_delivered_in_tx(&mut *tx, event.event_id, Utc::now())
+        .await?;
+    tx.commit().await?;
+
+    let mut tx = tx_manager.begin().await?;
+    assert!(
+        outbox
+            .claim_ready_batch_in_tx(&mut *tx, Utc::now(), 10)
+            .await?
+            .is_empty()
+    );
+    tx.commit().await?;
+    Ok(())
+}
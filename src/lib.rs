@@ -1,12 +1,24 @@
 pub mod api;
 pub mod logger;
+pub mod metrics;
+pub mod protocol;
 pub mod settings;
 
 pub mod server;
 
-pub mod application_port;
 pub mod application_impl;
+pub mod application_port;
 pub mod domain_model;
 pub mod domain_port;
+pub mod infra_encrypted;
+pub mod infra_flaky;
+pub mod infra_http;
+pub mod infra_instrumented;
+pub mod infra_memory;
 pub mod infra_mysql;
 pub mod infra_redis;
+pub mod infra_s3;
+pub mod infra_timeout;
+
+#[cfg(test)]
+mod it;
@@ -1,3 +1,4 @@
+use super::redact::Redacting;
 use anyhow::{Result, anyhow};
 use tracing_subscriber::{
     EnvFilter, Registry, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt,
@@ -18,7 +19,7 @@ impl Logger {
 
         tracing_subscriber::registry()
             .with(filter)
-            .with(fmt::layer())
+            .with(fmt::layer().with_writer(Redacting(std::io::stdout)))
             .init();
 
         Self { reload_handle }
@@ -2,6 +2,10 @@
 //! See `bin/logger_demo.rs` for a test binary demonstrating its usage.
 
 mod logger;
+mod redact;
+mod secret;
 pub use logger::*;
+pub use redact::*;
+pub use secret::*;
 
 pub use tracing::{debug, error, info, trace, warn};
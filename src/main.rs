@@ -1,11 +1,18 @@
+#![recursion_limit = "256"]
+
 use counterpoint::api;
 use counterpoint::logger::*;
 use counterpoint::server::*;
 use counterpoint::settings::*;
+use futures_util::future::try_join_all;
+use std::convert::Infallible;
 use std::fs;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
-use warp::Filter;
+use tokio_util::sync::CancellationToken;
+use warp::{Filter, Reply};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -20,36 +27,64 @@ async fn main() -> anyhow::Result<()> {
     };
     logger.reload_from_config(&logger_config)?;
 
-    let address: std::net::SocketAddr = project_settings.http.address.parse()?;
-    if !fs::metadata(&project_settings.http.cert_path)?.is_file() {
-        return Err(anyhow::anyhow!(
-            "TLS cert is not a regular file: {:?}",
-            project_settings.http.cert_path
-        ));
-    }
-    if !fs::metadata(&project_settings.http.key_path)?.is_file() {
-        return Err(anyhow::anyhow!(
-            "TLS key is not a regular file: {:?}",
-            project_settings.http.key_path
-        ));
+    let http = &project_settings.http;
+    let listeners = match http.listeners.as_slice() {
+        [] => vec![Listener {
+            address: http.address.clone(),
+            tls: true,
+            routes: "all".to_string(),
+        }],
+        listeners => listeners.to_vec(),
+    };
+    let addresses = listeners
+        .iter()
+        .map(|listener| listener.address.parse())
+        .collect::<Result<Vec<SocketAddr>, _>>()?;
+
+    if listeners.iter().any(|listener| listener.tls) {
+        if !fs::metadata(&http.cert_path)?.is_file() {
+            return Err(anyhow::anyhow!(
+                "TLS cert is not a regular file: {:?}",
+                http.cert_path
+            ));
+        }
+        if !fs::metadata(&http.key_path)?.is_file() {
+            return Err(anyhow::anyhow!(
+                "TLS key is not a regular file: {:?}",
+                http.key_path
+            ));
+        }
+        if let Some(ca_path) = &http.client_ca_path
+            && !fs::metadata(ca_path)?.is_file()
+        {
+            return Err(anyhow::anyhow!(
+                "Client CA bundle is not a regular file: {:?}",
+                ca_path
+            ));
+        }
     }
 
     let server = Arc::new(Server::try_new(&project_settings).await?);
 
-    let api_v1 = warp::path("api")
-        .and(warp::path("v1"))
-        .and(api::v1::routes(server.clone()))
-        .recover(api::v1::recover_error);
-
-    warp::serve(api_v1)
-        .tls()
-        .cert_path(project_settings.http.cert_path.clone())
-        .key_path(project_settings.http.key_path.clone())
-        .bind_with_graceful_shutdown(address, async {
+    let shutdown = CancellationToken::new();
+    let mut servers = Vec::new();
+    for (listener, address) in listeners.iter().zip(addresses) {
+        let api_v1 = api_v1(server.clone(), &listener.routes)?;
+        info!(%address, tls = listener.tls, routes = %listener.routes, "listening");
+        servers.push(serve(api_v1, address, listener.tls, http, shutdown.clone()));
+    }
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        let server = server.clone();
+        let grace = Duration::from_secs(http.drain_grace_secs);
+        async move {
             signal::ctrl_c().await.expect("Could not register SIGINT");
-        })
-        .1
-        .await;
+            info!(?grace, "draining before shutdown");
+            server.drain(grace).await;
+            shutdown.cancel();
+        }
+    });
+    try_join_all(servers).await?;
 
     let shutdown_timeout = std::time::Duration::from_secs(100);
     match tokio::time::timeout(shutdown_timeout, server.shutdown()).await {
@@ -59,3 +94,56 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// `/api/v1` with the route set a listener asked for.
+fn api_v1(
+    server: Arc<Server>,
+    routes: &str,
+) -> anyhow::Result<impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone + 'static> {
+    let routes = match routes {
+        "all" => api::v1::routes(server).map(boxed_reply).boxed(),
+        "public" => api::v1::public_routes(server).map(boxed_reply).boxed(),
+        "admin" => api::v1::admin_routes(server).map(boxed_reply).boxed(),
+        other => return Err(anyhow::anyhow!("Unknown listener routes: {}", other)),
+    };
+    Ok(api::v1::recover_errors(
+        warp::path("api").and(warp::path("v1")).and(routes),
+    ))
+}
+
+fn boxed_reply(reply: impl Reply + 'static) -> Box<dyn Reply> {
+    Box::new(reply)
+}
+
+async fn serve<F, R>(
+    filter: F,
+    address: SocketAddr,
+    tls: bool,
+    http: &Http,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()>
+where
+    F: Filter<Extract = (R,), Error = Infallible> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    let shutdown = shutdown.cancelled_owned();
+    match (tls, &http.client_ca_path) {
+        (true, Some(_)) => api::serve_mtls(filter, address, http, shutdown).await?,
+        (true, None) => {
+            warp::serve(filter)
+                .tls()
+                .cert_path(&http.cert_path)
+                .key_path(&http.key_path)
+                .bind_with_graceful_shutdown(address, shutdown)
+                .1
+                .await
+        }
+        (false, _) => {
+            warp::serve(filter)
+                .bind_with_graceful_shutdown(address, shutdown)
+                .1
+                .await
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,5 @@
+//! The `metrics` module owns the process-wide Prometheus registry.
+//! Collectors are registered on first use and rendered by the `/metrics` route.
+
+mod registry;
+pub use registry::*;
@@ -0,0 +1,187 @@
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+
+pub static REGISTRY: LazyLock<Registry> =
+    LazyLock::new(|| Registry::new_custom(Some("counterpoint".to_string()), None).unwrap());
+
+fn register<C: Collector + Clone + 'static>(collector: C) -> C {
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("metric registered twice");
+    collector
+}
+
+fn int_gauge(name: &str, help: &str) -> IntGauge {
+    register(IntGauge::new(name, help).expect("valid gauge"))
+}
+
+fn int_gauge_vec(name: &str, help: &str, labels: &[&str]) -> IntGaugeVec {
+    register(IntGaugeVec::new(Opts::new(name, help), labels).expect("valid gauge"))
+}
+
+fn int_counter(name: &str, help: &str) -> IntCounter {
+    register(IntCounter::new(name, help).expect("valid counter"))
+}
+
+fn int_counter_vec(name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    register(IntCounterVec::new(Opts::new(name, help), labels).expect("valid counter"))
+}
+
+fn histogram(name: &str, help: &str, buckets: Vec<f64>) -> Histogram {
+    register(
+        Histogram::with_opts(HistogramOpts::new(name, help).buckets(buckets))
+            .expect("valid histogram"),
+    )
+}
+
+fn histogram_vec(name: &str, help: &str, labels: &[&str]) -> HistogramVec {
+    register(HistogramVec::new(HistogramOpts::new(name, help), labels).expect("valid histogram"))
+}
+
+/// `"ok"` or `"error"`, for call sites without a finer outcome.
+pub fn outcome<T, E>(result: &Result<T, E>) -> &'static str {
+    if result.is_ok() { "ok" } else { "error" }
+}
+
+// region delivery pipeline
+
+pub static NOTIFIER_LAST_TICK: LazyLock<IntGauge> = LazyLock::new(|| {
+    int_gauge(
+        "notifier_last_tick_timestamp_seconds",
+        "Unix time of the last successful outbox relay tick",
+    )
+});
+
+pub static NOTIFIER_ERRORS: LazyLock<IntCounter> =
+    LazyLock::new(|| int_counter("notifier_errors_total", "Failed outbox relay ticks"));
+
+pub static OUTBOX_BACKLOG: LazyLock<IntGauge> =
+    LazyLock::new(|| int_gauge("outbox_backlog", "Outbox events not yet delivered to Kafka"));
+
+pub static CONSUMER_LAST_COMMIT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    int_gauge_vec(
+        "consumer_last_commit_timestamp_seconds",
+        "Unix time of the last committed Kafka message",
+        &["group"],
+    )
+});
+
+pub static CONSUMER_LAG: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    int_gauge_vec(
+        "consumer_lag",
+        "Messages between the consumed position and the high watermark",
+        &["group"],
+    )
+});
+
+pub static CONSUMER_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    int_counter_vec(
+        "consumer_errors_total",
+        "Kafka poll, handler and commit errors",
+        &["group"],
+    )
+});
+
+/// Upper bounds, in seconds, for the delivery latency histograms.
+pub const DELIVERY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+pub static DELIVERY_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    histogram(
+        "delivery_seconds",
+        "Outbox write to socket write, per delivered event",
+        DELIVERY_BUCKETS.to_vec(),
+    )
+});
+
+pub static DELIVERY_STAGE_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "delivery_stage_seconds",
+                "Time spent in each delivery stage: outbox, broker or mailbox",
+            )
+            .buckets(DELIVERY_BUCKETS.to_vec()),
+            &["stage"],
+        )
+        .expect("valid histogram"),
+    )
+});
+
+// endregion
+
+// region relationships and conversations
+
+pub static FRIENDSHIPS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    int_counter_vec(
+        "friendship_requests_total",
+        "add_friend calls by outcome",
+        &["outcome"],
+    )
+});
+
+pub static GROUPS_CREATED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    int_counter_vec(
+        "group_create_requests_total",
+        "create_group calls by outcome",
+        &["outcome"],
+    )
+});
+
+pub static GROUP_INVITES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    int_counter_vec(
+        "group_invites_total",
+        "invite_to_group calls by outcome",
+        &["outcome"],
+    )
+});
+
+pub static MESSAGES_SENT: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    int_counter_vec(
+        "messages_sent_total",
+        "send_message calls by outcome",
+        &["outcome"],
+    )
+});
+
+pub static HISTORY_PAGE_SIZE: LazyLock<Histogram> = LazyLock::new(|| {
+    histogram(
+        "history_page_size",
+        "Messages returned per history page",
+        vec![0.0, 1.0, 5.0, 10.0, 20.0, 50.0, 100.0],
+    )
+});
+
+pub static MEMBERSHIP_CHECK_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    histogram_vec(
+        "membership_check_seconds",
+        "Latency of conversation membership checks",
+        &["outcome"],
+    )
+});
+
+// endregion
+
+// region storage
+
+pub static REPO_CALL_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    histogram_vec(
+        "repo_call_seconds",
+        "Latency of instrumented repo and store calls",
+        &["repo", "method", "outcome"],
+    )
+});
+
+// endregion
+
+/// Render every registered collector in the Prometheus text format.
+pub fn render() -> anyhow::Result<String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
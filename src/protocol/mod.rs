@@ -0,0 +1,7 @@
+//! The `protocol` module describes the WebSocket wire format: the JSON Schema
+//! export used by client teams and the per-connection version negotiation.
+
+mod schema;
+mod version;
+pub use schema::*;
+pub use version::*;
@@ -0,0 +1,24 @@
+use crate::domain_model::*;
+use schemars::{Schema, schema_for};
+
+/// A named schema document, written out as `<name>.json`.
+pub struct SchemaDocument {
+    pub name: &'static str,
+    pub schema: Schema,
+}
+
+/// Schemas for every top-level frame on the WebSocket connection.
+///
+/// Payload structs are inlined into the `$defs` of the frame that carries them.
+pub fn protocol_schemas() -> Vec<SchemaDocument> {
+    vec![
+        SchemaDocument {
+            name: "c2s_command",
+            schema: schema_for!(C2SCommand),
+        },
+        SchemaDocument {
+            name: "s2c_event",
+            schema: schema_for!(S2CEvent),
+        },
+    ]
+}
@@ -0,0 +1,197 @@
+use crate::domain_model::*;
+use crate::logger::Secret;
+use serde::{Deserialize, Serialize};
+
+/// Wire protocol revision negotiated when a client opens the chat socket.
+///
+/// Clients that predate negotiation send nothing and are treated as `V2`,
+/// the events the server sent before versions existed.
+#[derive(
+    Debug, Default, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum ProtocolVersion {
+    /// Chat messages, friendships and group creation.
+    V1 = 1,
+    /// Adds `GroupMemberNew`.
+    #[default]
+    V2 = 2,
+    /// Adds `SessionTerminated`.
+    V3 = 3,
+    /// Adds `ChatTyping`.
+    V4 = 4,
+    /// Adds `ConversationMetaChanged`.
+    V5 = 5,
+    /// Adds `SecurityAlert`.
+    V6 = 6,
+    /// Adds `ChatRead`.
+    V7 = 7,
+    /// Adds `ConversationFrozen`.
+    V8 = 8,
+    /// Adds `ChatMessageEdited`.
+    V9 = 9,
+    /// Adds `ChatMessageDeleted`.
+    V10 = 10,
+    /// Adds `GroupUpdated`.
+    V11 = 11,
+    /// Adds `TypingEvent`, which older clients get as `ChatTyping`.
+    V12 = 12,
+    /// Adds `MessagePinned`.
+    V13 = 13,
+    /// Adds `ChatMentionNew`.
+    V14 = 14,
+    /// Adds `ChatMessageDelivered`, and the `Delivered` command that makes
+    /// the server send it.
+    V15 = 15,
+    /// Adds the `HistorySince` command.
+    V16 = 16,
+    /// Adds `SystemNotice`.
+    V17 = 17,
+    /// Adds `ConversationSnapshot`.
+    V18 = 18,
+}
+
+impl ProtocolVersion {
+    pub const OLDEST: ProtocolVersion = ProtocolVersion::V1;
+    pub const CURRENT: ProtocolVersion = ProtocolVersion::V18;
+}
+
+impl TryFrom<u16> for ProtocolVersion {
+    type Error = String;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(ProtocolVersion::V1),
+            2 => Ok(ProtocolVersion::V2),
+            3 => Ok(ProtocolVersion::V3),
+            4 => Ok(ProtocolVersion::V4),
+            5 => Ok(ProtocolVersion::V5),
+            6 => Ok(ProtocolVersion::V6),
+            7 => Ok(ProtocolVersion::V7),
+            8 => Ok(ProtocolVersion::V8),
+            9 => Ok(ProtocolVersion::V9),
+            10 => Ok(ProtocolVersion::V10),
+            11 => Ok(ProtocolVersion::V11),
+            12 => Ok(ProtocolVersion::V12),
+            13 => Ok(ProtocolVersion::V13),
+            14 => Ok(ProtocolVersion::V14),
+            15 => Ok(ProtocolVersion::V15),
+            16 => Ok(ProtocolVersion::V16),
+            17 => Ok(ProtocolVersion::V17),
+            18 => Ok(ProtocolVersion::V18),
+            other => Err(format!("unsupported protocol version: {other}")),
+        }
+    }
+}
+
+impl From<ProtocolVersion> for u16 {
+    fn from(version: ProtocolVersion) -> Self {
+        version as u16
+    }
+}
+
+/// How much of a long message a data-saver connection gets up front.
+pub const DATA_SAVER_PREVIEW_BYTES: usize = 256;
+
+/// Serializes server events for one client, in the revision it negotiated.
+#[derive(Debug, Clone, Copy)]
+pub struct EventEncoder {
+    version: ProtocolVersion,
+    /// Data-saver mode: `ChatMessageNew` content longer than this is cut.
+    preview_limit: Option<usize>,
+}
+
+impl EventEncoder {
+    pub fn new(version: ProtocolVersion) -> Self {
+        Self {
+            version,
+            preview_limit: None,
+        }
+    }
+
+    /// Cuts message content to `limit` bytes and marks it `truncated`.
+    pub fn with_preview_limit(self, limit: usize) -> Self {
+        Self {
+            preview_limit: Some(limit),
+            ..self
+        }
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    /// Returns `None` when the event has no representation in the client's
+    /// revision and should be dropped rather than sent.
+    pub fn encode(&self, event: &S2CEvent) -> anyhow::Result<Option<String>> {
+        // older clients only know "is typing" and let it lapse on their own
+        if let S2CEvent::TypingEvent(typing) = event
+            && self.version < introduced_in(event)
+        {
+            if !typing.typing {
+                return Ok(None);
+            }
+            return self.encode(&S2CEvent::ChatTyping(ChatTyping {
+                conversation_id: typing.conversation_id,
+                users: vec![typing.user_id],
+            }));
+        }
+        if self.version < introduced_in(event) {
+            return Ok(None);
+        }
+        if let (Some(limit), S2CEvent::ChatMessageNew(message)) = (self.preview_limit, event)
+            && message.content.expose().len() > limit
+        {
+            let preview = S2CEvent::ChatMessageNew(preview(message, limit));
+            return Ok(Some(serde_json::to_string(&preview)?));
+        }
+        Ok(Some(serde_json::to_string(event)?))
+    }
+}
+
+/// `message` with its content cut to at most `limit` bytes, on a char
+/// boundary.
+fn preview(message: &ChatMessageNew, limit: usize) -> ChatMessageNew {
+    let content = message.content.expose();
+    let mut end = limit.min(content.len());
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    ChatMessageNew {
+        conversation_id: message.conversation_id,
+        message_id: message.message_id,
+        message_offset: message.message_offset,
+        content: Secret::new(content[..end].to_owned()),
+        sender: message.sender,
+        username: message.username.clone(),
+        created_at: message.created_at,
+        truncated: true,
+        reply_to: message.reply_to,
+        attachments: message.attachments.clone(),
+    }
+}
+
+fn introduced_in(event: &S2CEvent) -> ProtocolVersion {
+    match event {
+        S2CEvent::ChatMessageACK(_)
+        | S2CEvent::ChatMessageNew(_)
+        | S2CEvent::FriendshipNew(_)
+        | S2CEvent::GroupNew(_) => ProtocolVersion::V1,
+        S2CEvent::GroupMemberNew(_) => ProtocolVersion::V2,
+        S2CEvent::SessionTerminated(_) => ProtocolVersion::V3,
+        S2CEvent::ChatTyping(_) => ProtocolVersion::V4,
+        S2CEvent::ConversationMetaChanged(_) => ProtocolVersion::V5,
+        S2CEvent::SecurityAlert(_) => ProtocolVersion::V6,
+        S2CEvent::ChatRead(_) => ProtocolVersion::V7,
+        S2CEvent::ConversationFrozen(_) => ProtocolVersion::V8,
+        S2CEvent::ChatMessageEdited(_) => ProtocolVersion::V9,
+        S2CEvent::ChatMessageDeleted(_) => ProtocolVersion::V10,
+        S2CEvent::GroupUpdated(_) => ProtocolVersion::V11,
+        S2CEvent::TypingEvent(_) => ProtocolVersion::V12,
+        S2CEvent::MessagePinned(_) => ProtocolVersion::V13,
+        S2CEvent::ChatMentionNew(_) => ProtocolVersion::V14,
+        S2CEvent::ChatMessageDelivered(_) => ProtocolVersion::V15,
+        S2CEvent::SystemNotice(_) => ProtocolVersion::V17,
+        S2CEvent::ConversationSnapshot(_) => ProtocolVersion::V18,
+    }
+}
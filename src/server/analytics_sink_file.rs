@@ -0,0 +1,55 @@
+use crate::server::AnalyticsSink;
+use chrono::Utc;
+use std::path::PathBuf;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Appends records to `events-<YYYYMMDDHH>-<node>.ndjson` under `dir`,
+/// starting a new file every UTC hour. Closed files are left for a shipper
+/// to move into ClickHouse or object storage.
+pub struct NdjsonFileSink {
+    dir: PathBuf,
+    node: String,
+    current: Mutex<Option<(String, File)>>,
+}
+
+impl NdjsonFileSink {
+    pub fn new(dir: PathBuf, node: String) -> Self {
+        Self {
+            dir,
+            node,
+            current: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AnalyticsSink for NdjsonFileSink {
+    async fn append(&self, line: &[u8]) -> anyhow::Result<()> {
+        let hour = Utc::now().format("%Y%m%d%H").to_string();
+        let mut current = self.current.lock().await;
+        if current.as_ref().is_none_or(|(open, _)| *open != hour) {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            let path = self
+                .dir
+                .join(format!("events-{}-{}.ndjson", hour, self.node));
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            *current = Some((hour, file));
+        }
+        let Some((_, file)) = current.as_mut() else {
+            unreachable!("opened above");
+        };
+
+        let mut record = Vec::with_capacity(line.len() + 1);
+        record.extend_from_slice(line);
+        record.push(b'\n');
+        file.write_all(&record).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
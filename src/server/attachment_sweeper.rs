@@ -0,0 +1,95 @@
+use crate::domain_port::*;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Uploads deleted per transaction.
+const SWEEP_BATCH: u32 = 500;
+
+/// Periodically deletes uploads that never went out with a message once they
+/// are older than `retention`, blobs included. A blob that fails to delete
+/// is only logged; nothing refers to it anymore.
+pub struct AttachmentSweeper {
+    attachment_repo: Arc<dyn AttachmentRepo>,
+    blob_store: Arc<dyn BlobStore>,
+    tx_manager: Arc<dyn TxManager>,
+    retention: Duration,
+    interval: Duration,
+    cancellation_token: CancellationToken,
+}
+
+impl AttachmentSweeper {
+    pub fn new(
+        attachment_repo: Arc<dyn AttachmentRepo>,
+        blob_store: Arc<dyn BlobStore>,
+        tx_manager: Arc<dyn TxManager>,
+        retention: Duration,
+        interval: Duration,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            attachment_repo,
+            blob_store,
+            tx_manager,
+            retention,
+            interval,
+            cancellation_token,
+        }
+    }
+
+    /// Deletes one batch's rows and then its blobs; returns the batch size.
+    async fn sweep_batch(&self) -> anyhow::Result<usize> {
+        let before = Utc::now() - chrono::Duration::from_std(self.retention)?;
+        let mut tx = self.tx_manager.begin().await?;
+        let swept = self
+            .attachment_repo
+            .delete_unattached_in_tx(&mut *tx, before, SWEEP_BATCH)
+            .await?;
+        tx.commit().await?;
+
+        for attachment in &swept {
+            let key = attachment.blob_key();
+            if let Err(e) = self.blob_store.delete(&key).await {
+                tracing::warn!("swept upload [{key}] kept its blob: {e:#}");
+            }
+        }
+        Ok(swept.len())
+    }
+
+    /// Batches until one comes back short.
+    async fn sweep(&self) {
+        let mut total = 0;
+        while !self.cancellation_token.is_cancelled() {
+            match self.sweep_batch().await {
+                Ok(n) => {
+                    total += n;
+                    if n < SWEEP_BATCH as usize {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Attachment sweeper error: {e:#}");
+                    break;
+                }
+            }
+        }
+        if total > 0 {
+            tracing::info!("swept {total} unattached uploads");
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancellation_token.cancelled() => {
+                    tracing::info!("Attachment sweeper shutting down...");
+                    break;
+                }
+                _ = ticker.tick() => self.sweep().await,
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::domain_model::*;
+use crate::server::EventPublisher;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Typing notices held back for one conversation.
+#[derive(Default)]
+struct Batch {
+    users: BTreeSet<UserId>,
+    receivers: BTreeSet<UserId>,
+    trace_id: Option<TraceId>,
+    /// The oldest notice's, so delivery latency includes the wait here.
+    created_at: Option<DateTime<Utc>>,
+}
+
+/// `EventPublisher` that holds `ChatTyping` back for `window` and then
+/// publishes one event per conversation naming everyone who typed, so a
+/// busy 500-member group costs one event per window instead of hundreds.
+/// Everything else goes straight through.
+///
+/// The notifier counts a held notice as delivered; one still waiting when
+/// the node stops is lost, which typing can afford.
+pub struct DebouncingPublisher {
+    inner: Arc<dyn EventPublisher>,
+    window: Duration,
+    pending: Arc<Mutex<HashMap<(String, ConversationId), Batch>>>,
+}
+
+impl DebouncingPublisher {
+    pub fn new(inner: Arc<dyn EventPublisher>, window: Duration) -> Self {
+        Self {
+            inner,
+            window,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Publishes whatever collected for the conversation once the window
+    /// that its first notice opened has passed.
+    fn flush_later(&self, topic: String, conversation_id: ConversationId) {
+        let inner = self.inner.clone();
+        let pending = self.pending.clone();
+        let window = self.window;
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+            let Some(batch) = pending
+                .lock()
+                .ok()
+                .and_then(|mut p| p.remove(&(topic.clone(), conversation_id)))
+            else {
+                return;
+            };
+
+            let envelope = S2CEnvelope {
+                receivers: batch.receivers.into_iter().collect(),
+                body: S2CEvent::ChatTyping(ChatTyping {
+                    conversation_id,
+                    users: batch.users.into_iter().collect(),
+                }),
+                trace_id: batch.trace_id,
+                created_at: batch.created_at,
+                published_at: Some(Utc::now()),
+            };
+            let result = match serde_json::to_vec(&envelope) {
+                Ok(payload) => {
+                    inner
+                        .publish(&topic, conversation_id.0.as_bytes(), &payload)
+                        .await
+                }
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                tracing::warn!("typing notice for [{}] dropped: {e:#}", conversation_id.0);
+            }
+        });
+    }
+}
+
+#[async_trait::async_trait]
+impl EventPublisher for DebouncingPublisher {
+    async fn publish(&self, topic: &str, key: &[u8], payload: &[u8]) -> anyhow::Result<()> {
+        let Ok(S2CEnvelope {
+            receivers,
+            body: S2CEvent::ChatTyping(typing),
+            trace_id,
+            created_at,
+            ..
+        }) = serde_json::from_slice::<S2CEnvelope>(payload)
+        else {
+            return self.inner.publish(topic, key, payload).await;
+        };
+
+        let batch_key = (topic.to_owned(), typing.conversation_id);
+        let opened = {
+            let mut pending = self
+                .pending
+                .lock()
+                .map_err(|e| anyhow::anyhow!("typing batches poisoned: {e}"))?;
+            let opened = !pending.contains_key(&batch_key);
+            let batch = pending.entry(batch_key).or_default();
+            batch.users.extend(typing.users);
+            batch.receivers.extend(receivers);
+            batch.trace_id = batch.trace_id.or(trace_id);
+            batch.created_at = match (batch.created_at, created_at) {
+                (Some(held), Some(new)) => Some(held.min(new)),
+                (held, new) => held.or(new),
+            };
+            opened
+        };
+        if opened {
+            self.flush_later(topic.to_owned(), typing.conversation_id);
+        }
+        Ok(())
+    }
+}
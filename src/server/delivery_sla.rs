@@ -0,0 +1,122 @@
+use crate::metrics;
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// When an event passed each stage on its way to a socket.
+#[derive(Debug, Clone, Copy)]
+pub struct DeliveryStamps {
+    /// The outbox row was written.
+    pub created_at: DateTime<Utc>,
+    /// The notifier handed it to the broker.
+    pub published_at: Option<DateTime<Utc>>,
+    /// Fan-out put it in the receiver's mailbox.
+    pub enqueued_at: DateTime<Utc>,
+}
+
+/// Seconds from `from` to `to`; clocks on different nodes may disagree, so
+/// never below zero.
+fn elapsed(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).to_std().unwrap_or_default().as_secs_f64()
+}
+
+/// Delivery latency per stage and end to end, recorded once the frame is
+/// written to the socket.
+///
+/// Besides the Prometheus histograms it keeps its own bucket counts for the
+/// current window, so `run` can log an alarm when the window's p99 is over
+/// the threshold without a Prometheus server doing the math.
+pub struct DeliverySla {
+    p99_alarm: Duration,
+    window: Duration,
+    /// One per `metrics::DELIVERY_BUCKETS` bound, plus one for anything slower.
+    window_counts: [AtomicU64; metrics::DELIVERY_BUCKETS.len() + 1],
+    cancellation_token: CancellationToken,
+}
+
+impl DeliverySla {
+    /// A zero `p99_alarm` records latencies but never alarms.
+    pub fn new(
+        p99_alarm: Duration,
+        window: Duration,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            p99_alarm,
+            window,
+            window_counts: Default::default(),
+            cancellation_token,
+        }
+    }
+
+    pub fn written(&self, stamps: &DeliveryStamps) {
+        let now = Utc::now();
+        if let Some(published_at) = stamps.published_at {
+            metrics::DELIVERY_STAGE_SECONDS
+                .with_label_values(&["outbox"])
+                .observe(elapsed(stamps.created_at, published_at));
+            metrics::DELIVERY_STAGE_SECONDS
+                .with_label_values(&["broker"])
+                .observe(elapsed(published_at, stamps.enqueued_at));
+        }
+        metrics::DELIVERY_STAGE_SECONDS
+            .with_label_values(&["mailbox"])
+            .observe(elapsed(stamps.enqueued_at, now));
+
+        let total = elapsed(stamps.created_at, now);
+        metrics::DELIVERY_SECONDS.observe(total);
+        let bucket = metrics::DELIVERY_BUCKETS
+            .iter()
+            .position(|bound| total <= *bound)
+            .unwrap_or(metrics::DELIVERY_BUCKETS.len());
+        self.window_counts[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The upper bound of the bucket holding the window's p99, and the
+    /// number of samples; resets the window. Infinite past the last bucket.
+    fn take_p99(&self) -> (f64, u64) {
+        let counts: Vec<u64> = self
+            .window_counts
+            .iter()
+            .map(|count| count.swap(0, Ordering::Relaxed))
+            .collect();
+        let samples: u64 = counts.iter().sum();
+        let rank = (samples as f64 * 0.99).ceil() as u64;
+
+        let mut seen = 0;
+        for (bucket, count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = metrics::DELIVERY_BUCKETS
+                    .get(bucket)
+                    .copied()
+                    .unwrap_or(f64::INFINITY);
+                return (bound, samples);
+            }
+        }
+        (0.0, samples)
+    }
+
+    pub async fn run(&self) {
+        let mut tick = tokio::time::interval(self.window);
+        tick.tick().await;
+        loop {
+            tokio::select! {
+                _ = self.cancellation_token.cancelled() => break,
+                _ = tick.tick() => {
+                    let (p99, samples) = self.take_p99();
+                    if !self.p99_alarm.is_zero() && samples > 0 && p99 > self.p99_alarm.as_secs_f64() {
+                        tracing::warn!(
+                            p99_le_secs = p99,
+                            alarm_secs = self.p99_alarm.as_secs_f64(),
+                            samples,
+                            window_secs = self.window.as_secs(),
+                            "delivery p99 over the alarm threshold"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
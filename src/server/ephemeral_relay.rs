@@ -0,0 +1,46 @@
+use crate::domain_model::*;
+use crate::server::EventPublisher;
+use chrono::Utc;
+use std::sync::Arc;
+
+/// Publishes events not worth storing, like typing, straight to the presence
+/// topic. There is no outbox row and no retry: one that fails to publish is
+/// simply never seen, and the next one replaces it.
+pub struct EphemeralRelay {
+    publisher: Arc<dyn EventPublisher>,
+    topic: String,
+}
+
+impl EphemeralRelay {
+    pub fn new(publisher: Arc<dyn EventPublisher>, topic: &str) -> Self {
+        Self {
+            publisher,
+            topic: topic.to_owned(),
+        }
+    }
+
+    /// `key` picks the partition, so notices for one conversation stay in
+    /// order.
+    pub async fn relay(
+        &self,
+        key: uuid::Uuid,
+        receivers: Vec<UserId>,
+        event: S2CEvent,
+    ) -> anyhow::Result<()> {
+        if receivers.is_empty() {
+            return Ok(());
+        }
+        // no `created_at`: nothing was written, so there is no delivery SLA
+        let envelope = S2CEnvelope {
+            receivers,
+            body: event,
+            trace_id: TraceId::current(),
+            created_at: None,
+            published_at: Some(Utc::now()),
+        };
+        let payload = serde_json::to_vec(&envelope)?;
+        self.publisher
+            .publish(&self.topic, key.as_bytes(), &payload)
+            .await
+    }
+}
@@ -1,15 +1,168 @@
-use crate::server::{EventConsumer, EventHandler, HandleOutcome};
+use crate::server::{EventConsumer, EventHandler, HandleOutcome, HealthMonitor, TopicSpec};
 use futures_util::StreamExt;
 use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication};
-use rdkafka::consumer::{Consumer, StreamConsumer};
-use rdkafka::{ClientConfig, Message};
-use std::sync::Arc;
-use std::time::Duration;
+use rdkafka::consumer::{Consumer, ConsumerContext, Rebalance, StreamConsumer};
+use rdkafka::error::KafkaResult;
+use rdkafka::message::BorrowedMessage;
+use rdkafka::{ClientConfig, ClientContext, Message, Offset, TopicPartitionList};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::runtime::RuntimeFlavor;
+use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+const LAG_PROBE_INTERVAL: Duration = Duration::from_secs(5);
+/// How long a revocation waits for the handler it interrupted.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+const SEEK_TIMEOUT: Duration = Duration::from_secs(1);
+const WATERMARK_TIMEOUT: Duration = Duration::from_millis(500);
+/// Keeps a poison message from turning `Retry` into a hot loop.
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+const ERROR_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Where a message sits, kept past the message itself.
+struct MessageAt {
+    topic: String,
+    partition: i32,
+    offset: i64,
+}
+
+impl MessageAt {
+    fn of(m: &BorrowedMessage<'_>) -> Self {
+        Self {
+            topic: m.topic().to_owned(),
+            partition: m.partition(),
+            offset: m.offset(),
+        }
+    }
+
+    fn same_partition(&self, m: &BorrowedMessage<'_>) -> bool {
+        self.topic == m.topic() && self.partition == m.partition()
+    }
+}
+
+/// The message whose handler is running.
+struct Handling {
+    at: MessageAt,
+    task: JoinHandle<anyhow::Result<HandleOutcome>>,
+}
+
+/// A partition paused by `HandleOutcome::Park`.
+struct Parked {
+    topic: String,
+    partition: i32,
+    until: Instant,
+}
+
+/// Rebalance hooks that keep commits off partitions we no longer own.
+///
+/// Handled messages only have their offset stored; librdkafka commits stored
+/// offsets in the background and, for revoked partitions, before letting
+/// them go. Handlers run as tasks of their own while the consumer keeps
+/// polling, and the polling is where rebalances happen: on revocation we
+/// stop taking messages from those partitions and wait for the handler in
+/// flight, which stores its own offset, so it is stored in time.
+struct DrainContext {
+    health: Arc<HealthMonitor>,
+    group: String,
+    /// Set by the first assignment; lag means nothing before it.
+    assigned: AtomicBool,
+    handlers: TaskTracker,
+    revoked: Mutex<HashSet<(String, i32)>>,
+}
+
+impl DrainContext {
+    fn new(health: Arc<HealthMonitor>, group: &str) -> Self {
+        Self {
+            health,
+            group: group.to_owned(),
+            assigned: AtomicBool::new(false),
+            handlers: TaskTracker::new(),
+            revoked: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn is_revoked(&self, topic: &str, partition: i32) -> bool {
+        self.revoked
+            .lock()
+            .map(|revoked| revoked.contains(&(topic.to_owned(), partition)))
+            .unwrap_or(false)
+    }
+
+    /// Rebalance callbacks run inside the poll, or in the consumer's drop,
+    /// and can't await. On a multi-threaded runtime `block_in_place` hands
+    /// the worker's other tasks, handlers included, to the rest of the
+    /// runtime while this one waits. Anywhere else the handler could not run
+    /// while we block, so we don't wait: its offset store then fails on the
+    /// partition we no longer own and the new owner handles it again.
+    fn wait_idle(&self) {
+        let runtime = tokio::runtime::Handle::try_current()
+            .ok()
+            .filter(|runtime| runtime.runtime_flavor() == RuntimeFlavor::MultiThread);
+        let Some(runtime) = runtime else {
+            tracing::debug!("rebalance: no runtime to drain on, handler left running");
+            return;
+        };
+        // nothing spawns while we wait: the consumer loop is the one in here
+        self.handlers.close();
+        let drained = tokio::task::block_in_place(|| {
+            runtime.block_on(tokio::time::timeout(DRAIN_TIMEOUT, self.handlers.wait()))
+        });
+        self.handlers.reopen();
+        if drained.is_err() {
+            tracing::warn!("rebalance: handler still running after drain timeout");
+        }
+    }
+}
+
+impl ClientContext for DrainContext {}
+
+impl ConsumerContext for DrainContext {
+    fn pre_rebalance(&self, rebalance: &Rebalance<'_>) {
+        if let Rebalance::Revoke(tpl) = rebalance {
+            if let Ok(mut revoked) = self.revoked.lock() {
+                for e in tpl.elements() {
+                    revoked.insert((e.topic().to_owned(), e.partition()));
+                }
+            }
+            tracing::info!(
+                partitions = tpl.count(),
+                "rebalance: draining revoked partitions"
+            );
+            self.wait_idle();
+        }
+    }
+
+    fn post_rebalance(&self, rebalance: &Rebalance<'_>) {
+        if let Rebalance::Assign(tpl) = rebalance {
+            if let Ok(mut revoked) = self.revoked.lock() {
+                for e in tpl.elements() {
+                    revoked.remove(&(e.topic().to_owned(), e.partition()));
+                }
+            }
+            self.assigned.store(true, Ordering::Relaxed);
+            tracing::info!(partitions = tpl.count(), "rebalance: partitions assigned");
+        }
+    }
+
+    fn commit_callback(&self, result: KafkaResult<()>, _offsets: &TopicPartitionList) {
+        match result {
+            Ok(()) => self.health.consumer_committed(&self.group),
+            Err(e) => {
+                tracing::warn!(error = ?e, "offset commit failed");
+                self.health.consumer_failed(&self.group);
+            }
+        }
+    }
+}
 
 pub struct KafkaConsumer {
     bootstrap_server: String,
     client_id: String,
+    health: Arc<HealthMonitor>,
     cancellation_token: CancellationToken,
 }
 
@@ -17,23 +170,196 @@ impl KafkaConsumer {
     pub fn new(
         bootstrap_server: &str,
         client_id: &str,
+        health: Arc<HealthMonitor>,
         cancellation_token: CancellationToken,
     ) -> Self {
         Self {
             bootstrap_server: bootstrap_server.to_string(),
             client_id: client_id.to_string(),
+            health,
             cancellation_token,
         }
     }
 
-    async fn ensure_topics(bootstrap: &str, topics: &[&str]) -> anyhow::Result<()> {
+    /// Total lag over the assigned partitions: how far each one's consumed
+    /// position trails its high watermark. A partition counts as caught up
+    /// until its first message, as a fresh group starts at the end.
+    ///
+    /// NOTE: `fetch_watermarks` is a blocking broker round-trip per
+    ///       partition, so it runs on the blocking pool, and callers must
+    ///       still rate-limit it.
+    async fn assignment_lag(consumer: &Arc<StreamConsumer<DrainContext>>) -> Option<i64> {
+        let consumer = consumer.clone();
+        tokio::task::spawn_blocking(move || {
+            let positions = consumer.position().ok()?;
+            let mut lag = 0;
+            for e in positions.elements() {
+                let Offset::Offset(position) = e.offset() else {
+                    continue;
+                };
+                let (_low, high) = consumer
+                    .fetch_watermarks(e.topic(), e.partition(), WATERMARK_TIMEOUT)
+                    .ok()?;
+                lag += (high - position).max(0);
+            }
+            Some(lag)
+        })
+        .await
+        .ok()?
+    }
+
+    /// Makes `at` the next message its partition delivers. `seek` waits on
+    /// the broker for up to `SEEK_TIMEOUT`, so it runs on the blocking pool.
+    async fn rewind(consumer: &Arc<StreamConsumer<DrainContext>>, at: &MessageAt) {
+        let consumer = consumer.clone();
+        let (topic, partition, offset) = (at.topic.clone(), at.partition, at.offset);
+        let seek = tokio::task::spawn_blocking(move || {
+            consumer.seek(&topic, partition, Offset::Offset(offset), SEEK_TIMEOUT)
+        });
+        match seek.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                tracing::warn!(error = ?e, "seek back failed; message will not be redelivered");
+            }
+            Err(e) => {
+                tracing::warn!(error = ?e, "seek back did not run; message will not be redelivered");
+            }
+        }
+    }
+
+    async fn park(
+        consumer: &Arc<StreamConsumer<DrainContext>>,
+        at: &MessageAt,
+        duration: Duration,
+        parked: &mut Vec<Parked>,
+    ) {
+        let mut tpl = TopicPartitionList::new();
+        tpl.add_partition(&at.topic, at.partition);
+        if let Err(e) = consumer.pause(&tpl) {
+            tracing::warn!(error = ?e, "pause failed; retrying instead of parking");
+        } else {
+            parked.push(Parked {
+                topic: at.topic.clone(),
+                partition: at.partition,
+                until: Instant::now() + duration,
+            });
+        }
+        Self::rewind(consumer, at).await;
+    }
+
+    /// Runs the handler for `m` as a task the rebalance drain can wait for.
+    /// A `Commit` stores the offset from within the task, so it is stored
+    /// by the time the drain sees the task finish.
+    fn start(
+        consumer: &Arc<StreamConsumer<DrainContext>>,
+        handler: &Arc<dyn EventHandler>,
+        m: &BorrowedMessage<'_>,
+    ) -> Handling {
+        let at = MessageAt::of(m);
+        let payload = m.payload().unwrap_or(&[]).to_vec();
+        let (consumer, handler) = (consumer.clone(), handler.clone());
+        let (topic, partition, offset) = (at.topic.clone(), at.partition, at.offset);
+        let task = consumer.context().handlers.clone().spawn(async move {
+            let outcome = handler.handle(&payload).await;
+            if matches!(outcome, Ok(HandleOutcome::Commit))
+                && let Err(e) = consumer.store_offset(&topic, partition, offset)
+            {
+                tracing::warn!(error = ?e, "offset store failed but ignored");
+            }
+            outcome
+        });
+        Handling { at, task }
+    }
+
+    /// What the handler in flight returned; never resolves without one.
+    async fn finished(handling: &mut Option<Handling>) -> anyhow::Result<HandleOutcome> {
+        match handling {
+            Some(handling) => (&mut handling.task)
+                .await
+                .unwrap_or_else(|e| Err(anyhow::anyhow!("handler task failed: {e}"))),
+            None => std::future::pending().await,
+        }
+    }
+
+    fn resume_due(consumer: &StreamConsumer<DrainContext>, parked: &mut Vec<Parked>) {
+        let now = Instant::now();
+        let mut tpl = TopicPartitionList::new();
+        parked.retain(|p| {
+            if p.until > now {
+                return true;
+            }
+            tpl.add_partition(&p.topic, p.partition);
+            false
+        });
+        // a partition revoked while parked is simply gone; nothing to resume
+        if tpl.count() > 0
+            && let Err(e) = consumer.resume(&tpl)
+        {
+            tracing::warn!(error = ?e, "resume of parked partitions failed");
+        }
+    }
+
+    /// Acts on what the handler for `at` returned. A rewind makes the
+    /// partition redeliver from `at`, so a later message from it held in
+    /// `next` is dropped; a revoked partition is left to its new owner.
+    async fn settle(
+        &self,
+        consumer: &Arc<StreamConsumer<DrainContext>>,
+        at: MessageAt,
+        outcome: anyhow::Result<HandleOutcome>,
+        parked: &mut Vec<Parked>,
+        next: &mut Option<BorrowedMessage<'_>>,
+    ) {
+        if consumer.context().is_revoked(&at.topic, at.partition) {
+            return;
+        }
+        let delay = match outcome {
+            Ok(HandleOutcome::Commit | HandleOutcome::SkipCommit) => return,
+            Ok(HandleOutcome::Retry) => {
+                // TODO: add a DLQ for poison messages
+                Self::rewind(consumer, &at).await;
+                Some(RETRY_DELAY)
+            }
+            Ok(HandleOutcome::Park(duration)) => {
+                tracing::debug!(
+                    topic = at.topic,
+                    partition = at.partition,
+                    offset = at.offset,
+                    ?duration,
+                    "parking partition"
+                );
+                Self::park(consumer, &at, duration, parked).await;
+                None
+            }
+            Err(e) => {
+                tracing::error!(error = ?e, "handler error; retrying");
+                self.health.consumer_failed(&consumer.context().group);
+                Self::rewind(consumer, &at).await;
+                Some(ERROR_RETRY_DELAY)
+            }
+        };
+        if next.as_ref().is_some_and(|m| at.same_partition(m)) {
+            *next = None;
+        }
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn ensure_topics(bootstrap: &str, topics: &[TopicSpec]) -> anyhow::Result<()> {
         let admin: AdminClient<_> = ClientConfig::new()
             .set("bootstrap.servers", bootstrap)
             .create()?;
 
         let new_topics: Vec<_> = topics
             .iter()
-            .map(|t| NewTopic::new(t, 1, TopicReplication::Fixed(1)))
+            .map(|t| {
+                NewTopic::new(
+                    &t.name,
+                    t.partitions,
+                    TopicReplication::Fixed(t.replication),
+                )
+            })
             .collect();
 
         let _ = admin
@@ -49,30 +375,72 @@ impl EventConsumer for KafkaConsumer {
     async fn run(
         &self,
         consumer_group_id: &str,
-        topics: &[&str],
+        topics: &[TopicSpec],
         handler: Arc<dyn EventHandler>,
     ) -> anyhow::Result<()> {
-        let consumer: StreamConsumer = ClientConfig::new()
+        self.health.consumer_started(consumer_group_id);
+        let context = DrainContext::new(self.health.clone(), consumer_group_id);
+        let consumer: StreamConsumer<DrainContext> = ClientConfig::new()
             .set("bootstrap.servers", &self.bootstrap_server)
             .set("client.id", &self.client_id)
             .set("group.id", consumer_group_id)
-            .set("enable.auto.commit", "false")
-            .set("auto.offset.reset", "earliest")
-            .create()?;
+            // commits only offsets the handler tasks stored as handled
+            .set("enable.auto.commit", "true")
+            .set("enable.auto.offset.store", "false")
+            .set("auto.commit.interval.ms", "1000")
+            // topics outlive the process; a fresh group only wants live events
+            .set("auto.offset.reset", "latest")
+            .create_with_context(context)?;
+        // shared with the blocking calls `rewind` and `assignment_lag` make
+        let consumer = Arc::new(consumer);
 
         Self::ensure_topics(&self.bootstrap_server, topics).await?;
-        consumer.subscribe(topics)?;
+        let names: Vec<&str> = topics.iter().map(|t| t.name.as_str()).collect();
+        consumer.subscribe(&names)?;
 
         let mut stream = consumer.stream();
+        let mut lag_probe = tokio::time::interval(LAG_PROBE_INTERVAL);
+        let mut parked: Vec<Parked> = Vec::new();
+        let mut handling: Option<Handling> = None;
+        // polled while a handler runs, so rebalances aren't held up by it,
+        // and held here until the handler is done
+        let mut next: Option<BorrowedMessage<'_>> = None;
 
         loop {
+            let resume_at = parked.iter().map(|p| p.until).min();
             let result = tokio::select! {
                 biased;
                 _ = self.cancellation_token.cancelled() => {
                     tracing::info!("Kafka consumer shutting down...");
                     break;
                 }
-                msg = stream.next() => msg,
+                outcome = Self::finished(&mut handling) => {
+                    let Some(done) = handling.take() else {
+                        continue;
+                    };
+                    self.settle(&consumer, done.at, outcome, &mut parked, &mut next)
+                        .await;
+                    if let Some(m) = next.take()
+                        && !consumer.context().is_revoked(m.topic(), m.partition())
+                    {
+                        handling = Some(Self::start(&consumer, &handler, &m));
+                    }
+                    continue;
+                }
+                _ = tokio::time::sleep_until(resume_at.unwrap_or_else(Instant::now).into()),
+                    if resume_at.is_some() => {
+                    Self::resume_due(&consumer, &mut parked);
+                    continue;
+                }
+                _ = lag_probe.tick() => {
+                    if consumer.context().assigned.load(Ordering::Relaxed)
+                        && let Some(lag) = Self::assignment_lag(&consumer).await
+                    {
+                        self.health.consumer_lag(consumer_group_id, lag);
+                    }
+                    continue;
+                }
+                msg = stream.next(), if next.is_none() => msg,
             };
 
             let Some(message) = result else {
@@ -84,36 +452,27 @@ impl EventConsumer for KafkaConsumer {
                 Err(e) => {
                     // broker hiccup
                     tracing::warn!(error = ?e, "consumer poll error");
+                    self.health.consumer_failed(consumer_group_id);
                     tokio::time::sleep(Duration::from_millis(200)).await;
                 }
                 Ok(m) => {
-                    let topic = m.topic();
-                    let key = m.key().unwrap_or(&[]);
-                    let payload = m.payload().unwrap_or(&[]);
-
-                    match handler.handle(payload).await {
-                        Ok(HandleOutcome::Commit | HandleOutcome::SkipCommit) => {
-                            if let Err(e) =
-                                consumer.commit_message(&m, rdkafka::consumer::CommitMode::Async)
-                            {
-                                tracing::warn!(error = ?e, "commit failed but ignored");
-                            }
-                        }
-                        Ok(HandleOutcome::Retry) => {
-                            // do nothing, retry on next poll
-                            // add a small delay to avoid hot-loop on poison messages
-                            // TODO: add a DLQ for poison messages
-                            tokio::time::sleep(Duration::from_millis(50)).await;
-                        }
-                        Err(e) => {
-                            tracing::error!(error = ?e, "handler error; retrying");
-                            tokio::time::sleep(Duration::from_millis(100)).await;
-                        }
+                    if consumer.context().is_revoked(m.topic(), m.partition()) {
+                        // buffered before the revocation; the new owner gets it
+                        continue;
+                    }
+                    if handling.is_some() {
+                        next = Some(m);
+                    } else {
+                        handling = Some(Self::start(&consumer, &handler, &m));
                     }
                 }
             }
         }
 
+        // its offset is stored before the partitions are let go
+        if let Some(handling) = handling.take() {
+            let _ = handling.task.await;
+        }
         consumer.unsubscribe();
 
         Ok(())
@@ -1,14 +1,80 @@
 use crate::domain_model::*;
-use crate::server::{EventHandler, HandleOutcome, OutboundQueue};
+use crate::domain_port::UserCacheInvalidator;
+use crate::server::{
+    AnalyticsSink, DeliveryStamps, EventHandler, HandleOutcome, OutboundQueue, SessionControl,
+};
+use chrono::Utc;
+use serde_json::{Value, json};
 use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
 
 pub struct ConnFanoutHandler {
     outbound_queue: Arc<dyn OutboundQueue>,
+    session_control: Arc<dyn SessionControl>,
+    user_cache: Option<Arc<dyn UserCacheInvalidator>>,
 }
 
 impl ConnFanoutHandler {
-    pub fn new(outbound_queue: Arc<dyn OutboundQueue>) -> Self {
-        Self { outbound_queue }
+    pub fn new(
+        outbound_queue: Arc<dyn OutboundQueue>,
+        session_control: Arc<dyn SessionControl>,
+    ) -> Self {
+        Self {
+            outbound_queue,
+            session_control,
+            user_cache: None,
+        }
+    }
+
+    /// Retired users are dropped from `user_cache` as their sessions are
+    /// terminated, which is after the retirement committed.
+    pub fn with_user_cache(mut self, user_cache: Arc<dyn UserCacheInvalidator>) -> Self {
+        self.user_cache = Some(user_cache);
+        self
+    }
+
+    async fn deliver(&self, s2c_envelope: S2CEnvelope) -> anyhow::Result<HandleOutcome> {
+        // Control events act on the local sessions instead of being delivered.
+        if let S2CEvent::SessionTerminated(terminated) = &s2c_envelope.body {
+            for r in s2c_envelope.receivers {
+                if let Some(user_cache) = &self.user_cache {
+                    user_cache.forget(r);
+                }
+                self.session_control.terminate(r, terminated.reason);
+            }
+            return Ok(HandleOutcome::Commit);
+        }
+
+        let stamps = s2c_envelope.created_at.map(|created_at| DeliveryStamps {
+            created_at,
+            published_at: s2c_envelope.published_at,
+            enqueued_at: Utc::now(),
+        });
+        let mut receivers = s2c_envelope.receivers;
+        // a notice naming nobody is for everyone on this node
+        if receivers.is_empty() && matches!(s2c_envelope.body, S2CEvent::SystemNotice(_)) {
+            receivers = self
+                .session_control
+                .sessions()
+                .into_iter()
+                .map(|session| session.user_id)
+                .collect();
+            receivers.sort();
+            receivers.dedup();
+        }
+        for r in receivers {
+            if let Err(e) = self
+                .outbound_queue
+                .enqueue(r, &s2c_envelope.body, stamps)
+                .await
+            {
+                tracing::warn!("outbound queue dropped (offline?): {e}");
+            }
+        }
+        tracing::debug!("delivered");
+
+        Ok(HandleOutcome::Commit)
     }
 }
 
@@ -18,12 +84,58 @@ impl EventHandler for ConnFanoutHandler {
         let s2c_envelope_json_value = serde_json::from_slice::<serde_json::Value>(payload)?;
         let s2c_envelope = serde_json::from_value::<S2CEnvelope>(s2c_envelope_json_value)?;
 
-        for r in s2c_envelope.receivers {
-            if let Err(e) = self.outbound_queue.enqueue(r, &s2c_envelope.body).await {
-                tracing::warn!("outbound queue dropped (offline?): {e}");
+        // continues the trace of the request that enqueued the event
+        let span = tracing::info_span!(
+            "fanout",
+            trace_id = s2c_envelope.trace_id.map(tracing::field::display),
+        );
+        self.deliver(s2c_envelope).instrument(span).await
+    }
+}
+
+/// How long a partition waits after the analytics sink fails to write.
+const ANALYTICS_SINK_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Records one line per event for volume analytics: its type, conversation,
+/// receiver count and pipeline timestamps. Message contents never leave the
+/// envelope.
+pub struct AnalyticsHandler {
+    sink: Arc<dyn AnalyticsSink>,
+}
+
+impl AnalyticsHandler {
+    pub fn new(sink: Arc<dyn AnalyticsSink>) -> Self {
+        Self { sink }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for AnalyticsHandler {
+    async fn handle(&self, payload: &[u8]) -> anyhow::Result<HandleOutcome> {
+        // read as a plain value so events newer than this build still count
+        let envelope = match serde_json::from_slice::<Value>(payload) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                tracing::warn!("analytics skipped malformed event: {e}");
+                return Ok(HandleOutcome::Commit);
             }
-        }
+        };
+        let record = json!({
+            "event": envelope["body"]["type"],
+            "conversation_id": envelope["body"]["content"]["conversation_id"],
+            "receivers": envelope["receivers"].as_array().map_or(0, Vec::len),
+            "trace_id": envelope["trace_id"],
+            "created_at": envelope["created_at"],
+            "published_at": envelope["published_at"],
+            "consumed_at": Utc::now(),
+        });
 
-        Ok(HandleOutcome::Commit)
+        match self.sink.append(&serde_json::to_vec(&record)?).await {
+            Ok(()) => Ok(HandleOutcome::Commit),
+            Err(e) => {
+                tracing::warn!("analytics sink failed: {e}");
+                Ok(HandleOutcome::Park(ANALYTICS_SINK_BACKOFF))
+            }
+        }
     }
 }
@@ -3,19 +3,53 @@ use rdkafka::ClientConfig;
 use rdkafka::producer::{FutureProducer, FutureRecord};
 use std::time::Duration;
 
+pub struct ProducerConfig {
+    /// "all", "1" or "0". Idempotent producing requires "all".
+    pub acks: String,
+    pub linger: Duration,
+    /// "none", "gzip", "snappy", "lz4" or "zstd".
+    pub compression: String,
+    /// Lets the broker drop the duplicates a retried send would otherwise
+    /// leave behind.
+    pub idempotence: bool,
+    /// Total time a send may spend on retries before it fails.
+    pub delivery_timeout: Duration,
+}
+
+impl Default for ProducerConfig {
+    fn default() -> Self {
+        Self {
+            acks: "all".to_string(),
+            linger: Duration::from_millis(5),
+            compression: "lz4".to_string(),
+            idempotence: true,
+            delivery_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
 pub struct KafkaPublisher {
     inner: FutureProducer,
 }
 
 impl KafkaPublisher {
-    pub fn new(bootstrap_server: &str, client_id: &str) -> anyhow::Result<Self> {
+    pub fn new(
+        bootstrap_server: &str,
+        client_id: &str,
+        config: &ProducerConfig,
+    ) -> anyhow::Result<Self> {
         let inner = ClientConfig::new()
             .set("bootstrap.servers", bootstrap_server)
             .set("client.id", client_id)
-            .set("acks", "all")
-            .set("enable.idempotence", "true")
+            .set("acks", &config.acks)
+            .set("linger.ms", config.linger.as_millis().to_string())
+            .set("compression.type", &config.compression)
+            .set("enable.idempotence", config.idempotence.to_string())
+            .set(
+                "delivery.timeout.ms",
+                config.delivery_timeout.as_millis().to_string(),
+            )
             .set("max.in.flight.requests.per.connection", "1")
-            .set("compression.type", "lz4")
             .create()?;
         Ok(Self { inner })
     }
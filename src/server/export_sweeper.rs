@@ -0,0 +1,49 @@
+use crate::application_port::*;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Periodically removes account exports past their retention, archives
+/// included, and jobs abandoned by a node that stopped; see
+/// `ExportService::expire`.
+pub struct ExportSweeper {
+    export_service: Arc<dyn ExportService>,
+    interval: Duration,
+    cancellation_token: CancellationToken,
+}
+
+impl ExportSweeper {
+    pub fn new(
+        export_service: Arc<dyn ExportService>,
+        interval: Duration,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            export_service,
+            interval,
+            cancellation_token,
+        }
+    }
+
+    async fn sweep(&self) {
+        match self.export_service.expire().await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("expired {n} account exports"),
+            Err(e) => tracing::warn!("Export sweeper error: {e}"),
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancellation_token.cancelled() => {
+                    tracing::info!("Export sweeper shutting down...");
+                    break;
+                }
+                _ = ticker.tick() => self.sweep().await,
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::metrics;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// A component is reported as stalled once its last success is older than this.
+const STALL_THRESHOLD_SECS: i64 = 30;
+
+/// Errors are only timed here; their detail goes to the log, not to whoever
+/// can reach the health route.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NotifierStatus {
+    pub last_tick_at: Option<DateTime<Utc>>,
+    pub outbox_backlog: u64,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConsumerStatus {
+    pub last_commit_at: Option<DateTime<Utc>>,
+    /// `None` until the group has been assigned its partitions and measured.
+    pub lag: Option<i64>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    /// Refusing new sockets ahead of a shutdown; reported unhealthy so the
+    /// load balancer stops sending clients here.
+    pub draining: bool,
+    pub notifier: NotifierStatus,
+    /// By consumer group.
+    pub consumers: BTreeMap<String, ConsumerStatus>,
+}
+
+/// Shared sink for delivery pipeline liveness.
+///
+/// `Notifier` and `KafkaConsumer` report into it; the health route and the
+/// Prometheus gauges read from it. Each consumer group is tracked on its own
+/// from the moment it starts, so one that never gets going stays unhealthy.
+#[derive(Default)]
+pub struct HealthMonitor {
+    notifier: Mutex<NotifierStatus>,
+    consumers: Mutex<BTreeMap<String, ConsumerStatus>>,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn notifier_ticked(&self) {
+        let now = Utc::now();
+        if let Ok(mut status) = self.notifier.lock() {
+            status.last_tick_at = Some(now);
+        }
+        metrics::NOTIFIER_LAST_TICK.set(now.timestamp());
+    }
+
+    pub fn notifier_backlog(&self, backlog: u64) {
+        if let Ok(mut status) = self.notifier.lock() {
+            status.outbox_backlog = backlog;
+        }
+        metrics::OUTBOX_BACKLOG.set(backlog as i64);
+    }
+
+    pub fn notifier_failed(&self) {
+        if let Ok(mut status) = self.notifier.lock() {
+            status.last_error_at = Some(Utc::now());
+        }
+        metrics::NOTIFIER_ERRORS.inc();
+    }
+
+    fn update_consumer(&self, group: &str, update: impl FnOnce(&mut ConsumerStatus)) {
+        if let Ok(mut consumers) = self.consumers.lock() {
+            update(consumers.entry(group.to_owned()).or_default());
+        }
+    }
+
+    pub fn consumer_started(&self, group: &str) {
+        self.update_consumer(group, |_| {});
+    }
+
+    pub fn consumer_committed(&self, group: &str) {
+        let now = Utc::now();
+        self.update_consumer(group, |status| status.last_commit_at = Some(now));
+        metrics::CONSUMER_LAST_COMMIT
+            .with_label_values(&[group])
+            .set(now.timestamp());
+    }
+
+    pub fn consumer_lag(&self, group: &str, lag: i64) {
+        self.update_consumer(group, |status| status.lag = Some(lag));
+        metrics::CONSUMER_LAG.with_label_values(&[group]).set(lag);
+    }
+
+    pub fn consumer_failed(&self, group: &str) {
+        self.update_consumer(group, |status| status.last_error_at = Some(Utc::now()));
+        metrics::CONSUMER_ERRORS.with_label_values(&[group]).inc();
+    }
+
+    pub fn report(&self) -> HealthReport {
+        let notifier = self.notifier.lock().map(|s| s.clone()).unwrap_or_default();
+        let consumers = self.consumers.lock().map(|c| c.clone()).unwrap_or_default();
+
+        // A consumer only commits when traffic flows, so an idle one is
+        // judged by lag instead of by commit age.
+        let notifier_alive = is_recent(notifier.last_tick_at);
+        let consumers_alive = consumers.values().all(|consumer| match consumer.lag {
+            None => false,
+            Some(0) => true,
+            Some(_) => is_recent(consumer.last_commit_at),
+        });
+
+        HealthReport {
+            healthy: notifier_alive && consumers_alive,
+            draining: false,
+            notifier,
+            consumers,
+        }
+    }
+}
+
+fn is_recent(at: Option<DateTime<Utc>>) -> bool {
+    match at {
+        Some(at) => (Utc::now() - at).num_seconds() < STALL_THRESHOLD_SECS,
+        None => false,
+    }
+}
@@ -0,0 +1,48 @@
+use crate::domain_port::*;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Periodically deletes idempotency claims past their retention window.
+pub struct IdemJanitor {
+    group_idem_repo: Arc<dyn GroupIdemRepo>,
+    retention: Duration,
+    interval: Duration,
+    cancellation_token: CancellationToken,
+}
+
+impl IdemJanitor {
+    pub fn new(
+        group_idem_repo: Arc<dyn GroupIdemRepo>,
+        retention: Duration,
+        interval: Duration,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            group_idem_repo,
+            retention,
+            interval,
+            cancellation_token,
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancellation_token.cancelled() => {
+                    tracing::info!("Idem janitor shutting down...");
+                    break;
+                }
+                _ = ticker.tick() => {
+                    match self.group_idem_repo.purge_expired(self.retention).await {
+                        Ok(0) => {}
+                        Ok(n) => tracing::debug!("purged {n} group idem claims"),
+                        Err(e) => tracing::warn!("Idem janitor error: {e}"),
+                    }
+                }
+            }
+        }
+    }
+}
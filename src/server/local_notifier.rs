@@ -0,0 +1,104 @@
+use crate::domain_port::OutboxEvent;
+use crate::server::{EventHandler, EventPublisher, HealthMonitor, Notifier};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Stands in for Kafka behind `EphemeralRelay` when storage is fake: the
+/// payloads it is handed reach `LocalNotifier`, which fans them out on this
+/// node. Topic and key are ignored.
+pub struct LocalPublisher {
+    payloads: UnboundedSender<Vec<u8>>,
+}
+
+impl LocalPublisher {
+    pub fn new(payloads: UnboundedSender<Vec<u8>>) -> Self {
+        Self { payloads }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventPublisher for LocalPublisher {
+    async fn publish(&self, _topic: &str, _key: &[u8], payload: &[u8]) -> anyhow::Result<()> {
+        self.payloads.send(payload.to_vec())?;
+        Ok(())
+    }
+}
+
+/// Stands in for the outbox, notifier and Kafka when storage is fake: events
+/// go straight from the fake services to fan-out on this node.
+pub struct LocalNotifier {
+    events: UnboundedReceiver<OutboxEvent>,
+    /// Already enveloped, from `LocalPublisher`.
+    relayed: UnboundedReceiver<Vec<u8>>,
+    /// Each event goes to all of them, in turn, as to separate consumer
+    /// groups.
+    handlers: Vec<Arc<dyn EventHandler>>,
+    health: Arc<HealthMonitor>,
+    cancellation_token: CancellationToken,
+}
+
+impl LocalNotifier {
+    pub fn new(
+        events: UnboundedReceiver<OutboxEvent>,
+        relayed: UnboundedReceiver<Vec<u8>>,
+        handlers: Vec<Arc<dyn EventHandler>>,
+        health: Arc<HealthMonitor>,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            events,
+            relayed,
+            handlers,
+            health,
+            cancellation_token,
+        }
+    }
+
+    async fn deliver(&self, event: &OutboxEvent) -> anyhow::Result<()> {
+        // same envelope as Kafka carries, so fan-out can't tell the difference
+        let payload = Notifier::build_envelope(event)?;
+        for handler in &self.handlers {
+            handler.handle(&payload).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn run(mut self) {
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancellation_token.cancelled() => {
+                    tracing::info!("Local notifier shutting down...");
+                    break;
+                }
+                event = self.events.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    match self.deliver(&event).await {
+                        Ok(()) => self.health.notifier_ticked(),
+                        Err(e) => {
+                            tracing::error!("Local notifier error: {:#?}", e);
+                            self.health.notifier_failed();
+                        }
+                    }
+                }
+                Some(payload) = self.relayed.recv() => {
+                    // lost like it would be on Kafka; nothing to report
+                    for handler in &self.handlers {
+                        if let Err(e) = handler.handle(&payload).await {
+                            tracing::debug!("Local relay dropped an event: {e:#}");
+                        }
+                    }
+                }
+                // an idle node is still a healthy one
+                _ = heartbeat.tick() => self.health.notifier_ticked(),
+            }
+        }
+    }
+}
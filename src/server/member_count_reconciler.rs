@@ -0,0 +1,47 @@
+use crate::domain_port::*;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Periodically repairs `chat_group.member_count` where it drifted from the
+/// membership table, e.g. after manual row edits. Every node runs one; the
+/// repo lets a single node through at a time.
+pub struct MemberCountReconciler {
+    group_repo: Arc<dyn GroupRepo>,
+    interval: Duration,
+    cancellation_token: CancellationToken,
+}
+
+impl MemberCountReconciler {
+    pub fn new(
+        group_repo: Arc<dyn GroupRepo>,
+        interval: Duration,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            group_repo,
+            interval,
+            cancellation_token,
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancellation_token.cancelled() => {
+                    tracing::info!("Member count reconciler shutting down...");
+                    break;
+                }
+                _ = ticker.tick() => {
+                    match self.group_repo.reconcile_member_counts().await {
+                        Ok(0) => {}
+                        Ok(n) => tracing::warn!("fixed drifted member_count on {n} groups"),
+                        Err(e) => tracing::warn!("Member count reconciler error: {e}"),
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::domain_model::RetentionPolicy;
+use crate::domain_port::*;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Messages deleted per statement, so one tick never holds long locks.
+const PRUNE_BATCH: u32 = 1000;
+
+/// Periodically deletes messages past their conversation's retention, see
+/// `ConversationService::set_retention`, and the blobs of their attachments.
+/// A blob that fails to delete is only logged; nothing refers to it anymore.
+pub struct MessagePruner {
+    message_repo: Arc<dyn MessageRepo>,
+    blob_store: Arc<dyn BlobStore>,
+    policy: RetentionPolicy,
+    interval: Duration,
+    cancellation_token: CancellationToken,
+}
+
+impl MessagePruner {
+    pub fn new(
+        message_repo: Arc<dyn MessageRepo>,
+        blob_store: Arc<dyn BlobStore>,
+        policy: RetentionPolicy,
+        interval: Duration,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            message_repo,
+            blob_store,
+            policy,
+            interval,
+            cancellation_token,
+        }
+    }
+
+    /// Batches until one comes back short.
+    async fn prune(&self) {
+        let mut total = 0;
+        while !self.cancellation_token.is_cancelled() {
+            match self
+                .message_repo
+                .prune_expired(&self.policy, PRUNE_BATCH)
+                .await
+            {
+                Ok(pruned) => {
+                    total += pruned.messages;
+                    for attachment in &pruned.attachments {
+                        let key = attachment.blob_key();
+                        if let Err(e) = self.blob_store.delete(&key).await {
+                            tracing::warn!("pruned attachment [{key}] kept its blob: {e:#}");
+                        }
+                    }
+                    if pruned.messages < u64::from(PRUNE_BATCH) {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Message pruner error: {e}");
+                    break;
+                }
+            }
+        }
+        if total > 0 {
+            tracing::info!("pruned {total} expired messages");
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancellation_token.cancelled() => {
+                    tracing::info!("Message pruner shutting down...");
+                    break;
+                }
+                _ = ticker.tick() => self.prune().await,
+            }
+        }
+    }
+}
@@ -1,15 +1,43 @@
+mod analytics_sink_file;
+mod attachment_sweeper;
+mod debouncing_publisher;
+mod delivery_sla;
+mod ephemeral_relay;
 mod event_consumer_impl;
 mod event_handler_impl;
 mod event_publisher_impl;
+mod export_sweeper;
+mod health;
+mod idem_janitor;
+mod local_notifier;
+mod member_count_reconciler;
+mod message_pruner;
 mod notifier;
 mod port;
+mod push_gateway;
 mod server;
 mod session_hub;
+mod session_reporter;
+mod webhook_dispatcher;
 
+pub use analytics_sink_file::*;
+pub use attachment_sweeper::*;
+pub use debouncing_publisher::*;
+pub use delivery_sla::*;
+pub use ephemeral_relay::*;
 pub use event_consumer_impl::*;
 pub use event_handler_impl::*;
 pub use event_publisher_impl::*;
+pub use export_sweeper::*;
+pub use health::*;
+pub use idem_janitor::*;
+pub use local_notifier::*;
+pub use member_count_reconciler::*;
+pub use message_pruner::*;
 pub use notifier::*;
 pub use port::*;
+pub use push_gateway::*;
 pub use server::*;
 pub use session_hub::*;
+pub use session_reporter::*;
+pub use webhook_dispatcher::*;
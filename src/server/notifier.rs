@@ -1,16 +1,21 @@
 use crate::domain_port::*;
-use crate::server::EventPublisher;
+use crate::server::{EventPublisher, HealthMonitor};
 use chrono::Utc;
 use serde_json::json;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+const BACKLOG_PROBE_INTERVAL: Duration = Duration::from_secs(5);
 
 pub struct Notifier {
     tx_manager: Arc<dyn TxManager>,
     outbox_repo: Arc<dyn OutboxRepo>,
     event_publisher: Arc<dyn EventPublisher>,
-    topic: String,
+    message_topic: String,
+    presence_topic: String,
+    health: Arc<HealthMonitor>,
     cancellation_token: CancellationToken,
 }
 
@@ -19,25 +24,54 @@ impl Notifier {
         tx_manager: Arc<dyn TxManager>,
         outbox_repo: Arc<dyn OutboxRepo>,
         event_publisher: Arc<dyn EventPublisher>,
-        topic: &str,
+        message_topic: &str,
+        presence_topic: &str,
+        health: Arc<HealthMonitor>,
         cancellation_token: CancellationToken,
     ) -> Self {
         Self {
             tx_manager,
             outbox_repo,
             event_publisher,
-            topic: topic.to_owned(),
+            message_topic: message_topic.to_owned(),
+            presence_topic: presence_topic.to_owned(),
+            health,
             cancellation_token,
         }
     }
 
-    fn build_envelope(
-        receivers_json: &serde_json::Value,
-        payload_json: &serde_json::Value,
-    ) -> anyhow::Result<Vec<u8>> {
+    /// Chat messages get a topic of their own so a burst of them never
+    /// delays session, friendship and group changes, or the other way round.
+    fn topic_for(&self, event_type: EventType) -> &str {
+        match event_type {
+            // edits share the partition key, so they never overtake the message
+            EventType::ChatMessageNew
+            | EventType::ChatMessageEdited
+            | EventType::ChatMessageDeleted
+            | EventType::MessagePinned
+            | EventType::ChatMentionNew => &self.message_topic,
+            EventType::FriendshipNew
+            | EventType::GroupNew
+            | EventType::GroupMemberNew
+            | EventType::GroupUpdated
+            | EventType::SessionTerminated
+            | EventType::ChatTyping
+            | EventType::ConversationMetaChanged
+            | EventType::SecurityAlert
+            | EventType::ChatRead
+            | EventType::ChatMessageDelivered
+            | EventType::SystemNotice
+            | EventType::ConversationFrozen => &self.presence_topic,
+        }
+    }
+
+    pub(crate) fn build_envelope(event: &OutboxEvent) -> anyhow::Result<Vec<u8>> {
         let envelope = json!({
-            "receivers": receivers_json,
-            "body": payload_json,
+            "receivers": event.receivers_json,
+            "body": event.payload_json,
+            "trace_id": event.trace_id,
+            "created_at": event.created_at,
+            "published_at": Utc::now(),
         });
 
         Ok(serde_json::to_vec(&envelope)?)
@@ -63,11 +97,17 @@ impl Notifier {
                 Some(key) => key,
                 None => event.event_id.0,
             };
-            let payload = Self::build_envelope(&event.receivers_json, &event.payload_json)?;
+            let payload = Self::build_envelope(event)?;
 
+            let span = tracing::debug_span!(
+                "outbox.publish",
+                event_id = %event.event_id.0,
+                trace_id = event.trace_id.map(tracing::field::display),
+            );
             match self
                 .event_publisher
-                .publish(&self.topic, key.as_bytes(), &payload)
+                .publish(self.topic_for(event.event_type), key.as_bytes(), &payload)
+                .instrument(span)
                 .await
             {
                 Ok(()) => {
@@ -89,7 +129,15 @@ impl Notifier {
         Ok(())
     }
 
+    async fn probe_backlog(&self) {
+        match self.outbox_repo.count_undelivered().await {
+            Ok(backlog) => self.health.notifier_backlog(backlog),
+            Err(e) => tracing::warn!("Notifier backlog probe failed: {e:#}"),
+        }
+    }
+
     pub async fn run(&self) -> anyhow::Result<()> {
+        let mut last_probe: Option<Instant> = None;
         loop {
             tokio::select! {
                 biased;
@@ -98,11 +146,21 @@ impl Notifier {
                     break;
                 }
                 result = self.tick_once() => {
-                    if let Err(e) = result {
-                        tracing::error!("Notifier error: {:#?}", e);
+                    match result {
+                        Ok(()) => self.health.notifier_ticked(),
+                        Err(e) => {
+                            tracing::error!("Notifier error: {:#?}", e);
+                            self.health.notifier_failed();
+                        }
                     }
                 }
             }
+
+            // probed between ticks so a slow COUNT never cancels an open batch
+            if last_probe.is_none_or(|at| at.elapsed() >= BACKLOG_PROBE_INTERVAL) {
+                self.probe_backlog().await;
+                last_probe = Some(Instant::now());
+            }
         }
         Ok(())
     }
@@ -1,7 +1,13 @@
 use crate::domain_model::*;
+use crate::protocol::ProtocolVersion;
+use crate::server::DeliveryStamps;
+use chrono::{DateTime, Utc};
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc::{Receiver, Sender};
 use warp::ws::Message;
 
@@ -14,6 +20,8 @@ pub enum ConnMessage {
     Ping,
     Pong,
     Close,
+    /// A close frame with a status code and reason; outbound only.
+    CloseWith(u16, String),
 }
 
 impl From<Message> for ConnMessage {
@@ -44,6 +52,7 @@ impl From<ConnMessage> for Message {
             ConnMessage::Ping => Message::ping(Vec::new()),
             ConnMessage::Pong => Message::pong(Vec::new()),
             ConnMessage::Close => Message::close(),
+            ConnMessage::CloseWith(code, reason) => Message::close_with(code, reason),
         }
     }
 }
@@ -103,19 +112,79 @@ impl ConnReceiver for Receiver<ConnMessage> {
 #[derive(Debug)]
 pub struct WsMessage(pub String);
 
+/// What the transport knows about a client when it connects.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionMeta {
+    pub remote_addr: Option<SocketAddr>,
+    /// `remote_addr`'s IP, or the client's as a trusted proxy reported it.
+    pub client_ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub device_id: Option<String>,
+    /// Asked for at connect; long messages arrive as previews.
+    pub data_saver: bool,
+    /// Asked for at connect; the first event is a `ConversationSnapshot`.
+    pub snapshot: bool,
+}
+
 #[async_trait::async_trait]
 pub trait ConnectionAcceptor: Send + Sync {
+    /// Whether a session from `meta` would be taken now; asked before the
+    /// WebSocket upgrade, so a refusal can still be an HTTP status.
+    fn has_room_for(&self, meta: &ConnectionMeta) -> bool;
     async fn accept_connection(
         &self,
         s2c_channel: Box<dyn ConnSender>,
         c2s_channel: Box<dyn ConnReceiver>,
         user_id: UserId,
+        protocol_version: ProtocolVersion,
+        meta: ConnectionMeta,
+    ) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub user_id: UserId,
+    pub remote_addr: Option<String>,
+    pub user_agent: Option<String>,
+    pub device_id: Option<String>,
+    pub connected_at: DateTime<Utc>,
+}
+
+/// Operator control over the sessions held by this node.
+pub trait SessionControl: Send + Sync {
+    fn sessions(&self) -> Vec<SessionInfo>;
+    /// Returns `false` if the user has no session on this node.
+    fn terminate(&self, user_id: UserId, reason: TerminationReason) -> bool;
+    /// While draining, new sockets are closed with a retry hint and the
+    /// sessions already open carry on.
+    fn set_draining(&self, draining: bool);
+    fn draining(&self) -> bool;
+}
+
+/// The sessions of every node, as each last reported them; see
+/// `SessionReporter`.
+#[async_trait::async_trait]
+pub trait SessionDirectory: Send + Sync {
+    /// Replaces what `node` reported before. The report lapses after `ttl`
+    /// unless renewed, so a node that went away drops out.
+    async fn report(
+        &self,
+        node: &str,
+        sessions: &[SessionInfo],
+        ttl: Duration,
     ) -> anyhow::Result<()>;
+    async fn sessions(&self) -> anyhow::Result<Vec<SessionInfo>>;
 }
 
 #[async_trait::async_trait]
 pub trait OutboundQueue: Send + Sync {
-    async fn enqueue(&self, receiver: UserId, event: &S2CEvent) -> anyhow::Result<()>;
+    /// `stamps` is `None` for events that did not come through the outbox.
+    async fn enqueue(
+        &self,
+        receiver: UserId,
+        event: &S2CEvent,
+        stamps: Option<DeliveryStamps>,
+    ) -> anyhow::Result<()>;
 }
 
 #[async_trait::async_trait]
@@ -123,23 +192,52 @@ pub trait EventPublisher: Send + Sync {
     async fn publish(&self, topic: &str, key: &[u8], payload: &[u8]) -> anyhow::Result<()>;
 }
 
+/// A topic as it should exist on the broker.
+#[derive(Debug, Clone)]
+pub struct TopicSpec {
+    pub name: String,
+    pub partitions: i32,
+    pub replication: i32,
+}
+
 #[async_trait::async_trait]
 pub trait EventConsumer: Send + Sync {
+    /// Creates any of `topics` that don't exist yet, then consumes them.
     async fn run(
         &self,
         consumer_group_id: &str,
-        topics: &[&str],
+        topics: &[TopicSpec],
         handler: Arc<dyn EventHandler>,
     ) -> anyhow::Result<()>;
 }
 
+/// What the consumer does with a message once its handler returns.
+///
+/// Kafka only redelivers what the consumer seeks back to, so `Retry` and
+/// `Park` rewind the partition to the message; everything after it on that
+/// partition waits too. A handler error is treated as `Retry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HandleOutcome {
+    /// Done; the offset moves past the message.
     Commit,
+    /// Redeliver the message right away, after a short pause.
     Retry,
+    /// Move on without touching the offset. The message is only seen again
+    /// if the partition is reassigned before a later message commits.
     SkipCommit,
+    /// Stop the partition and redeliver the message once the duration has
+    /// passed. Other partitions keep flowing.
+    Park(Duration),
 }
 
 #[async_trait::async_trait]
 pub trait EventHandler: Send + Sync {
     async fn handle(&self, payload: &[u8]) -> anyhow::Result<HandleOutcome>;
 }
+
+/// Where `AnalyticsHandler` records events, away from the delivery path.
+#[async_trait::async_trait]
+pub trait AnalyticsSink: Send + Sync {
+    /// Appends one record; `line` is JSON without the trailing newline.
+    async fn append(&self, line: &[u8]) -> anyhow::Result<()>;
+}
@@ -0,0 +1,245 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use crate::server::{EventHandler, HandleOutcome};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a partition waits when devices can't be read.
+const DEVICE_STORE_BACKOFF: Duration = Duration::from_secs(10);
+
+/// What a digest holds for one conversation: how many messages, and the
+/// newest of them.
+struct Held {
+    count: u32,
+    message_id: MessageId,
+    message_offset: MessageOffset,
+    sender_name: String,
+    created_at: DateTime<Utc>,
+}
+
+/// Messages held back for one user, by conversation.
+type Digest = HashMap<ConversationId, Held>;
+
+/// Names the newest message of the digest; `None` once every conversation
+/// in it was read.
+fn digest_notification(digest: &Digest) -> Option<PushNotification> {
+    let (conversation_id, newest) = digest.iter().max_by_key(|(_, held)| held.created_at)?;
+    Some(PushNotification {
+        conversation_id: *conversation_id,
+        message_id: newest.message_id,
+        sender_name: newest.sender_name.clone(),
+        message_count: digest.values().map(|held| held.count).sum(),
+        conversation_count: digest.len() as u32,
+    })
+}
+
+/// Sends a notification to devices and forgets the tokens the push service
+/// turns down.
+struct Gateway {
+    device_service: Arc<dyn DeviceService>,
+    push_provider: Arc<dyn PushProvider>,
+}
+
+impl Gateway {
+    async fn send(&self, devices: &[Device], notification: &PushNotification) {
+        let mut invalid: HashMap<PushPlatform, Vec<String>> = HashMap::new();
+        for device in devices {
+            match self.push_provider.send(device, notification).await {
+                Ok(PushOutcome::Delivered) => {}
+                Ok(PushOutcome::InvalidToken) => invalid
+                    .entry(device.platform)
+                    .or_default()
+                    .push(device.token.clone()),
+                Err(e) => tracing::warn!(
+                    user_id = %device.user_id,
+                    platform = device.platform.as_str(),
+                    "push failed: {e:#}"
+                ),
+            }
+        }
+
+        for (platform, tokens) in invalid {
+            match self.device_service.prune(platform, &tokens).await {
+                Ok(pruned) => tracing::info!(
+                    platform = platform.as_str(),
+                    "pruned {pruned} invalid push token(s)"
+                ),
+                // they come back as invalid on the next push and get another try
+                Err(e) => tracing::warn!("prune push tokens: {e}"),
+            }
+        }
+    }
+}
+
+/// Pushes new messages to their receivers' registered devices, and forgets
+/// the tokens the push service turns down. The sender's own devices are
+/// skipped; clients in the foreground drop the rest.
+///
+/// With a `digest_window`, a receiver's first message opens a digest that
+/// collects everything else for them until the window passes, and then
+/// goes out as one push. A `ChatRead` covering a conversation's newest held
+/// message takes that conversation out, and a digest left empty isn't
+/// pushed. Digests live on the node that consumed the messages, so one
+/// still waiting when the node stops is lost, and with several nodes a
+/// user may get one digest from each.
+///
+/// A push is best effort: one that fails is logged, not retried, so a
+/// flaky push service never holds back the partition.
+pub struct PushHandler {
+    gateway: Arc<Gateway>,
+    digest_window: Duration,
+    pending: Arc<Mutex<HashMap<UserId, Digest>>>,
+}
+
+impl PushHandler {
+    pub fn new(
+        device_service: Arc<dyn DeviceService>,
+        push_provider: Arc<dyn PushProvider>,
+        digest_window: Duration,
+    ) -> Self {
+        Self {
+            gateway: Arc::new(Gateway {
+                device_service,
+                push_provider,
+            }),
+            digest_window,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn push(&self, receivers: Vec<UserId>, message: &ChatMessageNew) -> HandleOutcome {
+        let devices = match self.gateway.device_service.devices_of(&receivers).await {
+            Ok(devices) => devices,
+            Err(e) => {
+                tracing::warn!("push devices unavailable: {e}");
+                return HandleOutcome::Park(DEVICE_STORE_BACKOFF);
+            }
+        };
+
+        let notification = PushNotification {
+            conversation_id: message.conversation_id,
+            message_id: message.message_id,
+            sender_name: message.username.clone(),
+            message_count: 1,
+            conversation_count: 1,
+        };
+        self.gateway.send(&devices, &notification).await;
+        HandleOutcome::Commit
+    }
+
+    /// Adds the message to each receiver's digest, opening the ones they
+    /// don't have yet.
+    fn hold(&self, receivers: Vec<UserId>, message: &ChatMessageNew) -> anyhow::Result<()> {
+        let mut opened = Vec::new();
+        {
+            let mut pending = self
+                .pending
+                .lock()
+                .map_err(|e| anyhow::anyhow!("push digests poisoned: {e}"))?;
+            for receiver in receivers {
+                if !pending.contains_key(&receiver) {
+                    opened.push(receiver);
+                }
+                let held = pending
+                    .entry(receiver)
+                    .or_default()
+                    .entry(message.conversation_id)
+                    .or_insert_with(|| Held {
+                        count: 0,
+                        message_id: message.message_id,
+                        message_offset: message.message_offset,
+                        sender_name: message.username.clone(),
+                        created_at: message.created_at,
+                    });
+                held.count += 1;
+                if message.message_offset > held.message_offset {
+                    held.message_id = message.message_id;
+                    held.message_offset = message.message_offset;
+                    held.sender_name = message.username.clone();
+                    held.created_at = message.created_at;
+                }
+            }
+        }
+        for user_id in opened {
+            self.flush_later(user_id);
+        }
+        Ok(())
+    }
+
+    /// Takes a conversation the user has read up to its newest held message
+    /// out of their digest.
+    fn read(&self, read: &ChatRead) -> anyhow::Result<()> {
+        let mut pending = self
+            .pending
+            .lock()
+            .map_err(|e| anyhow::anyhow!("push digests poisoned: {e}"))?;
+        if let Some(digest) = pending.get_mut(&read.user_id)
+            && digest
+                .get(&read.conversation_id)
+                .is_some_and(|held| held.message_offset <= read.last_read_off)
+        {
+            digest.remove(&read.conversation_id);
+        }
+        Ok(())
+    }
+
+    /// Pushes the user's digest once the window its first message opened
+    /// has passed.
+    fn flush_later(&self, user_id: UserId) {
+        let gateway = self.gateway.clone();
+        let pending = self.pending.clone();
+        let window = self.digest_window;
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+            let Some(notification) = pending
+                .lock()
+                .ok()
+                .and_then(|mut p| p.remove(&user_id))
+                .and_then(|digest| digest_notification(&digest))
+            else {
+                return;
+            };
+
+            match gateway.device_service.devices_of(&[user_id]).await {
+                Ok(devices) => gateway.send(&devices, &notification).await,
+                Err(e) => tracing::warn!(%user_id, "push digest dropped: {e}"),
+            }
+        });
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for PushHandler {
+    async fn handle(&self, payload: &[u8]) -> anyhow::Result<HandleOutcome> {
+        // events newer than this build aren't messages it knows how to push
+        let envelope = match serde_json::from_slice::<S2CEnvelope>(payload) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                tracing::debug!("push skipped an event: {e}");
+                return Ok(HandleOutcome::Commit);
+            }
+        };
+        match &envelope.body {
+            S2CEvent::ChatMessageNew(message) => {
+                let receivers: Vec<UserId> = envelope
+                    .receivers
+                    .into_iter()
+                    .filter(|r| *r != message.sender)
+                    .collect();
+                if self.digest_window.is_zero() {
+                    return Ok(self.push(receivers, message).await);
+                }
+                self.hold(receivers, message)?;
+                Ok(HandleOutcome::Commit)
+            }
+            S2CEvent::ChatRead(read) => {
+                self.read(read)?;
+                Ok(HandleOutcome::Commit)
+            }
+            _ => Ok(HandleOutcome::Commit),
+        }
+    }
+}
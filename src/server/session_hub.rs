@@ -1,51 +1,247 @@
 use crate::application_port::*;
 use crate::domain_model::*;
+use crate::domain_port::CommandDedupeStore;
+use crate::protocol::*;
 use crate::server::*;
 use anyhow::anyhow;
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::{Notify, Semaphore};
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
 const MAILBOX_CAP: usize = 256;
+/// How long a terminated session gets to flush its farewell before it is cut.
+const TERMINATE_GRACE: Duration = Duration::from_secs(2);
+/// How long a `ChatMessageSend` retry is answered from the dedupe cache.
+const DEDUPE_TTL_SECS: u64 = 60;
+/// Most conversations one `SetActiveConversations` may name.
+const MAX_ACTIVE_CONVERSATIONS: usize = 64;
+/// Most messages one `HistorySince` replays; the client asks again for more.
+const HISTORY_SINCE_LIMIT: usize = 500;
+/// Messages read per query while replaying `HistorySince`.
+const HISTORY_SINCE_PAGE: u16 = 100;
+/// Conversations in the snapshot a client can ask for at connect.
+const SNAPSHOT_CONVERSATIONS: u16 = 20;
+/// "Service Restart": the client should reconnect, which the load balancer
+/// sends to another node, after the pause the reason names.
+const DRAINING_CLOSE_CODE: u16 = 1012;
+const DRAINING_CLOSE_REASON: &str = "draining; retry after 5s";
 
 pub struct ActorConfig {
     pub max_inflight_messages: usize,
     pub max_inflight_results: usize,
     pub max_worker_timeout: u64,
+    pub encoder: EventEncoder,
+    pub active: Arc<ActiveConversations>,
+    pub sla: Arc<DeliverySla>,
+}
+
+/// The actor's ends of its connection and of the queues feeding it.
+struct ActorChannels {
+    s2c: Box<dyn ConnSender>,
+    c2s: Box<dyn ConnReceiver>,
+    control_tx: Sender<ConnMessage>,
+    control_rx: Receiver<ConnMessage>,
+    data_rx: LaneReceivers,
+}
+
+/// Outbound data lanes, drained in declaration order after the control channel.
+///
+/// Each lane has its own bounded queue, so a flood on a lower lane fills only
+/// that lane and never delays higher ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    /// Delivery and read receipts, and notices about the account itself.
+    Receipt,
+    /// New chat messages.
+    Chat,
+    /// Presence, typing and relationship notifications, and new messages in
+    /// conversations the client isn't rendering.
+    Background,
+}
+
+impl Lane {
+    pub fn of(event: &S2CEvent) -> Lane {
+        match event {
+            S2CEvent::ChatMessageACK(_)
+            | S2CEvent::SessionTerminated(_)
+            | S2CEvent::SecurityAlert(_)
+            | S2CEvent::ChatRead(_)
+            | S2CEvent::ChatMessageDelivered(_)
+            | S2CEvent::SystemNotice(_)
+            | S2CEvent::ConversationSnapshot(_) => Lane::Receipt,
+            S2CEvent::ChatMessageNew(_)
+            | S2CEvent::ChatMessageEdited(_)
+            | S2CEvent::ChatMessageDeleted(_)
+            | S2CEvent::MessagePinned(_)
+            | S2CEvent::ChatMentionNew(_) => Lane::Chat,
+            S2CEvent::FriendshipNew(_)
+            | S2CEvent::GroupNew(_)
+            | S2CEvent::GroupMemberNew(_)
+            | S2CEvent::GroupUpdated(_)
+            | S2CEvent::ChatTyping(_)
+            | S2CEvent::TypingEvent(_)
+            | S2CEvent::ConversationMetaChanged(_)
+            | S2CEvent::ConversationFrozen(_) => Lane::Background,
+        }
+    }
+}
+
+/// The conversations a client said it is rendering, see
+/// `SetActiveConversations`. `None` until it says, which counts every
+/// conversation as active.
+#[derive(Debug, Default)]
+pub struct ActiveConversations(RwLock<Option<HashSet<ConversationId>>>);
+
+impl ActiveConversations {
+    fn set(&self, conversation_ids: Vec<ConversationId>) {
+        let mut active = self.0.write().unwrap_or_else(|e| e.into_inner());
+        *active = Some(conversation_ids.into_iter().collect());
+    }
+
+    fn contains(&self, conversation_id: ConversationId) -> bool {
+        let active = self.0.read().unwrap_or_else(|e| e.into_inner());
+        active
+            .as_ref()
+            .is_none_or(|ids| ids.contains(&conversation_id))
+    }
+
+    /// The lane for `event`, or `None` if it isn't worth sending. A message
+    /// moved to the background lane can overtake or trail others from its
+    /// conversation; clients order by offset.
+    fn route(&self, event: &S2CEvent) -> Option<Lane> {
+        match event {
+            S2CEvent::ChatMessageNew(m) if !self.contains(m.conversation_id) => {
+                Some(Lane::Background)
+            }
+            S2CEvent::ChatMessageEdited(m) if !self.contains(m.conversation_id) => {
+                Some(Lane::Background)
+            }
+            S2CEvent::ChatMessageDeleted(m) if !self.contains(m.conversation_id) => {
+                Some(Lane::Background)
+            }
+            S2CEvent::MessagePinned(m) if !self.contains(m.conversation_id) => {
+                Some(Lane::Background)
+            }
+            // typing is repeated while it lasts, so one dropped here is
+            // replaced soon after the conversation comes on screen
+            S2CEvent::ChatTyping(t) if !self.contains(t.conversation_id) => None,
+            // a stop is still sent, to clear an indicator shown before the
+            // conversation went off screen
+            S2CEvent::TypingEvent(t) if t.typing && !self.contains(t.conversation_id) => None,
+            event => Some(Lane::of(event)),
+        }
+    }
+}
+
+/// A frame waiting in a data lane, with the stamps it picked up on the way.
+#[derive(Debug)]
+pub struct Queued {
+    pub message: ConnMessage,
+    pub stamps: Option<DeliveryStamps>,
+}
+
+#[derive(Clone)]
+pub struct LaneSenders {
+    pub receipt: Sender<Queued>,
+    pub chat: Sender<Queued>,
+    pub background: Sender<Queued>,
+}
+
+impl LaneSenders {
+    pub fn lane(&self, lane: Lane) -> &Sender<Queued> {
+        match lane {
+            Lane::Receipt => &self.receipt,
+            Lane::Chat => &self.chat,
+            Lane::Background => &self.background,
+        }
+    }
+}
+
+pub struct LaneReceivers {
+    pub receipt: Receiver<Queued>,
+    pub chat: Receiver<Queued>,
+    pub background: Receiver<Queued>,
+}
+
+fn lanes(cap: usize) -> (LaneSenders, LaneReceivers) {
+    let (receipt_tx, receipt_rx) = tokio::sync::mpsc::channel(cap);
+    let (chat_tx, chat_rx) = tokio::sync::mpsc::channel(cap);
+    let (background_tx, background_rx) = tokio::sync::mpsc::channel(cap);
+    (
+        LaneSenders {
+            receipt: receipt_tx,
+            chat: chat_tx,
+            background: background_tx,
+        },
+        LaneReceivers {
+            receipt: receipt_rx,
+            chat: chat_rx,
+            background: background_rx,
+        },
+    )
 }
 
 pub struct ClientRecord {
     pub user_id: UserId,
+    pub meta: ConnectionMeta,
+    pub connected_at: DateTime<Utc>,
     pub control: Sender<ConnMessage>,
-    pub mailbox: Sender<ConnMessage>,
+    pub mailbox: LaneSenders,
+    pub encoder: EventEncoder,
+    pub active: Arc<ActiveConversations>,
     pub actor_handle: Mutex<Option<JoinHandle<()>>>,
     pub cancellation_token: CancellationToken,
 }
 
 pub struct ServiceRegistry {
     pub conversation_service: Arc<dyn ConversationService>,
+    pub command_dedupe_store: Arc<dyn CommandDedupeStore>,
+    pub ephemeral_relay: Arc<EphemeralRelay>,
 }
 
 pub struct SessionHub {
     online_users: Arc<DashMap<UserId, ClientRecord>>,
     services: Arc<ServiceRegistry>,
+    sla: Arc<DeliverySla>,
+    draining: AtomicBool,
+    /// Concurrent sessions allowed from one client IP; 0 for no limit.
+    max_sessions_per_ip: usize,
 }
 
 impl SessionHub {
-    pub fn new(services: Arc<ServiceRegistry>) -> Self {
+    pub fn new(
+        services: Arc<ServiceRegistry>,
+        sla: Arc<DeliverySla>,
+        max_sessions_per_ip: usize,
+    ) -> Self {
         let online_users = Arc::new(DashMap::new());
 
         Self {
             online_users,
             services,
+            sla,
+            draining: AtomicBool::new(false),
+            max_sessions_per_ip,
         }
     }
 
+    fn sessions_from_ip(&self, ip: IpAddr) -> usize {
+        self.online_users
+            .iter()
+            .filter(|entry| entry.meta.client_ip == Some(ip))
+            .count()
+    }
+
     pub async fn shutdown(&self) {
         tracing::info!("SessionHub shutting down...");
 
@@ -74,16 +270,62 @@ impl SessionHub {
 
 #[async_trait::async_trait]
 impl ConnectionAcceptor for SessionHub {
+    fn has_room_for(&self, meta: &ConnectionMeta) -> bool {
+        match meta.client_ip {
+            Some(ip) if self.max_sessions_per_ip > 0 => {
+                self.sessions_from_ip(ip) < self.max_sessions_per_ip
+            }
+            _ => true,
+        }
+    }
+
     async fn accept_connection(
         &self,
-        s2c_channel: Box<dyn ConnSender>,
+        mut s2c_channel: Box<dyn ConnSender>,
         c2s_channel: Box<dyn ConnReceiver>,
         user_id: UserId,
+        protocol_version: ProtocolVersion,
+        meta: ConnectionMeta,
     ) -> anyhow::Result<()> {
+        if self.draining() {
+            tracing::info!("refusing session for [{}]: draining", user_id);
+            return s2c_channel
+                .send(ConnMessage::CloseWith(
+                    DRAINING_CLOSE_CODE,
+                    DRAINING_CLOSE_REASON.to_owned(),
+                ))
+                .await;
+        }
+        // the route asked before upgrading; this catches a burst that got
+        // past it together
+        if !self.has_room_for(&meta) {
+            tracing::warn!(
+                "rejecting session for [{}]: too many sessions from {:?}",
+                user_id,
+                meta.client_ip
+            );
+            return Err(anyhow!("too many sessions from {:?}", meta.client_ip));
+        }
+        tracing::info!(
+            "session opened: user={} addr={:?} agent={:?} device={:?}",
+            user_id,
+            meta.remote_addr,
+            meta.user_agent,
+            meta.device_id
+        );
+
+        let mut encoder = EventEncoder::new(protocol_version);
+        if meta.data_saver {
+            encoder = encoder.with_preview_limit(DATA_SAVER_PREVIEW_BYTES);
+        }
+        let active = Arc::new(ActiveConversations::default());
         let config = ActorConfig {
             max_inflight_messages: 64,
             max_inflight_results: 1024,
             max_worker_timeout: 1000,
+            encoder,
+            active: active.clone(),
+            sla: self.sla.clone(),
         };
 
         let services = self.services.clone();
@@ -91,17 +333,26 @@ impl ConnectionAcceptor for SessionHub {
         let actor_cancel = CancellationToken::new();
 
         let (sender_control_tx, sender_control_rx) = tokio::sync::mpsc::channel(MAILBOX_CAP);
-        let (sender_buffer_tx, sender_buffer_rx) = tokio::sync::mpsc::channel(MAILBOX_CAP);
+        let (sender_buffer_tx, sender_buffer_rx) = lanes(MAILBOX_CAP);
+
+        // queued before the record is visible, so no event can overtake it
+        if meta.snapshot
+            && let Some(snapshot) = snapshot_frame(user_id, &services, encoder).await
+        {
+            let _ = sender_control_tx.try_send(ConnMessage::Text(snapshot));
+        }
 
         let notify = Arc::new(Notify::new());
+        let channels = ActorChannels {
+            s2c: s2c_channel,
+            c2s: c2s_channel,
+            control_tx: sender_control_tx.clone(),
+            control_rx: sender_control_rx,
+            data_rx: sender_buffer_rx,
+        };
         let actor_handle = tokio::spawn(client_actor(
             user_id,
-            s2c_channel,
-            c2s_channel,
-            sender_control_tx.clone(),
-            sender_control_rx,
-            sender_buffer_tx.clone(),
-            sender_buffer_rx,
+            channels,
             services,
             config,
             actor_cancel.clone(),
@@ -111,12 +362,18 @@ impl ConnectionAcceptor for SessionHub {
 
         let new_user = ClientRecord {
             user_id,
+            meta,
+            connected_at: Utc::now(),
             control: sender_control_tx,
             mailbox: sender_buffer_tx,
+            encoder,
+            active,
             actor_handle: Mutex::new(Some(actor_handle)),
             cancellation_token: actor_cancel,
         };
-        self.online_users.insert(user_id, new_user);
+        if let Some(old) = self.online_users.insert(user_id, new_user) {
+            terminate_record(&old, TerminationReason::Replaced);
+        }
         notify.notify_one();
 
         Ok(())
@@ -125,12 +382,7 @@ impl ConnectionAcceptor for SessionHub {
 
 async fn client_actor(
     user_id: UserId,
-    s2c_channel: Box<dyn ConnSender>,
-    c2s_channel: Box<dyn ConnReceiver>,
-    sender_control_tx: Sender<ConnMessage>,
-    sender_control_rx: Receiver<ConnMessage>,
-    sender_data_tx: Sender<ConnMessage>,
-    sender_data_rx: Receiver<ConnMessage>,
+    channels: ActorChannels,
     services: Arc<ServiceRegistry>,
     config: ActorConfig,
     actor_cancel: CancellationToken,
@@ -138,22 +390,26 @@ async fn client_actor(
     online_users: Arc<DashMap<UserId, ClientRecord>>,
 ) {
     notify.notified().await;
-    tracing::info!("ClientActor [{}] starting", user_id);
+    tracing::info!(
+        "ClientActor [{}] starting (protocol {:?})",
+        user_id,
+        config.encoder.version()
+    );
 
     let sender_token = actor_cancel.clone();
     let sender_handle = tokio::spawn(outbound_sender(
-        s2c_channel,
-        sender_control_rx,
-        sender_data_rx,
+        channels.s2c,
+        channels.control_rx,
+        channels.data_rx,
+        config.sla.clone(),
         sender_token,
     ));
 
     let receiver_token = actor_cancel.clone();
     let receiver_handle = tokio::spawn(inbound_receiver(
         user_id,
-        c2s_channel,
-        sender_control_tx,
-        sender_data_tx,
+        channels.c2s,
+        channels.control_tx,
         services,
         config,
         receiver_token,
@@ -167,28 +423,39 @@ async fn client_actor(
             tracing::warn!("Receiver task ended first ({:?}): {:?}", user_id, res);
         }
     };
-    online_users.remove(&user_id);
+    // A newer session may already have replaced this record; only remove ours.
+    actor_cancel.cancel();
+    online_users.remove_if(&user_id, |_, record| {
+        record.cancellation_token.is_cancelled()
+    });
     tracing::debug!("online_users: {}", online_users.len());
 }
 
 async fn outbound_sender(
     mut s2c_channel: Box<dyn ConnSender>,
     mut sender_control_rx: Receiver<ConnMessage>,
-    mut sender_data_rx: Receiver<ConnMessage>,
+    mut sender_data_rx: LaneReceivers,
+    sla: Arc<DeliverySla>,
     actor_cancel: CancellationToken,
 ) {
-    while let Some(msg) = tokio::select! {
+    while let Some(Queued { message, stamps }) = tokio::select! {
         biased;
         _ = actor_cancel.cancelled() => None,
-        m = sender_control_rx.recv() => m,
-        m = sender_data_rx.recv() => m,
+        m = sender_control_rx.recv() => m.map(|message| Queued { message, stamps: None }),
+        m = sender_data_rx.receipt.recv() => m,
+        m = sender_data_rx.chat.recv() => m,
+        m = sender_data_rx.background.recv() => m,
     } {
-        tracing::trace!("outbound_sender: {:?}", msg);
-        if s2c_channel.send(msg).await.is_err() {
+        tracing::trace!("outbound_sender: {:?}", message);
+        let closing = matches!(message, ConnMessage::Close | ConnMessage::CloseWith(..));
+        if s2c_channel.send(message).await.is_err() || closing {
             tracing::trace!("outbound_sender shutting down");
             actor_cancel.cancel();
             break;
         }
+        if let Some(stamps) = stamps {
+            sla.written(&stamps);
+        }
     }
 }
 
@@ -196,7 +463,6 @@ async fn inbound_receiver(
     user_id: UserId,
     mut c2s_channel: Box<dyn ConnReceiver>,
     sender_control_tx: Sender<ConnMessage>,
-    sender_data_tx: Sender<ConnMessage>,
     services: Arc<ServiceRegistry>,
     config: ActorConfig,
     actor_cancel: CancellationToken,
@@ -209,6 +475,7 @@ async fn inbound_receiver(
     loop {
         let sender_control_tx = sender_control_tx.clone();
         let services = services.clone();
+        let active = config.active.clone();
         let actor_cancel = actor_cancel.clone();
 
         tokio::select! {
@@ -255,6 +522,8 @@ async fn inbound_receiver(
                         conn_msg,
                         sender_control_tx,
                         services,
+                        config.encoder,
+                        active,
                         actor_cancel.clone(),
                     );
                     let result = tokio::time::timeout(
@@ -286,6 +555,8 @@ async fn handle_incoming_message(
     conn_msg: ConnMessage,
     sender_control_tx: Sender<ConnMessage>,
     services: Arc<ServiceRegistry>,
+    encoder: EventEncoder,
+    active: Arc<ActiveConversations>,
     actor_cancel: CancellationToken,
 ) -> anyhow::Result<()> {
     match conn_msg {
@@ -294,21 +565,71 @@ async fn handle_incoming_message(
                 let sender = user_id;
                 let result = match request {
                     C2SCommand::ChatMessageSend(data) => {
-                        send_message(sender, data, services.conversation_service.clone()).await
+                        // each command starts its own trace; frames carry no headers
+                        let trace_id = TraceId::new();
+                        let span = tracing::info_span!("command", %trace_id);
+                        trace_id
+                            .scope(send_message(sender, data, services.clone()).instrument(span))
+                            .await
+                    }
+                    // no ACK; the client repeats the start while the user keeps typing
+                    C2SCommand::ChatTyping(ChatTypingSend { conversation_id })
+                    | C2SCommand::TypingStart(TypingSignal { conversation_id }) => {
+                        relay_typing(sender, conversation_id, true, &services).await;
+                        return Ok(());
+                    }
+                    C2SCommand::TypingStop(TypingSignal { conversation_id }) => {
+                        relay_typing(sender, conversation_id, false, &services).await;
+                        return Ok(());
+                    }
+                    // no ACK; the sender hears through ChatMessageDelivered
+                    C2SCommand::Delivered(DeliveryAck {
+                        conversation_id,
+                        message_id,
+                    }) => {
+                        if let Err(e) = services
+                            .conversation_service
+                            .mark_delivered(sender, conversation_id, message_id)
+                            .await
+                        {
+                            tracing::debug!("delivery ack from [{}] dropped: {e}", sender);
+                        }
+                        return Ok(());
+                    }
+                    // no ACK; the replayed messages are the answer
+                    C2SCommand::HistorySince(data) => {
+                        if let Err(e) =
+                            replay_since(sender, data, &services, encoder, &sender_control_tx).await
+                        {
+                            tracing::debug!("history replay for [{}] stopped: {e}", sender);
+                        }
+                        return Ok(());
+                    }
+                    C2SCommand::SetActiveConversations(data) => {
+                        if data.conversation_ids.len() > MAX_ACTIVE_CONVERSATIONS {
+                            tracing::debug!(
+                                "[{}] named {} active conversations; keeping the old set",
+                                sender,
+                                data.conversation_ids.len()
+                            );
+                            let _ = sender_control_tx
+                                .send(ConnMessage::Text(String::from(
+                                    "Too many active conversations",
+                                )))
+                                .await;
+                        } else {
+                            active.set(data.conversation_ids);
+                        }
+                        return Ok(());
                     }
                 };
 
                 match result {
-                    Ok(record) => {
-                        let ack = S2CEvent::ChatMessageACK(ChatMessageACK {
-                            conversation_id: record.conversation_id,
-                            message_id: record.message_id,
-                            message_offset: record.message_offset,
-                            created_at: record.created_at,
-                        });
-                        let _ = sender_control_tx
-                            .send(ConnMessage::Text(serde_json::to_string(&ack)?))
-                            .await;
+                    Ok(ack) => {
+                        let ack = S2CEvent::ChatMessageACK(ack);
+                        if let Some(message) = encoder.encode(&ack)? {
+                            let _ = sender_control_tx.send(ConnMessage::Text(message)).await;
+                        }
                         Ok(())
                     }
                     Err(e) => {
@@ -339,7 +660,7 @@ async fn handle_incoming_message(
             tracing::error!("unexpected pong from [{}]", user_id);
             Ok(())
         }
-        ConnMessage::Close => {
+        ConnMessage::Close | ConnMessage::CloseWith(..) => {
             actor_cancel.cancel();
             Ok(())
         }
@@ -351,18 +672,232 @@ async fn handle_incoming_message(
 async fn send_message(
     sender: UserId,
     data: ChatMessageSend,
-    conversation_service: Arc<dyn ConversationService>,
-) -> anyhow::Result<MessageRecord> {
-    let record = conversation_service
+    services: Arc<ServiceRegistry>,
+) -> anyhow::Result<ChatMessageACK> {
+    // A retried send is answered from the cache; the message_id unique key
+    // still guards the database if the cache misses.
+    match services
+        .command_dedupe_store
+        .get_ack(sender, data.message_id)
+        .await
+    {
+        Ok(Some(ack)) => {
+            return Ok(ChatMessageACK {
+                duplicate: true,
+                ..ack
+            });
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("dedupe lookup failed: {e}"),
+    }
+
+    let sent = services
+        .conversation_service
         .send_message(
             data.conversation_id,
             sender,
-            data.content.as_str(),
+            data.content.expose().as_str(),
             data.message_id,
+            data.reply_to,
+            &data.attachments,
         )
         .await
         .map_err(|e| anyhow::anyhow!("failed to send chat message: {}", e))?;
-    Ok(record)
+
+    let ack = ChatMessageACK {
+        conversation_id: sent.record.conversation_id,
+        message_id: sent.record.message_id,
+        message_offset: sent.record.message_offset,
+        created_at: sent.record.created_at,
+        sequence: sent.sender_seq,
+        duplicate: sent.duplicate,
+    };
+    if let Err(e) = services
+        .command_dedupe_store
+        .save_ack(sender, &ack, DEDUPE_TTL_SECS)
+        .await
+    {
+        tracing::warn!("dedupe save failed: {e}");
+    }
+    Ok(ack)
+}
+
+/// Never stored: a notice that fails here is dropped, and the next start or
+/// the client's own timeout makes up for it.
+async fn relay_typing(
+    sender: UserId,
+    conversation_id: ConversationId,
+    typing: bool,
+    services: &ServiceRegistry,
+) {
+    let result = async {
+        let receivers = services
+            .conversation_service
+            .typing_receivers(conversation_id, sender)
+            .await?;
+        let event = S2CEvent::TypingEvent(TypingEvent {
+            conversation_id,
+            user_id: sender,
+            typing,
+        });
+        services
+            .ephemeral_relay
+            .relay(conversation_id.0, receivers, event)
+            .await
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::debug!("typing notice from [{}] dropped: {e}", sender);
+    }
+}
+
+/// The encoded `ConversationSnapshot`; `None` if the conversations can't be
+/// read, which the client gets over HTTP instead.
+async fn snapshot_frame(
+    user_id: UserId,
+    services: &ServiceRegistry,
+    encoder: EventEncoder,
+) -> Option<String> {
+    let recent = services
+        .conversation_service
+        .recent_conversations(user_id, PageSize(SNAPSHOT_CONVERSATIONS), None)
+        .await
+        .inspect_err(|e| tracing::warn!("no snapshot for [{}]: {e}", user_id))
+        .ok()?;
+    let conversations = recent
+        .into_iter()
+        .map(|c| {
+            let (name, other_user, group_id) = match c.peer {
+                ConversationPeer::Direct { other_user, name } => (name, Some(other_user), None),
+                ConversationPeer::Group { group_id, name, .. } => (name, None, Some(group_id)),
+            };
+            SnapshotConversation {
+                conversation_id: c.conversation_id,
+                name,
+                other_user,
+                group_id,
+                last_msg_off: c.last_msg_off,
+                last_msg_at: c.last_msg_at,
+                unread_count: c.unread_count,
+                pinned: c.pinned,
+                frozen: c.frozen,
+            }
+        })
+        .collect();
+    let event = S2CEvent::ConversationSnapshot(ConversationSnapshot { conversations });
+    encoder.encode(&event).ok().flatten()
+}
+
+/// Sends the client what it missed after `after_offset`, page by page, up
+/// to `HISTORY_SINCE_LIMIT` messages.
+async fn replay_since(
+    user_id: UserId,
+    request: HistorySince,
+    services: &ServiceRegistry,
+    encoder: EventEncoder,
+    sender_control_tx: &Sender<ConnMessage>,
+) -> anyhow::Result<()> {
+    let mut after = request.after_offset;
+    let mut sent = 0;
+    while sent < HISTORY_SINCE_LIMIT {
+        let page_size = HISTORY_SINCE_PAGE.min((HISTORY_SINCE_LIMIT - sent) as u16);
+        let page = services
+            .conversation_service
+            .messages_since(user_id, request.conversation_id, after, PageSize(page_size))
+            .await?;
+        let last_page = page.len() < page_size as usize;
+        for message in page {
+            after = message.message_offset;
+            sent += 1;
+            if let Some(text) = encoder.encode(&S2CEvent::ChatMessageNew(message))? {
+                sender_control_tx.send(ConnMessage::Text(text)).await?;
+            }
+        }
+        if last_page {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn terminate_record(record: &ClientRecord, reason: TerminationReason) {
+    tracing::info!("terminating session [{}]: {:?}", record.user_id, reason);
+
+    let event = S2CEvent::SessionTerminated(SessionTerminated { reason });
+    if let Ok(Some(message)) = record.encoder.encode(&event) {
+        let _ = record.control.try_send(ConnMessage::Text(message));
+    }
+    let _ = record.control.try_send(ConnMessage::Close);
+
+    // The sender cancels the actor once the Close frame is out; this covers a
+    // full control channel or a client that stopped reading.
+    let token = record.cancellation_token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(TERMINATE_GRACE).await;
+        token.cancel();
+    });
+}
+
+// endregion
+
+// region session control
+
+impl SessionControl for SessionHub {
+    fn sessions(&self) -> Vec<SessionInfo> {
+        self.online_users
+            .iter()
+            .map(|entry| SessionInfo {
+                user_id: entry.user_id,
+                remote_addr: entry.meta.remote_addr.map(|a| a.to_string()),
+                user_agent: entry.meta.user_agent.clone(),
+                device_id: entry.meta.device_id.clone(),
+                connected_at: entry.connected_at,
+            })
+            .collect()
+    }
+
+    /// Tells the client why its session ends, closes the socket and stops the
+    /// actor.
+    fn terminate(&self, user_id: UserId, reason: TerminationReason) -> bool {
+        match self.online_users.get(&user_id) {
+            Some(record) => {
+                terminate_record(&record, reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn set_draining(&self, draining: bool) {
+        if self.draining.swap(draining, Ordering::SeqCst) != draining {
+            tracing::info!(
+                "SessionHub {} draining, {} sessions open",
+                if draining { "started" } else { "stopped" },
+                self.online_users.len()
+            );
+        }
+    }
+
+    fn draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+}
+
+/// A node on its own is the whole directory.
+#[async_trait::async_trait]
+impl SessionDirectory for SessionHub {
+    async fn report(
+        &self,
+        _node: &str,
+        _sessions: &[SessionInfo],
+        _ttl: Duration,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn sessions(&self) -> anyhow::Result<Vec<SessionInfo>> {
+        Ok(SessionControl::sessions(self))
+    }
 }
 
 // endregion
@@ -371,12 +906,34 @@ async fn send_message(
 
 #[async_trait::async_trait]
 impl OutboundQueue for SessionHub {
-    async fn enqueue(&self, receiver: UserId, event: &S2CEvent) -> anyhow::Result<()> {
+    async fn enqueue(
+        &self,
+        receiver: UserId,
+        event: &S2CEvent,
+        stamps: Option<DeliveryStamps>,
+    ) -> anyhow::Result<()> {
         if let Some(record) = self.online_users.get(&receiver) {
-            let message = serde_json::to_string(event)?;
-            match record.mailbox.try_send(ConnMessage::Text(message)) {
+            let Some(lane) = record.active.route(event) else {
+                tracing::trace!(
+                    "event for a background conversation dropped for [{}]",
+                    receiver
+                );
+                return Ok(());
+            };
+            let Some(message) = record.encoder.encode(event)? else {
+                tracing::trace!(
+                    "event not supported by protocol {:?}, dropped for [{}]",
+                    record.encoder.version(),
+                    receiver
+                );
+                return Ok(());
+            };
+            match record.mailbox.lane(lane).try_send(Queued {
+                message: ConnMessage::Text(message),
+                stamps,
+            }) {
                 Ok(_) => Ok(()),
-                Err(TrySendError::Full(..)) => Err(anyhow!("backpressure retry")),
+                Err(TrySendError::Full(..)) => Err(anyhow!("backpressure retry ({lane:?} lane)")),
                 Err(e) => Err(anyhow!("failed to enqueue message: {e}")),
             }
         } else {
@@ -0,0 +1,61 @@
+use crate::server::{SessionControl, SessionDirectory, SessionInfo};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// How often the node reports its sessions; the directory is this far
+/// behind at most.
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+/// A node that missed reports for this long drops out of the directory.
+const REPORT_TTL: Duration = Duration::from_secs(30);
+
+/// Periodically reports this node's sessions to the `SessionDirectory`, so
+/// operators can list the sessions of every node from any of them.
+pub struct SessionReporter {
+    session_control: Arc<dyn SessionControl>,
+    session_directory: Arc<dyn SessionDirectory>,
+    node: String,
+    cancellation_token: CancellationToken,
+}
+
+impl SessionReporter {
+    pub fn new(
+        session_control: Arc<dyn SessionControl>,
+        session_directory: Arc<dyn SessionDirectory>,
+        node: String,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            session_control,
+            session_directory,
+            node,
+            cancellation_token,
+        }
+    }
+
+    async fn report(&self, sessions: &[SessionInfo]) {
+        if let Err(e) = self
+            .session_directory
+            .report(&self.node, sessions, REPORT_TTL)
+            .await
+        {
+            tracing::warn!("Session reporter error: {e}");
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(REPORT_INTERVAL);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancellation_token.cancelled() => {
+                    tracing::info!("Session reporter shutting down...");
+                    // leave the directory now rather than when the report lapses
+                    self.report(&[]).await;
+                    break;
+                }
+                _ = ticker.tick() => self.report(&self.session_control.sessions()).await,
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::application_port::*;
+use crate::domain_model::*;
+use crate::domain_port::*;
+use crate::server::{EventHandler, HandleOutcome};
+use hmac::{Hmac, KeyInit, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// How long a partition waits when hooks can't be read.
+const WEBHOOK_STORE_BACKOFF: Duration = Duration::from_secs(10);
+/// How long a partition waits when too many deliveries are in flight.
+const WEBHOOK_BUSY_BACKOFF: Duration = Duration::from_secs(1);
+
+pub const WEBHOOK_ID_HEADER: &str = "x-counterpoint-webhook-id";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "x-counterpoint-timestamp";
+/// `sha256=` and the hex HMAC-SHA256, keyed by the hook's secret, of the
+/// timestamp header, a `.`, and the body.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-counterpoint-signature";
+
+/// What a webhook receives for each new message.
+#[derive(Serialize)]
+struct Delivery<'a> {
+    #[serde(rename = "type")]
+    event: &'static str,
+    webhook_id: WebhookId,
+    message: &'a ChatMessageNew,
+}
+
+/// How often, and how far apart, a delivery is tried.
+#[derive(Debug, Clone, Copy)]
+pub struct WebhookRetry {
+    pub max_attempts: u32,
+    /// Before the second attempt; each later one waits twice as long.
+    pub first_backoff: Duration,
+}
+
+/// Posts new messages to the webhooks of their conversation; every other
+/// event, and messages in conversations without hooks, pass straight
+/// through.
+///
+/// Each hook gets its delivery in a task of its own, so a slow endpoint
+/// holds back neither the partition nor the other hooks. At most
+/// `max_in_flight` deliveries run or wait to retry at once; when a message's
+/// hooks don't all fit, the partition parks and the message comes back
+/// whole, so no hook gets it twice. A failed delivery,
+/// no response or a 429 or 5xx, is tried again with doubling backoff up to
+/// `max_attempts`; any other status is final. Retries can reorder a hook's
+/// deliveries, and one still retrying when the node stops is lost; receivers
+/// order by `message_offset` and drop a `message_id` they have seen.
+pub struct WebhookDispatcher {
+    webhook_service: Arc<dyn WebhookService>,
+    client: Arc<dyn WebhookClient>,
+    retry: WebhookRetry,
+    in_flight: Arc<Semaphore>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(
+        webhook_service: Arc<dyn WebhookService>,
+        client: Arc<dyn WebhookClient>,
+        retry: WebhookRetry,
+        max_in_flight: usize,
+    ) -> Self {
+        Self {
+            webhook_service,
+            client,
+            retry,
+            // room for every hook of one conversation, or its messages park for good
+            in_flight: Arc::new(Semaphore::new(
+                max_in_flight.max(MAX_WEBHOOKS_PER_CONVERSATION),
+            )),
+        }
+    }
+
+    /// `permit` is held until the last attempt is over.
+    fn deliver(&self, target: WebhookTarget, body: Vec<u8>, permit: OwnedSemaphorePermit) {
+        let client = self.client.clone();
+        let retry = self.retry;
+        tokio::spawn(async move {
+            let _permit = permit;
+            let mut backoff = retry.first_backoff;
+            for attempt in 1..=retry.max_attempts.max(1) {
+                let request = match signed(&target, &body) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        tracing::warn!(webhook_id = %target.webhook_id.0, "webhook not signed: {e}");
+                        return;
+                    }
+                };
+                let retryable = match client.post(&request).await {
+                    Ok(status) if (200..300).contains(&status) => return,
+                    Ok(status) => {
+                        tracing::debug!(webhook_id = %target.webhook_id.0, attempt, status, "webhook refused");
+                        status == 429 || status >= 500
+                    }
+                    Err(e) => {
+                        tracing::debug!(webhook_id = %target.webhook_id.0, attempt, "webhook failed: {e:#}");
+                        true
+                    }
+                };
+                if !retryable || attempt == retry.max_attempts {
+                    break;
+                }
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            tracing::warn!(webhook_id = %target.webhook_id.0, url = %target.url, "webhook delivery dropped");
+        });
+    }
+}
+
+/// The request for one attempt, signed with the time it is made.
+fn signed(target: &WebhookTarget, body: &[u8]) -> anyhow::Result<WebhookRequest> {
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let mut mac = Hmac::<Sha256>::new_from_slice(target.secret.as_bytes())?;
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+    Ok(WebhookRequest {
+        url: target.url.clone(),
+        headers: vec![
+            (WEBHOOK_ID_HEADER, target.webhook_id.0.to_string()),
+            (WEBHOOK_TIMESTAMP_HEADER, timestamp),
+            (WEBHOOK_SIGNATURE_HEADER, signature),
+        ],
+        body: body.to_vec(),
+    })
+}
+
+#[async_trait::async_trait]
+impl EventHandler for WebhookDispatcher {
+    async fn handle(&self, payload: &[u8]) -> anyhow::Result<HandleOutcome> {
+        // events newer than this build aren't messages it knows how to send
+        let envelope = match serde_json::from_slice::<S2CEnvelope>(payload) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                tracing::debug!("webhooks skipped an event: {e}");
+                return Ok(HandleOutcome::Commit);
+            }
+        };
+        let S2CEvent::ChatMessageNew(message) = &envelope.body else {
+            return Ok(HandleOutcome::Commit);
+        };
+
+        let targets = match self.webhook_service.targets(message.conversation_id).await {
+            Ok(targets) => targets,
+            Err(e) => {
+                tracing::warn!("webhooks unavailable: {e}");
+                return Ok(HandleOutcome::Park(WEBHOOK_STORE_BACKOFF));
+            }
+        };
+        let Ok(mut permits) = self
+            .in_flight
+            .clone()
+            .try_acquire_many_owned(targets.len() as u32)
+        else {
+            tracing::debug!("webhook deliveries at capacity");
+            return Ok(HandleOutcome::Park(WEBHOOK_BUSY_BACKOFF));
+        };
+        for target in targets {
+            let body = serde_json::to_vec(&Delivery {
+                event: "chat.message.new",
+                webhook_id: target.webhook_id,
+                message,
+            })?;
+            let permit = permits.split(1).expect("one permit per target");
+            self.deliver(target, body, permit);
+        }
+        Ok(HandleOutcome::Commit)
+    }
+}